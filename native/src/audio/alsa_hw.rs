@@ -0,0 +1,502 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+use ringbuf::traits::{Consumer, Observer};
+
+use crate::audio::backend;
+use crate::audio::multi_output::OutputFanout;
+use crate::audio::state::SharedState;
+
+/// 未指定时的 period 大小与 buffer 中的 period 数。
+const DEFAULT_PERIOD_FRAMES: u32 = 1024;
+const DEFAULT_PERIODS: u32 = 4;
+/// 等待设备可写的超时，超时后回到循环检查停止标志。
+#[cfg(target_os = "linux")]
+const WAIT_TIMEOUT_MS: u32 = 100;
+#[cfg(target_os = "linux")]
+const ENODEV: i32 = 19;
+
+/// 直连 ALSA `hw:` 输出的设置，只在严格 BitPerfect 播放时生效。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct AlsaHwOptions {
+    pub(crate) enabled: bool,
+    /// 期望的 period/buffer 帧数，驱动就近取值；为空时用默认值。
+    pub(crate) period_frames: Option<u32>,
+    pub(crate) buffer_frames: Option<u32>,
+    /// 用 mmap 直接写 DMA 缓冲区，否则走 writei。
+    pub(crate) mmap: bool,
+}
+
+impl AlsaHwOptions {
+    fn period_frames(&self) -> u32 {
+        self.period_frames.unwrap_or(DEFAULT_PERIOD_FRAMES).max(1)
+    }
+
+    fn buffer_frames(&self) -> u32 {
+        self.buffer_frames
+            .unwrap_or(self.period_frames() * DEFAULT_PERIODS)
+            .max(self.period_frames() * 2)
+    }
+}
+
+/// 样本按小端写进 ALSA 缓冲区；24 位样本按 hw_params 选 3 字节紧凑或 4 字节容器。
+pub(crate) trait HwSample: cpal::SizedSample {
+    fn write_le(self, packed_24: bool, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_hw_sample {
+    ($($ty:ty),*) => {
+        $(impl HwSample for $ty {
+            fn write_le(self, _packed_24: bool, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+impl_hw_sample!(i8, u8, i16, u16, i32, u32, f32, f64);
+
+impl HwSample for cpal::I24 {
+    fn write_le(self, packed_24: bool, out: &mut Vec<u8>) {
+        let bytes = self.inner().to_le_bytes();
+        out.extend_from_slice(if packed_24 { &bytes[..3] } else { &bytes });
+    }
+}
+
+impl HwSample for cpal::U24 {
+    fn write_le(self, packed_24: bool, out: &mut Vec<u8>) {
+        let bytes = self.inner().to_le_bytes();
+        out.extend_from_slice(if packed_24 { &bytes[..3] } else { &bytes });
+    }
+}
+
+/// 输出设备对应的 `hw:` PCM；`plughw:` 换成同一张卡的 `hw:`，绕过插件转换。
+#[cfg(target_os = "linux")]
+pub(crate) fn hw_pcm_name(device_id: &str) -> Option<String> {
+    let locator = backend::linux_plughw_locator(device_id.trim())?;
+    Some(match locator.strip_prefix("plughw:") {
+        Some(rest) => format!("hw:{rest}"),
+        None => locator,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn hw_pcm_name(_device_id: &str) -> Option<String> {
+    None
+}
+
+/// 实际生效的 hw_params。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AlsaHwConfig {
+    pub(crate) pcm_name: String,
+    pub(crate) format: String,
+    pub(crate) period_frames: u64,
+    pub(crate) buffer_frames: u64,
+    pub(crate) mmap: bool,
+}
+
+/// 绕过 cpal 直接写 `hw:` PCM 的输出流；`play` 后才启动输出线程，drop 时停止。
+pub(crate) struct AlsaHwStream {
+    config: AlsaHwConfig,
+    stop: Arc<AtomicBool>,
+    run: Option<Box<dyn FnOnce() + Send>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AlsaHwStream {
+    pub(crate) fn config(&self) -> &AlsaHwConfig {
+        &self.config
+    }
+
+    pub(crate) fn play(&mut self) {
+        if let Some(run) = self.run.take() {
+            self.thread = Some(std::thread::spawn(run));
+        }
+    }
+}
+
+impl Drop for AlsaHwStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use alsa::pcm::{Access, Format, HwParams, IO, PCM, State};
+    use alsa::{Direction, ValueOr};
+    use std::time::Duration;
+
+    /// cpal 样本格式可对应的 ALSA 格式，按优先级排列。
+    pub(super) fn alsa_formats(format: cpal::SampleFormat) -> &'static [Format] {
+        match format {
+            cpal::SampleFormat::I8 => &[Format::S8],
+            cpal::SampleFormat::U8 => &[Format::U8],
+            cpal::SampleFormat::I16 => &[Format::S16LE],
+            cpal::SampleFormat::U16 => &[Format::U16LE],
+            cpal::SampleFormat::I24 => &[Format::S243LE, Format::S24LE],
+            cpal::SampleFormat::U24 => &[Format::U243LE, Format::U24LE],
+            cpal::SampleFormat::I32 => &[Format::S32LE],
+            cpal::SampleFormat::U32 => &[Format::U32LE],
+            cpal::SampleFormat::F32 => &[Format::FloatLE],
+            cpal::SampleFormat::F64 => &[Format::Float64LE],
+            _ => &[],
+        }
+    }
+
+    fn supported_format(hwp: &HwParams, format: cpal::SampleFormat) -> Option<Format> {
+        alsa_formats(format)
+            .iter()
+            .copied()
+            .find(|format| hwp.test_format(*format).is_ok())
+    }
+
+    /// 按 `candidates` 的顺序找出驱动原生支持的第一个样本格式。
+    pub(crate) fn find_bit_perfect_format(
+        pcm_name: &str,
+        sample_rate: u32,
+        channels: u16,
+        candidates: &[cpal::SampleFormat],
+    ) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
+        let pcm = PCM::new(pcm_name, Direction::Playback, true)
+            .map_err(|err| format!("无法打开 {}：{}", pcm_name, err))?;
+        let hwp = HwParams::any(&pcm)?;
+        hwp.set_rate_resample(false)?;
+        if hwp.test_rate(sample_rate).is_err() {
+            return Err(format!("{} 不支持采样率 {} Hz", pcm_name, sample_rate).into());
+        }
+        if hwp.test_channels(u32::from(channels)).is_err() {
+            return Err(format!("{} 不支持 {} 声道", pcm_name, channels).into());
+        }
+        let sample_format = candidates
+            .iter()
+            .copied()
+            .find(|format| supported_format(&hwp, *format).is_some())
+            .ok_or_else(|| format!("{} 不支持音源的原始样本格式", pcm_name))?;
+        let config = cpal::StreamConfig {
+            channels,
+            sample_rate,
+            buffer_size: cpal::BufferSize::Default,
+        };
+        Ok((config, sample_format))
+    }
+
+    fn configure<Out: HwSample>(
+        pcm: &PCM,
+        pcm_name: &str,
+        config: &cpal::StreamConfig,
+        options: AlsaHwOptions,
+    ) -> Result<(AlsaHwConfig, Format), Box<dyn std::error::Error>> {
+        let hwp = HwParams::any(pcm)?;
+        hwp.set_rate_resample(false)?;
+        hwp.set_access(if options.mmap {
+            Access::MMapInterleaved
+        } else {
+            Access::RWInterleaved
+        })?;
+        let format = supported_format(&hwp, Out::FORMAT)
+            .ok_or_else(|| format!("{} 不支持 {} 输出", pcm_name, Out::FORMAT))?;
+        hwp.set_format(format)?;
+        hwp.set_channels(u32::from(config.channels))?;
+        hwp.set_rate(config.sample_rate, ValueOr::Nearest)?;
+        hwp.set_period_size_near(
+            options.period_frames() as alsa::pcm::Frames,
+            ValueOr::Nearest,
+        )?;
+        hwp.set_buffer_size_near(options.buffer_frames() as alsa::pcm::Frames)?;
+        pcm.hw_params(&hwp)?;
+
+        let current = pcm.hw_params_current()?;
+        let rate = current.get_rate()?;
+        if rate != config.sample_rate {
+            return Err(format!(
+                "{} 只能以 {} Hz 打开，拒绝重采样到 {} Hz",
+                pcm_name, rate, config.sample_rate
+            )
+            .into());
+        }
+        let period_frames = current.get_period_size()?;
+        let buffer_frames = current.get_buffer_size()?;
+
+        let swp = pcm.sw_params_current()?;
+        swp.set_start_threshold(buffer_frames - period_frames)?;
+        swp.set_avail_min(period_frames)?;
+        pcm.sw_params(&swp)?;
+
+        Ok((
+            AlsaHwConfig {
+                pcm_name: pcm_name.to_string(),
+                format: format.to_string(),
+                period_frames: period_frames as u64,
+                buffer_frames: buffer_frames as u64,
+                mmap: options.mmap,
+            },
+            format,
+        ))
+    }
+
+    impl AlsaHwStream {
+        /// 以给定配置打开 `hw:` PCM 并设置 hw_params/sw_params，驱动不能原样接受时报错。
+        pub(crate) fn open<In, Out, C>(
+            pcm_name: &str,
+            config: &cpal::StreamConfig,
+            options: AlsaHwOptions,
+            consumer: C,
+            state: Arc<SharedState>,
+            fanout: Arc<OutputFanout>,
+        ) -> Result<Self, Box<dyn std::error::Error>>
+        where
+            In: Copy + Send + 'static,
+            Out: HwSample + cpal::FromSample<In> + Send + 'static,
+            f32: cpal::FromSample<Out>,
+            f64: cpal::FromSample<Out>,
+            C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
+        {
+            let pcm = PCM::new(pcm_name, Direction::Playback, false)
+                .map_err(|err| format!("无法打开 {}：{}", pcm_name, err))?;
+            let (hw_config, format) = configure::<Out>(&pcm, pcm_name, config, options)?;
+            println!("[alsa-hw] opened {:?}", hw_config);
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let output = OutputLoop {
+                pcm,
+                packed_24: matches!(format, Format::S243LE | Format::U243LE),
+                period_frames: hw_config.period_frames as usize,
+                mmap: options.mmap,
+                channels: usize::from(config.channels),
+                sample_rate: config.sample_rate,
+                state,
+                fanout,
+                stop: Arc::clone(&stop),
+            };
+            Ok(Self {
+                config: hw_config,
+                stop,
+                run: Some(Box::new(move || output.run::<In, Out, C>(consumer))),
+                thread: None,
+            })
+        }
+    }
+
+    struct OutputLoop {
+        pcm: PCM,
+        packed_24: bool,
+        period_frames: usize,
+        mmap: bool,
+        channels: usize,
+        sample_rate: u32,
+        state: Arc<SharedState>,
+        fanout: Arc<OutputFanout>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl OutputLoop {
+        fn run<In, Out, C>(self, mut consumer: C)
+        where
+            In: Copy,
+            Out: HwSample + cpal::FromSample<In>,
+            f32: cpal::FromSample<Out>,
+            f64: cpal::FromSample<Out>,
+            C: Consumer<Item = In> + Observer<Item = In>,
+        {
+            let mut promoted = false;
+            let mut data = vec![Out::EQUILIBRIUM; self.period_frames * self.channels];
+            let mut bytes = Vec::new();
+            let io = self.pcm.io_bytes();
+
+            while !self.stop.load(Ordering::Relaxed) {
+                backend::promote_output_thread_once(&mut promoted, &self.state);
+                match self.pcm.avail_update() {
+                    Ok(avail) if (avail as usize) < self.period_frames => {
+                        // buffer 已满但还没到 start_threshold（mmap 不会自动启动）时手动启动。
+                        if self.pcm.state() == State::Prepared {
+                            let _ = self.pcm.start();
+                        }
+                        if let Err(err) = self.pcm.wait(Some(WAIT_TIMEOUT_MS))
+                            && !self.recover(err)
+                        {
+                            break;
+                        }
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        if !self.recover(err) {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+
+                // delay 是新写入的样本前面还排着的帧数，即这批样本到发声的延迟。
+                let latency = self.pcm.delay().map_or(Duration::ZERO, |frames| {
+                    Duration::from_secs_f64(frames.max(0) as f64 / self.sample_rate as f64)
+                });
+                backend::render_output(
+                    &mut consumer,
+                    &mut data,
+                    &self.state,
+                    self.channels,
+                    &self.fanout,
+                    latency,
+                );
+                bytes.clear();
+                for sample in &data {
+                    sample.write_le(self.packed_24, &mut bytes);
+                }
+
+                let written = if self.mmap {
+                    self.write_mmap(&io, &bytes)
+                } else {
+                    self.write_rw(&io, &bytes)
+                };
+                if let Err(err) = written
+                    && !self.recover(err)
+                {
+                    break;
+                }
+            }
+            let _ = self.pcm.drop();
+        }
+
+        fn write_rw(&self, io: &IO<u8>, bytes: &[u8]) -> alsa::Result<()> {
+            let frame_bytes = bytes.len() / self.period_frames;
+            let mut offset = 0;
+            while offset < bytes.len() && !self.stop.load(Ordering::Relaxed) {
+                let frames = io.writei(&bytes[offset..])?;
+                offset += frames * frame_bytes;
+            }
+            Ok(())
+        }
+
+        fn write_mmap(&self, io: &IO<u8>, bytes: &[u8]) -> alsa::Result<()> {
+            let frame_bytes = bytes.len() / self.period_frames;
+            let mut offset = 0;
+            while offset < bytes.len() && !self.stop.load(Ordering::Relaxed) {
+                self.pcm.avail_update()?;
+                let remaining = (bytes.len() - offset) / frame_bytes;
+                let frames = io.mmap(remaining, |area| {
+                    let len = area.len().min(bytes.len() - offset);
+                    area[..len].copy_from_slice(&bytes[offset..offset + len]);
+                    len / frame_bytes
+                })?;
+                if frames == 0 {
+                    if self.pcm.state() == State::Prepared {
+                        self.pcm.start()?;
+                    }
+                    self.pcm.wait(Some(WAIT_TIMEOUT_MS))?;
+                }
+                offset += frames * frame_bytes;
+            }
+            Ok(())
+        }
+
+        /// xrun/挂起后重新 prepare 继续输出；设备消失或无法恢复时上报并返回 false。
+        fn recover(&self, err: alsa::Error) -> bool {
+            if err.errno() == ENODEV {
+                self.state
+                    .report_output_error(&cpal::StreamError::DeviceNotAvailable);
+                return false;
+            }
+            match self.pcm.try_recover(err, true) {
+                Ok(()) => {
+                    eprintln!("[alsa-hw] recovered from {}", err);
+                    self.state
+                        .report_output_error(&cpal::StreamError::BufferUnderrun);
+                    true
+                }
+                Err(recover_err) => {
+                    self.state
+                        .report_output_error(&cpal::StreamError::BackendSpecific {
+                            err: cpal::BackendSpecificError {
+                                description: recover_err.to_string(),
+                            },
+                        });
+                    false
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::find_bit_perfect_format;
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn find_bit_perfect_format(
+    _pcm_name: &str,
+    _sample_rate: u32,
+    _channels: u16,
+    _candidates: &[cpal::SampleFormat],
+) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
+    Err("直连 ALSA 输出仅支持 Linux".into())
+}
+
+#[cfg(not(target_os = "linux"))]
+impl AlsaHwStream {
+    pub(crate) fn open<In, Out, C>(
+        _pcm_name: &str,
+        _config: &cpal::StreamConfig,
+        _options: AlsaHwOptions,
+        _consumer: C,
+        _state: Arc<SharedState>,
+        _fanout: Arc<OutputFanout>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("直连 ALSA 输出仅支持 Linux".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn hw_pcm_name_strips_plugins() {
+        assert_eq!(hw_pcm_name("hw:1,0").as_deref(), Some("hw:1,0"));
+        assert_eq!(hw_pcm_name("plughw:1,0").as_deref(), Some("hw:1,0"));
+        assert_eq!(
+            hw_pcm_name("alsa:hw:CARD=3,DEV=1").as_deref(),
+            Some("hw:CARD=3,DEV=1")
+        );
+        assert_eq!(hw_pcm_name("default"), None);
+    }
+
+    #[test]
+    fn packs_24_bit_samples_into_three_or_four_bytes() {
+        let sample = cpal::I24::new(-2).unwrap();
+        let mut packed = Vec::new();
+        sample.write_le(true, &mut packed);
+        assert_eq!(packed, vec![0xfe, 0xff, 0xff]);
+
+        let mut padded = Vec::new();
+        sample.write_le(false, &mut padded);
+        assert_eq!(padded, vec![0xfe, 0xff, 0xff, 0xff]);
+
+        let mut pcm16 = Vec::new();
+        0x1234i16.write_le(true, &mut pcm16);
+        assert_eq!(pcm16, vec![0x34, 0x12]);
+    }
+
+    #[test]
+    fn buffer_defaults_to_several_periods() {
+        let options = AlsaHwOptions::default();
+        assert_eq!(options.period_frames(), DEFAULT_PERIOD_FRAMES);
+        assert_eq!(
+            options.buffer_frames(),
+            DEFAULT_PERIOD_FRAMES * DEFAULT_PERIODS
+        );
+
+        let tiny = AlsaHwOptions {
+            period_frames: Some(256),
+            buffer_frames: Some(100),
+            ..AlsaHwOptions::default()
+        };
+        assert_eq!(tiny.buffer_frames(), 512);
+    }
+}
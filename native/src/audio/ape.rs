@@ -0,0 +1,435 @@
+use ape_decoder::format::{self, ApeFileInfo};
+use ape_decoder::{ApeError, FrameDecoder};
+use std::io::{Read, Seek, SeekFrom};
+use symphonia::core::audio::{
+    AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
+};
+use symphonia::core::codecs::{
+    CODEC_TYPE_MONKEYS_AUDIO, CodecDescriptor, CodecParameters, Decoder, DecoderOptions,
+    FinalizeResult,
+};
+use symphonia::core::errors::SeekErrorKind;
+use symphonia::core::errors::{Error, Result, decode_error, seek_error, unsupported_error};
+use symphonia::core::formats::{
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{Metadata, MetadataLog};
+use symphonia::core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia::core::units::TimeBase;
+use symphonia::core::{support_codec, support_format};
+
+/// 每个 packet 前置的帧头：seek_remainder(u32) + frame_blocks(u32)。
+const PACKET_HEADER_LEN: usize = 8;
+/// 单帧压缩数据上限，与 ape-decoder 的检查一致。
+const MAX_FRAME_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Monkey's Audio（.ape）容器。
+///
+/// APE 的每一帧都可以独立解码，因此 packet 直接对应一帧，seek 精确到帧起点，
+/// 帧内剩余部分交给上层按 `actual_ts` 裁剪。
+pub(crate) struct ApeReader {
+    reader: MediaSourceStream,
+    info: ApeFileInfo,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    next_frame: u32,
+}
+
+impl ApeReader {
+    fn frame_ts(&self, frame: u32) -> u64 {
+        u64::from(frame) * u64::from(self.info.header.blocks_per_frame)
+    }
+
+    fn seek_remainder(&self, frame: u32) -> u32 {
+        ((self.info.seek_byte(frame) - self.info.seek_byte(0)) % 4) as u32
+    }
+}
+
+impl QueryDescriptor for ApeReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "ape",
+            "Monkey's Audio",
+            &["ape"],
+            &["audio/ape", "audio/x-ape"],
+            &[b"MAC "]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for ApeReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let info = format::parse(&mut source).map_err(ape_error)?;
+        let header = &info.header;
+        if info.descriptor.version < 3950 {
+            return unsupported_error("ape: files older than version 3.95 are not supported");
+        }
+        if header.format_flags & ape_decoder::format::APE_FORMAT_FLAG_FLOATING_POINT != 0 {
+            return unsupported_error("ape: floating point streams are not supported");
+        }
+        if !matches!(header.bits_per_sample, 8 | 16 | 24 | 32) || header.channels == 0 {
+            return unsupported_error("ape: unsupported sample layout");
+        }
+        if header.sample_rate == 0 || header.blocks_per_frame == 0 {
+            return decode_error("ape: invalid header");
+        }
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_MONKEYS_AUDIO)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_n_frames(info.total_blocks.max(0) as u64)
+            .with_bits_per_sample(u32::from(header.bits_per_sample))
+            .with_bits_per_coded_sample(u32::from(header.bits_per_sample))
+            .with_channels(channels_from_count(header.channels))
+            .with_max_frames_per_packet(u64::from(header.blocks_per_frame))
+            .with_extra_data(codec_extra_data(
+                info.descriptor.version,
+                header.compression_level,
+            ));
+
+        let first_frame = info.seek_byte(0);
+        source.seek(SeekFrom::Start(first_frame))?;
+        Ok(Self {
+            reader: source,
+            info,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            next_frame: 0,
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => {
+                TimeBase::new(1, self.info.header.sample_rate).calc_timestamp(time)
+            }
+        };
+        let total_frames = self.info.header.total_frames;
+        if total_frames == 0 || ts >= self.info.total_blocks.max(0) as u64 {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let frame = (ts / u64::from(self.info.header.blocks_per_frame)) as u32;
+        self.next_frame = frame.min(total_frames - 1);
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts: ts,
+            actual_ts: self.frame_ts(self.next_frame),
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame = self.next_frame;
+        if frame >= self.info.header.total_frames {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let remainder = self.seek_remainder(frame);
+        let frame_bytes = self.info.frame_byte_count(frame);
+        if frame_bytes == 0 || frame_bytes > MAX_FRAME_BYTES {
+            return decode_error("ape: invalid frame size");
+        }
+        let blocks = self.info.frame_block_count(frame);
+
+        // ape-decoder 的位读取器按 4 字节对齐读取，帧数据需要带上对齐前缀和 4 字节尾部余量。
+        let wanted = frame_bytes as usize + remainder as usize + 4;
+        let mut data = vec![0u8; PACKET_HEADER_LEN + wanted];
+        data[..4].copy_from_slice(&remainder.to_le_bytes());
+        data[4..8].copy_from_slice(&blocks.to_le_bytes());
+        self.reader.seek(SeekFrom::Start(
+            self.info.seek_byte(frame) - u64::from(remainder),
+        ))?;
+        let read = read_up_to(&mut self.reader, &mut data[PACKET_HEADER_LEN..])?;
+        if read < wanted - 4 {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        data.truncate(PACKET_HEADER_LEN + read);
+
+        self.next_frame += 1;
+        Ok(Packet::new_from_boxed_slice(
+            0,
+            self.frame_ts(frame),
+            u64::from(blocks),
+            data.into_boxed_slice(),
+        ))
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// 基于 ape-decoder `FrameDecoder` 的 Monkey's Audio 解码器。
+pub(crate) struct ApeDecoder {
+    params: CodecParameters,
+    frames: FrameDecoder,
+    stream: ApeStreamInfo,
+    buf: AudioBuffer<i32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ApeStreamInfo {
+    version: u16,
+    compression_level: u16,
+    channels: u16,
+    bits_per_sample: u16,
+}
+
+impl ApeStreamInfo {
+    fn from_params(params: &CodecParameters) -> Option<Self> {
+        let extra = params.extra_data.as_deref()?;
+        if extra.len() < 4 {
+            return None;
+        }
+        Some(Self {
+            version: u16::from_le_bytes([extra[0], extra[1]]),
+            compression_level: u16::from_le_bytes([extra[2], extra[3]]),
+            channels: params.channels?.count() as u16,
+            bits_per_sample: params.bits_per_sample? as u16,
+        })
+    }
+
+    fn frame_decoder(&self) -> Result<FrameDecoder> {
+        FrameDecoder::new(
+            self.version,
+            self.channels,
+            self.bits_per_sample,
+            self.compression_level,
+        )
+        .map_err(|_| Error::Unsupported("ape: unsupported stream parameters"))
+    }
+}
+
+impl Decoder for ApeDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (Some(stream), Some(sample_rate), Some(channels)) = (
+            ApeStreamInfo::from_params(params),
+            params.sample_rate,
+            params.channels,
+        ) else {
+            return unsupported_error("ape: missing stream parameters");
+        };
+        let max_frames = params.max_frames_per_packet.unwrap_or(73_728);
+        Ok(Self {
+            params: params.clone(),
+            frames: stream.frame_decoder()?,
+            stream,
+            buf: AudioBuffer::new(max_frames, SignalSpec::new(sample_rate, channels)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(
+            CODEC_TYPE_MONKEYS_AUDIO,
+            "ape",
+            "Monkey's Audio"
+        )]
+    }
+
+    fn reset(&mut self) {
+        if let Ok(frames) = self.stream.frame_decoder() {
+            self.frames = frames;
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let data = packet.buf();
+        if data.len() < PACKET_HEADER_LEN {
+            return decode_error("ape: truncated packet");
+        }
+        let remainder = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let blocks = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let pcm = match self
+            .frames
+            .decode_frame(&data[PACKET_HEADER_LEN..], remainder, blocks)
+        {
+            Ok(pcm) => pcm,
+            Err(err) => {
+                // 校验失败后解码器状态不可信，下一帧从干净状态开始。
+                self.reset();
+                return decode_error(ape_error_message(&err));
+            }
+        };
+
+        let channels = usize::from(self.stream.channels);
+        let bytes_per_sample = usize::from(self.stream.bits_per_sample / 8);
+        let frames = pcm.len() / (channels * bytes_per_sample);
+        if frames as u64 > self.buf.capacity() as u64 {
+            self.buf = AudioBuffer::new(frames as u64, *self.buf.spec());
+        }
+        self.buf.render_reserved(Some(frames));
+        for channel in 0..channels {
+            let out = self.buf.chan_mut(channel);
+            for (frame, sample) in out.iter_mut().enumerate() {
+                let offset = (frame * channels + channel) * bytes_per_sample;
+                *sample = pcm_sample(&pcm[offset..offset + bytes_per_sample]);
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+fn codec_extra_data(version: u16, compression_level: u16) -> Box<[u8]> {
+    let mut extra = Vec::with_capacity(4);
+    extra.extend_from_slice(&version.to_le_bytes());
+    extra.extend_from_slice(&compression_level.to_le_bytes());
+    extra.into_boxed_slice()
+}
+
+/// APE 沿用 WAVE 的声道顺序，与 Symphonia 的声道位序一致。
+pub(crate) fn channels_from_count(count: u16) -> Channels {
+    match count {
+        1 => Channels::FRONT_LEFT,
+        2 => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        _ => {
+            let count = u32::from(count).min(32);
+            let bits = if count == 32 {
+                u32::MAX
+            } else {
+                (1u32 << count) - 1
+            };
+            Channels::from_bits_truncate(bits)
+        }
+    }
+}
+
+/// 把 WAVE 小端 PCM 样本左对齐到 i32（8-bit 为无符号）。
+fn pcm_sample(bytes: &[u8]) -> i32 {
+    match *bytes {
+        [b0] => (i32::from(b0) - 128) << 24,
+        [b0, b1] => i32::from(i16::from_le_bytes([b0, b1])) << 16,
+        [b0, b1, b2] => i32::from_le_bytes([0, b0, b1, b2]),
+        [b0, b1, b2, b3] => i32::from_le_bytes([b0, b1, b2, b3]),
+        _ => 0,
+    }
+}
+
+pub(crate) fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+fn ape_error(err: ApeError) -> Error {
+    match err {
+        ApeError::Io(err) => Error::IoError(err),
+        ApeError::UnsupportedVersion(_) => Error::Unsupported("ape: unsupported version"),
+        other => Error::DecodeError(ape_error_message(&other)),
+    }
+}
+
+fn ape_error_message(err: &ApeError) -> &'static str {
+    match err {
+        ApeError::InvalidChecksum => "ape: frame checksum mismatch",
+        ApeError::InvalidFormat(message) | ApeError::DecodingError(message) => message,
+        _ => "ape: decode failed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::registry::{self, codec_registry};
+    use symphonia::core::probe::Hint;
+
+    /// 两帧（256 + 100 个采样块）的 16 bit 立体声 APE 流，版本 3990、压缩级别 2000。
+    const STEREO_16BIT: &[u8] = include_bytes!("testdata/stereo_16bit.ape");
+
+    #[test]
+    fn pcm_samples_are_left_aligned_to_i32() {
+        assert_eq!(pcm_sample(&[0x80]), 0);
+        assert_eq!(pcm_sample(&[0x00]), i32::MIN);
+        assert_eq!(pcm_sample(&(-2i16).to_le_bytes()), -2 << 16);
+        assert_eq!(pcm_sample(&[0xff, 0xff, 0x7f]), 0x7fff_ff00);
+        assert_eq!(pcm_sample(&[0x00, 0x00, 0x80]), i32::MIN);
+        assert_eq!(pcm_sample(&7i32.to_le_bytes()), 7);
+    }
+
+    #[test]
+    fn probe_rejects_truncated_ape_header() {
+        let mut bytes = b"MAC ".to_vec();
+        bytes.extend_from_slice(&3990u16.to_le_bytes());
+        bytes.resize(32, 0);
+        let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+        assert!(ApeReader::try_new(mss, &FormatOptions::default()).is_err());
+    }
+
+    #[test]
+    fn decodes_fixture_stream_bit_exact() {
+        let mss = MediaSourceStream::new(
+            Box::new(std::io::Cursor::new(STEREO_16BIT.to_vec())),
+            Default::default(),
+        );
+        let mut hint = Hint::new();
+        hint.with_extension("ape");
+        let mut format = registry::open_format(mss, &hint, None).unwrap();
+        let params = format.default_track().unwrap().codec_params.clone();
+        assert_eq!(params.sample_rate, Some(44_100));
+        assert_eq!(params.n_frames, Some(356));
+        let mut decoder = codec_registry()
+            .make(&params, &DecoderOptions::default())
+            .unwrap();
+
+        let mut samples = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            let AudioBufferRef::S32(buf) = decoder.decode(&packet).unwrap() else {
+                panic!("ape decodes to i32");
+            };
+            for frame in 0..buf.frames() {
+                samples.push(buf.chan(0)[frame] >> 16);
+                samples.push(buf.chan(1)[frame] >> 16);
+            }
+        }
+
+        assert_eq!(samples.len(), 356 * 2);
+        assert_eq!(samples[..4], [512, -423, 1527, 616]);
+        let checksum = samples
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, sample| {
+                (hash ^ (*sample as u16 as u64)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        assert_eq!(checksum, 0x315b_037a_f2c1_2e39);
+    }
+}
@@ -1,12 +1,19 @@
+use crate::audio::dsd::DOP_CARRIER_RATES;
+use crate::audio::multi_output::OutputFanout;
+use crate::audio::pipeline_stats;
+use crate::audio::silence;
 use crate::audio::state::SharedState;
-use cpal::traits::DeviceTrait;
+use crate::audio::thread_priority::{self, OUTPUT_THREAD_RT_PRIORITY, SchedPolicy};
+use cpal::traits::{DeviceTrait, HostTrait};
 use ringbuf::traits::{Consumer, Observer};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use symphonia::core::sample::SampleFormat as SymphoniaSampleFormat;
 
 const TARGET_OUTPUT_BUFFER_MS: u32 = 20;
+const DOP_OUTPUT_FORMATS: [cpal::SampleFormat; 2] =
+    [cpal::SampleFormat::I24, cpal::SampleFormat::I32];
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OutputDeviceInfo {
@@ -14,6 +21,8 @@ pub struct OutputDeviceInfo {
     pub name: String,
     pub is_default: bool,
     pub is_current: bool,
+    /// 所在声卡有播放音量控件，可用 ALSA 硬件音量代替软件音量。
+    pub hw_volume: bool,
 }
 
 pub(crate) fn is_supported_output_format(fmt: cpal::SampleFormat) -> bool {
@@ -32,10 +41,17 @@ pub(crate) fn is_supported_output_format(fmt: cpal::SampleFormat) -> bool {
     )
 }
 
+/// `dop` 为 true 时音源是 DoP 帧：只能走保留高 24 位原样的整数格式，
+/// 浮点或 16bit 回退都会破坏标记字节，DAC 无法识别。
 pub(crate) fn preferred_output_formats(
     bits_per_sample: Option<u32>,
     source_sample_format: Option<SymphoniaSampleFormat>,
+    dop: bool,
 ) -> Vec<cpal::SampleFormat> {
+    if dop {
+        return DOP_OUTPUT_FORMATS.to_vec();
+    }
+
     let mut prefer = Vec::new();
 
     if let Some(fmt) = source_sample_format {
@@ -93,121 +109,117 @@ pub(crate) fn preferred_output_formats(
     prefer
 }
 
-pub(crate) fn build_stream<S, C>(
+pub(crate) fn build_stream_converted<In, Out, C>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
     mut consumer: C,
     state: Arc<SharedState>,
     channels: usize,
+    fanout: Arc<OutputFanout>,
 ) -> Result<cpal::Stream, Box<dyn std::error::Error>>
 where
-    S: cpal::SizedSample + Send + 'static,
-    C: Consumer<Item = S> + Observer<Item = S> + Send + 'static,
+    In: Copy + Send + 'static,
+    Out: cpal::SizedSample + cpal::FromSample<In> + Send + 'static,
+    f32: cpal::FromSample<Out>,
+    f64: cpal::FromSample<Out>,
+    C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
 {
+    let mut output_thread_promoted = false;
+    let error_state = Arc::clone(&state);
     let stream = device.build_output_stream(
         config,
-        move |data: &mut [S], info: &cpal::OutputCallbackInfo| {
-            drain_discarded_buffer(&mut consumer, &state);
-
-            if should_wait_for_buffer(consumer.occupied_len(), channels, &state) {
-                data.fill(S::EQUILIBRIUM);
-                return;
-            }
-
-            if state.is_paused.load(Ordering::Relaxed) {
-                data.fill(S::EQUILIBRIUM);
-                return;
-            }
-
-            let mut samples_read = 0usize;
-            for sample in data.iter_mut() {
-                if let Some(s) = consumer.try_pop() {
-                    *sample = s;
-                    samples_read += 1;
-                } else {
-                    *sample = S::EQUILIBRIUM;
-                }
-            }
-
-            if samples_read > 0 {
-                let frames_read = (samples_read / channels) as u64;
-                let buffer_start_frame = state
-                    .current_frame
-                    .fetch_add(frames_read, Ordering::Relaxed);
-                state.update_playback_clock_from_output(
-                    buffer_start_frame,
-                    buffer_start_frame.saturating_add(frames_read),
-                    output_latency(info),
-                );
-            } else if state.decoder_done.load(Ordering::Relaxed)
-                && !state.is_finished.swap(true, Ordering::SeqCst)
-            {
-                state.finish_notify.notify_waiters();
-            }
+        move |data: &mut [Out], info: &cpal::OutputCallbackInfo| {
+            promote_output_thread_once(&mut output_thread_promoted, &state);
+            render_output(
+                &mut consumer,
+                data,
+                &state,
+                channels,
+                &fanout,
+                output_latency(info),
+            );
         },
-        |_err| {},
+        move |err| error_state.report_output_error(&err),
         None,
     )?;
     Ok(stream)
 }
 
-pub(crate) fn build_stream_converted<In, Out, C>(
-    device: &cpal::Device,
-    config: &cpal::StreamConfig,
-    mut consumer: C,
-    state: Arc<SharedState>,
+/// 一次输出回调：排空 seek 前的旧样本，缓冲中或暂停时输出静音，否则从 ring buffer
+/// 取样本写进 `data` 并推进进度。`latency` 是这批样本到真正发声的延迟。
+/// cpal 回调和直连 ALSA 的输出线程共用。
+pub(crate) fn render_output<In, Out, C>(
+    consumer: &mut C,
+    data: &mut [Out],
+    state: &SharedState,
     channels: usize,
-) -> Result<cpal::Stream, Box<dyn std::error::Error>>
-where
-    In: Copy + Send + 'static,
-    Out: cpal::SizedSample + cpal::FromSample<In> + Send + 'static,
-    C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
+    fanout: &OutputFanout,
+    latency: Duration,
+) where
+    Out: cpal::SizedSample + cpal::FromSample<In>,
+    f32: cpal::FromSample<Out>,
+    f64: cpal::FromSample<Out>,
+    In: Copy,
+    C: Consumer<Item = In> + Observer<Item = In>,
 {
-    let stream = device.build_output_stream(
-        config,
-        move |data: &mut [Out], info: &cpal::OutputCallbackInfo| {
-            drain_discarded_buffer(&mut consumer, &state);
+    state.output_callbacks.fetch_add(1, Ordering::Relaxed);
+    let now = Instant::now();
+    let sample_rate = state.sample_rate.load(Ordering::Relaxed);
+    state.pipeline_stats.record_callback(
+        pipeline_stats::samples_duration(data.len(), channels, sample_rate),
+        now,
+    );
+    if drain_discarded_buffer(consumer, state) {
+        fanout.flush();
+    }
 
-            if should_wait_for_buffer(consumer.occupied_len(), channels, &state) {
-                data.fill(Out::EQUILIBRIUM);
-                return;
-            }
+    let buffered_samples = consumer.occupied_len();
+    state.pipeline_stats.sample_fill(
+        pipeline_stats::samples_duration(buffered_samples, channels, sample_rate),
+        pipeline_stats::samples_duration(consumer.capacity().get(), channels, sample_rate),
+        now,
+    );
+    if should_wait_for_buffer(buffered_samples, channels, state) {
+        data.fill(Out::EQUILIBRIUM);
+        return;
+    }
 
-            if state.is_paused.load(Ordering::Relaxed) {
-                data.fill(Out::EQUILIBRIUM);
-                return;
-            }
+    if state.is_paused.load(Ordering::Relaxed) {
+        data.fill(Out::EQUILIBRIUM);
+        return;
+    }
 
-            let mut samples_read = 0usize;
-            for sample in data.iter_mut() {
-                if let Some(s) = consumer.try_pop() {
-                    *sample = Out::from_sample(s);
-                    samples_read += 1;
-                } else {
-                    *sample = Out::EQUILIBRIUM;
-                }
-            }
+    let skipped_frames = skip_silence::<In, Out, C>(consumer, state, channels, data.len());
+    if skipped_frames > 0 {
+        state
+            .current_frame
+            .fetch_add(skipped_frames, Ordering::Relaxed);
+    }
 
-            if samples_read > 0 {
-                let frames_read = (samples_read / channels) as u64;
-                let buffer_start_frame = state
-                    .current_frame
-                    .fetch_add(frames_read, Ordering::Relaxed);
-                state.update_playback_clock_from_output(
-                    buffer_start_frame,
-                    buffer_start_frame.saturating_add(frames_read),
-                    output_latency(info),
-                );
-            } else if state.decoder_done.load(Ordering::Relaxed)
-                && !state.is_finished.swap(true, Ordering::SeqCst)
-            {
-                state.finish_notify.notify_waiters();
-            }
-        },
-        |_err| {},
-        None,
-    )?;
-    Ok(stream)
+    let (samples_read, range_ended) =
+        pop_within_range(consumer, data, state, channels, Out::from_sample);
+    record_played_silence(&data[..samples_read], state, channels);
+
+    if samples_read > 0 {
+        if !state.scrubbing.load(Ordering::Relaxed) {
+            state.sample_verifier.record_output(&data[..samples_read]);
+        }
+        fanout.push(&data[..samples_read]);
+        let frames_read = (samples_read / channels) as u64;
+        state.silence.consume(frames_read);
+        let buffer_start_frame = state
+            .current_frame
+            .fetch_add(frames_read, Ordering::Relaxed);
+        state.update_playback_clock_from_output(
+            buffer_start_frame,
+            buffer_start_frame.saturating_add(frames_read),
+            latency,
+        );
+    } else if (range_ended || state.decoder_done.load(Ordering::Relaxed))
+        && !state.is_finished.swap(true, Ordering::SeqCst)
+    {
+        state.finish_notify.notify_waiters();
+    }
 }
 
 fn output_latency(info: &cpal::OutputCallbackInfo) -> Duration {
@@ -218,7 +230,164 @@ fn output_latency(info: &cpal::OutputCallbackInfo) -> Duration {
         .unwrap_or(Duration::ZERO)
 }
 
-fn drain_discarded_buffer<S, C>(consumer: &mut C, state: &SharedState)
+/// cpal 不暴露回调线程句柄，只能在回调线程里首次进入时自行提升优先级。
+pub(crate) fn promote_output_thread_once(promoted: &mut bool, state: &SharedState) {
+    if *promoted {
+        return;
+    }
+    *promoted = true;
+    if !state.realtime_scheduling.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let granted =
+        thread_priority::promote_current_thread(SchedPolicy::Fifo, OUTPUT_THREAD_RT_PRIORITY);
+    *state.output_thread_priority.lock().unwrap() = Some(granted);
+}
+
+/// 从 ring buffer 取样本填满 `data`，不越过播放区间终点；到达终点时若排队了
+/// 相邻区间就直接续上。返回取到的样本数以及是否停在了区间终点。
+fn pop_within_range<In, Out, C>(
+    consumer: &mut C,
+    data: &mut [Out],
+    state: &SharedState,
+    channels: usize,
+    convert: impl Fn(In) -> Out,
+) -> (usize, bool)
+where
+    Out: cpal::Sample,
+    C: Consumer<Item = In>,
+{
+    let start_frame = state.current_frame.load(Ordering::Relaxed);
+    let mut samples_read = 0usize;
+    let mut range_ended = false;
+
+    while samples_read < data.len() {
+        let remaining = data.len() - samples_read;
+        let frame = start_frame + (samples_read / channels) as u64;
+        let budget = match state.frames_until_range_end(frame) {
+            None => remaining,
+            Some(0) => {
+                if state.advance_to_next_range() {
+                    continue;
+                }
+                range_ended = true;
+                break;
+            }
+            Some(frames) => remaining.min(frames.saturating_mul(channels as u64) as usize),
+        };
+
+        let mut popped = 0usize;
+        for sample in &mut data[samples_read..samples_read + budget] {
+            match consumer.try_pop() {
+                Some(s) => {
+                    *sample = convert(s);
+                    popped += 1;
+                }
+                None => break,
+            }
+        }
+        samples_read += popped;
+        if popped < budget {
+            break;
+        }
+    }
+
+    data[samples_read..].fill(Out::EQUILIBRIUM);
+    (samples_read, range_ended)
+}
+
+fn silence_skipping_allowed(state: &SharedState) -> bool {
+    state.silence.is_active()
+        && !state.scrubbing.load(Ordering::Relaxed)
+        && !state.sample_verifier.is_enabled()
+}
+
+/// 按静音设置丢弃 ring buffer 开头的静音帧，返回丢弃的帧数。不越过播放区间终点；
+/// 静音还没到头时至少留下这次回调要输出的 `data_len` 个样本，避免缓冲被取空。
+fn skip_silence<In, Out, C>(
+    consumer: &mut C,
+    state: &SharedState,
+    channels: usize,
+    data_len: usize,
+) -> u64
+where
+    In: Copy,
+    Out: cpal::Sample + cpal::FromSample<In>,
+    f32: cpal::FromSample<Out>,
+    C: Consumer<Item = In> + Observer<Item = In>,
+{
+    if channels == 0 || !silence_skipping_allowed(state) {
+        return 0;
+    }
+
+    let buffered_frames = (consumer.occupied_len() / channels) as u64;
+    let range_left = state.frames_until_range_end(state.current_frame.load(Ordering::Relaxed));
+    let scan_frames = range_left.map_or(buffered_frames, |left| left.min(buffered_frames));
+    let track_end_buffered = match range_left {
+        Some(left) => left <= buffered_frames,
+        None => state.decoder_done.load(Ordering::Relaxed),
+    };
+
+    // 上次回调确认过的静音帧不再重看，只扫描之后新到的帧，回调平摊下来只做 O(period) 的工作。
+    let threshold = state.silence.threshold();
+    let scanned = state.silence.scanned_frames();
+    let (head, tail) = consumer.as_slices();
+    let sample_at = |index: usize| match head.get(index) {
+        Some(sample) => *sample,
+        None => tail[index - head.len()],
+    };
+    let mut silent_frames = scanned.min(scan_frames);
+    while silent_frames < scan_frames {
+        let start = silent_frames as usize * channels;
+        let frame = (start..start + channels)
+            .map(|index| cpal::Sample::to_sample::<f32>(Out::from_sample(sample_at(index))));
+        if !silence::is_silent_frame(frame, threshold) {
+            break;
+        }
+        silent_frames += 1;
+    }
+    if silent_frames > scanned {
+        state.silence.extend_scan(silent_frames - scanned);
+    }
+
+    let reaches_end = silent_frames == scan_frames && track_end_buffered;
+    let mut skip = state.silence.frames_to_skip(silent_frames, reaches_end);
+    if silent_frames == scan_frames && !reaches_end {
+        skip = skip.min(silent_frames.saturating_sub((data_len / channels) as u64));
+    }
+    if skip > 0 {
+        consumer.skip(skip as usize * channels);
+        state.silence.consume(skip);
+    }
+    skip
+}
+
+/// 记下刚输出的样本末尾有多少静音，跨回调累计静音的长度。
+fn record_played_silence<Out>(played: &[Out], state: &SharedState, channels: usize)
+where
+    Out: cpal::Sample,
+    f32: cpal::FromSample<Out>,
+{
+    if channels == 0 || !silence_skipping_allowed(state) {
+        return;
+    }
+    let threshold = state.silence.threshold();
+    let trailing = played
+        .rchunks_exact(channels)
+        .take_while(|frame| {
+            silence::is_silent_frame(
+                frame.iter().map(|s| cpal::Sample::to_sample::<f32>(*s)),
+                threshold,
+            )
+        })
+        .count() as u64;
+    state
+        .silence
+        .record_played((played.len() / channels) as u64, trailing);
+}
+
+fn drain_discarded_buffer<S, C>(consumer: &mut C, state: &SharedState) -> bool
 where
     C: Consumer<Item = S>,
 {
@@ -226,8 +395,11 @@ where
         state.is_discarding_buffer.store(true, Ordering::SeqCst);
         state.discard_buffer.store(false, Ordering::SeqCst);
         while consumer.try_pop().is_some() {}
+        state.silence.reset_scan();
         state.is_discarding_buffer.store(false, Ordering::SeqCst);
+        return true;
     }
+    false
 }
 
 fn should_wait_for_buffer(buffered_samples: usize, channels: usize, state: &SharedState) -> bool {
@@ -242,6 +414,7 @@ fn should_wait_for_buffer(buffered_samples: usize, channels: usize, state: &Shar
     if state.waiting_for_seek.load(Ordering::Relaxed) {
         if buffered_samples >= min_samples_to_resume || decoder_done {
             state.waiting_for_seek.store(false, Ordering::Relaxed);
+            state.pipeline_stats.finish_seek(Instant::now());
             return false;
         }
 
@@ -250,6 +423,15 @@ fn should_wait_for_buffer(buffered_samples: usize, channels: usize, state: &Shar
 
     if buffered_samples == 0 && !decoder_done {
         state.waiting_for_seek.store(true, Ordering::Relaxed);
+        // 开播前的空缓冲、暂停和拖动预览不算欠载。
+        let started = state.current_frame.load(Ordering::Relaxed)
+            > state.range_start_frame.load(Ordering::Relaxed);
+        if started
+            && !state.is_paused.load(Ordering::Relaxed)
+            && !state.scrubbing.load(Ordering::Relaxed)
+        {
+            state.pipeline_stats.record_underrun();
+        }
         return true;
     }
 
@@ -262,10 +444,11 @@ pub(crate) fn find_best_config(
     channels: u16,
     bits_per_sample: Option<u32>,
     source_sample_format: Option<SymphoniaSampleFormat>,
+    dop: bool,
 ) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
     println!(
-        "[audio] target request: sample_rate={}Hz, channels={}",
-        target_sr, channels
+        "[audio] target request: sample_rate={}Hz, channels={}, dop={}",
+        target_sr, channels, dop
     );
 
     let mut candidates = Vec::new();
@@ -278,7 +461,7 @@ pub(crate) fn find_best_config(
         }
     }
 
-    let prefer = preferred_output_formats(bits_per_sample, source_sample_format);
+    let prefer = preferred_output_formats(bits_per_sample, source_sample_format, dop);
     for fmt in prefer.iter() {
         if let Some(c) = candidates.iter().find(|c| c.sample_format() == *fmt) {
             let config = stream_config_with_target_buffer(c, target_sr);
@@ -286,6 +469,10 @@ pub(crate) fn find_best_config(
         }
     }
 
+    if dop {
+        return Err(format!("Hardware doesn't accept DoP carrier at {}Hz", target_sr).into());
+    }
+
     if let Some(c) = candidates
         .iter()
         .find(|c| is_supported_output_format(c.sample_format()))
@@ -297,12 +484,62 @@ pub(crate) fn find_best_config(
     Err("Hardware doesn't support file's sample-rate/channels in compatible sample format".into())
 }
 
+/// 副输出只需要能出声：优先源采样率和声道数，格式限定 F32/I32/I16，
+/// 采样率不一致时交给副输出自己的重采样器。
+pub(crate) fn find_secondary_output_config(
+    device: &cpal::Device,
+    source_rate: u32,
+    channels: u16,
+) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
+    const FORMATS: [cpal::SampleFormat; 3] = [
+        cpal::SampleFormat::F32,
+        cpal::SampleFormat::I32,
+        cpal::SampleFormat::I16,
+    ];
+
+    let candidates = device
+        .supported_output_configs()?
+        .filter(|c| FORMATS.contains(&c.sample_format()))
+        .collect::<Vec<_>>();
+    let best = candidates.iter().min_by_key(|c| {
+        let rate_supported =
+            source_rate >= c.min_sample_rate() && source_rate <= c.max_sample_rate();
+        let format_rank = FORMATS
+            .iter()
+            .position(|f| *f == c.sample_format())
+            .unwrap_or(FORMATS.len());
+        (!rate_supported, c.channels() != channels, format_rank)
+    });
+    let Some(best) = best else {
+        return Err("No F32/I32/I16 output config for secondary output".into());
+    };
+
+    let sample_rate = source_rate.clamp(best.min_sample_rate(), best.max_sample_rate());
+    Ok((
+        stream_config_with_target_buffer(best, sample_rate),
+        best.sample_format(),
+    ))
+}
+
+/// 按设备 id（Linux 上兼容 hw→plughw 改写）查找输出设备。
+pub(crate) fn find_output_device(name: &str) -> Result<cpal::Device, Box<dyn std::error::Error>> {
+    #[cfg(target_os = "linux")]
+    let target_id = linux_plughw_locator(name).unwrap_or_else(|| name.to_string());
+    #[cfg(not(target_os = "linux"))]
+    let target_id = name;
+
+    cpal::default_host()
+        .output_devices()?
+        .find(|d| device_id(d) == target_id)
+        .ok_or_else(|| format!("Device not found: {}", name).into())
+}
+
 #[cfg(test)]
 pub(crate) fn exact_output_format(
     bits_per_sample: Option<u32>,
     source_sample_format: Option<SymphoniaSampleFormat>,
 ) -> Option<cpal::SampleFormat> {
-    bit_perfect_output_formats(bits_per_sample, source_sample_format)
+    bit_perfect_output_formats(bits_per_sample, source_sample_format, false)
         .into_iter()
         .next()
 }
@@ -310,7 +547,12 @@ pub(crate) fn exact_output_format(
 pub(crate) fn bit_perfect_output_formats(
     bits_per_sample: Option<u32>,
     source_sample_format: Option<SymphoniaSampleFormat>,
+    dop: bool,
 ) -> Vec<cpal::SampleFormat> {
+    if dop {
+        return DOP_OUTPUT_FORMATS.to_vec();
+    }
+
     if let Some(fmt) = source_sample_format {
         return match fmt {
             SymphoniaSampleFormat::F32 => vec![cpal::SampleFormat::F32],
@@ -341,11 +583,19 @@ pub(crate) fn find_bit_perfect_config(
     channels: u16,
     bits_per_sample: Option<u32>,
     source_sample_format: Option<SymphoniaSampleFormat>,
+    dop: bool,
 ) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
-    let required_formats = bit_perfect_output_formats(bits_per_sample, source_sample_format);
+    let required_formats = bit_perfect_output_formats(bits_per_sample, source_sample_format, dop);
     if required_formats.is_empty() {
         return Err("当前无法满足BitPerfect条件拒绝播放：无法确定音源样本格式".into());
     }
+    if dop && !DOP_CARRIER_RATES.contains(&target_sr) {
+        return Err(format!(
+            "当前无法满足BitPerfect条件拒绝播放：DoP 载波采样率只能是 176400Hz 或 352800Hz，当前为 {}Hz",
+            target_sr
+        )
+        .into());
+    }
 
     let mut supported_channels = std::collections::BTreeSet::new();
     let mut supported_formats = std::collections::BTreeSet::new();
@@ -525,6 +775,19 @@ pub(crate) fn device_display_name(device: &cpal::Device) -> String {
     alsa_name
 }
 
+/// 声卡列表的快照，插拔声卡时会变化；用来避免每次轮询都重新枚举设备。
+/// 拿不到时返回 `None`，调用方应每次都重新枚举。
+pub(crate) fn sound_card_signature() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/asound/cards").ok()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
 #[cfg(target_os = "linux")]
 pub(crate) fn linux_plughw_locator(id: &str) -> Option<String> {
     if let Some(stripped) = id.strip_prefix("alsa:hw:") {
@@ -588,6 +851,7 @@ pub(crate) fn append_linux_alsa_hint_output_devices(
         devices.push(OutputDeviceInfo {
             is_default: default_id == Some(id.as_str()),
             is_current: current_id == Some(id.as_str()),
+            hw_volume: false,
             id,
             name,
         });
@@ -644,6 +908,7 @@ pub(crate) fn append_linux_proc_asound_output_devices_at(
         devices.push(OutputDeviceInfo {
             is_default: default_id == Some(id.as_str()),
             is_current: current_id == Some(id.as_str()),
+            hw_volume: false,
             id,
             name,
         });
@@ -807,6 +1072,16 @@ pub(crate) fn alsa_reservation_name_for_device_id(id: &str) -> Option<String> {
     alsa_card_index_from_device_id(id).map(|card_index| format!("Audio{card_index}"))
 }
 
+/// 设备当前生效的 hw_params 所在的 procfs 路径，只认 `hw:`/`plughw:` 设备。
+#[cfg(target_os = "linux")]
+pub(crate) fn alsa_hw_params_path(id: &str) -> Option<std::path::PathBuf> {
+    let card_index = alsa_card_index_from_device_id(id)?;
+    let device_index = extract_alsa_device_token(id).unwrap_or(0);
+    Some(std::path::PathBuf::from(format!(
+        "/proc/asound/card{card_index}/pcm{device_index}p/sub0/hw_params"
+    )))
+}
+
 #[cfg(target_os = "linux")]
 pub(crate) fn alsa_card_index_from_device_id(id: &str) -> Option<u32> {
     alsa_card_index_from_device_id_at(id, std::path::Path::new("/proc/asound"))
@@ -906,45 +1181,42 @@ pub(crate) fn disambiguate_output_device_names(devices: &mut [OutputDeviceInfo])
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::audio::state::{NO_TRIM_FRAME, SharedState};
+    use crate::audio::silence::SilenceOptions;
+    use crate::audio::state::SharedState;
     use ringbuf::HeapRb;
     use ringbuf::traits::{Observer, Producer, Split};
-    use std::sync::Mutex;
-    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
-    use tokio::sync::Notify;
+    use std::sync::atomic::Ordering;
 
     fn create_state(sample_rate: u32) -> SharedState {
-        SharedState {
-            is_paused: AtomicBool::new(false),
-            current_frame: AtomicU64::new(0),
-            playback_clock: Mutex::new(crate::audio::state::PlaybackClock::new()),
-            trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
-            sample_rate: AtomicU32::new(sample_rate),
-            has_seek_request: AtomicBool::new(false),
-            seek_request: Mutex::new(None),
-            is_terminating: AtomicBool::new(false),
-            discard_buffer: AtomicBool::new(false),
-            is_discarding_buffer: AtomicBool::new(false),
-            decoder_done: AtomicBool::new(false),
-            is_finished: AtomicBool::new(false),
-            finish_notify: Notify::new(),
-            buffered_frames: AtomicU64::new(0),
-            waiting_for_seek: AtomicBool::new(false),
-        }
+        SharedState::new(sample_rate)
     }
 
     #[test]
     fn preferred_output_formats_prioritizes_source_format() {
-        let formats = preferred_output_formats(Some(24), Some(SymphoniaSampleFormat::F32));
+        let formats = preferred_output_formats(Some(24), Some(SymphoniaSampleFormat::F32), false);
 
         assert_eq!(formats[0], cpal::SampleFormat::F32);
         assert_eq!(formats[1], cpal::SampleFormat::F64);
         assert!(formats.contains(&cpal::SampleFormat::I24));
     }
 
+    #[test]
+    fn dop_output_only_allows_integer_formats_carrying_24_bits() {
+        let expected = vec![cpal::SampleFormat::I24, cpal::SampleFormat::I32];
+
+        assert_eq!(
+            preferred_output_formats(Some(24), Some(SymphoniaSampleFormat::S24), true),
+            expected
+        );
+        assert_eq!(
+            bit_perfect_output_formats(Some(24), Some(SymphoniaSampleFormat::S24), true),
+            expected
+        );
+    }
+
     #[test]
     fn preferred_output_formats_keeps_fallbacks_unique() {
-        let formats = preferred_output_formats(Some(16), Some(SymphoniaSampleFormat::S16));
+        let formats = preferred_output_formats(Some(16), Some(SymphoniaSampleFormat::S16), false);
 
         let mut unique_formats = Vec::new();
         for format in &formats {
@@ -1165,11 +1437,11 @@ mod tests {
             Some(cpal::SampleFormat::I24)
         );
         assert_eq!(
-            bit_perfect_output_formats(Some(24), Some(SymphoniaSampleFormat::S24)),
+            bit_perfect_output_formats(Some(24), Some(SymphoniaSampleFormat::S24), false),
             vec![cpal::SampleFormat::I24, cpal::SampleFormat::I32]
         );
         assert_eq!(
-            bit_perfect_output_formats(Some(24), None),
+            bit_perfect_output_formats(Some(24), None, false),
             vec![cpal::SampleFormat::I24, cpal::SampleFormat::I32]
         );
         assert_eq!(
@@ -1275,12 +1547,14 @@ mod tests {
                 name: "hw:CARD=1,DEV=0".to_string(),
                 is_default: false,
                 is_current: false,
+                hw_volume: false,
             },
             OutputDeviceInfo {
                 id: "plughw:CARD=1,DEV=0".to_string(),
                 name: "plughw:CARD=1,DEV=0".to_string(),
                 is_default: false,
                 is_current: false,
+                hw_volume: false,
             },
         ];
 
@@ -1302,18 +1576,21 @@ mod tests {
                 name: "USB DAC".to_string(),
                 is_default: false,
                 is_current: false,
+                hw_volume: false,
             },
             OutputDeviceInfo {
                 id: "hw:CARD=1,DEV=0".to_string(),
                 name: "USB DAC".to_string(),
                 is_default: false,
                 is_current: true,
+                hw_volume: false,
             },
             OutputDeviceInfo {
                 id: "default".to_string(),
                 name: "System Default".to_string(),
                 is_default: true,
                 is_current: false,
+                hw_volume: false,
             },
         ];
 
@@ -1323,4 +1600,63 @@ mod tests {
         assert_eq!(devices[1].name, "USB DAC [hw:CARD=1,DEV=0]");
         assert_eq!(devices[2].name, "System Default");
     }
+
+    #[test]
+    fn output_stops_at_range_end_unless_a_next_range_is_queued() {
+        let state = create_state(48_000);
+        let (mut producer, mut consumer) = HeapRb::<i16>::new(64).split();
+        for sample in 0..32 {
+            producer.try_push(sample).unwrap();
+        }
+        state.current_frame.store(100, Ordering::SeqCst);
+        state.set_range(90, Some(104));
+        state.queue_next_range(Some(Some(108)));
+
+        let mut data = [0i16; 20];
+        let (samples_read, range_ended) =
+            pop_within_range(&mut consumer, &mut data, &state, 2, |s| s);
+
+        assert_eq!(samples_read, 16);
+        assert!(range_ended);
+        assert_eq!(&data[..16], &(0..16).collect::<Vec<i16>>()[..]);
+        assert_eq!(&data[16..], &[0; 4]);
+        assert_eq!(state.range_start_frame.load(Ordering::SeqCst), 104);
+        assert_eq!(state.range_transitions.load(Ordering::SeqCst), 1);
+        assert_eq!(consumer.occupied_len(), 16);
+    }
+
+    #[test]
+    fn skip_silence_shortens_gaps_and_keeps_the_source_timeline() {
+        let state = create_state(1000);
+        state.silence.configure(
+            SilenceOptions {
+                skip_gaps: true,
+                ..SilenceOptions::default()
+            },
+            1000,
+        );
+        let (mut producer, mut consumer) = HeapRb::<f32>::new(4096).split();
+        for frame in 0..2200 {
+            let loud = !(100..2100).contains(&frame);
+            producer.try_push(if loud { 0.5 } else { 0.0 }).unwrap();
+        }
+        let fanout = OutputFanout::new();
+        let mut data = [0f32; 100];
+
+        render_output(&mut consumer, &mut data, &state, 1, &fanout, Duration::ZERO);
+        assert_eq!(state.current_frame.load(Ordering::SeqCst), 100);
+
+        // 2000 帧静音只播 500 帧，其余直接跳过，进度照常推进。
+        let mut played_silence = 0;
+        loop {
+            render_output(&mut consumer, &mut data, &state, 1, &fanout, Duration::ZERO);
+            if data[0] != 0.0 {
+                break;
+            }
+            played_silence += data.len();
+        }
+        assert_eq!(played_silence, 500);
+        assert_eq!(state.current_frame.load(Ordering::SeqCst), 2200);
+        assert_eq!(consumer.occupied_len(), 0);
+    }
 }
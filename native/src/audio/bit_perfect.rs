@@ -0,0 +1,691 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ringbuf::traits::{Consumer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+
+use symphonia::core::sample::SampleFormat as SymphoniaSampleFormat;
+
+use crate::audio::alsa_hw::AlsaHwConfig;
+use crate::audio::{backend, jack_output, virtual_output};
+
+/// 每隔多少个样本比对一次哈希。
+const CHECKPOINT_SAMPLES: u64 = 1 << 16;
+/// 一侧领先太多时只保留最近的校验点，避免另一侧停滞时无限增长。
+const MAX_PENDING_CHECKPOINTS: usize = 64;
+/// 输出回调交给校验线程的样本缓冲，够 384 kHz 立体声约 0.7 秒。
+const OUTPUT_TAP_SAMPLES: usize = 1 << 19;
+/// 校验线程取样本的间隔。
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 送进管线的音源格式（DSD 走 DoP 时为封装后的 PCM 参数）。
+#[derive(Clone, Debug)]
+pub(crate) struct SourceFormat {
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    pub(crate) bits_per_sample: Option<u32>,
+    pub(crate) sample_format: Option<SymphoniaSampleFormat>,
+    pub(crate) dsd_rate: Option<u32>,
+}
+
+/// 与设备协商出的 cpal 输出配置。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OutputFormat {
+    pub(crate) device_id: String,
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    pub(crate) sample_format: cpal::SampleFormat,
+    pub(crate) buffer_frames: Option<u32>,
+    pub(crate) dop: bool,
+    /// 绕过 cpal 直连 `hw:` 时实际生效的 hw_params。
+    pub(crate) alsa_hw: Option<AlsaHwConfig>,
+}
+
+/// `/proc/asound/cardX/pcmYp/sub0/hw_params` 的内容；流未打开时只有 `closed`。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct HwParamsSnapshot {
+    pub(crate) path: String,
+    pub(crate) raw: String,
+    pub(crate) closed: bool,
+    pub(crate) access: Option<String>,
+    pub(crate) format: Option<String>,
+    pub(crate) channels: Option<u32>,
+    pub(crate) rate: Option<u32>,
+    pub(crate) period_frames: Option<u64>,
+    pub(crate) buffer_frames: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ReservationStatus {
+    pub(crate) name: String,
+    /// pw-reserve 子进程仍在运行。
+    pub(crate) held: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct VerificationReport {
+    pub(crate) enabled: bool,
+    /// 已通过校验点比对的样本数。
+    pub(crate) verified_samples: u64,
+    pub(crate) matched_checkpoints: u64,
+    pub(crate) mismatched_checkpoints: u64,
+    /// 第一次不一致的校验点位置（自上次 seek 起的样本数）。
+    pub(crate) first_mismatch_sample: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct BitPerfectReport {
+    pub(crate) strict: bool,
+    pub(crate) source: Option<SourceFormat>,
+    pub(crate) output: Option<OutputFormat>,
+    pub(crate) hw_params: Option<HwParamsSnapshot>,
+    /// 音源到声卡之间会改变样本的环节，空表示没有。
+    pub(crate) conversions: Vec<String>,
+    pub(crate) volume_active: bool,
+    pub(crate) dsp_active: bool,
+    pub(crate) reservation: Option<ReservationStatus>,
+    pub(crate) verification: VerificationReport,
+    pub(crate) bit_perfect: bool,
+}
+
+impl BitPerfectReport {
+    pub(crate) fn new(
+        strict: bool,
+        source: Option<SourceFormat>,
+        output: Option<OutputFormat>,
+        reservation: Option<ReservationStatus>,
+        verification: VerificationReport,
+        dsp: Vec<String>,
+    ) -> Self {
+        let hw_params = output
+            .as_ref()
+            .and_then(|output| read_hw_params(&output.device_id));
+        let mut conversions = match (&source, &output) {
+            (Some(source), Some(output)) => conversion_stages(source, output, hw_params.as_ref()),
+            _ => Vec::new(),
+        };
+        // 管线内没有软件音量：直连声卡时音量交给硬件混音器，经过系统混音时由混音器缩放样本。
+        // DSP 只有耳机上的 HRTF 双耳渲染。
+        let volume_active = output
+            .as_ref()
+            .is_some_and(|output| applies_system_volume(&output.device_id));
+        let dsp_active = !dsp.is_empty();
+        conversions.extend(dsp);
+        let bit_perfect = source.is_some()
+            && output.is_some()
+            && conversions.is_empty()
+            && !volume_active
+            && !dsp_active
+            && verification.mismatched_checkpoints == 0;
+
+        Self {
+            strict,
+            source,
+            output,
+            hw_params,
+            conversions,
+            volume_active,
+            dsp_active,
+            reservation,
+            verification,
+            bit_perfect,
+        }
+    }
+}
+
+/// 输出是否经过系统混音器（PipeWire/PulseAudio 等），音量会在那里缩放样本。
+/// 直连声卡的音量由硬件混音器调节；JACK 和虚拟输出不带音量。
+fn applies_system_volume(device_id: &str) -> bool {
+    !backend::is_linux_real_hardware_output_id(device_id)
+        && !jack_output::is_jack_device(device_id)
+        && !virtual_output::is_virtual_device(device_id)
+}
+
+/// 列出音源到声卡之间会改变样本的环节。
+pub(crate) fn conversion_stages(
+    source: &SourceFormat,
+    output: &OutputFormat,
+    hw_params: Option<&HwParamsSnapshot>,
+) -> Vec<String> {
+    let mut stages = Vec::new();
+    if source.dsd_rate.is_some() && !output.dop {
+        stages.push("DSD 解码为 PCM".to_string());
+    }
+    if source.sample_rate != output.sample_rate {
+        stages.push(format!(
+            "采样率转换：{} Hz → {} Hz",
+            source.sample_rate, output.sample_rate
+        ));
+    }
+    if source.channels != output.channels {
+        stages.push(format!(
+            "声道转换：{} → {}",
+            source.channels, output.channels
+        ));
+    }
+    if !backend::bit_perfect_output_formats(
+        source.bits_per_sample,
+        source.sample_format,
+        output.dop,
+    )
+    .contains(&output.sample_format)
+    {
+        let source_format = source
+            .sample_format
+            .map(|format| format!("{:?}", format))
+            .or_else(|| source.bits_per_sample.map(|bits| format!("{} bit", bits)))
+            .unwrap_or_else(|| "未知".to_string());
+        stages.push(format!(
+            "样本格式转换：{} → {}",
+            source_format, output.sample_format
+        ));
+    }
+
+    let device_id = output.device_id.trim().to_ascii_lowercase();
+    if !backend::is_linux_real_hardware_output_id(&device_id) {
+        stages.push(format!(
+            "经过系统混音或 ALSA 插件输出：{}",
+            output.device_id
+        ));
+        return stages;
+    }
+
+    match hw_params.filter(|hw| !hw.closed) {
+        Some(hw) => {
+            if hw.rate.is_some_and(|rate| rate != output.sample_rate) {
+                stages.push(format!(
+                    "驱动采样率与输出不一致：{} Hz",
+                    hw.rate.unwrap_or_default()
+                ));
+            }
+            if hw
+                .channels
+                .is_some_and(|channels| channels != u32::from(output.channels))
+            {
+                stages.push(format!(
+                    "驱动声道数与输出不一致：{}",
+                    hw.channels.unwrap_or_default()
+                ));
+            }
+            if let Some(format) = &hw.format
+                && !alsa_format_names(output.sample_format).contains(&format.as_str())
+            {
+                stages.push(format!("驱动样本格式与输出不一致：{}", format));
+            }
+        }
+        None if device_id.starts_with("plughw:") => {
+            stages.push("plughw 可能在驱动前转换格式（未读到 hw_params）".to_string());
+        }
+        None => {}
+    }
+    stages
+}
+
+/// cpal 样本格式在 hw_params 中对应的 ALSA 格式名。
+fn alsa_format_names(format: cpal::SampleFormat) -> &'static [&'static str] {
+    match format {
+        cpal::SampleFormat::I8 => &["S8"],
+        cpal::SampleFormat::U8 => &["U8"],
+        cpal::SampleFormat::I16 => &["S16_LE"],
+        cpal::SampleFormat::U16 => &["U16_LE"],
+        cpal::SampleFormat::I24 => &["S24_LE", "S24_3LE"],
+        cpal::SampleFormat::U24 => &["U24_LE", "U24_3LE"],
+        cpal::SampleFormat::I32 => &["S32_LE"],
+        cpal::SampleFormat::U32 => &["U32_LE"],
+        cpal::SampleFormat::F32 => &["FLOAT_LE"],
+        cpal::SampleFormat::F64 => &["FLOAT64_LE"],
+        _ => &[],
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_hw_params(device_id: &str) -> Option<HwParamsSnapshot> {
+    let path = backend::alsa_hw_params_path(device_id)?;
+    let raw = std::fs::read_to_string(&path).ok()?;
+    Some(HwParamsSnapshot {
+        path: path.to_string_lossy().into_owned(),
+        ..parse_hw_params(&raw)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_hw_params(_device_id: &str) -> Option<HwParamsSnapshot> {
+    None
+}
+
+fn parse_hw_params(raw: &str) -> HwParamsSnapshot {
+    let mut snapshot = HwParamsSnapshot {
+        raw: raw.to_string(),
+        closed: raw.trim() == "closed",
+        ..HwParamsSnapshot::default()
+    };
+    for line in raw.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        // rate 行形如 `44100 (44100/1)`，只取第一个数。
+        let value = value.split_whitespace().next().unwrap_or_default();
+        match key.trim() {
+            "access" => snapshot.access = Some(value.to_string()),
+            "format" => snapshot.format = Some(value.to_string()),
+            "channels" => snapshot.channels = value.parse().ok(),
+            "rate" => snapshot.rate = value.parse().ok(),
+            "period_size" => snapshot.period_frames = value.parse().ok(),
+            "buffer_size" => snapshot.buffer_frames = value.parse().ok(),
+            _ => {}
+        }
+    }
+    snapshot
+}
+
+/// 逐样本累积的 FNV-1a 哈希，样本统一换成 f64 的位模式，
+/// 解码端和输出回调两侧的整数/浮点表示因此可以直接比较。
+struct RunningHash {
+    samples: u64,
+    hash: u64,
+}
+
+impl RunningHash {
+    fn new() -> Self {
+        Self {
+            samples: 0,
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    /// 累积一段样本，返回途中经过的校验点。
+    fn update(&mut self, samples: impl Iterator<Item = f64>) -> Vec<(u64, u64)> {
+        let mut checkpoints = Vec::new();
+        for sample in samples {
+            for byte in sample.to_bits().to_le_bytes() {
+                self.hash ^= u64::from(byte);
+                self.hash = self.hash.wrapping_mul(FNV_PRIME);
+            }
+            self.samples += 1;
+            if self.samples.is_multiple_of(CHECKPOINT_SAMPLES) {
+                checkpoints.push((self.samples, self.hash));
+            }
+        }
+        checkpoints
+    }
+}
+
+#[derive(Default)]
+struct Checkpoints {
+    source: VecDeque<(u64, u64)>,
+    output: VecDeque<(u64, u64)>,
+    report: VerificationReport,
+}
+
+impl Checkpoints {
+    /// 两侧都到达的校验点逐个比对；落后一侧还没到的留待下次。
+    fn settle(&mut self) {
+        while let (Some(&(source_at, source_hash)), Some(&(output_at, output_hash))) =
+            (self.source.front(), self.output.front())
+        {
+            if source_at < output_at {
+                self.source.pop_front();
+                continue;
+            }
+            if output_at < source_at {
+                self.output.pop_front();
+                continue;
+            }
+            self.source.pop_front();
+            self.output.pop_front();
+            if source_hash == output_hash {
+                self.report.matched_checkpoints += 1;
+                self.report.verified_samples += CHECKPOINT_SAMPLES;
+            } else {
+                self.report.mismatched_checkpoints += 1;
+                self.report.first_mismatch_sample.get_or_insert(source_at);
+            }
+        }
+    }
+}
+
+fn push_bounded(queue: &mut VecDeque<(u64, u64)>, checkpoints: Vec<(u64, u64)>) {
+    queue.extend(checkpoints);
+    while queue.len() > MAX_PENDING_CHECKPOINTS {
+        queue.pop_front();
+    }
+}
+
+/// 输出侧哈希状态，只在校验线程、seek 和取报告时加锁，实时回调从不碰它。
+struct OutputSide {
+    hash: RunningHash,
+    tap: Option<HeapCons<f64>>,
+}
+
+/// 校验线程与 `SampleVerifier` 共用的部分。
+struct VerifierCore {
+    source: Mutex<RunningHash>,
+    output: Mutex<OutputSide>,
+    checkpoints: Mutex<Checkpoints>,
+    /// 输出回调没能把样本完整交给校验线程；此后输出侧哈希不可信，直到下次 reset。
+    output_lost: AtomicBool,
+}
+
+impl VerifierCore {
+    /// 取走输出回调送来的样本并累积哈希，到达校验点时与音源侧比对。
+    fn drain_output(&self) {
+        let mut output = self.output.lock().unwrap();
+        let OutputSide { hash, tap } = &mut *output;
+        let Some(tap) = tap.as_mut() else {
+            return;
+        };
+        if self.output_lost.load(Ordering::Acquire) {
+            tap.clear();
+            return;
+        }
+        let checkpoints = hash.update(tap.pop_iter());
+        if !checkpoints.is_empty() {
+            let mut pending = self.checkpoints.lock().unwrap();
+            push_bounded(&mut pending.output, checkpoints);
+            pending.settle();
+        }
+    }
+}
+
+struct VerifierWorker {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// 校验模式：对解码出的音源样本和交给输出回调的样本分别做哈希，按样本序号对齐比较。
+/// 音源侧在解码线程里直接计算；输出回调只把样本拷进无锁 SPSC ring，
+/// 哈希和比对由单独的校验线程完成，实时线程上不做逐字节计算，也不等锁。
+pub(crate) struct SampleVerifier {
+    enabled: AtomicBool,
+    core: Arc<VerifierCore>,
+    /// 输出回调写入的一端。回调里只 `try_lock`，只有开关校验时才会有人竞争。
+    output_tap: Mutex<Option<HeapProd<f64>>>,
+    worker: Mutex<Option<VerifierWorker>>,
+}
+
+impl SampleVerifier {
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            core: Arc::new(VerifierCore {
+                source: Mutex::new(RunningHash::new()),
+                output: Mutex::new(OutputSide {
+                    hash: RunningHash::new(),
+                    tap: None,
+                }),
+                checkpoints: Mutex::new(Checkpoints::default()),
+                output_lost: AtomicBool::new(false),
+            }),
+            output_tap: Mutex::new(None),
+            worker: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        let mut worker = self.worker.lock().unwrap();
+        if enabled && worker.is_none() {
+            let (producer, consumer) = HeapRb::<f64>::new(OUTPUT_TAP_SAMPLES).split();
+            *self.output_tap.lock().unwrap() = Some(producer);
+            self.core.output.lock().unwrap().tap = Some(consumer);
+            let stop = Arc::new(AtomicBool::new(false));
+            let core = Arc::clone(&self.core);
+            let stop_for_thread = Arc::clone(&stop);
+            let handle = thread::spawn(move || {
+                while !stop_for_thread.load(Ordering::Acquire) {
+                    core.drain_output();
+                    thread::park_timeout(DRAIN_INTERVAL);
+                }
+            });
+            *worker = Some(VerifierWorker { stop, handle });
+        }
+        self.enabled.store(enabled, Ordering::SeqCst);
+        if !enabled && let Some(worker) = worker.take() {
+            stop_worker(worker);
+            *self.output_tap.lock().unwrap() = None;
+            self.core.output.lock().unwrap().tap = None;
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 解码线程调用，只传实际写进 ring buffer 的样本。
+    pub(crate) fn record_source(&self, samples: &[f64]) {
+        if !self.is_enabled() {
+            return;
+        }
+        let checkpoints = self
+            .core
+            .source
+            .lock()
+            .unwrap()
+            .update(samples.iter().copied());
+        if !checkpoints.is_empty() {
+            let mut pending = self.core.checkpoints.lock().unwrap();
+            push_bounded(&mut pending.source, checkpoints);
+            pending.settle();
+        }
+    }
+
+    /// 输出回调调用，传本次从 ring buffer 取出并写给设备的样本。只做拷贝；
+    /// 拿不到 ring 或 ring 已满时记为丢失，不阻塞实时线程。
+    pub(crate) fn record_output<S>(&self, samples: &[S])
+    where
+        S: Copy,
+        f64: cpal::FromSample<S>,
+    {
+        if !self.is_enabled() {
+            return;
+        }
+        let Ok(mut tap) = self.output_tap.try_lock() else {
+            self.core.output_lost.store(true, Ordering::Release);
+            return;
+        };
+        let Some(producer) = tap.as_mut() else {
+            return;
+        };
+        let pushed = producer.push_iter(
+            samples
+                .iter()
+                .map(|&sample| cpal::FromSample::from_sample_(sample)),
+        );
+        if pushed < samples.len() {
+            self.core.output_lost.store(true, Ordering::Release);
+        }
+    }
+
+    /// seek 后 ring buffer 已清空，两侧从新位置重新对齐；统计结果保留。
+    /// 调用时输出回调已经取空了 seek 前的样本，ring 里剩下的都属于旧位置。
+    pub(crate) fn reset(&self) {
+        *self.core.source.lock().unwrap() = RunningHash::new();
+        {
+            let mut output = self.core.output.lock().unwrap();
+            output.hash = RunningHash::new();
+            if let Some(tap) = output.tap.as_mut() {
+                tap.clear();
+            }
+            self.core.output_lost.store(false, Ordering::Release);
+        }
+        let mut pending = self.core.checkpoints.lock().unwrap();
+        pending.source.clear();
+        pending.output.clear();
+    }
+
+    pub(crate) fn report(&self) -> VerificationReport {
+        // 先把校验线程还没处理的样本算完，报告才反映到当前为止的输出。
+        self.core.drain_output();
+        VerificationReport {
+            enabled: self.is_enabled(),
+            ..self.core.checkpoints.lock().unwrap().report
+        }
+    }
+}
+
+impl Drop for SampleVerifier {
+    fn drop(&mut self) {
+        if let Ok(worker) = self.worker.get_mut()
+            && let Some(worker) = worker.take()
+        {
+            stop_worker(worker);
+        }
+    }
+}
+
+fn stop_worker(worker: VerifierWorker) {
+    worker.stop.store(true, Ordering::Release);
+    worker.handle.thread().unpark();
+    let _ = worker.handle.join();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(len: u64) -> Vec<i16> {
+        (0..len).map(|index| (index % 4096) as i16 - 2048).collect()
+    }
+
+    fn as_f64(samples: &[i16]) -> Vec<f64> {
+        samples
+            .iter()
+            .map(|&sample| f64::from(sample) / 32768.0)
+            .collect()
+    }
+
+    #[test]
+    fn verifier_matches_identical_streams_and_flags_altered_samples() {
+        let verifier = SampleVerifier::new();
+        verifier.set_enabled(true);
+        let samples = ramp(CHECKPOINT_SAMPLES * 2);
+
+        // 输出回调先于解码端记账也能对上。
+        verifier.record_output(&samples[..CHECKPOINT_SAMPLES as usize]);
+        verifier.record_source(&as_f64(&samples));
+        let mut altered = samples[CHECKPOINT_SAMPLES as usize..].to_vec();
+        altered[7] ^= 1;
+        verifier.record_output(&altered);
+
+        let report = verifier.report();
+        assert!(report.enabled);
+        assert_eq!(report.matched_checkpoints, 1);
+        assert_eq!(report.verified_samples, CHECKPOINT_SAMPLES);
+        assert_eq!(report.mismatched_checkpoints, 1);
+        assert_eq!(report.first_mismatch_sample, Some(CHECKPOINT_SAMPLES * 2));
+    }
+
+    #[test]
+    fn verifier_realigns_after_reset_and_ignores_samples_while_disabled() {
+        let verifier = SampleVerifier::new();
+        let samples = ramp(CHECKPOINT_SAMPLES);
+        verifier.record_output(&samples);
+        assert_eq!(verifier.report(), VerificationReport::default());
+
+        verifier.set_enabled(true);
+        verifier.record_source(&as_f64(&samples[..100]));
+        verifier.reset();
+        verifier.record_source(&as_f64(&samples));
+        verifier.record_output(&samples);
+
+        let report = verifier.report();
+        assert_eq!(report.matched_checkpoints, 1);
+        assert_eq!(report.mismatched_checkpoints, 0);
+    }
+
+    #[test]
+    fn lost_output_samples_pause_comparison_until_reset() {
+        let verifier = SampleVerifier::new();
+        verifier.set_enabled(true);
+        let samples = ramp(CHECKPOINT_SAMPLES);
+
+        // 回调拿不到 ring 时这段输出丢了，之后的输出侧哈希不再参与比对。
+        {
+            let _busy = verifier.output_tap.lock().unwrap();
+            verifier.record_output(&samples[..10]);
+        }
+        verifier.record_output(&samples[10..]);
+        verifier.record_source(&as_f64(&samples));
+        assert_eq!(verifier.report().mismatched_checkpoints, 0);
+        assert_eq!(verifier.report().matched_checkpoints, 0);
+
+        verifier.reset();
+        verifier.record_output(&samples);
+        verifier.record_source(&as_f64(&samples));
+        let report = verifier.report();
+        assert_eq!(report.matched_checkpoints, 1);
+        assert_eq!(report.mismatched_checkpoints, 0);
+    }
+
+    #[test]
+    fn volume_counts_as_active_only_behind_a_system_mixer() {
+        assert!(applies_system_volume("pipewire"));
+        assert!(applies_system_volume("default"));
+        assert!(!applies_system_volume("hw:1,0"));
+        assert!(!applies_system_volume("plughw:CARD=DAC,DEV=0"));
+        assert!(!applies_system_volume("jack:system"));
+        assert!(!applies_system_volume("null:"));
+    }
+
+    #[test]
+    fn parses_open_and_closed_hw_params() {
+        let open = parse_hw_params(
+            "access: MMAP_INTERLEAVED\nformat: S32_LE\nsubformat: STD\nchannels: 2\n\
+             rate: 44100 (44100/1)\nperiod_size: 4410\nbuffer_size: 22050\n",
+        );
+        assert!(!open.closed);
+        assert_eq!(open.access.as_deref(), Some("MMAP_INTERLEAVED"));
+        assert_eq!(open.format.as_deref(), Some("S32_LE"));
+        assert_eq!(open.channels, Some(2));
+        assert_eq!(open.rate, Some(44_100));
+        assert_eq!(open.period_frames, Some(4_410));
+        assert_eq!(open.buffer_frames, Some(22_050));
+
+        let closed = parse_hw_params("closed\n");
+        assert!(closed.closed);
+        assert_eq!(closed.rate, None);
+    }
+
+    #[test]
+    fn conversion_stages_cover_format_plugin_and_driver_mismatches() {
+        let source = SourceFormat {
+            sample_rate: 44_100,
+            channels: 2,
+            bits_per_sample: Some(24),
+            sample_format: Some(SymphoniaSampleFormat::S24),
+            dsd_rate: None,
+        };
+        let output = OutputFormat {
+            device_id: "hw:1,0".to_string(),
+            sample_rate: 44_100,
+            channels: 2,
+            sample_format: cpal::SampleFormat::I32,
+            buffer_frames: None,
+            dop: false,
+            alsa_hw: None,
+        };
+        let hw = parse_hw_params("format: S32_LE\nchannels: 2\nrate: 44100 (44100/1)\n");
+        assert!(conversion_stages(&source, &output, Some(&hw)).is_empty());
+
+        let resampled = parse_hw_params("format: S32_LE\nchannels: 2\nrate: 48000 (48000/1)\n");
+        assert_eq!(
+            conversion_stages(&source, &output, Some(&resampled)),
+            vec!["驱动采样率与输出不一致：48000 Hz".to_string()]
+        );
+
+        let shared = OutputFormat {
+            device_id: "pipewire".to_string(),
+            sample_format: cpal::SampleFormat::F32,
+            ..output
+        };
+        let stages = conversion_stages(&source, &shared, None);
+        assert_eq!(stages.len(), 2);
+        assert!(stages[0].starts_with("样本格式转换"));
+        assert!(stages[1].contains("pipewire"));
+    }
+}
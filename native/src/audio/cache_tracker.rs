@@ -1,8 +1,10 @@
+use crate::audio::seek_index::{self, FrameSeekIndex, SharedSeekIndex};
 use crate::cache::song::SongStreamCacheMeta;
 use std::fs;
-use std::io;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use stream_download::{StreamPhase, StreamState};
 
@@ -10,15 +12,28 @@ use stream_download::{StreamPhase, StreamState};
 pub struct SongCacheTracker {
     metadata_path: Arc<PathBuf>,
     inner: Arc<Mutex<SongCacheTrackerState>>,
+    seek_index: SharedSeekIndex,
+    /// 是否下载完整发生变化时加一，读端据此判断要不要重新查询 `is_fully_downloaded`。
+    completion_generation: Arc<AtomicU64>,
 }
 
 struct SongCacheTrackerState {
     meta: SongStreamCacheMeta,
     last_persisted_bytes: u64,
+    /// 缓存数据文件；设置后随下载扫描帧头，维护 `seek_index`。
+    cache_path: Option<PathBuf>,
+}
+
+/// 在状态锁内取出的待扫描范围，释放锁后再读文件。
+struct PendingScan {
+    cache_path: PathBuf,
+    contiguous_end: u64,
 }
 
 impl SongCacheTracker {
     const PERSIST_STEP_BYTES: u64 = 128 * 1024;
+    const SEEK_INDEX_STEP_BYTES: u64 = 16 * 1024;
+    const SEEK_INDEX_READ_BYTES: u64 = 1024 * 1024;
 
     pub fn new(metadata_path: impl Into<PathBuf>) -> io::Result<Self> {
         let metadata_path = metadata_path.into();
@@ -26,24 +41,61 @@ impl SongCacheTracker {
         let meta = serde_json::from_str::<SongStreamCacheMeta>(&raw)
             .map_err(|err| io::Error::other(err.to_string()))?;
         let last_persisted_bytes = meta.downloaded_bytes();
+        let seek_index = FrameSeekIndex::load(
+            &seek_index::index_path_for_metadata(&metadata_path),
+            meta.content_length,
+        );
 
         Ok(Self {
             metadata_path: Arc::new(metadata_path),
             inner: Arc::new(Mutex::new(SongCacheTrackerState {
                 meta,
                 last_persisted_bytes,
+                cache_path: None,
             })),
+            seek_index: Arc::new(Mutex::new(seek_index)),
+            completion_generation: Arc::new(AtomicU64::new(0)),
         })
     }
 
-    pub fn set_content_length(&self, content_length: Option<u64>) -> io::Result<()> {
-        let mut state = self.inner.lock().unwrap();
-        if state.meta.content_length == content_length {
-            return Ok(());
+    /// 随下载为缓存文件建立帧偏移索引，已缓存的开头部分立即补扫。
+    pub fn with_seek_index(self, cache_path: impl Into<PathBuf>) -> Self {
+        let scan = {
+            let mut state = self.inner.lock().unwrap();
+            state.cache_path = Some(cache_path.into());
+            self.pending_scan_locked(&state)
+        };
+        if let Some(scan) = scan
+            && let Err(err) = self.scan_seek_index(&scan, true)
+        {
+            eprintln!("[cache] failed to scan song cache for seek index: {err}");
         }
+        self
+    }
+
+    pub(crate) fn seek_index(&self) -> SharedSeekIndex {
+        Arc::clone(&self.seek_index)
+    }
 
-        state.meta.set_content_length(content_length);
-        self.persist_locked(&mut state)
+    pub(crate) fn completion_generation(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.completion_generation)
+    }
+
+    pub fn set_content_length(&self, content_length: Option<u64>) -> io::Result<()> {
+        let scan = {
+            let mut state = self.inner.lock().unwrap();
+            if state.meta.content_length == content_length {
+                return Ok(());
+            }
+
+            let was_complete = state.meta.is_fully_downloaded();
+            state.meta.set_content_length(content_length);
+            self.note_completion_locked(&state, was_complete);
+            self.reset_seek_index_if_stale(content_length);
+            self.persist_locked(&mut state)?;
+            self.pending_scan_locked(&state)
+        };
+        self.update_seek_index(scan, true)
     }
 
     pub fn record_progress(&self, progress: StreamState, content_length: Option<u64>) {
@@ -53,8 +105,16 @@ impl SongCacheTracker {
     }
 
     pub fn persist(&self) -> io::Result<()> {
-        let mut state = self.inner.lock().unwrap();
-        self.persist_locked(&mut state)
+        let scan = {
+            let mut state = self.inner.lock().unwrap();
+            self.persist_locked(&mut state)?;
+            self.pending_scan_locked(&state)
+        };
+        self.update_seek_index(scan, true)
+    }
+
+    pub fn is_fully_downloaded(&self) -> bool {
+        self.inner.lock().unwrap().meta.is_fully_downloaded()
     }
 
     pub fn record_range(&self, range: Range<u64>) {
@@ -64,13 +124,20 @@ impl SongCacheTracker {
     }
 
     fn try_record_range(&self, range: Range<u64>) -> io::Result<()> {
-        let mut state = self.inner.lock().unwrap();
-        state.meta.add_range(range);
-        let downloaded_bytes = state.meta.downloaded_bytes();
-        if downloaded_bytes.saturating_sub(state.last_persisted_bytes) >= Self::PERSIST_STEP_BYTES {
-            self.persist_locked(&mut state)?;
-        }
-        Ok(())
+        let (scan, persisted) = {
+            let mut state = self.inner.lock().unwrap();
+            let was_complete = state.meta.is_fully_downloaded();
+            state.meta.add_range(range);
+            self.note_completion_locked(&state, was_complete);
+            let downloaded_bytes = state.meta.downloaded_bytes();
+            let persisted = downloaded_bytes.saturating_sub(state.last_persisted_bytes)
+                >= Self::PERSIST_STEP_BYTES;
+            if persisted {
+                self.persist_locked(&mut state)?;
+            }
+            (self.pending_scan_locked(&state), persisted)
+        };
+        self.update_seek_index(scan, persisted)
     }
 
     fn try_record_progress(
@@ -80,15 +147,18 @@ impl SongCacheTracker {
     ) -> io::Result<()> {
         let mut state = self.inner.lock().unwrap();
         let previous_bytes = state.meta.downloaded_bytes();
+        let was_complete = state.meta.is_fully_downloaded();
 
         if state.meta.content_length != content_length {
             state.meta.set_content_length(content_length);
+            self.reset_seek_index_if_stale(content_length);
         }
 
         state.meta.add_range(progress.current_chunk.clone());
         if matches!(progress.phase, StreamPhase::Complete) {
             state.meta.mark_complete();
         }
+        self.note_completion_locked(&state, was_complete);
 
         let downloaded_bytes = state.meta.downloaded_bytes();
         if matches!(progress.phase, StreamPhase::Complete)
@@ -97,6 +167,9 @@ impl SongCacheTracker {
             || downloaded_bytes < previous_bytes
         {
             self.persist_locked(&mut state)?;
+            let scan = self.pending_scan_locked(&state);
+            drop(state);
+            self.update_seek_index(scan, true)?;
         }
 
         Ok(())
@@ -111,4 +184,92 @@ impl SongCacheTracker {
         state.last_persisted_bytes = state.meta.downloaded_bytes();
         Ok(())
     }
+
+    fn note_completion_locked(&self, state: &SongCacheTrackerState, was_complete: bool) {
+        if state.meta.is_fully_downloaded() != was_complete {
+            self.completion_generation.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    /// 内容长度变化说明远端文件变了，旧索引作废。
+    fn reset_seek_index_if_stale(&self, content_length: Option<u64>) {
+        let mut index = self.seek_index.lock().unwrap();
+        if index.content_length != content_length {
+            *index = FrameSeekIndex::new(content_length);
+        }
+    }
+
+    /// 取出从文件开头起连续已下载的范围；索引比它还长（元数据回退或被清理）时重建索引。
+    fn pending_scan_locked(&self, state: &SongCacheTrackerState) -> Option<PendingScan> {
+        let cache_path = state.cache_path.clone()?;
+        let contiguous_end = state
+            .meta
+            .downloaded_ranges
+            .first()
+            .filter(|range| range.start == 0)
+            .map_or(0, |range| range.end);
+
+        let mut index = self.seek_index.lock().unwrap();
+        if index.scanned_bytes > contiguous_end {
+            *index = FrameSeekIndex::new(state.meta.content_length);
+        }
+        Some(PendingScan {
+            cache_path,
+            contiguous_end,
+        })
+    }
+
+    /// 在状态锁外补扫索引；`persisted` 时同时把索引写回磁盘。
+    fn update_seek_index(&self, scan: Option<PendingScan>, persisted: bool) -> io::Result<()> {
+        let Some(scan) = scan else {
+            return Ok(());
+        };
+        self.scan_seek_index(&scan, persisted)?;
+        if persisted {
+            self.seek_index
+                .lock()
+                .unwrap()
+                .save(&seek_index::index_path_for_metadata(&self.metadata_path))?;
+        }
+        Ok(())
+    }
+
+    /// 扫描已下载、但还没建索引的部分，读文件时不持有任何锁；
+    /// 非 `force` 时攒够 `SEEK_INDEX_STEP_BYTES` 再读，减少写回调里的小块读取。
+    fn scan_seek_index(&self, scan: &PendingScan, force: bool) -> io::Result<()> {
+        let mut file: Option<fs::File> = None;
+        loop {
+            let start = {
+                let index = self.seek_index.lock().unwrap();
+                let pending = scan.contiguous_end.saturating_sub(index.scanned_bytes);
+                if index.unsupported
+                    || pending == 0
+                    || (file.is_none() && !force && pending < Self::SEEK_INDEX_STEP_BYTES)
+                {
+                    return Ok(());
+                }
+                index.scanned_bytes
+            };
+
+            let file = match &mut file {
+                Some(file) => file,
+                None => file.insert(fs::File::open(&scan.cache_path)?),
+            };
+            let len = (scan.contiguous_end - start).min(Self::SEEK_INDEX_READ_BYTES);
+            let mut data = Vec::with_capacity(len as usize);
+            file.seek(SeekFrom::Start(start))?;
+            file.take(len).read_to_end(&mut data)?;
+
+            let mut index = self.seek_index.lock().unwrap();
+            if index.scanned_bytes != start {
+                // 其他写回调已经扫过这一段。
+                continue;
+            }
+            index.scan(&data, start);
+            if index.scanned_bytes == start {
+                // 剩余数据不足一帧，等下次写入。
+                return Ok(());
+            }
+        }
+    }
 }
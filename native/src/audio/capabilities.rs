@@ -0,0 +1,190 @@
+use cpal::traits::DeviceTrait;
+
+use crate::audio::backend;
+
+/// 常见的音乐采样率，用于把 cpal/ALSA 给出的范围换成界面可直接展示的列表。
+const STANDARD_SAMPLE_RATES: [u32; 10] = [
+    44_100, 48_000, 88_200, 96_000, 176_400, 192_000, 352_800, 384_000, 705_600, 768_000,
+];
+
+/// `supported_output_configs` 返回的一项配置范围。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OutputConfigRange {
+    pub(crate) channels: u16,
+    pub(crate) min_sample_rate: u32,
+    pub(crate) max_sample_rate: u32,
+    pub(crate) sample_format: String,
+    /// 缓冲区帧数范围，后端未给出时为空。
+    pub(crate) buffer_frames: Option<(u32, u32)>,
+}
+
+/// 直接打开 `hw:` 设备读到的 hw_params 范围，不经过 ALSA 插件转换。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AlsaHwParamsRange {
+    pub(crate) sample_rates: Vec<u32>,
+    pub(crate) min_sample_rate: u32,
+    pub(crate) max_sample_rate: u32,
+    pub(crate) min_channels: u32,
+    pub(crate) max_channels: u32,
+    pub(crate) formats: Vec<String>,
+    pub(crate) period_frames: (u64, u64),
+    pub(crate) buffer_frames: (u64, u64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DeviceCapabilities {
+    pub(crate) device_id: String,
+    pub(crate) configs: Vec<OutputConfigRange>,
+    /// 至少一项配置覆盖的常见采样率。
+    pub(crate) sample_rates: Vec<u32>,
+    pub(crate) channel_counts: Vec<u16>,
+    pub(crate) sample_formats: Vec<String>,
+    pub(crate) hw_params: Option<AlsaHwParamsRange>,
+}
+
+/// 查询输出设备支持的配置。设备正被独占播放时 ALSA 会拒绝打开，
+/// 此时 `hw_params` 为空，cpal 的查询也可能失败。
+pub(crate) fn probe_output_device(
+    device_id: &str,
+) -> Result<DeviceCapabilities, Box<dyn std::error::Error>> {
+    let device = backend::find_output_device(device_id)?;
+    let configs = device
+        .supported_output_configs()?
+        .map(|config| OutputConfigRange {
+            channels: config.channels(),
+            min_sample_rate: config.min_sample_rate(),
+            max_sample_rate: config.max_sample_rate(),
+            sample_format: config.sample_format().to_string(),
+            buffer_frames: match *config.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => Some((min, max)),
+                cpal::SupportedBufferSize::Unknown => None,
+            },
+        })
+        .collect::<Vec<_>>();
+
+    let mut channel_counts = configs
+        .iter()
+        .map(|config| config.channels)
+        .collect::<Vec<_>>();
+    channel_counts.sort_unstable();
+    channel_counts.dedup();
+    let mut sample_formats = Vec::new();
+    for config in &configs {
+        if !sample_formats.contains(&config.sample_format) {
+            sample_formats.push(config.sample_format.clone());
+        }
+    }
+    let rate_ranges = configs
+        .iter()
+        .map(|config| (config.min_sample_rate, config.max_sample_rate))
+        .collect::<Vec<_>>();
+
+    Ok(DeviceCapabilities {
+        device_id: device_id.to_string(),
+        sample_rates: standard_rates_within(&rate_ranges),
+        channel_counts,
+        sample_formats,
+        configs,
+        hw_params: probe_hw_params(device_id),
+    })
+}
+
+fn standard_rates_within(ranges: &[(u32, u32)]) -> Vec<u32> {
+    STANDARD_SAMPLE_RATES
+        .into_iter()
+        .filter(|rate| ranges.iter().any(|(min, max)| min <= rate && rate <= max))
+        .collect()
+}
+
+/// `hw:` 设备对应的 ALSA PCM 名称；插件设备（plughw、default 等）返回 `None`。
+#[cfg(target_os = "linux")]
+fn hw_pcm_name(device_id: &str) -> Option<String> {
+    let normalized = device_id.trim().to_ascii_lowercase();
+    if !normalized.starts_with("hw:") && !normalized.starts_with("alsa:hw:") {
+        return None;
+    }
+    backend::linux_plughw_locator(device_id.trim())
+}
+
+#[cfg(target_os = "linux")]
+fn probe_hw_params(device_id: &str) -> Option<AlsaHwParamsRange> {
+    use alsa::Direction;
+    use alsa::pcm::{Format, HwParams, PCM};
+
+    const FORMATS: [Format; 9] = [
+        Format::S16LE,
+        Format::S243LE,
+        Format::S24LE,
+        Format::S32LE,
+        Format::FloatLE,
+        Format::Float64LE,
+        Format::DSDU8,
+        Format::DSDU16LE,
+        Format::DSDU32LE,
+    ];
+
+    let name = hw_pcm_name(device_id)?;
+    let pcm = match PCM::new(&name, Direction::Playback, true) {
+        Ok(pcm) => pcm,
+        Err(err) => {
+            eprintln!("[audio] open {} for hw_params failed: {}", name, err);
+            return None;
+        }
+    };
+    let hwp = HwParams::any(&pcm).ok()?;
+
+    Some(AlsaHwParamsRange {
+        sample_rates: STANDARD_SAMPLE_RATES
+            .into_iter()
+            .filter(|rate| hwp.test_rate(*rate).is_ok())
+            .collect(),
+        min_sample_rate: hwp.get_rate_min().ok()?,
+        max_sample_rate: hwp.get_rate_max().ok()?,
+        min_channels: hwp.get_channels_min().ok()?,
+        max_channels: hwp.get_channels_max().ok()?,
+        formats: FORMATS
+            .into_iter()
+            .filter(|format| hwp.test_format(*format).is_ok())
+            .map(|format| format.to_string())
+            .collect(),
+        period_frames: (
+            hwp.get_period_size_min().ok()? as u64,
+            hwp.get_period_size_max().ok()? as u64,
+        ),
+        buffer_frames: (
+            hwp.get_buffer_size_min().ok()? as u64,
+            hwp.get_buffer_size_max().ok()? as u64,
+        ),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_hw_params(_device_id: &str) -> Option<AlsaHwParamsRange> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_rates_are_filtered_by_config_ranges() {
+        assert_eq!(
+            standard_rates_within(&[(44_100, 48_000), (96_000, 192_000)]),
+            vec![44_100, 48_000, 96_000, 176_400, 192_000]
+        );
+        assert!(standard_rates_within(&[]).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn only_hw_devices_get_a_hw_params_probe() {
+        assert_eq!(hw_pcm_name("hw:1,0"), Some("hw:1,0".to_string()));
+        assert_eq!(
+            hw_pcm_name("alsa:hw:CARD=1,DEV=0"),
+            Some("hw:CARD=1,DEV=0".to_string())
+        );
+        assert_eq!(hw_pcm_name("plughw:1,0"), None);
+        assert_eq!(hw_pcm_name("default"), None);
+    }
+}
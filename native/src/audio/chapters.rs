@@ -0,0 +1,829 @@
+//! 章节：MP3 的 ID3v2 `CHAP`/`CTOC` 帧，MP4/M4B 的 QuickTime 章节轨（`tref/chap`）
+//! 与 Nero `chpl` 原子。探测时直接读原始字节，Symphonia 不解析这些结构。
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+
+/// 整块读入内存的 ID3 标签 / `moov` 原子的大小上限，避免按损坏的长度分配内存。
+const MAX_ID3_TAG_LEN: u64 = 16 * 1024 * 1024;
+const MAX_MOOV_LEN: u64 = 64 * 1024 * 1024;
+/// 章节标题的长度上限（QuickTime 文本样本）。
+const MAX_TITLE_LEN: usize = 4 * 1024;
+/// 章节文本轨的样本数（章节数）上限。
+const MAX_CHAPTERS: usize = 4 * 1024;
+/// 按下“上一章”时，当前章节已播放超过该时长则回到本章开头。
+pub(crate) const RESTART_CHAPTER_THRESHOLD: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Chapter {
+    pub(crate) title: String,
+    pub(crate) start: Duration,
+    /// 最后一章在容器未给出终点时为 `None`。
+    pub(crate) end: Option<Duration>,
+}
+
+/// 从音源开头探测章节，读完把位置放回 0。解析失败只记日志，不影响播放。
+pub(crate) fn read_chapters<R: Read + Seek + ?Sized>(source: &mut R) -> Vec<Chapter> {
+    let chapters = read_chapters_inner(source).unwrap_or_else(|err| {
+        eprintln!("[Chapters] 解析章节失败（忽略）: {}", err);
+        Vec::new()
+    });
+    if let Err(err) = source.seek(SeekFrom::Start(0)) {
+        eprintln!("[Chapters] 无法回到音源开头: {}", err);
+    }
+    chapters
+}
+
+fn read_chapters_inner<R: Read + Seek + ?Sized>(source: &mut R) -> io::Result<Vec<Chapter>> {
+    source.seek(SeekFrom::Start(0))?;
+    let mut head = [0u8; 10];
+    if read_up_to(source, &mut head)? < head.len() {
+        return Ok(Vec::new());
+    }
+    let chapters = if &head[0..3] == b"ID3" {
+        read_id3_chapters(source, &head)?
+    } else if &head[4..8] == b"ftyp" {
+        read_mp4_chapters(source)?
+    } else {
+        Vec::new()
+    };
+    Ok(normalize(chapters))
+}
+
+/// 按起点排序，补上缺失的终点。
+fn normalize(mut chapters: Vec<Chapter>) -> Vec<Chapter> {
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters.dedup_by_key(|chapter| chapter.start);
+    let starts: Vec<Duration> = chapters.iter().skip(1).map(|c| c.start).collect();
+    for (chapter, next_start) in chapters.iter_mut().zip(starts) {
+        if chapter
+            .end
+            .is_none_or(|end| end <= chapter.start || end > next_start)
+        {
+            chapter.end = Some(next_start);
+        }
+    }
+    if let Some(last) = chapters.last_mut()
+        && last.end.is_some_and(|end| end <= last.start)
+    {
+        last.end = None;
+    }
+    for (index, chapter) in chapters.iter_mut().enumerate() {
+        if chapter.title.is_empty() {
+            chapter.title = format!("第 {} 章", index + 1);
+        }
+    }
+    chapters
+}
+
+/// `position` 所在章节的下标；第一章之前为 `None`。
+pub(crate) fn chapter_index_at(chapters: &[Chapter], position: Duration) -> Option<usize> {
+    chapters
+        .iter()
+        .rposition(|chapter| chapter.start <= position)
+}
+
+/// “下一章”的起点；已在最后一章时为 `None`。
+pub(crate) fn next_chapter_start(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    chapters
+        .iter()
+        .find(|chapter| chapter.start > position)
+        .map(|chapter| chapter.start)
+}
+
+/// “上一章”的目标：本章已播放超过 `RESTART_CHAPTER_THRESHOLD` 时回到本章开头，
+/// 否则到上一章开头；在第一章时回到它的开头。
+pub(crate) fn previous_chapter_start(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    let index = chapter_index_at(chapters, position)?;
+    let current = &chapters[index];
+    if position.saturating_sub(current.start) > RESTART_CHAPTER_THRESHOLD || index == 0 {
+        return Some(current.start);
+    }
+    Some(chapters[index - 1].start)
+}
+
+fn read_up_to<R: Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn read_exact_vec<R: Read + ?Sized>(reader: &mut R, len: u64, max: u64) -> io::Result<Vec<u8>> {
+    if len > max {
+        return Err(invalid_data(format!("长度 {} 超出上限", len)));
+    }
+    let mut data = vec![0u8; len as usize];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// 大端字节游标。
+struct Bytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(invalid_data("数据被截断"));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// NUL 结尾的字符串（不含 NUL）。
+    fn cstr(&mut self) -> io::Result<&'a [u8]> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid_data("字符串缺少结尾"))?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.pos..];
+        self.pos = self.data.len();
+        rest
+    }
+}
+
+// ---- ID3v2 ----
+
+fn synchsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0u32, |acc, &b| (acc << 7) | u32::from(b & 0x7f))
+}
+
+/// 去掉非同步化插入的 0x00（`FF 00` → `FF`）。
+fn remove_unsync(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = 0u8;
+    for &b in data {
+        if !(prev == 0xff && b == 0x00) {
+            out.push(b);
+        }
+        prev = b;
+    }
+    out
+}
+
+/// ID3 文本：首字节为编码（0 Latin-1，1 带 BOM 的 UTF-16，2 UTF-16BE，3 UTF-8）。
+fn decode_id3_text(data: &[u8]) -> String {
+    let Some((&encoding, text)) = data.split_first() else {
+        return String::new();
+    };
+    let text = match encoding {
+        0 => text.iter().map(|&b| char::from(b)).collect(),
+        1 | 2 => decode_utf16(text, encoding == 2),
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+    text.trim_end_matches('\0').trim().to_string()
+}
+
+fn decode_utf16(data: &[u8], default_be: bool) -> String {
+    let (big_endian, data) = match data {
+        [0xfe, 0xff, rest @ ..] => (true, rest),
+        [0xff, 0xfe, rest @ ..] => (false, rest),
+        _ => (default_be, data),
+    };
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| {
+            let pair = [pair[0], pair[1]];
+            if big_endian {
+                u16::from_be_bytes(pair)
+            } else {
+                u16::from_le_bytes(pair)
+            }
+        })
+        .take_while(|&unit| unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+struct Id3Frame<'a> {
+    id: &'a [u8],
+    data: Vec<u8>,
+}
+
+/// 逐个取出 ID3v2.3/2.4 的帧，处理 2.4 的帧级非同步化与数据长度指示。
+fn id3_frames(data: &[u8], major: u8) -> io::Result<Vec<Id3Frame<'_>>> {
+    let mut frames = Vec::new();
+    let mut bytes = Bytes::new(data);
+    while bytes.remaining() >= 10 {
+        let id = bytes.take(4)?;
+        if id[0] == 0 {
+            break;
+        }
+        let size_bytes = bytes.take(4)?;
+        let size = if major >= 4 {
+            synchsafe(size_bytes)
+        } else {
+            u32::from_be_bytes(size_bytes.try_into().unwrap())
+        } as usize;
+        let flags = bytes.u16()?;
+        let mut body = bytes.take(size.min(bytes.remaining()))?;
+        let mut unsync = false;
+        if major >= 4 {
+            if flags & 0x0001 != 0 && body.len() >= 4 {
+                body = &body[4..];
+            }
+            unsync = flags & 0x0002 != 0;
+        }
+        frames.push(Id3Frame {
+            id,
+            data: if unsync {
+                remove_unsync(body)
+            } else {
+                body.to_vec()
+            },
+        });
+    }
+    Ok(frames)
+}
+
+fn read_id3_chapters<R: Read + Seek + ?Sized>(
+    source: &mut R,
+    head: &[u8; 10],
+) -> io::Result<Vec<Chapter>> {
+    let major = head[3];
+    if !(3..=4).contains(&major) {
+        return Ok(Vec::new());
+    }
+    let flags = head[5];
+    let size = u64::from(synchsafe(&head[6..10]));
+    let tag = read_exact_vec(source, size, MAX_ID3_TAG_LEN)?;
+    let mut tag = if major == 3 && flags & 0x80 != 0 {
+        remove_unsync(&tag)
+    } else {
+        tag
+    };
+    if flags & 0x40 != 0 {
+        let ext_len = match major {
+            3 => 4 + u32::from_be_bytes(tag[..4].try_into().unwrap_or_default()) as usize,
+            _ => synchsafe(tag.get(..4).unwrap_or_default()) as usize,
+        };
+        tag.drain(..ext_len.min(tag.len()));
+    }
+    parse_id3_chapter_frames(&tag, major)
+}
+
+fn parse_id3_chapter_frames(tag: &[u8], major: u8) -> io::Result<Vec<Chapter>> {
+    let mut chapters: Vec<(Vec<u8>, Chapter)> = Vec::new();
+    let mut order: Option<Vec<Vec<u8>>> = None;
+    for frame in id3_frames(tag, major)? {
+        match frame.id {
+            b"CHAP" => {
+                let mut bytes = Bytes::new(&frame.data);
+                let element_id = bytes.cstr()?.to_vec();
+                let start_ms = bytes.u32()?;
+                let end_ms = bytes.u32()?;
+                bytes.skip(8)?;
+                let title = id3_frames(bytes.rest(), major)?
+                    .into_iter()
+                    .find(|sub| sub.id == b"TIT2")
+                    .map(|sub| decode_id3_text(&sub.data))
+                    .unwrap_or_default();
+                chapters.push((
+                    element_id,
+                    Chapter {
+                        title,
+                        start: Duration::from_millis(u64::from(start_ms)),
+                        end: (end_ms != u32::MAX).then(|| Duration::from_millis(u64::from(end_ms))),
+                    },
+                ));
+            }
+            b"CTOC" => {
+                let mut bytes = Bytes::new(&frame.data);
+                bytes.cstr()?;
+                let flags = bytes.u8()?;
+                let count = bytes.u8()?;
+                let children = (0..count)
+                    .map(|_| bytes.cstr().map(<[u8]>::to_vec))
+                    .collect::<io::Result<Vec<_>>>()?;
+                // 只采用顶层目录的顺序。
+                if flags & 0x02 != 0 || order.is_none() {
+                    order = Some(children);
+                }
+            }
+            _ => {}
+        }
+    }
+    // 有目录时只保留目录里列出的章节。
+    if let Some(order) = order.filter(|order| !order.is_empty()) {
+        chapters.retain(|(id, _)| order.contains(id));
+    }
+    Ok(chapters.into_iter().map(|(_, chapter)| chapter).collect())
+}
+
+// ---- MP4 ----
+
+/// 一个原子的类型与内容。
+struct Atom<'a> {
+    kind: &'a [u8],
+    body: &'a [u8],
+}
+
+fn atoms(data: &[u8]) -> impl Iterator<Item = Atom<'_>> {
+    let mut bytes = Bytes::new(data);
+    std::iter::from_fn(move || {
+        if bytes.remaining() < 8 {
+            return None;
+        }
+        let size = bytes.u32().ok()? as u64;
+        let kind = bytes.take(4).ok()?;
+        let header = if size == 1 { 16 } else { 8 };
+        let size = match size {
+            0 => (bytes.remaining() + 8) as u64,
+            1 => bytes.u64().ok()?,
+            size => size,
+        };
+        let body_len = usize::try_from(size.checked_sub(header)?).ok()?;
+        let body = bytes.take(body_len).ok()?;
+        Some(Atom { kind, body })
+    })
+}
+
+fn child<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    let (first, rest) = path.split_first()?;
+    let atom = atoms(data).find(|atom| atom.kind == *first)?;
+    if rest.is_empty() {
+        Some(atom.body)
+    } else {
+        child(atom.body, rest)
+    }
+}
+
+/// 顶层原子里找到 `moov` 并整块读入。
+fn read_moov<R: Read + Seek + ?Sized>(source: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut offset = 0u64;
+    loop {
+        source.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 16];
+        if read_up_to(source, &mut header[..8])? < 8 {
+            return Ok(None);
+        }
+        let mut size = u64::from(u32::from_be_bytes(header[0..4].try_into().unwrap()));
+        let mut header_len = 8;
+        if size == 1 {
+            source.read_exact(&mut header[8..16])?;
+            size = u64::from_be_bytes(header[8..16].try_into().unwrap());
+            header_len = 16;
+        }
+        if &header[4..8] == b"moov" {
+            let body_len = if size == 0 {
+                source.seek(SeekFrom::End(0))? - offset - header_len
+            } else {
+                size.saturating_sub(header_len)
+            };
+            source.seek(SeekFrom::Start(offset + header_len))?;
+            return read_exact_vec(source, body_len, MAX_MOOV_LEN).map(Some);
+        }
+        if size < header_len {
+            return Ok(None);
+        }
+        offset += size;
+    }
+}
+
+fn read_mp4_chapters<R: Read + Seek + ?Sized>(source: &mut R) -> io::Result<Vec<Chapter>> {
+    let Some(moov) = read_moov(source)? else {
+        return Ok(Vec::new());
+    };
+    let chapters = read_chapter_track(source, &moov)?;
+    if !chapters.is_empty() {
+        return Ok(chapters);
+    }
+    match child(&moov, &[b"udta", b"chpl"]) {
+        Some(chpl) => parse_chpl(chpl),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Nero 章节：起点以 100ns 为单位，没有终点。
+fn parse_chpl(body: &[u8]) -> io::Result<Vec<Chapter>> {
+    let mut bytes = Bytes::new(body);
+    let version = bytes.u8()?;
+    bytes.skip(3)?;
+    if version > 0 {
+        bytes.skip(4)?;
+    }
+    let count = bytes.u8()?;
+    (0..count)
+        .map(|_| {
+            let start = bytes.u64()?;
+            let len = bytes.u8()? as usize;
+            let title = String::from_utf8_lossy(bytes.take(len)?).trim().to_string();
+            Ok(Chapter {
+                title,
+                start: Duration::from_nanos(start.saturating_mul(100)),
+                end: None,
+            })
+        })
+        .collect()
+}
+
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let mut bytes = Bytes::new(child(trak, &[b"tkhd"])?);
+    let version = bytes.u8().ok()?;
+    bytes.skip(3 + if version == 1 { 16 } else { 8 }).ok()?;
+    bytes.u32().ok()
+}
+
+/// 主轨道 `tref/chap` 指向的文本轨，每个样本是一章的标题。
+fn read_chapter_track<R: Read + Seek + ?Sized>(
+    source: &mut R,
+    moov: &[u8],
+) -> io::Result<Vec<Chapter>> {
+    let traks: Vec<&[u8]> = atoms(moov)
+        .filter(|atom| atom.kind == b"trak")
+        .map(|atom| atom.body)
+        .collect();
+    let chapter_ids: Vec<u32> = traks
+        .iter()
+        .filter_map(|trak| child(trak, &[b"tref", b"chap"]))
+        .flat_map(|chap| {
+            chap.chunks_exact(4)
+                .map(|id| u32::from_be_bytes(id.try_into().unwrap()))
+        })
+        .collect();
+    let Some(trak) = traks
+        .iter()
+        .find(|trak| track_id(trak).is_some_and(|id| chapter_ids.contains(&id)))
+    else {
+        return Ok(Vec::new());
+    };
+    let Some(mdia) = child(trak, &[b"mdia"]) else {
+        return Ok(Vec::new());
+    };
+    let timescale = child(mdia, &[b"mdhd"])
+        .and_then(|mdhd| {
+            let mut bytes = Bytes::new(mdhd);
+            let version = bytes.u8().ok()?;
+            bytes.skip(3 + if version == 1 { 16 } else { 8 }).ok()?;
+            bytes.u32().ok()
+        })
+        .filter(|&timescale| timescale > 0)
+        .ok_or_else(|| invalid_data("章节轨缺少 timescale"))?;
+    let stbl = child(mdia, &[b"minf", b"stbl"]).ok_or_else(|| invalid_data("章节轨缺少 stbl"))?;
+    let table = SampleTable::parse(stbl)?;
+
+    let mut chapters = Vec::with_capacity(table.sizes.len());
+    let mut time = 0u64;
+    for (index, (&offset, &size)) in table.offsets.iter().zip(&table.sizes).enumerate() {
+        let delta = table.durations.get(index).copied().unwrap_or(0);
+        let start = Duration::from_secs_f64(time as f64 / f64::from(timescale));
+        time += u64::from(delta);
+        let end = Duration::from_secs_f64(time as f64 / f64::from(timescale));
+        source.seek(SeekFrom::Start(offset))?;
+        let sample = read_exact_vec(source, u64::from(size), MAX_TITLE_LEN as u64 + 2)?;
+        chapters.push(Chapter {
+            title: decode_text_sample(&sample),
+            start,
+            end: (delta > 0).then_some(end),
+        });
+    }
+    Ok(chapters)
+}
+
+/// QuickTime 文本样本：16 位长度 + UTF-8 或带 BOM 的 UTF-16 文本，后面可能跟着样式原子。
+fn decode_text_sample(sample: &[u8]) -> String {
+    let Some((len, text)) = sample.split_first_chunk::<2>() else {
+        return String::new();
+    };
+    let len = (u16::from_be_bytes(*len) as usize).min(text.len());
+    let text = &text[..len];
+    let text = if text.starts_with(&[0xfe, 0xff]) || text.starts_with(&[0xff, 0xfe]) {
+        decode_utf16(text, true)
+    } else {
+        String::from_utf8_lossy(text).into_owned()
+    };
+    text.trim().to_string()
+}
+
+/// full box 的表：跳过 version/flags，返回条目数与后面的数据。
+fn entries(body: &[u8]) -> io::Result<(Bytes<'_>, u32)> {
+    let mut bytes = Bytes::new(body);
+    bytes.skip(4)?;
+    let count = bytes.u32()?;
+    Ok((bytes, count))
+}
+
+/// 章节轨每个样本的时长、大小与文件偏移。
+struct SampleTable {
+    durations: Vec<u32>,
+    sizes: Vec<u32>,
+    offsets: Vec<u64>,
+}
+
+impl SampleTable {
+    fn parse(stbl: &[u8]) -> io::Result<Self> {
+        let missing = |name: &str| invalid_data(format!("章节轨缺少 {name}"));
+        let (mut stts, count) = entries(child(stbl, &[b"stts"]).ok_or_else(|| missing("stts"))?)?;
+        let mut durations = Vec::new();
+        for _ in 0..count {
+            let samples = stts.u32()?;
+            let delta = stts.u32()?;
+            if durations.len() + samples as usize > MAX_CHAPTERS {
+                return Err(invalid_data("章节数量异常"));
+            }
+            durations.extend(std::iter::repeat_n(delta, samples as usize));
+        }
+
+        let mut stsz = Bytes::new(child(stbl, &[b"stsz"]).ok_or_else(|| missing("stsz"))?);
+        stsz.skip(4)?;
+        let fixed_size = stsz.u32()?;
+        let sample_count = stsz.u32()? as usize;
+        if sample_count > MAX_CHAPTERS {
+            return Err(invalid_data("章节数量异常"));
+        }
+        let sizes = if fixed_size > 0 {
+            vec![fixed_size; sample_count]
+        } else {
+            (0..sample_count)
+                .map(|_| stsz.u32())
+                .collect::<io::Result<_>>()?
+        };
+
+        let chunk_offsets: Vec<u64> = if let Some(stco) = child(stbl, &[b"stco"]) {
+            let (mut stco, count) = entries(stco)?;
+            (0..count)
+                .map(|_| stco.u32().map(u64::from))
+                .collect::<io::Result<_>>()?
+        } else {
+            let (mut co64, count) =
+                entries(child(stbl, &[b"co64"]).ok_or_else(|| missing("stco"))?)?;
+            (0..count).map(|_| co64.u64()).collect::<io::Result<_>>()?
+        };
+
+        let (mut stsc, count) = entries(child(stbl, &[b"stsc"]).ok_or_else(|| missing("stsc"))?)?;
+        let runs = (0..count)
+            .map(|_| {
+                let first_chunk = stsc.u32()?;
+                let samples_per_chunk = stsc.u32()?;
+                stsc.skip(4)?;
+                Ok((first_chunk.max(1) as usize - 1, samples_per_chunk as usize))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut offsets = Vec::with_capacity(sample_count);
+        for (run_index, &(first_chunk, samples_per_chunk)) in runs.iter().enumerate() {
+            let last_chunk = runs
+                .get(run_index + 1)
+                .map_or(chunk_offsets.len(), |&(next, _)| next);
+            for &chunk_offset in chunk_offsets
+                .get(first_chunk..last_chunk.min(chunk_offsets.len()))
+                .unwrap_or_default()
+            {
+                let mut offset = chunk_offset;
+                for _ in 0..samples_per_chunk {
+                    let Some(&size) = sizes.get(offsets.len()) else {
+                        break;
+                    };
+                    offsets.push(offset);
+                    offset += u64::from(size);
+                }
+            }
+        }
+
+        Ok(Self {
+            durations,
+            sizes,
+            offsets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chapter(title: &str, start_ms: u64, end_ms: Option<u64>) -> Chapter {
+        Chapter {
+            title: title.to_string(),
+            start: Duration::from_millis(start_ms),
+            end: end_ms.map(Duration::from_millis),
+        }
+    }
+
+    fn id3_frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    fn chap(id: &str, start_ms: u32, end_ms: u32, title: &str) -> Vec<u8> {
+        let mut body = id.as_bytes().to_vec();
+        body.push(0);
+        body.extend_from_slice(&start_ms.to_be_bytes());
+        body.extend_from_slice(&end_ms.to_be_bytes());
+        body.extend_from_slice(&[0xff; 8]);
+        let mut text = vec![3];
+        text.extend_from_slice(title.as_bytes());
+        body.extend_from_slice(&id3_frame(b"TIT2", &text));
+        id3_frame(b"CHAP", &body)
+    }
+
+    #[test]
+    fn id3_chap_frames_follow_the_table_of_contents() {
+        let mut frames = Vec::new();
+        frames.extend_from_slice(&chap("ch1", 60_000, 120_000, "第二段"));
+        frames.extend_from_slice(&chap("ch0", 0, 60_000, "开场"));
+        frames.extend_from_slice(&chap("ad", 120_000, 130_000, "未列出"));
+        let mut toc = b"toc\0".to_vec();
+        toc.extend_from_slice(&[0x03, 2]);
+        toc.extend_from_slice(b"ch0\0ch1\0");
+        frames.extend_from_slice(&id3_frame(b"CTOC", &toc));
+
+        let mut file = b"ID3\x03\x00\x00".to_vec();
+        let size = frames.len() as u32;
+        file.extend(
+            (0..4)
+                .rev()
+                .map(|shift| ((size >> (shift * 7)) & 0x7f) as u8),
+        );
+        file.extend_from_slice(&frames);
+        file.extend_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+
+        let mut source = Cursor::new(file);
+        source.set_position(5);
+        assert_eq!(
+            read_chapters(&mut source),
+            vec![
+                chapter("开场", 0, Some(60_000)),
+                chapter("第二段", 60_000, Some(120_000)),
+            ]
+        );
+        assert_eq!(source.position(), 0);
+    }
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(body);
+        atom
+    }
+
+    fn full_atom(kind: &[u8; 4], fields: &[u32]) -> Vec<u8> {
+        let mut body = vec![0u8; 4];
+        for field in fields {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+        atom(kind, &body)
+    }
+
+    #[test]
+    fn mp4_chapter_track_titles_are_read_from_sample_data() {
+        let titles = ["Intro", "正文"];
+        let samples: Vec<Vec<u8>> = titles
+            .iter()
+            .map(|title| {
+                let mut sample = (title.len() as u16).to_be_bytes().to_vec();
+                sample.extend_from_slice(title.as_bytes());
+                sample
+            })
+            .collect();
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0");
+        let mdat_body: Vec<u8> = samples.concat();
+        let mdat = atom(b"mdat", &mdat_body);
+        let data_offset = (ftyp.len() + 8) as u32;
+
+        let audio_trak = atom(
+            b"trak",
+            &[
+                full_atom(b"tkhd", &[0, 0, 1]),
+                atom(b"tref", &atom(b"chap", &2u32.to_be_bytes())),
+            ]
+            .concat(),
+        );
+        let stbl = [
+            full_atom(b"stts", &[2, 1, 1000, 1, 2500]),
+            full_atom(
+                b"stsz",
+                &[0, 2, samples[0].len() as u32, samples[1].len() as u32],
+            ),
+            full_atom(b"stsc", &[1, 1, 2, 1]),
+            full_atom(b"stco", &[1, data_offset]),
+        ]
+        .concat();
+        let text_trak = atom(
+            b"trak",
+            &[
+                full_atom(b"tkhd", &[0, 0, 2]),
+                atom(
+                    b"mdia",
+                    &[
+                        full_atom(b"mdhd", &[0, 0, 1000, 3500]),
+                        atom(b"minf", &atom(b"stbl", &stbl)),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        );
+        let chpl = {
+            let mut body = vec![1, 0, 0, 0, 0, 0, 0, 0, 1];
+            body.extend_from_slice(&0u64.to_be_bytes());
+            body.push(5);
+            body.extend_from_slice(b"Nero!");
+            atom(b"udta", &atom(b"chpl", &body))
+        };
+        let moov = atom(b"moov", &[audio_trak, text_trak, chpl].concat());
+        let file = [ftyp, mdat, moov].concat();
+
+        assert_eq!(
+            read_chapters(&mut Cursor::new(file)),
+            vec![
+                chapter("Intro", 0, Some(1000)),
+                chapter("正文", 1000, Some(3500)),
+            ]
+        );
+    }
+
+    #[test]
+    fn nero_chapters_are_used_without_a_chapter_track() {
+        let mut body = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "A"), (15_000_000u64, "B")] {
+            body.extend_from_slice(&start.to_be_bytes());
+            body.push(title.len() as u8);
+            body.extend_from_slice(title.as_bytes());
+        }
+        let file = [
+            atom(b"ftyp", b"M4B \0\0\0\0"),
+            atom(b"moov", &atom(b"udta", &atom(b"chpl", &body))),
+        ]
+        .concat();
+
+        assert_eq!(
+            read_chapters(&mut Cursor::new(file)),
+            vec![chapter("A", 0, Some(1500)), chapter("B", 1500, None)]
+        );
+    }
+
+    #[test]
+    fn chapter_navigation_targets() {
+        let chapters = vec![
+            chapter("A", 0, Some(60_000)),
+            chapter("B", 60_000, Some(120_000)),
+            chapter("C", 120_000, None),
+        ];
+        let at = |ms| Duration::from_millis(ms);
+
+        assert_eq!(chapter_index_at(&chapters, at(59_999)), Some(0));
+        assert_eq!(chapter_index_at(&chapters, at(60_000)), Some(1));
+        assert_eq!(next_chapter_start(&chapters, at(70_000)), Some(at(120_000)));
+        assert_eq!(next_chapter_start(&chapters, at(130_000)), None);
+        assert_eq!(previous_chapter_start(&chapters, at(61_000)), Some(at(0)));
+        assert_eq!(
+            previous_chapter_start(&chapters, at(70_000)),
+            Some(at(60_000))
+        );
+        assert_eq!(previous_chapter_start(&chapters, at(1_000)), Some(at(0)));
+        assert!(chapter_index_at(&[], at(0)).is_none());
+    }
+}
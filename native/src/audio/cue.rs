@@ -0,0 +1,384 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::audio::player::PlaybackRange;
+
+/// CUE 时间码 mm:ss:ff 中每秒的帧数（CD 扇区）。
+const CUE_FRAMES_PER_SECOND: u64 = 75;
+/// FILE 指向的文件不存在时（常见于把 .wav 转成 .flac 后没改 CUE），
+/// 按同名不同扩展名依次查找。
+const FALLBACK_EXTENSIONS: &[&str] = &[
+    "flac", "wav", "ape", "wv", "tta", "m4a", "mp3", "ogg", "opus", "dsf", "dff",
+];
+
+pub(crate) fn is_cue_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct CueSheet {
+    pub(crate) title: Option<String>,
+    pub(crate) performer: Option<String>,
+    /// REM 注释（如 GENRE、DATE、REPLAYGAIN_ALBUM_GAIN），键统一大写。
+    pub(crate) rem: Vec<(String, String)>,
+    pub(crate) tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CueTrack {
+    pub(crate) number: u32,
+    pub(crate) title: Option<String>,
+    /// 缺省时继承整张专辑的 PERFORMER。
+    pub(crate) performer: Option<String>,
+    pub(crate) rem: Vec<(String, String)>,
+    /// 已按 CUE 所在目录解析的音频文件路径。
+    pub(crate) file: PathBuf,
+    /// INDEX 00（pregap 起点），不存在时为 `None`。
+    pub(crate) pregap: Option<Duration>,
+    /// INDEX 01，音轨正式开始的位置。
+    pub(crate) start: Duration,
+    /// 同一文件中下一轨的 INDEX 01；文件最后一轨为 `None`，播到文件末尾。
+    pub(crate) end: Option<Duration>,
+}
+
+impl CueTrack {
+    pub(crate) fn range(&self) -> PlaybackRange {
+        PlaybackRange {
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    pub(crate) fn duration(&self) -> Option<Duration> {
+        self.end.map(|end| end.saturating_sub(self.start))
+    }
+}
+
+impl CueSheet {
+    pub(crate) fn track(&self, number: u32) -> Option<&CueTrack> {
+        self.tracks.iter().find(|track| track.number == number)
+    }
+
+    /// 同一文件中紧接在 `number` 之后、首尾相连的下一轨，用于无缝续播。
+    pub(crate) fn following_track(&self, number: u32) -> Option<&CueTrack> {
+        let index = self
+            .tracks
+            .iter()
+            .position(|track| track.number == number)?;
+        let current = &self.tracks[index];
+        self.tracks
+            .get(index + 1)
+            .filter(|next| next.file == current.file && current.end == Some(next.start))
+    }
+}
+
+pub(crate) fn read_file(path: &Path) -> io::Result<CueSheet> {
+    let bytes = std::fs::read(path)?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse(&decode_text(&bytes), base_dir)
+}
+
+/// CUE 没有编码声明：优先按 UTF-8（含 BOM）/ UTF-16 BOM 解码，
+/// 否则按 GBK（GB18030）处理，国内抓轨软件生成的 CUE 大多是这种编码。
+pub(crate) fn decode_text(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_len)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return text.into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => encoding_rs::GB18030.decode(bytes).0.into_owned(),
+    }
+}
+
+pub(crate) fn parse(text: &str, base_dir: &Path) -> io::Result<CueSheet> {
+    let mut sheet = CueSheet::default();
+    let mut current_file: Option<PathBuf> = None;
+    let mut current: Option<PendingTrack> = None;
+    let mut pending: Vec<PendingTrack> = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let Some((command, rest)) = split_command(line) else {
+            continue;
+        };
+        let line_no = line_no + 1;
+        let invalid = |message: &str| invalid_data(format!("CUE 第 {line_no} 行{message}"));
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                let name = file_name(rest).ok_or_else(|| invalid("：FILE 缺少文件名"))?;
+                current_file = Some(resolve_file(base_dir, &name));
+            }
+            "TRACK" => {
+                let file = current_file
+                    .clone()
+                    .ok_or_else(|| invalid("：TRACK 出现在 FILE 之前"))?;
+                let number = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|value| value.parse::<u32>().ok())
+                    .ok_or_else(|| invalid("：TRACK 编号无效"))?;
+                pending.extend(current.take());
+                current = Some(PendingTrack::new(number, file));
+            }
+            "INDEX" => {
+                let track = current
+                    .as_mut()
+                    .ok_or_else(|| invalid("：INDEX 出现在 TRACK 之前"))?;
+                let mut args = rest.split_whitespace();
+                let index = args.next().and_then(|value| value.parse::<u32>().ok());
+                let time = args.next().and_then(parse_timestamp);
+                let (Some(index), Some(time)) = (index, time) else {
+                    return Err(invalid("：INDEX 格式无效"));
+                };
+                // 跨文件的音轨（INDEX 00 在上一个 FILE 里）以 INDEX 01 所在文件为准。
+                if let Some(file) = current_file.as_ref() {
+                    track.file.clone_from(file);
+                }
+                match index {
+                    0 => track.pregap = Some(time),
+                    1 => track.start = Some(time),
+                    _ => {}
+                }
+            }
+            "TITLE" => {
+                let value = unquote(rest);
+                match current.as_mut() {
+                    Some(track) => track.title = Some(value),
+                    None => sheet.title = Some(value),
+                }
+            }
+            "PERFORMER" => {
+                let value = unquote(rest);
+                match current.as_mut() {
+                    Some(track) => track.performer = Some(value),
+                    None => sheet.performer = Some(value),
+                }
+            }
+            "REM" => {
+                if let Some((key, value)) = split_command(rest) {
+                    let entry = (key.to_ascii_uppercase(), unquote(value));
+                    match current.as_mut() {
+                        Some(track) => track.rem.push(entry),
+                        None => sheet.rem.push(entry),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    pending.extend(current.take());
+
+    let mut tracks = Vec::with_capacity(pending.len());
+    for track in pending {
+        let start = track
+            .start
+            .or(track.pregap)
+            .ok_or_else(|| invalid_data(format!("CUE 音轨 {} 缺少 INDEX 01", track.number)))?;
+        tracks.push(CueTrack {
+            number: track.number,
+            title: track.title,
+            performer: track.performer.or_else(|| sheet.performer.clone()),
+            rem: track.rem,
+            file: track.file,
+            pregap: track.pregap,
+            start,
+            end: None,
+        });
+    }
+    for index in 1..tracks.len() {
+        let (previous, next) = tracks.split_at_mut(index);
+        let previous = previous.last_mut().unwrap();
+        if previous.file == next[0].file && next[0].start > previous.start {
+            previous.end = Some(next[0].start);
+        }
+    }
+
+    if tracks.is_empty() {
+        return Err(invalid_data("CUE 中没有音轨"));
+    }
+    sheet.tracks = tracks;
+    Ok(sheet)
+}
+
+struct PendingTrack {
+    number: u32,
+    title: Option<String>,
+    performer: Option<String>,
+    rem: Vec<(String, String)>,
+    file: PathBuf,
+    pregap: Option<Duration>,
+    start: Option<Duration>,
+}
+
+impl PendingTrack {
+    fn new(number: u32, file: PathBuf) -> Self {
+        Self {
+            number,
+            title: None,
+            performer: None,
+            rem: Vec::new(),
+            file,
+            pregap: None,
+            start: None,
+        }
+    }
+}
+
+fn split_command(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start_matches('\u{feff}').trim();
+    if line.is_empty() {
+        return None;
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((command, rest)) => Some((command, rest.trim())),
+        None => Some((line, "")),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('"') {
+        Some(inner) => inner.split('"').next().unwrap_or_default().to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// `FILE "name with spaces.flac" WAVE`；不带引号时最后一个词是文件类型。
+fn file_name(rest: &str) -> Option<String> {
+    let name = if rest.starts_with('"') {
+        unquote(rest)
+    } else {
+        match rest.rsplit_once(char::is_whitespace) {
+            Some((name, _file_type)) => name.trim().to_string(),
+            None => rest.to_string(),
+        }
+    };
+    Some(name).filter(|name| !name.is_empty())
+}
+
+fn resolve_file(base_dir: &Path, name: &str) -> PathBuf {
+    // Windows 抓轨软件写出的路径分隔符是反斜杠。
+    let path = base_dir.join(name.replace('\\', "/"));
+    if path.exists() {
+        return path;
+    }
+    FALLBACK_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|candidate| candidate.exists())
+        .unwrap_or(path)
+}
+
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut parts = value.split(':').map(|part| part.parse::<u64>().ok());
+    let (Some(Some(minutes)), Some(Some(seconds)), Some(Some(frames)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    if seconds >= 60 || frames >= CUE_FRAMES_PER_SECOND {
+        return None;
+    }
+    let total_frames = (minutes * 60 + seconds) * CUE_FRAMES_PER_SECOND + frames;
+    Some(Duration::from_nanos(
+        total_frames * 1_000_000_000 / CUE_FRAMES_PER_SECOND,
+    ))
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM GENRE Pop
+REM DATE 2003
+PERFORMER "周杰伦"
+TITLE "叶惠美"
+FILE "CDImage.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "以父之名"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "懦夫"
+    PERFORMER "Jay Chou"
+    REM REPLAYGAIN_TRACK_GAIN -6.20 dB
+    INDEX 00 05:39:40
+    INDEX 01 05:41:10
+  TRACK 03 AUDIO
+    TITLE "晴天"
+    INDEX 01 09:17:73
+"#;
+
+    #[test]
+    fn parses_tracks_with_index_boundaries_and_inherited_performer() {
+        let sheet = parse(SHEET, Path::new("/music")).unwrap();
+
+        assert_eq!(sheet.title.as_deref(), Some("叶惠美"));
+        assert_eq!(
+            sheet.rem,
+            vec![
+                ("GENRE".to_string(), "Pop".to_string()),
+                ("DATE".to_string(), "2003".to_string())
+            ]
+        );
+        assert_eq!(sheet.tracks.len(), 3);
+
+        let first = &sheet.tracks[0];
+        assert_eq!(first.file, Path::new("/music/CDImage.wav"));
+        assert_eq!(first.performer.as_deref(), Some("周杰伦"));
+        assert_eq!(first.start, Duration::ZERO);
+        assert_eq!(first.end, Some(sheet.tracks[1].start));
+
+        let second = &sheet.tracks[1];
+        assert_eq!(second.performer.as_deref(), Some("Jay Chou"));
+        assert_eq!(second.pregap, Some(Duration::from_nanos(339_533_333_333)));
+        assert_eq!(second.start, Duration::from_nanos(341_133_333_333));
+        assert_eq!(
+            second.rem,
+            vec![("REPLAYGAIN_TRACK_GAIN".to_string(), "-6.20 dB".to_string())]
+        );
+
+        assert_eq!(sheet.tracks[2].end, None);
+        assert_eq!(sheet.following_track(1).map(|track| track.number), Some(2));
+        assert!(sheet.following_track(3).is_none());
+    }
+
+    #[test]
+    fn decodes_gbk_encoded_sheets() {
+        let (gbk, _, _) = encoding_rs::GBK.encode(SHEET);
+        assert!(std::str::from_utf8(&gbk).is_err());
+
+        let sheet = parse(&decode_text(&gbk), Path::new("")).unwrap();
+
+        assert_eq!(sheet.performer.as_deref(), Some("周杰伦"));
+        assert_eq!(sheet.tracks[2].title.as_deref(), Some("晴天"));
+    }
+
+    #[test]
+    fn tracks_in_different_files_do_not_share_boundaries() {
+        let text = "FILE \"a.flac\" WAVE\n TRACK 01 AUDIO\n  INDEX 01 00:00:00\n\
+                    FILE b.flac WAVE\n TRACK 02 AUDIO\n  INDEX 01 00:00:00\n";
+
+        let sheet = parse(text, Path::new("")).unwrap();
+
+        assert_eq!(sheet.tracks[0].file, Path::new("a.flac"));
+        assert_eq!(sheet.tracks[1].file, Path::new("b.flac"));
+        assert_eq!(sheet.tracks[0].end, None);
+        assert!(sheet.following_track(1).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_index_lines() {
+        let text = "FILE \"a.flac\" WAVE\n TRACK 01 AUDIO\n  INDEX 01 00:00:75\n";
+
+        assert!(parse(text, Path::new("")).is_err());
+    }
+}
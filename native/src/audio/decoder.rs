@@ -1,26 +1,56 @@
+use crate::audio::chapters::{self, Chapter};
+use crate::audio::dsd::{self, DsdOutput};
+use crate::audio::ncm;
+use crate::audio::opus;
+use crate::audio::registry;
+use crate::audio::seek_index;
 use crate::audio::state::{NO_TRIM_FRAME, SharedState};
 use ringbuf::traits::Producer;
 use std::path::Path;
 use std::sync::atomic::Ordering;
-use std::time::Duration;
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::{CODEC_TYPE_NULL, Decoder, DecoderOptions};
+use std::time::{Duration, Instant};
+use symphonia::core::audio::{Channels, SampleBuffer};
+use symphonia::core::codecs::{
+    CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3, CODEC_TYPE_NULL, Decoder, DecoderOptions,
+};
 use symphonia::core::conv::ConvertibleSample;
 use symphonia::core::errors::Error as SymphoniaError;
-use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo, SeekedTo};
+use symphonia::core::formats::{FormatReader, SeekMode, SeekTo, SeekedTo};
 use symphonia::core::io::{MediaSource, MediaSourceStream};
-use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use symphonia::core::sample::{Sample, SampleFormat as SymphoniaSampleFormat};
 use symphonia::core::units::TimeBase;
 
+/// 拖动预览时每个位置播放的片段长度。
+const SCRUB_SNIPPET: Duration = Duration::from_millis(80);
+/// 预览片段两端的淡入淡出长度，避免片段拼接处爆音。
+const SCRUB_FADE: Duration = Duration::from_millis(8);
+/// 上一个片段剩余不到这么长时才开始解码下一个位置。
+const SCRUB_REFILL_THRESHOLD: Duration = Duration::from_millis(20);
+
 pub(crate) struct AudioMetadata {
     pub(crate) sample_rate: u32,
     pub(crate) channels: u16,
+    /// 声道位置掩码，容器和解码器都没给出时为 `None`。
+    pub(crate) channel_layout: Option<Channels>,
     pub(crate) bits_per_sample: Option<u32>,
     pub(crate) sample_format: Option<SymphoniaSampleFormat>,
+    /// DSD 音源的原生采样率（如 DSD64 为 2822400），仅用于展示；PCM 音源为 None。
+    pub(crate) dsd_rate: Option<u32>,
     pub(crate) time_base: Option<TimeBase>,
     pub(crate) track_id: u32,
+    /// 容器给出的总长度（time_base 单位）。
+    pub(crate) n_frames: Option<u64>,
+    /// `n_frames` 是按码率估算的（MP3 没有 Xing/VBRI 目录），播放时应随读取的数据修正。
+    pub(crate) n_frames_estimated: bool,
+    /// 音源总字节数，`n_frames` 缺失时用于按码率估算时长。
+    pub(crate) byte_len: Option<u64>,
+    /// 第一帧音频之前的标签字节数，估算码率时从 `byte_len` 中扣除。
+    pub(crate) audio_offset: u64,
+    /// 音源自带的标签，导出时带到新文件。
+    pub(crate) tags: registry::Tags,
+    /// 容器内的章节，按起点排序；没有章节时为空。
+    pub(crate) chapters: Vec<Chapter>,
     pub(crate) decoder: Box<dyn Decoder>,
     pub(crate) format_reader: Box<dyn FormatReader>,
 }
@@ -29,7 +59,16 @@ pub(crate) async fn spawn_probe_task(
     source: Box<dyn MediaSource>,
     extension: Option<String>,
 ) -> Result<AudioMetadata, Box<dyn std::error::Error>> {
-    tokio::task::spawn_blocking(move || probe_source(source, extension))
+    spawn_probe_task_with_correction(source, extension, None).await
+}
+
+/// 同 `spawn_probe_task`，额外携带 WavPack hybrid 模式的 .wvc 校正文件。
+pub(crate) async fn spawn_probe_task_with_correction(
+    source: Box<dyn MediaSource>,
+    extension: Option<String>,
+    correction: Option<Box<dyn MediaSource>>,
+) -> Result<AudioMetadata, Box<dyn std::error::Error>> {
+    tokio::task::spawn_blocking(move || probe_source(source, extension, correction))
         .await?
         .map_err(|e| e as Box<dyn std::error::Error>)
 }
@@ -46,20 +85,21 @@ fn probe_file_duration_ms_blocking(
     file_path: &str,
 ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
     let path = Path::new(file_path);
-    let file = std::fs::File::open(path)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
     let mut hint = Hint::new();
-    if let Some(extension) = path.extension().and_then(|value| value.to_str()) {
-        hint.with_extension(extension);
-    }
+    let mss = if ncm::is_ncm_path(path) {
+        let (source, header) = ncm::open_file(path)?;
+        if let Some(format) = header.format() {
+            hint.with_extension(format);
+        }
+        MediaSourceStream::new(Box::new(source), Default::default())
+    } else {
+        if let Some(extension) = path.extension().and_then(|value| value.to_str()) {
+            hint.with_extension(extension);
+        }
+        MediaSourceStream::new(Box::new(std::fs::File::open(path)?), Default::default())
+    };
 
-    let probed = symphonia::default::get_probe().format(
-        &hint,
-        mss,
-        &FormatOptions::default(),
-        &MetadataOptions::default(),
-    )?;
-    let mut format = probed.format;
+    let mut format = registry::open_format(mss, &hint, None)?;
     let track = format
         .tracks()
         .iter()
@@ -69,9 +109,16 @@ fn probe_file_duration_ms_blocking(
     let time_base = track.codec_params.time_base;
     let sample_rate = track.codec_params.sample_rate;
     let start_ts = track.codec_params.start_ts;
+    // Opus 的 pre-skip 由解码器裁掉，不计入可播放时长。
+    let delay_ticks = frames_to_ticks(
+        opus::timeline_delay_frames(&track.codec_params),
+        time_base,
+        sample_rate,
+    );
 
     if let Some(frames) = track.codec_params.n_frames
-        && let Some(duration_ms) = duration_ticks_to_ms(frames, time_base, sample_rate)
+        && let Some(duration_ms) =
+            duration_ticks_to_ms(frames.saturating_sub(delay_ticks), time_base, sample_rate)
     {
         return Ok(duration_ms);
     }
@@ -95,8 +142,23 @@ fn probe_file_duration_ms_blocking(
         }
     }
 
-    duration_ticks_to_ms(end_ts.saturating_sub(start_ts), time_base, sample_rate)
-        .ok_or_else(|| "Audio duration is unavailable".into())
+    duration_ticks_to_ms(
+        end_ts.saturating_sub(start_ts).saturating_sub(delay_ticks),
+        time_base,
+        sample_rate,
+    )
+    .ok_or_else(|| "Audio duration is unavailable".into())
+}
+
+fn frames_to_ticks(frames: u64, time_base: Option<TimeBase>, sample_rate: Option<u32>) -> u64 {
+    match (time_base, sample_rate) {
+        (Some(time_base), Some(sample_rate)) if time_base.numer > 0 && sample_rate > 0 => {
+            let ticks = u128::from(frames) * u128::from(time_base.denom)
+                / (u128::from(time_base.numer) * u128::from(sample_rate));
+            ticks.min(u128::from(u64::MAX)) as u64
+        }
+        _ => frames,
+    }
 }
 
 fn duration_ticks_to_ms(
@@ -122,23 +184,27 @@ fn duration_ticks_to_ms(
 }
 
 fn probe_source(
-    source: Box<dyn MediaSource>,
+    mut source: Box<dyn MediaSource>,
     extension: Option<String>,
+    correction: Option<Box<dyn MediaSource>>,
 ) -> Result<AudioMetadata, Box<dyn std::error::Error + Send + Sync>> {
+    let byte_len = source.byte_len();
+    // 章节结构 Symphonia 不解析，先直接读原始字节；读完会回到开头。
+    let (chapters, stream_start) = if source.is_seekable() {
+        (
+            chapters::read_chapters(&mut *source),
+            seek_index::read_stream_start(&mut *source),
+        )
+    } else {
+        (Vec::new(), seek_index::StreamStart::default())
+    };
     let mss = MediaSourceStream::new(source, Default::default());
     let mut hint = Hint::new();
     if let Some(ext) = extension {
         hint.with_extension(&ext);
     }
 
-    let probed = symphonia::default::get_probe().format(
-        &hint,
-        mss,
-        &FormatOptions::default(),
-        &MetadataOptions::default(),
-    )?;
-
-    let mut format = probed.format;
+    let (mut format, tags) = registry::open_format_with_tags(mss, &hint, correction)?;
     let track = format
         .tracks()
         .iter()
@@ -147,22 +213,20 @@ fn probe_source(
 
     let track_id = track.id;
     let mut sr = track.codec_params.sample_rate;
-    let mut channels = track
-        .codec_params
-        .channels
-        .map(|channels| channels.count() as u16);
+    let mut channel_layout = track.codec_params.channels;
     let bits_per_sample = track.codec_params.bits_per_sample;
     let sample_format = track.codec_params.sample_format;
     let time_base = track.codec_params.time_base;
+    let n_frames = track.codec_params.n_frames;
+    let n_frames_estimated = stream_start.mp3_without_toc
+        && [CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3].contains(&track.codec_params.codec);
+    let dsd_rate = dsd::native_rate(&track.codec_params);
     let mut decoder =
-        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+        registry::codec_registry().make(&track.codec_params, &DecoderOptions::default())?;
     let decoder_params = decoder.codec_params();
     sr = sr.or(decoder_params.sample_rate);
-    channels = channels.or_else(|| {
-        decoder_params
-            .channels
-            .map(|channels| channels.count() as u16)
-    });
+    channel_layout = channel_layout.or(decoder_params.channels);
+    let channels = channel_layout.map(|channels| channels.count() as u16);
 
     let (sr, channels) = match (sr, channels) {
         (Some(sr), Some(channels)) if channels > 0 => (sr, channels),
@@ -177,6 +241,9 @@ fn probe_source(
                 },
             )?;
             decoder.reset();
+            channel_layout = channel_layout
+                .filter(|channels| !channels.is_empty())
+                .or(Some(decoded_spec.layout));
 
             (
                 sr.filter(|sample_rate| *sample_rate > 0)
@@ -188,22 +255,36 @@ fn probe_source(
         }
     };
 
-    Ok(AudioMetadata {
+    let mut meta = AudioMetadata {
         sample_rate: sr,
         channels,
+        channel_layout,
         bits_per_sample,
         sample_format,
+        dsd_rate,
         time_base,
         track_id,
+        n_frames,
+        n_frames_estimated,
+        byte_len,
+        audio_offset: stream_start.audio_offset,
+        tags,
+        chapters,
         decoder,
         format_reader: format,
-    })
+    };
+    if dsd_rate.is_some() {
+        // registry 构造的 DSD 解码器默认输出 PCM；是否改走 DoP 由播放端按设备决定。
+        dsd::apply_output_params(&mut meta, DsdOutput::Pcm)?;
+    }
+    Ok(meta)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct DecodedStreamSpec {
     sample_rate: u32,
     channels: u16,
+    layout: Channels,
 }
 
 fn decode_stream_spec(
@@ -228,6 +309,7 @@ fn decode_stream_spec(
                 return Ok(DecodedStreamSpec {
                     sample_rate: spec.rate,
                     channels,
+                    layout: spec.channels,
                 });
             }
             Err(SymphoniaError::DecodeError(_)) => continue,
@@ -263,12 +345,19 @@ pub(crate) fn handle_seek_if_needed(
 
         decoder.reset();
 
+        let requested_frame = (target.as_secs_f64() * sr as f64) as u64;
+        // 容器时间戳包含解码器裁掉的前导帧（Opus pre-skip），并且部分编解码器需要从
+        // 目标之前预滚一段才能收敛；多解出来的样本由下方的 trim 丢掉。
+        let delay_frames = opus::timeline_delay_frames(decoder.codec_params());
+        let seek_frame = (requested_frame + delay_frames)
+            .saturating_sub(opus::seek_preroll_frames(decoder.codec_params()));
+
         println!("[Seek-Check] 正在执行底层的 format.seek (网络 IO 可能在此阻塞)...");
         let start = std::time::Instant::now();
         let res = format.seek(
             SeekMode::Accurate,
             SeekTo::Time {
-                time: symphonia::core::units::Time::from(target.as_secs_f64()),
+                time: symphonia::core::units::Time::from(seek_frame as f64 / sr as f64),
                 track_id: Some(track_id),
             },
         );
@@ -284,11 +373,11 @@ pub(crate) fn handle_seek_if_needed(
         }
 
         state.buffered_frames.store(0, Ordering::SeqCst);
-        let requested_frame = (target.as_secs_f64() * sr as f64) as u64;
         // Symphonia Accurate seek 可能从请求位置之前的 packet/keyframe 开始解码。
         // 进度应该锚到用户请求的位置，同时把 actual..requested 之间的样本裁掉；
         // 只锚到 actual 会让 UI 看起来一致，但用户实际听到的仍不是 requested 位置。
-        let completion = seek_completion_plan(&res, requested_frame, sr, time_base, track_id);
+        let completion =
+            seek_completion_plan(&res, requested_frame, sr, time_base, track_id, delay_frames);
         state.decoder_done.store(false, Ordering::SeqCst);
         // 注意：此处不再二次拉高 discard_buffer。前置那次（见上）已负责排空旧的
         // seek 前样本；seek 完成后 ringbuf 里即将被写入的是目标位置的正常样本，再次置
@@ -298,6 +387,8 @@ pub(crate) fn handle_seek_if_needed(
         if state.is_terminating.load(Ordering::Relaxed) {
             return;
         }
+        state.sample_verifier.reset();
+        state.reset_binaural();
 
         let committed = state.commit_seek_completion_if_current(
             completion.anchor_frame,
@@ -342,6 +433,7 @@ pub(crate) fn seek_completion_plan(
     sample_rate: u32,
     time_base: Option<TimeBase>,
     expected_track_id: u32,
+    delay_frames: u64,
 ) -> SeekCompletionPlan {
     let actual_frame = seek_actual_frame(
         seeked,
@@ -349,6 +441,7 @@ pub(crate) fn seek_completion_plan(
         sample_rate,
         time_base,
         expected_track_id,
+        delay_frames,
     );
     let trim_until_frame = actual_frame
         .filter(|actual| *actual < requested_frame)
@@ -367,6 +460,7 @@ fn seek_actual_frame(
     sample_rate: u32,
     time_base: Option<TimeBase>,
     expected_track_id: u32,
+    delay_frames: u64,
 ) -> Option<u64> {
     match seeked {
         Ok(result) => {
@@ -374,7 +468,9 @@ fn seek_actual_frame(
                 return None;
             }
 
-            let Some(actual) = timestamp_to_frame(result.actual_ts, sample_rate, time_base) else {
+            let Some(actual) = timestamp_to_frame(result.actual_ts, sample_rate, time_base)
+                .map(|frame| frame.saturating_sub(delay_frames))
+            else {
                 return None;
             };
 
@@ -390,7 +486,11 @@ fn seek_actual_frame(
     }
 }
 
-fn timestamp_to_frame(ts: u64, sample_rate: u32, time_base: Option<TimeBase>) -> Option<u64> {
+pub(crate) fn timestamp_to_frame(
+    ts: u64,
+    sample_rate: u32,
+    time_base: Option<TimeBase>,
+) -> Option<u64> {
     let time_base = time_base?;
     if time_base.numer == 0 || time_base.denom == 0 {
         return None;
@@ -468,18 +568,24 @@ where
             if packet.track_id() != track_id {
                 return true;
             }
-            match decoder.decode(&packet) {
+            let delay_frames = opus::timeline_delay_frames(decoder.codec_params());
+            let decode_started_at = Instant::now();
+            let decoded = decoder.decode(&packet);
+            state
+                .pipeline_stats
+                .record_decode(decode_started_at.elapsed());
+            match decoded {
                 Ok(decoded) => {
                     let spec = *decoded.spec();
                     let num_frames = decoded.frames();
                     if num_frames == 0 {
                         return true;
                     }
+                    state.record_decoded_packet(packet.data.len(), num_frames);
 
-                    let mut sample_buf = SampleBuffer::<S>::new(num_frames as u64, spec);
-                    sample_buf.copy_interleaved_ref(decoded);
                     let trim = seek_packet_trim(
-                        timestamp_to_frame(packet.ts(), sample_rate, time_base),
+                        timestamp_to_frame(packet.ts(), sample_rate, time_base)
+                            .map(|frame| frame.saturating_sub(delay_frames)),
                         num_frames,
                         state.trim_until_frame.load(Ordering::Relaxed),
                     );
@@ -488,17 +594,50 @@ where
                         state.clear_trim();
                     }
 
+                    // 双耳渲染：多声道卷积成双声道后再写入 ring buffer，不做逐样本校验。
+                    if state.is_binaural() {
+                        if trim.keep_frames > 0 {
+                            let mut source_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
+                            source_buf.copy_interleaved_ref(decoded);
+                            let skip_samples = trim
+                                .skip_frames
+                                .saturating_mul(spec.channels.count())
+                                .min(source_buf.samples().len());
+                            let rendered = state
+                                .render_binaural(&source_buf.samples()[skip_samples..])
+                                .unwrap_or_default();
+                            let converted: Vec<S> =
+                                rendered.into_iter().map(S::from_sample).collect();
+                            push_samples_blocking(producer, &converted, state);
+                        }
+                        return true;
+                    }
+
+                    // 校验模式下另存一份 f64 形式的原始样本，与输出回调拿到的样本比对。
+                    let source_buf = state.sample_verifier.is_enabled().then(|| {
+                        let mut source_buf = SampleBuffer::<f64>::new(num_frames as u64, spec);
+                        source_buf.copy_interleaved_ref(decoded.clone());
+                        source_buf
+                    });
+                    let mut sample_buf = SampleBuffer::<S>::new(num_frames as u64, spec);
+                    sample_buf.copy_interleaved_ref(decoded);
+
                     if trim.keep_frames > 0 {
                         let channels = spec.channels.count();
                         let skip_samples = trim
                             .skip_frames
                             .saturating_mul(channels)
                             .min(sample_buf.samples().len());
-                        push_samples_blocking::<S, _>(
+                        let written = push_samples_blocking::<S, _>(
                             producer,
                             &sample_buf.samples()[skip_samples..],
                             state,
                         );
+                        if let Some(source_buf) = &source_buf {
+                            state.sample_verifier.record_source(
+                                &source_buf.samples()[skip_samples..skip_samples + written],
+                            );
+                        }
                     }
                 }
                 Err(symphonia::core::errors::Error::DecodeError(e)) => {
@@ -555,7 +694,127 @@ fn fail_playback_after_stream_error(
     }
 }
 
-fn push_samples_blocking<S, P>(producer: &mut P, samples: &[S], state: &SharedState)
+/// 拖动预览要读的音轨：容器、解码器与音轨参数。
+pub(crate) struct ScrubTrack<'a> {
+    pub(crate) format: &'a mut dyn FormatReader,
+    pub(crate) decoder: &'a mut dyn Decoder,
+    pub(crate) track_id: u32,
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: usize,
+    pub(crate) time_base: Option<TimeBase>,
+}
+
+/// 拖动预览：上一个片段快播完时取最新的预览位置，粗略 seek 过去，解码一小段并加窗后写入
+/// ring buffer；期间到达的位置只保留最后一个。返回是否写入了片段。
+pub(crate) fn play_scrub_snippet_if_needed<S, P>(
+    state: &SharedState,
+    track: ScrubTrack<'_>,
+    producer: &mut P,
+) -> bool
+where
+    S: ConvertibleSample + Copy,
+    P: Producer<Item = S>,
+{
+    let ScrubTrack {
+        format,
+        decoder,
+        track_id,
+        sample_rate: sr,
+        channels,
+        time_base,
+    } = track;
+    let frames_of = |duration: Duration| (duration.as_secs_f64() * sr as f64) as usize;
+    let ring_channels = if state.is_binaural() { 2 } else { channels };
+    if producer.occupied_len() > frames_of(SCRUB_REFILL_THRESHOLD) * ring_channels {
+        return false;
+    }
+    let Some(target) = state.take_scrub_request() else {
+        return false;
+    };
+
+    decoder.reset();
+    let seeked = format.seek(
+        SeekMode::Coarse,
+        SeekTo::Time {
+            time: symphonia::core::units::Time::from(target.as_secs_f64()),
+            track_id: Some(track_id),
+        },
+    );
+    let frame = match seeked {
+        Ok(seeked) => timestamp_to_frame(seeked.actual_ts, sr, time_base)
+            .unwrap_or_else(|| frames_of(target) as u64),
+        Err(err) => {
+            eprintln!("[Scrub] 预览 seek 失败: {err}");
+            return false;
+        }
+    };
+
+    let snippet_samples = frames_of(SCRUB_SNIPPET) * channels;
+    let mut samples: Vec<f32> = Vec::with_capacity(snippet_samples);
+    while samples.len() < snippet_samples {
+        if state.is_terminating.load(Ordering::Relaxed) || !state.scrubbing.load(Ordering::Relaxed)
+        {
+            return false;
+        }
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(_) => break,
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                if decoded.frames() == 0 || decoded.spec().channels.count() != channels {
+                    continue;
+                }
+                let mut sample_buf =
+                    SampleBuffer::<f32>::new(decoded.frames() as u64, *decoded.spec());
+                sample_buf.copy_interleaved_ref(decoded);
+                samples.extend_from_slice(sample_buf.samples());
+            }
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(_) => break,
+        }
+    }
+    samples.truncate(snippet_samples);
+    if samples.is_empty() {
+        return false;
+    }
+    apply_scrub_window(&mut samples, channels, frames_of(SCRUB_FADE));
+    // 每个片段各自从静音开始卷积，不带上一个片段的尾巴。
+    state.reset_binaural();
+    let samples = state.render_binaural(&samples).unwrap_or(samples);
+
+    state.current_frame.store(frame, Ordering::SeqCst);
+    state.reset_playback_clock(frame);
+    // 预览片段很短，不能像 seek 之后那样等缓冲满 1 秒才开始输出。
+    state.waiting_for_seek.store(false, Ordering::SeqCst);
+    let converted: Vec<S> = samples.into_iter().map(S::from_sample).collect();
+    push_samples_blocking(producer, &converted, state);
+    true
+}
+
+/// 片段两端各 `fade_frames` 帧升余弦淡入淡出，中间保持原样。
+fn apply_scrub_window(samples: &mut [f32], channels: usize, fade_frames: usize) {
+    let frames = samples.len() / channels.max(1);
+    let fade_frames = fade_frames.min(frames / 2);
+    if fade_frames == 0 {
+        return;
+    }
+    for index in 0..fade_frames {
+        let gain = 0.5 - 0.5 * (std::f32::consts::PI * index as f32 / fade_frames as f32).cos();
+        let head = index * channels;
+        let tail = (frames - 1 - index) * channels;
+        for channel in 0..channels {
+            samples[head + channel] *= gain;
+            samples[tail + channel] *= gain;
+        }
+    }
+}
+
+/// 返回实际写入的样本数；终止或收到新的 seek 请求时提前返回。
+fn push_samples_blocking<S, P>(producer: &mut P, samples: &[S], state: &SharedState) -> usize
 where
     S: Sample + Copy,
     P: Producer<Item = S>,
@@ -569,7 +828,7 @@ where
 
         if state.has_seek_request.load(Ordering::Relaxed) {
             println!("[Seek-Check] 检测到新 Seek 请求，中断当前数据推送");
-            return;
+            return written;
         }
 
         // 暂停时输出回调不再消费 ringbuf。若这里仍死等“有空位”，
@@ -595,18 +854,16 @@ where
             std::thread::sleep(Duration::from_millis(10));
         }
     }
+    written
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::audio::state::PlaybackClock;
     use std::borrow::Cow;
-    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64};
-    use std::sync::{Arc, Mutex};
+    use std::sync::Arc;
     use std::time::Instant;
     use symphonia::core::audio::{AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec};
-    use tokio::sync::Notify;
 
     #[test]
     fn converts_track_ticks_to_milliseconds() {
@@ -621,24 +878,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn scrub_window_fades_both_ends_and_keeps_the_middle() {
+        let mut samples = vec![1.0f32; 2 * 100];
+        apply_scrub_window(&mut samples, 2, 10);
+
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(samples[1], 0.0);
+        assert_eq!(samples[198], 0.0);
+        assert!(samples[10] > 0.0 && samples[10] < 1.0);
+        assert!(samples[2 * 5] < samples[2 * 9]);
+        assert!(samples[20..180].iter().all(|sample| *sample == 1.0));
+    }
+
     fn create_state() -> SharedState {
-        SharedState {
-            is_paused: AtomicBool::new(false),
-            current_frame: AtomicU64::new(48_000),
-            playback_clock: Mutex::new(PlaybackClock::new()),
-            trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
-            sample_rate: AtomicU32::new(48_000),
-            has_seek_request: AtomicBool::new(true),
-            seek_request: Mutex::new(Some(Duration::from_secs(1))),
-            is_terminating: AtomicBool::new(false),
-            discard_buffer: AtomicBool::new(false),
-            is_discarding_buffer: AtomicBool::new(false),
-            decoder_done: AtomicBool::new(false),
-            is_finished: AtomicBool::new(false),
-            finish_notify: Notify::new(),
-            buffered_frames: AtomicU64::new(12_000),
-            waiting_for_seek: AtomicBool::new(true),
-        }
+        let state = SharedState::new(48_000);
+        state.current_frame.store(48_000, Ordering::SeqCst);
+        state.has_seek_request.store(true, Ordering::SeqCst);
+        *state.seek_request.lock().unwrap() = Some(Duration::from_secs(1));
+        state.buffered_frames.store(12_000, Ordering::SeqCst);
+        state.waiting_for_seek.store(true, Ordering::SeqCst);
+        state
     }
 
     #[test]
@@ -790,7 +1050,7 @@ mod tests {
         let seeked: Result<SeekedTo, SymphoniaError> =
             Ok(seeked_to(actual_frame, requested_frame, 0));
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 0);
         assert_eq!(completion.anchor_frame, requested_frame);
         assert_eq!(completion.actual_frame, Some(actual_frame));
         assert_eq!(completion.trim_until_frame, Some(requested_frame));
@@ -827,12 +1087,37 @@ mod tests {
             "test must prove timestamp units differ from sample frames"
         );
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 0);
         assert_eq!(completion.anchor_frame, requested_frame);
         assert_eq!(completion.actual_frame, Some(expected_actual_frame));
         assert_eq!(completion.trim_until_frame, Some(requested_frame));
     }
 
+    #[test]
+    fn seek_completion_excludes_decoder_trimmed_leading_frames() {
+        let sr: u32 = 48_000;
+        let time_base = TimeBase::new(1, sr);
+        let requested_frame = 48_000;
+        // Opus：容器时间戳含 312 帧 pre-skip，且 seek 预滚了 3840 帧。
+        let actual_ts = requested_frame + 312 - 3_840;
+        let seeked: Result<SeekedTo, SymphoniaError> = Ok(seeked_to(actual_ts, actual_ts, 0));
+
+        let completion =
+            seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 312);
+        assert_eq!(completion.actual_frame, Some(requested_frame - 3_840));
+        assert_eq!(completion.trim_until_frame, Some(requested_frame));
+    }
+
+    #[test]
+    fn opus_duration_excludes_pre_skip() {
+        let path = std::env::temp_dir().join(format!("opus_duration_{}.opus", std::process::id()));
+        std::fs::write(&path, crate::audio::opus::tests::ogg_opus_bytes(50, 480, 0)).unwrap();
+
+        let duration = probe_file_duration_ms_blocking(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(duration, 990);
+    }
+
     /// Accurate seek 保证 actual <= requested；若 demuxer 在流式/部分下载场景里返回了一个
     /// 落在请求 *之后* 的异常 actual，不据此裁剪，避免丢掉目标之后的有效样本。
     #[test]
@@ -844,7 +1129,7 @@ mod tests {
         let seeked: Result<SeekedTo, SymphoniaError> =
             Ok(seeked_to(requested_frame + 10_000, requested_frame, 0));
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 0);
         assert_eq!(completion.anchor_frame, requested_frame);
         assert_eq!(completion.actual_frame, None);
         assert_eq!(completion.trim_until_frame, None);
@@ -860,7 +1145,7 @@ mod tests {
             std::io::Error::new(std::io::ErrorKind::Other, "boom"),
         ));
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 0);
         assert_eq!(completion.anchor_frame, requested_frame);
         assert_eq!(completion.actual_frame, None);
         assert_eq!(completion.trim_until_frame, None);
@@ -873,7 +1158,7 @@ mod tests {
         let seeked: Result<SeekedTo, SymphoniaError> =
             Ok(seeked_to(requested_frame - 1024, requested_frame, 0));
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, None, 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, None, 0, 0);
         assert_eq!(completion.anchor_frame, requested_frame);
         assert_eq!(completion.actual_frame, None);
         assert_eq!(completion.trim_until_frame, None);
@@ -887,7 +1172,7 @@ mod tests {
         let seeked: Result<SeekedTo, SymphoniaError> =
             Ok(seeked_to(requested_frame - 1024, requested_frame, 9));
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 0);
         assert_eq!(completion.anchor_frame, requested_frame);
         assert_eq!(completion.actual_frame, None);
         assert_eq!(completion.trim_until_frame, None);
@@ -965,7 +1250,7 @@ mod tests {
         let seeked: Result<SeekedTo, SymphoniaError> =
             Ok(seeked_to(actual_frame, requested_frame, 0));
 
-        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0);
+        let completion = seek_completion_plan(&seeked, requested_frame, sr, Some(time_base), 0, 0);
         state
             .current_frame
             .store(completion.anchor_frame, Ordering::SeqCst);
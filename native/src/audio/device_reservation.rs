@@ -1,6 +1,7 @@
 #[cfg(target_os = "linux")]
 mod linux {
     use crate::audio::backend;
+    use crate::audio::bit_perfect::ReservationStatus;
     use std::os::unix::process::CommandExt;
     use std::process::{Child, Command, Stdio};
     use std::time::Duration;
@@ -13,6 +14,7 @@ mod linux {
     }
 
     pub(crate) struct DeviceReservation {
+        name: String,
         child: Child,
     }
 
@@ -54,7 +56,18 @@ mod linux {
                 .into());
             }
 
-            Ok(Self { child })
+            Ok(Self {
+                name: reservation_name,
+                child,
+            })
+        }
+
+        /// pw-reserve 被其他程序抢走预留或意外退出后，`held` 变为 false。
+        pub(crate) fn status(&mut self) -> ReservationStatus {
+            ReservationStatus {
+                name: self.name.clone(),
+                held: matches!(self.child.try_wait(), Ok(None)),
+            }
         }
 
         fn release(&mut self) {
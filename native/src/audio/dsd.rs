@@ -0,0 +1,957 @@
+use crate::audio::ape::{channels_from_count, read_up_to};
+use crate::audio::decoder::AudioMetadata;
+use std::io::{Seek, SeekFrom};
+use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia::core::codecs::{
+    CodecDescriptor, CodecParameters, CodecType, Decoder, DecoderOptions, FinalizeResult,
+    decl_codec_type,
+};
+use symphonia::core::errors::{
+    Error, Result, SeekErrorKind, decode_error, seek_error, unsupported_error,
+};
+use symphonia::core::formats::{
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+};
+use symphonia::core::io::{MediaSourceStream, ReadBytes};
+use symphonia::core::meta::{Metadata, MetadataLog};
+use symphonia::core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia::core::sample::SampleFormat as SymphoniaSampleFormat;
+use symphonia::core::units::TimeBase;
+use symphonia::core::{support_codec, support_format};
+
+/// 1bit DSD 流；packet 内按声道平铺，每字节高位在前（时间上更早）。
+pub(crate) const CODEC_TYPE_DSD: CodecType = decl_codec_type(b"dsd");
+
+/// DoP 载波采样率：DSD64/DSD128 每 16 个 DSD 位封装成一个 24bit PCM 帧。
+pub(crate) const DOP_CARRIER_RATES: [u32; 2] = [176_400, 352_800];
+
+/// DoP 帧高 8 位的标记字节，逐帧交替。
+const DOP_MARKERS: [u32; 2] = [0x05, 0xfa];
+/// DSD 静音图样（0/1 各半）。
+const DSD_SILENCE: u8 = 0x69;
+/// DFF 数据按字节交错，每个 packet 每声道取这么多字节。
+const DFF_PACKET_BYTES: usize = 4096;
+/// DSF 的 block 大小规范值为 4096；放宽上限只为拒绝明显损坏的头。
+const MAX_DSF_BLOCK_BYTES: u32 = 1 << 20;
+/// PCM 转换的最高输出采样率；更高的 DSD 倍率加大抽取比。
+const MAX_PCM_RATE: u32 = 352_800;
+/// 每个抽取比对应的 FIR 长度（按 DSD 位计）。
+const TAPS_PER_DECIMATION: usize = 64;
+/// 通带边缘相对输出采样率的位置，余下到 Nyquist 的部分作过渡带。
+const CUTOFF: f64 = 0.445;
+const KAISER_BETA: f64 = 9.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DsdOutput {
+    /// 解码端抽取成 f32 PCM。
+    Pcm,
+    /// 原样封装成 DoP 帧，交给支持 DoP 的 DAC 还原。
+    Dop,
+}
+
+pub(crate) fn native_rate(params: &CodecParameters) -> Option<u32> {
+    (params.codec == CODEC_TYPE_DSD)
+        .then_some(params.sample_rate)
+        .flatten()
+}
+
+fn decimation(dsd_rate: u32) -> u32 {
+    let mut factor = 32;
+    while dsd_rate / factor > MAX_PCM_RATE {
+        factor *= 2;
+    }
+    factor
+}
+
+/// DSD→PCM 的输出采样率：DSD64 为 88.2kHz，DSD128 为 176.4kHz，最高 352.8kHz。
+pub(crate) fn pcm_rate(dsd_rate: u32) -> u32 {
+    dsd_rate / decimation(dsd_rate)
+}
+
+pub(crate) fn dop_carrier_rate(dsd_rate: u32) -> Option<u32> {
+    Some(dsd_rate / 16)
+        .filter(|rate| dsd_rate.is_multiple_of(16) && DOP_CARRIER_RATES.contains(rate))
+}
+
+/// 按输出方式改写交给输出端的 PCM 参数；`dsd_rate` 保持原生 DSD 采样率用于展示。
+pub(crate) fn apply_output_params(
+    meta: &mut AudioMetadata,
+    output: DsdOutput,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let dsd_rate = meta.dsd_rate.ok_or("not a DSD stream")?;
+    match output {
+        DsdOutput::Pcm => {
+            meta.sample_rate = pcm_rate(dsd_rate);
+            meta.bits_per_sample = None;
+            meta.sample_format = Some(SymphoniaSampleFormat::F32);
+        }
+        DsdOutput::Dop => {
+            meta.sample_rate = dop_carrier_rate(dsd_rate).ok_or_else(|| {
+                format!(
+                    "DSD{} 超出 DoP 支持范围（仅支持 DSD64/DSD128）",
+                    dsd_rate / 44_100
+                )
+            })?;
+            meta.bits_per_sample = Some(24);
+            meta.sample_format = Some(SymphoniaSampleFormat::S24);
+        }
+    }
+    Ok(())
+}
+
+/// 切换 DSD 输出方式：重建解码器并同步 PCM 参数，需在开始解码前调用。
+pub(crate) fn configure_output(
+    meta: &mut AudioMetadata,
+    output: DsdOutput,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    apply_output_params(meta, output)?;
+    let params = meta.decoder.codec_params().clone();
+    meta.decoder = Box::new(DsdDecoder::with_output(&params, output)?);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    /// DSF：每声道一个 block 依次排列，`lsb_first` 时字节内低位在前。
+    Dsf { lsb_first: bool },
+    /// DSDIFF：按字节交错，高位在前。
+    Dff,
+}
+
+/// DSF（Sony）与 DFF（Philips DSDIFF）容器，统一成按声道平铺、高位在前的 packet。
+pub(crate) struct DsdReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    container: Container,
+    channels: usize,
+    data_start: u64,
+    /// 每个 packet 每声道的字节数。
+    block_bytes: usize,
+    total_bits: u64,
+    next_block: u64,
+    raw: Vec<u8>,
+}
+
+struct StreamInfo {
+    container: Container,
+    channels: u16,
+    dsd_rate: u32,
+    data_start: u64,
+    block_bytes: usize,
+    total_bits: u64,
+}
+
+fn parse_dsf(source: &mut MediaSourceStream) -> Result<StreamInfo> {
+    // "DSD " 头：块长、文件长、元数据偏移，共 28 字节。
+    source.ignore_bytes(24)?;
+    let mut id = [0u8; 4];
+    source.read_buf_exact(&mut id)?;
+    if &id != b"fmt " {
+        return decode_error("dsd: missing DSF fmt chunk");
+    }
+    let fmt_len = source.read_u64()?;
+    if fmt_len < 52 {
+        return decode_error("dsd: DSF fmt chunk is too short");
+    }
+    let _version = source.read_u32()?;
+    let format_id = source.read_u32()?;
+    let _channel_type = source.read_u32()?;
+    let channels = source.read_u32()?;
+    let dsd_rate = source.read_u32()?;
+    let bits = source.read_u32()?;
+    let total_bits = source.read_u64()?;
+    let block_bytes = source.read_u32()?;
+    source.ignore_bytes(fmt_len - 48)?;
+    if format_id != 0 {
+        return unsupported_error("dsd: only raw DSF streams are supported");
+    }
+    if !matches!(bits, 1 | 8) {
+        return decode_error("dsd: invalid DSF bit order");
+    }
+    if block_bytes == 0 || block_bytes > MAX_DSF_BLOCK_BYTES {
+        return decode_error("dsd: invalid DSF block size");
+    }
+
+    source.read_buf_exact(&mut id)?;
+    if &id != b"data" {
+        return decode_error("dsd: missing DSF data chunk");
+    }
+    let _data_len = source.read_u64()?;
+    Ok(StreamInfo {
+        container: Container::Dsf {
+            lsb_first: bits == 1,
+        },
+        channels: u16::try_from(channels).unwrap_or(0),
+        dsd_rate,
+        data_start: source.pos(),
+        block_bytes: block_bytes as usize,
+        total_bits,
+    })
+}
+
+fn parse_dff(source: &mut MediaSourceStream) -> Result<StreamInfo> {
+    source.ignore_bytes(8)?;
+    let mut id = [0u8; 4];
+    source.read_buf_exact(&mut id)?;
+    if &id != b"DSD " {
+        return unsupported_error("dsd: FRM8 form is not DSD");
+    }
+
+    let mut dsd_rate = 0;
+    let mut channels = 0;
+    loop {
+        source.read_buf_exact(&mut id)?;
+        let len = source.read_be_u64()?;
+        match &id {
+            b"PROP" => {
+                source.read_buf_exact(&mut id)?;
+                if &id != b"SND " {
+                    source.ignore_bytes(padded(len).saturating_sub(4))?;
+                    continue;
+                }
+                let mut remaining = len.saturating_sub(4);
+                while remaining >= 12 {
+                    source.read_buf_exact(&mut id)?;
+                    let sub_len = source.read_be_u64()?;
+                    let mut body = padded(sub_len);
+                    remaining = remaining.saturating_sub(12 + body);
+                    match &id {
+                        b"FS  " => {
+                            dsd_rate = source.read_be_u32()?;
+                            body = body.saturating_sub(4);
+                        }
+                        b"CHNL" => {
+                            channels = source.read_be_u16()?;
+                            body = body.saturating_sub(2);
+                        }
+                        b"CMPR" => {
+                            source.read_buf_exact(&mut id)?;
+                            if &id != b"DSD " {
+                                return unsupported_error(
+                                    "dsd: compressed DST streams are not supported",
+                                );
+                            }
+                            body = body.saturating_sub(4);
+                        }
+                        _ => {}
+                    }
+                    source.ignore_bytes(body)?;
+                }
+                source.ignore_bytes(remaining)?;
+            }
+            b"DSD " => {
+                if channels == 0 {
+                    return decode_error("dsd: DFF sound data before channel info");
+                }
+                return Ok(StreamInfo {
+                    container: Container::Dff,
+                    channels,
+                    dsd_rate,
+                    data_start: source.pos(),
+                    block_bytes: DFF_PACKET_BYTES,
+                    total_bits: len / u64::from(channels) * 8,
+                });
+            }
+            b"DST " => return unsupported_error("dsd: compressed DST streams are not supported"),
+            _ => source.ignore_bytes(padded(len))?,
+        }
+    }
+}
+
+/// IFF 块按偶数字节对齐。
+fn padded(len: u64) -> u64 {
+    len + (len & 1)
+}
+
+impl DsdReader {
+    fn block_bits(&self) -> u64 {
+        self.block_bytes as u64 * 8
+    }
+}
+
+impl QueryDescriptor for DsdReader {
+    fn query() -> &'static [Descriptor] {
+        &[
+            support_format!(
+                "dsf",
+                "DSD Stream File",
+                &["dsf"],
+                &["audio/dsf", "audio/x-dsf"],
+                &[b"DSD "]
+            ),
+            support_format!(
+                "dff",
+                "DSD Interchange File Format",
+                &["dff"],
+                &["audio/dff", "audio/x-dff"],
+                &[b"FRM8"]
+            ),
+        ]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for DsdReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut marker = [0u8; 4];
+        source.read_buf_exact(&mut marker)?;
+        let info = match &marker {
+            b"DSD " => parse_dsf(&mut source)?,
+            b"FRM8" => parse_dff(&mut source)?,
+            _ => return unsupported_error("dsd: not a DSF or DFF file"),
+        };
+        if info.channels == 0 || info.dsd_rate == 0 || !info.dsd_rate.is_multiple_of(8) {
+            return unsupported_error("dsd: unsupported stream layout");
+        }
+        if info.total_bits == 0 {
+            return decode_error("dsd: empty stream");
+        }
+
+        let block_bits = info.block_bytes as u64 * 8;
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_DSD)
+            .with_sample_rate(info.dsd_rate)
+            .with_time_base(TimeBase::new(1, info.dsd_rate))
+            .with_n_frames(info.total_bits)
+            .with_bits_per_coded_sample(1)
+            .with_channels(channels_from_count(info.channels))
+            .with_max_frames_per_packet(block_bits);
+
+        Ok(Self {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            container: info.container,
+            channels: usize::from(info.channels),
+            data_start: info.data_start,
+            block_bytes: info.block_bytes,
+            total_bits: info.total_bits,
+            next_block: 0,
+            raw: Vec::new(),
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => self.tracks[0]
+                .codec_params
+                .time_base
+                .map_or(0, |time_base| time_base.calc_timestamp(time)),
+        };
+        if ts >= self.total_bits {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+        self.next_block = ts / self.block_bits();
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts: ts,
+            actual_ts: self.next_block * self.block_bits(),
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let ts = self.next_block * self.block_bits();
+        if ts >= self.total_bits {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        let bits = (self.total_bits - ts).min(self.block_bits());
+        let bytes = bits.div_ceil(8) as usize;
+        let offset = self.data_start + self.next_block * (self.block_bytes * self.channels) as u64;
+        if self.reader.pos() != offset {
+            self.reader.seek(SeekFrom::Start(offset))?;
+        }
+
+        // DSF 末尾 block 补零到整块，DFF 只读有效字节。
+        let read_len = match self.container {
+            Container::Dsf { .. } => self.block_bytes * self.channels,
+            Container::Dff => bytes * self.channels,
+        };
+        self.raw.resize(read_len, 0);
+        let filled = read_up_to(&mut self.reader, &mut self.raw)?;
+        if filled == 0 {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        // 文件被截断时用静音补齐，避免把上一块的残留数据当成音频。
+        self.raw[filled..].fill(DSD_SILENCE);
+
+        let mut data = vec![0u8; bytes * self.channels];
+        for (channel, out) in data.chunks_exact_mut(bytes).enumerate() {
+            match self.container {
+                Container::Dsf { lsb_first } => {
+                    let block = &self.raw[channel * self.block_bytes..][..bytes];
+                    for (dst, src) in out.iter_mut().zip(block) {
+                        *dst = if lsb_first { src.reverse_bits() } else { *src };
+                    }
+                }
+                Container::Dff => {
+                    for (dst, src) in out
+                        .iter_mut()
+                        .zip(self.raw[channel..].iter().step_by(self.channels))
+                    {
+                        *dst = *src;
+                    }
+                }
+            }
+        }
+
+        self.next_block += 1;
+        Ok(Packet::new_from_boxed_slice(
+            0,
+            ts,
+            bits,
+            data.into_boxed_slice(),
+        ))
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// 1bit 流的 FIR 低通抽取器：滤波器按 8 个系数一组预先展开成 256 项查找表，
+/// 每个输出样本只需对窗口内的每个字节查一次表。
+struct Decimator {
+    tables: Vec<[f32; 256]>,
+    /// 两个输出样本之间的输入字节数。
+    step: usize,
+    history: Vec<Vec<u8>>,
+    /// 自上一个输出样本以来已消耗的字节数。
+    phase: usize,
+    window: Vec<u8>,
+}
+
+impl Decimator {
+    fn new(decimation: u32, channels: usize) -> Self {
+        let taps = decimation as usize * TAPS_PER_DECIMATION;
+        let coeffs = lowpass(taps, CUTOFF / f64::from(decimation));
+        let tables = coeffs
+            .chunks_exact(8)
+            .map(|group| {
+                let mut table = [0f32; 256];
+                for (byte, entry) in table.iter_mut().enumerate() {
+                    let sum: f64 = group
+                        .iter()
+                        .enumerate()
+                        .map(|(bit, coeff)| {
+                            if byte & (0x80 >> bit) != 0 {
+                                *coeff
+                            } else {
+                                -*coeff
+                            }
+                        })
+                        .sum();
+                    *entry = sum as f32;
+                }
+                table
+            })
+            .collect::<Vec<_>>();
+        let history = vec![vec![DSD_SILENCE; tables.len()]; channels];
+        Self {
+            tables,
+            step: decimation as usize / 8,
+            history,
+            phase: 0,
+            window: Vec::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        for history in &mut self.history {
+            history.fill(DSD_SILENCE);
+        }
+        self.phase = 0;
+    }
+
+    /// 处理一个声道的新字节，返回产出的样本数；所有声道需按相同长度依次调用，
+    /// 处理完一整组后再调用 `advance`。
+    fn process(&mut self, channel: usize, data: &[u8], out: &mut [f32]) -> usize {
+        let taps = self.tables.len();
+        self.window.clear();
+        self.window.extend_from_slice(&self.history[channel]);
+        self.window.extend_from_slice(data);
+
+        let mut produced = 0;
+        let mut end = taps + self.step - self.phase;
+        while end <= self.window.len() && produced < out.len() {
+            let window = &self.window[end - taps..end];
+            out[produced] = self
+                .tables
+                .iter()
+                .zip(window)
+                .map(|(table, byte)| table[usize::from(*byte)])
+                .sum();
+            produced += 1;
+            end += self.step;
+        }
+
+        let keep = self.window.len() - taps;
+        self.history[channel].copy_from_slice(&self.window[keep..]);
+        produced
+    }
+
+    fn advance(&mut self, bytes: usize) {
+        self.phase = (self.phase + bytes) % self.step;
+    }
+
+    fn output_len(&self, bytes: usize) -> usize {
+        (self.phase + bytes) / self.step
+    }
+}
+
+/// Kaiser 窗 sinc 低通，`cutoff` 以输入采样率为单位，直流增益归一。
+fn lowpass(taps: usize, cutoff: f64) -> Vec<f64> {
+    let center = (taps - 1) as f64 / 2.0;
+    let norm = bessel_i0(KAISER_BETA);
+    let mut coeffs = (0..taps)
+        .map(|n| {
+            let x = n as f64 - center;
+            let sinc = if x == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * std::f64::consts::PI * cutoff * x).sin() / (std::f64::consts::PI * x)
+            };
+            let r = x / center;
+            sinc * bessel_i0(KAISER_BETA * (1.0 - r * r).max(0.0).sqrt()) / norm
+        })
+        .collect::<Vec<_>>();
+    let sum: f64 = coeffs.iter().sum();
+    for coeff in &mut coeffs {
+        *coeff /= sum;
+    }
+    coeffs
+}
+
+/// 第一类零阶修正 Bessel 函数，用于 Kaiser 窗。
+pub(crate) fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..64 {
+        term *= half / k as f64;
+        let squared = term * term;
+        sum += squared;
+        if squared < sum * 1e-17 {
+            break;
+        }
+    }
+    sum
+}
+
+enum Output {
+    Pcm {
+        decimator: Decimator,
+        buf: AudioBuffer<f32>,
+    },
+    Dop {
+        buf: AudioBuffer<i32>,
+    },
+}
+
+/// DSD 解码器：默认抽取成 PCM；`DsdOutput::Dop` 时把 DSD 位原样封装成 DoP 帧，
+/// 24bit 数据放在 i32 高位，输出端按 I24/I32 直通。
+pub(crate) struct DsdDecoder {
+    params: CodecParameters,
+    channels: usize,
+    output: Output,
+}
+
+impl DsdDecoder {
+    pub(crate) fn with_output(params: &CodecParameters, output: DsdOutput) -> Result<Self> {
+        let (Some(dsd_rate), Some(channels)) = (params.sample_rate, params.channels) else {
+            return unsupported_error("dsd: missing stream parameters");
+        };
+        let max_bits = params
+            .max_frames_per_packet
+            .unwrap_or(DFF_PACKET_BYTES as u64 * 8);
+        let output = match output {
+            DsdOutput::Pcm => {
+                let decimation = decimation(dsd_rate);
+                let spec = SignalSpec::new(dsd_rate / decimation, channels);
+                Output::Pcm {
+                    decimator: Decimator::new(decimation, channels.count()),
+                    buf: AudioBuffer::new(max_bits / u64::from(decimation) + 1, spec),
+                }
+            }
+            DsdOutput::Dop => {
+                let Some(carrier) = dop_carrier_rate(dsd_rate) else {
+                    return unsupported_error("dsd: DoP needs DSD64 or DSD128");
+                };
+                Output::Dop {
+                    buf: AudioBuffer::new(max_bits / 16, SignalSpec::new(carrier, channels)),
+                }
+            }
+        };
+        Ok(Self {
+            params: params.clone(),
+            channels: channels.count(),
+            output,
+        })
+    }
+}
+
+impl Decoder for DsdDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        Self::with_output(params, DsdOutput::Pcm)
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(
+            CODEC_TYPE_DSD,
+            "dsd",
+            "Direct Stream Digital"
+        )]
+    }
+
+    fn reset(&mut self) {
+        // DoP 标记按绝对帧序号计算，seek 后无需保留状态。
+        if let Output::Pcm { decimator, .. } = &mut self.output {
+            decimator.reset();
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let data = packet.buf();
+        if !data.len().is_multiple_of(self.channels) {
+            return decode_error("dsd: packet is not channel aligned");
+        }
+        let bytes = data.len() / self.channels;
+
+        match &mut self.output {
+            Output::Pcm { decimator, buf } => {
+                buf.clear();
+                let frames = decimator.output_len(bytes);
+                if frames > buf.capacity() {
+                    return decode_error("dsd: packet is longer than the stream block size");
+                }
+                buf.render_reserved(Some(frames));
+                for (channel, input) in data.chunks_exact(bytes).enumerate() {
+                    decimator.process(channel, input, buf.chan_mut(channel));
+                }
+                decimator.advance(bytes);
+                Ok(buf.as_audio_buffer_ref())
+            }
+            Output::Dop { buf } => {
+                buf.clear();
+                // 奇数字节只会出现在流末尾，凑不满一帧的那半帧直接丢弃。
+                let frames = bytes / 2;
+                if frames > buf.capacity() {
+                    return decode_error("dsd: packet is longer than the stream block size");
+                }
+                buf.render_reserved(Some(frames));
+                // 标记取决于帧在流中的绝对位置（每帧 16 bit），seek 裁掉奇数帧后
+                // 送到 DAC 的帧仍然严格交替。
+                let first_marker = (packet.ts() / 16 % 2) as usize;
+                for (channel, input) in data.chunks_exact(bytes).enumerate() {
+                    let mut index = first_marker;
+                    for (sample, pair) in
+                        buf.chan_mut(channel).iter_mut().zip(input.chunks_exact(2))
+                    {
+                        let word =
+                            DOP_MARKERS[index] << 16 | u32::from(pair[0]) << 8 | u32::from(pair[1]);
+                        *sample = (word << 8) as i32;
+                        index ^= 1;
+                    }
+                }
+                Ok(buf.as_audio_buffer_ref())
+            }
+        }
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        match &self.output {
+            Output::Pcm { buf, .. } => buf.as_audio_buffer_ref(),
+            Output::Dop { buf, .. } => buf.as_audio_buffer_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::registry::{self, codec_registry};
+    use std::io::Cursor;
+    use symphonia::core::probe::Hint;
+
+    const DSD64: u32 = 2_822_400;
+
+    fn dsf_bytes(channels: &[Vec<u8>], dsd_rate: u32, block: usize, lsb_first: bool) -> Vec<u8> {
+        let per_channel = channels[0].len();
+        let blocks = per_channel.div_ceil(block);
+        let data_len = blocks * block * channels.len();
+        let mut out = b"DSD ".to_vec();
+        out.extend_from_slice(&28u64.to_le_bytes());
+        out.extend_from_slice(&((28 + 52 + 12 + data_len) as u64).to_le_bytes());
+        out.extend_from_slice(&0u64.to_le_bytes());
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&52u64.to_le_bytes());
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(channels.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(channels.len() as u32).to_le_bytes());
+        out.extend_from_slice(&dsd_rate.to_le_bytes());
+        out.extend_from_slice(&(if lsb_first { 1u32 } else { 8 }).to_le_bytes());
+        out.extend_from_slice(&(per_channel as u64 * 8).to_le_bytes());
+        out.extend_from_slice(&(block as u32).to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&((12 + data_len) as u64).to_le_bytes());
+        for index in 0..blocks {
+            for channel in channels {
+                let start = index * block;
+                let chunk = &channel[start..(start + block).min(per_channel)];
+                out.extend(chunk.iter().map(|byte| {
+                    if lsb_first {
+                        byte.reverse_bits()
+                    } else {
+                        *byte
+                    }
+                }));
+                out.resize(out.len() + block - chunk.len(), 0);
+            }
+        }
+        out
+    }
+
+    fn iff_chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        out.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn dff_bytes(channels: &[Vec<u8>], dsd_rate: u32) -> Vec<u8> {
+        let mut chnl = (channels.len() as u16).to_be_bytes().to_vec();
+        for id in [b"SLFT", b"SRGT"].iter().cycle().take(channels.len()) {
+            chnl.extend_from_slice(*id);
+        }
+        let mut cmpr = b"DSD ".to_vec();
+        cmpr.push(14);
+        cmpr.extend_from_slice(b"not compressed");
+        let mut prop = b"SND ".to_vec();
+        prop.extend(iff_chunk(b"FS  ", &dsd_rate.to_be_bytes()));
+        prop.extend(iff_chunk(b"CHNL", &chnl));
+        prop.extend(iff_chunk(b"CMPR", &cmpr));
+        let interleaved: Vec<u8> = (0..channels[0].len())
+            .flat_map(|index| channels.iter().map(move |channel| channel[index]))
+            .collect();
+
+        let mut form = b"DSD ".to_vec();
+        form.extend(iff_chunk(b"FVER", &0x0105_0000u32.to_be_bytes()));
+        form.extend(iff_chunk(b"PROP", &prop));
+        form.extend(iff_chunk(b"DSD ", &interleaved));
+        iff_chunk(b"FRM8", &form)
+    }
+
+    fn open(bytes: Vec<u8>, extension: &str) -> Box<dyn FormatReader> {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        registry::open_format(mss, &hint, None).unwrap()
+    }
+
+    /// 二阶 sigma-delta 调制，输出高位在前的 DSD 字节。
+    fn modulate(samples: impl Iterator<Item = f64>) -> Vec<u8> {
+        let (mut first, mut second) = (0.0f64, 0.0f64);
+        let mut out = Vec::new();
+        let mut byte = 0u8;
+        for (index, sample) in samples.enumerate() {
+            let bit = second >= 0.0;
+            let feedback = if bit { 1.0 } else { -1.0 };
+            first += sample - feedback;
+            second += first - feedback;
+            byte = byte << 1 | u8::from(bit);
+            if index % 8 == 7 {
+                out.push(byte);
+                byte = 0;
+            }
+        }
+        out
+    }
+
+    fn pattern(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|index| (index as u8).wrapping_mul(31).wrapping_add(seed))
+            .collect()
+    }
+
+    #[test]
+    fn dsf_and_dff_yield_the_same_planar_msb_first_packets() {
+        let channels = vec![pattern(10_001, 3), pattern(10_001, 170)];
+        let mut dsf = open(dsf_bytes(&channels, DSD64, 4096, true), "dsf");
+        let mut dff = open(dff_bytes(&channels, DSD64), "dff");
+
+        for format in [&dsf, &dff] {
+            let params = &format.tracks()[0].codec_params;
+            assert_eq!(native_rate(params), Some(DSD64));
+            assert_eq!(params.n_frames, Some(10_001 * 8));
+        }
+
+        let mut decoded = vec![Vec::new(), Vec::new()];
+        let mut last_dur = 0;
+        while let Ok(packet) = dsf.next_packet() {
+            let other = dff.next_packet().unwrap();
+            assert_eq!(packet.buf(), other.buf());
+            assert_eq!((packet.ts(), packet.dur()), (other.ts(), other.dur()));
+            let bytes = packet.buf().len() / 2;
+            for (channel, data) in packet.buf().chunks_exact(bytes).enumerate() {
+                decoded[channel].extend_from_slice(data);
+            }
+            last_dur = packet.dur();
+        }
+        assert!(dff.next_packet().is_err());
+        assert_eq!(decoded, channels);
+        assert_eq!(last_dur, (10_001 - 2 * 4096) * 8);
+    }
+
+    #[test]
+    fn dop_frames_carry_sixteen_bits_with_alternating_markers() {
+        let channels = vec![pattern(4096 * 2, 1), pattern(4096 * 2, 2)];
+        let mut format = open(dsf_bytes(&channels, DSD64, 4096, false), "dsf");
+        let mut decoder =
+            DsdDecoder::with_output(&format.tracks()[0].codec_params, DsdOutput::Dop).unwrap();
+
+        let mut left = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            let AudioBufferRef::S32(buf) = decoded else {
+                panic!("DoP decodes to i32");
+            };
+            assert_eq!(buf.spec().rate, 176_400);
+            assert_eq!(buf.chan(0).len(), 2048);
+            assert_eq!(buf.chan(0)[0] as u32 >> 24, buf.chan(1)[0] as u32 >> 24);
+            left.extend_from_slice(buf.chan(0));
+        }
+
+        for (index, sample) in left.iter().enumerate() {
+            let word = *sample as u32;
+            assert_eq!(word & 0xff, 0);
+            assert_eq!(word >> 24, DOP_MARKERS[index % 2]);
+            assert_eq!(word >> 16 & 0xff, u32::from(channels[0][index * 2]));
+            assert_eq!(word >> 8 & 0xff, u32::from(channels[0][index * 2 + 1]));
+        }
+    }
+
+    #[test]
+    fn dop_markers_follow_absolute_frame_index_across_seek() {
+        // 末尾 packet 只有 3 帧：按 packet 累计奇偶的做法会让 seek 回开头后标记错位。
+        let channels = vec![pattern(4096 * 2 + 6, 3), pattern(4096 * 2 + 6, 4)];
+        let mut format = open(dsf_bytes(&channels, DSD64, 4096, false), "dsf");
+        let mut decoder =
+            DsdDecoder::with_output(&format.tracks()[0].codec_params, DsdOutput::Dop).unwrap();
+        let markers = |decoded: AudioBufferRef<'_>| {
+            let AudioBufferRef::S32(buf) = decoded else {
+                panic!("DoP decodes to i32");
+            };
+            buf.chan(0)
+                .iter()
+                .map(|sample| *sample as u32 >> 24)
+                .collect::<Vec<_>>()
+        };
+
+        let mut first_pass = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            first_pass.extend(markers(decoder.decode(&packet).unwrap()));
+        }
+        assert_eq!(first_pass.len(), 4096 + 3);
+        for (index, marker) in first_pass.iter().enumerate() {
+            assert_eq!(*marker, DOP_MARKERS[index % 2]);
+        }
+
+        format
+            .seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0 })
+            .unwrap();
+        decoder.reset();
+        let packet = format.next_packet().unwrap();
+        let replay = markers(decoder.decode(&packet).unwrap());
+        assert_eq!(replay, first_pass[..2048]);
+    }
+
+    #[test]
+    fn pcm_conversion_recovers_a_modulated_sine() {
+        let bits = DSD64 as usize / 10;
+        let sine = |t: f64| 0.5 * (2.0 * std::f64::consts::PI * 1_000.0 * t).sin();
+        let dsd = modulate((0..bits).map(|index| sine(index as f64 / f64::from(DSD64))));
+        let mut format = open(dsf_bytes(&[dsd], DSD64, 4096, true), "dsf");
+        let mut decoder = codec_registry()
+            .make(&format.tracks()[0].codec_params, &DecoderOptions::default())
+            .unwrap();
+
+        let mut pcm = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            let AudioBufferRef::F32(buf) = decoded else {
+                panic!("DSD converts to f32");
+            };
+            assert_eq!(buf.spec().rate, 88_200);
+            pcm.extend_from_slice(buf.chan(0));
+        }
+        assert_eq!(pcm.len(), bits / 32);
+
+        // 输出样本 n 对应窗口中心 32(n+1) - taps/2 - 0.5（按 DSD 位计）。
+        let taps = (32 * TAPS_PER_DECIMATION) as f64;
+        let error = pcm
+            .iter()
+            .enumerate()
+            .skip(200)
+            .map(|(index, sample)| {
+                let center = 32.0 * (index + 1) as f64 - taps / 2.0 - 0.5;
+                (f64::from(*sample) - sine(center / f64::from(DSD64))).abs()
+            })
+            .fold(0.0, f64::max);
+        assert!(error < 0.01, "max error {error}");
+    }
+
+    #[test]
+    fn seek_lands_on_block_boundary_and_rates_follow_dsd_multiple() {
+        let channels = vec![pattern(4096 * 3, 9)];
+        let mut format = open(dsf_bytes(&channels, DSD64 * 2, 4096, true), "dsf");
+        let seeked = format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp {
+                    ts: 4096 * 8 + 100,
+                    track_id: 0,
+                },
+            )
+            .unwrap();
+        assert_eq!(seeked.actual_ts, 4096 * 8);
+        assert_eq!(format.next_packet().unwrap().ts(), 4096 * 8);
+
+        assert_eq!(pcm_rate(DSD64), 88_200);
+        assert_eq!(pcm_rate(DSD64 * 2), 176_400);
+        assert_eq!(pcm_rate(DSD64 * 8), 352_800);
+        assert_eq!(dop_carrier_rate(DSD64 * 2), Some(352_800));
+        assert_eq!(dop_carrier_rate(DSD64 * 4), None);
+    }
+}
@@ -0,0 +1,788 @@
+//! 离线导出：用与播放相同的探测与解码路径把音源解码成 PCM，不按实时节奏，
+//! 经可选的增益、重采样与 TPDF 抖动后写成 WAV、FLAC 或裸 PCM，并带上原有标签。
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use ringbuf::HeapRb;
+use ringbuf::traits::{Consumer, Observer, Split};
+
+use crate::audio::decoder::{self, AudioMetadata};
+use crate::audio::dsd::bessel_i0;
+use crate::audio::flac_encoder::FlacWriter;
+use crate::audio::registry::Tags;
+use crate::audio::state::SharedState;
+use crate::audio::wav::WavWriter;
+
+/// 解码线程与编码端之间的缓冲时长。
+const RING_SECONDS: usize = 2;
+/// 编码端每次取出的帧数。
+const CHUNK_FRAMES: usize = 8192;
+/// 可导出的位深；32 位写成浮点，FLAC 只支持 16/24 位。
+const EXPORT_BITS: [u16; 3] = [16, 24, 32];
+/// 重采样低通每侧的过零点数，决定过渡带宽度与阻带衰减。
+const RESAMPLE_ZERO_CROSSINGS: f64 = 32.0;
+/// 通带边缘相对较低一方 Nyquist 的位置。
+const RESAMPLE_ROLLOFF: f64 = 0.95;
+const RESAMPLE_KAISER_BETA: f64 = 9.0;
+/// 多相系数表的最大相位数，约分后相位更多时在相邻相位间插值。
+const MAX_PHASES: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExportFormat {
+    Wav,
+    Flac,
+    /// 无文件头的交错小端 PCM，24 位按 3 字节紧凑存放。
+    Pcm,
+}
+
+impl TryFrom<&str> for ExportFormat {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "wav" => Ok(Self::Wav),
+            "flac" => Ok(Self::Flac),
+            "pcm" | "raw" => Ok(Self::Pcm),
+            _ => Err(format!("不支持的导出格式：{value}")),
+        }
+    }
+}
+
+/// 按音源中的 ReplayGain 标签调整音量。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    /// 缺少专辑增益时退回单曲增益。
+    Album,
+}
+
+impl TryFrom<&str> for ReplayGainMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "off" => Ok(Self::Off),
+            "track" => Ok(Self::Track),
+            "album" => Ok(Self::Album),
+            _ => Err(format!("Unknown replay gain mode: {value}")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ExportOptions {
+    /// 目标采样率，缺省保持音源采样率。
+    pub(crate) sample_rate: Option<u32>,
+    /// 目标位深，缺省时音源高于 16 位取 24，否则取 16。
+    pub(crate) bits: Option<u16>,
+    /// 样本被改动或位深降低时加 TPDF 抖动；原样导出时不加，保证逐位一致。
+    pub(crate) dither: bool,
+    pub(crate) gain_db: f64,
+    pub(crate) replay_gain: ReplayGainMode,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            sample_rate: None,
+            bits: None,
+            dither: true,
+            gain_db: 0.0,
+            replay_gain: ReplayGainMode::Off,
+        }
+    }
+}
+
+/// 导出任务的进度与取消标记，由发起方持有并可在其它线程查询。
+#[derive(Debug, Default)]
+pub(crate) struct ExportControl {
+    cancelled: AtomicBool,
+    /// 已处理的音源帧数。
+    done_frames: AtomicU64,
+    /// 音源总帧数，0 表示未知。
+    total_frames: AtomicU64,
+    sample_rate: AtomicU32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ExportProgress {
+    pub(crate) done_frames: u64,
+    pub(crate) total_frames: Option<u64>,
+    pub(crate) sample_rate: u32,
+}
+
+impl ExportControl {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn progress(&self) -> ExportProgress {
+        let total_frames = self.total_frames.load(Ordering::Relaxed);
+        ExportProgress {
+            done_frames: self.done_frames.load(Ordering::Relaxed),
+            total_frames: (total_frames > 0).then_some(total_frames),
+            sample_rate: self.sample_rate.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 导出完成后的结果。
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ExportReport {
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    pub(crate) bits: u16,
+    /// 写入的帧数（目标采样率下）。
+    pub(crate) frames: u64,
+    pub(crate) dithered: bool,
+    /// 实际应用的增益（含 ReplayGain）。
+    pub(crate) gain_db: f64,
+}
+
+/// 把已探测的音源导出到 `dest`。阻塞直到完成；先写到同目录的临时文件，
+/// 成功后改名覆盖 `dest`，取消或失败时只删除临时文件，`dest` 原有内容不受影响。
+pub(crate) fn export_track(
+    meta: AudioMetadata,
+    dest: &Path,
+    format: ExportFormat,
+    options: ExportOptions,
+    control: &ExportControl,
+) -> Result<ExportReport, Box<dyn std::error::Error>> {
+    let partial = partial_path(dest).ok_or("未指定输出文件路径")?;
+    let result = run_export(meta, &partial, format, options, control).and_then(|report| {
+        std::fs::rename(&partial, dest)
+            .map_err(|err| format!("无法写入输出文件 {}：{}", dest.display(), err))?;
+        Ok(report)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+/// `dest` 旁边的临时文件名，带进程号与序号，同一 `dest` 的并发导出互不覆盖。
+fn partial_path(dest: &Path) -> Option<PathBuf> {
+    static NEXT_PARTIAL: AtomicU64 = AtomicU64::new(0);
+    let name = dest.file_name()?.to_string_lossy();
+    let serial = NEXT_PARTIAL.fetch_add(1, Ordering::Relaxed);
+    Some(dest.with_file_name(format!(".{name}.{}-{serial}.part", std::process::id())))
+}
+
+fn run_export(
+    meta: AudioMetadata,
+    dest: &Path,
+    format: ExportFormat,
+    options: ExportOptions,
+    control: &ExportControl,
+) -> Result<ExportReport, Box<dyn std::error::Error>> {
+    let AudioMetadata {
+        sample_rate: source_rate,
+        channels,
+        bits_per_sample,
+        time_base,
+        track_id,
+        n_frames,
+        tags,
+        mut decoder,
+        format_reader: mut reader,
+        ..
+    } = meta;
+    let sample_rate = options.sample_rate.unwrap_or(source_rate);
+    if sample_rate == 0 {
+        return Err("导出采样率不能为 0".into());
+    }
+    let bits = options
+        .bits
+        .unwrap_or(if bits_per_sample.is_some_and(|bits| bits > 16) {
+            24
+        } else {
+            16
+        });
+    if !EXPORT_BITS.contains(&bits) {
+        return Err(format!("导出不支持 {} 位", bits).into());
+    }
+
+    let gain_db = options.gain_db + replay_gain_db(&tags, options.replay_gain);
+    let resample = sample_rate != source_rate;
+    let exact_source = bits_per_sample.is_some_and(|source_bits| source_bits <= u32::from(bits));
+    let dithered = options.dither && bits < 32 && (gain_db != 0.0 || resample || !exact_source);
+    let tags = export_tags(tags, gain_db != 0.0);
+    let mut writer = ExportWriter::create(dest, format, channels, sample_rate, bits, &tags)?;
+
+    control.sample_rate.store(source_rate, Ordering::Relaxed);
+    if let Some(total) =
+        n_frames.and_then(|ts| decoder::timestamp_to_frame(ts, source_rate, time_base))
+    {
+        control.total_frames.store(total, Ordering::Relaxed);
+    }
+
+    let channel_count = usize::from(channels);
+    let state = SharedState::new(source_rate);
+    let ring_samples = source_rate as usize * channel_count * RING_SECONDS;
+    let (mut producer, mut consumer) = HeapRb::<f64>::new(ring_samples.max(CHUNK_FRAMES)).split();
+    let gain = 10f64.powf(gain_db / 20.0);
+    let mut resampler = resample.then(|| Resampler::new(channel_count, source_rate, sample_rate));
+    let mut quantizer = Quantizer::new(bits, dithered);
+    let mut input = Vec::with_capacity(CHUNK_FRAMES * channel_count);
+    let mut output = Vec::new();
+    let mut frames = 0u64;
+
+    let encoded = std::thread::scope(|scope| -> Result<(), Box<dyn std::error::Error>> {
+        let state = &state;
+        scope.spawn(move || {
+            loop {
+                if state.is_terminating.load(Ordering::Relaxed)
+                    || !decoder::decode_next_packet::<f64, _>(
+                        &mut *reader,
+                        &mut *decoder,
+                        track_id,
+                        source_rate,
+                        time_base,
+                        &mut producer,
+                        state,
+                    )
+                {
+                    break;
+                }
+            }
+            state.decoder_done.store(true, Ordering::SeqCst);
+        });
+
+        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+            loop {
+                if control.is_cancelled() {
+                    return Err("导出已取消".into());
+                }
+                let decoder_done = state.decoder_done.load(Ordering::SeqCst);
+                input.clear();
+                let available = consumer.occupied_len() - consumer.occupied_len() % channel_count;
+                input.extend(
+                    consumer
+                        .pop_iter()
+                        .take(available.min(CHUNK_FRAMES * channel_count)),
+                );
+                if input.is_empty() {
+                    if !decoder_done {
+                        std::thread::sleep(Duration::from_millis(1));
+                        continue;
+                    }
+                    if state.is_finished.load(Ordering::SeqCst) {
+                        return Err("音源下载失败，导出中断".into());
+                    }
+                }
+                let last = input.is_empty();
+                control
+                    .done_frames
+                    .fetch_add((input.len() / channel_count) as u64, Ordering::Relaxed);
+                input.iter_mut().for_each(|sample| *sample *= gain);
+
+                output.clear();
+                match resampler.as_mut() {
+                    Some(resampler) => resampler.process(&input, &mut output, last),
+                    None => output.extend_from_slice(&input),
+                }
+                frames += (output.len() / channel_count) as u64;
+                writer.write(&output, &mut quantizer)?;
+                if last {
+                    return Ok(());
+                }
+            }
+        })();
+        // 编码端出错或取消时让解码线程尽快退出。
+        state.is_terminating.store(true, Ordering::SeqCst);
+        result
+    });
+    encoded?;
+    writer.finish()?;
+
+    Ok(ExportReport {
+        sample_rate,
+        channels,
+        bits,
+        frames,
+        dithered,
+        gain_db,
+    })
+}
+
+/// 标签里的 ReplayGain 增益（dB），缺失时为 0。
+fn replay_gain_db(tags: &Tags, mode: ReplayGainMode) -> f64 {
+    let find = |key: &str| {
+        tags.iter()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, value)| {
+                let value = value.trim();
+                let value = value
+                    .strip_suffix("dB")
+                    .or_else(|| value.strip_suffix("db"))
+                    .unwrap_or(value);
+                value.trim().parse::<f64>().ok()
+            })
+            .filter(|gain| gain.is_finite())
+    };
+    match mode {
+        ReplayGainMode::Off => None,
+        ReplayGainMode::Track => find("REPLAYGAIN_TRACK_GAIN"),
+        ReplayGainMode::Album => {
+            find("REPLAYGAIN_ALBUM_GAIN").or_else(|| find("REPLAYGAIN_TRACK_GAIN"))
+        }
+    }
+    .unwrap_or(0.0)
+}
+
+/// 已改动音量时 ReplayGain 标签不再成立，去掉以免播放器重复补偿。
+fn export_tags(tags: Tags, gain_applied: bool) -> Tags {
+    tags.into_iter()
+        .filter(|(key, _)| !gain_applied || !key.starts_with("REPLAYGAIN_"))
+        .collect()
+}
+
+/// 把 [-1, 1) 的样本量化到目标位深，需要时叠加 ±1 LSB 的 TPDF 抖动。
+struct Quantizer {
+    bits: u16,
+    scale: f64,
+    dither: bool,
+    seed: u64,
+}
+
+impl Quantizer {
+    fn new(bits: u16, dither: bool) -> Self {
+        Self {
+            bits,
+            scale: 2f64.powi(i32::from(bits) - 1),
+            dither,
+            seed: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    fn uniform(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn quantize(&mut self, sample: f64) -> i32 {
+        let mut value = sample * self.scale;
+        if self.dither {
+            value += self.uniform() - self.uniform();
+        }
+        value.round().clamp(-self.scale, self.scale - 1.0) as i32
+    }
+}
+
+/// 交错 f64 样本的流式多相重采样：Kaiser 窗 sinc 低通，按有理数比例 L/M 变换采样率。
+/// 相位数超过 `MAX_PHASES` 时在相邻两组系数之间线性插值。
+struct Resampler {
+    channels: usize,
+    /// 约分后的目标、源采样率。
+    up: u64,
+    down: u64,
+    /// 每侧的抽头数，一组系数共 `2 * half` 个。
+    half: usize,
+    phases: u64,
+    /// `phases + 1` 组系数，第 r 组对应输入位置的小数部分 r / phases。
+    table: Vec<f64>,
+    /// 尚需参与卷积的输入帧，开头补了 `half - 1` 帧零。
+    buffer: Vec<f64>,
+    /// 下一输出帧在 `buffer` 中的整数帧位置与小数相位（以 1/up 为单位）。
+    position: usize,
+    phase: u64,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl Resampler {
+    fn new(channels: usize, source_rate: u32, target_rate: u32) -> Self {
+        let divisor = gcd(u64::from(source_rate), u64::from(target_rate));
+        let up = u64::from(target_rate) / divisor;
+        let down = u64::from(source_rate) / divisor;
+        // 截止频率以源采样率的 Nyquist 为 1，降采样时压到目标 Nyquist 以下防止混叠。
+        let cutoff = (up as f64 / down as f64).min(1.0) * RESAMPLE_ROLLOFF;
+        let half = (RESAMPLE_ZERO_CROSSINGS / cutoff).ceil() as usize;
+        let phases = up.min(MAX_PHASES);
+        let taps = 2 * half;
+        let norm = bessel_i0(RESAMPLE_KAISER_BETA);
+        let mut table = Vec::with_capacity((phases as usize + 1) * taps);
+        for row in 0..=phases {
+            let frac = row as f64 / phases as f64;
+            let start = table.len();
+            table.extend((0..taps).map(|tap| {
+                // 输出位置到该抽头对应输入帧的距离。
+                let t = frac + (half - 1) as f64 - tap as f64;
+                let x = std::f64::consts::PI * cutoff * t;
+                let sinc = if x.abs() < 1e-12 { 1.0 } else { x.sin() / x };
+                let r = t / half as f64;
+                let window = bessel_i0(RESAMPLE_KAISER_BETA * (1.0 - r * r).max(0.0).sqrt()) / norm;
+                sinc * window
+            }));
+            let sum: f64 = table[start..].iter().sum();
+            table[start..].iter_mut().for_each(|coeff| *coeff /= sum);
+        }
+        Self {
+            channels,
+            up,
+            down,
+            half,
+            phases,
+            table,
+            buffer: vec![0.0; (half - 1) * channels],
+            position: half - 1,
+            phase: 0,
+            input_frames: 0,
+            output_frames: 0,
+        }
+    }
+
+    /// 只生成现有输入足以卷积的帧；`last` 时补零冲洗，输出总帧数按采样率比例取整。
+    fn process(&mut self, input: &[f64], output: &mut Vec<f64>, last: bool) {
+        let channels = self.channels;
+        self.buffer.extend_from_slice(input);
+        self.input_frames += (input.len() / channels) as u64;
+        let target = if last {
+            self.buffer
+                .resize(self.buffer.len() + (self.half + 1) * channels, 0.0);
+            (self.input_frames * self.up + self.down / 2) / self.down
+        } else {
+            u64::MAX
+        };
+
+        let taps = 2 * self.half;
+        let mut coeffs = vec![0.0; taps];
+        while self.output_frames < target
+            && (self.position + self.half + 1) * channels <= self.buffer.len()
+        {
+            let scaled = self.phase * self.phases;
+            let row = (scaled / self.up) as usize;
+            let t = (scaled % self.up) as f64 / self.up as f64;
+            let lower = &self.table[row * taps..(row + 1) * taps];
+            let upper = &self.table[(row + 1) * taps..(row + 2) * taps];
+            for ((coeff, lower), upper) in coeffs.iter_mut().zip(lower).zip(upper) {
+                *coeff = lower + (upper - lower) * t;
+            }
+
+            let first = (self.position + 1 - self.half) * channels;
+            for channel in 0..channels {
+                let sum: f64 = coeffs
+                    .iter()
+                    .enumerate()
+                    .map(|(tap, coeff)| coeff * self.buffer[first + tap * channels + channel])
+                    .sum();
+                output.push(sum);
+            }
+
+            self.output_frames += 1;
+            self.phase += self.down;
+            self.position += (self.phase / self.up) as usize;
+            self.phase %= self.up;
+        }
+
+        let consumed = (self.position + 1).saturating_sub(self.half);
+        let consumed = consumed.min(self.buffer.len() / channels);
+        self.buffer.drain(..consumed * channels);
+        self.position -= consumed;
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+enum ExportWriter {
+    Wav(WavWriter),
+    Flac(FlacWriter),
+    Pcm(BufWriter<File>),
+}
+
+impl ExportWriter {
+    fn create(
+        dest: &Path,
+        format: ExportFormat,
+        channels: u16,
+        sample_rate: u32,
+        bits: u16,
+        tags: &Tags,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match format {
+            ExportFormat::Wav => {
+                let sample_format = match bits {
+                    16 => cpal::SampleFormat::I16,
+                    24 => cpal::SampleFormat::I24,
+                    _ => cpal::SampleFormat::F32,
+                };
+                Self::Wav(WavWriter::create(
+                    dest,
+                    channels,
+                    sample_rate,
+                    sample_format,
+                    tags,
+                )?)
+            }
+            ExportFormat::Flac => {
+                Self::Flac(FlacWriter::create(dest, channels, sample_rate, bits, tags)?)
+            }
+            ExportFormat::Pcm => {
+                Self::Pcm(BufWriter::new(File::create(dest).map_err(|err| {
+                    format!("无法创建输出文件 {}：{}", dest.display(), err)
+                })?))
+            }
+        })
+    }
+
+    fn write(&mut self, samples: &[f64], quantizer: &mut Quantizer) -> io::Result<()> {
+        if quantizer.bits == 32 {
+            let bytes: Vec<u8> = samples
+                .iter()
+                .flat_map(|sample| (*sample as f32).to_le_bytes())
+                .collect();
+            return self.write_bytes(&bytes);
+        }
+        let quantized: Vec<i32> = samples
+            .iter()
+            .map(|sample| quantizer.quantize(*sample))
+            .collect();
+        if let Self::Flac(writer) = self {
+            return writer.write(&quantized);
+        }
+        let width = usize::from(quantizer.bits / 8);
+        let bytes: Vec<u8> = quantized
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes().into_iter().take(width))
+            .collect();
+        self.write_bytes(&bytes)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Wav(writer) => writer.write_bytes(bytes),
+            Self::Pcm(writer) => writer.write_all(bytes),
+            Self::Flac(_) => unreachable!("FLAC 只接受整数样本"),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Wav(writer) => writer.finish(),
+            Self::Flac(writer) => writer.finish(),
+            Self::Pcm(mut writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm16_wav(path: &Path, sample_rate: u32, channels: u16, samples: &[i16], tags: &Tags) {
+        let mut writer =
+            WavWriter::create(path, channels, sample_rate, cpal::SampleFormat::I16, tags).unwrap();
+        writer.write(samples).unwrap();
+        writer.finish().unwrap();
+    }
+
+    fn sweep(frames: usize, channels: usize) -> Vec<i16> {
+        (0..frames * channels)
+            .map(|index| {
+                let frame = (index / channels) as f64;
+                ((frame * frame * 1e-5).sin() * 20_000.0) as i16 + (index % 3) as i16
+            })
+            .collect()
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("ncm-export-{}-{name}", std::process::id()))
+    }
+
+    async fn export(
+        source: &Path,
+        dest: &Path,
+        format: ExportFormat,
+        options: ExportOptions,
+    ) -> ExportReport {
+        let meta = crate::audio::player::probe_file(source.to_str().unwrap())
+            .await
+            .unwrap();
+        export_track(meta, dest, format, options, &ExportControl::new()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn untouched_export_is_bit_exact_and_keeps_tags() {
+        let source = temp_path("source.wav");
+        let samples = sweep(20_000, 2);
+        let tags = vec![("TITLE".to_string(), "晴天".to_string())];
+        pcm16_wav(&source, 44_100, 2, &samples, &tags);
+
+        let flac = temp_path("exact.flac");
+        let report = export(&source, &flac, ExportFormat::Flac, ExportOptions::default()).await;
+        assert_eq!(report.bits, 16);
+        assert!(!report.dithered);
+        assert_eq!(report.frames, 20_000);
+
+        let raw = temp_path("exact.pcm");
+        let meta = crate::audio::player::probe_file(flac.to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(
+            meta.tags
+                .contains(&("TITLE".to_string(), "晴天".to_string()))
+        );
+        export_track(
+            meta,
+            &raw,
+            ExportFormat::Pcm,
+            ExportOptions::default(),
+            &ExportControl::new(),
+        )
+        .unwrap();
+        let expected: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert!(std::fs::read(&raw).unwrap() == expected);
+
+        for path in [source, flac, raw] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn resampling_to_24_bit_dithers_and_keeps_duration() {
+        let source = temp_path("rate.wav");
+        pcm16_wav(&source, 44_100, 2, &sweep(44_100, 2), &Vec::new());
+        let dest = temp_path("rate.wav.out");
+        let options = ExportOptions {
+            sample_rate: Some(48_000),
+            bits: Some(24),
+            gain_db: -6.0,
+            ..ExportOptions::default()
+        };
+        let report = export(&source, &dest, ExportFormat::Wav, options).await;
+        assert_eq!(report.sample_rate, 48_000);
+        assert_eq!(report.frames, 48_000);
+        assert!(report.dithered);
+
+        let meta = crate::audio::player::probe_file(dest.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(meta.sample_rate, 48_000);
+        assert_eq!(meta.bits_per_sample, Some(24));
+        std::fs::remove_file(source).unwrap();
+        std::fs::remove_file(dest).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancelled_export_removes_partial_file() {
+        let source = temp_path("cancel.wav");
+        pcm16_wav(&source, 44_100, 2, &sweep(44_100, 2), &Vec::new());
+        let dest = temp_path("cancel.flac");
+        std::fs::write(&dest, b"previous export").unwrap();
+        let meta = crate::audio::player::probe_file(source.to_str().unwrap())
+            .await
+            .unwrap();
+        let control = ExportControl::new();
+        control.cancel();
+        let err = export_track(
+            meta,
+            &dest,
+            ExportFormat::Flac,
+            ExportOptions::default(),
+            &control,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "导出已取消");
+        assert_eq!(std::fs::read(&dest).unwrap(), b"previous export");
+        let prefix = format!(".{}.", dest.file_name().unwrap().to_string_lossy());
+        let leftovers = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .count();
+        assert_eq!(leftovers, 0, "partial file must be removed");
+        std::fs::remove_file(source).unwrap();
+        std::fs::remove_file(dest).unwrap();
+    }
+
+    #[test]
+    fn replay_gain_prefers_album_and_drops_stale_tags() {
+        let tags = vec![
+            ("REPLAYGAIN_TRACK_GAIN".to_string(), "-7.5 dB".to_string()),
+            ("TITLE".to_string(), "晴天".to_string()),
+        ];
+        assert_eq!(replay_gain_db(&tags, ReplayGainMode::Album), -7.5);
+        assert_eq!(replay_gain_db(&tags, ReplayGainMode::Off), 0.0);
+        assert_eq!(export_tags(tags.clone(), true), vec![tags[1].clone()]);
+        assert_eq!(export_tags(tags.clone(), false), tags);
+    }
+
+    fn sine(frames: usize, channels: usize, rate: f64, freq: f64) -> Vec<f64> {
+        (0..frames * channels)
+            .map(|index| {
+                let t = (index / channels) as f64 / rate;
+                0.5 * (2.0 * std::f64::consts::PI * freq * t).sin()
+            })
+            .collect()
+    }
+
+    fn resample(input: &[f64], channels: usize, from: u32, to: u32) -> Vec<f64> {
+        let mut resampler = Resampler::new(channels, from, to);
+        let mut output = Vec::new();
+        for chunk in input.chunks(1000 * channels) {
+            resampler.process(chunk, &mut output, false);
+        }
+        resampler.process(&[], &mut output, true);
+        output
+    }
+
+    #[test]
+    fn sinc_resampler_keeps_tones_accurate_and_in_phase() {
+        let input = sine(44_100, 2, 44_100.0, 1_000.0);
+        let output = resample(&input, 2, 44_100, 48_000);
+        assert_eq!(output.len(), 48_000 * 2);
+        let expected = sine(48_000, 2, 48_000.0, 1_000.0);
+        // 两端受补零影响，只比较中段。
+        let error = output[2_000..94_000]
+            .iter()
+            .zip(&expected[2_000..94_000])
+            .map(|(got, want)| (got - want).abs())
+            .fold(0.0, f64::max);
+        assert!(error < 1e-4, "max error {error}");
+    }
+
+    #[test]
+    fn sinc_resampler_removes_tones_above_the_new_nyquist() {
+        let input = sine(96_000, 1, 96_000.0, 30_000.0);
+        let output = resample(&input, 1, 96_000, 44_100);
+        assert_eq!(output.len(), 44_100);
+        let peak = output[1_000..43_000]
+            .iter()
+            .fold(0.0, |peak: f64, sample| peak.max(sample.abs()));
+        assert!(peak < 1e-3, "aliased peak {peak}");
+    }
+
+    #[test]
+    fn quantizer_is_exact_without_dither_and_bounded_with_it() {
+        let mut exact = Quantizer::new(16, false);
+        assert_eq!(exact.quantize(-1.0), -32768);
+        assert_eq!(exact.quantize(1.0), 32767);
+        assert_eq!(exact.quantize(1234.0 / 32768.0), 1234);
+
+        let mut dithered = Quantizer::new(16, true);
+        let values: Vec<i32> = (0..1000).map(|_| dithered.quantize(0.0)).collect();
+        assert!(values.iter().all(|value| (-1..=1).contains(value)));
+        assert!(values.iter().any(|value| *value != 0));
+    }
+}
@@ -0,0 +1,642 @@
+//! 离线导出用的 FLAC 编码器：固定 4096 帧分块，每个子帧在 CONSTANT、VERBATIM 和
+//! 0~4 阶 FIXED 预测里取最短的一种，立体声再在左右/左侧/右侧/中侧四种声道组合里挑选。
+//! 残差按分区 Rice 编码。STREAMINFO 在写完后回填，MD5 留空（规范允许，表示未计算）。
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// 每个 FLAC 帧包含的采样帧数。
+const BLOCK_SIZE: usize = 4096;
+const MAX_PARTITION_ORDER: u32 = 8;
+const MAX_FIXED_ORDER: usize = 4;
+const STREAMINFO_LEN: usize = 34;
+/// 回填 STREAMINFO 时的文件偏移："fLaC" 加 4 字节块头。
+const STREAMINFO_AT: u64 = 8;
+const VENDOR: &str = "ncm-desktop-for-linux";
+
+/// FLAC 可写的位深。
+const FLAC_BITS: [u16; 2] = [16, 24];
+
+pub(crate) struct FlacWriter {
+    file: BufWriter<File>,
+    channels: usize,
+    bits: u32,
+    sample_rate: u32,
+    /// 未凑满一个块的交错样本。
+    pending: Vec<i32>,
+    frame_number: u64,
+    total_frames: u64,
+    min_frame_bytes: u32,
+    max_frame_bytes: u32,
+    frame: BitWriter,
+}
+
+impl FlacWriter {
+    /// 创建文件并写入文件头；`tags` 原样写进 VORBIS_COMMENT 块。
+    pub(crate) fn create(
+        path: &Path,
+        channels: u16,
+        sample_rate: u32,
+        bits: u16,
+        tags: &[(String, String)],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !(1..=8).contains(&channels) {
+            return Err(format!("FLAC 不支持 {} 声道", channels).into());
+        }
+        if !FLAC_BITS.contains(&bits) {
+            return Err(format!("FLAC 导出不支持 {} 位", bits).into());
+        }
+        if sample_rate == 0 || sample_rate >= 1 << 20 {
+            return Err(format!("FLAC 不支持 {} Hz 采样率", sample_rate).into());
+        }
+        let file = File::create(path)
+            .map_err(|err| format!("无法创建输出文件 {}：{}", path.display(), err))?;
+
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            channels: usize::from(channels),
+            bits: u32::from(bits),
+            sample_rate,
+            pending: Vec::with_capacity(BLOCK_SIZE * usize::from(channels)),
+            frame_number: 0,
+            total_frames: 0,
+            min_frame_bytes: 0,
+            max_frame_bytes: 0,
+            frame: BitWriter::default(),
+        };
+        let comment = vorbis_comment(tags);
+        writer.file.write_all(b"fLaC")?;
+        writer
+            .file
+            .write_all(&block_header(false, 0, STREAMINFO_LEN))?;
+        writer.file.write_all(&writer.streaminfo())?;
+        writer
+            .file
+            .write_all(&block_header(true, 4, comment.len()))?;
+        writer.file.write_all(&comment)?;
+        Ok(writer)
+    }
+
+    /// 写入交错排列、已量化到目标位深的样本。
+    pub(crate) fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        let block_samples = BLOCK_SIZE * self.channels;
+        let mut samples = samples;
+        while !samples.is_empty() {
+            let take = (block_samples - self.pending.len()).min(samples.len());
+            self.pending.extend_from_slice(&samples[..take]);
+            samples = &samples[take..];
+            if self.pending.len() == block_samples {
+                self.flush_block()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 编码剩余样本并回填 STREAMINFO。
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        let whole = self.pending.len() - self.pending.len() % self.channels;
+        self.pending.truncate(whole);
+        if !self.pending.is_empty() {
+            self.flush_block()?;
+        }
+        let streaminfo = self.streaminfo();
+        self.file.seek(SeekFrom::Start(STREAMINFO_AT))?;
+        self.file.write_all(&streaminfo)?;
+        self.file.flush()
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        let frames = self.pending.len() / self.channels;
+        let mut frame = std::mem::take(&mut self.frame);
+        frame.clear();
+        self.encode_frame(&mut frame, frames);
+        let bytes = frame.bytes();
+        self.file.write_all(bytes)?;
+
+        let len = bytes.len() as u32;
+        self.min_frame_bytes = if self.frame_number == 0 {
+            len
+        } else {
+            self.min_frame_bytes.min(len)
+        };
+        self.max_frame_bytes = self.max_frame_bytes.max(len);
+        self.frame_number += 1;
+        self.total_frames += frames as u64;
+        self.pending.clear();
+        self.frame = frame;
+        Ok(())
+    }
+
+    fn encode_frame(&self, out: &mut BitWriter, frames: usize) {
+        let channels: Vec<Vec<i64>> = (0..self.channels)
+            .map(|channel| {
+                self.pending
+                    .iter()
+                    .skip(channel)
+                    .step_by(self.channels)
+                    .map(|sample| i64::from(*sample))
+                    .collect()
+            })
+            .collect();
+
+        // 立体声时比较四种声道组合，side 声道多占 1 位。
+        let bits = self.bits;
+        let mut encoded: Vec<(Vec<i64>, u32, Subframe)> = Vec::new();
+        let with_best = |(samples, bits): (Vec<i64>, u32)| {
+            let subframe = Subframe::best(&samples, bits);
+            (samples, bits, subframe)
+        };
+        let assignment = if channels.len() == 2 {
+            let (left, right) = (&channels[0], &channels[1]);
+            let side: Vec<i64> = left.iter().zip(right).map(|(l, r)| l - r).collect();
+            let mid: Vec<i64> = left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+            let mut variants = [
+                (left.clone(), bits),
+                (right.clone(), bits),
+                (side, bits + 1),
+                (mid, bits),
+            ]
+            .map(|variant| Some(with_best(variant)));
+            let cost = |index: usize| variants[index].as_ref().map_or(0, |(_, _, sub)| sub.cost);
+            let (assignment, pair) = [
+                (0b0001, [0, 1]),
+                (0b1000, [0, 2]),
+                (0b1001, [2, 1]),
+                (0b1010, [3, 2]),
+            ]
+            .into_iter()
+            .min_by_key(|(_, pair)| cost(pair[0]) + cost(pair[1]))
+            .unwrap();
+            encoded.extend(pair.map(|index| variants[index].take().unwrap()));
+            assignment
+        } else {
+            encoded.extend(
+                channels
+                    .into_iter()
+                    .map(|samples| with_best((samples, bits))),
+            );
+            self.channels as u64 - 1
+        };
+
+        out.put(0b1111_1111_1111_1000, 16);
+        let (size_code, size_extra) = match frames {
+            BLOCK_SIZE => (0b1100, None),
+            1..=256 => (0b0110, Some(((frames - 1) as u64, 8))),
+            _ => (0b0111, Some(((frames - 1) as u64, 16))),
+        };
+        out.put(size_code, 4);
+        out.put(u64::from(sample_rate_code(self.sample_rate)), 4);
+        out.put(assignment, 4);
+        out.put(if bits == 16 { 0b100 } else { 0b110 }, 3);
+        out.put(0, 1);
+        out.put_utf8(self.frame_number);
+        if let Some((value, width)) = size_extra {
+            out.put(value, width);
+        }
+        let crc = crc8(out.bytes());
+        out.put(u64::from(crc), 8);
+
+        for (samples, bits, subframe) in &encoded {
+            subframe.write(out, samples, *bits);
+        }
+        out.align();
+        let crc = crc16(out.bytes());
+        out.put(u64::from(crc), 16);
+    }
+
+    fn streaminfo(&self) -> [u8; STREAMINFO_LEN] {
+        let mut info = BitWriter::default();
+        info.put(BLOCK_SIZE as u64, 16);
+        info.put(BLOCK_SIZE as u64, 16);
+        info.put(u64::from(self.min_frame_bytes), 24);
+        info.put(u64::from(self.max_frame_bytes), 24);
+        info.put(u64::from(self.sample_rate), 20);
+        info.put(self.channels as u64 - 1, 3);
+        info.put(u64::from(self.bits - 1), 5);
+        info.put(self.total_frames.min((1 << 36) - 1), 36);
+        info.put(0, 64);
+        info.put(0, 64);
+        info.bytes().try_into().unwrap()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SubframeKind {
+    Constant,
+    Verbatim,
+    Fixed { order: usize, rice: RicePlan },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct RicePlan {
+    partition_order: u32,
+    params: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+struct Subframe {
+    kind: SubframeKind,
+    /// 子帧编码后的位数，用于比较。
+    cost: u64,
+}
+
+impl Subframe {
+    fn best(samples: &[i64], bits: u32) -> Self {
+        const HEADER: u64 = 8;
+        if samples.iter().all(|sample| *sample == samples[0]) {
+            return Self {
+                kind: SubframeKind::Constant,
+                cost: HEADER + u64::from(bits),
+            };
+        }
+        let mut best = Self {
+            kind: SubframeKind::Verbatim,
+            cost: HEADER + samples.len() as u64 * u64::from(bits),
+        };
+        for order in 0..=MAX_FIXED_ORDER.min(samples.len() - 1) {
+            let residual = fixed_residual(samples, order);
+            let (rice, rice_cost) = plan_rice(&residual, samples.len(), order);
+            let cost = HEADER + order as u64 * u64::from(bits) + rice_cost;
+            if cost < best.cost {
+                best = Self {
+                    kind: SubframeKind::Fixed { order, rice },
+                    cost,
+                };
+            }
+        }
+        best
+    }
+
+    fn write(&self, out: &mut BitWriter, samples: &[i64], bits: u32) {
+        match &self.kind {
+            SubframeKind::Constant => {
+                out.put(0, 8);
+                out.put_signed(samples[0], bits);
+            }
+            SubframeKind::Verbatim => {
+                out.put(0b0000_0010, 8);
+                for sample in samples {
+                    out.put_signed(*sample, bits);
+                }
+            }
+            SubframeKind::Fixed { order, rice } => {
+                out.put(0b0001_0000 | ((*order as u64) << 1), 8);
+                for sample in &samples[..*order] {
+                    out.put_signed(*sample, bits);
+                }
+                write_residual(out, &fixed_residual(samples, *order), samples.len(), rice);
+            }
+        }
+    }
+}
+
+/// FIXED 预测的残差，从第 `order` 个样本开始。
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    (order..samples.len())
+        .map(|i| {
+            let s = |back: usize| samples[i - back];
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// 一个分区用参数 `k` 做 Rice 编码的位数。
+fn rice_bits(values: &[u64], k: u32) -> u64 {
+    values.len() as u64 * u64::from(k + 1) + values.iter().map(|value| value >> k).sum::<u64>()
+}
+
+/// 按均值估出 Rice 参数，再比较相邻的几个取最短的。
+fn best_rice_param(values: &[u64]) -> (u32, u64) {
+    if values.is_empty() {
+        return (0, 0);
+    }
+    let mean = values.iter().sum::<u64>() / values.len() as u64;
+    let guess = 64 - mean.leading_zeros();
+    (guess.saturating_sub(1)..=guess + 1)
+        .filter(|k| *k <= 30)
+        .map(|k| (k, rice_bits(values, k)))
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap()
+}
+
+/// 在可用的分区阶数里选总位数最少的划分。
+fn plan_rice(residual: &[i64], block_size: usize, order: usize) -> (RicePlan, u64) {
+    let values: Vec<u64> = residual.iter().map(|value| zigzag(*value)).collect();
+    let mut best: Option<(RicePlan, u64)> = None;
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partitions = 1usize << partition_order;
+        if !block_size.is_multiple_of(partitions) || block_size / partitions <= order {
+            break;
+        }
+        let params: Vec<(u32, u64)> = partition_ranges(block_size, order, partition_order)
+            .map(|range| best_rice_param(&values[range]))
+            .collect();
+        let param_bits = if params.iter().any(|(k, _)| *k > 14) {
+            5
+        } else {
+            4
+        };
+        let cost = 6 + params
+            .iter()
+            .map(|(_, bits)| param_bits + bits)
+            .sum::<u64>();
+        if best.as_ref().is_none_or(|(_, best_cost)| cost < *best_cost) {
+            best = Some((
+                RicePlan {
+                    partition_order,
+                    params: params.into_iter().map(|(k, _)| k).collect(),
+                },
+                cost,
+            ));
+        }
+    }
+    best.unwrap()
+}
+
+/// 各分区在残差数组里的范围；第一个分区要扣掉预测器的预热样本。
+fn partition_ranges(
+    block_size: usize,
+    order: usize,
+    partition_order: u32,
+) -> impl Iterator<Item = std::ops::Range<usize>> {
+    let len = block_size >> partition_order;
+    (0..1usize << partition_order).map(move |index| {
+        let start = (index * len).saturating_sub(order);
+        let end = (index + 1) * len - order;
+        start..end
+    })
+}
+
+fn write_residual(out: &mut BitWriter, residual: &[i64], block_size: usize, plan: &RicePlan) {
+    let wide = plan.params.iter().any(|k| *k > 14);
+    out.put(u64::from(wide), 2);
+    out.put(u64::from(plan.partition_order), 4);
+    let order = block_size - residual.len();
+    for (range, k) in partition_ranges(block_size, order, plan.partition_order).zip(&plan.params) {
+        out.put(u64::from(*k), if wide { 5 } else { 4 });
+        for value in &residual[range] {
+            let value = zigzag(*value);
+            out.put_unary(value >> k);
+            out.put(value & ((1 << k) - 1), *k);
+        }
+    }
+}
+
+/// 帧头里的采样率编码；不在表里的采样率取 STREAMINFO 中的值。
+fn sample_rate_code(sample_rate: u32) -> u32 {
+    match sample_rate {
+        88_200 => 0b0001,
+        176_400 => 0b0010,
+        192_000 => 0b0011,
+        8_000 => 0b0100,
+        16_000 => 0b0101,
+        22_050 => 0b0110,
+        24_000 => 0b0111,
+        32_000 => 0b1000,
+        44_100 => 0b1001,
+        48_000 => 0b1010,
+        96_000 => 0b1011,
+        _ => 0,
+    }
+}
+
+fn block_header(last: bool, kind: u8, len: usize) -> [u8; 4] {
+    let len = (len as u32).to_be_bytes();
+    [u8::from(last) << 7 | kind, len[1], len[2], len[3]]
+}
+
+fn vorbis_comment(tags: &[(String, String)]) -> Vec<u8> {
+    let mut comment = Vec::new();
+    comment.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+    comment.extend_from_slice(VENDOR.as_bytes());
+    comment.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+    for (key, value) in tags {
+        let entry = format!("{key}={value}");
+        comment.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+        comment.extend_from_slice(entry.as_bytes());
+    }
+    comment
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, byte| {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// 按 MSB 优先写入的位流。
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn clear(&mut self) {
+        self.bytes.clear();
+        self.acc = 0;
+        self.bits = 0;
+    }
+
+    fn put(&mut self, value: u64, width: u32) {
+        let mut width = width;
+        while width > 0 {
+            let take = width.min(32);
+            width -= take;
+            let chunk = (value >> width) & ((1u64 << take) - 1);
+            self.acc = (self.acc << take) | chunk;
+            self.bits += take;
+            while self.bits >= 8 {
+                self.bits -= 8;
+                self.bytes.push((self.acc >> self.bits) as u8);
+            }
+        }
+    }
+
+    fn put_signed(&mut self, value: i64, width: u32) {
+        self.put(value as u64 & ((1u64 << width) - 1), width);
+    }
+
+    fn put_unary(&mut self, zeros: u64) {
+        let mut zeros = zeros;
+        while zeros >= 32 {
+            self.put(0, 32);
+            zeros -= 32;
+        }
+        self.put(1, zeros as u32 + 1);
+    }
+
+    /// 帧号按 UTF-8 的变长方式编码。
+    fn put_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.put(value, 8);
+            return;
+        }
+        let mut continuation = 1;
+        while value >> (6 * continuation) >= 1 << (6 - continuation) {
+            continuation += 1;
+        }
+        let lead = (0xff00u64 >> (continuation + 1)) & 0xff;
+        self.put(lead | (value >> (6 * continuation)), 8);
+        for index in (0..continuation).rev() {
+            self.put(0x80 | ((value >> (6 * index)) & 0x3f), 8);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.put(0, 8 - self.bits);
+        }
+    }
+
+    /// 已写满的字节；调用前需先对齐或只用于帧头 CRC。
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::registry;
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::probe::Hint;
+
+    fn decode(path: &Path) -> (u32, usize, Vec<i32>, registry::Tags) {
+        let file = File::open(path).unwrap();
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("flac");
+        let (mut format, tags) = registry::open_format_with_tags(mss, &hint, None).unwrap();
+        let track = format.default_track().unwrap().clone();
+        let mut decoder = registry::codec_registry()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .unwrap();
+        let mut samples = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            let mut buf = SampleBuffer::<i32>::new(decoded.frames() as u64, *decoded.spec());
+            buf.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buf.samples());
+        }
+        let channels = track.codec_params.channels.unwrap().count();
+        (
+            track.codec_params.sample_rate.unwrap(),
+            channels,
+            samples,
+            tags,
+        )
+    }
+
+    fn round_trip(channels: u16, bits: u16, sample_rate: u32, samples: &[i32]) {
+        let path = std::env::temp_dir().join(format!(
+            "ncm-flac-{}-{channels}-{bits}-{sample_rate}.flac",
+            std::process::id()
+        ));
+        let tags = vec![
+            ("TITLE".to_string(), "晴天".to_string()),
+            ("ARTIST".to_string(), "周杰伦".to_string()),
+        ];
+        let mut writer = FlacWriter::create(&path, channels, sample_rate, bits, &tags).unwrap();
+        for chunk in samples.chunks(1000 * usize::from(channels)) {
+            writer.write(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let (decoded_rate, decoded_channels, decoded, decoded_tags) = decode(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded_rate, sample_rate);
+        assert_eq!(decoded_channels, usize::from(channels));
+        // symphonia 把整数样本左对齐到 32 位。
+        let shift = 32 - u32::from(bits);
+        let decoded: Vec<i32> = decoded.iter().map(|sample| sample >> shift).collect();
+        assert_eq!(decoded.len(), samples.len());
+        assert!(decoded == samples, "decoded samples differ");
+        assert!(decoded_tags.contains(&("TITLE".to_string(), "晴天".to_string())));
+        assert!(decoded_tags.contains(&("ARTIST".to_string(), "周杰伦".to_string())));
+    }
+
+    fn test_signal(channels: u16, bits: u16, frames: usize) -> Vec<i32> {
+        let peak = f64::from((1 << (bits - 1)) - 1);
+        let mut seed = 0x2545_f491_u32;
+        (0..frames)
+            .flat_map(|frame| (0..channels).map(move |channel| (frame, channel)))
+            .map(|(frame, channel)| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let tone = (frame as f64 * (0.01 + f64::from(channel) * 0.003)).sin() * 0.6;
+                let noise = f64::from(seed % 2001) / 1000.0 - 1.0;
+                ((tone + noise * 0.05) * peak).round() as i32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stereo_16_bit_round_trips_through_symphonia() {
+        round_trip(2, 16, 44_100, &test_signal(2, 16, BLOCK_SIZE * 2 + 1234));
+    }
+
+    #[test]
+    fn multichannel_24_bit_and_uncommon_rate_round_trip() {
+        round_trip(3, 24, 37_800, &test_signal(3, 24, BLOCK_SIZE + 77));
+    }
+
+    #[test]
+    fn silence_and_full_scale_extremes_round_trip() {
+        let mut samples = vec![0; 2 * 3000];
+        samples.extend((0..2 * 3000).map(|i| if i % 3 == 0 { 32767 } else { -32768 }));
+        round_trip(2, 16, 48_000, &samples);
+    }
+
+    #[test]
+    fn large_frame_numbers_use_multi_byte_utf8() {
+        let mut writer = BitWriter::default();
+        writer.put_utf8(0x7f);
+        writer.put_utf8(0x80);
+        writer.put_utf8(0x1_0000);
+        assert_eq!(writer.bytes(), &[0x7f, 0xc2, 0x80, 0xf0, 0x90, 0x80, 0x80]);
+    }
+
+    #[test]
+    fn frame_checksums_match_reference_values() {
+        assert_eq!(crc8(b"123456789"), 0xf4);
+        assert_eq!(crc16(b"123456789"), 0xfee8);
+    }
+}
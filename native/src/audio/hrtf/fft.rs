@@ -0,0 +1,139 @@
+//! 双耳渲染用的基 2 复数 FFT，长度固定，旋转因子与位反转表预先算好。
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Complex {
+    pub(crate) re: f32,
+    pub(crate) im: f32,
+}
+
+impl Complex {
+    pub(crate) fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    /// `self += a * b`，频域卷积累加用。
+    pub(crate) fn mul_add(&mut self, a: Self, b: Self) {
+        let product = a.mul(b);
+        self.re += product.re;
+        self.im += product.im;
+    }
+}
+
+pub(crate) struct Fft {
+    len: usize,
+    twiddles: Vec<Complex>,
+    bit_reverse: Vec<usize>,
+}
+
+impl Fft {
+    /// `len` 必须是 2 的幂。
+    pub(crate) fn new(len: usize) -> Self {
+        assert!(len.is_power_of_two(), "FFT 长度必须是 2 的幂");
+        let bits = len.trailing_zeros();
+        let twiddles = (0..len / 2)
+            .map(|index| {
+                let angle = -2.0 * std::f64::consts::PI * index as f64 / len as f64;
+                Complex::new(angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+        let bit_reverse = (0..len)
+            .map(|index| {
+                if bits == 0 {
+                    0
+                } else {
+                    index.reverse_bits() >> (usize::BITS - bits)
+                }
+            })
+            .collect();
+        Self {
+            len,
+            twiddles,
+            bit_reverse,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn forward(&self, buf: &mut [Complex]) {
+        self.transform(buf, false);
+    }
+
+    /// 逆变换，结果已除以长度。
+    pub(crate) fn inverse(&self, buf: &mut [Complex]) {
+        self.transform(buf, true);
+        let scale = 1.0 / self.len as f32;
+        for value in buf.iter_mut() {
+            value.re *= scale;
+            value.im *= scale;
+        }
+    }
+
+    fn transform(&self, buf: &mut [Complex], inverse: bool) {
+        debug_assert_eq!(buf.len(), self.len);
+        for (index, &reversed) in self.bit_reverse.iter().enumerate() {
+            if index < reversed {
+                buf.swap(index, reversed);
+            }
+        }
+
+        let mut size = 2;
+        while size <= self.len {
+            let half = size / 2;
+            let step = self.len / size;
+            for start in (0..self.len).step_by(size) {
+                for offset in 0..half {
+                    let mut twiddle = self.twiddles[offset * step];
+                    if inverse {
+                        twiddle.im = -twiddle.im;
+                    }
+                    let even = buf[start + offset];
+                    let odd = buf[start + offset + half].mul(twiddle);
+                    buf[start + offset] = Complex::new(even.re + odd.re, even.im + odd.im);
+                    buf[start + offset + half] = Complex::new(even.re - odd.re, even.im - odd.im);
+                }
+            }
+            size *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_direct_dft_and_round_trips() {
+        let fft = Fft::new(16);
+        let input: Vec<Complex> = (0..16)
+            .map(|index| Complex::new((index as f32 * 0.7).sin(), (index % 3) as f32))
+            .collect();
+        let mut spectrum = input.clone();
+        fft.forward(&mut spectrum);
+
+        for (bin, value) in spectrum.iter().enumerate() {
+            let (mut re, mut im) = (0.0f64, 0.0f64);
+            for (index, sample) in input.iter().enumerate() {
+                let angle = -2.0 * std::f64::consts::PI * (bin * index) as f64 / 16.0;
+                re += f64::from(sample.re) * angle.cos() - f64::from(sample.im) * angle.sin();
+                im += f64::from(sample.re) * angle.sin() + f64::from(sample.im) * angle.cos();
+            }
+            assert!((f64::from(value.re) - re).abs() < 1e-4);
+            assert!((f64::from(value.im) - im).abs() < 1e-4);
+        }
+
+        fft.inverse(&mut spectrum);
+        for (restored, original) in spectrum.iter().zip(&input) {
+            assert!((restored.re - original.re).abs() < 1e-5);
+            assert!((restored.im - original.im).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,598 @@
+//! HRTF 双耳渲染：把 5.1/7.1 等多声道音源的每个声道当作一只虚拟扬声器，
+//! 用该方向的头相关脉冲响应（HRIR）卷积到左右耳，代替耳机上的简单下混。
+//! HRIR 取自内置的球形头模型或用户提供的 SOFA 文件。渲染在解码线程里进行，
+//! ring buffer 和输出流都是双声道；HRIR 开头的纯延迟裁掉，直达声的到达延迟计入播放时钟。
+
+mod fft;
+pub(crate) mod sofa;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use fft::{Complex, Fft};
+use symphonia::core::audio::Channels;
+
+/// 每次卷积的块长（帧）。
+const BLOCK_FRAMES: usize = 512;
+/// 内置模型的 HRIR 长度（秒），覆盖双耳时间差与头影滤波的衰减。
+const BUILTIN_IR_SECONDS: f64 = 0.004;
+/// 球形头模型的头半径（米）与声速（米/秒）。
+const HEAD_RADIUS: f64 = 0.0875;
+const SPEED_OF_SOUND: f64 = 343.0;
+/// 多声道叠加后的总增益，留出余量避免削波。
+const OUTPUT_GAIN: f32 = 0.5;
+const LFE_GAIN: f32 = 0.5;
+/// 所有 HRIR 开头都低于峰值这一比例的部分是纯延迟，直接裁掉。
+const LEAD_THRESHOLD: f32 = 1e-3;
+/// 达到自身峰值这一比例处视为直达声到达，用来估计渲染引入的延迟。
+const ONSET_THRESHOLD: f32 = 0.1;
+
+/// 一个方向的左右耳脉冲响应。方位角逆时针为正（左侧为 +90°），0° 为正前方。
+#[derive(Clone, Debug)]
+pub(crate) struct Hrir {
+    azimuth: f64,
+    elevation: f64,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct HrirSet {
+    name: String,
+    sample_rate: u32,
+    hrirs: Vec<Hrir>,
+}
+
+impl HrirSet {
+    /// Brown–Duda 球形头模型：双耳时间差按 Woodworth 公式，头影用一阶高架滤波。
+    pub(crate) fn spherical_head(sample_rate: u32) -> Self {
+        let len = ((f64::from(sample_rate) * BUILTIN_IR_SECONDS) as usize)
+            .next_power_of_two()
+            .max(32);
+        let hrirs = (0..72)
+            .map(|step| {
+                let azimuth = wrap_degrees(f64::from(step) * 5.0);
+                Hrir {
+                    azimuth,
+                    elevation: 0.0,
+                    left: head_shadow_ir((azimuth - 90.0).abs(), sample_rate, len),
+                    right: head_shadow_ir((azimuth + 90.0).abs(), sample_rate, len),
+                }
+            })
+            .collect();
+        let mut set = Self {
+            name: "内置球形头模型".to_string(),
+            sample_rate,
+            hrirs,
+        };
+        set.normalize();
+        set
+    }
+
+    /// 读取 SOFA（SimpleFreeFieldHRIR）文件。
+    pub(crate) fn load_sofa(path: &Path) -> std::io::Result<Self> {
+        let mut set = sofa::read_file(path)?;
+        set.normalize();
+        Ok(set)
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 与给定方向夹角最小的测量点。
+    fn nearest(&self, azimuth: f64, elevation: f64) -> Option<&Hrir> {
+        let target = direction(azimuth, elevation);
+        self.hrirs.iter().max_by(|a, b| {
+            let a = dot(direction(a.azimuth, a.elevation), target);
+            let b = dot(direction(b.azimuth, b.elevation), target);
+            a.total_cmp(&b)
+        })
+    }
+
+    /// 按正前方 HRIR 的能量归一化，不同数据集听感响度接近。
+    fn normalize(&mut self) {
+        let Some(front) = self.nearest(0.0, 0.0) else {
+            return;
+        };
+        let energy = front
+            .left
+            .iter()
+            .chain(&front.right)
+            .map(|sample| sample * sample)
+            .sum::<f32>()
+            / 2.0;
+        if energy <= f32::EPSILON {
+            return;
+        }
+        let scale = energy.sqrt().recip();
+        for hrir in &mut self.hrirs {
+            for sample in hrir.left.iter_mut().chain(hrir.right.iter_mut()) {
+                *sample *= scale;
+            }
+        }
+    }
+}
+
+/// 双耳渲染使用的 HRTF 数据，按输出设备选择。
+#[derive(Clone, Debug)]
+pub(crate) enum HrtfDataset {
+    Builtin,
+    Sofa(Arc<HrirSet>),
+}
+
+impl HrtfDataset {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            HrtfDataset::Builtin => "内置球形头模型",
+            HrtfDataset::Sofa(set) => set.name(),
+        }
+    }
+}
+
+fn wrap_degrees(degrees: f64) -> f64 {
+    let wrapped = degrees.rem_euclid(360.0);
+    if wrapped > 180.0 {
+        wrapped - 360.0
+    } else {
+        wrapped
+    }
+}
+
+fn direction(azimuth: f64, elevation: f64) -> [f64; 3] {
+    let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+    [
+        elevation.cos() * azimuth.cos(),
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// 声源与耳朵轴线夹角为 `incidence`（度）时该耳的脉冲响应。
+fn head_shadow_ir(incidence: f64, sample_rate: u32, len: usize) -> Vec<f32> {
+    const ALPHA_MIN: f64 = 0.1;
+    const THETA_MIN: f64 = 150.0;
+    let incidence = incidence.min(360.0 - incidence);
+    let alpha = (1.0 + ALPHA_MIN / 2.0)
+        + (1.0 - ALPHA_MIN / 2.0) * (incidence / THETA_MIN * 180.0).to_radians().cos();
+    let theta = incidence.to_radians();
+    let head_delay = HEAD_RADIUS / SPEED_OF_SOUND;
+    let delay = if theta < std::f64::consts::FRAC_PI_2 {
+        head_delay * (1.0 - theta.cos())
+    } else {
+        head_delay * (1.0 + theta - std::f64::consts::FRAC_PI_2)
+    };
+
+    // 分数延迟的冲激，线性插值到相邻两个样本。
+    let mut impulse = vec![0.0f64; len];
+    let position = delay * f64::from(sample_rate);
+    let index = position.floor() as usize;
+    let fraction = position - position.floor();
+    if index + 1 < len {
+        impulse[index] = 1.0 - fraction;
+        impulse[index + 1] = fraction;
+    }
+
+    // 一阶头影滤波 (2ω0 + αs) / (2ω0 + s)，双线性变换离散化。
+    let omega = 2.0 * SPEED_OF_SOUND / HEAD_RADIUS;
+    let k = 2.0 * f64::from(sample_rate);
+    let (b0, b1) = (omega + alpha * k, omega - alpha * k);
+    let (a0, a1) = (omega + k, omega - k);
+    let mut previous_input = 0.0;
+    let mut previous_output = 0.0;
+    impulse
+        .into_iter()
+        .map(|input| {
+            let output = (b0 * input + b1 * previous_input - a1 * previous_output) / a0;
+            previous_input = input;
+            previous_output = output;
+            output as f32
+        })
+        .collect()
+}
+
+/// 加 Hann 窗的 sinc 插值重采样，幅度按采样率比例缩放，保持频率响应不变。
+fn resample_ir(ir: &[f32], from: u32, to: u32) -> Vec<f32> {
+    const HALF_TAPS: f64 = 16.0;
+    if from == to || ir.is_empty() {
+        return ir.to_vec();
+    }
+    let ratio = f64::from(to) / f64::from(from);
+    let cutoff = ratio.min(1.0);
+    let half_width = HALF_TAPS / cutoff;
+    let out_len = (ir.len() as f64 * ratio).ceil() as usize;
+    (0..out_len)
+        .map(|index| {
+            let center = index as f64 / ratio;
+            let first = (center - half_width).ceil().max(0.0) as usize;
+            let last = ((center + half_width).floor() as usize).min(ir.len() - 1);
+            let sum: f64 = (first..=last)
+                .map(|tap| {
+                    let x = center - tap as f64;
+                    let window = 0.5 + 0.5 * (std::f64::consts::PI * x / half_width).cos();
+                    let arg = std::f64::consts::PI * cutoff * x;
+                    let sinc = if arg.abs() < 1e-9 {
+                        1.0
+                    } else {
+                        arg.sin() / arg
+                    };
+                    f64::from(ir[tap]) * cutoff * sinc * window
+                })
+                .sum();
+            (sum / ratio) as f32
+        })
+        .collect()
+}
+
+/// 容器没有给出声道掩码时，按 WAVE/FLAC 的默认声道顺序推断。
+pub(crate) fn default_layout(channels: u16) -> Option<Channels> {
+    const FRONT: Channels = Channels::FRONT_LEFT.union(Channels::FRONT_RIGHT);
+    const REAR: Channels = Channels::REAR_LEFT.union(Channels::REAR_RIGHT);
+    const SIDE: Channels = Channels::SIDE_LEFT.union(Channels::SIDE_RIGHT);
+    const CENTRE_LFE: Channels = Channels::FRONT_CENTRE.union(Channels::LFE1);
+    Some(match channels {
+        3 => FRONT | Channels::FRONT_CENTRE,
+        4 => FRONT | REAR,
+        5 => FRONT | Channels::FRONT_CENTRE | REAR,
+        6 => FRONT | CENTRE_LFE | REAR,
+        7 => FRONT | CENTRE_LFE | Channels::REAR_CENTRE | SIDE,
+        8 => FRONT | CENTRE_LFE | REAR | SIDE,
+        _ => return None,
+    })
+}
+
+/// 按声道掩码的位顺序（即交错样本顺序）给出每个声道的虚拟扬声器方位（度）与增益；
+/// 方位为 `None` 的是 LFE，不卷积直接混入两耳。含有无法定位的声道时返回 `None`，退回普通下混。
+fn speaker_layout(channels: Channels) -> Option<Vec<(Option<f64>, f32)>> {
+    const SIDE: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    if channels.count() < 3 {
+        return None;
+    }
+    // 有侧环绕时后环绕放在 ±150°，否则后环绕就是 5.1 的环绕声道，放在 ±110°。
+    let rear = if channels.contains(Channels::SIDE_LEFT | Channels::SIDE_RIGHT) {
+        150.0
+    } else {
+        110.0
+    };
+    channels
+        .iter()
+        .map(|channel| {
+            Some(match channel {
+                Channels::FRONT_LEFT => (Some(30.0), 1.0),
+                Channels::FRONT_RIGHT => (Some(-30.0), 1.0),
+                Channels::FRONT_LEFT_CENTRE => (Some(15.0), 1.0),
+                Channels::FRONT_RIGHT_CENTRE => (Some(-15.0), 1.0),
+                Channels::FRONT_CENTRE => (Some(0.0), SIDE),
+                Channels::LFE1 | Channels::LFE2 => (None, LFE_GAIN),
+                Channels::REAR_LEFT => (Some(rear), SIDE),
+                Channels::REAR_RIGHT => (Some(-rear), SIDE),
+                Channels::REAR_CENTRE => (Some(180.0), SIDE),
+                Channels::SIDE_LEFT => (Some(90.0), SIDE),
+                Channels::SIDE_RIGHT => (Some(-90.0), SIDE),
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+/// 多声道到双耳的分块 FFT 卷积（overlap-add），输出帧数与输入相同。
+pub(crate) struct BinauralRenderer {
+    channels: usize,
+    /// 需要卷积的声道及其左右耳滤波器的频谱（已乘上增益）。
+    filters: Vec<(usize, [Vec<Complex>; 2])>,
+    /// LFE 声道及其增益。
+    lfe: Vec<(usize, f32)>,
+    fft: Fft,
+    block: usize,
+    input: Vec<Complex>,
+    ears: [Vec<Complex>; 2],
+    tails: [Vec<f32>; 2],
+    latency_frames: u64,
+}
+
+impl BinauralRenderer {
+    /// 声道掩码里有无法定位的声道，或数据集里找不到 HRIR 时返回 `None`。
+    pub(crate) fn new(set: &HrirSet, layout: Channels, sample_rate: u32) -> Option<Self> {
+        let channels = layout.count();
+        let layout = speaker_layout(layout)?;
+        let mut lfe = Vec::new();
+        let mut irs = Vec::new();
+        for (channel, &(azimuth, gain)) in layout.iter().enumerate() {
+            let Some(azimuth) = azimuth else {
+                lfe.push((channel, gain * OUTPUT_GAIN));
+                continue;
+            };
+            let hrir = set.nearest(azimuth, 0.0)?;
+            let left = resample_ir(&hrir.left, set.sample_rate, sample_rate);
+            let right = resample_ir(&hrir.right, set.sample_rate, sample_rate);
+            irs.push((channel, gain * OUTPUT_GAIN, [left, right]));
+        }
+
+        let peak = irs
+            .iter()
+            .flat_map(|(_, _, ears)| ears.iter().flatten())
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        if peak <= 0.0 {
+            return None;
+        }
+        let lead = irs
+            .iter()
+            .flat_map(|(_, _, ears)| ears.iter())
+            .filter_map(|ir| ir.iter().position(|s| s.abs() > peak * LEAD_THRESHOLD))
+            .min()
+            .unwrap_or(0);
+        let latency_frames = irs
+            .iter()
+            .flat_map(|(_, _, ears)| ears.iter())
+            .filter_map(|ir| {
+                let ear_peak = ir.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+                ir.iter()
+                    .position(|s| ear_peak > 0.0 && s.abs() >= ear_peak * ONSET_THRESHOLD)
+            })
+            .min()
+            .unwrap_or(lead)
+            .saturating_sub(lead) as u64;
+        let ir_len = irs
+            .iter()
+            .flat_map(|(_, _, ears)| ears.iter())
+            .map(|ir| ir.len().saturating_sub(lead))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+
+        let block = BLOCK_FRAMES;
+        let fft = Fft::new((block + ir_len - 1).next_power_of_two());
+        let spectrum = |ir: &[f32], gain: f32| {
+            let mut buf = vec![Complex::default(); fft.len()];
+            for (value, sample) in buf.iter_mut().zip(ir.iter().skip(lead)) {
+                value.re = sample * gain;
+            }
+            fft.forward(&mut buf);
+            buf
+        };
+        let filters = irs
+            .iter()
+            .map(|(channel, gain, [left, right])| {
+                (*channel, [spectrum(left, *gain), spectrum(right, *gain)])
+            })
+            .collect();
+
+        let len = fft.len();
+        Some(Self {
+            channels,
+            filters,
+            lfe,
+            fft,
+            block,
+            input: vec![Complex::default(); len],
+            ears: [vec![Complex::default(); len], vec![Complex::default(); len]],
+            tails: [vec![0.0; len], vec![0.0; len]],
+            latency_frames,
+        })
+    }
+
+    /// 直达声相对输入延后的帧数。
+    pub(crate) fn latency_frames(&self) -> u64 {
+        self.latency_frames
+    }
+
+    /// seek 之后丢掉上一段的混响尾巴。
+    pub(crate) fn reset(&mut self) {
+        for tail in &mut self.tails {
+            tail.fill(0.0);
+        }
+    }
+
+    /// 交错的多声道输入渲染成交错的双声道输出。
+    pub(crate) fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        let frames = input.len() / self.channels;
+        output.clear();
+        output.reserve(frames * 2);
+        for block_start in (0..frames).step_by(self.block) {
+            let block_frames = self.block.min(frames - block_start);
+            let block =
+                &input[block_start * self.channels..(block_start + block_frames) * self.channels];
+            self.convolve_block(block, block_frames);
+            for (frame, samples) in block.chunks_exact(self.channels).enumerate() {
+                let lfe: f32 = self
+                    .lfe
+                    .iter()
+                    .map(|&(channel, gain)| samples[channel] * gain)
+                    .sum();
+                output.push(self.ears[0][frame].re + lfe);
+                output.push(self.ears[1][frame].re + lfe);
+            }
+        }
+    }
+
+    fn convolve_block(&mut self, block: &[f32], frames: usize) {
+        for ear in &mut self.ears {
+            ear.fill(Complex::default());
+        }
+        for (channel, spectra) in &self.filters {
+            self.input.fill(Complex::default());
+            for (value, samples) in self.input.iter_mut().zip(block.chunks_exact(self.channels)) {
+                value.re = samples[*channel];
+            }
+            self.fft.forward(&mut self.input);
+            for (ear, spectrum) in self.ears.iter_mut().zip(spectra) {
+                for ((acc, input), filter) in ear.iter_mut().zip(&self.input).zip(spectrum) {
+                    acc.mul_add(*input, *filter);
+                }
+            }
+        }
+        for (ear, tail) in self.ears.iter_mut().zip(&mut self.tails) {
+            self.fft.inverse(ear);
+            for (value, carried) in ear.iter_mut().zip(tail.iter()) {
+                value.re += carried;
+            }
+            tail.fill(0.0);
+            for (carried, value) in tail.iter_mut().zip(&ear[frames..]) {
+                *carried = value.re;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn energy(samples: impl Iterator<Item = f32>) -> f32 {
+        samples.map(|sample| sample * sample).sum()
+    }
+
+    #[test]
+    fn speakers_are_placed_on_the_matching_side() {
+        let set = HrirSet::spherical_head(48_000);
+        let mut renderer = BinauralRenderer::new(&set, default_layout(6).unwrap(), 48_000).unwrap();
+
+        // 只有左环绕声道有声音：左耳明显更响，且右耳比左耳晚到。
+        let mut input = vec![0.0f32; 6 * 1024];
+        input[4] = 1.0;
+        let mut output = Vec::new();
+        renderer.process(&input, &mut output);
+        assert_eq!(output.len(), 2 * 1024);
+        let left = energy(output.iter().step_by(2).copied());
+        let right = energy(output.iter().skip(1).step_by(2).copied());
+        assert!(left > right * 1.5, "left={left} right={right}");
+        let onset = |ear: usize| {
+            output
+                .iter()
+                .skip(ear)
+                .step_by(2)
+                .position(|sample| sample.abs() > 0.05)
+                .unwrap()
+        };
+        assert!(onset(0) < onset(1));
+
+        // 中置声道左右对称。
+        let mut input = vec![0.0f32; 6 * 256];
+        input[2] = 1.0;
+        renderer.reset();
+        renderer.process(&input, &mut output);
+        for frame in output.chunks_exact(2) {
+            assert!((frame[0] - frame[1]).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn block_boundaries_do_not_change_the_output() {
+        let set = HrirSet::spherical_head(44_100);
+        let input: Vec<f32> = (0..8 * 1500)
+            .map(|index| ((index * 7919) % 200) as f32 / 100.0 - 1.0)
+            .collect();
+
+        let mut whole = BinauralRenderer::new(&set, default_layout(8).unwrap(), 44_100).unwrap();
+        let mut expected = Vec::new();
+        whole.process(&input, &mut expected);
+
+        let mut split = BinauralRenderer::new(&set, default_layout(8).unwrap(), 44_100).unwrap();
+        let mut actual = Vec::new();
+        let mut chunk = Vec::new();
+        for part in [
+            &input[..8 * 700],
+            &input[8 * 700..8 * 701],
+            &input[8 * 701..],
+        ] {
+            split.process(part, &mut chunk);
+            actual.extend_from_slice(&chunk);
+        }
+        assert_eq!(actual.len(), expected.len());
+        for (a, b) in actual.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn pure_delay_is_trimmed_and_pre_ringing_counts_as_latency() {
+        // 开头 20 个样本的纯延迟被裁掉；低电平的前振铃保留，直达声晚到 6 帧。
+        let ir = |peak: usize| {
+            let mut ir = vec![0.0f32; 64];
+            ir[14] = 0.01;
+            ir[peak] = 1.0;
+            ir
+        };
+        let set = HrirSet {
+            name: "test".to_string(),
+            sample_rate: 48_000,
+            hrirs: vec![Hrir {
+                azimuth: 0.0,
+                elevation: 0.0,
+                left: ir(20),
+                right: ir(24),
+            }],
+        };
+        let mut renderer = BinauralRenderer::new(&set, default_layout(3).unwrap(), 48_000).unwrap();
+        assert_eq!(renderer.latency_frames(), 6);
+
+        let mut input = vec![0.0f32; 3 * 64];
+        input[0] = 1.0;
+        let mut output = Vec::new();
+        renderer.process(&input, &mut output);
+        let peak = |ear: usize| {
+            (0..64)
+                .max_by(|&a, &b| {
+                    output[a * 2 + ear]
+                        .abs()
+                        .total_cmp(&output[b * 2 + ear].abs())
+                })
+                .unwrap()
+        };
+        assert_eq!((peak(0), peak(1)), (6, 10));
+    }
+
+    #[test]
+    fn only_known_multichannel_layouts_are_rendered() {
+        let set = HrirSet::spherical_head(48_000);
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        assert!(BinauralRenderer::new(&set, stereo, 48_000).is_none());
+        assert_eq!(default_layout(12), None);
+        let top = default_layout(6).unwrap() | Channels::TOP_CENTRE;
+        assert!(BinauralRenderer::new(&set, top, 48_000).is_none());
+        assert!(BinauralRenderer::new(&set, default_layout(5).unwrap(), 96_000).is_some());
+    }
+
+    #[test]
+    fn channel_mask_decides_where_each_channel_is_placed() {
+        // 4.1：FL FR LFE RL RR，第三个声道是 LFE 而不是中置。
+        let layout = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::LFE1
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT;
+        assert_eq!(
+            speaker_layout(layout).unwrap(),
+            vec![
+                (Some(30.0), 1.0),
+                (Some(-30.0), 1.0),
+                (None, LFE_GAIN),
+                (Some(110.0), std::f32::consts::FRAC_1_SQRT_2),
+                (Some(-110.0), std::f32::consts::FRAC_1_SQRT_2),
+            ]
+        );
+
+        let set = HrirSet::spherical_head(48_000);
+        let mut renderer = BinauralRenderer::new(&set, layout, 48_000).unwrap();
+        let mut input = vec![0.0f32; 5 * 256];
+        input[2] = 1.0;
+        let mut output = Vec::new();
+        renderer.process(&input, &mut output);
+        assert_eq!(&output[..2], &[LFE_GAIN * OUTPUT_GAIN; 2]);
+        assert!(output[2..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn resampling_keeps_the_low_frequency_gain() {
+        let ir: Vec<f32> = (0..64).map(|i| if i == 8 { 1.0 } else { 0.0 }).collect();
+        for to in [22_050, 96_000] {
+            let resampled = resample_ir(&ir, 48_000, to);
+            let dc: f32 = resampled.iter().sum();
+            assert!((dc - 1.0).abs() < 0.05, "{to}: {dc}");
+        }
+    }
+}
@@ -0,0 +1,1601 @@
+//! SOFA（AES69）HRTF 文件读取。SOFA 是 netCDF-4/HDF5 文件，这里只实现读取
+//! SimpleFreeFieldHRIR 约定所需的 HDF5 子集：
+//! - 超级块 v0–v3，对象头 v1/v2；
+//! - 符号表组、紧凑链接和分形堆里的密集链接；
+//! - 紧凑、连续、分块（v1 B 树、单块、隐式、固定数组索引）存储，deflate/shuffle 过滤器；
+//! - 浮点与整数数据，定长字符串属性。
+//!
+//! 读取的变量：`Data.IR`（M×2×N）、`Data.SamplingRate`、`SourcePosition`（M×3）
+//! 和可选的 `Data.Delay`。文件里的校验和不做校验。
+
+use std::io;
+use std::path::Path;
+
+use super::{Hrir, HrirSet};
+
+const SIGNATURE: &[u8; 8] = b"\x89HDF\r\n\x1a\n";
+/// SOFA 文件大小上限，整个文件读入内存解析。
+const MAX_FILE_LEN: u64 = 512 * 1024 * 1024;
+/// 单个数据集解开后的大小上限。
+const MAX_DATASET_LEN: u64 = 256 * 1024 * 1024;
+/// 隐式索引分块数据集的分块数上限，防止损坏的维度信息撑爆分块列表。
+const MAX_IMPLICIT_CHUNKS: u64 = 1 << 20;
+/// 对象头续块与 B 树的嵌套上限，防止损坏文件里的环。
+const MAX_DEPTH: usize = 32;
+
+const MSG_DATASPACE: u16 = 0x01;
+const MSG_LINK_INFO: u16 = 0x02;
+const MSG_DATATYPE: u16 = 0x03;
+const MSG_LINK: u16 = 0x06;
+const MSG_LAYOUT: u16 = 0x08;
+const MSG_FILTERS: u16 = 0x0b;
+const MSG_ATTRIBUTE: u16 = 0x0c;
+const MSG_CONTINUATION: u16 = 0x10;
+const MSG_SYMBOL_TABLE: u16 = 0x11;
+
+const FILTER_DEFLATE: u16 = 1;
+const FILTER_SHUFFLE: u16 = 2;
+const FILTER_FLETCHER32: u16 = 3;
+
+pub(crate) fn read_file(path: &Path) -> io::Result<HrirSet> {
+    let len = std::fs::metadata(path)?.len();
+    if len > MAX_FILE_LEN {
+        return Err(invalid_data(format!("SOFA 文件过大：{} 字节", len)));
+    }
+    let data = std::fs::read(path)?;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "SOFA".to_string());
+    parse(&data, name)
+}
+
+fn parse(data: &[u8], name: String) -> io::Result<HrirSet> {
+    let (file, root) = Hdf5::open(data)?;
+    let links = file.group_links(root)?;
+    let dataset = |variable: &str| -> io::Result<Option<Dataset>> {
+        links
+            .iter()
+            .find(|(name, _)| name == variable)
+            .map(|&(_, addr)| file.dataset(addr))
+            .transpose()
+    };
+    let missing = |variable: &str| invalid_data(format!("SOFA 文件缺少 {variable}"));
+
+    let ir = dataset("Data.IR")?.ok_or_else(|| missing("Data.IR"))?;
+    let [measurements, receivers, taps] = ir.dims[..] else {
+        return Err(invalid_data("Data.IR 应为 M×R×N 三维数组"));
+    };
+    if receivers != 2 {
+        return Err(invalid_data(format!(
+            "只支持双耳 HRIR，Data.IR 有 {receivers} 个接收点"
+        )));
+    }
+    let (measurements, taps) = (measurements as usize, taps as usize);
+    let sample_rate = dataset("Data.SamplingRate")?
+        .ok_or_else(|| missing("Data.SamplingRate"))?
+        .values
+        .first()
+        .copied()
+        .filter(|rate| rate.is_finite() && (1.0..=1_000_000.0).contains(rate))
+        .ok_or_else(|| invalid_data("Data.SamplingRate 无效"))? as u32;
+    let positions = dataset("SourcePosition")?.ok_or_else(|| missing("SourcePosition"))?;
+    if positions.dims.len() != 2 || positions.dims[1] != 3 {
+        return Err(invalid_data("SourcePosition 应为 M×3 二维数组"));
+    }
+    let cartesian = positions
+        .attribute("Type")
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("cartesian"));
+    let delays = dataset("Data.Delay")?;
+
+    // 按测量点取一行；只有一行（所有测量点共用）时都取第一行。
+    let row = |dataset: &Dataset, index: usize, width: usize| -> Vec<f64> {
+        let rows = dataset.values.len() / width;
+        let row = index.min(rows.saturating_sub(1));
+        dataset
+            .values
+            .get(row * width..(row + 1) * width)
+            .map(<[f64]>::to_vec)
+            .unwrap_or_default()
+    };
+    let hrirs = (0..measurements)
+        .map(|index| {
+            let (azimuth, elevation) = match row(&positions, index, 3)[..] {
+                [x, y, z] if cartesian => {
+                    (y.atan2(x).to_degrees(), z.atan2(x.hypot(y)).to_degrees())
+                }
+                [azimuth, elevation, _] => (azimuth, elevation),
+                _ => return Err(invalid_data("SourcePosition 数据不完整")),
+            };
+            let delay = delays
+                .as_ref()
+                .map(|delays| row(delays, index, 2))
+                .unwrap_or_default();
+            let ear = |receiver: usize| {
+                let start = (index * 2 + receiver) * taps;
+                let leading = delay
+                    .get(receiver)
+                    .map_or(0, |delay| delay.clamp(0.0, taps as f64).round() as usize);
+                let values = ir
+                    .values
+                    .get(start..start + taps)
+                    .ok_or_else(|| invalid_data("Data.IR 数据不完整"))?;
+                let mut samples = vec![0.0f32; leading];
+                samples.extend(values.iter().map(|&v| v as f32));
+                Ok::<_, io::Error>(samples)
+            };
+            Ok(Hrir {
+                azimuth,
+                elevation,
+                left: ear(0)?,
+                right: ear(1)?,
+            })
+        })
+        .collect::<io::Result<Vec<Hrir>>>()?;
+    if hrirs.is_empty() {
+        return Err(invalid_data("SOFA 文件没有测量点"));
+    }
+    Ok(HrirSet {
+        name,
+        sample_rate,
+        hrirs,
+    })
+}
+
+/// 按各维长度算出的字节数；溢出或超过 `MAX_DATASET_LEN` 时视为损坏。
+fn byte_len(dims: &[u64], element_size: usize) -> io::Result<u64> {
+    dims.iter()
+        .try_fold(element_size as u64, |len, dim| len.checked_mul(*dim))
+        .filter(|len| *len <= MAX_DATASET_LEN)
+        .ok_or_else(|| invalid_data("HDF5 数据集过大"))
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// 小端字节游标；地址与长度字段的宽度由超级块决定。
+#[derive(Clone)]
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(invalid_data("HDF5 数据被截断"));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(self.uint(4)? as u32)
+    }
+
+    /// `len` 字节的小端无符号整数（最多 8 字节）。
+    fn uint(&mut self, len: usize) -> io::Result<u64> {
+        if len > 8 {
+            return Err(invalid_data("HDF5 整数字段过宽"));
+        }
+        Ok(self
+            .take(len)?
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| (acc << 8) | u64::from(b)))
+    }
+
+    fn signature(&mut self, expected: &[u8; 4]) -> io::Result<()> {
+        if self.take(4)? != expected {
+            return Err(invalid_data(format!(
+                "HDF5 结构签名不是 {}",
+                String::from_utf8_lossy(expected)
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 对象头里的一条消息。
+struct Message<'a> {
+    kind: u16,
+    body: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NumberType {
+    Float {
+        size: usize,
+        big_endian: bool,
+    },
+    Integer {
+        size: usize,
+        big_endian: bool,
+        signed: bool,
+    },
+    String {
+        size: usize,
+    },
+    Other,
+}
+
+impl NumberType {
+    fn size(self) -> usize {
+        match self {
+            NumberType::Float { size, .. }
+            | NumberType::Integer { size, .. }
+            | NumberType::String { size } => size,
+            NumberType::Other => 0,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> f64 {
+        let ordered = |big_endian: bool| {
+            let mut raw = [0u8; 8];
+            if big_endian {
+                for (target, source) in raw.iter_mut().zip(bytes.iter().rev()) {
+                    *target = *source;
+                }
+            } else {
+                raw[..bytes.len()].copy_from_slice(bytes);
+            }
+            u64::from_le_bytes(raw)
+        };
+        match self {
+            NumberType::Float {
+                size: 4,
+                big_endian,
+            } => f64::from(f32::from_bits(ordered(big_endian) as u32)),
+            NumberType::Float { big_endian, .. } => f64::from_bits(ordered(big_endian)),
+            NumberType::Integer {
+                size,
+                big_endian,
+                signed,
+            } => {
+                let raw = ordered(big_endian);
+                if signed && size < 8 {
+                    let shift = 64 - size * 8;
+                    (((raw << shift) as i64) >> shift) as f64
+                } else if signed {
+                    raw as i64 as f64
+                } else {
+                    raw as f64
+                }
+            }
+            NumberType::String { .. } | NumberType::Other => 0.0,
+        }
+    }
+}
+
+enum Layout<'a> {
+    Compact(&'a [u8]),
+    Contiguous { addr: u64, size: u64 },
+    Chunked { chunk: Vec<u64>, index: ChunkIndex },
+}
+
+enum ChunkIndex {
+    BTreeV1(u64),
+    Single {
+        addr: u64,
+        size: Option<u64>,
+        mask: u32,
+    },
+    Implicit(u64),
+    FixedArray(u64),
+}
+
+struct Filter {
+    id: u16,
+    values: Vec<u32>,
+}
+
+struct Dataset {
+    dims: Vec<u64>,
+    values: Vec<f64>,
+    attributes: Vec<(String, String)>,
+}
+
+impl Dataset {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Hdf5<'a> {
+    data: &'a [u8],
+    base: u64,
+    offset_size: usize,
+    length_size: usize,
+}
+
+impl<'a> Hdf5<'a> {
+    /// 找到超级块，返回文件与根组对象头地址。
+    fn open(data: &'a [u8]) -> io::Result<(Self, u64)> {
+        let start = std::iter::successors(Some(0usize), |offset| {
+            Some(if *offset == 0 { 512 } else { offset * 2 })
+        })
+        .take_while(|offset| offset + SIGNATURE.len() <= data.len())
+        .find(|&offset| &data[offset..offset + SIGNATURE.len()] == SIGNATURE)
+        .ok_or_else(|| invalid_data("不是 HDF5/SOFA 文件"))?;
+
+        let mut reader = Reader::new(&data[start..]);
+        reader.skip(SIGNATURE.len())?;
+        let version = reader.u8()?;
+        let (offset_size, length_size) = match version {
+            0 | 1 => {
+                reader.skip(4)?;
+                let sizes = (reader.u8()? as usize, reader.u8()? as usize);
+                reader.skip(1 + 4 + 4)?;
+                if version == 1 {
+                    reader.skip(4)?;
+                }
+                sizes
+            }
+            2 | 3 => {
+                let sizes = (reader.u8()? as usize, reader.u8()? as usize);
+                reader.skip(1)?;
+                sizes
+            }
+            _ => {
+                return Err(invalid_data(format!("不支持的 HDF5 超级块版本 {version}")));
+            }
+        };
+        if !matches!(offset_size, 2 | 4 | 8) || !matches!(length_size, 2 | 4 | 8) {
+            return Err(invalid_data("HDF5 地址宽度无效"));
+        }
+        let base = reader.uint(offset_size)?;
+        let root = if version < 2 {
+            // 空闲空间、文件结尾、驱动信息地址，然后是根组符号表项。
+            reader.skip(3 * offset_size)?;
+            reader.skip(offset_size)?;
+            reader.uint(offset_size)?
+        } else {
+            // 超级块扩展、文件结尾地址。
+            reader.skip(2 * offset_size)?;
+            reader.uint(offset_size)?
+        };
+        Ok((
+            Self {
+                data,
+                base,
+                offset_size,
+                length_size,
+            },
+            root,
+        ))
+    }
+
+    fn undefined(&self, addr: u64) -> bool {
+        addr == u64::MAX >> (64 - self.offset_size * 8)
+    }
+
+    fn at(&self, addr: u64) -> io::Result<Reader<'a>> {
+        let start = addr
+            .checked_add(self.base)
+            .and_then(|start| usize::try_from(start).ok())
+            .filter(|&start| start <= self.data.len() && !self.undefined(addr))
+            .ok_or_else(|| invalid_data(format!("HDF5 地址越界：{addr}")))?;
+        Ok(Reader {
+            data: self.data,
+            pos: start,
+        })
+    }
+
+    fn bytes(&self, addr: u64, len: u64) -> io::Result<&'a [u8]> {
+        let len = usize::try_from(len).map_err(|_| invalid_data("HDF5 数据块过大"))?;
+        self.at(addr)?.take(len)
+    }
+
+    fn offset(&self, reader: &mut Reader) -> io::Result<u64> {
+        reader.uint(self.offset_size)
+    }
+
+    fn length(&self, reader: &mut Reader) -> io::Result<u64> {
+        reader.uint(self.length_size)
+    }
+
+    /// 读出对象头里的全部消息，包括续块里的。
+    fn messages(&self, addr: u64) -> io::Result<Vec<Message<'a>>> {
+        let mut reader = self.at(addr)?;
+        let mut messages = Vec::new();
+        let mut blocks = Vec::new();
+        let v2 = reader.data.get(reader.pos..reader.pos + 4) == Some(b"OHDR");
+        let mut flags = 0;
+        if v2 {
+            reader.skip(4)?;
+            if reader.u8()? != 2 {
+                return Err(invalid_data("不支持的 HDF5 对象头版本"));
+            }
+            flags = reader.u8()?;
+            if flags & 0x20 != 0 {
+                reader.skip(16)?;
+            }
+            if flags & 0x10 != 0 {
+                reader.skip(4)?;
+            }
+            let size = reader.uint(1 << (flags & 0x03))? as usize;
+            blocks.push(reader.take(size)?);
+        } else {
+            if reader.u8()? != 1 {
+                return Err(invalid_data("不支持的 HDF5 对象头版本"));
+            }
+            reader.skip(1 + 2 + 4)?;
+            let size = reader.u32()? as usize;
+            reader.skip(4)?;
+            blocks.push(reader.take(size)?);
+        }
+
+        let mut index = 0;
+        while let Some(&block) = blocks.get(index) {
+            if index > MAX_DEPTH * 8 {
+                return Err(invalid_data("HDF5 对象头续块过多"));
+            }
+            index += 1;
+            let mut reader = Reader::new(block);
+            loop {
+                let header_len = if !v2 {
+                    8
+                } else if flags & 0x04 != 0 {
+                    6
+                } else {
+                    4
+                };
+                if reader.remaining() < header_len {
+                    break;
+                }
+                let (kind, size, message_flags) = if v2 {
+                    let kind = u16::from(reader.u8()?);
+                    let size = reader.u16()? as usize;
+                    let message_flags = reader.u8()?;
+                    if flags & 0x04 != 0 {
+                        reader.skip(2)?;
+                    }
+                    (kind, size, message_flags)
+                } else {
+                    let kind = reader.u16()?;
+                    let size = reader.u16()? as usize;
+                    let message_flags = reader.u8()?;
+                    reader.skip(3)?;
+                    (kind, size, message_flags)
+                };
+                let body = reader.take(size)?;
+                if message_flags & 0x02 != 0 && kind != MSG_CONTINUATION {
+                    // 共享消息存在别处，SOFA 用到的变量不会用到。
+                    continue;
+                }
+                if kind == MSG_CONTINUATION {
+                    let mut body = Reader::new(body);
+                    let addr = self.offset(&mut body)?;
+                    let len = self.length(&mut body)?;
+                    let block = self.bytes(addr, len)?;
+                    blocks.push(if v2 {
+                        let mut chunk = Reader::new(block);
+                        chunk.signature(b"OCHK")?;
+                        &block[4..block.len().saturating_sub(4).max(4)]
+                    } else {
+                        block
+                    });
+                } else {
+                    messages.push(Message { kind, body });
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// 组里的硬链接：名字与对象头地址。
+    fn group_links(&self, addr: u64) -> io::Result<Vec<(String, u64)>> {
+        let mut links = Vec::new();
+        for message in self.messages(addr)? {
+            let mut reader = Reader::new(message.body);
+            match message.kind {
+                MSG_LINK => links.extend(self.link(&mut reader)?),
+                MSG_LINK_INFO => {
+                    reader.skip(1)?;
+                    let flags = reader.u8()?;
+                    if flags & 0x01 != 0 {
+                        reader.skip(8)?;
+                    }
+                    let heap = self.offset(&mut reader)?;
+                    if !self.undefined(heap) {
+                        self.dense_links(heap, &mut links)?;
+                    }
+                }
+                MSG_SYMBOL_TABLE => {
+                    let btree = self.offset(&mut reader)?;
+                    let heap = self.offset(&mut reader)?;
+                    let mut heap = self.at(heap)?;
+                    heap.signature(b"HEAP")?;
+                    heap.skip(4)?;
+                    self.length(&mut heap)?;
+                    self.length(&mut heap)?;
+                    let names = self.offset(&mut heap)?;
+                    self.symbol_table_links(btree, names, 0, &mut links)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(links)
+    }
+
+    /// 链接消息；软链接和外部链接跳过。
+    fn link(&self, reader: &mut Reader) -> io::Result<Option<(String, u64)>> {
+        if reader.u8()? != 1 {
+            return Err(invalid_data("不支持的 HDF5 链接消息版本"));
+        }
+        let flags = reader.u8()?;
+        let link_type = if flags & 0x08 != 0 { reader.u8()? } else { 0 };
+        if flags & 0x04 != 0 {
+            reader.skip(8)?;
+        }
+        if flags & 0x10 != 0 {
+            reader.skip(1)?;
+        }
+        let name_len = reader.uint(1 << (flags & 0x03))? as usize;
+        let name = String::from_utf8_lossy(reader.take(name_len)?).into_owned();
+        match link_type {
+            0 => Ok(Some((name, self.offset(reader)?))),
+            _ => {
+                let len = reader.u16()? as usize;
+                reader.skip(len)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// 旧式组：v1 B 树指向符号表节点，名字存在局部堆里。
+    fn symbol_table_links(
+        &self,
+        addr: u64,
+        names: u64,
+        depth: usize,
+        links: &mut Vec<(String, u64)>,
+    ) -> io::Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("HDF5 B 树过深"));
+        }
+        let mut node = self.at(addr)?;
+        node.signature(b"TREE")?;
+        node.skip(1)?;
+        let level = node.u8()?;
+        let entries = node.u16()?;
+        node.skip(2 * self.offset_size)?;
+        for _ in 0..entries {
+            self.length(&mut node)?;
+            let child = self.offset(&mut node)?;
+            if level > 0 {
+                self.symbol_table_links(child, names, depth + 1, links)?;
+                continue;
+            }
+            let mut symbols = self.at(child)?;
+            symbols.signature(b"SNOD")?;
+            symbols.skip(2)?;
+            let count = symbols.u16()?;
+            for _ in 0..count {
+                let name_offset = self.offset(&mut symbols)?;
+                let header = self.offset(&mut symbols)?;
+                symbols.skip(4 + 4 + 16)?;
+                let mut name = self.at(names + name_offset)?;
+                let rest = &name.data[name.pos..];
+                let len = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+                let name = String::from_utf8_lossy(name.take(len)?).into_owned();
+                links.push((name, header));
+            }
+        }
+        Ok(())
+    }
+
+    /// 密集存储的链接：逐个直接块顺序解析其中的链接消息。
+    /// SOFA 文件写完不再改动，块内对象连续排列，未用部分为 0。
+    fn dense_links(&self, heap: u64, links: &mut Vec<(String, u64)>) -> io::Result<()> {
+        let mut header = self.at(heap)?;
+        header.signature(b"FRHP")?;
+        header.skip(1)?;
+        header.u16()?;
+        let filters_len = header.u16()?;
+        let flags = header.u8()?;
+        header.u32()?;
+        self.length(&mut header)?;
+        self.offset(&mut header)?;
+        self.length(&mut header)?;
+        self.offset(&mut header)?;
+        for _ in 0..8 {
+            self.length(&mut header)?;
+        }
+        let width = header.u16()? as u64;
+        let start_block = self.length(&mut header)?;
+        let max_direct_block = self.length(&mut header)?;
+        let max_heap_bits = header.u16()? as usize;
+        header.u16()?;
+        let root = self.offset(&mut header)?;
+        let root_rows = header.u16()?;
+        if filters_len != 0 {
+            return Err(invalid_data("不支持带过滤器的 HDF5 分形堆"));
+        }
+        if self.undefined(root) {
+            return Ok(());
+        }
+
+        let block_offset_len = max_heap_bits.div_ceil(8);
+        let direct_block_prefix = 4 + 1 + self.offset_size + block_offset_len;
+        let checksum = if flags & 0x02 != 0 { 4 } else { 0 };
+        let mut direct_blocks = Vec::new();
+        if root_rows == 0 {
+            direct_blocks.push((root, start_block));
+        } else {
+            let mut indirect = self.at(root)?;
+            indirect.signature(b"FHIB")?;
+            indirect.skip(1 + self.offset_size + block_offset_len)?;
+            let max_direct_rows = (max_direct_block.max(1).ilog2() as u64)
+                .saturating_sub(start_block.max(1).ilog2() as u64)
+                + 2;
+            for row in 0..u64::from(root_rows) {
+                if row >= max_direct_rows {
+                    return Err(invalid_data("不支持多层 HDF5 分形堆"));
+                }
+                let size = if row < 2 {
+                    start_block
+                } else {
+                    start_block << (row - 1)
+                };
+                for _ in 0..width {
+                    let addr = self.offset(&mut indirect)?;
+                    if !self.undefined(addr) {
+                        direct_blocks.push((addr, size));
+                    }
+                }
+            }
+        }
+
+        for (addr, size) in direct_blocks {
+            let block = self.bytes(addr, size)?;
+            let mut reader = Reader::new(block);
+            reader.signature(b"FHDB")?;
+            reader.skip(direct_block_prefix - 4 + checksum)?;
+            while reader.remaining() > 2 && block[reader.pos] == 1 {
+                links.extend(self.link(&mut reader)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn dataset(&self, addr: u64) -> io::Result<Dataset> {
+        let mut dims = None;
+        let mut number = None;
+        let mut layout = None;
+        let mut filters = Vec::new();
+        let mut attributes = Vec::new();
+        for message in self.messages(addr)? {
+            let mut reader = Reader::new(message.body);
+            match message.kind {
+                MSG_DATASPACE => dims = Some(self.dataspace(&mut reader)?),
+                MSG_DATATYPE => number = Some(datatype(&mut reader)?),
+                MSG_LAYOUT => layout = Some(self.layout(&mut reader)?),
+                MSG_FILTERS => filters = filter_pipeline(&mut reader)?,
+                MSG_ATTRIBUTE => attributes.extend(self.attribute(&mut reader)?),
+                _ => {}
+            }
+        }
+        let dims = dims.ok_or_else(|| invalid_data("HDF5 数据集缺少数据空间"))?;
+        let number = number.ok_or_else(|| invalid_data("HDF5 数据集缺少数据类型"))?;
+        let layout = layout.ok_or_else(|| invalid_data("HDF5 数据集缺少存储布局"))?;
+        if !matches!(
+            number,
+            NumberType::Float { .. } | NumberType::Integer { .. }
+        ) {
+            return Err(invalid_data("HDF5 数据集不是数值类型"));
+        }
+
+        let element_size = number.size();
+        let total = byte_len(&dims, element_size)?;
+        let elements = total / element_size as u64;
+        let raw = match layout {
+            Layout::Compact(data) => data.to_vec(),
+            Layout::Contiguous { addr, .. } if self.undefined(addr) => vec![0; total as usize],
+            Layout::Contiguous { addr, size } => self.bytes(addr, size.min(total))?.to_vec(),
+            Layout::Chunked { chunk, index } => {
+                self.read_chunked(&dims, &chunk, element_size, &index, &filters)?
+            }
+        };
+        if (raw.len() as u64) < total {
+            return Err(invalid_data("HDF5 数据集数据不完整"));
+        }
+        let values = raw
+            .chunks_exact(element_size)
+            .take(elements as usize)
+            .map(|bytes| number.decode(bytes))
+            .collect();
+        Ok(Dataset {
+            dims,
+            values,
+            attributes,
+        })
+    }
+
+    fn dataspace(&self, reader: &mut Reader) -> io::Result<Vec<u64>> {
+        let version = reader.u8()?;
+        let rank = reader.u8()? as usize;
+        reader.skip(1)?;
+        match version {
+            1 => reader.skip(5)?,
+            2 => {
+                if reader.u8()? == 2 {
+                    return Ok(vec![0]);
+                }
+            }
+            _ => return Err(invalid_data("不支持的 HDF5 数据空间版本")),
+        }
+        (0..rank).map(|_| self.length(reader)).collect()
+    }
+
+    fn layout(&self, reader: &mut Reader<'a>) -> io::Result<Layout<'a>> {
+        let version = reader.u8()?;
+        if !(3..=4).contains(&version) {
+            return Err(invalid_data(format!(
+                "不支持的 HDF5 存储布局版本 {version}"
+            )));
+        }
+        match reader.u8()? {
+            0 => {
+                let size = reader.u16()? as usize;
+                Ok(Layout::Compact(reader.take(size)?))
+            }
+            1 => Ok(Layout::Contiguous {
+                addr: self.offset(reader)?,
+                size: self.length(reader)?,
+            }),
+            2 if version == 3 => {
+                let rank = reader.u8()? as usize;
+                let index = ChunkIndex::BTreeV1(self.offset(reader)?);
+                let chunk = (0..rank)
+                    .map(|_| reader.u32().map(u64::from))
+                    .collect::<io::Result<_>>()?;
+                Ok(Layout::Chunked { chunk, index })
+            }
+            2 => {
+                let flags = reader.u8()?;
+                let rank = reader.u8()? as usize;
+                let dim_len = reader.u8()? as usize;
+                let chunk = (0..rank)
+                    .map(|_| reader.uint(dim_len))
+                    .collect::<io::Result<_>>()?;
+                let index = match reader.u8()? {
+                    1 => {
+                        let (size, mask) = if flags & 0x02 != 0 {
+                            (Some(self.length(reader)?), reader.u32()?)
+                        } else {
+                            (None, 0)
+                        };
+                        ChunkIndex::Single {
+                            addr: self.offset(reader)?,
+                            size,
+                            mask,
+                        }
+                    }
+                    2 => ChunkIndex::Implicit(self.offset(reader)?),
+                    3 => {
+                        reader.skip(1)?;
+                        ChunkIndex::FixedArray(self.offset(reader)?)
+                    }
+                    kind => {
+                        return Err(invalid_data(format!("不支持的 HDF5 分块索引类型 {kind}")));
+                    }
+                };
+                Ok(Layout::Chunked { chunk, index })
+            }
+            class => Err(invalid_data(format!("不支持的 HDF5 存储方式 {class}"))),
+        }
+    }
+
+    /// 只保留定长字符串属性（如 `Type`、`Units`）。
+    fn attribute(&self, reader: &mut Reader) -> io::Result<Option<(String, String)>> {
+        let version = reader.u8()?;
+        reader.skip(1)?;
+        let name_len = reader.u16()? as usize;
+        let type_len = reader.u16()? as usize;
+        let space_len = reader.u16()? as usize;
+        if version == 3 {
+            reader.skip(1)?;
+        }
+        let padded = |len: usize| {
+            if version == 1 {
+                len.next_multiple_of(8)
+            } else {
+                len
+            }
+        };
+        let name = reader.take(padded(name_len))?;
+        let name = String::from_utf8_lossy(&name[..name_len.min(name.len())])
+            .trim_end_matches('\0')
+            .to_string();
+        let mut datatype_reader = Reader::new(reader.take(padded(type_len))?);
+        let mut space = Reader::new(reader.take(padded(space_len))?);
+        let NumberType::String { size } = datatype(&mut datatype_reader)? else {
+            return Ok(None);
+        };
+        let elements = self
+            .dataspace(&mut space)?
+            .iter()
+            .fold(1u64, |elements, dim| elements.saturating_mul(*dim))
+            .max(1);
+        let len = (size as u64)
+            .saturating_mul(elements)
+            .min(reader.remaining() as u64) as usize;
+        let value = String::from_utf8_lossy(reader.take(len)?)
+            .trim_end_matches(['\0', ' '])
+            .to_string();
+        Ok(Some((name, value)))
+    }
+
+    /// 把所有分块解开后拼成完整数组（行主序）。
+    fn read_chunked(
+        &self,
+        dims: &[u64],
+        chunk: &[u64],
+        element_size: usize,
+        index: &ChunkIndex,
+        filters: &[Filter],
+    ) -> io::Result<Vec<u8>> {
+        let rank = dims.len();
+        if chunk.len() != rank + 1 || chunk[..rank].contains(&0) {
+            return Err(invalid_data("HDF5 分块维度与数据空间不符"));
+        }
+        let chunk = &chunk[..rank];
+        let total = byte_len(dims, element_size)? as usize;
+        let mut out = vec![0u8; total];
+        let chunk_bytes = byte_len(chunk, element_size)? as usize;
+
+        let grid: Vec<u64> = dims
+            .iter()
+            .zip(chunk)
+            .map(|(dim, chunk)| dim.div_ceil(*chunk))
+            .collect();
+        let grid_offsets = |linear: u64| {
+            let mut offsets = vec![0u64; rank];
+            let mut rest = linear;
+            for axis in (0..rank).rev() {
+                offsets[axis] = rest % grid[axis].max(1) * chunk[axis];
+                rest /= grid[axis].max(1);
+            }
+            offsets
+        };
+
+        let mut chunks: Vec<(Vec<u64>, u64, u64, u32)> = Vec::new();
+        match *index {
+            ChunkIndex::BTreeV1(addr) => {
+                if !self.undefined(addr) {
+                    self.chunk_btree(addr, rank, 0, &mut chunks)?;
+                }
+            }
+            ChunkIndex::Single { addr, size, mask } => {
+                chunks.push((
+                    vec![0; rank],
+                    addr,
+                    size.unwrap_or(chunk_bytes as u64),
+                    mask,
+                ));
+            }
+            ChunkIndex::Implicit(addr) => {
+                let count = grid
+                    .iter()
+                    .try_fold(1u64, |count, dim| count.checked_mul(*dim))
+                    .filter(|count| *count <= MAX_IMPLICIT_CHUNKS)
+                    .ok_or_else(|| invalid_data("HDF5 分块过多"))?;
+                for linear in 0..count {
+                    let addr = addr + linear * chunk_bytes as u64;
+                    chunks.push((grid_offsets(linear), addr, chunk_bytes as u64, 0));
+                }
+            }
+            ChunkIndex::FixedArray(addr) => {
+                let filtered = !filters.is_empty();
+                for (linear, (addr, size, mask)) in
+                    self.fixed_array(addr, filtered)?.into_iter().enumerate()
+                {
+                    let size = size.unwrap_or(chunk_bytes as u64);
+                    chunks.push((grid_offsets(linear as u64), addr, size, mask));
+                }
+            }
+        }
+
+        for (offsets, addr, size, mask) in chunks {
+            if self.undefined(addr) {
+                continue;
+            }
+            let data = apply_filters(self.bytes(addr, size)?, filters, mask)?;
+            if data.len() < chunk_bytes {
+                return Err(invalid_data("HDF5 分块数据不完整"));
+            }
+            copy_chunk(&data, &mut out, dims, chunk, &offsets, element_size);
+        }
+        Ok(out)
+    }
+
+    /// v1 B 树（类型 1）的叶子：分块偏移、地址、大小与过滤器掩码。
+    fn chunk_btree(
+        &self,
+        addr: u64,
+        rank: usize,
+        depth: usize,
+        chunks: &mut Vec<(Vec<u64>, u64, u64, u32)>,
+    ) -> io::Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("HDF5 B 树过深"));
+        }
+        let mut node = self.at(addr)?;
+        node.signature(b"TREE")?;
+        if node.u8()? != 1 {
+            return Err(invalid_data("HDF5 分块 B 树类型错误"));
+        }
+        let level = node.u8()?;
+        let entries = node.u16()?;
+        node.skip(2 * self.offset_size)?;
+        for _ in 0..entries {
+            let size = u64::from(node.u32()?);
+            let mask = node.u32()?;
+            let offsets = (0..=rank)
+                .map(|_| node.uint(8))
+                .collect::<io::Result<Vec<_>>>()?;
+            let child = self.offset(&mut node)?;
+            if level > 0 {
+                self.chunk_btree(child, rank, depth + 1, chunks)?;
+            } else {
+                chunks.push((offsets[..rank].to_vec(), child, size, mask));
+            }
+        }
+        Ok(())
+    }
+
+    /// 固定数组索引（不分页）：每个分块的地址，过滤后还有大小与掩码。
+    fn fixed_array(&self, addr: u64, filtered: bool) -> io::Result<Vec<(u64, Option<u64>, u32)>> {
+        let mut header = self.at(addr)?;
+        header.signature(b"FAHD")?;
+        header.skip(2)?;
+        let entry_size = header.u8()? as usize;
+        let page_bits = header.u8()?;
+        let count = self.length(&mut header)?;
+        let data_block = self.offset(&mut header)?;
+        if page_bits < 64 && count > 1u64 << page_bits {
+            return Err(invalid_data("不支持分页的 HDF5 固定数组"));
+        }
+
+        let mut block = self.at(data_block)?;
+        block.signature(b"FADB")?;
+        block.skip(2 + self.offset_size)?;
+        (0..count)
+            .map(|_| {
+                let addr = self.offset(&mut block)?;
+                if filtered {
+                    let size_len = entry_size.saturating_sub(self.offset_size + 4);
+                    let size = block.uint(size_len)?;
+                    let mask = block.u32()?;
+                    Ok((addr, Some(size), mask))
+                } else {
+                    Ok((addr, None, 0))
+                }
+            })
+            .collect()
+    }
+}
+
+fn datatype(reader: &mut Reader) -> io::Result<NumberType> {
+    let class_and_version = reader.u8()?;
+    let flags = reader.u8()?;
+    reader.skip(2)?;
+    let size = reader.u32()? as usize;
+    let big_endian = flags & 0x01 != 0;
+    Ok(match class_and_version & 0x0f {
+        0 if matches!(size, 1 | 2 | 4 | 8) => NumberType::Integer {
+            size,
+            big_endian,
+            signed: flags & 0x08 != 0,
+        },
+        1 if matches!(size, 4 | 8) && flags & 0x40 == 0 => NumberType::Float { size, big_endian },
+        3 => NumberType::String { size },
+        _ => NumberType::Other,
+    })
+}
+
+fn filter_pipeline(reader: &mut Reader) -> io::Result<Vec<Filter>> {
+    let version = reader.u8()?;
+    let count = reader.u8()?;
+    if version == 1 {
+        reader.skip(6)?;
+    }
+    (0..count)
+        .map(|_| {
+            let id = reader.u16()?;
+            let name_len = if version == 1 || id >= 256 {
+                reader.u16()? as usize
+            } else {
+                0
+            };
+            reader.skip(2)?;
+            let value_count = reader.u16()? as usize;
+            reader.skip(if version == 1 {
+                name_len.next_multiple_of(8)
+            } else {
+                name_len
+            })?;
+            let values = (0..value_count)
+                .map(|_| reader.u32())
+                .collect::<io::Result<Vec<_>>>()?;
+            if version == 1 && value_count % 2 == 1 {
+                reader.skip(4)?;
+            }
+            Ok(Filter { id, values })
+        })
+        .collect()
+}
+
+/// 按写入的反序撤销过滤器；掩码里置位的过滤器在该分块上没有应用。
+fn apply_filters(data: &[u8], filters: &[Filter], mask: u32) -> io::Result<Vec<u8>> {
+    let mut data = data.to_vec();
+    for (index, filter) in filters.iter().enumerate().rev() {
+        if mask & (1 << index) != 0 {
+            continue;
+        }
+        data = match filter.id {
+            FILTER_DEFLATE => miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(
+                &data,
+                MAX_DATASET_LEN as usize,
+            )
+            .map_err(|err| invalid_data(format!("HDF5 deflate 数据损坏：{:?}", err.status)))?,
+            FILTER_SHUFFLE => {
+                let size = filter.values.first().copied().unwrap_or(1) as usize;
+                unshuffle(&data, size)
+            }
+            FILTER_FLETCHER32 => {
+                data.truncate(data.len().saturating_sub(4));
+                data
+            }
+            id => {
+                return Err(invalid_data(format!("不支持的 HDF5 过滤器 {id}")));
+            }
+        };
+    }
+    Ok(data)
+}
+
+/// shuffle 过滤器把每个元素的第 k 个字节放在一起，这里还原成逐元素排列。
+fn unshuffle(data: &[u8], element_size: usize) -> Vec<u8> {
+    if element_size <= 1 {
+        return data.to_vec();
+    }
+    let elements = data.len() / element_size;
+    let mut out = data.to_vec();
+    for byte in 0..element_size {
+        for element in 0..elements {
+            out[element * element_size + byte] = data[byte * elements + element];
+        }
+    }
+    out
+}
+
+/// 把一个分块按行主序拷进完整数组，越过数组边界的部分丢掉。
+fn copy_chunk(
+    chunk_data: &[u8],
+    out: &mut [u8],
+    dims: &[u64],
+    chunk: &[u64],
+    offsets: &[u64],
+    element_size: usize,
+) {
+    let rank = dims.len();
+    if rank == 0 {
+        let len = element_size.min(out.len()).min(chunk_data.len());
+        out[..len].copy_from_slice(&chunk_data[..len]);
+        return;
+    }
+    let row_len = chunk[rank - 1].min(dims[rank - 1].saturating_sub(offsets[rank - 1])) as usize;
+    let rows = chunk[..rank - 1].iter().product::<u64>();
+    for row in 0..rows {
+        // 分块内这一行的各维坐标。
+        let mut rest = row;
+        let mut target = 0u64;
+        let mut inside = true;
+        let mut coords = vec![0u64; rank - 1];
+        for axis in (0..rank - 1).rev() {
+            coords[axis] = rest % chunk[axis];
+            rest /= chunk[axis];
+        }
+        for axis in 0..rank - 1 {
+            let position = offsets[axis] + coords[axis];
+            if position >= dims[axis] {
+                inside = false;
+                break;
+            }
+            target = target * dims[axis] + position;
+        }
+        if !inside || row_len == 0 {
+            continue;
+        }
+        let target = (target * dims[rank - 1] + offsets[rank - 1]) as usize * element_size;
+        let source = row as usize * chunk[rank - 1] as usize * element_size;
+        let len = row_len * element_size;
+        out[target..target + len].copy_from_slice(&chunk_data[source..source + len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNDEFINED: u64 = u64::MAX;
+
+    fn le(value: u64, len: usize) -> Vec<u8> {
+        value.to_le_bytes()[..len].to_vec()
+    }
+
+    fn padded(mut bytes: Vec<u8>) -> Vec<u8> {
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes
+    }
+
+    /// 按 8 字节对齐追加结构的 HDF5 文件，开头留给超级块。
+    struct Builder {
+        data: Vec<u8>,
+    }
+
+    impl Builder {
+        fn new() -> Self {
+            Self { data: vec![0; 128] }
+        }
+
+        fn put(&mut self, bytes: &[u8]) -> u64 {
+            self.data.resize(self.data.len().next_multiple_of(8), 0);
+            let addr = self.data.len() as u64;
+            self.data.extend_from_slice(bytes);
+            addr
+        }
+
+        fn finish(mut self, superblock: &[u8]) -> Vec<u8> {
+            self.data[..superblock.len()].copy_from_slice(superblock);
+            self.data
+        }
+    }
+
+    fn v1_header(messages: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let body: Vec<u8> = messages
+            .iter()
+            .flat_map(|(kind, body)| {
+                let body = padded(body.clone());
+                [
+                    le(u64::from(*kind), 2),
+                    le(body.len() as u64, 2),
+                    vec![0; 4],
+                    body,
+                ]
+                .concat()
+            })
+            .collect();
+        [
+            vec![1, 0],
+            le(messages.len() as u64, 2),
+            le(1, 4),
+            le(body.len() as u64, 4),
+            vec![0; 4],
+            body,
+        ]
+        .concat()
+    }
+
+    fn v2_header(messages: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let body: Vec<u8> = messages
+            .iter()
+            .flat_map(|(kind, body)| {
+                [
+                    vec![*kind as u8],
+                    le(body.len() as u64, 2),
+                    vec![0],
+                    body.clone(),
+                ]
+                .concat()
+            })
+            .collect();
+        [
+            b"OHDR".to_vec(),
+            vec![2, 0x01],
+            le(body.len() as u64, 2),
+            body,
+            vec![0; 4],
+        ]
+        .concat()
+    }
+
+    fn dataspace(version: u8, dims: &[u64]) -> Vec<u8> {
+        let prefix = if version == 1 {
+            vec![1, dims.len() as u8, 0, 0, 0, 0, 0, 0]
+        } else {
+            vec![2, dims.len() as u8, 0, 1]
+        };
+        [prefix, dims.iter().flat_map(|dim| le(*dim, 8)).collect()].concat()
+    }
+
+    fn float_type(size: u32) -> Vec<u8> {
+        [
+            vec![0x11, 0x20, 0x3f, 0],
+            le(u64::from(size), 4),
+            vec![0; 12],
+        ]
+        .concat()
+    }
+
+    fn int_type(size: u32) -> Vec<u8> {
+        [vec![0x10, 0, 0, 0], le(u64::from(size), 4), vec![0; 4]].concat()
+    }
+
+    fn string_attribute(version: u8, name: &str, value: &str) -> Vec<u8> {
+        let name = [name.as_bytes(), b"\0"].concat();
+        let datatype = [vec![0x13, 0, 0, 0], le(value.len() as u64, 4)].concat();
+        let space = dataspace(1, &[]);
+        let field = |bytes: &[u8]| {
+            if version == 1 {
+                padded(bytes.to_vec())
+            } else {
+                bytes.to_vec()
+            }
+        };
+        [
+            vec![version, 0],
+            le(name.len() as u64, 2),
+            le(datatype.len() as u64, 2),
+            le(space.len() as u64, 2),
+            if version == 3 { vec![0] } else { vec![] },
+            field(&name),
+            field(&datatype),
+            field(&space),
+            value.as_bytes().to_vec(),
+        ]
+        .concat()
+    }
+
+    fn f64_bytes(values: &[f64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn link(name: &str, addr: u64) -> Vec<u8> {
+        [
+            vec![1, 0x10, 0, name.len() as u8],
+            name.as_bytes().to_vec(),
+            le(addr, 8),
+        ]
+        .concat()
+    }
+
+    fn shuffle(data: &[u8], element_size: usize) -> Vec<u8> {
+        let elements = data.len() / element_size;
+        let mut out = data.to_vec();
+        for byte in 0..element_size {
+            for element in 0..elements {
+                out[byte * elements + element] = data[element * element_size + byte];
+            }
+        }
+        out
+    }
+
+    /// 超级块 v0，符号表组，连续/紧凑存储的 f64 数据。
+    fn legacy_file() -> Vec<u8> {
+        let mut file = Builder::new();
+        let ir: Vec<f64> = (0..16).map(|index| f64::from(index) / 16.0).collect();
+        let ir_data = file.put(&f64_bytes(&ir));
+        let ir_header = file.put(&v1_header(&[
+            (MSG_DATASPACE, dataspace(1, &[2, 2, 4])),
+            (MSG_DATATYPE, float_type(8)),
+            (
+                MSG_LAYOUT,
+                [vec![3, 1], le(ir_data, 8), le(128, 8)].concat(),
+            ),
+        ]));
+        let rate = f64_bytes(&[44_100.0]);
+        let rate_header = file.put(&v1_header(&[
+            (MSG_DATASPACE, dataspace(1, &[1])),
+            (MSG_DATATYPE, float_type(8)),
+            (MSG_LAYOUT, [vec![3, 0], le(8, 2), rate].concat()),
+        ]));
+        let positions = f64_bytes(&[30.0, 0.0, 1.2, -30.0, 10.0, 1.2]);
+        let positions_header = file.put(&v1_header(&[
+            (MSG_ATTRIBUTE, string_attribute(1, "Type", "spherical")),
+            (MSG_DATASPACE, dataspace(1, &[2, 3])),
+            (MSG_DATATYPE, float_type(8)),
+            (MSG_LAYOUT, [vec![3, 0], le(48, 2), positions].concat()),
+        ]));
+
+        let names = b"\0Data.IR\0Data.SamplingRate\0SourcePosition\0";
+        let heap_data = file.put(names);
+        let heap = file.put(
+            &[
+                b"HEAP".to_vec(),
+                vec![0; 4],
+                le(names.len() as u64, 8),
+                le(UNDEFINED, 8),
+                le(heap_data, 8),
+            ]
+            .concat(),
+        );
+        let entry = |name_offset: u64, header: u64| {
+            [le(name_offset, 8), le(header, 8), vec![0; 24]].concat()
+        };
+        let symbols = file.put(
+            &[
+                b"SNOD".to_vec(),
+                vec![1, 0],
+                le(3, 2),
+                entry(1, ir_header),
+                entry(9, rate_header),
+                entry(27, positions_header),
+            ]
+            .concat(),
+        );
+        let btree = file.put(
+            &[
+                b"TREE".to_vec(),
+                vec![0, 0],
+                le(1, 2),
+                le(UNDEFINED, 8),
+                le(UNDEFINED, 8),
+                le(0, 8),
+                le(symbols, 8),
+                le(27, 8),
+            ]
+            .concat(),
+        );
+        let root = file.put(&v1_header(&[(
+            MSG_SYMBOL_TABLE,
+            [le(btree, 8), le(heap, 8)].concat(),
+        )]));
+
+        let superblock = [
+            SIGNATURE.to_vec(),
+            vec![0, 0, 0, 0, 0, 8, 8, 0],
+            le(4, 2),
+            le(16, 2),
+            vec![0; 4],
+            le(0, 8),
+            le(UNDEFINED, 8),
+            le(file.data.len() as u64, 8),
+            le(UNDEFINED, 8),
+            le(0, 8),
+            le(root, 8),
+        ]
+        .concat();
+        file.finish(&superblock)
+    }
+
+    /// 超级块 v2，OHDR 对象头，分形堆里的密集链接，shuffle + deflate 分块的 f32 数据。
+    fn modern_file() -> Vec<u8> {
+        let mut file = Builder::new();
+        let ir: Vec<f32> = (0..48).map(|index| index as f32 - 24.0).collect();
+        let mut chunks = Vec::new();
+        for rows in [0..32, 32..48] {
+            let mut values = ir[rows].to_vec();
+            values.resize(32, 0.0);
+            let bytes: Vec<u8> = values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&shuffle(&bytes, 4), 6);
+            chunks.push((file.put(&compressed), compressed.len() as u64));
+        }
+        let array_header_addr = file.data.len().next_multiple_of(8) as u64;
+        let array_block = {
+            let entries: Vec<u8> = chunks
+                .iter()
+                .flat_map(|&(addr, size)| [le(addr, 8), le(size, 4), le(0, 4)].concat())
+                .collect();
+            [
+                b"FADB".to_vec(),
+                vec![0, 1],
+                le(array_header_addr + 64, 8),
+                entries,
+            ]
+            .concat()
+        };
+        let array_header = [
+            b"FAHD".to_vec(),
+            vec![0, 1, 16, 10],
+            le(2, 8),
+            le(array_header_addr + 64, 8),
+            vec![0; 4],
+        ]
+        .concat();
+        assert_eq!(file.put(&array_header), array_header_addr);
+        file.data.resize(array_header_addr as usize + 64, 0);
+        file.put(&array_block);
+
+        let filters = [
+            vec![2, 2],
+            le(2, 2),
+            le(0, 2),
+            le(1, 2),
+            le(4, 4),
+            le(1, 2),
+            le(0, 2),
+            le(1, 2),
+            le(6, 4),
+        ]
+        .concat();
+        let ir_header = file.put(&v2_header(&[
+            (MSG_DATASPACE, dataspace(2, &[3, 2, 8])),
+            (MSG_DATATYPE, float_type(4)),
+            (MSG_FILTERS, filters),
+            (
+                MSG_LAYOUT,
+                [
+                    vec![4, 2, 0, 4, 4],
+                    le(2, 4),
+                    le(2, 4),
+                    le(8, 4),
+                    le(4, 4),
+                    vec![3, 10],
+                    le(array_header_addr, 8),
+                ]
+                .concat(),
+            ),
+        ]));
+        let rate_data = file.put(&le(48_000, 4));
+        let rate_header = file.put(&v2_header(&[
+            (MSG_DATASPACE, dataspace(2, &[1])),
+            (MSG_DATATYPE, int_type(4)),
+            (
+                MSG_LAYOUT,
+                [vec![3, 1], le(rate_data, 8), le(4, 8)].concat(),
+            ),
+        ]));
+        let positions = f64_bytes(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        let positions_header = file.put(&v2_header(&[
+            (MSG_ATTRIBUTE, string_attribute(3, "Type", "cartesian")),
+            (MSG_DATASPACE, dataspace(2, &[3, 3])),
+            (MSG_DATATYPE, float_type(8)),
+            (MSG_LAYOUT, [vec![3, 0], le(72, 2), positions].concat()),
+        ]));
+        let delay = f64_bytes(&[2.0, 0.0]);
+        let delay_header = file.put(&v2_header(&[
+            (MSG_DATASPACE, dataspace(2, &[1, 2])),
+            (MSG_DATATYPE, float_type(8)),
+            (MSG_LAYOUT, [vec![3, 0], le(16, 2), delay].concat()),
+        ]));
+
+        let heap_addr = file.data.len().next_multiple_of(8) as u64;
+        let heap_block_addr = heap_addr + 256;
+        let heap = [
+            b"FRHP".to_vec(),
+            vec![0],
+            le(7, 2),
+            le(0, 2),
+            vec![0],
+            le(4096, 4),
+            le(0, 8),
+            le(UNDEFINED, 8),
+            le(0, 8),
+            le(UNDEFINED, 8),
+            vec![0; 64],
+            le(4, 2),
+            le(512, 8),
+            le(65_536, 8),
+            le(32, 2),
+            le(1, 2),
+            le(heap_block_addr, 8),
+            le(0, 2),
+            vec![0; 4],
+        ]
+        .concat();
+        assert_eq!(file.put(&heap), heap_addr);
+        file.data.resize(heap_block_addr as usize, 0);
+        let mut block = [
+            b"FHDB".to_vec(),
+            vec![0],
+            le(heap_addr, 8),
+            le(0, 4),
+            link("Data.IR", ir_header),
+            link("SourcePosition", positions_header),
+            link("Data.Delay", delay_header),
+        ]
+        .concat();
+        block.resize(512, 0);
+        file.put(&block);
+
+        let root = file.put(&v2_header(&[
+            (MSG_LINK, link("Data.SamplingRate", rate_header)),
+            (
+                MSG_LINK_INFO,
+                [vec![0, 0], le(heap_addr, 8), le(UNDEFINED, 8)].concat(),
+            ),
+        ]));
+
+        let superblock = [
+            SIGNATURE.to_vec(),
+            vec![2, 8, 8, 0],
+            le(0, 8),
+            le(UNDEFINED, 8),
+            le(file.data.len() as u64, 8),
+            le(root, 8),
+            vec![0; 4],
+        ]
+        .concat();
+        file.finish(&superblock)
+    }
+
+    #[test]
+    fn reads_symbol_table_groups_and_contiguous_data() {
+        let set = parse(&legacy_file(), "legacy".to_string()).unwrap();
+        assert_eq!(set.name, "legacy");
+        assert_eq!(set.sample_rate, 44_100);
+        assert_eq!(set.hrirs.len(), 2);
+        let second = &set.hrirs[1];
+        assert_eq!((second.azimuth, second.elevation), (-30.0, 10.0));
+        assert_eq!(second.left, [0.5, 0.5625, 0.625, 0.6875]);
+        assert_eq!(second.right, [0.75, 0.8125, 0.875, 0.9375]);
+    }
+
+    #[test]
+    fn reads_dense_links_and_filtered_chunks() {
+        let set = parse(&modern_file(), "modern".to_string()).unwrap();
+        assert_eq!(set.sample_rate, 48_000);
+        let directions: Vec<(f64, f64)> = set
+            .hrirs
+            .iter()
+            .map(|hrir| (hrir.azimuth.round(), hrir.elevation.round()))
+            .collect();
+        assert_eq!(directions, [(0.0, 0.0), (90.0, 0.0), (0.0, 90.0)]);
+
+        // Data.Delay 只有一行，所有测量点左耳都前置两个零。
+        let last = &set.hrirs[2];
+        let expected: Vec<f32> = (32..40).map(|index| index as f32 - 24.0).collect();
+        assert_eq!(last.left[..2], [0.0, 0.0]);
+        assert_eq!(last.left[2..], expected[..]);
+        let expected: Vec<f32> = (40..48).map(|index| index as f32 - 24.0).collect();
+        assert_eq!(last.right, expected);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_sofa() {
+        assert!(parse(b"RIFF\0\0\0\0WAVEfmt ", "x".to_string()).is_err());
+        let mut truncated = legacy_file();
+        truncated.truncate(400);
+        assert!(parse(&truncated, "x".to_string()).is_err());
+    }
+
+    #[test]
+    fn rejects_dimensions_that_overflow() {
+        // 把 Data.IR 的 2×2×4 改成乘积溢出 u64 的维度，应当报错而不是 panic。
+        let original = dataspace(1, &[2, 2, 4]);
+        for dims in [[1 << 40, 2, 1 << 40], [u64::MAX, 2, 4]] {
+            let mut file = legacy_file();
+            let at = file
+                .windows(original.len())
+                .position(|window| window == original)
+                .unwrap();
+            file[at..at + original.len()].copy_from_slice(&dataspace(1, &dims));
+            let err = parse(&file, "x".to_string()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! 通过 ALSA simple mixer 调节声卡的硬件音量。软件音量会改写样本，
+//! 严格 BitPerfect 播放时音量只能交给 DAC 自带的混音器控件。
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::Mutex;
+
+use crate::audio::backend;
+
+/// 挑选播放音量控件时优先考虑的名称，都没有时取第一个带播放音量的控件。
+const PREFERRED_ELEMENTS: [&str; 6] = ["Master", "PCM", "Digital", "Headphone", "Speaker", "Front"];
+
+/// 各声卡是否有硬件音量的缓存，设备枚举时不必每次都打开混音器。
+static HW_VOLUME_CARDS: Mutex<CardCache> = Mutex::new(CardCache {
+    signature: None,
+    cards: BTreeMap::new(),
+});
+
+/// dB 范围超过该值时按 dB 做感知映射（与 alsamixer 一致），否则按原始刻度线性映射。
+const MAX_LINEAR_DB_RANGE: f64 = 24.0;
+
+/// 硬件混音器控件的当前状态，`volume` 为 0..=1。
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct HwVolume {
+    pub(crate) card: u32,
+    pub(crate) element: String,
+    pub(crate) volume: f64,
+    /// 驱动没有给出 dB 信息时为空。
+    pub(crate) db: Option<f64>,
+    pub(crate) min_db: Option<f64>,
+    pub(crate) max_db: Option<f64>,
+    /// 控件没有静音开关时为空。
+    pub(crate) muted: Option<bool>,
+}
+
+fn choose_element(names: &[String]) -> Option<usize> {
+    PREFERRED_ELEMENTS
+        .iter()
+        .find_map(|preferred| {
+            names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(preferred))
+        })
+        .or_else(|| (!names.is_empty()).then_some(0))
+}
+
+fn db_range(min_db: f64, max_db: f64) -> Option<(f64, f64)> {
+    (max_db > min_db).then_some((min_db, max_db))
+}
+
+fn raw_to_fraction(raw: i64, (min, max): (i64, i64)) -> f64 {
+    if max <= min {
+        return 0.0;
+    }
+    ((raw - min) as f64 / (max - min) as f64).clamp(0.0, 1.0)
+}
+
+fn fraction_to_raw(volume: f64, (min, max): (i64, i64)) -> i64 {
+    min + (volume.clamp(0.0, 1.0) * (max - min) as f64).round() as i64
+}
+
+fn db_to_fraction(db: f64, (min_db, max_db): (f64, f64)) -> f64 {
+    let floor = 10f64.powf((min_db - max_db) / 60.0);
+    let value = 10f64.powf((db - max_db) / 60.0);
+    ((value - floor) / (1.0 - floor)).clamp(0.0, 1.0)
+}
+
+fn fraction_to_db(volume: f64, (min_db, max_db): (f64, f64)) -> f64 {
+    let floor = 10f64.powf((min_db - max_db) / 60.0);
+    let value = volume.clamp(0.0, 1.0) * (1.0 - floor) + floor;
+    (60.0 * value.log10() + max_db).clamp(min_db, max_db)
+}
+
+fn uses_db_mapping(range: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    range.filter(|(min_db, max_db)| max_db - min_db > MAX_LINEAR_DB_RANGE)
+}
+
+/// 按声卡缓存的查询结果；声卡列表签名变化（插拔）后整体作废，拿不到签名时不缓存。
+struct CardCache {
+    signature: Option<String>,
+    cards: BTreeMap<u32, bool>,
+}
+
+impl CardCache {
+    fn get(&mut self, signature: Option<String>, card: u32, probe: impl FnOnce() -> bool) -> bool {
+        if signature.is_none() || signature != self.signature {
+            self.cards.clear();
+            self.signature = signature;
+        }
+        *self.cards.entry(card).or_insert_with(probe)
+    }
+}
+
+/// 设备所在声卡是否有可调的播放音量控件；每张卡只在首次查询时打开混音器。
+pub(crate) fn has_hw_volume(device_id: &str) -> bool {
+    let Some(card) = imp::card_index(device_id) else {
+        return false;
+    };
+    HW_VOLUME_CARDS
+        .lock()
+        .unwrap()
+        .get(backend::sound_card_signature(), card, || {
+            imp::with_element(device_id, |_| Ok(())).is_ok()
+        })
+}
+
+pub(crate) fn get_volume(device_id: &str) -> Result<HwVolume, Box<dyn Error>> {
+    imp::with_element(device_id, imp::read)
+}
+
+pub(crate) fn set_volume(device_id: &str, volume: f64) -> Result<HwVolume, Box<dyn Error>> {
+    if !volume.is_finite() {
+        return Err(format!("无效的音量：{volume}").into());
+    }
+    imp::with_element(device_id, |element| {
+        imp::write_volume(element, volume)?;
+        imp::read(element)
+    })
+}
+
+pub(crate) fn set_mute(device_id: &str, muted: bool) -> Result<HwVolume, Box<dyn Error>> {
+    imp::with_element(device_id, |element| {
+        imp::write_mute(element, muted)?;
+        imp::read(element)
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::error::Error;
+
+    use alsa::Round;
+    use alsa::mixer::{MilliBel, Mixer, Selem, SelemChannelId};
+
+    use super::{
+        HwVolume, choose_element, db_range, db_to_fraction, fraction_to_db, fraction_to_raw,
+        raw_to_fraction, uses_db_mapping,
+    };
+    use crate::audio::backend;
+
+    pub(super) fn card_index(device_id: &str) -> Option<u32> {
+        backend::alsa_card_index_from_device_id(device_id)
+    }
+
+    pub(super) struct Element<'a> {
+        card: u32,
+        name: String,
+        selem: Selem<'a>,
+    }
+
+    pub(super) fn with_element<T>(
+        device_id: &str,
+        f: impl FnOnce(&Element) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let card = backend::alsa_card_index_from_device_id(device_id)
+            .ok_or_else(|| format!("设备 {device_id} 不是 ALSA 声卡，没有硬件音量"))?;
+        let mixer = Mixer::new(&format!("hw:{card}"), false)?;
+        let mut elements = mixer
+            .iter()
+            .filter_map(Selem::new)
+            .filter(|selem| selem.has_playback_volume())
+            .map(|selem| Element {
+                card,
+                name: selem
+                    .get_id()
+                    .get_name()
+                    .map(str::to_string)
+                    .unwrap_or_default(),
+                selem,
+            })
+            .collect::<Vec<_>>();
+        let names = elements
+            .iter()
+            .map(|element| element.name.clone())
+            .collect::<Vec<_>>();
+        let index =
+            choose_element(&names).ok_or_else(|| format!("声卡 {card} 没有播放音量控件"))?;
+        f(&elements.swap_remove(index))
+    }
+
+    fn element_db_range(element: &Element) -> Option<(f64, f64)> {
+        let (min, max) = element.selem.get_playback_db_range();
+        db_range(f64::from(min.to_db()), f64::from(max.to_db()))
+    }
+
+    pub(super) fn read(element: &Element) -> Result<HwVolume, Box<dyn Error>> {
+        let selem = &element.selem;
+        let channel = SelemChannelId::mono();
+        let range = element_db_range(element);
+        let db = range
+            .and_then(|_| selem.get_playback_vol_db(channel).ok())
+            .map(|db| f64::from(db.to_db()));
+        let volume = match (uses_db_mapping(range), db) {
+            (Some(range), Some(db)) => db_to_fraction(db, range),
+            _ => raw_to_fraction(
+                selem.get_playback_volume(channel)?,
+                selem.get_playback_volume_range(),
+            ),
+        };
+        let muted = if selem.has_playback_switch() {
+            Some(selem.get_playback_switch(channel)? == 0)
+        } else {
+            None
+        };
+
+        Ok(HwVolume {
+            card: element.card,
+            element: element.name.clone(),
+            volume,
+            db,
+            min_db: range.map(|(min_db, _)| min_db),
+            max_db: range.map(|(_, max_db)| max_db),
+            muted,
+        })
+    }
+
+    pub(super) fn write_volume(element: &Element, volume: f64) -> Result<(), Box<dyn Error>> {
+        let selem = &element.selem;
+        match uses_db_mapping(element_db_range(element)) {
+            Some(range) => selem.set_playback_db_all(
+                MilliBel::from_db(fraction_to_db(volume, range) as f32),
+                Round::Floor,
+            )?,
+            None => selem.set_playback_volume_all(fraction_to_raw(
+                volume,
+                selem.get_playback_volume_range(),
+            ))?,
+        }
+        Ok(())
+    }
+
+    pub(super) fn write_mute(element: &Element, muted: bool) -> Result<(), Box<dyn Error>> {
+        if !element.selem.has_playback_switch() {
+            return Err(format!("控件 {} 没有静音开关", element.name).into());
+        }
+        element
+            .selem
+            .set_playback_switch_all(if muted { 0 } else { 1 })?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use std::error::Error;
+
+    use super::HwVolume;
+
+    pub(super) enum Element {}
+
+    pub(super) fn card_index(_device_id: &str) -> Option<u32> {
+        None
+    }
+
+    pub(super) fn with_element<T>(
+        _device_id: &str,
+        _f: impl FnOnce(&Element) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        Err("当前平台不支持硬件音量".into())
+    }
+
+    pub(super) fn read(element: &Element) -> Result<HwVolume, Box<dyn Error>> {
+        match *element {}
+    }
+
+    pub(super) fn write_volume(element: &Element, _volume: f64) -> Result<(), Box<dyn Error>> {
+        match *element {}
+    }
+
+    pub(super) fn write_mute(element: &Element, _muted: bool) -> Result<(), Box<dyn Error>> {
+        match *element {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn card_cache_probes_each_card_once_per_signature() {
+        let mut cache = CardCache {
+            signature: None,
+            cards: BTreeMap::new(),
+        };
+        let signature = || Some("0 [PCH]\n1 [D90]\n".to_string());
+        let mut probes = 0;
+        for card in [0, 1, 0, 1] {
+            cache.get(signature(), card, || {
+                probes += 1;
+                card == 1
+            });
+        }
+        assert_eq!(probes, 2);
+        assert!(cache.get(signature(), 1, || unreachable!()));
+
+        // 插拔后同一编号可能是另一张卡，需要重新查询。
+        assert!(!cache.get(Some("0 [PCH]\n".to_string()), 1, || false));
+        let mut probes = 0;
+        for _ in 0..2 {
+            cache.get(None, 0, || {
+                probes += 1;
+                true
+            });
+        }
+        assert_eq!(probes, 2);
+    }
+
+    #[test]
+    fn choose_element_prefers_master_then_falls_back_to_first() {
+        assert_eq!(
+            choose_element(&names(&["Speaker", "PCM", "Master"])),
+            Some(2)
+        );
+        assert_eq!(choose_element(&names(&["Headphone", "pcm"])), Some(1));
+        assert_eq!(choose_element(&names(&["Topping D90", "Clock"])), Some(0));
+        assert_eq!(choose_element(&[]), None);
+    }
+
+    #[test]
+    fn raw_volume_maps_linearly() {
+        assert_eq!(fraction_to_raw(0.5, (0, 255)), 128);
+        assert_eq!(fraction_to_raw(1.5, (-10, 10)), 10);
+        assert_eq!(raw_to_fraction(128, (0, 256)), 0.5);
+        assert_eq!(raw_to_fraction(3, (5, 5)), 0.0);
+    }
+
+    #[test]
+    fn db_volume_round_trips_and_hits_the_range_ends() {
+        let range = (-127.0, 0.0);
+        assert!(uses_db_mapping(Some(range)).is_some());
+        assert!(uses_db_mapping(Some((-12.0, 0.0))).is_none());
+        assert_eq!(db_range(0.0, 0.0), None);
+
+        assert!((fraction_to_db(1.0, range) - 0.0).abs() < 1e-9);
+        assert!((fraction_to_db(0.0, range) - -127.0).abs() < 1e-9);
+        for volume in [0.1, 0.25, 0.5, 0.9] {
+            let db = fraction_to_db(volume, range);
+            assert!((db_to_fraction(db, range) - volume).abs() < 1e-9);
+        }
+        // 感知映射下一半音量大约对应 -18 dB，而不是线性的 -63.5 dB。
+        assert!((fraction_to_db(0.5, range) + 18.0).abs() < 0.5);
+    }
+}
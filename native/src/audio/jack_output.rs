@@ -0,0 +1,491 @@
+//! JACK 输出：按声道注册输出端口，在 JACK 的 process 回调里从 ring buffer 取样本。
+//! 服务器采样率与音源不同时做线性重采样，端口的播放延迟计入播放时钟。
+//! 需要启用 `jack` feature，运行时动态加载 libjack。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ringbuf::traits::{Consumer, Observer};
+
+use crate::audio::backend::OutputDeviceInfo;
+use crate::audio::multi_output::OutputFanout;
+use crate::audio::state::SharedState;
+
+/// JACK 设备 ID 的前缀，后面是要连接的目标客户端，如 `jack:system`。
+pub(crate) const DEVICE_PREFIX: &str = "jack:";
+const DEFAULT_CLIENT_NAME: &str = "ncm-desktop";
+/// 设备 ID 只有前缀时连接的客户端。
+const DEFAULT_TARGET_CLIENT: &str = "system";
+
+/// JACK 输出的设置，在下一次开始播放时生效。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct JackOptions {
+    pub(crate) client_name: String,
+    /// 为假时只注册端口，连线交给用户。
+    pub(crate) auto_connect: bool,
+    /// 依次连接的目标端口（如 `system:playback_1`），为空时连接设备 ID 中客户端的输入端口。
+    pub(crate) connect_ports: Vec<String>,
+}
+
+impl Default for JackOptions {
+    fn default() -> Self {
+        Self {
+            client_name: DEFAULT_CLIENT_NAME.to_string(),
+            auto_connect: true,
+            connect_ports: Vec::new(),
+        }
+    }
+}
+
+pub(crate) fn is_jack_device(device_id: &str) -> bool {
+    target_client(device_id).is_some()
+}
+
+/// `jack:<client>` 中要连接的目标客户端。
+fn target_client(device_id: &str) -> Option<&str> {
+    let client = device_id.trim().strip_prefix(DEVICE_PREFIX)?.trim();
+    Some(if client.is_empty() {
+        DEFAULT_TARGET_CLIENT
+    } else {
+        client
+    })
+}
+
+/// 输出端口到目标端口的连线：单声道连到所有目标，否则按顺序一一对应。
+#[cfg_attr(not(feature = "jack"), allow(dead_code))]
+fn connection_plan<'a>(outputs: &'a [String], targets: &'a [String]) -> Vec<(&'a str, &'a str)> {
+    match outputs {
+        [mono] => targets
+            .iter()
+            .map(|target| (mono.as_str(), target.as_str()))
+            .collect(),
+        _ => outputs
+            .iter()
+            .zip(targets)
+            .map(|(output, target)| (output.as_str(), target.as_str()))
+            .collect(),
+    }
+}
+
+/// JACK 按服务器采样率输出 F32，只能接受普通 PCM。
+pub(crate) fn stream_config(
+    sample_rate: u32,
+    channels: u16,
+    strict_bit_perfect: bool,
+    dop: bool,
+) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
+    if strict_bit_perfect {
+        return Err(
+            "当前无法满足BitPerfect条件拒绝播放：JACK 输出经过服务器混音，不是独占的硬件设备"
+                .into(),
+        );
+    }
+    if dop {
+        return Err("JACK 输出不支持 DoP".into());
+    }
+    Ok((
+        cpal::StreamConfig {
+            channels,
+            sample_rate,
+            buffer_size: cpal::BufferSize::Default,
+        },
+        cpal::SampleFormat::F32,
+    ))
+}
+
+/// 实际生效的 JACK 输出参数。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct JackStreamInfo {
+    pub(crate) sample_rate: u32,
+    pub(crate) buffer_frames: u32,
+}
+
+/// 已激活的 JACK 客户端；`play` 之前输出静音，drop 时注销客户端。
+pub(crate) struct JackStream {
+    info: JackStreamInfo,
+    started: Arc<AtomicBool>,
+    #[cfg(feature = "jack")]
+    _client: imp::ActiveClient,
+}
+
+impl JackStream {
+    pub(crate) fn info(&self) -> &JackStreamInfo {
+        &self.info
+    }
+
+    pub(crate) fn play(&self) {
+        self.started.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "jack")]
+mod imp {
+    use std::time::Duration;
+
+    use cpal::Sample;
+    use jack::{
+        AsyncClient, AudioOut, Client, ClientOptions, ClientStatus, Control, Frames, LatencyType,
+        NotificationHandler, Port, PortFlags, ProcessHandler, ProcessScope,
+    };
+    use ringbuf::traits::{Producer, Split};
+    use ringbuf::{HeapCons, HeapProd, HeapRb};
+
+    use super::*;
+    use crate::audio::backend;
+    use crate::audio::multi_output::LinearResampler;
+
+    const AUDIO_PORT_TYPE: &str = "32 bit float mono audio";
+    /// 重采样前暂存的音源帧数上限，远大于一个 period 的用量。
+    const STAGING_FRAMES: usize = 16_384;
+
+    pub(super) type ActiveClient = AsyncClient<Notifications, OutputProcess>;
+
+    fn open_client(name: &str) -> Result<Client, Box<dyn std::error::Error>> {
+        let (client, _status) = Client::new(name, ClientOptions::NO_START_SERVER)
+            .map_err(|err| format!("无法连接 JACK 服务器：{err}"))?;
+        Ok(client)
+    }
+
+    /// 带音频输入端口的客户端，每个都可以作为输出目标。
+    fn sink_clients(own_name: &str) -> Vec<String> {
+        let Ok(client) = open_client(&format!("{own_name}-probe")) else {
+            return Vec::new();
+        };
+        let mut clients: Vec<String> = Vec::new();
+        for port in client.ports(None, Some(AUDIO_PORT_TYPE), PortFlags::IS_INPUT) {
+            let Some((name, _)) = port.split_once(':') else {
+                continue;
+            };
+            if name != own_name && name != client.name() && !clients.iter().any(|c| c == name) {
+                clients.push(name.to_string());
+            }
+        }
+        clients
+    }
+
+    pub(super) fn output_devices(
+        options: &JackOptions,
+        current_id: Option<&str>,
+    ) -> Vec<OutputDeviceInfo> {
+        sink_clients(&options.client_name)
+            .into_iter()
+            .map(|client| {
+                let id = format!("{DEVICE_PREFIX}{client}");
+                OutputDeviceInfo {
+                    is_default: false,
+                    is_current: current_id == Some(id.as_str()),
+                    hw_volume: false,
+                    name: format!("JACK - {client}"),
+                    id,
+                }
+            })
+            .collect()
+    }
+
+    /// 服务器停止时按设备断开处理，交给设备丢失策略。
+    pub(super) struct Notifications {
+        state: Arc<SharedState>,
+    }
+
+    impl NotificationHandler for Notifications {
+        fn shutdown(&mut self, _status: ClientStatus, reason: &str) {
+            eprintln!("[jack] server shut down: {}", reason);
+            self.state
+                .report_output_error(&cpal::StreamError::DeviceNotAvailable);
+        }
+
+        fn xrun(&mut self, _: &Client) -> Control {
+            self.state
+                .report_output_error(&cpal::StreamError::BufferUnderrun);
+            Control::Continue
+        }
+    }
+
+    /// 从 ring buffer 渲染出交错的 F32 音源帧，参数是这些帧距离播放出去的延迟。
+    type Render = Box<dyn FnMut(&mut [f32], Duration) + Send>;
+
+    pub(super) struct OutputProcess {
+        ports: Vec<Port<AudioOut>>,
+        render: Render,
+        /// 渲染出的音源帧先进暂存区，再由重采样器按服务器采样率取用。
+        staging: HeapProd<f32>,
+        staged: HeapCons<f32>,
+        resampler: LinearResampler,
+        scratch: Vec<f32>,
+        output: Vec<f32>,
+        source_rate: u32,
+        started: Arc<AtomicBool>,
+    }
+
+    impl ProcessHandler for OutputProcess {
+        fn process(&mut self, client: &Client, ps: &ProcessScope) -> Control {
+            if !self.started.load(Ordering::Relaxed) {
+                for port in &mut self.ports {
+                    port.as_mut_slice(ps).fill(0.0);
+                }
+                return Control::Continue;
+            }
+
+            let frames = ps.n_frames() as usize;
+            let channels = self.ports.len();
+            let output_rate = client.sample_rate().max(1) as f64;
+            let source_rate = f64::from(self.source_rate);
+            let ratio = source_rate / output_rate;
+
+            // 补足本周期要用的音源帧，多留两帧给插值。
+            let staged = self.staged.occupied_len() / channels;
+            let wanted = (frames as f64 * ratio).ceil() as usize + 2;
+            let needed = wanted
+                .saturating_sub(staged)
+                .min(self.staging.vacant_len() / channels);
+            if needed > 0 {
+                let (_, port_latency) = self.ports[0].get_latency_range(LatencyType::Playback);
+                let latency = Duration::from_secs_f64(
+                    f64::from(port_latency) / output_rate + staged as f64 / source_rate,
+                );
+                self.scratch.resize(needed * channels, 0.0);
+                (self.render)(&mut self.scratch, latency);
+                self.staging.push_slice(&self.scratch);
+            }
+
+            self.output.resize(frames * channels, 0.0);
+            self.resampler
+                .process(&mut self.staged, &mut self.output, channels, ratio);
+            for (channel, port) in self.ports.iter_mut().enumerate() {
+                for (sample, frame) in port
+                    .as_mut_slice(ps)
+                    .iter_mut()
+                    .zip(self.output.chunks_exact(channels))
+                {
+                    *sample = frame[channel];
+                }
+            }
+            Control::Continue
+        }
+
+        fn buffer_size(&mut self, _: &Client, size: Frames) -> Control {
+            self.output.resize(size as usize * self.ports.len(), 0.0);
+            Control::Continue
+        }
+    }
+
+    impl JackStream {
+        /// 注册输出端口并激活客户端，按设置连线；`play` 之前输出静音。
+        pub(crate) fn open<In, Out, C>(
+            device_id: &str,
+            options: &JackOptions,
+            config: &cpal::StreamConfig,
+            mut consumer: C,
+            state: Arc<SharedState>,
+            fanout: Arc<OutputFanout>,
+        ) -> Result<Self, Box<dyn std::error::Error>>
+        where
+            In: Copy + Send + 'static,
+            Out: cpal::SizedSample + cpal::FromSample<In> + Send + 'static,
+            f32: cpal::FromSample<Out>,
+            f64: cpal::FromSample<Out>,
+            C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
+        {
+            let target =
+                target_client(device_id).ok_or_else(|| format!("不是 JACK 设备：{device_id}"))?;
+            let client = open_client(&options.client_name)?;
+            let sample_rate = client.sample_rate() as u32;
+            let buffer_frames = client.buffer_size();
+
+            let channels = usize::from(config.channels);
+            let mut ports = Vec::with_capacity(channels);
+            for index in 1..=channels {
+                ports.push(client.register_port(&format!("out_{index}"), AudioOut)?);
+            }
+            let port_names = ports
+                .iter()
+                .map(|port| port.name())
+                .collect::<Result<Vec<_>, _>>()?;
+            let targets = if !options.auto_connect {
+                Vec::new()
+            } else if options.connect_ports.is_empty() {
+                let prefix = format!("{target}:");
+                client
+                    .ports(None, Some(AUDIO_PORT_TYPE), PortFlags::IS_INPUT)
+                    .into_iter()
+                    .filter(|port| port.starts_with(&prefix))
+                    .collect()
+            } else {
+                options.connect_ports.clone()
+            };
+
+            let mut rendered = Vec::<Out>::with_capacity(STAGING_FRAMES * channels);
+            let render_state = Arc::clone(&state);
+            let render: Render = Box::new(move |data, latency| {
+                rendered.resize(data.len(), Out::EQUILIBRIUM);
+                backend::render_output::<In, Out, C>(
+                    &mut consumer,
+                    &mut rendered,
+                    &render_state,
+                    channels,
+                    &fanout,
+                    latency,
+                );
+                for (out, sample) in data.iter_mut().zip(&rendered) {
+                    *out = f32::from_sample(*sample);
+                }
+            });
+            let (staging, staged) = HeapRb::<f32>::new(STAGING_FRAMES * channels).split();
+            let started = Arc::new(AtomicBool::new(false));
+            let process = OutputProcess {
+                ports,
+                render,
+                staging,
+                staged,
+                resampler: LinearResampler::new(channels),
+                scratch: Vec::with_capacity(STAGING_FRAMES * channels),
+                output: vec![0.0; buffer_frames as usize * channels],
+                source_rate: config.sample_rate,
+                started: Arc::clone(&started),
+            };
+
+            let active = client
+                .activate_async(Notifications { state }, process)
+                .map_err(|err| format!("无法激活 JACK 客户端：{err}"))?;
+            for (output, target) in connection_plan(&port_names, &targets) {
+                if let Err(err) = active.as_client().connect_ports_by_name(output, target) {
+                    eprintln!("[jack] connect {} -> {} failed: {}", output, target, err);
+                }
+            }
+
+            println!(
+                "[jack] opened {} at {} Hz, {} frames, source {} Hz",
+                active.as_client().name(),
+                sample_rate,
+                buffer_frames,
+                config.sample_rate
+            );
+            Ok(Self {
+                info: JackStreamInfo {
+                    sample_rate,
+                    buffer_frames,
+                },
+                started,
+                _client: active,
+            })
+        }
+    }
+}
+
+/// JACK 服务器上可作为输出目标的客户端；服务器未运行或未启用 JACK 时为空。
+#[cfg(feature = "jack")]
+pub(crate) fn output_devices(
+    options: &JackOptions,
+    current_id: Option<&str>,
+) -> Vec<OutputDeviceInfo> {
+    imp::output_devices(options, current_id)
+}
+
+#[cfg(not(feature = "jack"))]
+pub(crate) fn output_devices(
+    _options: &JackOptions,
+    _current_id: Option<&str>,
+) -> Vec<OutputDeviceInfo> {
+    Vec::new()
+}
+
+#[cfg(not(feature = "jack"))]
+impl JackStream {
+    pub(crate) fn open<In, Out, C>(
+        _device_id: &str,
+        _options: &JackOptions,
+        _config: &cpal::StreamConfig,
+        _consumer: C,
+        _state: Arc<SharedState>,
+        _fanout: Arc<OutputFanout>,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        In: Copy + Send + 'static,
+        Out: cpal::SizedSample + cpal::FromSample<In> + Send + 'static,
+        f32: cpal::FromSample<Out>,
+        f64: cpal::FromSample<Out>,
+        C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
+    {
+        Err("未启用 JACK 支持（需以 jack feature 编译）".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn device_ids_select_the_target_client() {
+        assert!(is_jack_device("jack:system"));
+        assert!(!is_jack_device("hw:CARD=0,DEV=0"));
+        assert_eq!(target_client("jack:system"), Some("system"));
+        assert_eq!(target_client("jack:"), Some(DEFAULT_TARGET_CLIENT));
+        assert_eq!(
+            target_client("jack:Calf Studio Gear"),
+            Some("Calf Studio Gear")
+        );
+        assert_eq!(target_client("default"), None);
+    }
+
+    #[test]
+    fn mono_output_connects_to_every_target() {
+        let targets = names(&["system:playback_1", "system:playback_2"]);
+        assert_eq!(
+            connection_plan(&names(&["ncm:out_1"]), &targets),
+            vec![
+                ("ncm:out_1", "system:playback_1"),
+                ("ncm:out_1", "system:playback_2")
+            ]
+        );
+        assert_eq!(
+            connection_plan(&names(&["ncm:out_1", "ncm:out_2"]), &targets[..1]),
+            vec![("ncm:out_1", "system:playback_1")]
+        );
+    }
+
+    #[test]
+    fn strict_and_dop_sources_are_rejected() {
+        assert!(stream_config(44_100, 2, true, false).is_err());
+        assert!(stream_config(176_400, 2, false, true).is_err());
+        let (config, format) = stream_config(96_000, 2, false, false).unwrap();
+        assert_eq!(config.sample_rate, 96_000);
+        assert_eq!(format, cpal::SampleFormat::F32);
+    }
+
+    /// 需要运行中的 `jackd -d dummy -r 48000`：`cargo test --features jack -- --ignored`。
+    #[cfg(feature = "jack")]
+    #[test]
+    #[ignore]
+    fn plays_through_dummy_server_with_resampling() {
+        use ringbuf::HeapRb;
+        use ringbuf::traits::{Producer, Split};
+        use std::time::Duration;
+
+        let state = Arc::new(SharedState::new(44_100));
+        let (mut producer, consumer) = HeapRb::<f32>::new(44_100 * 2).split();
+        producer.push_slice(&vec![0.25; 44_100 * 2]);
+        state.decoder_done.store(true, Ordering::SeqCst);
+
+        let (config, _) = stream_config(44_100, 2, false, false).unwrap();
+        let stream = JackStream::open::<f32, f32, _>(
+            "jack:system",
+            &JackOptions::default(),
+            &config,
+            consumer,
+            Arc::clone(&state),
+            Arc::new(OutputFanout::new()),
+        )
+        .unwrap();
+        assert!(stream.info().buffer_frames > 0);
+        stream.play();
+        std::thread::sleep(Duration::from_millis(500));
+
+        let frames = state.current_frame.load(Ordering::Relaxed);
+        assert!(frames > 0 && frames <= 44_100);
+        assert!(state.output_callbacks.load(Ordering::Relaxed) > 0);
+    }
+}
@@ -1,12 +1,36 @@
+pub(crate) mod alsa_hw;
+pub(crate) mod ape;
 pub(crate) mod backend;
+pub(crate) mod bit_perfect;
 pub(crate) mod cache_tracker;
+pub(crate) mod capabilities;
+pub(crate) mod chapters;
+pub(crate) mod cue;
 pub(crate) mod decoder;
 pub(crate) mod device_reservation;
+pub(crate) mod dsd;
+pub(crate) mod export;
+pub(crate) mod flac_encoder;
 pub(crate) mod http_client;
+pub(crate) mod hrtf;
+pub(crate) mod hw_mixer;
+pub(crate) mod jack_output;
+pub(crate) mod multi_output;
+pub(crate) mod ncm;
+pub(crate) mod opus;
+pub(crate) mod pipeline_stats;
 pub(crate) mod player;
+pub(crate) mod registry;
+pub(crate) mod seek_index;
+pub(crate) mod silence;
 pub(crate) mod source;
 pub(crate) mod state;
+pub(crate) mod thread_priority;
+pub(crate) mod tta;
 pub mod utils;
+pub(crate) mod virtual_output;
+pub(crate) mod wav;
+pub(crate) mod wavpack;
 
 pub use backend::OutputDeviceInfo;
 pub use player::AudioPlayer;
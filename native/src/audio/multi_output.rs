@@ -0,0 +1,584 @@
+use cpal::traits::{DeviceTrait, StreamTrait};
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::audio::backend;
+use crate::audio::state::SharedState;
+
+/// 副输出在 delay 之外额外保留的缓冲（吸收回调周期抖动）。
+const SECONDARY_CUSHION_MS: u32 = 40;
+/// 副输出 ringbuf 在目标水位之外的余量。
+const SECONDARY_HEADROOM_MS: u32 = 1_000;
+/// 漂移补偿允许的最大比率修正（±2000ppm，足够覆盖常见晶振误差）。
+const MAX_DRIFT_CORRECTION: f64 = 0.002;
+const DRIFT_KP: f64 = 0.01;
+const DRIFT_KI: f64 = 0.0005;
+const FILL_SMOOTHING: f64 = 0.01;
+
+/// 一个副输出设备的配置：设备 id + 对齐用的额外延迟。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SecondaryOutputTarget {
+    pub(crate) device_id: String,
+    pub(crate) delay_ms: u32,
+}
+
+/// 副输出运行状态快照。
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SecondaryOutputReport {
+    pub(crate) device_id: String,
+    pub(crate) delay_ms: u32,
+    pub(crate) sample_rate: u32,
+    pub(crate) resample_ratio: f64,
+    pub(crate) buffered_ms: u32,
+    pub(crate) underruns: u64,
+}
+
+/// 主输出回调与副输出回调之间共享的控制/统计量。
+struct SecondaryLink {
+    flush: AtomicBool,
+    ratio_bits: AtomicU64,
+    buffered_frames: AtomicU64,
+    underruns: AtomicU64,
+}
+
+impl SecondaryLink {
+    fn new(ratio: f64) -> Self {
+        Self {
+            flush: AtomicBool::new(false),
+            ratio_bits: AtomicU64::new(ratio.to_bits()),
+            buffered_frames: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+        }
+    }
+}
+
+struct FanoutTap {
+    producer: HeapProd<f32>,
+    link: Arc<SecondaryLink>,
+}
+
+/// 主输出回调把实际送出的样本复制给各副输出；主设备即时钟基准。
+pub(crate) struct OutputFanout {
+    active: AtomicBool,
+    taps: Mutex<Vec<FanoutTap>>,
+}
+
+impl OutputFanout {
+    pub(crate) fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            taps: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 在主输出回调中调用；重配期间拿不到锁就丢弃这一周期，不阻塞实时线程。
+    pub(crate) fn push<S>(&self, samples: &[S])
+    where
+        S: cpal::Sample,
+        f32: cpal::FromSample<S>,
+    {
+        if samples.is_empty() || !self.active.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(mut taps) = self.taps.try_lock() else {
+            return;
+        };
+        for tap in taps.iter_mut() {
+            tap.producer
+                .push_iter(samples.iter().map(|sample| sample.to_sample::<f32>()));
+        }
+    }
+
+    /// seek/切歌清空主缓冲时，副输出也要丢弃旧数据并重新预填充。
+    pub(crate) fn flush(&self) {
+        if !self.active.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(taps) = self.taps.try_lock() else {
+            return;
+        };
+        for tap in taps.iter() {
+            tap.link.flush.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn replace(&self, taps: Vec<FanoutTap>) {
+        let mut guard = self.taps.lock().unwrap();
+        self.active.store(!taps.is_empty(), Ordering::SeqCst);
+        *guard = taps;
+    }
+
+    pub(crate) fn clear(&self) {
+        self.replace(Vec::new());
+    }
+}
+
+/// 按副输出环形缓冲的水位调整重采样比率，抵消设备间的时钟漂移。
+/// 比率定义为“每个输出帧消耗的源帧数”。
+pub(crate) struct DriftController {
+    nominal_ratio: f64,
+    target_frames: f64,
+    source_rate: f64,
+    smoothed_fill: Option<f64>,
+    integral: f64,
+    ratio: f64,
+}
+
+impl DriftController {
+    pub(crate) fn new(source_rate: u32, device_rate: u32, target_frames: usize) -> Self {
+        let nominal_ratio = source_rate as f64 / device_rate.max(1) as f64;
+        Self {
+            nominal_ratio,
+            target_frames: target_frames as f64,
+            source_rate: source_rate.max(1) as f64,
+            smoothed_fill: None,
+            integral: 0.0,
+            ratio: nominal_ratio,
+        }
+    }
+
+    pub(crate) fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.smoothed_fill = None;
+        self.integral = 0.0;
+        self.ratio = self.nominal_ratio;
+    }
+
+    /// 水位高于目标说明本设备消耗偏慢，需要提高比率；反之降低。
+    pub(crate) fn update(&mut self, fill_frames: usize, period_source_frames: usize) -> f64 {
+        let fill = fill_frames as f64;
+        let smoothed = match self.smoothed_fill {
+            Some(prev) => prev + (fill - prev) * FILL_SMOOTHING,
+            None => fill,
+        };
+        self.smoothed_fill = Some(smoothed);
+
+        let error_secs = (smoothed - self.target_frames) / self.source_rate;
+        let period_secs = period_source_frames as f64 / self.source_rate;
+        self.integral = (self.integral + error_secs * period_secs).clamp(
+            -MAX_DRIFT_CORRECTION / DRIFT_KI,
+            MAX_DRIFT_CORRECTION / DRIFT_KI,
+        );
+
+        let correction = (error_secs * DRIFT_KP + self.integral * DRIFT_KI)
+            .clamp(-MAX_DRIFT_CORRECTION, MAX_DRIFT_CORRECTION);
+        self.ratio = self.nominal_ratio * (1.0 + correction);
+        self.ratio
+    }
+}
+
+/// 交错 PCM 的线性插值重采样器，按输出声道数做简单的声道映射。
+pub(crate) struct LinearResampler {
+    source_channels: usize,
+    prev: Vec<f32>,
+    next: Vec<f32>,
+    position: f64,
+    primed: bool,
+}
+
+impl LinearResampler {
+    pub(crate) fn new(source_channels: usize) -> Self {
+        let source_channels = source_channels.max(1);
+        Self {
+            source_channels,
+            prev: vec![0.0; source_channels],
+            next: vec![0.0; source_channels],
+            position: 0.0,
+            primed: false,
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.prev.fill(0.0);
+        self.next.fill(0.0);
+        self.position = 0.0;
+        self.primed = false;
+    }
+
+    /// 填满 `out`；源数据不足时剩余部分填静音并返回 false。
+    pub(crate) fn process<C>(
+        &mut self,
+        consumer: &mut C,
+        out: &mut [f32],
+        out_channels: usize,
+        ratio: f64,
+    ) -> bool
+    where
+        C: Consumer<Item = f32> + Observer<Item = f32>,
+    {
+        let out_channels = out_channels.max(1);
+        if !self.primed {
+            if !self.pop_frame(consumer) {
+                out.fill(0.0);
+                return false;
+            }
+            self.prev.copy_from_slice(&self.next);
+            if !self.pop_frame(consumer) {
+                out.fill(0.0);
+                return false;
+            }
+            self.primed = true;
+        }
+
+        let frames = out.len() / out_channels;
+        for index in 0..frames {
+            while self.position >= 1.0 {
+                self.prev.copy_from_slice(&self.next);
+                if !self.pop_frame(consumer) {
+                    out[index * out_channels..].fill(0.0);
+                    self.primed = false;
+                    self.position = 0.0;
+                    return false;
+                }
+                self.position -= 1.0;
+            }
+
+            let t = self.position as f32;
+            let frame = &mut out[index * out_channels..(index + 1) * out_channels];
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let src = channel % self.source_channels;
+                *sample = self.prev[src] + (self.next[src] - self.prev[src]) * t;
+            }
+            self.position += ratio;
+        }
+        out[frames * out_channels..].fill(0.0);
+        true
+    }
+
+    fn pop_frame<C>(&mut self, consumer: &mut C) -> bool
+    where
+        C: Consumer<Item = f32> + Observer<Item = f32>,
+    {
+        if consumer.occupied_len() < self.source_channels {
+            return false;
+        }
+        for sample in self.next.iter_mut() {
+            *sample = consumer.try_pop().unwrap_or(0.0);
+        }
+        true
+    }
+}
+
+/// 一个正在运行的副输出。
+pub(crate) struct SecondaryOutput {
+    target: SecondaryOutputTarget,
+    sample_rate: u32,
+    source_rate: u32,
+    link: Arc<SecondaryLink>,
+    _stream: cpal::Stream,
+}
+
+impl SecondaryOutput {
+    pub(crate) fn report(&self) -> SecondaryOutputReport {
+        let buffered_frames = self.link.buffered_frames.load(Ordering::Relaxed);
+        SecondaryOutputReport {
+            device_id: self.target.device_id.clone(),
+            delay_ms: self.target.delay_ms,
+            sample_rate: self.sample_rate,
+            resample_ratio: f64::from_bits(self.link.ratio_bits.load(Ordering::Relaxed)),
+            buffered_ms: (buffered_frames * 1_000 / u64::from(self.source_rate.max(1)))
+                .min(u64::from(u32::MAX)) as u32,
+            underruns: self.link.underruns.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 按当前曲目格式打开所有副输出，并把对应的 tap 挂到 fanout 上。
+/// 单个设备打开失败只记录日志，不影响主输出。
+pub(crate) fn open_secondary_outputs(
+    targets: &[SecondaryOutputTarget],
+    primary_device_id: &str,
+    source_rate: u32,
+    source_channels: u16,
+    state: &Arc<SharedState>,
+    fanout: &OutputFanout,
+) -> Vec<SecondaryOutput> {
+    let mut outputs = Vec::new();
+    let mut taps = Vec::new();
+    for target in targets {
+        if target.device_id == primary_device_id {
+            eprintln!("[audio] 副输出 {} 与主输出相同，已跳过", target.device_id);
+            continue;
+        }
+        match open_secondary_output(target, source_rate, source_channels, state) {
+            Ok((output, tap)) => {
+                outputs.push(output);
+                taps.push(tap);
+            }
+            Err(err) => {
+                eprintln!("[audio] 打开副输出 {} 失败: {}", target.device_id, err);
+            }
+        }
+    }
+    fanout.replace(taps);
+    outputs
+}
+
+/// 校验副输出设备都存在，避免配置到下一首才发现失效。
+pub(crate) fn validate_targets(
+    targets: &[SecondaryOutputTarget],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for target in targets {
+        backend::find_output_device(&target.device_id)?;
+    }
+    Ok(())
+}
+
+fn open_secondary_output(
+    target: &SecondaryOutputTarget,
+    source_rate: u32,
+    source_channels: u16,
+    state: &Arc<SharedState>,
+) -> Result<(SecondaryOutput, FanoutTap), Box<dyn std::error::Error>> {
+    let device = backend::find_output_device(&target.device_id)?;
+    let (config, sample_format) =
+        backend::find_secondary_output_config(&device, source_rate, source_channels)?;
+
+    let frames_per_ms = (source_rate as usize).div_ceil(1_000);
+    let target_frames = frames_per_ms * (target.delay_ms + SECONDARY_CUSHION_MS) as usize;
+    let capacity_frames = target_frames + frames_per_ms * SECONDARY_HEADROOM_MS as usize;
+    let rb = HeapRb::<f32>::new(capacity_frames * source_channels as usize);
+    let (producer, consumer) = rb.split();
+
+    let controller = DriftController::new(source_rate, config.sample_rate, target_frames);
+    let link = Arc::new(SecondaryLink::new(controller.ratio()));
+    let pipeline = SecondaryPipeline {
+        consumer,
+        resampler: LinearResampler::new(source_channels as usize),
+        controller,
+        link: Arc::clone(&link),
+        state: Arc::clone(state),
+        target_frames,
+        source_channels: source_channels as usize,
+        out_channels: config.channels as usize,
+        filling: true,
+        scratch: Vec::new(),
+    };
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_secondary_stream::<f32>(&device, &config, pipeline)?,
+        cpal::SampleFormat::I32 => build_secondary_stream::<i32>(&device, &config, pipeline)?,
+        cpal::SampleFormat::I16 => build_secondary_stream::<i16>(&device, &config, pipeline)?,
+        other => return Err(format!("Unsupported sample format: {:?}", other).into()),
+    };
+    stream.play()?;
+    println!(
+        "[audio] 副输出 {} 已启动: {}Hz/{}ch, delay={}ms",
+        target.device_id, config.sample_rate, config.channels, target.delay_ms
+    );
+
+    Ok((
+        SecondaryOutput {
+            target: target.clone(),
+            sample_rate: config.sample_rate,
+            source_rate,
+            link: Arc::clone(&link),
+            _stream: stream,
+        },
+        FanoutTap { producer, link },
+    ))
+}
+
+struct SecondaryPipeline {
+    consumer: HeapCons<f32>,
+    resampler: LinearResampler,
+    controller: DriftController,
+    link: Arc<SecondaryLink>,
+    state: Arc<SharedState>,
+    target_frames: usize,
+    source_channels: usize,
+    out_channels: usize,
+    filling: bool,
+    scratch: Vec<f32>,
+}
+
+impl SecondaryPipeline {
+    fn render(&mut self, out_len: usize) {
+        if self.scratch.len() < out_len {
+            self.scratch.resize(out_len, 0.0);
+        }
+        let out = &mut self.scratch[..out_len];
+
+        if self.link.flush.swap(false, Ordering::SeqCst) {
+            self.consumer.clear();
+            self.resampler.reset();
+            self.controller.reset();
+            self.filling = true;
+        }
+
+        let fill_frames = self.consumer.occupied_len() / self.source_channels;
+        self.link
+            .buffered_frames
+            .store(fill_frames as u64, Ordering::Relaxed);
+
+        // 暂停时保持水位不动，恢复后各设备仍然对齐。
+        if self.state.is_paused.load(Ordering::Relaxed) {
+            out.fill(0.0);
+            return;
+        }
+
+        // 预填充到 delay + cushion 再出声，这段等待就是该设备的对齐延迟。
+        if self.filling {
+            if fill_frames < self.target_frames {
+                out.fill(0.0);
+                return;
+            }
+            self.filling = false;
+        }
+
+        let out_frames = out_len / self.out_channels.max(1);
+        let period_source_frames = (out_frames as f64 * self.controller.ratio()).round() as usize;
+        let ratio = self.controller.update(fill_frames, period_source_frames);
+        self.link
+            .ratio_bits
+            .store(ratio.to_bits(), Ordering::Relaxed);
+
+        if !self
+            .resampler
+            .process(&mut self.consumer, out, self.out_channels, ratio)
+        {
+            self.link.underruns.fetch_add(1, Ordering::Relaxed);
+            self.controller.reset();
+            self.filling = true;
+        }
+    }
+}
+
+fn build_secondary_stream<Out>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut pipeline: SecondaryPipeline,
+) -> Result<cpal::Stream, Box<dyn std::error::Error>>
+where
+    Out: cpal::SizedSample + cpal::FromSample<f32> + Send + 'static,
+{
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [Out], _info: &cpal::OutputCallbackInfo| {
+            pipeline.render(data.len());
+            for (sample, rendered) in data.iter_mut().zip(pipeline.scratch.iter()) {
+                *sample = Out::from_sample(*rendered);
+            }
+        },
+        |err| eprintln!("[audio] 副输出流错误: {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled_consumer(samples: &[f32]) -> HeapCons<f32> {
+        let rb = HeapRb::<f32>::new(samples.len().max(1));
+        let (mut producer, consumer) = rb.split();
+        producer.push_slice(samples);
+        consumer
+    }
+
+    #[test]
+    fn drift_controller_speeds_up_when_buffer_grows_and_slows_when_it_drains() {
+        let mut controller = DriftController::new(48_000, 48_000, 4_800);
+        assert_eq!(controller.ratio(), 1.0);
+
+        let mut ratio = 1.0;
+        for _ in 0..200 {
+            ratio = controller.update(9_600, 480);
+        }
+        assert!(
+            ratio > 1.0,
+            "overfull buffer must consume faster, got {ratio}"
+        );
+
+        controller.reset();
+        for _ in 0..200 {
+            ratio = controller.update(0, 480);
+        }
+        assert!(
+            ratio < 1.0,
+            "draining buffer must consume slower, got {ratio}"
+        );
+    }
+
+    #[test]
+    fn drift_correction_is_clamped_around_nominal_ratio() {
+        let mut controller = DriftController::new(44_100, 48_000, 4_410);
+        let nominal = 44_100.0 / 48_000.0;
+        for _ in 0..10_000 {
+            controller.update(441_000, 441);
+        }
+        let ratio = controller.ratio();
+        assert!(ratio <= nominal * (1.0 + MAX_DRIFT_CORRECTION) + 1e-12);
+        assert!(ratio > nominal);
+    }
+
+    #[test]
+    fn resampler_at_unity_ratio_passes_frames_through() {
+        let mut consumer = filled_consumer(&[0.0, 0.0, 0.1, -0.1, 0.2, -0.2, 0.3, -0.3]);
+        let mut resampler = LinearResampler::new(2);
+        let mut out = [9.0f32; 6];
+
+        assert!(resampler.process(&mut consumer, &mut out, 2, 1.0));
+        assert_eq!(out, [0.0, 0.0, 0.1, -0.1, 0.2, -0.2]);
+    }
+
+    #[test]
+    fn resampler_interpolates_and_maps_mono_to_stereo() {
+        let mut consumer = filled_consumer(&[0.0, 1.0, 2.0]);
+        let mut resampler = LinearResampler::new(1);
+        let mut out = [0.0f32; 6];
+
+        assert!(resampler.process(&mut consumer, &mut out, 2, 0.5));
+        assert_eq!(out, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn resampler_reports_underrun_and_pads_with_silence() {
+        let mut consumer = filled_consumer(&[0.5, 0.5]);
+        let mut resampler = LinearResampler::new(1);
+        let mut out = [9.0f32; 4];
+
+        assert!(!resampler.process(&mut consumer, &mut out, 1, 1.0));
+        assert_eq!(out, [0.5, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn fanout_flush_marks_every_secondary_link() {
+        let fanout = OutputFanout::new();
+        let links = [
+            Arc::new(SecondaryLink::new(1.0)),
+            Arc::new(SecondaryLink::new(1.0)),
+        ];
+        let mut consumers = Vec::new();
+        let taps = links
+            .iter()
+            .map(|link| {
+                let (producer, consumer) = HeapRb::<f32>::new(8).split();
+                consumers.push(consumer);
+                FanoutTap {
+                    producer,
+                    link: Arc::clone(link),
+                }
+            })
+            .collect();
+        fanout.replace(taps);
+
+        fanout.push(&[0.25f32, -0.25]);
+        fanout.flush();
+
+        for (link, consumer) in links.iter().zip(consumers.iter()) {
+            assert!(link.flush.load(Ordering::SeqCst));
+            assert_eq!(consumer.occupied_len(), 2);
+        }
+
+        fanout.clear();
+        fanout.push(&[1.0f32]);
+        assert_eq!(consumers[0].occupied_len(), 2);
+    }
+}
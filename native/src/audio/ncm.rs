@@ -0,0 +1,526 @@
+use aes::Aes128;
+use aes::cipher::{BlockDecrypt, KeyInit, generic_array::GenericArray};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use symphonia::core::io::MediaSource;
+
+const MAGIC: &[u8; 8] = b"CTENFDAM";
+const CORE_KEY: &[u8; 16] = b"hzHRAmso5kInbaxW";
+const META_KEY: &[u8; 16] = b"#14ljk_!\\]&0U<'(";
+const KEY_PREFIX: &[u8] = b"neteasecloudmusic";
+const META_PREFIX: &[u8] = b"163 key(Don't modify):";
+const META_JSON_PREFIX: &[u8] = b"music:";
+/// 头部各段长度的上限，用于拒绝损坏文件，避免按垃圾长度分配内存。
+const MAX_KEY_LEN: u32 = 4 * 1024;
+const MAX_META_LEN: u32 = 1024 * 1024;
+const MAX_COVER_LEN: u32 = 32 * 1024 * 1024;
+
+pub(crate) fn is_ncm_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ncm"))
+}
+
+/// `.ncm` 内嵌的歌曲信息（JSON 中的已知字段），原始 JSON 保存在 `raw_json`。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct NcmMetadata {
+    pub(crate) music_id: Option<String>,
+    pub(crate) music_name: Option<String>,
+    pub(crate) artists: Vec<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) format: Option<String>,
+    pub(crate) bitrate: Option<u64>,
+    pub(crate) duration_ms: Option<u64>,
+    pub(crate) raw_json: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMetadata {
+    music_id: Option<serde_json::Value>,
+    music_name: Option<String>,
+    #[serde(default)]
+    artist: Vec<Vec<serde_json::Value>>,
+    album: Option<String>,
+    format: Option<String>,
+    bitrate: Option<u64>,
+    duration: Option<u64>,
+}
+
+impl NcmMetadata {
+    fn parse(json: &str) -> io::Result<Self> {
+        let raw: RawMetadata = serde_json::from_str(json).map_err(invalid_data)?;
+        Ok(Self {
+            music_id: raw.music_id.map(|id| match id {
+                serde_json::Value::String(id) => id,
+                other => other.to_string(),
+            }),
+            music_name: raw.music_name,
+            artists: raw
+                .artist
+                .into_iter()
+                .filter_map(|entry| entry.into_iter().next())
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect(),
+            album: raw.album,
+            format: raw.format,
+            bitrate: raw.bitrate,
+            duration_ms: raw.duration,
+            raw_json: json.to_string(),
+        })
+    }
+
+    /// 歌名、歌手与专辑对应的 Vorbis 注释风格标签。
+    pub(crate) fn tags(&self) -> Vec<(String, String)> {
+        let artist = (!self.artists.is_empty()).then(|| self.artists.join("/"));
+        [
+            ("TITLE", self.music_name.clone()),
+            ("ARTIST", artist),
+            ("ALBUM", self.album.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.filter(|v| !v.is_empty())?)))
+        .collect()
+    }
+}
+
+/// `.ncm` 头部：解出的音频密钥流、元数据、封面以及音频数据起点。
+pub(crate) struct NcmHeader {
+    keystream: [u8; 256],
+    pub(crate) metadata: Option<NcmMetadata>,
+    pub(crate) cover: Option<Vec<u8>>,
+    audio_offset: u64,
+}
+
+impl NcmHeader {
+    pub(crate) fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an ncm file"));
+        }
+        skip(reader, 2)?;
+
+        let key_len = read_len(reader, MAX_KEY_LEN)?;
+        let mut key = vec![0u8; key_len];
+        reader.read_exact(&mut key)?;
+        key.iter_mut().for_each(|byte| *byte ^= 0x64);
+        let key = aes_ecb_decrypt(CORE_KEY, &key)?;
+        let key = key
+            .strip_prefix(KEY_PREFIX)
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| invalid_data("ncm key block is malformed"))?;
+        let keystream = keystream(key);
+
+        let meta_len = read_len(reader, MAX_META_LEN)?;
+        let metadata = if meta_len > 0 {
+            let mut meta = vec![0u8; meta_len];
+            reader.read_exact(&mut meta)?;
+            meta.iter_mut().for_each(|byte| *byte ^= 0x63);
+            // 元数据只是附带信息（电台节目是 `dj:` 前缀等），解不开也照常解密音频。
+            decrypt_metadata(&meta)
+                .inspect_err(|err| eprintln!("[ncm] ignore unreadable metadata: {err}"))
+                .ok()
+        } else {
+            None
+        };
+
+        // CRC32（未使用）+ 1 字节版本，随后是封面帧长与实际图片长度。
+        skip(reader, 5)?;
+        let cover_frame_len = read_len(reader, MAX_COVER_LEN)?;
+        let cover_len = read_len(reader, MAX_COVER_LEN)?;
+        if cover_len > cover_frame_len {
+            return Err(invalid_data("ncm cover frame is malformed"));
+        }
+        let cover = if cover_len > 0 {
+            let mut cover = vec![0u8; cover_len];
+            reader.read_exact(&mut cover)?;
+            Some(cover)
+        } else {
+            None
+        };
+        skip(reader, (cover_frame_len - cover_len) as u64)?;
+
+        let audio_offset =
+            (8 + 2 + 4 + key_len + 4 + meta_len + 5 + 4 + 4 + cover_frame_len) as u64;
+        Ok(Self {
+            keystream,
+            metadata,
+            cover,
+            audio_offset,
+        })
+    }
+
+    /// 音频格式：优先取元数据里的 `format`，缺失时由调用方按解密后的内容判断。
+    pub(crate) fn format(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|meta| meta.format.as_deref())
+            .filter(|format| !format.is_empty())
+    }
+}
+
+fn decrypt_metadata(meta: &[u8]) -> io::Result<NcmMetadata> {
+    let encoded = meta
+        .strip_prefix(META_PREFIX)
+        .ok_or_else(|| invalid_data("ncm metadata is malformed"))?;
+    let encrypted = BASE64.decode(encoded).map_err(invalid_data)?;
+    let decrypted = aes_ecb_decrypt(META_KEY, &encrypted)?;
+    let json = decrypted
+        .strip_prefix(META_JSON_PREFIX)
+        .ok_or_else(|| invalid_data("ncm metadata is malformed"))?;
+    NcmMetadata::parse(std::str::from_utf8(json).map_err(invalid_data)?)
+}
+
+/// AES-128-ECB 解密并去掉 PKCS#7 填充。
+fn aes_ecb_decrypt(key: &[u8; 16], data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.is_empty() || !data.len().is_multiple_of(16) {
+        return Err(invalid_data("ncm encrypted block has invalid length"));
+    }
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    let mut out = data.to_vec();
+    for block in out.chunks_exact_mut(16) {
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+    }
+    let pad = usize::from(out[out.len() - 1]);
+    if pad == 0
+        || pad > 16
+        || out[out.len() - pad..]
+            .iter()
+            .any(|byte| usize::from(*byte) != pad)
+    {
+        return Err(invalid_data("ncm encrypted block has invalid padding"));
+    }
+    out.truncate(out.len() - pad);
+    Ok(out)
+}
+
+/// RC4 的密钥编排，但密钥流只取决于位置对 256 取模，因此可以任意 seek。
+fn keystream(key: &[u8]) -> [u8; 256] {
+    let mut sbox: [u8; 256] = std::array::from_fn(|index| index as u8);
+    let mut last = 0u8;
+    for (index, key_byte) in (0..256).zip(key.iter().cycle()) {
+        last = sbox[index].wrapping_add(last).wrapping_add(*key_byte);
+        sbox.swap(index, usize::from(last));
+    }
+    std::array::from_fn(|index| {
+        let j = (index + 1) & 0xff;
+        let a = sbox[j];
+        let b = sbox[(usize::from(a) + j) & 0xff];
+        sbox[usize::from(a.wrapping_add(b))]
+    })
+}
+
+fn read_len(reader: &mut impl Read, max: u32) -> io::Result<usize> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    let len = u32::from_le_bytes(bytes);
+    if len > max {
+        return Err(invalid_data("ncm header field is too large"));
+    }
+    Ok(len as usize)
+}
+
+fn skip(reader: &mut impl Read, count: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(count), &mut io::sink())?;
+    if skipped != count {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// 边读边解密的 `.ncm` 音频流，位置从音频数据起点算起，支持任意 seek。
+pub(crate) struct NcmSource<R> {
+    inner: R,
+    keystream: [u8; 256],
+    audio_offset: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> NcmSource<R> {
+    pub(crate) fn new(mut inner: R) -> io::Result<(Self, NcmHeader)> {
+        inner.seek(SeekFrom::Start(0))?;
+        let header = NcmHeader::read(&mut inner)?;
+        let total = inner.seek(SeekFrom::End(0))?;
+        let len = total
+            .checked_sub(header.audio_offset)
+            .filter(|len| *len > 0)
+            .ok_or_else(|| invalid_data("ncm file has no audio data"))?;
+        inner.seek(SeekFrom::Start(header.audio_offset))?;
+        Ok((
+            Self {
+                inner,
+                keystream: header.keystream,
+                audio_offset: header.audio_offset,
+                len,
+                pos: 0,
+            },
+            header,
+        ))
+    }
+}
+
+impl<R: Read> Read for NcmSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        for (offset, byte) in buf[..read].iter_mut().enumerate() {
+            *byte ^= self.keystream[((self.pos + offset as u64) & 0xff) as usize];
+        }
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for NcmSource<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start"))?;
+        self.inner
+            .seek(SeekFrom::Start(self.audio_offset + target))?;
+        self.pos = target;
+        Ok(target)
+    }
+}
+
+impl<R: Read + Seek + Send + Sync> MediaSource for NcmSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.len)
+    }
+}
+
+pub(crate) fn open_file(path: &Path) -> io::Result<(NcmSource<File>, NcmHeader)> {
+    NcmSource::new(File::open(path)?)
+}
+
+/// 读取 `.ncm` 内嵌的元数据与封面，不解密音频。
+pub(crate) fn read_header(path: &Path) -> io::Result<NcmHeader> {
+    NcmHeader::read(&mut io::BufReader::new(File::open(path)?))
+}
+
+/// 解密出原始 FLAC/MP3 文件，默认写到源文件同目录；返回输出路径。
+/// 输出文件已存在时，`overwrite` 为 false 则返回 `AlreadyExists`。
+pub(crate) fn convert_file(
+    path: &Path,
+    output_dir: Option<&Path>,
+    overwrite: bool,
+) -> io::Result<PathBuf> {
+    let (mut source, header) = open_file(path)?;
+    let mut head = [0u8; 4];
+    source.read_exact(&mut head)?;
+    source.seek(SeekFrom::Start(0))?;
+    let extension = header
+        .format()
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_else(|| sniff_extension(&head).to_string());
+
+    let dir = output_dir
+        .map(Path::to_path_buf)
+        .or_else(|| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "ncm path has no file name"))?;
+    std::fs::create_dir_all(&dir)?;
+    let output = dir.join(stem).with_extension(&extension);
+    if !overwrite {
+        // 先占住输出路径，解密期间出现的同名文件也不会被覆盖。
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&output)?;
+    }
+
+    let tmp_path = output.with_extension(format!("{extension}.tmp"));
+    let result = (|| {
+        let mut writer = io::BufWriter::new(File::create(&tmp_path)?);
+        io::copy(&mut io::BufReader::new(&mut source), &mut writer)?;
+        writer.flush()
+    })();
+    if let Err(err) = result.and_then(|()| std::fs::rename(&tmp_path, &output)) {
+        let _ = std::fs::remove_file(&tmp_path);
+        if !overwrite {
+            let _ = std::fs::remove_file(&output);
+        }
+        return Err(err);
+    }
+    Ok(output)
+}
+
+fn sniff_extension(head: &[u8; 4]) -> &'static str {
+    if head == b"fLaC" { "flac" } else { "mp3" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncrypt;
+    use std::io::Cursor;
+
+    const AUDIO_KEY: &[u8] = b"1234567890123456789012345678901E";
+
+    fn aes_ecb_encrypt(key: &[u8; 16], data: &[u8]) -> Vec<u8> {
+        let cipher = Aes128::new(GenericArray::from_slice(key));
+        let pad = 16 - data.len() % 16;
+        let mut out = data.to_vec();
+        out.resize(data.len() + pad, pad as u8);
+        for block in out.chunks_exact_mut(16) {
+            cipher.encrypt_block(GenericArray::from_mut_slice(block));
+        }
+        out
+    }
+
+    fn ncm_bytes(audio: &[u8], json: &str, cover: &[u8]) -> Vec<u8> {
+        let mut key = KEY_PREFIX.to_vec();
+        key.extend_from_slice(AUDIO_KEY);
+        let mut key = aes_ecb_encrypt(CORE_KEY, &key);
+        key.iter_mut().for_each(|byte| *byte ^= 0x64);
+
+        let mut meta = META_JSON_PREFIX.to_vec();
+        meta.extend_from_slice(json.as_bytes());
+        let mut meta = [
+            META_PREFIX,
+            BASE64.encode(aes_ecb_encrypt(META_KEY, &meta)).as_bytes(),
+        ]
+        .concat();
+        meta.iter_mut().for_each(|byte| *byte ^= 0x63);
+
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        out.extend_from_slice(&key);
+        out.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+        out.extend_from_slice(&meta);
+        out.extend_from_slice(&[0; 5]);
+        // 封面帧比图片多留 3 字节，验证按帧长跳过。
+        out.extend_from_slice(&(cover.len() as u32 + 3).to_le_bytes());
+        out.extend_from_slice(&(cover.len() as u32).to_le_bytes());
+        out.extend_from_slice(cover);
+        out.extend_from_slice(&[0; 3]);
+
+        let stream = keystream(AUDIO_KEY);
+        out.extend(
+            audio
+                .iter()
+                .enumerate()
+                .map(|(index, byte)| byte ^ stream[index & 0xff]),
+        );
+        out
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        let mut audio = b"fLaC".to_vec();
+        audio.extend((0..len).map(|index| (index * 7 + index / 300) as u8));
+        audio
+    }
+
+    const JSON: &str = r#"{"musicId":1901371647,"musicName":"Test Song","artist":[["Singer A",1],["Singer B",2]],"album":"Album","bitrate":999000,"duration":215000,"format":"flac"}"#;
+
+    #[test]
+    fn header_exposes_metadata_and_cover() {
+        let cover = b"\x89PNG fake cover".to_vec();
+        let bytes = ncm_bytes(&payload(10), JSON, &cover);
+        let header = NcmHeader::read(&mut Cursor::new(bytes)).unwrap();
+
+        let meta = header.metadata.as_ref().unwrap();
+        assert_eq!(meta.music_id.as_deref(), Some("1901371647"));
+        assert_eq!(meta.music_name.as_deref(), Some("Test Song"));
+        assert_eq!(meta.artists, vec!["Singer A", "Singer B"]);
+        assert_eq!(meta.duration_ms, Some(215_000));
+        assert!(
+            meta.tags()
+                .contains(&("ARTIST".to_string(), "Singer A/Singer B".to_string()))
+        );
+        assert_eq!(header.format(), Some("flac"));
+        assert_eq!(header.cover, Some(cover));
+    }
+
+    #[test]
+    fn source_decrypts_on_the_fly_and_seeks_anywhere() {
+        let audio = payload(5_000);
+        let (mut source, _) = NcmSource::new(Cursor::new(ncm_bytes(&audio, JSON, b""))).unwrap();
+        assert_eq!(source.byte_len(), Some(audio.len() as u64));
+
+        let mut all = Vec::new();
+        source.read_to_end(&mut all).unwrap();
+        assert_eq!(all, audio);
+
+        let mut chunk = [0u8; 600];
+        source.seek(SeekFrom::Start(1_234)).unwrap();
+        source.read_exact(&mut chunk).unwrap();
+        assert_eq!(chunk[..], audio[1_234..1_834]);
+        source.seek(SeekFrom::End(-600)).unwrap();
+        source.read_exact(&mut chunk).unwrap();
+        assert_eq!(chunk[..], audio[audio.len() - 600..]);
+    }
+
+    #[test]
+    fn convert_writes_plain_audio_next_to_the_source() {
+        let dir = std::env::temp_dir().join(format!("ncm-convert-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("song.ncm");
+        let audio = payload(2_000);
+        std::fs::write(&path, ncm_bytes(&audio, JSON, b"")).unwrap();
+
+        let output = convert_file(&path, None, false).unwrap();
+
+        assert_eq!(output, dir.join("song.flac"));
+        assert_eq!(std::fs::read(&output).unwrap(), audio);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn convert_keeps_an_existing_output_unless_overwriting() {
+        let dir = std::env::temp_dir().join(format!("ncm-overwrite-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("song.ncm");
+        let audio = payload(2_000);
+        std::fs::write(&path, ncm_bytes(&audio, JSON, b"")).unwrap();
+        std::fs::write(dir.join("song.flac"), b"mine").unwrap();
+
+        let err = convert_file(&path, None, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(dir.join("song.flac")).unwrap(), b"mine");
+
+        let output = convert_file(&path, None, true).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), audio);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unreadable_metadata_does_not_block_the_audio() {
+        let audio = payload(1_000);
+        let (mut source, header) =
+            NcmSource::new(Cursor::new(ncm_bytes(&audio, "not json", b""))).unwrap();
+        assert!(header.metadata.is_none());
+        assert_eq!(header.format(), None);
+
+        let mut all = Vec::new();
+        source.read_to_end(&mut all).unwrap();
+        assert_eq!(all, audio);
+    }
+
+    #[test]
+    fn rejects_files_without_the_ncm_magic() {
+        let err = NcmHeader::read(&mut Cursor::new(payload(100)))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,493 @@
+use opus_rs::multistream::{ChannelMappingTable, MultistreamDecoder};
+use symphonia::core::audio::{
+    AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
+};
+use symphonia::core::codecs::{
+    CODEC_TYPE_OPUS, CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult,
+};
+use symphonia::core::errors::{Error, Result, decode_error, unsupported_error};
+use symphonia::core::formats::Packet;
+use symphonia::core::support_codec;
+use symphonia::core::units::TimeBase;
+
+/// Opus 解码输出固定为 48kHz（RFC 7845 §5.1）。
+pub(crate) const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// seek 后解码器状态需要约 80ms 预滚才能收敛（RFC 7845 §4.6）。
+pub(crate) const OPUS_SEEK_PREROLL_FRAMES: u64 = 3_840;
+/// 单个 Opus packet 最长 120ms。
+const MAX_FRAMES_PER_PACKET: usize = 5_760;
+
+/// OpusHead 中与解码相关的字段。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OpusHead {
+    pub(crate) channels: u8,
+    pub(crate) pre_skip: u16,
+    pub(crate) output_gain_q8: i16,
+    pub(crate) mapping_family: u8,
+    mapping_tail: Vec<u8>,
+}
+
+impl OpusHead {
+    /// 解析 Ogg/Matroska 携带的 OpusHead（extra_data）。
+    pub(crate) fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < 19 || &buf[..8] != b"OpusHead" {
+            return None;
+        }
+        let channels = buf[9];
+        if channels == 0 {
+            return None;
+        }
+        Some(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([buf[10], buf[11]]),
+            output_gain_q8: i16::from_le_bytes([buf[16], buf[17]]),
+            mapping_family: buf[18],
+            mapping_tail: buf[19..].to_vec(),
+        })
+    }
+
+    /// 缺少 OpusHead 时按声道数推一个 family 0 的默认头。
+    fn fallback(channels: u8) -> Self {
+        Self {
+            channels,
+            pre_skip: 0,
+            output_gain_q8: 0,
+            mapping_family: 0,
+            mapping_tail: Vec::new(),
+        }
+    }
+
+    /// Q7.8 dB 增益转换为线性倍数。
+    pub(crate) fn linear_gain(&self) -> f32 {
+        10f32.powf(f32::from(self.output_gain_q8) / (20.0 * 256.0))
+    }
+
+    fn mapping_table(&self) -> Option<ChannelMappingTable> {
+        if self.mapping_family == 0 {
+            if self.channels > 2 {
+                return None;
+            }
+            return Some(ChannelMappingTable {
+                channels: self.channels,
+                stream_count: 1,
+                coupled_count: u8::from(self.channels == 2),
+                mapping: (0..self.channels).collect(),
+            });
+        }
+        ChannelMappingTable::parse(self.mapping_family, self.channels, &self.mapping_tail)
+    }
+
+    /// family 0/1 使用 Vorbis 声道顺序，需要映射到 Symphonia 的位序平面；
+    /// 其余 family 没有定义布局，按顺序占用前 N 个声道位。
+    fn channel_layout(&self) -> (Channels, Vec<usize>) {
+        let vorbis_order: &[Channels] = match (self.mapping_family, self.channels) {
+            (0 | 1, 1) => &[Channels::FRONT_LEFT],
+            (0 | 1, 2) => &[Channels::FRONT_LEFT, Channels::FRONT_RIGHT],
+            (1, 3) => &[
+                Channels::FRONT_LEFT,
+                Channels::FRONT_CENTRE,
+                Channels::FRONT_RIGHT,
+            ],
+            (1, 4) => &[
+                Channels::FRONT_LEFT,
+                Channels::FRONT_RIGHT,
+                Channels::REAR_LEFT,
+                Channels::REAR_RIGHT,
+            ],
+            (1, 5) => &[
+                Channels::FRONT_LEFT,
+                Channels::FRONT_CENTRE,
+                Channels::FRONT_RIGHT,
+                Channels::REAR_LEFT,
+                Channels::REAR_RIGHT,
+            ],
+            (1, 6) => &[
+                Channels::FRONT_LEFT,
+                Channels::FRONT_CENTRE,
+                Channels::FRONT_RIGHT,
+                Channels::REAR_LEFT,
+                Channels::REAR_RIGHT,
+                Channels::LFE1,
+            ],
+            (1, 7) => &[
+                Channels::FRONT_LEFT,
+                Channels::FRONT_CENTRE,
+                Channels::FRONT_RIGHT,
+                Channels::SIDE_LEFT,
+                Channels::SIDE_RIGHT,
+                Channels::REAR_CENTRE,
+                Channels::LFE1,
+            ],
+            (1, 8) => &[
+                Channels::FRONT_LEFT,
+                Channels::FRONT_CENTRE,
+                Channels::FRONT_RIGHT,
+                Channels::SIDE_LEFT,
+                Channels::SIDE_RIGHT,
+                Channels::REAR_LEFT,
+                Channels::REAR_RIGHT,
+                Channels::LFE1,
+            ],
+            _ => {
+                let count = u32::from(self.channels).min(32);
+                let bits = if count == 32 {
+                    u32::MAX
+                } else {
+                    (1u32 << count) - 1
+                };
+                let channels = Channels::from_bits_truncate(bits);
+                return (channels, (0..count as usize).collect());
+            }
+        };
+
+        let channels = vorbis_order
+            .iter()
+            .fold(Channels::empty(), |acc, channel| acc | *channel);
+        let planes = vorbis_order
+            .iter()
+            .map(|channel| (channels.bits() & (channel.bits() - 1)).count_ones() as usize)
+            .collect();
+        (channels, planes)
+    }
+}
+
+/// 纯 Rust 的 Opus 解码器，注册进共享的 codec registry 后供所有探测路径使用。
+///
+/// pre-skip 按 packet 时间戳裁掉，因此 seek 回开头时同样会被跳过；
+/// 时间线上 pre-skip 通过 `codec_params().delay` 暴露给调用方换算进度。
+pub(crate) struct OpusDecoder {
+    params: CodecParameters,
+    head: OpusHead,
+    table: ChannelMappingTable,
+    inner: MultistreamDecoder,
+    gain: f32,
+    plane_of_channel: Vec<usize>,
+    time_base: Option<TimeBase>,
+    interleaved: Vec<f32>,
+    buf: AudioBuffer<f32>,
+}
+
+impl OpusDecoder {
+    fn packet_start_frame(&self, ts: u64) -> u64 {
+        match self.time_base {
+            Some(tb) if tb.numer > 0 && tb.denom > 0 => {
+                let frames = u128::from(ts) * u128::from(tb.numer) * u128::from(OPUS_SAMPLE_RATE)
+                    / u128::from(tb.denom);
+                u64::try_from(frames).unwrap_or(u64::MAX)
+            }
+            _ => ts,
+        }
+    }
+}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let head = match params.extra_data.as_deref().and_then(OpusHead::parse) {
+            Some(head) => head,
+            None => {
+                let channels = params.channels.map_or(2, |channels| channels.count());
+                if !(1..=2).contains(&channels) {
+                    return unsupported_error("opus: missing OpusHead for multichannel stream");
+                }
+                OpusHead::fallback(channels as u8)
+            }
+        };
+        let Some(table) = head.mapping_table() else {
+            return unsupported_error("opus: unsupported channel mapping");
+        };
+        let inner = MultistreamDecoder::new(OPUS_SAMPLE_RATE as i32, table.clone())
+            .map_err(|_| Error::Unsupported("opus: invalid channel mapping"))?;
+
+        let (channels, plane_of_channel) = head.channel_layout();
+        let mut codec_params = params.clone();
+        codec_params
+            .with_sample_rate(OPUS_SAMPLE_RATE)
+            .with_channels(channels)
+            .with_delay(u32::from(head.pre_skip));
+        let spec = SignalSpec::new(OPUS_SAMPLE_RATE, channels);
+
+        Ok(Self {
+            gain: head.linear_gain(),
+            time_base: params.time_base,
+            params: codec_params,
+            head,
+            table,
+            inner,
+            plane_of_channel,
+            interleaved: vec![0.0; MAX_FRAMES_PER_PACKET * spec.channels.count()],
+            buf: AudioBuffer::new(MAX_FRAMES_PER_PACKET as u64, spec),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_OPUS, "opus", "Opus (pure Rust)")]
+    }
+
+    fn reset(&mut self) {
+        // opus-rs 没有公开 reset，重新建一个解码器等价于清空内部状态。
+        if let Ok(inner) = MultistreamDecoder::new(OPUS_SAMPLE_RATE as i32, self.table.clone()) {
+            self.inner = inner;
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let channels = usize::from(self.head.channels);
+        let frames =
+            match self
+                .inner
+                .decode(packet.buf(), MAX_FRAMES_PER_PACKET, &mut self.interleaved)
+            {
+                Ok(frames) => frames,
+                Err(err) => return decode_error(err),
+            };
+
+        let skip = u64::from(self.head.pre_skip)
+            .saturating_sub(self.packet_start_frame(packet.ts))
+            .min(frames as u64) as usize;
+        let keep = frames - skip;
+        self.buf.render_reserved(Some(keep));
+        for (channel, plane) in self.plane_of_channel.iter().enumerate() {
+            let out = self.buf.chan_mut(*plane);
+            for (frame, sample) in out.iter_mut().enumerate() {
+                *sample = self.interleaved[(skip + frame) * channels + channel] * self.gain;
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+/// 容器时间线上需要扣掉的前导帧数。
+///
+/// 仅 Opus 的 pre-skip 由本 crate 的解码器裁掉；其余编解码器保持时间戳原样，
+/// 与未开启 gapless 时的行为一致。
+pub(crate) fn timeline_delay_frames(params: &CodecParameters) -> u64 {
+    if params.codec != CODEC_TYPE_OPUS {
+        return 0;
+    }
+    params
+        .delay
+        .or_else(|| {
+            params
+                .extra_data
+                .as_deref()
+                .and_then(OpusHead::parse)
+                .map(|head| u32::from(head.pre_skip))
+        })
+        .map_or(0, u64::from)
+}
+
+/// seek 时需要额外向前预滚的帧数。
+pub(crate) fn seek_preroll_frames(params: &CodecParameters) -> u64 {
+    if params.codec == CODEC_TYPE_OPUS {
+        OPUS_SEEK_PREROLL_FRAMES
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::audio::registry::{self, codec_registry};
+    use opus_rs::{Application, OpusEncoder};
+    use std::io::Cursor;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::probe::Hint;
+
+    const PACKET_FRAMES: usize = 960;
+
+    fn ogg_crc(data: &[u8]) -> u32 {
+        let mut crc = 0u32;
+        for byte in data {
+            crc ^= u32::from(*byte) << 24;
+            for _ in 0..8 {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04c1_1db7
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    fn ogg_page(out: &mut Vec<u8>, header_type: u8, granule: u64, seq: u32, packets: &[Vec<u8>]) {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let start = out.len();
+        out.extend_from_slice(b"OggS");
+        out.push(0);
+        out.push(header_type);
+        out.extend_from_slice(&granule.to_le_bytes());
+        out.extend_from_slice(&0x4f50_5553u32.to_le_bytes());
+        out.extend_from_slice(&seq.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.push(lacing.len() as u8);
+        out.extend_from_slice(&lacing);
+        for packet in packets {
+            out.extend_from_slice(packet);
+        }
+        let crc = ogg_crc(&out[start..]);
+        out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+    }
+
+    /// 编码一段立体声正弦波并封装为 Ogg Opus（每页一个 20ms packet）。
+    pub(crate) fn ogg_opus_bytes(packets: usize, pre_skip: u16, output_gain_q8: i16) -> Vec<u8> {
+        let mut encoder = OpusEncoder::new(48_000, 2, Application::Audio).unwrap();
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(2);
+        head.extend_from_slice(&pre_skip.to_le_bytes());
+        head.extend_from_slice(&48_000u32.to_le_bytes());
+        head.extend_from_slice(&output_gain_q8.to_le_bytes());
+        head.push(0);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut out = Vec::new();
+        ogg_page(&mut out, 0x02, 0, 0, &[head]);
+        ogg_page(&mut out, 0x00, 0, 1, &[tags]);
+        let mut pcm = vec![0f32; PACKET_FRAMES * 2];
+        for index in 0..packets {
+            for frame in 0..PACKET_FRAMES {
+                let t = (index * PACKET_FRAMES + frame) as f32 / 48_000.0;
+                let sample = (t * 440.0 * std::f32::consts::TAU).sin() * 0.5;
+                pcm[frame * 2] = sample;
+                pcm[frame * 2 + 1] = sample;
+            }
+            let mut packet = vec![0u8; 4_000];
+            let len = encoder.encode(&pcm, PACKET_FRAMES, &mut packet).unwrap();
+            packet.truncate(len);
+            let header_type = if index + 1 == packets { 0x04 } else { 0x00 };
+            let granule = ((index + 1) * PACKET_FRAMES) as u64;
+            ogg_page(&mut out, header_type, granule, index as u32 + 2, &[packet]);
+        }
+        out
+    }
+
+    fn open(
+        bytes: Vec<u8>,
+    ) -> (
+        Box<dyn symphonia::core::formats::FormatReader>,
+        Box<dyn Decoder>,
+    ) {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("opus");
+        let format = registry::open_format(mss, &hint, None).unwrap();
+        let decoder = codec_registry()
+            .make(
+                &format.default_track().unwrap().codec_params,
+                &DecoderOptions::default(),
+            )
+            .unwrap();
+        (format, decoder)
+    }
+
+    fn decode_all(
+        format: &mut dyn symphonia::core::formats::FormatReader,
+        decoder: &mut dyn Decoder,
+    ) -> (usize, f32) {
+        let mut frames = 0;
+        let mut peak = 0f32;
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            frames += decoded.frames();
+            let mut samples = symphonia::core::audio::SampleBuffer::<f32>::new(
+                decoded.capacity() as u64,
+                *decoded.spec(),
+            );
+            samples.copy_interleaved_ref(decoded);
+            peak = samples
+                .samples()
+                .iter()
+                .fold(peak, |peak, sample| peak.max(sample.abs()));
+        }
+        (frames, peak)
+    }
+
+    #[test]
+    fn ogg_opus_decodes_through_shared_registry_with_pre_skip_trimmed() {
+        let (mut format, mut decoder) = open(ogg_opus_bytes(50, 312, 0));
+        assert_eq!(decoder.codec_params().sample_rate, Some(OPUS_SAMPLE_RATE));
+        assert_eq!(decoder.codec_params().channels.unwrap().count(), 2);
+        assert_eq!(timeline_delay_frames(decoder.codec_params()), 312);
+
+        let (frames, peak) = decode_all(&mut *format, &mut *decoder);
+        assert_eq!(frames, 50 * PACKET_FRAMES - 312);
+        assert!(
+            peak > 0.3,
+            "decoded audio should carry the sine, peak {peak}"
+        );
+    }
+
+    #[test]
+    fn output_gain_from_header_is_applied() {
+        let (mut format, mut decoder) = open(ogg_opus_bytes(25, 0, 0));
+        let (_, unity_peak) = decode_all(&mut *format, &mut *decoder);
+        // -6.02 dB ≈ 0.5 倍。
+        let (mut format, mut decoder) = open(ogg_opus_bytes(25, 0, -1541));
+        let (_, attenuated_peak) = decode_all(&mut *format, &mut *decoder);
+
+        let ratio = attenuated_peak / unity_peak;
+        assert!((ratio - 0.5).abs() < 0.01, "gain ratio {ratio}");
+    }
+
+    #[test]
+    fn decoder_resets_and_resumes_after_seek() {
+        let (mut format, mut decoder) = open(ogg_opus_bytes(50, 312, 0));
+        let track_id = format.default_track().unwrap().id;
+        let seeked = format
+            .seek(
+                symphonia::core::formats::SeekMode::Accurate,
+                symphonia::core::formats::SeekTo::TimeStamp {
+                    ts: 24_000,
+                    track_id,
+                },
+            )
+            .unwrap();
+        decoder.reset();
+        assert!(seeked.actual_ts <= 24_000);
+
+        let packet = format.next_packet().unwrap();
+        // seek 离开开头后不应再裁 pre-skip。
+        assert_eq!(decoder.decode(&packet).unwrap().frames(), PACKET_FRAMES);
+    }
+
+    #[test]
+    fn vorbis_channel_order_maps_to_symphonia_planes() {
+        let mut head = OpusHead::fallback(6);
+        head.mapping_family = 1;
+        let (channels, planes) = head.channel_layout();
+        assert_eq!(channels.count(), 6);
+        // L C R RL RR LFE → FL FR FC LFE RL RR
+        assert_eq!(planes, vec![0, 2, 1, 4, 5, 3]);
+    }
+
+    #[test]
+    fn parses_opus_head_fields() {
+        let bytes = ogg_opus_bytes(1, 312, -256);
+        let head = OpusHead::parse(&bytes[28..28 + 19]).unwrap();
+        assert_eq!(head.channels, 2);
+        assert_eq!(head.pre_skip, 312);
+        assert_eq!(head.output_gain_q8, -256);
+        assert!((head.linear_gain() - 0.891).abs() < 0.001);
+    }
+}
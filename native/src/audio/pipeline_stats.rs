@@ -0,0 +1,253 @@
+//! 播放管线的健康指标：缓冲水位、欠载、解码耗时、seek 延迟、输出回调抖动和网络卡顿。
+//! 计数自播放器创建起累计，输出回调里只做原子操作与 `try_lock`，不会阻塞音频线程。
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 缓冲水位的采样间隔与保留条数（约 30 秒）。
+pub(crate) const FILL_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+const FILL_HISTORY_LEN: usize = 120;
+/// 一次读取阻塞超过该时长记为一次网络卡顿。
+pub(crate) const NETWORK_STALL_THRESHOLD: Duration = Duration::from_millis(100);
+/// 回调间隔超过预期的倍数视为迟到。
+const LATE_CALLBACK_FACTOR: f64 = 1.5;
+
+/// 一组耗时的次数、总和、最大值与最近一次，单位微秒。
+#[derive(Debug, Default)]
+struct Timing {
+    count: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+    last_us: AtomicU64,
+}
+
+impl Timing {
+    fn record(&self, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u128::from(u64::MAX)) as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+        self.last_us.store(us, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> TimingSnapshot {
+        let count = self.count.load(Ordering::Relaxed);
+        TimingSnapshot {
+            count,
+            avg_us: self.total_us.load(Ordering::Relaxed) / count.max(1),
+            max_us: self.max_us.load(Ordering::Relaxed),
+            last_us: self.last_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TimingSnapshot {
+    pub(crate) count: u64,
+    pub(crate) avg_us: u64,
+    pub(crate) max_us: u64,
+    pub(crate) last_us: u64,
+}
+
+#[derive(Debug, Default)]
+struct FillHistory {
+    /// 缓冲中的时长（毫秒），旧的在前。
+    samples: VecDeque<u32>,
+    capacity_ms: u32,
+    sampled_at: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct CallbackClock {
+    last_at: Option<Instant>,
+    /// 上一次回调交出的时长，即到下一次回调的预期间隔。
+    expected: Duration,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct PipelineStats {
+    underruns: AtomicU64,
+    fill: Mutex<FillHistory>,
+    decode: Timing,
+    seek: Timing,
+    seek_started_at: Mutex<Option<Instant>>,
+    callback_clock: Mutex<CallbackClock>,
+    /// 回调间隔与预期间隔之差的绝对值。
+    callback_jitter: Timing,
+    late_callbacks: AtomicU64,
+    network_stalls: Timing,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PipelineStatsSnapshot {
+    pub(crate) underruns: u64,
+    pub(crate) buffered_ms: u32,
+    pub(crate) buffer_capacity_ms: u32,
+    /// 每 `FILL_SAMPLE_INTERVAL` 一个的缓冲时长，旧的在前。
+    pub(crate) fill_history_ms: Vec<u32>,
+    pub(crate) decode: TimingSnapshot,
+    pub(crate) seek: TimingSnapshot,
+    pub(crate) callback_jitter: TimingSnapshot,
+    pub(crate) late_callbacks: u64,
+    pub(crate) network_stalls: TimingSnapshot,
+}
+
+impl PipelineStats {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// 播放中缓冲被取空，输出转入等待。
+    pub(crate) fn record_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_decode(&self, elapsed: Duration) {
+        self.decode.record(elapsed);
+    }
+
+    pub(crate) fn begin_seek(&self, now: Instant) {
+        *self.seek_started_at.lock().unwrap() = Some(now);
+    }
+
+    /// seek 后输出重新开始出声。
+    pub(crate) fn finish_seek(&self, now: Instant) {
+        let Ok(mut started_at) = self.seek_started_at.try_lock() else {
+            return;
+        };
+        if let Some(started_at) = started_at.take() {
+            self.seek.record(now.saturating_duration_since(started_at));
+        }
+    }
+
+    pub(crate) fn record_network_stall(&self, elapsed: Duration) {
+        self.network_stalls.record(elapsed);
+    }
+
+    /// 每次输出回调调用；`period` 为这次交出的样本时长。
+    pub(crate) fn record_callback(&self, period: Duration, now: Instant) {
+        let Ok(mut clock) = self.callback_clock.try_lock() else {
+            return;
+        };
+        if let Some(last_at) = clock.last_at {
+            let interval = now.saturating_duration_since(last_at);
+            let expected = clock.expected;
+            self.callback_jitter.record(interval.abs_diff(expected));
+            if interval.as_secs_f64() > expected.as_secs_f64() * LATE_CALLBACK_FACTOR {
+                self.late_callbacks.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        clock.last_at = Some(now);
+        clock.expected = period;
+    }
+
+    /// 输出流重建后第一次回调的间隔没有意义。
+    pub(crate) fn reset_callback_clock(&self) {
+        *self.callback_clock.lock().unwrap() = CallbackClock::default();
+    }
+
+    /// 按 `FILL_SAMPLE_INTERVAL` 记录缓冲水位。
+    pub(crate) fn sample_fill(&self, buffered: Duration, capacity: Duration, now: Instant) {
+        let Ok(mut fill) = self.fill.try_lock() else {
+            return;
+        };
+        if fill
+            .sampled_at
+            .is_some_and(|at| now.saturating_duration_since(at) < FILL_SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        fill.sampled_at = Some(now);
+        fill.capacity_ms = duration_ms(capacity);
+        if fill.samples.len() == FILL_HISTORY_LEN {
+            fill.samples.pop_front();
+        }
+        fill.samples.push_back(duration_ms(buffered));
+    }
+
+    pub(crate) fn snapshot(&self) -> PipelineStatsSnapshot {
+        let fill = self.fill.lock().unwrap();
+        PipelineStatsSnapshot {
+            underruns: self.underruns.load(Ordering::Relaxed),
+            buffered_ms: fill.samples.back().copied().unwrap_or(0),
+            buffer_capacity_ms: fill.capacity_ms,
+            fill_history_ms: fill.samples.iter().copied().collect(),
+            decode: self.decode.snapshot(),
+            seek: self.seek.snapshot(),
+            callback_jitter: self.callback_jitter.snapshot(),
+            late_callbacks: self.late_callbacks.load(Ordering::Relaxed),
+            network_stalls: self.network_stalls.snapshot(),
+        }
+    }
+}
+
+fn duration_ms(duration: Duration) -> u32 {
+    duration.as_millis().min(u128::from(u32::MAX)) as u32
+}
+
+/// `samples` 个交错样本对应的时长。
+pub(crate) fn samples_duration(samples: usize, channels: usize, sample_rate: u32) -> Duration {
+    if channels == 0 || sample_rate == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64((samples / channels) as f64 / f64::from(sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_jitter_measures_distance_from_previous_period() {
+        let stats = PipelineStats::new();
+        let start = Instant::now();
+        let period = Duration::from_millis(10);
+        stats.record_callback(period, start);
+        stats.record_callback(period, start + Duration::from_millis(12));
+        stats.record_callback(period, start + Duration::from_millis(40));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.callback_jitter.count, 2);
+        assert_eq!(snapshot.callback_jitter.max_us, 18_000);
+        assert_eq!(snapshot.callback_jitter.last_us, 18_000);
+        assert_eq!(snapshot.late_callbacks, 1);
+
+        stats.reset_callback_clock();
+        stats.record_callback(period, start + Duration::from_secs(5));
+        assert_eq!(stats.snapshot().callback_jitter.count, 2);
+    }
+
+    #[test]
+    fn fill_history_is_sampled_and_bounded() {
+        let stats = PipelineStats::new();
+        let start = Instant::now();
+        let capacity = Duration::from_secs(10);
+        for index in 0..FILL_HISTORY_LEN as u64 + 10 {
+            let now = start + FILL_SAMPLE_INTERVAL * index as u32;
+            stats.sample_fill(Duration::from_millis(index), capacity, now);
+            // 间隔内的重复采样被忽略。
+            stats.sample_fill(Duration::ZERO, capacity, now + Duration::from_millis(1));
+        }
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.fill_history_ms.len(), FILL_HISTORY_LEN);
+        assert_eq!(snapshot.fill_history_ms[0], 10);
+        assert_eq!(snapshot.buffered_ms, FILL_HISTORY_LEN as u32 + 9);
+        assert_eq!(snapshot.buffer_capacity_ms, 10_000);
+    }
+
+    #[test]
+    fn seek_latency_is_recorded_once_per_seek() {
+        let stats = PipelineStats::new();
+        let start = Instant::now();
+        stats.begin_seek(start);
+        stats.finish_seek(start + Duration::from_millis(80));
+        stats.finish_seek(start + Duration::from_millis(500));
+
+        let seek = stats.snapshot().seek;
+        assert_eq!(seek.count, 1);
+        assert_eq!(seek.last_us, 80_000);
+    }
+}
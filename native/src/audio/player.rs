@@ -1,14 +1,14 @@
 #[cfg(target_os = "linux")]
 use crate::audio::device_reservation::DeviceReservation;
-use cpal::traits::{HostTrait, StreamTrait};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use ringbuf::HeapRb;
-use ringbuf::traits::{Producer, Split};
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
 use std::io::{Read, Seek, SeekFrom};
 use std::num::NonZeroUsize;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::LazyLock;
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Condvar, Mutex as StdMutex};
 use std::time::Duration;
 use stream_download::http::HttpStream;
@@ -17,18 +17,40 @@ use stream_download::source::SourceStream;
 use stream_download::storage::adaptive::AdaptiveStorageProvider;
 use stream_download::storage::temp::TempStorageProvider;
 use stream_download::{Settings, StreamDownload};
+use symphonia::core::audio::Channels;
 use symphonia::core::conv::ConvertibleSample;
-use tokio::sync::Notify;
+use symphonia::core::io::MediaSource;
 
+use crate::audio::alsa_hw::{self, AlsaHwOptions, AlsaHwStream, HwSample};
 use crate::audio::backend::{self, OutputDeviceInfo};
+use crate::audio::bit_perfect::{BitPerfectReport, OutputFormat, SourceFormat};
 use crate::audio::cache_tracker::SongCacheTracker;
+use crate::audio::chapters::Chapter;
 use crate::audio::decoder::{self, AudioMetadata};
+use crate::audio::dsd::{self, DsdOutput};
+use crate::audio::hrtf::{self, BinauralRenderer, HrirSet, HrtfDataset};
 use crate::audio::http_client::RangeSanitizingClient;
+use crate::audio::hw_mixer::{self, HwVolume};
+use crate::audio::jack_output::{self, JackOptions, JackStream};
+use crate::audio::multi_output::{
+    self, OutputFanout, SecondaryOutput, SecondaryOutputReport, SecondaryOutputTarget,
+};
+use crate::audio::ncm;
+use crate::audio::pipeline_stats::{PipelineStats, PipelineStatsSnapshot};
+use crate::audio::seek_index::{self, SharedSeekIndex};
+use crate::audio::silence::SilenceOptions;
 use crate::audio::source::{
-    PersistentFileStorageProvider, SeekableSource, SharedStorageState, prepare_blocking_seek,
+    CacheHandoffSource, PersistentFileStorageProvider, SeekableSource, SharedStorageState,
+    prepare_blocking_seek,
+};
+use crate::audio::state::{NO_RANGE_END_FRAME, SharedState, TotalFrames};
+use crate::audio::thread_priority::{
+    self, DECODE_THREAD_RT_PRIORITY, SchedPolicy, SchedulingReport,
 };
-use crate::audio::state::{NO_TRIM_FRAME, PlaybackClock, SharedState};
 use crate::audio::utils::estimate_prefetch_bytes;
+use crate::audio::virtual_output::{
+    self, VirtualOutput, VirtualOutputOptions, VirtualOutputStream,
+};
 use crate::cache::song::SongStreamCacheMeta;
 
 const OUTPUT_BUFFER_SECONDS: usize = 6;
@@ -220,6 +242,7 @@ async fn build_cached_stream_download(
     let content_len = stream.content_length();
     let tracker = SongCacheTracker::new(metadata_path)?;
     tracker.set_content_length(content_len)?;
+    let tracker = tracker.with_seek_index(cache_path);
 
     let prefetch_bytes =
         estimate_prefetch_bytes(content_len, duration_ms, cache_ahead_secs.unwrap_or(30));
@@ -258,11 +281,21 @@ async fn build_cached_stream_download(
     })
 }
 
+/// 播放位置对应的字节偏移：帧索引能给出帧起点时用索引，否则按时长等比估算。
 fn playback_position_to_byte(
     content_length: Option<u64>,
     duration_ms: Option<u64>,
     playback_position_ms: u64,
+    seek_index: &SharedSeekIndex,
 ) -> Option<u64> {
+    if let Some(byte) = seek_index
+        .lock()
+        .unwrap()
+        .byte_for_time(playback_position_ms)
+    {
+        return Some(byte);
+    }
+
     let content_length = content_length?;
     let duration_ms = duration_ms.filter(|duration| *duration > 0)?;
     if content_length == 0 {
@@ -275,55 +308,152 @@ fn playback_position_to_byte(
     Some(position)
 }
 
+/// 播放内容的时长；`estimated` 表示容器未给出总长度，由码率和音源大小推算。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct StreamDuration {
+    pub(crate) duration: Duration,
+    pub(crate) estimated: bool,
+}
+
+/// 文件中的一段播放区间（CUE 音轨），`end` 为 `None` 时播到文件末尾。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PlaybackRange {
+    pub(crate) start: Duration,
+    pub(crate) end: Option<Duration>,
+}
+
+impl PlaybackRange {
+    fn start_frame(&self, sample_rate: u32) -> u64 {
+        duration_to_nearest_frame(self.start, sample_rate)
+    }
+
+    fn end_frame(&self, sample_rate: u32) -> Option<u64> {
+        self.end
+            .map(|end| duration_to_nearest_frame(end, sample_rate))
+    }
+}
+
+fn duration_to_nearest_frame(duration: Duration, sample_rate: u32) -> u64 {
+    (duration.as_secs_f64() * sample_rate as f64).round() as u64
+}
+
+/// JACK 与虚拟输出设备不对应 cpal 设备。
+/// 直连声卡、不经 `plug` 转换的 ALSA 设备；`plughw:` 可能转换格式或重采样，会破坏 DoP 标记。
+fn is_raw_hw_device(device_id: &str) -> bool {
+    let normalized = device_id.trim().to_ascii_lowercase();
+    cfg!(target_os = "linux")
+        && (normalized.starts_with("hw:") || normalized.starts_with("alsa:hw:"))
+}
+
+/// DSD 音源是否尝试 DoP：严格 BitPerfect 时只在直连 `hw:` 设备上，否则要用户为该设备开启。
+/// 不解码 DoP 的 DAC 收到 DoP 帧只会播出满幅噪声，拿不准时一律转 PCM。
+fn wants_dop(device_id: &str, strict_bit_perfect: bool, dop_enabled: bool) -> bool {
+    if strict_bit_perfect {
+        is_raw_hw_device(device_id)
+    } else {
+        dop_enabled
+    }
+}
+
+fn is_non_cpal_device(device_id: &str) -> bool {
+    jack_output::is_jack_device(device_id) || virtual_output::is_virtual_device(device_id)
+}
+
+/// 主输出：cpal 流、严格 BitPerfect 下直连的 ALSA `hw:` PCM、JACK 客户端或虚拟输出。
+enum OutputStream {
+    Cpal(cpal::Stream),
+    AlsaHw(AlsaHwStream),
+    Jack(JackStream),
+    Virtual(VirtualOutputStream),
+}
+
+impl OutputStream {
+    fn play(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Self::Cpal(stream) => stream.play()?,
+            Self::AlsaHw(stream) => stream.play(),
+            Self::Jack(stream) => stream.play(),
+            Self::Virtual(stream) => stream.play(),
+        }
+        Ok(())
+    }
+}
+
 pub struct AudioPlayer {
-    device: cpal::Device,
+    /// JACK 与虚拟输出不经过 cpal，为空。
+    device: Option<cpal::Device>,
     requested_device_id: Option<String>,
-    stream: Option<cpal::Stream>,
+    stream: Option<OutputStream>,
     state: Arc<SharedState>,
+    realtime_scheduling: bool,
+    alsa_hw: AlsaHwOptions,
+    jack: JackOptions,
+    virtual_output: VirtualOutputOptions,
+    bit_perfect_verification: bool,
+    /// 当前播放的严格模式、音源格式与协商出的输出配置，供 BitPerfect 报告使用。
+    strict_bit_perfect: bool,
+    source_format: Option<SourceFormat>,
+    output_format: Option<OutputFormat>,
+    fanout: Arc<OutputFanout>,
+    /// 写进 ring buffer 的声道数；双耳渲染时为 2，与音源声道数不同。
+    stream_channels: u16,
+    /// 用户为当前设备开启了 DoP，非严格模式下 DSD 音源也先尝试 DoP。
+    dop_enabled: bool,
+    /// 当前输出的是 DoP 帧；副输出无法播放 DoP，此时不开副输出。
+    dop_output: bool,
+    secondary_targets: Vec<SecondaryOutputTarget>,
+    secondary_outputs: Vec<SecondaryOutput>,
     #[cfg(target_os = "linux")]
     device_reservation: Option<DeviceReservation>,
+    /// 管线健康指标，自播放器创建起累计。
+    pipeline_stats: Arc<PipelineStats>,
+    silence: SilenceOptions,
+    /// 当前曲目的章节，时间相对曲目开头。
+    chapters: Vec<Chapter>,
+    /// 当前输出设备上多声道音源的 HRTF 双耳渲染；为空时照常下混。
+    hrtf: Option<HrtfDataset>,
+    /// 当前曲目正在做双耳渲染时的说明，计入 BitPerfect 报告。
+    binaural: Option<String>,
 }
 
 impl AudioPlayer {
     pub fn new(device_name: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
-        let host = cpal::default_host();
-        let device = if let Some(name) = device_name {
-            #[cfg(target_os = "linux")]
-            let target_id = backend::linux_plughw_locator(name).unwrap_or_else(|| name.to_string());
-            #[cfg(not(target_os = "linux"))]
-            let target_id = name;
-
-            host.output_devices()?
-                .find(|d| backend::device_id(d) == target_id)
-                .ok_or_else(|| format!("Device not found: {}", name))?
-        } else {
-            host.default_output_device()
-                .ok_or("No default output device found")?
+        let device = match device_name {
+            Some(name) if is_non_cpal_device(name) => None,
+            Some(name) => Some(backend::find_output_device(name)?),
+            None => Some(
+                cpal::default_host()
+                    .default_output_device()
+                    .ok_or("No default output device found")?,
+            ),
         };
 
         Ok(Self {
             device,
             requested_device_id: device_name.map(|s| s.to_string()),
             stream: None,
-            state: Arc::new(SharedState {
-                is_paused: AtomicBool::new(false),
-                current_frame: AtomicU64::new(0),
-                playback_clock: std::sync::Mutex::new(PlaybackClock::new()),
-                trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
-                sample_rate: AtomicU32::new(0),
-                seek_request: std::sync::Mutex::new(None),
-                is_terminating: AtomicBool::new(false),
-                discard_buffer: AtomicBool::new(false),
-                is_discarding_buffer: AtomicBool::new(false),
-                decoder_done: AtomicBool::new(false),
-                is_finished: AtomicBool::new(false),
-                finish_notify: Notify::new(),
-                buffered_frames: AtomicU64::new(0),
-                waiting_for_seek: AtomicBool::new(false),
-                has_seek_request: AtomicBool::new(false),
-            }),
+            state: Arc::new(SharedState::new(0)),
+            realtime_scheduling: false,
+            alsa_hw: AlsaHwOptions::default(),
+            jack: JackOptions::default(),
+            virtual_output: VirtualOutputOptions::default(),
+            bit_perfect_verification: false,
+            strict_bit_perfect: false,
+            source_format: None,
+            output_format: None,
+            fanout: Arc::new(OutputFanout::new()),
+            stream_channels: 0,
+            dop_enabled: false,
+            dop_output: false,
+            secondary_targets: Vec::new(),
+            secondary_outputs: Vec::new(),
             #[cfg(target_os = "linux")]
             device_reservation: None,
+            pipeline_stats: Arc::new(PipelineStats::new()),
+            silence: SilenceOptions::default(),
+            chapters: Vec::new(),
+            hrtf: None,
+            binaural: None,
         })
     }
 
@@ -357,6 +487,7 @@ impl AudioPlayer {
                 name,
                 is_default,
                 is_current,
+                hw_volume: false,
             });
         }
         #[cfg(target_os = "linux")]
@@ -374,7 +505,19 @@ impl AudioPlayer {
 
         #[cfg(target_os = "linux")]
         backend::collapse_linux_duplicate_output_devices(&mut devices);
+        devices.extend(jack_output::output_devices(
+            &self.jack,
+            self.requested_device_id.as_deref(),
+        ));
+        devices.extend(virtual_output::output_devices(
+            &self.virtual_output,
+            self.requested_device_id.as_deref(),
+        ));
         backend::disambiguate_output_device_names(&mut devices);
+        // 同一声卡上的设备共用混音器，查询结果按卡缓存在 hw_mixer 里。
+        for device in &mut devices {
+            device.hw_volume = hw_mixer::has_hw_volume(&device.id);
+        }
         Ok(devices)
     }
 
@@ -384,24 +527,7 @@ impl AudioPlayer {
         start_at: Option<Duration>,
         strict_bit_perfect: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let extension = Path::new(url)
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string());
-        let stream = open_http_stream(url).await?;
-        let reader = StreamDownload::from_stream(
-            stream,
-            AdaptiveStorageProvider::new(
-                TempStorageProvider::default(),
-                NonZeroUsize::new(512 * 1024).unwrap(),
-            ),
-            Settings::default().prefetch_bytes(512 * 1024),
-        )
-        .await?;
-
-        let content_len = reader.content_length();
-        let source = Box::new(SeekableSource::new(reader, content_len));
-        let meta = decoder::spawn_probe_task(source, extension).await?;
+        let meta = probe_url(url, Some(Arc::clone(&self.pipeline_stats))).await?;
         self.setup_and_play(meta, start_at, strict_bit_perfect)
     }
 
@@ -416,26 +542,16 @@ impl AudioPlayer {
         start_at: Option<Duration>,
         strict_bit_perfect: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let extension = Path::new(url)
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string());
-        let download = build_cached_stream_download(
+        let meta = probe_cached_url(
             url,
             cache_path,
             metadata_path,
             duration_ms,
             cache_ahead_secs,
             max_cache_ahead_bytes,
+            Some(Arc::clone(&self.pipeline_stats)),
         )
         .await?;
-
-        let reader = download.reader;
-        let content_len = reader.content_length();
-        let source = Box::new(
-            SeekableSource::new(reader, content_len).with_storage_state(download.storage_state),
-        );
-        let meta = decoder::spawn_probe_task(source, extension).await?;
         self.setup_and_play(meta, start_at, strict_bit_perfect)
     }
 
@@ -477,6 +593,7 @@ impl AudioPlayer {
 
         let completed_for_driver = Arc::clone(&download.completed);
         let control_for_driver = Arc::clone(&control);
+        let seek_index = download.tracker.seek_index();
         let drive_result = tokio::task::spawn_blocking(move || {
             let mut reader = download.reader;
             let storage_state = download.storage_state;
@@ -505,6 +622,7 @@ impl AudioPlayer {
                     content_length,
                     duration_ms,
                     state.playback_position_ms,
+                    &seek_index,
                 ) else {
                     continue;
                 };
@@ -559,51 +677,143 @@ impl AudioPlayer {
         start_at: Option<Duration>,
         strict_bit_perfect: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let path_buf = Path::new(path);
-        let extension = path_buf
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string());
-        let file = std::fs::File::open(path_buf)?;
-        let source = Box::new(file);
+        let meta = probe_file(path).await?;
+        self.setup_and_play(meta, start_at, strict_bit_perfect)
+    }
+
+    /// 只播放文件中的一段（CUE 音轨），进度从区间起点算起，播到 `range.end` 即结束。
+    /// `next_range` 是紧接其后的区间，到达终点时不重新打开文件直接续播。
+    pub(crate) async fn play_file_range(
+        &mut self,
+        path: &str,
+        range: PlaybackRange,
+        next_range: Option<PlaybackRange>,
+        start_at: Option<Duration>,
+        strict_bit_perfect: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let meta = probe_file(path).await?;
+        self.setup_and_play_range(
+            meta,
+            Some((range, next_range)),
+            start_at,
+            strict_bit_perfect,
+        )
+    }
+
+    /// 更新当前区间结束后要续播的相邻区间。
+    pub(crate) fn queue_next_range(&self, next_range: Option<PlaybackRange>) {
+        let sample_rate = self.state.sample_rate.load(Ordering::Relaxed);
+        self.state
+            .queue_next_range(next_range.map(|range| range.end_frame(sample_rate)));
+    }
+
+    /// 输出已无缝切换到排队区间的次数。
+    pub(crate) fn range_transitions(&self) -> u64 {
+        self.state.range_transitions.load(Ordering::SeqCst)
+    }
+
+    /// 播放内存中的完整音频数据（JS 侧传入的 Buffer）。
+    pub async fn play_buffer(
+        &mut self,
+        data: Arc<[u8]>,
+        extension: Option<&str>,
+        start_at: Option<Duration>,
+        strict_bit_perfect: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if data.is_empty() {
+            return Err("Audio buffer is empty".into());
+        }
+        let extension = extension.map(|ext| ext.trim_start_matches('.').to_string());
+        let source = Box::new(std::io::Cursor::new(data));
 
         let meta = decoder::spawn_probe_task(source, extension).await?;
         self.setup_and_play(meta, start_at, strict_bit_perfect)
     }
 
     pub(crate) fn setup_and_play(
+        &mut self,
+        meta: AudioMetadata,
+        start_at: Option<Duration>,
+        strict_bit_perfect: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.setup_and_play_range(meta, None, start_at, strict_bit_perfect)
+    }
+
+    fn setup_and_play_range(
         &mut self,
         mut meta: AudioMetadata,
+        range: Option<(PlaybackRange, Option<PlaybackRange>)>,
         start_at: Option<Duration>,
         strict_bit_perfect: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
         self.stop();
 
-        self.state = Arc::new(SharedState {
-            is_paused: AtomicBool::new(false),
-            current_frame: AtomicU64::new(0),
-            playback_clock: std::sync::Mutex::new(PlaybackClock::new()),
-            trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
-            sample_rate: AtomicU32::new(meta.sample_rate),
-            seek_request: std::sync::Mutex::new(None),
-            is_terminating: AtomicBool::new(false),
-            discard_buffer: AtomicBool::new(false),
-            is_discarding_buffer: AtomicBool::new(false),
-            decoder_done: AtomicBool::new(false),
-            is_finished: AtomicBool::new(false),
-            finish_notify: Notify::new(),
-            buffered_frames: AtomicU64::new(0),
-            waiting_for_seek: AtomicBool::new(false),
-            has_seek_request: AtomicBool::new(false),
-        });
+        let dop =
+            meta.dsd_rate.is_some() && self.configure_dsd_output(&mut meta, strict_bit_perfect)?;
+        self.dop_output = dop;
+
+        self.state = Arc::new(
+            SharedState::new(meta.sample_rate)
+                .with_pipeline_stats(Arc::clone(&self.pipeline_stats)),
+        );
+        self.state
+            .realtime_scheduling
+            .store(self.realtime_scheduling, Ordering::SeqCst);
 
         let sr = meta.sample_rate;
-        let channels = meta.channels;
-        let should_predecode = start_at.is_none_or(|target| target.is_zero());
+        let renderer = self.binaural_renderer(
+            meta.channels,
+            meta.channel_layout,
+            sr,
+            strict_bit_perfect,
+            dop,
+        );
+        // 双耳渲染后解码线程只写双声道，输出配置、ring buffer 和副输出都按双声道算；
+        // 样本已被卷积改变，校验没有意义。
+        self.state
+            .sample_verifier
+            .set_enabled(self.bit_perfect_verification && renderer.is_none());
+        self.binaural = renderer
+            .as_ref()
+            .and(self.hrtf.as_ref())
+            .map(|dataset| format!("HRTF 双耳渲染：{}", dataset.name()));
+        let channels = if renderer.is_some() { 2 } else { meta.channels };
+        self.stream_channels = channels;
+        self.state.set_binaural(renderer);
+        // 没有目录的 MP3 只有按码率估算的总帧数，交给播放中按已读数据修正的估算。
+        if let Some(total_frames) = meta
+            .n_frames
+            .filter(|_| !meta.n_frames_estimated)
+            .and_then(|n_frames| decoder::timestamp_to_frame(n_frames, sr, meta.time_base))
+        {
+            self.state
+                .total_frames
+                .store(total_frames, Ordering::SeqCst);
+        }
+        self.state.source_bytes.store(
+            meta.byte_len
+                .map_or(0, |len| len.saturating_sub(meta.audio_offset)),
+            Ordering::SeqCst,
+        );
+        // CUE 分轨本身就是章节，整轨镜像里的章节时间也对不上分轨进度。
+        self.chapters = if range.is_none() {
+            std::mem::take(&mut meta.chapters)
+        } else {
+            Vec::new()
+        };
+        let mut start_at = start_at.unwrap_or(Duration::ZERO);
+        let from_track_start = start_at.is_zero();
+        if let Some((range, next_range)) = range {
+            self.state
+                .set_range(range.start_frame(sr), range.end_frame(sr));
+            self.queue_next_range(next_range);
+            start_at += range.start;
+        }
+        let should_predecode = start_at.is_zero();
 
         #[cfg(target_os = "linux")]
         if strict_bit_perfect {
-            let active_device_id = backend::device_id(&self.device);
+            let active_device_id = self.output_device_id();
             if !backend::is_linux_real_hardware_output_id(&active_device_id) {
                 return Err(format!(
                     "当前无法满足BitPerfect条件拒绝播放：当前输出端点 {} 不是真实硬件设备",
@@ -615,39 +825,66 @@ impl AudioPlayer {
 
         #[cfg(target_os = "linux")]
         let device_reservation = if strict_bit_perfect {
-            Some(DeviceReservation::reserve(&backend::device_id(
-                &self.device,
-            ))?)
+            Some(DeviceReservation::reserve(&self.output_device_id())?)
+        } else {
+            None
+        };
+
+        // 严格模式且开启了直连 ALSA 时绕过 cpal，格式直接按驱动的 hw_params 挑选。
+        let alsa_hw_pcm = if strict_bit_perfect && self.alsa_hw.enabled {
+            alsa_hw::hw_pcm_name(&self.output_device_id())
         } else {
             None
         };
 
-        let (config, sample_format) = if strict_bit_perfect {
+        let (config, sample_format) = if let Some(pcm_name) = &alsa_hw_pcm {
+            alsa_hw::find_bit_perfect_format(
+                pcm_name,
+                sr,
+                channels,
+                &backend::bit_perfect_output_formats(meta.bits_per_sample, meta.sample_format, dop),
+            )
+            .map_err(|err| format!("当前无法满足BitPerfect条件拒绝播放：{}", err))?
+        } else if self.jack_device().is_some() {
+            jack_output::stream_config(sr, channels, strict_bit_perfect, dop)?
+        } else if let Some(output) = self.virtual_device() {
+            virtual_output::stream_config(
+                &output,
+                sr,
+                channels,
+                &backend::bit_perfect_output_formats(meta.bits_per_sample, meta.sample_format, dop),
+                strict_bit_perfect,
+                dop,
+            )?
+        } else if strict_bit_perfect {
             backend::find_bit_perfect_config(
-                &self.device,
+                self.cpal_device()?,
                 sr,
                 channels,
                 meta.bits_per_sample,
                 meta.sample_format,
+                dop,
             )?
         } else {
             match backend::find_best_config(
-                &self.device,
+                self.cpal_device()?,
                 sr,
                 channels,
                 meta.bits_per_sample,
                 meta.sample_format,
+                dop,
             ) {
                 Ok(cfg) => cfg,
                 Err(primary_err) => {
                     let primary_msg = primary_err.to_string();
                     if self.maybe_fallback_to_default_device()? {
                         backend::find_best_config(
-                            &self.device,
+                            self.cpal_device()?,
                             sr,
                             channels,
                             meta.bits_per_sample,
                             meta.sample_format,
+                            dop,
                         )
                         .map_err(|fallback_err| {
                             format!(
@@ -662,14 +899,34 @@ impl AudioPlayer {
             }
         };
 
+        let source_format = SourceFormat {
+            sample_rate: sr,
+            channels: meta.channels,
+            bits_per_sample: meta.bits_per_sample,
+            sample_format: meta.sample_format,
+            dsd_rate: meta.dsd_rate,
+        };
+        let mut output_format = OutputFormat {
+            device_id: self.output_device_id(),
+            sample_rate: config.sample_rate,
+            channels: config.channels,
+            sample_format,
+            buffer_frames: match config.buffer_size {
+                cpal::BufferSize::Fixed(frames) => Some(frames),
+                cpal::BufferSize::Default => None,
+            },
+            dop,
+            alsa_hw: None,
+        };
+
         let state_for_cb = self.state.clone();
-        let stream = match sample_format {
+        let mut stream = match sample_format {
             cpal::SampleFormat::I16 => {
                 let rb = HeapRb::<i16>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<i16, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<i16, _>(
-                    &self.device,
+                let stream = self.open_output::<i16, i16, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -682,8 +939,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<u16>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<u16, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<u16, _>(
-                    &self.device,
+                let stream = self.open_output::<u16, u16, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -696,8 +953,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<i8>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<i8, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<i8, _>(
-                    &self.device,
+                let stream = self.open_output::<i8, i8, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -710,8 +967,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<u8>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<u8, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<u8, _>(
-                    &self.device,
+                let stream = self.open_output::<u8, u8, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -724,8 +981,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<i32>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<i32, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream_converted::<i32, cpal::I24, _>(
-                    &self.device,
+                let stream = self.open_output::<i32, cpal::I24, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -738,8 +995,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<u32>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<u32, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream_converted::<u32, cpal::U24, _>(
-                    &self.device,
+                let stream = self.open_output::<u32, cpal::U24, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -752,8 +1009,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<i32>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<i32, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<i32, _>(
-                    &self.device,
+                let stream = self.open_output::<i32, i32, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -766,8 +1023,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<u32>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<u32, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<u32, _>(
-                    &self.device,
+                let stream = self.open_output::<u32, u32, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -780,8 +1037,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<f32>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<f32, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<f32, _>(
-                    &self.device,
+                let stream = self.open_output::<f32, f32, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -794,8 +1051,8 @@ impl AudioPlayer {
                 let rb = HeapRb::<f64>::new(output_buffer_samples(sr, channels));
                 let (mut producer, consumer) = rb.split();
                 self.predecode_initial::<f64, _>(&mut meta, &mut producer, should_predecode);
-                let stream = backend::build_stream::<f64, _>(
-                    &self.device,
+                let stream = self.open_output::<f64, f64, _>(
+                    alsa_hw_pcm.as_deref(),
                     &config,
                     consumer,
                     state_for_cb,
@@ -807,12 +1064,37 @@ impl AudioPlayer {
             _ => return Err(format!("Unsupported sample format: {:?}", sample_format).into()),
         };
 
-        if let Some(start_at) = start_at.filter(|target| !target.is_zero()) {
-            self.seek(start_at);
+        self.open_secondary_outputs();
+
+        if !start_at.is_zero() {
+            self.state.schedule_seek(start_at);
+        }
+        self.strict_bit_perfect = strict_bit_perfect;
+        self.apply_silence_options();
+        if from_track_start {
+            self.state.silence.start_track();
         }
 
         stream.play()?;
+        match &stream {
+            OutputStream::AlsaHw(alsa_stream) => {
+                let hw_config = alsa_stream.config();
+                output_format.buffer_frames = u32::try_from(hw_config.buffer_frames).ok();
+                output_format.alsa_hw = Some(hw_config.clone());
+            }
+            OutputStream::Jack(jack_stream) => {
+                output_format.sample_rate = jack_stream.info().sample_rate;
+                output_format.buffer_frames = Some(jack_stream.info().buffer_frames);
+            }
+            OutputStream::Virtual(virtual_stream) => {
+                output_format.sample_rate = virtual_stream.info().sample_rate;
+                output_format.buffer_frames = Some(virtual_stream.info().period_frames);
+            }
+            OutputStream::Cpal(_) => {}
+        }
         self.stream = Some(stream);
+        self.source_format = Some(source_format);
+        self.output_format = Some(output_format);
         #[cfg(target_os = "linux")]
         {
             self.device_reservation = device_reservation;
@@ -820,12 +1102,132 @@ impl AudioPlayer {
         Ok(())
     }
 
+    /// DSD 音源选择输出方式，返回是否走 DoP。严格 BitPerfect 下直连 `hw:` 设备必须 DoP；
+    /// 用户为当前设备开启 DoP 时先尝试 DoP，不支持再退回解码端转 PCM；其余情况都转 PCM。
+    fn configure_dsd_output(
+        &self,
+        meta: &mut AudioMetadata,
+        strict_bit_perfect: bool,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let device_id = self.output_device_id();
+        if !wants_dop(&device_id, strict_bit_perfect, self.dop_enabled) {
+            return Ok(false);
+        }
+        if strict_bit_perfect {
+            dsd::configure_output(meta, DsdOutput::Dop)
+                .map_err(|err| format!("当前无法满足BitPerfect条件拒绝播放：{}", err))?;
+            return Ok(true);
+        }
+
+        let Some(device) = self.device.as_ref() else {
+            return Ok(false);
+        };
+        if dsd::configure_output(meta, DsdOutput::Dop).is_ok() {
+            if backend::find_best_config(
+                device,
+                meta.sample_rate,
+                meta.channels,
+                meta.bits_per_sample,
+                meta.sample_format,
+                true,
+            )
+            .is_ok()
+            {
+                return Ok(true);
+            }
+            dsd::configure_output(meta, DsdOutput::Pcm)
+                .map_err(|err| err as Box<dyn std::error::Error>)?;
+        }
+        Ok(false)
+    }
+
+    /// 当前输出设备的 ID；JACK 与虚拟输出没有对应的 cpal 设备，直接用请求的 ID。
+    fn output_device_id(&self) -> String {
+        match &self.device {
+            Some(device) => backend::device_id(device),
+            None => self.requested_device_id.clone().unwrap_or_default(),
+        }
+    }
+
+    fn cpal_device(&self) -> Result<&cpal::Device, Box<dyn std::error::Error>> {
+        self.device
+            .as_ref()
+            .ok_or_else(|| format!("{} 不是声卡设备", self.output_device_id()).into())
+    }
+
+    fn virtual_device(&self) -> Option<VirtualOutput> {
+        self.requested_device_id
+            .as_deref()
+            .and_then(VirtualOutput::parse)
+    }
+
+    fn jack_device(&self) -> Option<&str> {
+        self.requested_device_id
+            .as_deref()
+            .filter(|device_id| jack_output::is_jack_device(device_id))
+    }
+
+    fn open_output<In, Out, C>(
+        &self,
+        alsa_hw_pcm: Option<&str>,
+        config: &cpal::StreamConfig,
+        consumer: C,
+        state: Arc<SharedState>,
+        channels: usize,
+    ) -> Result<OutputStream, Box<dyn std::error::Error>>
+    where
+        In: Copy + Send + 'static,
+        Out: HwSample + cpal::FromSample<In> + Send + 'static,
+        f32: cpal::FromSample<Out>,
+        f64: cpal::FromSample<Out>,
+        C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
+    {
+        let fanout = Arc::clone(&self.fanout);
+        if let Some(device_id) = self.jack_device() {
+            return JackStream::open::<In, Out, C>(
+                device_id, &self.jack, config, consumer, state, fanout,
+            )
+            .map(OutputStream::Jack);
+        }
+        if let Some(output) = self.virtual_device() {
+            return VirtualOutputStream::open::<In, Out, C>(
+                &output,
+                &self.virtual_output,
+                config,
+                consumer,
+                state,
+                fanout,
+            )
+            .map(OutputStream::Virtual);
+        }
+        if let Some(pcm_name) = alsa_hw_pcm {
+            return AlsaHwStream::open::<In, Out, C>(
+                pcm_name,
+                config,
+                self.alsa_hw,
+                consumer,
+                state,
+                fanout,
+            )
+            .map(OutputStream::AlsaHw);
+        }
+        backend::build_stream_converted::<In, Out, C>(
+            self.cpal_device()?,
+            config,
+            consumer,
+            state,
+            channels,
+            fanout,
+        )
+        .map(OutputStream::Cpal)
+    }
+
     fn maybe_fallback_to_default_device(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
         let host = cpal::default_host();
         if let Some(default) = host.default_output_device() {
-            if backend::device_id(&default) != backend::device_id(&self.device) {
+            if Some(backend::device_id(&default)) != self.device.as_ref().map(backend::device_id) {
                 println!("[audio] falling back to default device...");
-                self.device = default;
+                self.device = Some(default);
                 return Ok(true);
             }
         }
@@ -844,9 +1246,18 @@ impl AudioPlayer {
         let mut format = meta.format_reader;
         let track_id = meta.track_id;
         let sr = meta.sample_rate;
+        let channels = usize::from(meta.channels);
         let time_base = meta.time_base;
 
         std::thread::spawn(move || {
+            if state.realtime_scheduling.load(Ordering::Relaxed) {
+                let granted = thread_priority::promote_current_thread(
+                    SchedPolicy::RoundRobin,
+                    DECODE_THREAD_RT_PRIORITY,
+                );
+                *state.decode_thread_priority.lock().unwrap() = Some(granted);
+            }
+
             loop {
                 if state.is_terminating.load(Ordering::Relaxed) {
                     break;
@@ -862,6 +1273,25 @@ impl AudioPlayer {
                     time_base,
                 );
 
+                // 拖动预览期间不做正常解码，只按最新位置播放短片段。
+                if state.scrubbing.load(Ordering::Relaxed) {
+                    if !decoder::play_scrub_snippet_if_needed::<S, _>(
+                        &state,
+                        decoder::ScrubTrack {
+                            format: &mut *format,
+                            decoder: &mut *decoder,
+                            track_id,
+                            sample_rate: sr,
+                            channels,
+                            time_base,
+                        },
+                        &mut producer,
+                    ) {
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                    continue;
+                }
+
                 // 暂停时不要继续往 ringbuf 塞数据：输出侧不消费，塞满后解码线程
                 // 会卡在 push 上，resume 时表现为整进程假死。
                 if state.is_paused.load(Ordering::Relaxed) {
@@ -898,8 +1328,13 @@ impl AudioPlayer {
             return;
         }
 
+        let channels = if self.state.is_binaural() {
+            2
+        } else {
+            meta.channels
+        };
         let target_samples =
-            predecode_target_samples(meta.sample_rate, meta.channels).min(producer.vacant_len());
+            predecode_target_samples(meta.sample_rate, channels).min(producer.vacant_len());
         while producer.occupied_len() < target_samples && !producer.is_full() {
             if !decoder::decode_next_packet::<S, _>(
                 &mut *meta.format_reader,
@@ -925,7 +1360,10 @@ impl AudioPlayer {
     }
 
     pub fn progress(&self) -> Duration {
-        let frames = self.state.progress_frame();
+        let frames = self
+            .state
+            .progress_frame()
+            .saturating_sub(self.state.range_start_frame.load(Ordering::Relaxed));
         let rate = self.state.sample_rate.load(Ordering::Relaxed);
         if rate == 0 {
             return Duration::ZERO;
@@ -933,13 +1371,87 @@ impl AudioPlayer {
         Duration::from_secs_f64(frames as f64 / rate as f64)
     }
 
+    /// 当前播放内容的时长（CUE 音轨为区间长度）。容器没有总帧数时按码率估算，
+    /// 估算值取整到秒，随读取的数据变多而更新。
+    pub(crate) fn duration(&self) -> Option<StreamDuration> {
+        let rate = self.state.sample_rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            return None;
+        }
+        let range_start = self.state.range_start_frame.load(Ordering::Relaxed);
+        let range_end = self.state.range_end_frame.load(Ordering::Relaxed);
+        let range_frames = |total: u64| {
+            let end = if range_end == NO_RANGE_END_FRAME {
+                total
+            } else {
+                range_end.min(total)
+            };
+            end.saturating_sub(range_start)
+        };
+
+        match self.state.total_frames()? {
+            TotalFrames::Exact(total) => Some(StreamDuration {
+                duration: Duration::from_secs_f64(range_frames(total) as f64 / rate as f64),
+                estimated: false,
+            }),
+            TotalFrames::Estimated(total) => Some(StreamDuration {
+                duration: Duration::from_secs(
+                    (range_frames(total) as f64 / rate as f64).round() as u64
+                ),
+                estimated: true,
+            }),
+        }
+    }
+
+    /// 开始拖动预览；之后用 `scrub_to` 更新位置，`end_scrub` 结束。
+    pub(crate) fn begin_scrub(&self) {
+        self.state
+            .begin_scrub(self.range_offset() + self.progress());
+    }
+
+    /// `target` 相对当前播放区间的起点；连续调用只保留最新的位置。
+    /// 预览片段不能越过区间终点，否则输出端会当作区间播完。返回是否处于拖动预览中。
+    pub(crate) fn scrub_to(&self, target: Duration) -> bool {
+        let mut target = self.range_offset() + target;
+        let range_end = self.state.range_end_frame.load(Ordering::Relaxed);
+        let rate = self.state.sample_rate.load(Ordering::Relaxed);
+        if range_end != NO_RANGE_END_FRAME && rate > 0 {
+            let end = Duration::from_secs_f64(range_end as f64 / rate as f64);
+            target = target.min(end.saturating_sub(Duration::from_millis(200)));
+        }
+        self.state.scrub_to(target)
+    }
+
+    /// 结束拖动预览：`commit` 时精确 seek 到最后预览的位置，否则回到拖动前的位置。
+    /// 返回是否确实结束了一次预览。
+    pub(crate) fn end_scrub(&self, commit: bool) -> bool {
+        self.state.end_scrub(commit).is_some()
+    }
+
+    fn range_offset(&self) -> Duration {
+        let range_start = self.state.range_start_frame.load(Ordering::Relaxed);
+        let rate = self.state.sample_rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(range_start as f64 / rate as f64)
+        }
+    }
+
+    /// `target` 相对当前播放区间的起点。
     pub fn seek(&self, target: Duration) {
-        self.state.schedule_seek(target);
+        self.state.schedule_seek(self.range_offset() + target);
     }
 
     pub fn stop(&mut self) {
         self.state.is_terminating.store(true, Ordering::SeqCst);
         self.stream = None;
+        self.source_format = None;
+        self.output_format = None;
+        self.fanout.clear();
+        self.secondary_outputs.clear();
+        self.chapters.clear();
+        self.binaural = None;
         #[cfg(target_os = "linux")]
         {
             self.device_reservation = None;
@@ -952,11 +1464,296 @@ impl AudioPlayer {
         self.state.is_finished.load(Ordering::Relaxed)
     }
 
+    /// 主输出流因设备断开等原因无法继续时返回原因，需重建播放器才能恢复。
+    pub(crate) fn output_fault(&self) -> Option<String> {
+        self.stream.as_ref()?;
+        self.state.output_fault(std::time::Instant::now())
+    }
+
+    /// 开关解码/输出线程的实时调度；在下一次开始播放时生效。
+    pub fn set_realtime_scheduling(&mut self, enabled: bool) {
+        self.realtime_scheduling = enabled;
+    }
+
+    pub(crate) fn scheduling_report(&self) -> SchedulingReport {
+        SchedulingReport {
+            enabled: self.realtime_scheduling,
+            decode_thread: *self.state.decode_thread_priority.lock().unwrap(),
+            output_thread: *self.state.output_thread_priority.lock().unwrap(),
+            rtprio_limit: thread_priority::rtprio_limit(),
+        }
+    }
+
+    pub(crate) fn pipeline_stats(&self) -> PipelineStatsSnapshot {
+        self.pipeline_stats.snapshot()
+    }
+
+    pub(crate) fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    /// 多声道音源在本设备上的 HRTF 双耳渲染；在下一次开始播放时生效。
+    pub(crate) fn set_hrtf(&mut self, dataset: Option<HrtfDataset>) {
+        self.hrtf = dataset;
+    }
+
+    /// 只有音源声道多于设备声道（耳机上播 5.1/7.1）时才渲染；严格 BitPerfect 与 DoP 不改样本。
+    fn binaural_renderer(
+        &self,
+        channels: u16,
+        layout: Option<Channels>,
+        sample_rate: u32,
+        strict_bit_perfect: bool,
+        dop: bool,
+    ) -> Option<BinauralRenderer> {
+        let dataset = self.hrtf.as_ref()?;
+        if strict_bit_perfect || dop || channels <= self.device_channels() {
+            return None;
+        }
+        let set = match dataset {
+            HrtfDataset::Builtin => &HrirSet::spherical_head(sample_rate),
+            HrtfDataset::Sofa(set) => set.as_ref(),
+        };
+        let layout = layout
+            .filter(|layout| layout.count() == usize::from(channels))
+            .or_else(|| hrtf::default_layout(channels))?;
+        BinauralRenderer::new(set, layout, sample_rate)
+    }
+
+    /// 设备默认配置的声道数；JACK 与虚拟输出按双声道算。
+    fn device_channels(&self) -> u16 {
+        self.device
+            .as_ref()
+            .and_then(|device| device.default_output_config().ok())
+            .map_or(2, |config| config.channels())
+    }
+
+    /// 严格 BitPerfect 播放时是否绕过 cpal 直连 ALSA `hw:` 设备；在下一次开始播放时生效。
+    pub(crate) fn set_alsa_hw_output(&mut self, options: AlsaHwOptions) {
+        self.alsa_hw = options;
+    }
+
+    /// 当前输出设备所在声卡的硬件音量，不经过播放管线，不影响 BitPerfect。
+    pub(crate) fn hw_volume(&self) -> Result<HwVolume, Box<dyn std::error::Error>> {
+        hw_mixer::get_volume(&self.output_device_id())
+    }
+
+    pub(crate) fn set_hw_volume(
+        &self,
+        volume: f64,
+    ) -> Result<HwVolume, Box<dyn std::error::Error>> {
+        hw_mixer::set_volume(&self.output_device_id(), volume)
+    }
+
+    pub(crate) fn set_hw_mute(&self, muted: bool) -> Result<HwVolume, Box<dyn std::error::Error>> {
+        hw_mixer::set_mute(&self.output_device_id(), muted)
+    }
+
+    /// JACK 输出的客户端名称与连线设置；在下一次开始播放时生效。
+    pub(crate) fn set_jack_output(&mut self, options: JackOptions) {
+        self.jack = options;
+    }
+
+    /// 当前设备是否接受 DoP；在下一次开始播放时生效。
+    pub(crate) fn set_dop_output(&mut self, enabled: bool) {
+        self.dop_enabled = enabled;
+    }
+
+    /// `null:`/`file:`/`fifo:` 虚拟输出的设置；在下一次开始播放时生效。
+    pub(crate) fn set_virtual_output(&mut self, options: VirtualOutputOptions) {
+        self.virtual_output = options;
+    }
+
+    /// 修剪首尾静音与跳过长静音的设置，对正在播放的曲目立即生效。
+    pub(crate) fn set_silence_options(&mut self, options: SilenceOptions) {
+        self.silence = options;
+        self.apply_silence_options();
+    }
+
+    /// 严格 BitPerfect 与 DoP 输出不丢弃样本。
+    fn apply_silence_options(&self) {
+        let options = if self.strict_bit_perfect || self.dop_output {
+            SilenceOptions::default()
+        } else {
+            self.silence
+        };
+        let sample_rate = self.state.sample_rate.load(Ordering::Relaxed);
+        self.state.silence.configure(options, sample_rate);
+    }
+
+    /// 开关 BitPerfect 校验模式；在下一次开始播放时生效。
+    pub(crate) fn set_bit_perfect_verification(&mut self, enabled: bool) {
+        self.bit_perfect_verification = enabled;
+    }
+
+    pub(crate) fn bit_perfect_report(&mut self) -> BitPerfectReport {
+        #[cfg(target_os = "linux")]
+        let reservation = self
+            .device_reservation
+            .as_mut()
+            .map(DeviceReservation::status);
+        #[cfg(not(target_os = "linux"))]
+        let reservation = None;
+
+        BitPerfectReport::new(
+            self.strict_bit_perfect,
+            self.source_format.clone(),
+            self.output_format.clone(),
+            reservation,
+            self.state.sample_verifier.report(),
+            self.binaural.iter().cloned().collect(),
+        )
+    }
+
+    /// 设置同步输出的副设备；正在播放时立即按当前曲目格式重建副输出。
+    pub(crate) fn set_secondary_outputs(
+        &mut self,
+        targets: Vec<SecondaryOutputTarget>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        multi_output::validate_targets(&targets)?;
+        self.secondary_targets = targets;
+        if self.stream.is_some() {
+            self.open_secondary_outputs();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn secondary_output_reports(&self) -> Vec<SecondaryOutputReport> {
+        self.secondary_outputs
+            .iter()
+            .map(SecondaryOutput::report)
+            .collect()
+    }
+
+    fn open_secondary_outputs(&mut self) {
+        self.fanout.clear();
+        self.secondary_outputs.clear();
+        if self.secondary_targets.is_empty() || self.dop_output {
+            return;
+        }
+
+        let sample_rate = self.state.sample_rate.load(Ordering::Relaxed);
+        self.secondary_outputs = multi_output::open_secondary_outputs(
+            &self.secondary_targets,
+            &self.output_device_id(),
+            sample_rate,
+            self.stream_channels,
+            &self.state,
+            &self.fanout,
+        );
+    }
+
     pub(crate) fn get_state(&self) -> Arc<SharedState> {
         self.state.clone()
     }
 }
 
+/// 打开 HTTP 音源并探测格式，边下边解码，不落盘。`stall_monitor` 记录读取时的网络卡顿。
+pub(crate) async fn probe_url(
+    url: &str,
+    stall_monitor: Option<Arc<PipelineStats>>,
+) -> Result<AudioMetadata, Box<dyn std::error::Error>> {
+    let extension = Path::new(url)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string());
+    let stream = open_http_stream(url).await?;
+    let reader = StreamDownload::from_stream(
+        stream,
+        AdaptiveStorageProvider::new(
+            TempStorageProvider::default(),
+            NonZeroUsize::new(512 * 1024).unwrap(),
+        ),
+        Settings::default().prefetch_bytes(512 * 1024),
+    )
+    .await?;
+
+    let content_len = reader.content_length();
+    let mut source = SeekableSource::new(reader, content_len);
+    if let Some(stats) = stall_monitor {
+        source = source.with_stall_monitor(stats);
+    }
+    decoder::spawn_probe_task(Box::new(source), extension).await
+}
+
+/// 打开边下边存的 HTTP 音源并探测格式；缓存文件完整后直接从文件读取。
+pub(crate) async fn probe_cached_url(
+    url: &str,
+    cache_path: &str,
+    metadata_path: &str,
+    duration_ms: Option<u64>,
+    cache_ahead_secs: Option<u32>,
+    max_cache_ahead_bytes: Option<u64>,
+    stall_monitor: Option<Arc<PipelineStats>>,
+) -> Result<AudioMetadata, Box<dyn std::error::Error>> {
+    let extension = Path::new(url)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string());
+    let download = build_cached_stream_download(
+        url,
+        cache_path,
+        metadata_path,
+        duration_ms,
+        cache_ahead_secs,
+        max_cache_ahead_bytes,
+    )
+    .await?;
+
+    let reader = download.reader;
+    let content_len = reader.content_length();
+    let tracker = download.tracker;
+    let seek_index = tracker.seek_index();
+    let generation = tracker.completion_generation();
+    let mut stream =
+        SeekableSource::new(reader, content_len).with_storage_state(download.storage_state);
+    if let Some(stats) = stall_monitor {
+        stream = stream.with_stall_monitor(stats);
+    }
+    let source = Box::new(CacheHandoffSource::new(
+        stream,
+        cache_path,
+        generation,
+        move || tracker.is_fully_downloaded(),
+    ));
+    let mut meta = decoder::spawn_probe_task(source, extension).await?;
+    meta.format_reader = seek_index::wrap_reader(meta.format_reader, seek_index);
+    Ok(meta)
+}
+
+pub(crate) async fn probe_file(path: &str) -> Result<AudioMetadata, Box<dyn std::error::Error>> {
+    let path_buf = Path::new(path);
+    let extension = path_buf
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string());
+    // 官方客户端下载的 .ncm：边读边解密，格式取内嵌元数据里的 format。
+    if ncm::is_ncm_path(path_buf) {
+        let (source, header) = ncm::open_file(path_buf)?;
+        let extension = header.format().map(str::to_string);
+        let mut meta = decoder::spawn_probe_task(Box::new(source), extension).await?;
+        // 音频本身缺少的标签用 .ncm 内嵌的歌曲信息补上。
+        for (key, value) in header.metadata.iter().flat_map(ncm::NcmMetadata::tags) {
+            if !meta.tags.iter().any(|(existing, _)| *existing == key) {
+                meta.tags.push((key, value));
+            }
+        }
+        return Ok(meta);
+    }
+
+    let file = std::fs::File::open(path_buf)?;
+    let source = Box::new(file);
+
+    // WavPack hybrid：同目录下存在同名 .wvc 时一起解码，还原无损数据。
+    let correction = extension
+        .as_deref()
+        .filter(|ext| ext.eq_ignore_ascii_case("wv"))
+        .and_then(|_| std::fs::File::open(path_buf.with_extension("wvc")).ok())
+        .map(|file| Box::new(file) as Box<dyn MediaSource>);
+
+    decoder::spawn_probe_task_with_correction(source, extension, correction).await
+}
+
 fn output_buffer_samples(sample_rate: u32, channels: u16) -> usize {
     sample_rate as usize * channels as usize * OUTPUT_BUFFER_SECONDS
 }
@@ -981,28 +1778,75 @@ mod tests {
     use super::*;
     use std::io::{Read, Write};
     use std::net::TcpListener;
-    use std::sync::Mutex;
-    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64};
     use std::thread;
 
     fn create_state(sample_rate: u32) -> SharedState {
-        SharedState {
-            is_paused: AtomicBool::new(false),
-            current_frame: AtomicU64::new(0),
-            playback_clock: Mutex::new(PlaybackClock::new()),
-            trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
-            sample_rate: AtomicU32::new(sample_rate),
-            has_seek_request: AtomicBool::new(false),
-            seek_request: Mutex::new(None),
-            is_terminating: AtomicBool::new(false),
-            discard_buffer: AtomicBool::new(false),
-            is_discarding_buffer: AtomicBool::new(false),
-            decoder_done: AtomicBool::new(false),
-            is_finished: AtomicBool::new(false),
-            finish_notify: Notify::new(),
-            buffered_frames: AtomicU64::new(0),
-            waiting_for_seek: AtomicBool::new(false),
+        SharedState::new(sample_rate)
+    }
+
+    fn pcm16_wav_bytes(sample_rate: u32, channels: u16, frames: u32) -> Vec<u8> {
+        let data_len = frames * u32::from(channels) * 2;
+        let mut bytes = Vec::with_capacity(44 + data_len as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * u32::from(channels) * 2).to_le_bytes());
+        bytes.extend_from_slice(&(channels * 2).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        bytes.resize(44 + data_len as usize, 0);
+        bytes
+    }
+
+    #[test]
+    fn dop_is_only_chosen_for_raw_hw_or_when_enabled_for_the_device() {
+        assert_eq!(wants_dop("hw:1,0", true, false), cfg!(target_os = "linux"));
+        assert!(!wants_dop("plughw:1,0", true, false));
+        assert!(!wants_dop("null:", true, true));
+        assert!(!wants_dop("hw:1,0", false, false));
+        assert!(!wants_dop("plughw:1,0", false, false));
+        assert!(wants_dop("plughw:1,0", false, true));
+    }
+
+    #[tokio::test]
+    async fn in_memory_buffer_plays_through_the_shared_decoder_path() {
+        let mut player = AudioPlayer::new(Some("null:")).expect("null output");
+        assert!(
+            player
+                .play_buffer(Arc::from([]), None, None, false)
+                .await
+                .is_err()
+        );
+
+        let data: Arc<[u8]> = Arc::from(pcm16_wav_bytes(44_100, 2, 11_025));
+        player
+            .play_buffer(data, Some(".wav"), None, false)
+            .await
+            .expect("play in-memory wav");
+
+        assert_eq!(
+            player.duration(),
+            Some(StreamDuration {
+                duration: Duration::from_millis(250),
+                estimated: false,
+            })
+        );
+        let source = player.bit_perfect_report().source.expect("source format");
+        assert_eq!(source.sample_rate, 44_100);
+        assert_eq!(source.channels, 2);
+        assert_eq!(source.bits_per_sample, Some(16));
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(3);
+        while !player.is_finished() && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
         }
+        assert!(player.is_finished(), "buffer should play to the end");
+        assert_eq!(player.progress(), Duration::from_millis(250));
     }
 
     #[test]
@@ -0,0 +1,136 @@
+use crate::audio::ape::{ApeDecoder, ApeReader};
+use crate::audio::dsd::{DsdDecoder, DsdReader};
+use crate::audio::opus::OpusDecoder;
+use crate::audio::tta::{TtaDecoder, TtaReader};
+use crate::audio::wavpack::{WavPackDecoder, WavPackReader};
+use std::sync::LazyLock;
+use symphonia::core::codecs::CodecRegistry;
+use symphonia::core::errors::Result;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::{MetadataOptions, StandardTagKey, Tag};
+use symphonia::core::probe::{Hint, Probe};
+
+/// 音源标签：Vorbis 注释风格的大写名字与值，保持读到的先后顺序。
+pub(crate) type Tags = Vec<(String, String)>;
+
+/// 所有探测/解码路径共用的 codec registry：Symphonia 自带解码器 + 本 crate 补充的解码器。
+static CODECS: LazyLock<CodecRegistry> = LazyLock::new(|| {
+    let mut registry = CodecRegistry::new();
+    symphonia::default::register_enabled_codecs(&mut registry);
+    registry.register_all::<OpusDecoder>();
+    registry.register_all::<ApeDecoder>();
+    registry.register_all::<WavPackDecoder>();
+    registry.register_all::<TtaDecoder>();
+    registry.register_all::<DsdDecoder>();
+    registry
+});
+
+/// 与 `CODECS` 对应的格式探测器，替代 `symphonia::default::get_probe()`。
+static PROBE: LazyLock<Probe> = LazyLock::new(|| {
+    let mut probe = Probe::default();
+    symphonia::default::register_enabled_formats(&mut probe);
+    probe.register_all::<ApeReader>();
+    probe.register_all::<WavPackReader>();
+    probe.register_all::<TtaReader>();
+    probe.register_all::<DsdReader>();
+    probe
+});
+
+pub(crate) fn codec_registry() -> &'static CodecRegistry {
+    &CODECS
+}
+
+pub(crate) fn probe() -> &'static Probe {
+    &PROBE
+}
+
+/// 探测容器格式。带 WavPack 校正文件（.wvc）时直接构造 WavPack reader，
+/// 由它把两路 block 合并成无损 packet。
+pub(crate) fn open_format(
+    mss: MediaSourceStream,
+    hint: &Hint,
+    correction: Option<Box<dyn MediaSource>>,
+) -> Result<Box<dyn FormatReader>> {
+    open_format_with_tags(mss, hint, correction).map(|(format, _)| format)
+}
+
+/// 同 `open_format`，额外返回探测阶段（如 ID3v2）与容器内的标签，
+/// 标签名统一成 Vorbis 注释风格的大写名字，同名时容器内的优先。
+pub(crate) fn open_format_with_tags(
+    mss: MediaSourceStream,
+    hint: &Hint,
+    correction: Option<Box<dyn MediaSource>>,
+) -> Result<(Box<dyn FormatReader>, Tags)> {
+    let format_opts = FormatOptions::default();
+    if let Some(correction) = correction {
+        let correction = MediaSourceStream::new(correction, Default::default());
+        let mut format: Box<dyn FormatReader> = Box::new(WavPackReader::with_correction(
+            mss,
+            correction,
+            &format_opts,
+        )?);
+        let tags = collect_tags(&[], &mut *format);
+        return Ok((format, tags));
+    }
+    let mut probed = probe().format(hint, mss, &format_opts, &MetadataOptions::default())?;
+    let probed_tags = probed
+        .metadata
+        .get()
+        .and_then(|mut metadata| metadata.skip_to_latest().map(|rev| rev.tags().to_vec()))
+        .unwrap_or_default();
+    let tags = collect_tags(&probed_tags, &mut *probed.format);
+    Ok((probed.format, tags))
+}
+
+fn collect_tags(probed: &[Tag], format: &mut dyn FormatReader) -> Tags {
+    let mut tags = Tags::new();
+    let container = format
+        .metadata()
+        .skip_to_latest()
+        .map(|rev| rev.tags().to_vec())
+        .unwrap_or_default();
+    for tag in container.iter().chain(probed.iter()) {
+        let value = tag.value.to_string();
+        // RIFF INFO 的值带结尾的 NUL。
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if value.is_empty() {
+            continue;
+        }
+        let key = tag
+            .std_key
+            .and_then(vorbis_key)
+            .map(str::to_string)
+            .unwrap_or_else(|| tag.key.to_ascii_uppercase());
+        if !tags.iter().any(|(existing, _)| *existing == key) {
+            tags.push((key, value.to_string()));
+        }
+    }
+    tags
+}
+
+/// 常用标签对应的 Vorbis 注释名；其余标签沿用容器里的原名。
+fn vorbis_key(key: StandardTagKey) -> Option<&'static str> {
+    Some(match key {
+        StandardTagKey::TrackTitle => "TITLE",
+        StandardTagKey::Artist => "ARTIST",
+        StandardTagKey::Album => "ALBUM",
+        StandardTagKey::AlbumArtist => "ALBUMARTIST",
+        StandardTagKey::Composer => "COMPOSER",
+        StandardTagKey::Date => "DATE",
+        StandardTagKey::Genre => "GENRE",
+        StandardTagKey::Comment => "COMMENT",
+        StandardTagKey::TrackNumber => "TRACKNUMBER",
+        StandardTagKey::TrackTotal => "TRACKTOTAL",
+        StandardTagKey::DiscNumber => "DISCNUMBER",
+        StandardTagKey::DiscTotal => "DISCTOTAL",
+        StandardTagKey::Copyright => "COPYRIGHT",
+        StandardTagKey::Lyrics => "LYRICS",
+        StandardTagKey::IdentIsrc => "ISRC",
+        StandardTagKey::ReplayGainTrackGain => "REPLAYGAIN_TRACK_GAIN",
+        StandardTagKey::ReplayGainTrackPeak => "REPLAYGAIN_TRACK_PEAK",
+        StandardTagKey::ReplayGainAlbumGain => "REPLAYGAIN_ALBUM_GAIN",
+        StandardTagKey::ReplayGainAlbumPeak => "REPLAYGAIN_ALBUM_PEAK",
+        _ => return None,
+    })
+}
@@ -0,0 +1,902 @@
+use crate::audio::registry;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use symphonia::core::codecs::{CODEC_TYPE_AAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3};
+use symphonia::core::errors::{Error, Result, SeekErrorKind, seek_error, unsupported_error};
+use symphonia::core::formats::{
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{Metadata, MetadataLog};
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
+use symphonia::default::formats::{AdtsReader, MpaReader};
+
+/// 每隔多少帧记录一个索引点；seek 时最多从索引点向前逐帧解析这么多帧。
+const POINT_INTERVAL_FRAMES: u64 = 16;
+/// 识别流类型时最多搜索的字节数，超过仍找不到连续两个帧头则放弃建索引。
+const MAX_SYNC_SEARCH_BYTES: u64 = 64 * 1024;
+/// seek 时为 MP3 bit reservoir 最多回退的参考帧数，与 Symphonia 保持一致。
+const MAX_REF_FRAMES: usize = 4;
+const MPEG_HEADER_LEN: usize = 4;
+const ADTS_HEADER_LEN: usize = 7;
+const ADTS_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+pub(crate) type SharedSeekIndex = Arc<Mutex<FrameSeekIndex>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum FrameStreamKind {
+    Mp3,
+    Adts,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SeekPoint {
+    pub(crate) ts: u64,
+    pub(crate) byte: u64,
+}
+
+/// 裸 MPEG 音频 / ADTS AAC 流的帧偏移索引。
+///
+/// 随下载从文件开头连续扫描帧头，记录“时间戳 → 帧起始字节”，
+/// 时间戳与 Symphonia 解复用器给出的 packet ts 一致（跳过 Xing/Info/VBRI 帧）。
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FrameSeekIndex {
+    pub(crate) content_length: Option<u64>,
+    pub(crate) kind: Option<FrameStreamKind>,
+    pub(crate) sample_rate: u32,
+    pub(crate) first_frame_byte: u64,
+    pub(crate) points: Vec<SeekPoint>,
+    /// 下一次扫描的起点，总是落在帧头（或待重新同步的位置）上。
+    pub(crate) scanned_bytes: u64,
+    /// `scanned_bytes` 处那一帧的时间戳。
+    pub(crate) scanned_ts: u64,
+    pub(crate) scanned_frames: u64,
+    /// 不是可索引的裸帧流（MP4、FLAC 等），不再扫描。
+    pub(crate) unsupported: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FrameHeader {
+    len: usize,
+    samples: u64,
+    sample_rate: u32,
+    mpeg1: bool,
+    layer3: bool,
+    crc: bool,
+    side_info_len: usize,
+}
+
+impl FrameSeekIndex {
+    pub(crate) fn new(content_length: Option<u64>) -> Self {
+        Self {
+            content_length,
+            ..Self::default()
+        }
+    }
+
+    /// 读取持久化的索引；文件不存在、损坏或与当前内容长度不符时返回空索引。
+    pub(crate) fn load(path: &Path, content_length: Option<u64>) -> Self {
+        std::fs::read(path)
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Self>(&raw).ok())
+            .filter(|index| index.content_length == content_length)
+            .unwrap_or_else(|| Self::new(content_length))
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let serialized =
+            serde_json::to_vec(self).map_err(|err| io::Error::other(err.to_string()))?;
+        crate::cache::io_util::atomic_write(path, &serialized)
+            .map_err(|err| io::Error::other(err.to_string()))
+    }
+
+    pub(crate) fn is_ready(&self) -> bool {
+        self.kind.is_some() && !self.unsupported
+    }
+
+    /// 扫描从 `start` 开始的一段连续数据；`start` 必须等于 `scanned_bytes`。
+    /// 末尾不完整的帧留到下一次数据到达时再扫描。
+    pub(crate) fn scan(&mut self, data: &[u8], start: u64) {
+        if self.unsupported || start != self.scanned_bytes {
+            return;
+        }
+
+        let mut pos = 0usize;
+        if self.kind.is_none() {
+            match self.detect(data, start) {
+                Some(detected) => pos = detected,
+                None => return,
+            }
+        }
+        let Some(kind) = self.kind else {
+            return;
+        };
+
+        while pos < data.len() {
+            let rest = &data[pos..];
+            let header = match kind {
+                FrameStreamKind::Mp3 => rest
+                    .get(..MPEG_HEADER_LEN)
+                    .map(|bytes| parse_mpeg_header(be_u32(bytes))),
+                FrameStreamKind::Adts => rest.get(..ADTS_HEADER_LEN).map(parse_adts_header),
+            };
+            let Some(header) = header else {
+                break;
+            };
+            let Some(header) = header else {
+                pos += 1;
+                continue;
+            };
+            let Some(frame) = rest.get(..header.len) else {
+                break;
+            };
+
+            let byte = start + pos as u64;
+            pos += header.len;
+            if kind == FrameStreamKind::Mp3 && is_info_frame(frame, &header) {
+                continue;
+            }
+            if self.scanned_frames.is_multiple_of(POINT_INTERVAL_FRAMES) {
+                self.points.push(SeekPoint {
+                    ts: self.scanned_ts,
+                    byte,
+                });
+            }
+            self.scanned_ts += header.samples;
+            self.scanned_frames += 1;
+        }
+
+        self.scanned_bytes = start + pos as u64;
+    }
+
+    /// 识别流类型并定位第一帧，返回扫描应继续的 `data` 内偏移；数据不足时返回 `None`。
+    fn detect(&mut self, data: &[u8], start: u64) -> Option<usize> {
+        let mut pos = 0usize;
+        if start == 0 {
+            if data.len() < 12 {
+                return None;
+            }
+            if is_container_magic(data) {
+                self.unsupported = true;
+                return None;
+            }
+            if &data[..3] == b"ID3" {
+                let tag_len = id3_tag_len(data);
+                // 识别前 `first_frame_byte` 暂存标签之后的位置，作为同步字搜索的起点。
+                self.first_frame_byte = tag_len;
+                self.scanned_bytes = tag_len;
+                if tag_len > data.len() as u64 {
+                    return None;
+                }
+                pos = tag_len as usize;
+            }
+        }
+
+        while pos < data.len() {
+            let offset = start + pos as u64;
+            if offset.saturating_sub(self.first_frame_byte) > MAX_SYNC_SEARCH_BYTES {
+                self.unsupported = true;
+                return None;
+            }
+            match sniff_frame_pair(&data[pos..]) {
+                Sniff::NeedMore => {
+                    self.scanned_bytes = offset;
+                    return None;
+                }
+                Sniff::NoFrame => pos += 1,
+                Sniff::Found(kind, header) => {
+                    self.kind = Some(kind);
+                    self.sample_rate = header.sample_rate;
+                    self.first_frame_byte = offset;
+                    self.scanned_bytes = offset;
+                    return Some(pos);
+                }
+            }
+        }
+
+        self.scanned_bytes = start + pos as u64;
+        None
+    }
+
+    /// 时间戳不晚于 `ts` 的最后一个已知帧起点（含扫描末端）。
+    pub(crate) fn point_before(&self, ts: u64) -> Option<SeekPoint> {
+        if !self.is_ready() || self.points.is_empty() {
+            return None;
+        }
+        if ts >= self.scanned_ts {
+            return Some(SeekPoint {
+                ts: self.scanned_ts,
+                byte: self.scanned_bytes,
+            });
+        }
+        let index = self.points.partition_point(|point| point.ts <= ts);
+        self.points.get(index.saturating_sub(1)).copied()
+    }
+
+    /// 把播放位置换算成字节偏移：已扫描范围内取帧起点，超出部分按已扫描段的平均码率外推。
+    pub(crate) fn byte_for_time(&self, position_ms: u64) -> Option<u64> {
+        if !self.is_ready() || self.sample_rate == 0 {
+            return None;
+        }
+        let ts = (u128::from(position_ms) * u128::from(self.sample_rate) / 1000) as u64;
+        let point = self.point_before(ts)?;
+        let mut byte = point.byte;
+        if ts > self.scanned_ts && self.scanned_ts > 0 {
+            let scanned_len = self.scanned_bytes.saturating_sub(self.first_frame_byte);
+            let extra = u128::from(ts - self.scanned_ts) * u128::from(scanned_len)
+                / u128::from(self.scanned_ts);
+            byte = byte.saturating_add(extra.min(u128::from(u64::MAX)) as u64);
+        }
+        Some(match self.content_length {
+            Some(content_length) => byte.min(content_length.saturating_sub(1)),
+            None => byte,
+        })
+    }
+}
+
+/// 帧索引文件与 `.meta.json` 放在一起：`song.mp3.meta.json` → `song.mp3.seek.json`。
+pub(crate) fn index_path_for_metadata(metadata_path: &Path) -> PathBuf {
+    let name = metadata_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = name.strip_suffix(".meta.json").unwrap_or(&name);
+    metadata_path.with_file_name(format!("{stem}.seek.json"))
+}
+
+/// 音源开头的布局，用于修正时长：码率估算要扣掉标签，MP3 没有目录时总帧数不可信。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct StreamStart {
+    /// 第一帧音频的字节偏移（跳过 ID3v2 标签与其中的封面）；不是裸帧流时为标签之后的位置。
+    pub(crate) audio_offset: u64,
+    /// 裸 MP3 流的第一帧没有 Xing/Info/VBRI 目录，Symphonia 给出的总帧数只是按码率估算的。
+    pub(crate) mp3_without_toc: bool,
+}
+
+/// 读取音源开头的布局，标签内容本身不读入；读完回到开头。
+pub(crate) fn read_stream_start<R: Read + Seek + ?Sized>(source: &mut R) -> StreamStart {
+    let start = read_stream_start_inner(source).unwrap_or_else(|err| {
+        eprintln!("[seek-index] 读取音源开头失败（忽略）: {err}");
+        StreamStart::default()
+    });
+    if let Err(err) = source.seek(SeekFrom::Start(0)) {
+        eprintln!("[seek-index] 无法回到音源开头: {err}");
+    }
+    start
+}
+
+fn read_stream_start_inner<R: Read + Seek + ?Sized>(source: &mut R) -> io::Result<StreamStart> {
+    let mut tag_end = 0u64;
+    loop {
+        source.seek(SeekFrom::Start(tag_end))?;
+        let mut head = Vec::with_capacity(10);
+        Read::take(&mut *source, 10).read_to_end(&mut head)?;
+        if head.len() < 10 || &head[..3] != b"ID3" {
+            break;
+        }
+        tag_end += id3_tag_len(&head);
+    }
+
+    source.seek(SeekFrom::Start(tag_end))?;
+    let mut data = Vec::new();
+    Read::take(&mut *source, MAX_SYNC_SEARCH_BYTES).read_to_end(&mut data)?;
+    let mut index = FrameSeekIndex {
+        first_frame_byte: tag_end,
+        scanned_bytes: tag_end,
+        ..FrameSeekIndex::default()
+    };
+    let found = if data.len() < 12 || is_container_magic(&data) {
+        None
+    } else {
+        index.detect(&data, tag_end)
+    };
+    let Some(pos) = found else {
+        return Ok(StreamStart {
+            audio_offset: tag_end,
+            mp3_without_toc: false,
+        });
+    };
+
+    let frame = &data[pos..];
+    let mp3_without_toc = index.kind == Some(FrameStreamKind::Mp3)
+        && parse_mpeg_header(be_u32(frame)).is_some_and(|header| {
+            !is_info_frame(frame.get(..header.len).unwrap_or(frame), &header)
+        });
+    Ok(StreamStart {
+        audio_offset: index.first_frame_byte,
+        mp3_without_toc,
+    })
+}
+
+/// ID3v2 标签总长：10 字节头 + syncsafe 长度 + 可选的 10 字节尾。
+fn id3_tag_len(head: &[u8]) -> u64 {
+    let size = head[6..10]
+        .iter()
+        .fold(0u64, |size, byte| (size << 7) | u64::from(byte & 0x7f));
+    let footer = if head[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn is_container_magic(data: &[u8]) -> bool {
+    const MAGICS: [&[u8]; 8] = [
+        b"RIFF", b"OggS", b"fLaC", b"MAC ", b"wvpk", b"TTA1", b"FRM8", b"DSD ",
+    ];
+    MAGICS.iter().any(|magic| data.starts_with(magic)) || &data[4..8] == b"ftyp"
+}
+
+enum Sniff {
+    NeedMore,
+    NoFrame,
+    Found(FrameStreamKind, FrameHeader),
+}
+
+/// 连续两个帧头一致才认定为帧流，避免把标签或封面里的随机字节当成同步字。
+fn sniff_frame_pair(data: &[u8]) -> Sniff {
+    if data.len() < 2 {
+        return Sniff::NeedMore;
+    }
+    if data[0] != 0xff || data[1] & 0xe0 != 0xe0 {
+        return Sniff::NoFrame;
+    }
+
+    for kind in [FrameStreamKind::Adts, FrameStreamKind::Mp3] {
+        let parse = |bytes: &[u8]| match kind {
+            FrameStreamKind::Mp3 => bytes
+                .get(..MPEG_HEADER_LEN)
+                .map(|bytes| parse_mpeg_header(be_u32(bytes))),
+            FrameStreamKind::Adts => bytes.get(..ADTS_HEADER_LEN).map(parse_adts_header),
+        };
+        let Some(first) = parse(data) else {
+            return Sniff::NeedMore;
+        };
+        let Some(first) = first else {
+            continue;
+        };
+        let Some(second) = data.get(first.len..).and_then(parse) else {
+            return Sniff::NeedMore;
+        };
+        if second.is_some_and(|second| second.sample_rate == first.sample_rate) {
+            return Sniff::Found(kind, first);
+        }
+    }
+    Sniff::NoFrame
+}
+
+/// 解析 MPEG 音频帧头，接受范围与 Symphonia 的 `check_header` + `parse_frame_header` 一致。
+fn parse_mpeg_header(word: u32) -> Option<FrameHeader> {
+    const BITRATES_V1: [[u32; 15]; 3] = [
+        [
+            0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ],
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ],
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ],
+    ];
+    const BITRATES_V2: [[u32; 15]; 2] = [
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ];
+
+    if word & 0xffe0_0000 != 0xffe0_0000 {
+        return None;
+    }
+    let version = (word >> 19) & 0x3;
+    let layer = match (word >> 17) & 0x3 {
+        0x3 => 1,
+        0x2 => 2,
+        0x1 => 3,
+        _ => return None,
+    };
+    let bitrate_index = ((word >> 12) & 0xf) as usize;
+    let sample_rate_index = ((word >> 10) & 0x3) as usize;
+    if version == 0x1 || bitrate_index == 0 || bitrate_index == 0xf || sample_rate_index == 3 {
+        return None;
+    }
+
+    let mpeg1 = version == 0x3;
+    let base_rate = [44100, 48000, 32000][sample_rate_index];
+    let sample_rate = match version {
+        0x3 => base_rate,
+        0x2 => base_rate / 2,
+        _ => base_rate / 4,
+    };
+    let bitrate = if mpeg1 {
+        BITRATES_V1[layer - 1][bitrate_index]
+    } else {
+        BITRATES_V2[usize::from(layer != 1)][bitrate_index]
+    } * 1000;
+    let padding = ((word >> 9) & 0x1) as usize;
+    let (len, samples) = match layer {
+        1 => ((12 * bitrate / sample_rate) as usize * 4 + padding * 4, 384),
+        2 => ((144 * bitrate / sample_rate) as usize + padding, 1152),
+        _ if mpeg1 => ((144 * bitrate / sample_rate) as usize + padding, 1152),
+        _ => ((72 * bitrate / sample_rate) as usize + padding, 576),
+    };
+    let mono = (word >> 6) & 0x3 == 0x3;
+    let side_info_len = match (mpeg1, mono) {
+        (true, true) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+        (false, false) => 17,
+    };
+    if len <= MPEG_HEADER_LEN {
+        return None;
+    }
+
+    Some(FrameHeader {
+        len,
+        samples,
+        sample_rate,
+        mpeg1,
+        layer3: layer == 3,
+        crc: (word >> 16) & 0x1 == 0,
+        side_info_len,
+    })
+}
+
+/// 解析 ADTS 帧头；与 Symphonia 一样只接受 0xFFF1 同步字、每帧一个 AAC 块。
+fn parse_adts_header(bytes: &[u8]) -> Option<FrameHeader> {
+    if bytes[0] != 0xff || bytes[1] != 0xf1 {
+        return None;
+    }
+    let sample_rate = *ADTS_SAMPLE_RATES.get(usize::from((bytes[2] >> 2) & 0xf))?;
+    let len = (usize::from(bytes[3] & 0x3) << 11)
+        | (usize::from(bytes[4]) << 3)
+        | usize::from(bytes[5] >> 5);
+    if len <= ADTS_HEADER_LEN || bytes[6] & 0x3 != 0 {
+        return None;
+    }
+
+    Some(FrameHeader {
+        len,
+        samples: 1024,
+        sample_rate,
+        mpeg1: false,
+        layer3: false,
+        crc: false,
+        side_info_len: 0,
+    })
+}
+
+/// Xing/Info/VBRI 帧只携带元数据，Symphonia 解复用时直接丢弃，不计入时间戳。
+fn is_info_frame(frame: &[u8], header: &FrameHeader) -> bool {
+    if !header.layer3 {
+        return false;
+    }
+    let offset = MPEG_HEADER_LEN + header.side_info_len;
+    let xing = frame
+        .get(offset..offset + 8)
+        .is_some_and(|tag| &tag[..4] == b"Xing" || &tag[..4] == b"Info");
+    let vbri = frame
+        .get(36..36 + 26)
+        .is_some_and(|tag| &tag[..4] == b"VBRI");
+    xing || vbri
+}
+
+/// 为边下边播的裸 MP3/ADTS 流包一层：有帧索引时 seek 直接跳到索引点，
+/// 不必像 Symphonia 那样从头（或当前位置）经网络逐帧扫描。
+/// 索引只提供起点：在索引点处重建 Symphonia 的 reader，由它完成精确 seek 与解复用。
+pub(crate) struct IndexedSeekReader {
+    /// 重建失败时为空，之后的读取都返回错误。
+    inner: Option<Box<dyn FormatReader>>,
+    kind: FrameStreamKind,
+    index: SharedSeekIndex,
+    /// 原 reader 的轨道参数（时长、编码器延迟等），重建后保持不变。
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    /// `inner` 起点对应的时间戳：重建的 reader 从 0 计数，packet 时间戳要加上它。
+    ts_offset: u64,
+}
+
+/// 编解码器是 MPEG 音频或 AAC 时包上 `IndexedSeekReader`，其余原样返回。
+/// MP4 里的 AAC 也会进来，但索引识别不出 ADTS 帧，seek 时会退回原 reader。
+pub(crate) fn wrap_reader(
+    mut inner: Box<dyn FormatReader>,
+    index: SharedSeekIndex,
+) -> Box<dyn FormatReader> {
+    let Some(track) = inner.tracks().first() else {
+        return inner;
+    };
+    let kind = match track.codec_params.codec {
+        CODEC_TYPE_MP1 | CODEC_TYPE_MP2 | CODEC_TYPE_MP3 => FrameStreamKind::Mp3,
+        CODEC_TYPE_AAC => FrameStreamKind::Adts,
+        _ => return inner,
+    };
+    if inner.tracks().len() != 1 || track.codec_params.sample_rate.is_none() {
+        return inner;
+    }
+
+    // 重建的 reader 不会再带上原 reader 的元数据，先按顺序搬过来。
+    let mut metadata = MetadataLog::default();
+    let mut source = inner.metadata();
+    while let Some(revision) = source.pop() {
+        metadata.push(revision);
+    }
+    if let Some(latest) = source.current() {
+        metadata.push(latest.clone());
+    }
+
+    Box::new(IndexedSeekReader {
+        tracks: inner.tracks().to_vec(),
+        cues: inner.cues().to_vec(),
+        inner: Some(inner),
+        kind,
+        index,
+        metadata,
+        ts_offset: 0,
+    })
+}
+
+/// 在 `byte` 处重新构造 reader；`None` 表示回到文件开头重新探测（跳过 ID3 等前缀）。
+fn reopen(
+    mut stream: MediaSourceStream,
+    kind: FrameStreamKind,
+    byte: Option<u64>,
+) -> Result<Box<dyn FormatReader>> {
+    let options = FormatOptions::default();
+    stream.seek(SeekFrom::Start(byte.unwrap_or(0)))?;
+    match (byte, kind) {
+        (Some(_), FrameStreamKind::Mp3) => Ok(Box::new(MpaReader::try_new(stream, &options)?)),
+        (Some(_), FrameStreamKind::Adts) => Ok(Box::new(AdtsReader::try_new(stream, &options)?)),
+        (None, _) => {
+            let mut hint = Hint::new();
+            hint.with_extension(match kind {
+                FrameStreamKind::Mp3 => "mp3",
+                FrameStreamKind::Adts => "aac",
+            });
+            registry::open_format(stream, &hint, None)
+        }
+    }
+}
+
+fn reader_lost() -> Error {
+    Error::IoError(io::Error::other("indexed seek reader lost its stream"))
+}
+
+impl IndexedSeekReader {
+    /// 把 `inner` 换成从 `byte` 开始的新 reader，`ts_offset` 是该位置的时间戳。
+    fn rebuild(&mut self, byte: Option<u64>, ts_offset: u64) -> Result<&mut Box<dyn FormatReader>> {
+        let stream = self.inner.take().ok_or_else(reader_lost)?.into_inner();
+        let reader = reopen(stream, self.kind, byte)?;
+        self.ts_offset = ts_offset;
+        Ok(self.inner.insert(reader))
+    }
+}
+
+impl FormatReader for IndexedSeekReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        unsupported_error("indexed seek reader must wrap an existing reader")
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let track_id = self.tracks[0].id;
+        let Some(sample_rate) = self.tracks[0].codec_params.sample_rate else {
+            return seek_error(SeekErrorKind::Unseekable);
+        };
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => TimeBase::new(1, sample_rate).calc_timestamp(time),
+        };
+        // 留出 bit reservoir 参考帧的余量，Symphonia 会从索引点向前解析到目标帧。
+        let margin = MAX_REF_FRAMES as u64 * 1152;
+        let point = {
+            let index = self.index.lock().unwrap();
+            if index.kind == Some(self.kind) && index.sample_rate == sample_rate {
+                index.point_before(required_ts.saturating_sub(margin))
+            } else {
+                None
+            }
+        };
+
+        let (reader, ts_offset) = match point {
+            Some(point) => (self.rebuild(Some(point.byte), point.ts)?, point.ts),
+            // 没有索引可用：当前 reader 起点不晚于目标时交给它，否则回到文件开头。
+            None if required_ts >= self.ts_offset => {
+                let ts_offset = self.ts_offset;
+                (self.inner.as_mut().ok_or_else(reader_lost)?, ts_offset)
+            }
+            None => (self.rebuild(None, 0)?, 0),
+        };
+        let seeked = reader.seek(
+            mode,
+            SeekTo::TimeStamp {
+                ts: required_ts - ts_offset,
+                track_id,
+            },
+        )?;
+        Ok(SeekedTo {
+            track_id,
+            required_ts,
+            actual_ts: seeked.actual_ts + ts_offset,
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let mut packet = self.inner.as_mut().ok_or_else(reader_lost)?.next_packet()?;
+        packet.ts += self.ts_offset;
+        Ok(packet)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        match self.inner {
+            Some(inner) => inner.into_inner(),
+            None => {
+                MediaSourceStream::new(Box::new(io::Cursor::new(Vec::new())), Default::default())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// MPEG-1 Layer III 128kbps/44.1kHz 帧头，`padding` 决定帧长 417 或 418。
+    fn mp3_frame(padding: bool, fill: u8) -> Vec<u8> {
+        let word: u32 = 0xfffb_9000 | if padding { 0x200 } else { 0 } | 0xc0;
+        let header = parse_mpeg_header(word).unwrap();
+        let mut frame = vec![fill; header.len];
+        frame[..4].copy_from_slice(&word.to_be_bytes());
+        // main_data_begin = 0，帧内只有静音数据。
+        frame[4..4 + header.side_info_len].fill(0);
+        frame
+    }
+
+    fn adts_frame(payload: usize) -> Vec<u8> {
+        let len = ADTS_HEADER_LEN + payload;
+        let mut frame = vec![
+            0xff,
+            0xf1,
+            0x50, // AAC LC, 44.1kHz
+            0x80 | ((len >> 11) & 0x3) as u8,
+            ((len >> 3) & 0xff) as u8,
+            (((len & 0x7) << 5) as u8) | 0x1f,
+            0xfc,
+        ];
+        frame.resize(len, 0);
+        frame
+    }
+
+    fn mp3_stream(frames: usize) -> (Vec<u8>, Vec<u64>) {
+        let mut data = b"ID3\x04\x00\x00\x00\x00\x00\x10".to_vec();
+        data.extend(std::iter::repeat_n(0u8, 16));
+        let mut offsets = Vec::new();
+        for frame in 0..frames {
+            offsets.push(data.len() as u64);
+            data.extend(mp3_frame(frame % 3 == 0, 0));
+        }
+        (data, offsets)
+    }
+
+    #[test]
+    fn scans_mp3_frames_incrementally_and_skips_id3() {
+        let (data, offsets) = mp3_stream(40);
+        let mut index = FrameSeekIndex::new(Some(data.len() as u64));
+        for chunk_start in (0..data.len()).step_by(1000) {
+            let end = (chunk_start + 1000).min(data.len());
+            let start = index.scanned_bytes as usize;
+            index.scan(&data[start..end], start as u64);
+        }
+
+        assert_eq!(index.kind, Some(FrameStreamKind::Mp3));
+        assert_eq!(index.sample_rate, 44100);
+        assert_eq!(index.first_frame_byte, 26);
+        assert_eq!(index.scanned_frames, 40);
+        assert_eq!(index.scanned_bytes, data.len() as u64);
+        assert_eq!(
+            index.points,
+            vec![
+                SeekPoint {
+                    ts: 0,
+                    byte: offsets[0]
+                },
+                SeekPoint {
+                    ts: 16 * 1152,
+                    byte: offsets[16]
+                },
+                SeekPoint {
+                    ts: 32 * 1152,
+                    byte: offsets[32]
+                },
+            ]
+        );
+        assert_eq!(index.point_before(20 * 1152).unwrap().byte, offsets[16]);
+        assert_eq!(
+            index.point_before(99 * 1152).unwrap().byte,
+            data.len() as u64
+        );
+        let byte = index.byte_for_time(1000).unwrap();
+        assert_eq!(byte, offsets[32]);
+    }
+
+    #[test]
+    fn stream_start_skips_tags_and_flags_mp3_without_toc() {
+        let (data, offsets) = mp3_stream(8);
+        assert_eq!(
+            read_stream_start(&mut Cursor::new(&data)),
+            StreamStart {
+                audio_offset: offsets[0],
+                mp3_without_toc: true,
+            }
+        );
+
+        let mut xing = mp3_frame(false, 0);
+        let header = parse_mpeg_header(be_u32(&xing)).unwrap();
+        let tag = MPEG_HEADER_LEN + header.side_info_len;
+        xing[tag..tag + 4].copy_from_slice(b"Xing");
+        let mut with_toc = data[..offsets[0] as usize].to_vec();
+        with_toc.extend(xing);
+        with_toc.extend_from_slice(&data[offsets[0] as usize..]);
+        let start = read_stream_start(&mut Cursor::new(&with_toc));
+        assert_eq!(start.audio_offset, offsets[0]);
+        assert!(!start.mp3_without_toc);
+
+        let mut flac = b"ID3\x04\x00\x00\x00\x00\x00\x04\x00\x00\x00\x00".to_vec();
+        flac.extend_from_slice(b"fLaC\x00\x00\x00\x22");
+        flac.resize(64, 0);
+        assert_eq!(
+            read_stream_start(&mut Cursor::new(&flac)),
+            StreamStart {
+                audio_offset: 14,
+                mp3_without_toc: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn probe_marks_cbr_estimated_mp3_length_and_skips_tags() {
+        let (data, offsets) = mp3_stream(40);
+        let meta = crate::audio::decoder::spawn_probe_task(
+            Box::new(Cursor::new(data)),
+            Some("mp3".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(meta.n_frames.is_some());
+        assert!(meta.n_frames_estimated);
+        assert_eq!(meta.audio_offset, offsets[0]);
+    }
+
+    #[test]
+    fn scans_adts_frames_and_rejects_containers() {
+        let mut data = Vec::new();
+        for frame in 0..20 {
+            data.extend(adts_frame(100 + frame));
+        }
+        let mut index = FrameSeekIndex::new(None);
+        index.scan(&data, 0);
+        assert_eq!(index.kind, Some(FrameStreamKind::Adts));
+        assert_eq!(index.scanned_ts, 20 * 1024);
+        assert_eq!(
+            index.points[1],
+            SeekPoint {
+                ts: 16 * 1024,
+                byte: (0..16).map(|n| 107 + n).sum()
+            }
+        );
+
+        let mut mp4 = FrameSeekIndex::new(None);
+        mp4.scan(b"\x00\x00\x00\x20ftypM4A \x00\x00\x00\x00", 0);
+        assert!(mp4.unsupported);
+        assert_eq!(mp4.point_before(0), None);
+    }
+
+    #[test]
+    fn index_round_trips_next_to_metadata() {
+        let dir = std::env::temp_dir().join(format!("seek_index_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = index_path_for_metadata(&dir.join("song.mp3.meta.json"));
+        assert_eq!(path, dir.join("song.mp3.seek.json"));
+
+        let (data, _) = mp3_stream(20);
+        let mut index = FrameSeekIndex::new(Some(data.len() as u64));
+        index.scan(&data, 0);
+        index.save(&path).unwrap();
+
+        assert_eq!(FrameSeekIndex::load(&path, Some(data.len() as u64)), index);
+        assert_eq!(
+            FrameSeekIndex::load(&path, Some(1)),
+            FrameSeekIndex::new(Some(1))
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn indexed_reader_seeks_to_the_same_packets_as_symphonia() {
+        let (data, _) = mp3_stream(60);
+        let mut index = FrameSeekIndex::new(Some(data.len() as u64));
+        index.scan(&data, 0);
+        let index = Arc::new(Mutex::new(index));
+
+        let open = || {
+            let mss =
+                MediaSourceStream::new(Box::new(Cursor::new(data.clone())), Default::default());
+            let mut hint = Hint::new();
+            hint.with_extension("mp3");
+            registry::open_format(mss, &hint, None).unwrap()
+        };
+        let mut reference = open();
+        let mut indexed = wrap_reader(open(), Arc::clone(&index));
+
+        let to = || SeekTo::TimeStamp {
+            ts: 37 * 1152 + 10,
+            track_id: 0,
+        };
+        let expected = reference.seek(SeekMode::Accurate, to()).unwrap();
+        let seeked = indexed.seek(SeekMode::Accurate, to()).unwrap();
+        assert_eq!(seeked.required_ts, expected.required_ts);
+        assert_eq!(seeked.actual_ts, 37 * 1152);
+
+        for _ in 0..5 {
+            let packet = indexed.next_packet().unwrap();
+            let mut want = reference.next_packet().unwrap();
+            while want.ts < packet.ts {
+                want = reference.next_packet().unwrap();
+            }
+            assert_eq!((packet.ts, packet.dur), (want.ts, want.dur));
+            assert_eq!(packet.data, want.data);
+        }
+    }
+
+    #[test]
+    fn indexed_reader_falls_back_to_symphonia_without_an_index() {
+        let (data, _) = mp3_stream(60);
+        let mut index = FrameSeekIndex::new(Some(data.len() as u64));
+        index.scan(&data, 0);
+        let index = Arc::new(Mutex::new(index));
+
+        let open = || {
+            let mss =
+                MediaSourceStream::new(Box::new(Cursor::new(data.clone())), Default::default());
+            let mut hint = Hint::new();
+            hint.with_extension("mp3");
+            registry::open_format(mss, &hint, None).unwrap()
+        };
+        let mut reference = open();
+        let mut indexed = wrap_reader(open(), Arc::clone(&index));
+        let to = |ts| SeekTo::TimeStamp { ts, track_id: 0 };
+
+        indexed.seek(SeekMode::Accurate, to(40 * 1152)).unwrap();
+        // 索引失效后向回 seek：重建的 reader 起点在目标之后，只能从文件开头重新打开。
+        *index.lock().unwrap() = FrameSeekIndex::new(None);
+        let seeked = indexed.seek(SeekMode::Accurate, to(2 * 1152)).unwrap();
+        let expected = reference.seek(SeekMode::Accurate, to(2 * 1152)).unwrap();
+        assert_eq!(seeked.actual_ts, expected.actual_ts);
+
+        for _ in 0..5 {
+            let packet = indexed.next_packet().unwrap();
+            let want = reference.next_packet().unwrap();
+            assert_eq!((packet.ts, packet.dur), (want.ts, want.dur));
+            assert_eq!(packet.data, want.data);
+        }
+        assert!(indexed.metadata().current().is_none());
+    }
+}
@@ -0,0 +1,216 @@
+//! 静音检测：修剪曲目首尾的静音，以及播客式“跳过静音”模式下压缩曲目中间的长停顿。
+//! 跳过发生在输出端：直接丢弃 ring buffer 开头的静音帧，`current_frame` 照常按丢弃的
+//! 帧数推进，进度始终对应音源时间轴。
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SilenceOptions {
+    /// 修剪曲目开头与结尾的静音，让切歌更紧凑。
+    pub(crate) trim_edges: bool,
+    /// 跳过曲目中间的长静音，只保留 `min_silence` 长的停顿。
+    pub(crate) skip_gaps: bool,
+    /// 所有声道的峰值都不超过该电平（dBFS）时视为静音。
+    pub(crate) threshold_db: f64,
+    /// 静音至少持续这么久才处理，更短的停顿原样播放。
+    pub(crate) min_silence: Duration,
+}
+
+impl Default for SilenceOptions {
+    fn default() -> Self {
+        Self {
+            trim_edges: false,
+            skip_gaps: false,
+            threshold_db: -60.0,
+            min_silence: Duration::from_millis(500),
+        }
+    }
+}
+
+/// 每次播放共享的静音跳过状态，输出回调里只读写原子量。
+#[derive(Debug, Default)]
+pub(crate) struct SilenceSkipper {
+    trim_edges: AtomicBool,
+    skip_gaps: AtomicBool,
+    /// 线性幅度阈值，f32 的位模式。
+    threshold: AtomicU32,
+    min_frames: AtomicU64,
+    /// 当前这段静音已经播放的帧数。
+    run_frames: AtomicU64,
+    /// 从曲目起点播放、还没出过声。
+    at_track_start: AtomicBool,
+    /// ring buffer 开头已扫描确认是静音的帧数。跨回调保留，每帧只扫描一次。
+    scanned_frames: AtomicU64,
+}
+
+impl SilenceSkipper {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn configure(&self, options: SilenceOptions, sample_rate: u32) {
+        let threshold = 10f64.powf(options.threshold_db / 20.0) as f32;
+        let min_frames = (options.min_silence.as_secs_f64() * f64::from(sample_rate)) as u64;
+        self.threshold.store(threshold.to_bits(), Ordering::Relaxed);
+        self.min_frames.store(min_frames, Ordering::Relaxed);
+        self.trim_edges.store(options.trim_edges, Ordering::Relaxed);
+        self.skip_gaps.store(options.skip_gaps, Ordering::Relaxed);
+        self.reset_scan();
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.trim_edges.load(Ordering::Relaxed) || self.skip_gaps.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn threshold(&self) -> f32 {
+        f32::from_bits(self.threshold.load(Ordering::Relaxed))
+    }
+
+    /// 从曲目（或 CUE 音轨）起点开始播放。
+    pub(crate) fn start_track(&self) {
+        self.run_frames.store(0, Ordering::Relaxed);
+        self.at_track_start.store(true, Ordering::Relaxed);
+        self.reset_scan();
+    }
+
+    /// seek 之后原来那段静音不再连续。
+    pub(crate) fn reset_run(&self) {
+        self.run_frames.store(0, Ordering::Relaxed);
+        self.at_track_start.store(false, Ordering::Relaxed);
+        self.reset_scan();
+    }
+
+    /// ring buffer 开头已确认是静音的帧数，下次扫描从这里接着往后看。
+    pub(crate) fn scanned_frames(&self) -> u64 {
+        self.scanned_frames.load(Ordering::Relaxed)
+    }
+
+    /// 扫描又确认了 `frames` 帧静音。
+    pub(crate) fn extend_scan(&self, frames: u64) {
+        self.scanned_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// 输出端从 ring buffer 开头取走（播放或丢弃）了 `frames` 帧。
+    pub(crate) fn consume(&self, frames: u64) {
+        let _ = self
+            .scanned_frames
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |scanned| {
+                Some(scanned.saturating_sub(frames))
+            });
+    }
+
+    /// ring buffer 被清空或阈值变了，之前的扫描结果作废。少算只会多扫一遍，
+    /// 所以任何线程都可以随时调用。
+    pub(crate) fn reset_scan(&self) {
+        self.scanned_frames.store(0, Ordering::Relaxed);
+    }
+
+    /// 输出端开头有 `silent_frames` 帧连续静音时应丢弃多少帧。
+    /// `reaches_end` 表示这段静音一直持续到曲目或音轨终点。
+    pub(crate) fn frames_to_skip(&self, silent_frames: u64, reaches_end: bool) -> u64 {
+        let run_frames = self.run_frames.load(Ordering::Relaxed);
+        let min_frames = self.min_frames.load(Ordering::Relaxed);
+        if silent_frames == 0 || run_frames.saturating_add(silent_frames) < min_frames {
+            return 0;
+        }
+        let at_edge = reaches_end || self.at_track_start.load(Ordering::Relaxed);
+        if self.trim_edges.load(Ordering::Relaxed) && at_edge {
+            silent_frames
+        } else if self.skip_gaps.load(Ordering::Relaxed) {
+            silent_frames.saturating_sub(min_frames.saturating_sub(run_frames))
+        } else {
+            0
+        }
+    }
+
+    /// 输出了 `frames` 帧，其中末尾 `trailing_silent_frames` 帧是静音。
+    pub(crate) fn record_played(&self, frames: u64, trailing_silent_frames: u64) {
+        if frames == 0 {
+            return;
+        }
+        if trailing_silent_frames >= frames {
+            self.run_frames.fetch_add(frames, Ordering::Relaxed);
+        } else {
+            self.run_frames
+                .store(trailing_silent_frames, Ordering::Relaxed);
+            self.at_track_start.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 一帧交错样本是否都不超过阈值。
+pub(crate) fn is_silent_frame(frame: impl IntoIterator<Item = f32>, threshold: f32) -> bool {
+    frame.into_iter().all(|sample| sample.abs() <= threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skipper(options: SilenceOptions) -> SilenceSkipper {
+        let skipper = SilenceSkipper::new();
+        skipper.configure(options, 1000);
+        skipper
+    }
+
+    #[test]
+    fn edges_are_trimmed_once_silence_is_long_enough() {
+        let skipper = skipper(SilenceOptions {
+            trim_edges: true,
+            ..SilenceOptions::default()
+        });
+        skipper.start_track();
+        assert_eq!(skipper.frames_to_skip(400, false), 0);
+        assert_eq!(skipper.frames_to_skip(800, false), 800);
+
+        // 出声以后，中间的静音不修剪，结尾的静音整段丢掉。
+        skipper.record_played(100, 0);
+        assert_eq!(skipper.frames_to_skip(800, false), 0);
+        assert_eq!(skipper.frames_to_skip(800, true), 800);
+        assert_eq!(skipper.frames_to_skip(300, true), 0);
+    }
+
+    #[test]
+    fn gaps_are_shortened_to_the_minimum_length() {
+        let skipper = skipper(SilenceOptions {
+            skip_gaps: true,
+            ..SilenceOptions::default()
+        });
+        skipper.record_played(100, 0);
+        assert_eq!(skipper.frames_to_skip(2000, false), 1500);
+
+        // 已经播放了一部分静音，只需再保留剩下的部分。
+        skipper.record_played(100, 40);
+        skipper.record_played(200, 200);
+        assert_eq!(skipper.run_frames.load(Ordering::Relaxed), 240);
+        assert_eq!(skipper.frames_to_skip(1000, false), 740);
+
+        skipper.reset_run();
+        assert_eq!(skipper.frames_to_skip(300, false), 0);
+    }
+
+    #[test]
+    fn scan_position_follows_consumed_frames() {
+        let skipper = skipper(SilenceOptions::default());
+        skipper.extend_scan(300);
+        skipper.consume(120);
+        assert_eq!(skipper.scanned_frames(), 180);
+        skipper.extend_scan(20);
+        skipper.consume(500);
+        assert_eq!(skipper.scanned_frames(), 0);
+
+        skipper.extend_scan(50);
+        skipper.reset_run();
+        assert_eq!(skipper.scanned_frames(), 0);
+    }
+
+    #[test]
+    fn threshold_is_applied_to_every_channel() {
+        let skipper = skipper(SilenceOptions::default());
+        let threshold = skipper.threshold();
+        assert!((threshold - 0.001).abs() < 1e-6);
+        assert!(is_silent_frame([0.0005, -0.0009], threshold));
+        assert!(!is_silent_frame([0.0005, -0.01], threshold));
+    }
+}
@@ -2,19 +2,25 @@ use std::fs::{self, File, OpenOptions};
 use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
 use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use stream_download::storage::StorageProvider;
 use symphonia::core::io::MediaSource;
 
+use crate::audio::pipeline_stats::{NETWORK_STALL_THRESHOLD, PipelineStats};
+
 /// 逃逸预算下限：保证足够写完 in-flight chunk（hyper 默认读缓冲上限约 512KB），
 /// 让下载任务能回到事件循环处理 seek 消息。
 const MIN_ESCAPE_BUDGET_BYTES: u64 = 1024 * 1024;
+/// 切换到本地缓存失败后，继续从网络流读这么多字节再重试一次。
+const HANDOFF_RETRY_BYTES: u64 = 64 * 1024;
 
 pub struct SeekableSource<R> {
     inner: R,
     len: Option<u64>,
     storage_state: Option<SharedStorageState>,
+    stall_monitor: Option<Arc<PipelineStats>>,
 }
 
 impl<R: Read + Seek + Send + Sync> SeekableSource<R> {
@@ -23,6 +29,7 @@ impl<R: Read + Seek + Send + Sync> SeekableSource<R> {
             inner,
             len,
             storage_state: None,
+            stall_monitor: None,
         }
     }
 
@@ -30,10 +37,14 @@ impl<R: Read + Seek + Send + Sync> SeekableSource<R> {
         self.storage_state = Some(storage_state);
         self
     }
-}
 
-impl<R: Read + Seek + Send + Sync> Read for SeekableSource<R> {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    /// 读取阻塞超过 `NETWORK_STALL_THRESHOLD`（等下载、空洞回填、临时 EOF 重试）时记一次网络卡顿。
+    pub(crate) fn with_stall_monitor(mut self, stats: Arc<PipelineStats>) -> Self {
+        self.stall_monitor = Some(stats);
+        self
+    }
+
+    fn read_inner(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         if buf.is_empty() {
             return self.inner.read(buf);
         }
@@ -102,6 +113,21 @@ impl<R: Read + Seek + Send + Sync> Read for SeekableSource<R> {
     }
 }
 
+impl<R: Read + Seek + Send + Sync> Read for SeekableSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(stats) = self.stall_monitor.clone() else {
+            return self.read_inner(buf);
+        };
+        let started_at = Instant::now();
+        let result = self.read_inner(buf);
+        let elapsed = started_at.elapsed();
+        if elapsed >= NETWORK_STALL_THRESHOLD {
+            stats.record_network_stall(elapsed);
+        }
+        result
+    }
+}
+
 impl<R: Read + Seek + Send + Sync> Seek for SeekableSource<R> {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
         // SeekFrom::Current(0) 只是位置查询，不触发任何下载协调。
@@ -132,6 +158,137 @@ impl<R: Read + Seek + Send + Sync> MediaSource for SeekableSource<R> {
     }
 }
 
+type CacheCompleteCheck = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// 流式缓存下载完成后，把 format reader 底下的数据源从 StreamDownload
+/// 无缝切换到本地缓存文件（同一字节偏移），随后 drop 掉 HTTP 流。
+/// 切换之后 seek 不再经过下载任务的节流协调，连接也随之释放。
+/// 只在 `generation` 变化后才查询是否下载完成，读/seek 本身不碰下载端的锁；
+/// 下载完成后代数不再变化，切换失败时改为按读取的字节数定期重试。
+pub struct CacheHandoffSource<R> {
+    stream: Option<SeekableSource<R>>,
+    file: Option<File>,
+    cache_path: PathBuf,
+    len: Option<u64>,
+    generation: Arc<AtomicU64>,
+    checked_generation: Option<u64>,
+    /// 上次切换失败后，距离下次重试还要从网络流读取的字节数。
+    retry_in_bytes: Option<u64>,
+    is_cache_complete: CacheCompleteCheck,
+}
+
+impl<R: Read + Seek + Send + Sync> CacheHandoffSource<R> {
+    pub fn new<F>(
+        stream: SeekableSource<R>,
+        cache_path: impl Into<PathBuf>,
+        generation: Arc<AtomicU64>,
+        is_cache_complete: F,
+    ) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        let len = stream.len;
+        Self {
+            stream: Some(stream),
+            file: None,
+            cache_path: cache_path.into(),
+            len,
+            generation,
+            checked_generation: None,
+            retry_in_bytes: None,
+            is_cache_complete: Box::new(is_cache_complete),
+        }
+    }
+
+    fn maybe_handoff(&mut self) {
+        if self.file.is_some() {
+            return;
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        if self.checked_generation == Some(generation) && self.retry_in_bytes != Some(0) {
+            return;
+        }
+        self.checked_generation = Some(generation);
+        self.retry_in_bytes = None;
+        if !(self.is_cache_complete)() {
+            return;
+        }
+        let Some(stream) = self.stream.as_mut() else {
+            return;
+        };
+        match open_cache_file_at(&self.cache_path, self.len, stream) {
+            Ok(Some(file)) => {
+                println!(
+                    "[audio] 缓存下载完成，切换到本地文件: {}",
+                    self.cache_path.display()
+                );
+                self.file = Some(file);
+                self.stream = None;
+            }
+            Ok(None) => self.retry_in_bytes = Some(HANDOFF_RETRY_BYTES),
+            Err(err) => {
+                // 切换失败不影响播放：继续走流式读取，读够重试字节数后再试。
+                eprintln!("[audio] 切换到本地缓存文件失败，继续使用网络流: {err}");
+                self.retry_in_bytes = Some(HANDOFF_RETRY_BYTES);
+            }
+        }
+    }
+}
+
+/// 打开缓存文件并定位到流当前的读位置；文件长度不足时返回 `None`。
+fn open_cache_file_at<R: Read + Seek + Send + Sync>(
+    cache_path: &std::path::Path,
+    len: Option<u64>,
+    stream: &mut SeekableSource<R>,
+) -> io::Result<Option<File>> {
+    let mut file = File::open(cache_path)?;
+    if let Some(len) = len
+        && file.metadata()?.len() < len
+    {
+        return Ok(None);
+    }
+    let position = stream.stream_position()?;
+    file.seek(SeekFrom::Start(position))?;
+    Ok(Some(file))
+}
+
+impl<R: Read + Seek + Send + Sync> Read for CacheHandoffSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.maybe_handoff();
+        match (self.file.as_mut(), self.stream.as_mut()) {
+            (Some(file), _) => file.read(buf),
+            (None, Some(stream)) => {
+                let read = stream.read(buf)?;
+                if let Some(remaining) = self.retry_in_bytes.as_mut() {
+                    *remaining = remaining.saturating_sub(read as u64);
+                }
+                Ok(read)
+            }
+            (None, None) => Ok(0),
+        }
+    }
+}
+
+impl<R: Read + Seek + Send + Sync> Seek for CacheHandoffSource<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.maybe_handoff();
+        match (self.file.as_mut(), self.stream.as_mut()) {
+            (Some(file), _) => file.seek(pos),
+            (None, Some(stream)) => stream.seek(pos),
+            (None, None) => Err(io::Error::other("cache handoff source has no reader")),
+        }
+    }
+}
+
+impl<R: Read + Seek + Send + Sync> MediaSource for CacheHandoffSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+    fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
 fn read_with_temporary_eof_retry<R: Read + Seek>(
     inner: &mut R,
     buf: &mut [u8],
@@ -472,6 +629,102 @@ mod tests {
     use std::thread;
     use std::time::Duration;
 
+    #[test]
+    fn cache_handoff_switches_to_local_file_at_current_offset() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let path = std::env::temp_dir().join(format!(
+            "stream-cache-handoff-{}-{}.bin",
+            std::process::id(),
+            crate::cache::types::now_unix_secs()
+        ));
+        std::fs::write(&path, b"abcdefghij").unwrap();
+        let complete = Arc::new(AtomicBool::new(false));
+        let complete_for_check = Arc::clone(&complete);
+        let checks = Arc::new(AtomicU64::new(0));
+        let checks_for_check = Arc::clone(&checks);
+        let generation = Arc::new(AtomicU64::new(0));
+        let stream = SeekableSource::new(std::io::Cursor::new(b"ABCDEFGHIJ".to_vec()), Some(10));
+        let mut source =
+            CacheHandoffSource::new(stream, &path, Arc::clone(&generation), move || {
+                checks_for_check.fetch_add(1, Ordering::SeqCst);
+                complete_for_check.load(Ordering::SeqCst)
+            });
+
+        let mut buf = [0u8; 4];
+        source.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ABCD");
+        assert!(source.file.is_none());
+
+        // 代数没变时不再查询下载状态。
+        complete.store(true, Ordering::SeqCst);
+        source.read_exact(&mut buf[..1]).unwrap();
+        assert_eq!(&buf[..1], b"E");
+        assert_eq!(checks.load(Ordering::SeqCst), 1);
+        source.seek(SeekFrom::Start(4)).unwrap();
+
+        generation.fetch_add(1, Ordering::SeqCst);
+        source.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"efgh", "must continue from the same byte offset");
+        assert!(source.file.is_some());
+        assert!(source.stream.is_none(), "network stream must be dropped");
+
+        source.seek(SeekFrom::Start(1)).unwrap();
+        source.read_exact(&mut buf[..2]).unwrap();
+        assert_eq!(&buf[..2], b"bc");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn cache_handoff_keeps_streaming_when_local_file_is_short() {
+        let path = std::env::temp_dir().join(format!(
+            "stream-cache-handoff-short-{}-{}.bin",
+            std::process::id(),
+            crate::cache::types::now_unix_secs()
+        ));
+        std::fs::write(&path, b"abc").unwrap();
+        let stream = SeekableSource::new(std::io::Cursor::new(b"ABCDEFGHIJ".to_vec()), Some(10));
+        let generation = Arc::new(AtomicU64::new(0));
+        let mut source = CacheHandoffSource::new(stream, &path, generation, || true);
+
+        let mut buf = [0u8; 4];
+        source.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ABCD");
+        assert!(source.file.is_none());
+        assert!(source.stream.is_some());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn cache_handoff_retries_after_a_failed_switch_without_a_new_generation() {
+        let path = std::env::temp_dir().join(format!(
+            "stream-cache-handoff-retry-{}-{}.bin",
+            std::process::id(),
+            crate::cache::types::now_unix_secs()
+        ));
+        let len = 2 * HANDOFF_RETRY_BYTES as usize;
+        let remote: Vec<u8> = (0..len).map(|index| (index % 251) as u8).collect();
+        let local: Vec<u8> = remote.iter().map(|byte| byte ^ 0xff).collect();
+        std::fs::write(&path, &local[..10]).unwrap();
+        let stream = SeekableSource::new(std::io::Cursor::new(remote.clone()), Some(len as u64));
+        let generation = Arc::new(AtomicU64::new(0));
+        let mut source = CacheHandoffSource::new(stream, &path, generation, || true);
+
+        let mut buf = vec![0u8; HANDOFF_RETRY_BYTES as usize - 1];
+        source.read_exact(&mut buf).unwrap();
+        assert!(source.file.is_none(), "short cache file must not be used");
+
+        // 缓存文件补齐后代数不再变化，读够重试字节数即再次尝试切换。
+        std::fs::write(&path, &local).unwrap();
+        source.read_exact(&mut buf[..1]).unwrap();
+        assert!(source.file.is_none());
+        source.read_exact(&mut buf[..4]).unwrap();
+        let offset = HANDOFF_RETRY_BYTES as usize;
+        assert_eq!(buf[..4], local[offset..offset + 4]);
+        assert!(source.stream.is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
     #[test]
     fn ranges_cover_and_insert_merge_adjacent_intervals() {
         let mut ranges = Vec::new();
@@ -581,6 +834,45 @@ mod tests {
         assert_eq!(source.read(&mut bytes).unwrap(), 0);
     }
 
+    #[test]
+    fn slow_reads_are_reported_as_network_stalls() {
+        struct SlowFirstReader {
+            cursor: io::Cursor<Vec<u8>>,
+            stalled: bool,
+        }
+
+        impl Read for SlowFirstReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if !self.stalled {
+                    self.stalled = true;
+                    thread::sleep(NETWORK_STALL_THRESHOLD + Duration::from_millis(20));
+                }
+                self.cursor.read(buf)
+            }
+        }
+
+        impl Seek for SlowFirstReader {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.cursor.seek(pos)
+            }
+        }
+
+        let stats = Arc::new(PipelineStats::new());
+        let reader = SlowFirstReader {
+            cursor: io::Cursor::new(b"abcdef".to_vec()),
+            stalled: false,
+        };
+        let mut source =
+            SeekableSource::new(reader, Some(6)).with_stall_monitor(Arc::clone(&stats));
+        let mut bytes = [0u8; 3];
+        source.read_exact(&mut bytes).unwrap();
+        source.read_exact(&mut bytes).unwrap();
+
+        let stalls = stats.snapshot().network_stalls;
+        assert_eq!(stalls.count, 1);
+        assert!(stalls.last_us >= NETWORK_STALL_THRESHOLD.as_micros() as u64);
+    }
+
     #[test]
     fn writer_seek_moves_throttle_anchor_to_avoid_forward_seek_deadlock() {
         let path = std::env::temp_dir().join(format!(
@@ -1,9 +1,48 @@
-use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::Notify;
 
+use crate::audio::bit_perfect::SampleVerifier;
+use crate::audio::hrtf::BinauralRenderer;
+use crate::audio::pipeline_stats::PipelineStats;
+use crate::audio::silence::SilenceSkipper;
+use crate::audio::thread_priority::ThreadPriority;
+
 pub(crate) const NO_TRIM_FRAME: u64 = u64::MAX;
+pub(crate) const NO_RANGE_END_FRAME: u64 = u64::MAX;
+pub(crate) const UNKNOWN_TOTAL_FRAMES: u64 = u64::MAX;
+/// 输出回调停止且后端持续报错超过该时长，视为设备已失效。
+const OUTPUT_STALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 一次拖动预览：结束时据此恢复暂停状态，或在取消时回到原位置。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ScrubSession {
+    pub(crate) was_paused: bool,
+    pub(crate) origin: Duration,
+    pub(crate) target: Option<Duration>,
+}
+
+/// 流的总帧数：容器给出的准确值，或按已解码数据的平均码率估算的值。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TotalFrames {
+    Exact(u64),
+    Estimated(u64),
+}
+
+/// 输出流错误回调上报的情况。ALSA 设备被拔出时只会不断报后端错误，
+/// 需结合回调是否还在推进来判断。
+#[derive(Debug, Default)]
+struct OutputHealth {
+    /// 设备已断开或流已失效，只能重建。
+    fatal: Option<String>,
+    last_error: Option<String>,
+    errors: u64,
+    /// 上次看到输出回调推进时的回调计数、错误计数和时间。
+    watched_callbacks: u64,
+    watched_errors: u64,
+    watched_at: Option<Instant>,
+}
 
 #[derive(Debug)]
 pub(crate) struct PlaybackClock {
@@ -11,6 +50,8 @@ pub(crate) struct PlaybackClock {
     submitted_frame: u64,
     sample_rate: u32,
     updated_at: Option<Instant>,
+    /// 解码侧 DSP（HRTF 卷积）让直达声晚到的帧数，跨 seek 保留。
+    dsp_latency_frames: u64,
 }
 
 impl PlaybackClock {
@@ -20,6 +61,7 @@ impl PlaybackClock {
             submitted_frame: 0,
             sample_rate: 0,
             updated_at: None,
+            dsp_latency_frames: 0,
         }
     }
 
@@ -37,7 +79,8 @@ impl PlaybackClock {
         sample_rate: u32,
         updated_at: Instant,
     ) {
-        self.audible_frame_at_update = audible_frame_at_update;
+        self.audible_frame_at_update =
+            audible_frame_at_update.saturating_sub(self.dsp_latency_frames);
         self.submitted_frame = submitted_frame;
         self.sample_rate = sample_rate;
         self.updated_at = Some(updated_at);
@@ -75,9 +118,290 @@ pub(crate) struct SharedState {
     pub(crate) finish_notify: Notify,
     pub(crate) buffered_frames: AtomicU64,
     pub(crate) waiting_for_seek: AtomicBool,
+    pub(crate) realtime_scheduling: AtomicBool,
+    pub(crate) decode_thread_priority: Mutex<Option<ThreadPriority>>,
+    pub(crate) output_thread_priority: Mutex<Option<ThreadPriority>>,
+    /// 只播放文件中的一段（CUE 音轨）时的起止帧；进度相对起点计算。
+    pub(crate) range_start_frame: AtomicU64,
+    pub(crate) range_end_frame: AtomicU64,
+    /// 排队的相邻区间终点：输出到达当前终点后直接续上，不经过 finish。
+    pub(crate) has_next_range: AtomicBool,
+    pub(crate) next_range_end_frame: AtomicU64,
+    pub(crate) range_transitions: AtomicU64,
+    /// 切到下一区间后置位，直到某个 `wait_finished` 取走；`notify_waiters` 不会留给之后才注册的等待者。
+    range_transition_pending: AtomicBool,
+    /// 容器给出的总帧数，缺失时为 `UNKNOWN_TOTAL_FRAMES`。
+    pub(crate) total_frames: AtomicU64,
+    /// 音源中音频数据的字节数（HTTP content_length 或文件大小减去开头的标签），未知为 0。
+    pub(crate) source_bytes: AtomicU64,
+    /// 已解码 packet 的字节数与帧数，用于估算码率。
+    pub(crate) decoded_packet_bytes: AtomicU64,
+    pub(crate) decoded_packet_frames: AtomicU64,
+    /// 拖动预览中：解码线程只按最新位置粗略 seek 并播放短片段。
+    pub(crate) scrubbing: AtomicBool,
+    pub(crate) scrub_request: Mutex<Option<Duration>>,
+    pub(crate) scrub_session: Mutex<Option<ScrubSession>>,
+    /// 输出回调次数，判断流是否还在运行。
+    pub(crate) output_callbacks: AtomicU64,
+    output_health: Mutex<OutputHealth>,
+    /// BitPerfect 校验模式下比对解码样本与输出样本。
+    pub(crate) sample_verifier: SampleVerifier,
+    /// 管线健康指标，由播放器跨曲目共享。
+    pub(crate) pipeline_stats: Arc<PipelineStats>,
+    /// 修剪首尾静音与跳过长静音。
+    pub(crate) silence: SilenceSkipper,
+    /// 多声道音源在耳机上做 HRTF 双耳渲染；ring buffer 里是渲染后的双声道样本。
+    binaural: Mutex<Option<BinauralRenderer>>,
 }
 
 impl SharedState {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        Self {
+            is_paused: AtomicBool::new(false),
+            current_frame: AtomicU64::new(0),
+            playback_clock: Mutex::new(PlaybackClock::new()),
+            trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
+            sample_rate: AtomicU32::new(sample_rate),
+            has_seek_request: AtomicBool::new(false),
+            seek_request: Mutex::new(None),
+            is_terminating: AtomicBool::new(false),
+            discard_buffer: AtomicBool::new(false),
+            is_discarding_buffer: AtomicBool::new(false),
+            decoder_done: AtomicBool::new(false),
+            is_finished: AtomicBool::new(false),
+            finish_notify: Notify::new(),
+            buffered_frames: AtomicU64::new(0),
+            waiting_for_seek: AtomicBool::new(false),
+            realtime_scheduling: AtomicBool::new(false),
+            decode_thread_priority: Mutex::new(None),
+            output_thread_priority: Mutex::new(None),
+            range_start_frame: AtomicU64::new(0),
+            range_end_frame: AtomicU64::new(NO_RANGE_END_FRAME),
+            has_next_range: AtomicBool::new(false),
+            next_range_end_frame: AtomicU64::new(NO_RANGE_END_FRAME),
+            range_transitions: AtomicU64::new(0),
+            range_transition_pending: AtomicBool::new(false),
+            total_frames: AtomicU64::new(UNKNOWN_TOTAL_FRAMES),
+            source_bytes: AtomicU64::new(0),
+            decoded_packet_bytes: AtomicU64::new(0),
+            decoded_packet_frames: AtomicU64::new(0),
+            scrubbing: AtomicBool::new(false),
+            scrub_request: Mutex::new(None),
+            scrub_session: Mutex::new(None),
+            output_callbacks: AtomicU64::new(0),
+            output_health: Mutex::new(OutputHealth::default()),
+            sample_verifier: SampleVerifier::new(),
+            pipeline_stats: Arc::new(PipelineStats::new()),
+            silence: SilenceSkipper::new(),
+            binaural: Mutex::new(None),
+        }
+    }
+
+    /// 换用播放器持有的指标，让计数跨曲目累计。
+    pub(crate) fn with_pipeline_stats(mut self, stats: Arc<PipelineStats>) -> Self {
+        stats.reset_callback_clock();
+        self.pipeline_stats = stats;
+        self
+    }
+
+    /// 由 cpal 的错误回调调用；欠载由后端自行恢复，不计入。
+    pub(crate) fn report_output_error(&self, err: &cpal::StreamError) {
+        let mut health = self.output_health.lock().unwrap();
+        match err {
+            cpal::StreamError::BufferUnderrun => {}
+            cpal::StreamError::DeviceNotAvailable | cpal::StreamError::StreamInvalidated => {
+                if health.fatal.is_none() {
+                    eprintln!("Output stream failed: {}", err);
+                    health.fatal = Some(err.to_string());
+                }
+            }
+            _ => {
+                health.errors += 1;
+                health.last_error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// 输出流已无法继续时返回原因：收到致命错误，或回调停止推进后
+    /// 仍持续报错超过 `OUTPUT_STALL_TIMEOUT`。
+    pub(crate) fn output_fault(&self, now: Instant) -> Option<String> {
+        let mut health = self.output_health.lock().unwrap();
+        if let Some(fatal) = &health.fatal {
+            return Some(fatal.clone());
+        }
+
+        let callbacks = self
+            .output_callbacks
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let watched_at = match health.watched_at {
+            Some(watched_at) if callbacks == health.watched_callbacks => watched_at,
+            _ => {
+                health.watched_callbacks = callbacks;
+                health.watched_errors = health.errors;
+                health.watched_at = Some(now);
+                return None;
+            }
+        };
+        let stalled = now.saturating_duration_since(watched_at) >= OUTPUT_STALL_TIMEOUT;
+        (stalled && health.errors > health.watched_errors)
+            .then(|| health.last_error.clone().unwrap_or_default())
+    }
+
+    /// 进入拖动预览：丢掉已缓冲的正常播放数据，暂停中也让输出端播放预览片段。
+    pub(crate) fn begin_scrub(&self, origin: Duration) {
+        let mut session = self.scrub_session.lock().unwrap();
+        if session.is_some() {
+            return;
+        }
+        *session = Some(ScrubSession {
+            was_paused: self
+                .is_paused
+                .swap(false, std::sync::atomic::Ordering::SeqCst),
+            origin,
+            target: None,
+        });
+        self.clear_trim();
+        self.discard_buffer
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        self.scrubbing
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// 只保留最新的预览位置，解码线程空闲时才取走，连续拖动不会积压。
+    /// 不在预览中时忽略并返回 false。
+    pub(crate) fn scrub_to(&self, target: Duration) -> bool {
+        let mut session = self.scrub_session.lock().unwrap();
+        let Some(session) = session.as_mut() else {
+            return false;
+        };
+        session.target = Some(target);
+        *self.scrub_request.lock().unwrap() = Some(target);
+        true
+    }
+
+    pub(crate) fn take_scrub_request(&self) -> Option<Duration> {
+        self.scrub_request.lock().unwrap().take()
+    }
+
+    /// 退出拖动预览：`commit` 时精确 seek 到最后预览的位置，否则回到开始拖动前的位置，
+    /// 并恢复原来的暂停状态。返回最终位置；不在预览中时返回 `None`。
+    pub(crate) fn end_scrub(&self, commit: bool) -> Option<Duration> {
+        let session = self.scrub_session.lock().unwrap().take()?;
+        self.scrubbing
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        self.scrub_request.lock().unwrap().take();
+
+        let target = match session.target {
+            Some(target) if commit => target,
+            _ => session.origin,
+        };
+        self.schedule_seek(target);
+        self.is_paused
+            .store(session.was_paused, std::sync::atomic::Ordering::SeqCst);
+        Some(target)
+    }
+
+    pub(crate) fn record_decoded_packet(&self, bytes: usize, frames: usize) {
+        self.decoded_packet_bytes
+            .fetch_add(bytes as u64, std::sync::atomic::Ordering::Relaxed);
+        self.decoded_packet_frames
+            .fetch_add(frames as u64, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// 容器没有总帧数时，用源字节数除以已解码部分的平均每帧字节数估算，
+    /// 随着读到的数据变多逐步收敛。
+    pub(crate) fn total_frames(&self) -> Option<TotalFrames> {
+        let exact = self.total_frames.load(std::sync::atomic::Ordering::Relaxed);
+        if exact != UNKNOWN_TOTAL_FRAMES {
+            return Some(TotalFrames::Exact(exact));
+        }
+
+        let source_bytes = self.source_bytes.load(std::sync::atomic::Ordering::Relaxed);
+        let bytes = self
+            .decoded_packet_bytes
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let frames = self
+            .decoded_packet_frames
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if source_bytes == 0 || bytes == 0 || frames == 0 {
+            return None;
+        }
+        let estimated = u128::from(source_bytes) * u128::from(frames) / u128::from(bytes);
+        Some(TotalFrames::Estimated(
+            estimated.min(u128::from(u64::MAX - 1)) as u64,
+        ))
+    }
+
+    pub(crate) fn set_range(&self, start_frame: u64, end_frame: Option<u64>) {
+        self.range_start_frame
+            .store(start_frame, std::sync::atomic::Ordering::SeqCst);
+        self.range_end_frame.store(
+            end_frame.unwrap_or(NO_RANGE_END_FRAME),
+            std::sync::atomic::Ordering::SeqCst,
+        );
+    }
+
+    /// `None` 表示不续播；`Some(None)` 表示续播到文件末尾。
+    pub(crate) fn queue_next_range(&self, end_frame: Option<Option<u64>>) {
+        self.next_range_end_frame.store(
+            end_frame.flatten().unwrap_or(NO_RANGE_END_FRAME),
+            std::sync::atomic::Ordering::SeqCst,
+        );
+        self.has_next_range
+            .store(end_frame.is_some(), std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// `frame` 距离当前区间终点还有多少帧；不限终点时为 `None`。
+    pub(crate) fn frames_until_range_end(&self, frame: u64) -> Option<u64> {
+        let end = self
+            .range_end_frame
+            .load(std::sync::atomic::Ordering::Relaxed);
+        (end != NO_RANGE_END_FRAME).then(|| end.saturating_sub(frame))
+    }
+
+    /// 输出到达区间终点时调用：有排队的相邻区间就切过去并唤醒 `wait_finished`，
+    /// 返回是否已切换。
+    pub(crate) fn advance_to_next_range(&self) -> bool {
+        if !self
+            .has_next_range
+            .swap(false, std::sync::atomic::Ordering::SeqCst)
+        {
+            return false;
+        }
+        let end = self
+            .range_end_frame
+            .load(std::sync::atomic::Ordering::SeqCst);
+        self.range_start_frame
+            .store(end, std::sync::atomic::Ordering::SeqCst);
+        self.range_end_frame.store(
+            self.next_range_end_frame
+                .load(std::sync::atomic::Ordering::SeqCst),
+            std::sync::atomic::Ordering::SeqCst,
+        );
+        self.range_transitions
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.silence.start_track();
+        self.range_transition_pending
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        self.finish_notify.notify_waiters();
+        true
+    }
+
+    /// 等到曲目结束或切到下一区间；在本次调用前发生、尚未被取走的切换立即返回。
+    pub(crate) async fn wait_finish_event(&self) {
+        let notified = self.finish_notify.notified();
+        if self.is_finished.load(std::sync::atomic::Ordering::SeqCst)
+            || self
+                .range_transition_pending
+                .swap(false, std::sync::atomic::Ordering::SeqCst)
+        {
+            return;
+        }
+        notified.await;
+        self.range_transition_pending
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+    }
+
     pub(crate) fn schedule_seek(&self, target: Duration) {
         let mut seek_req = self.seek_request.lock().unwrap();
         let sample_rate = self.sample_rate.load(std::sync::atomic::Ordering::Relaxed);
@@ -94,6 +418,8 @@ impl SharedState {
         self.trim_until_frame
             .store(NO_TRIM_FRAME, std::sync::atomic::Ordering::SeqCst);
         self.reset_playback_clock(target_frame);
+        self.pipeline_stats.begin_seek(Instant::now());
+        self.silence.reset_run();
         *seek_req = Some(target);
     }
 
@@ -164,6 +490,35 @@ impl SharedState {
             .reset_to(frame, sample_rate);
     }
 
+    /// 装上双耳渲染器，并把它引入的延迟计入播放时钟。
+    pub(crate) fn set_binaural(&self, renderer: Option<BinauralRenderer>) {
+        let latency = renderer
+            .as_ref()
+            .map_or(0, BinauralRenderer::latency_frames);
+        self.playback_clock.lock().unwrap().dsp_latency_frames = latency;
+        *self.binaural.lock().unwrap() = renderer;
+    }
+
+    pub(crate) fn is_binaural(&self) -> bool {
+        self.binaural.lock().unwrap().is_some()
+    }
+
+    /// 交错的多声道样本渲染成双声道；没有启用双耳渲染时返回 `None`。
+    pub(crate) fn render_binaural(&self, samples: &[f32]) -> Option<Vec<f32>> {
+        let mut renderer = self.binaural.lock().unwrap();
+        let renderer = renderer.as_mut()?;
+        let mut output = Vec::new();
+        renderer.process(samples, &mut output);
+        Some(output)
+    }
+
+    /// seek 或预览跳转后丢掉上一段的卷积尾巴。
+    pub(crate) fn reset_binaural(&self) {
+        if let Some(renderer) = self.binaural.lock().unwrap().as_mut() {
+            renderer.reset();
+        }
+    }
+
     pub(crate) fn clear_trim(&self) {
         self.trim_until_frame
             .store(NO_TRIM_FRAME, std::sync::atomic::Ordering::SeqCst);
@@ -184,23 +539,62 @@ mod tests {
     use std::sync::atomic::Ordering;
 
     fn create_state(sample_rate: u32) -> SharedState {
-        SharedState {
-            is_paused: AtomicBool::new(false),
-            current_frame: AtomicU64::new(0),
-            playback_clock: Mutex::new(PlaybackClock::new()),
-            trim_until_frame: AtomicU64::new(NO_TRIM_FRAME),
-            sample_rate: AtomicU32::new(sample_rate),
-            has_seek_request: AtomicBool::new(false),
-            seek_request: Mutex::new(None),
-            is_terminating: AtomicBool::new(false),
-            discard_buffer: AtomicBool::new(false),
-            is_discarding_buffer: AtomicBool::new(false),
-            decoder_done: AtomicBool::new(false),
-            is_finished: AtomicBool::new(false),
-            finish_notify: Notify::new(),
-            buffered_frames: AtomicU64::new(0),
-            waiting_for_seek: AtomicBool::new(false),
-        }
+        SharedState::new(sample_rate)
+    }
+
+    #[test]
+    fn scrub_coalesces_targets_and_restores_pause_on_cancel() {
+        let state = create_state(48_000);
+        state.is_paused.store(true, Ordering::SeqCst);
+        assert!(!state.scrub_to(Duration::from_secs(5)));
+        assert_eq!(state.take_scrub_request(), None);
+
+        state.begin_scrub(Duration::from_secs(10));
+        assert!(state.scrubbing.load(Ordering::SeqCst));
+        assert!(!state.is_paused.load(Ordering::SeqCst));
+        assert!(state.discard_buffer.load(Ordering::SeqCst));
+
+        assert!(state.scrub_to(Duration::from_secs(20)));
+        state.scrub_to(Duration::from_secs(25));
+        assert_eq!(state.take_scrub_request(), Some(Duration::from_secs(25)));
+        assert_eq!(state.take_scrub_request(), None);
+
+        assert_eq!(state.end_scrub(false), Some(Duration::from_secs(10)));
+        assert!(!state.scrubbing.load(Ordering::SeqCst));
+        assert!(state.is_paused.load(Ordering::SeqCst));
+        assert_eq!(
+            *state.seek_request.lock().unwrap(),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(state.end_scrub(true), None);
+    }
+
+    #[test]
+    fn committed_scrub_seeks_to_last_target() {
+        let state = create_state(48_000);
+        state.begin_scrub(Duration::from_secs(10));
+        state.scrub_to(Duration::from_secs(42));
+
+        assert_eq!(state.end_scrub(true), Some(Duration::from_secs(42)));
+        assert!(!state.is_paused.load(Ordering::SeqCst));
+        assert_eq!(state.current_frame.load(Ordering::SeqCst), 42 * 48_000);
+    }
+
+    #[test]
+    fn total_frames_prefers_container_count_and_otherwise_tracks_bitrate() {
+        let state = create_state(44_100);
+        assert_eq!(state.total_frames(), None);
+
+        state.source_bytes.store(1_000_000, Ordering::SeqCst);
+        state.record_decoded_packet(400, 1152);
+        state.record_decoded_packet(440, 1152);
+        assert_eq!(
+            state.total_frames(),
+            Some(TotalFrames::Estimated(1_000_000 * 2304 / 840))
+        );
+
+        state.total_frames.store(2_646_000, Ordering::SeqCst);
+        assert_eq!(state.total_frames(), Some(TotalFrames::Exact(2_646_000)));
     }
 
     #[test]
@@ -277,6 +671,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn playback_clock_subtracts_dsp_latency_across_seeks() {
+        let state = create_state(48_000);
+        state.playback_clock.lock().unwrap().dsp_latency_frames = 480;
+        state.reset_playback_clock(96_000);
+        state.current_frame.store(100_000, Ordering::SeqCst);
+
+        state.update_playback_clock_from_output(100_000, 100_000, Duration::ZERO);
+
+        let progress = state.progress_frame();
+        assert!(
+            (99_500..=99_530).contains(&progress),
+            "expected around 99520 frames, got {progress}"
+        );
+    }
+
+    #[test]
+    fn reaching_range_end_rolls_into_the_queued_range_once() {
+        let state = create_state(44_100);
+        state.set_range(0, Some(1_000));
+        state.queue_next_range(Some(Some(3_000)));
+
+        assert_eq!(state.frames_until_range_end(400), Some(600));
+        assert!(state.advance_to_next_range());
+        assert_eq!(state.range_start_frame.load(Ordering::SeqCst), 1_000);
+        assert_eq!(state.frames_until_range_end(1_000), Some(2_000));
+        assert_eq!(state.range_transitions.load(Ordering::SeqCst), 1);
+
+        assert!(!state.advance_to_next_range());
+        state.set_range(0, None);
+        assert_eq!(state.frames_until_range_end(1_000), None);
+    }
+
+    #[tokio::test]
+    async fn range_transition_reaches_a_waiter_that_registers_late_exactly_once() {
+        let state = Arc::new(create_state(44_100));
+        state.set_range(0, Some(1_000));
+        state.queue_next_range(Some(Some(3_000)));
+        assert!(state.advance_to_next_range());
+
+        tokio::time::timeout(Duration::from_secs(1), state.wait_finish_event())
+            .await
+            .expect("late waiter should see the transition");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), state.wait_finish_event())
+                .await
+                .is_err(),
+            "the transition was already consumed"
+        );
+
+        let waiter = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move { state.wait_finish_event().await }
+        });
+        tokio::task::yield_now().await;
+        state.queue_next_range(Some(None));
+        assert!(state.advance_to_next_range());
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiting task should wake on the transition")
+            .unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), state.wait_finish_event())
+                .await
+                .is_err(),
+            "a woken waiter consumes the transition"
+        );
+    }
+
     #[test]
     fn playback_clock_never_reports_beyond_submitted_frame() {
         let mut clock = PlaybackClock::new();
@@ -285,4 +748,45 @@ mod tests {
 
         assert_eq!(clock.estimate(now), Some(10_500));
     }
+
+    fn backend_error() -> cpal::StreamError {
+        cpal::StreamError::BackendSpecific {
+            err: cpal::BackendSpecificError {
+                description: "No such device".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn device_loss_is_reported_immediately() {
+        let state = create_state(48_000);
+        state.report_output_error(&cpal::StreamError::BufferUnderrun);
+        assert_eq!(state.output_fault(Instant::now()), None);
+
+        state.report_output_error(&cpal::StreamError::DeviceNotAvailable);
+        assert!(state.output_fault(Instant::now()).is_some());
+    }
+
+    #[test]
+    fn backend_errors_count_as_fault_only_once_callbacks_stall() {
+        let state = create_state(48_000);
+        let start = Instant::now();
+        assert_eq!(state.output_fault(start), None);
+
+        // 回调仍在推进：偶发错误不算故障。
+        state.report_output_error(&backend_error());
+        state.output_callbacks.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(state.output_fault(start + OUTPUT_STALL_TIMEOUT), None);
+
+        let stalled_at = start + OUTPUT_STALL_TIMEOUT;
+        state.report_output_error(&backend_error());
+        assert_eq!(
+            state.output_fault(stalled_at + Duration::from_millis(100)),
+            None
+        );
+        assert_eq!(
+            state.output_fault(stalled_at + OUTPUT_STALL_TIMEOUT),
+            Some("A backend-specific error has occurred: No such device".to_string())
+        );
+    }
 }
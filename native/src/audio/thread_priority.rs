@@ -0,0 +1,192 @@
+use std::fmt;
+
+/// 解码线程请求的实时优先级（低于输出回调，避免抢占音频回调线程）。
+pub(crate) const DECODE_THREAD_RT_PRIORITY: i32 = 5;
+/// 输出回调线程请求的实时优先级。
+pub(crate) const OUTPUT_THREAD_RT_PRIORITY: i32 = 10;
+/// 实时调度被拒绝时回退的 nice 目标值。
+const FALLBACK_NICE_LEVEL: i32 = -10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SchedPolicy {
+    Fifo,
+    RoundRobin,
+}
+
+/// 线程最终拿到的调度等级。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ThreadPriority {
+    RealTime { policy: SchedPolicy, priority: i32 },
+    Nice(i32),
+    Default,
+}
+
+impl fmt::Display for ThreadPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RealTime {
+                policy: SchedPolicy::Fifo,
+                priority,
+            } => write!(f, "SCHED_FIFO:{priority}"),
+            Self::RealTime {
+                policy: SchedPolicy::RoundRobin,
+                priority,
+            } => write!(f, "SCHED_RR:{priority}"),
+            Self::Nice(level) => write!(f, "nice:{level}"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// 按 `RLIMIT_NICE` 换算出允许的最低 nice 值（`20 - rlim_cur`），并夹到回退目标。
+fn fallback_nice_level(nice_limit: Option<u64>) -> Option<i32> {
+    let lowest_allowed = match nice_limit {
+        Some(limit) => 20 - limit.min(40) as i32,
+        None => FALLBACK_NICE_LEVEL,
+    };
+    let level = FALLBACK_NICE_LEVEL.max(lowest_allowed);
+    (level < 0).then_some(level)
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{SchedPolicy, ThreadPriority, fallback_nice_level};
+
+    const SCHED_FIFO: i32 = 1;
+    const SCHED_RR: i32 = 2;
+    const RLIMIT_NICE: i32 = 13;
+    const RLIMIT_RTPRIO: i32 = 14;
+    const RLIM_INFINITY: u64 = u64::MAX;
+    const PRIO_PROCESS: i32 = 0;
+
+    #[repr(C)]
+    struct SchedParam {
+        sched_priority: i32,
+    }
+
+    #[repr(C)]
+    struct RLimit {
+        rlim_cur: u64,
+        rlim_max: u64,
+    }
+
+    unsafe extern "C" {
+        fn pthread_self() -> usize;
+        fn pthread_setschedparam(thread: usize, policy: i32, param: *const SchedParam) -> i32;
+        fn sched_get_priority_max(policy: i32) -> i32;
+        fn getrlimit(resource: i32, rlim: *mut RLimit) -> i32;
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+        fn gettid() -> i32;
+    }
+
+    fn rlimit_current(resource: i32) -> Option<u64> {
+        let mut limit = RLimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { getrlimit(resource, &mut limit) } != 0 {
+            return None;
+        }
+        (limit.rlim_cur != RLIM_INFINITY).then_some(limit.rlim_cur)
+    }
+
+    /// 当前进程的 `RLIMIT_RTPRIO` 软限制；`None` 表示无限制。
+    pub(crate) fn rtprio_limit() -> Option<u64> {
+        rlimit_current(RLIMIT_RTPRIO)
+    }
+
+    pub(crate) fn promote_current_thread(policy: SchedPolicy, priority: i32) -> ThreadPriority {
+        let raw_policy = match policy {
+            SchedPolicy::Fifo => SCHED_FIFO,
+            SchedPolicy::RoundRobin => SCHED_RR,
+        };
+        let max_priority = unsafe { sched_get_priority_max(raw_policy) }.max(1);
+        // RLIMIT_RTPRIO 为 0 时仍尝试一次：持有 CAP_SYS_NICE 的进程不受该限制。
+        let limited = match rtprio_limit() {
+            Some(limit) if limit > 0 => priority.min(limit.min(i32::MAX as u64) as i32),
+            _ => priority,
+        };
+        let priority = limited.clamp(1, max_priority);
+
+        let param = SchedParam {
+            sched_priority: priority,
+        };
+        let result = unsafe { pthread_setschedparam(pthread_self(), raw_policy, &param) };
+        if result == 0 {
+            return ThreadPriority::RealTime { policy, priority };
+        }
+
+        eprintln!(
+            "[audio] 实时调度被拒绝（errno={result}，RLIMIT_RTPRIO={:?}），回退到 nice",
+            rtprio_limit()
+        );
+        let Some(level) = fallback_nice_level(rlimit_current(RLIMIT_NICE)) else {
+            return ThreadPriority::Default;
+        };
+        // Linux 上 setpriority(PRIO_PROCESS, tid) 只作用于该线程。
+        let tid = unsafe { gettid() };
+        if unsafe { setpriority(PRIO_PROCESS, tid as u32, level) } == 0 {
+            ThreadPriority::Nice(level)
+        } else {
+            ThreadPriority::Default
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{promote_current_thread, rtprio_limit};
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn promote_current_thread(_policy: SchedPolicy, _priority: i32) -> ThreadPriority {
+    ThreadPriority::Default
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn rtprio_limit() -> Option<u64> {
+    None
+}
+
+/// 一次调度诊断快照：是否启用，以及解码/输出线程各自拿到的等级。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SchedulingReport {
+    pub(crate) enabled: bool,
+    pub(crate) decode_thread: Option<ThreadPriority>,
+    pub(crate) output_thread: Option<ThreadPriority>,
+    pub(crate) rtprio_limit: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_nice_level_respects_rlimit_nice() {
+        assert_eq!(fallback_nice_level(None), Some(-10));
+        assert_eq!(fallback_nice_level(Some(40)), Some(-10));
+        assert_eq!(fallback_nice_level(Some(25)), Some(-5));
+        assert_eq!(fallback_nice_level(Some(20)), None);
+        assert_eq!(fallback_nice_level(Some(0)), None);
+    }
+
+    #[test]
+    fn thread_priority_is_reported_in_scheduler_terms() {
+        assert_eq!(
+            ThreadPriority::RealTime {
+                policy: SchedPolicy::Fifo,
+                priority: 10
+            }
+            .to_string(),
+            "SCHED_FIFO:10"
+        );
+        assert_eq!(
+            ThreadPriority::RealTime {
+                policy: SchedPolicy::RoundRobin,
+                priority: 5
+            }
+            .to_string(),
+            "SCHED_RR:5"
+        );
+        assert_eq!(ThreadPriority::Nice(-10).to_string(), "nice:-10");
+        assert_eq!(ThreadPriority::Default.to_string(), "default");
+    }
+}
@@ -0,0 +1,839 @@
+use crate::audio::ape::channels_from_count;
+use std::io::{Seek, SeekFrom};
+use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia::core::codecs::{
+    CODEC_TYPE_TTA, CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult,
+};
+use symphonia::core::errors::{
+    Error, Result, SeekErrorKind, decode_error, seek_error, unsupported_error,
+};
+use symphonia::core::formats::{
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadBytes};
+use symphonia::core::meta::{Metadata, MetadataLog};
+use symphonia::core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia::core::units::TimeBase;
+use symphonia::core::{support_codec, support_format};
+
+const HEADER_LEN: usize = 22;
+const FORMAT_SIMPLE: u16 = 1;
+/// 单个 TTA 帧最多约 1.04 秒；用于拒绝损坏的 seek table。
+const MAX_FRAME_BYTES: u32 = 64 * 1024 * 1024;
+/// seek table 最多的帧数（约 48 天），防止损坏的头部让 seek table 分配过大的内存。
+const MAX_FRAMES: u64 = 1 << 22;
+
+/// TTA1 帧长固定为 256/245 秒。
+pub(crate) fn frame_length(sample_rate: u32) -> u64 {
+    u64::from(sample_rate) * 256 / 245
+}
+
+/// True Audio（.tta）容器：定长帧 + 头部 seek table，seek 精确到帧起点。
+pub(crate) struct TtaReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    frame_len: u64,
+    total_samples: u64,
+    frame_offsets: Vec<u64>,
+    frame_sizes: Vec<u32>,
+    next_frame: usize,
+}
+
+impl TtaReader {
+    fn frame_samples(&self, frame: usize) -> u64 {
+        let start = frame as u64 * self.frame_len;
+        (self.total_samples - start).min(self.frame_len)
+    }
+}
+
+impl QueryDescriptor for TtaReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "tta",
+            "True Audio",
+            &["tta"],
+            &["audio/tta", "audio/x-tta"],
+            &[b"TTA1"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for TtaReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        source.read_buf_exact(&mut header)?;
+        if &header[..4] != b"TTA1" {
+            return unsupported_error("tta: missing TTA1 header");
+        }
+        if crc32(&header[..18])
+            != u32::from_le_bytes([header[18], header[19], header[20], header[21]])
+        {
+            return decode_error("tta: header checksum mismatch");
+        }
+        let format = u16::from_le_bytes([header[4], header[5]]);
+        let channels = u16::from_le_bytes([header[6], header[7]]);
+        let bits = u16::from_le_bytes([header[8], header[9]]);
+        let sample_rate = u32::from_le_bytes([header[10], header[11], header[12], header[13]]);
+        let total_samples = u64::from(u32::from_le_bytes([
+            header[14], header[15], header[16], header[17],
+        ]));
+        if format != FORMAT_SIMPLE {
+            return unsupported_error("tta: encrypted or float streams are not supported");
+        }
+        if channels == 0 || !matches!(bits, 8 | 16 | 24) || sample_rate == 0 {
+            return unsupported_error("tta: unsupported stream layout");
+        }
+        let frame_len = frame_length(sample_rate);
+        if total_samples == 0 || frame_len == 0 {
+            return decode_error("tta: empty stream");
+        }
+
+        let frames = total_samples.div_ceil(frame_len);
+        // 每帧在 seek table 里占 4 字节，帧本身至少 5 字节，文件放不下的帧数必然是损坏的。
+        let fits_stream = source
+            .byte_len()
+            .is_none_or(|len| frames.saturating_mul(9) <= len);
+        if frames > MAX_FRAMES || !fits_stream {
+            return decode_error("tta: frame count is out of range");
+        }
+        let frames = frames as usize;
+        let mut table = vec![0u8; frames * 4];
+        source.read_buf_exact(&mut table)?;
+        let table_crc = source.read_u32()?;
+        if crc32(&table) != table_crc {
+            return decode_error("tta: seek table checksum mismatch");
+        }
+
+        let mut offset = source.pos();
+        let mut frame_offsets = Vec::with_capacity(frames);
+        let mut frame_sizes = Vec::with_capacity(frames);
+        for size in table.chunks_exact(4) {
+            let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]);
+            if size <= 4 || size > MAX_FRAME_BYTES {
+                return decode_error("tta: invalid seek table");
+            }
+            frame_offsets.push(offset);
+            frame_sizes.push(size);
+            offset += u64::from(size);
+        }
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_TTA)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_n_frames(total_samples)
+            .with_bits_per_sample(u32::from(bits))
+            .with_bits_per_coded_sample(u32::from(bits))
+            .with_channels(channels_from_count(channels))
+            .with_max_frames_per_packet(frame_len);
+
+        Ok(Self {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            frame_len,
+            total_samples,
+            frame_offsets,
+            frame_sizes,
+            next_frame: 0,
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => self.tracks[0]
+                .codec_params
+                .time_base
+                .map_or(0, |time_base| time_base.calc_timestamp(time)),
+        };
+        if ts >= self.total_samples {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+        self.next_frame = (ts / self.frame_len) as usize;
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts: ts,
+            actual_ts: self.next_frame as u64 * self.frame_len,
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame = self.next_frame;
+        let (Some(&offset), Some(&size)) =
+            (self.frame_offsets.get(frame), self.frame_sizes.get(frame))
+        else {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        };
+        if self.reader.pos() != offset {
+            self.reader.seek(SeekFrom::Start(offset))?;
+        }
+        let data = self.reader.read_boxed_slice_exact(size as usize)?;
+        self.next_frame += 1;
+        Ok(Packet::new_from_boxed_slice(
+            0,
+            frame as u64 * self.frame_len,
+            self.frame_samples(frame),
+            data,
+        ))
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// TTA1 解码器：每帧独立，帧内依次经过 Rice 解码、自适应滤波、固定预测与声道去相关。
+pub(crate) struct TtaDecoder {
+    params: CodecParameters,
+    bytes_per_sample: usize,
+    channels: Vec<ChannelState>,
+    samples: Vec<i32>,
+    buf: AudioBuffer<i32>,
+}
+
+#[derive(Clone, Default)]
+struct ChannelState {
+    predictor: i32,
+    filter: Filter,
+    rice: Rice,
+}
+
+#[derive(Clone, Default)]
+struct Filter {
+    shift: u32,
+    round: i32,
+    error: i32,
+    qm: [i32; 8],
+    dx: [i32; 8],
+    dl: [i32; 8],
+}
+
+impl Filter {
+    fn new(bytes_per_sample: usize) -> Self {
+        let shift = [10, 9, 10][bytes_per_sample - 1];
+        Self {
+            shift,
+            round: 1 << (shift - 1),
+            ..Self::default()
+        }
+    }
+
+    fn decode(&mut self, value: &mut i32) {
+        if self.error < 0 {
+            for (qm, dx) in self.qm.iter_mut().zip(self.dx) {
+                *qm = qm.wrapping_sub(dx);
+            }
+        } else if self.error > 0 {
+            for (qm, dx) in self.qm.iter_mut().zip(self.dx) {
+                *qm = qm.wrapping_add(dx);
+            }
+        }
+        let mut sum = self.round;
+        for (dl, qm) in self.dl.iter().zip(self.qm) {
+            sum = sum.wrapping_add(dl.wrapping_mul(qm));
+        }
+
+        self.dx.copy_within(1..5, 0);
+        self.dl.copy_within(1..5, 0);
+        let dl = &mut self.dl;
+        self.dx[4] = (dl[4] >> 30) | 1;
+        self.dx[5] = ((dl[5] >> 30) | 2) & !1;
+        self.dx[6] = ((dl[6] >> 30) | 2) & !1;
+        self.dx[7] = ((dl[7] >> 30) | 4) & !3;
+
+        self.error = *value;
+        *value = value.wrapping_add(sum >> self.shift);
+
+        dl[4] = dl[5].wrapping_neg();
+        dl[5] = dl[6].wrapping_neg();
+        dl[6] = value.wrapping_sub(dl[7]);
+        dl[7] = *value;
+        dl[5] = dl[5].wrapping_add(dl[6]);
+        dl[4] = dl[4].wrapping_add(dl[5]);
+    }
+}
+
+#[derive(Clone)]
+struct Rice {
+    k0: u32,
+    k1: u32,
+    sum0: u32,
+    sum1: u32,
+}
+
+impl Default for Rice {
+    fn default() -> Self {
+        Self {
+            k0: 10,
+            k1: 10,
+            sum0: shift_16(10),
+            sum1: shift_16(10),
+        }
+    }
+}
+
+/// 自适应 Rice 参数：累计和越过相邻档位阈值时调整 k。
+fn adapt(k: &mut u32, sum: &mut u32, value: u32) {
+    *sum = sum.wrapping_add(value.wrapping_sub(*sum >> 4));
+    if *k > 0 && *sum < shift_16(*k) {
+        *k -= 1;
+    } else if *sum > shift_16(*k + 1) {
+        *k += 1;
+    }
+}
+
+fn shift_1(k: u32) -> u32 {
+    1u32.checked_shl(k).unwrap_or(0x8000_0000)
+}
+
+fn shift_16(k: u32) -> u32 {
+    shift_1(k + 4)
+}
+
+/// LSB 优先的位读取器。
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    cache: u64,
+    bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            cache: 0,
+            bits: 0,
+        }
+    }
+
+    fn refill(&mut self, wanted: u32) -> Result<()> {
+        while self.bits < wanted {
+            let Some(&byte) = self.data.get(self.pos) else {
+                return decode_error("tta: frame is truncated");
+            };
+            self.cache |= u64::from(byte) << self.bits;
+            self.pos += 1;
+            self.bits += 8;
+        }
+        Ok(())
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32> {
+        if count == 0 {
+            return Ok(0);
+        }
+        self.refill(count)?;
+        let value = (self.cache & ((1u64 << count) - 1)) as u32;
+        self.cache >>= count;
+        self.bits -= count;
+        Ok(value)
+    }
+
+    fn read_unary(&mut self) -> Result<u32> {
+        let mut count = 0u32;
+        loop {
+            self.refill(1)?;
+            let ones = (!self.cache).trailing_zeros().min(self.bits);
+            count = count.saturating_add(ones);
+            self.cache >>= ones;
+            self.bits -= ones;
+            if self.bits > 0 {
+                self.cache >>= 1;
+                self.bits -= 1;
+                return Ok(count);
+            }
+        }
+    }
+}
+
+impl TtaDecoder {
+    fn reset_channels(&mut self) {
+        let fresh = ChannelState {
+            filter: Filter::new(self.bytes_per_sample),
+            ..ChannelState::default()
+        };
+        for channel in &mut self.channels {
+            *channel = fresh.clone();
+        }
+    }
+
+    fn decode_frame(&mut self, data: &[u8], frames: usize) -> Result<()> {
+        if data.len() < 4 {
+            return decode_error("tta: frame is truncated");
+        }
+        let (payload, crc) = data.split_at(data.len() - 4);
+        if crc32(payload) != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return decode_error("tta: frame checksum mismatch");
+        }
+
+        self.reset_channels();
+        let channel_count = self.channels.len();
+        let predictor_shift = if self.bytes_per_sample == 1 { 4 } else { 5 };
+        self.samples.clear();
+        self.samples.resize(frames * channel_count, 0);
+        let mut bits = BitReader::new(payload);
+
+        for frame in self.samples.chunks_exact_mut(channel_count) {
+            for (state, sample) in self.channels.iter_mut().zip(frame.iter_mut()) {
+                let rice = &mut state.rice;
+                let mut unary = bits.read_unary()?;
+                let depth = unary > 0;
+                let k = if depth {
+                    unary -= 1;
+                    rice.k1
+                } else {
+                    rice.k0
+                };
+                if k > 24 {
+                    return decode_error("tta: invalid rice parameter");
+                }
+                let mut value = (unary << k).wrapping_add(bits.read_bits(k)?);
+                if depth {
+                    adapt(&mut rice.k1, &mut rice.sum1, value);
+                    value = value.wrapping_add(shift_1(rice.k0));
+                }
+                adapt(&mut rice.k0, &mut rice.sum0, value);
+
+                // 奇数为正、偶数为负的交错编码。
+                let mut decoded = if value & 1 == 1 {
+                    ((value >> 1) as i32).wrapping_add(1)
+                } else {
+                    ((value >> 1) as i32).wrapping_neg()
+                };
+                state.filter.decode(&mut decoded);
+                let predictor = state.predictor;
+                let predicted = ((i64::from(predictor) << predictor_shift) - i64::from(predictor))
+                    >> predictor_shift;
+                decoded = decoded.wrapping_add(predicted as i32);
+                state.predictor = decoded;
+                *sample = decoded;
+            }
+
+            if channel_count > 1 {
+                let last = channel_count - 1;
+                frame[last] = frame[last].wrapping_add(frame[last - 1] / 2);
+                for channel in (0..last).rev() {
+                    frame[channel] = frame[channel + 1].wrapping_sub(frame[channel]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for TtaDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (Some(sample_rate), Some(channels), Some(bits)) =
+            (params.sample_rate, params.channels, params.bits_per_sample)
+        else {
+            return unsupported_error("tta: missing stream parameters");
+        };
+        if !matches!(bits, 8 | 16 | 24) {
+            return unsupported_error("tta: unsupported bit depth");
+        }
+        let max_frames = params
+            .max_frames_per_packet
+            .unwrap_or_else(|| frame_length(sample_rate));
+        Ok(Self {
+            params: params.clone(),
+            bytes_per_sample: (bits / 8) as usize,
+            channels: vec![ChannelState::default(); channels.count()],
+            samples: Vec::new(),
+            buf: AudioBuffer::new(max_frames, SignalSpec::new(sample_rate, channels)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_TTA, "tta", "True Audio")]
+    }
+
+    fn reset(&mut self) {
+        // 帧之间不共享状态，每帧开头都会重新初始化。
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let frames = packet.dur() as usize;
+        if frames as u64 > self.buf.capacity() as u64 {
+            return decode_error("tta: frame is longer than the stream frame length");
+        }
+        self.decode_frame(packet.buf(), frames)?;
+
+        let channel_count = self.channels.len();
+        let shift = 32 - self.bytes_per_sample as u32 * 8;
+        self.buf.render_reserved(Some(frames));
+        for channel in 0..channel_count {
+            let out = self.buf.chan_mut(channel);
+            for (frame, sample) in out.iter_mut().enumerate() {
+                *sample = self.samples[frame * channel_count + channel] << shift;
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// TTA 的头部、seek table 与帧尾均使用标准 CRC-32（IEEE）。
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::audio::registry::{self, codec_registry};
+    use std::io::Cursor;
+    use symphonia::core::audio::Signal;
+    use symphonia::core::probe::Hint;
+
+    /// 按参考编码器流程独立生成的两帧（2089 + 111 个采样）16 bit 立体声 TTA1 文件，
+    /// 采样率 2000 Hz，内容见 `fixture_signal`。
+    const STEREO_16BIT: &[u8] = include_bytes!("testdata/stereo_16bit.tta");
+
+    fn fixture_signal(n: i32) -> [i32; 2] {
+        let left = if (1000..1008).contains(&n) {
+            if n % 2 == 1 { 20_000 } else { -20_000 }
+        } else {
+            (n * 37) % 201 - 100
+        };
+        [left, left / 2 + n % 7 - 3]
+    }
+
+    /// 与 `Filter::decode` 对称的编码端滤波器。
+    fn filter_encode(filter: &mut Filter, value: &mut i32) {
+        if filter.error < 0 {
+            for (qm, dx) in filter.qm.iter_mut().zip(filter.dx) {
+                *qm = qm.wrapping_sub(dx);
+            }
+        } else if filter.error > 0 {
+            for (qm, dx) in filter.qm.iter_mut().zip(filter.dx) {
+                *qm = qm.wrapping_add(dx);
+            }
+        }
+        let mut sum = filter.round;
+        for (dl, qm) in filter.dl.iter().zip(filter.qm) {
+            sum = sum.wrapping_add(dl.wrapping_mul(qm));
+        }
+        filter.dx.copy_within(1..5, 0);
+        filter.dl.copy_within(1..5, 0);
+        let dl = &mut filter.dl;
+        filter.dx[4] = (dl[4] >> 30) | 1;
+        filter.dx[5] = ((dl[5] >> 30) | 2) & !1;
+        filter.dx[6] = ((dl[6] >> 30) | 2) & !1;
+        filter.dx[7] = ((dl[7] >> 30) | 4) & !3;
+        dl[4] = dl[5].wrapping_neg();
+        dl[5] = dl[6].wrapping_neg();
+        dl[6] = value.wrapping_sub(dl[7]);
+        dl[7] = *value;
+        dl[5] = dl[5].wrapping_add(dl[6]);
+        dl[4] = dl[4].wrapping_add(dl[5]);
+        *value = value.wrapping_sub(sum >> filter.shift);
+        filter.error = *value;
+    }
+
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        cache: u64,
+        bits: u32,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u32, count: u32) {
+            self.cache |= u64::from(value & ((1u64 << count) - 1) as u32) << self.bits;
+            self.bits += count;
+            while self.bits >= 8 {
+                self.bytes.push(self.cache as u8);
+                self.cache >>= 8;
+                self.bits -= 8;
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            if self.bits > 0 {
+                self.bytes.push(self.cache as u8);
+            }
+            self.bytes
+        }
+    }
+
+    fn encode_frame(samples: &[i32], channels: usize, bytes_per_sample: usize) -> Vec<u8> {
+        let mut states = vec![
+            ChannelState {
+                filter: Filter::new(bytes_per_sample),
+                ..ChannelState::default()
+            };
+            channels
+        ];
+        let predictor_shift = if bytes_per_sample == 1 { 4 } else { 5 };
+        let mut writer = BitWriter::default();
+        for frame in samples.chunks_exact(channels) {
+            let mut residual = 0;
+            for (channel, state) in states.iter_mut().enumerate() {
+                let mut value = frame[channel];
+                if channels > 1 {
+                    if channel < channels - 1 {
+                        residual = frame[channel + 1] - value;
+                        value = residual;
+                    } else {
+                        value -= residual / 2;
+                    }
+                }
+                let temp = value;
+                let predictor = i64::from(state.predictor);
+                value -= (((predictor << predictor_shift) - predictor) >> predictor_shift) as i32;
+                state.predictor = temp;
+                filter_encode(&mut state.filter, &mut value);
+
+                let mut out = if value > 0 {
+                    (value as u32) * 2 - 1
+                } else {
+                    value.unsigned_abs() * 2
+                };
+                let rice = &mut state.rice;
+                let mut k = rice.k0;
+                adapt(&mut rice.k0, &mut rice.sum0, out);
+                if out >= 1 << k {
+                    out -= 1 << k;
+                    k = rice.k1;
+                    adapt(&mut rice.k1, &mut rice.sum1, out);
+                    for _ in 0..1 + (out >> k) {
+                        writer.write(1, 1);
+                    }
+                }
+                writer.write(0, 1);
+                writer.write(out, k);
+            }
+        }
+        let mut bytes = writer.finish();
+        let crc = crc32(&bytes);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// 生成一个完整的 TTA1 文件（交错整数样本）。
+    pub(crate) fn tta_bytes(
+        samples: &[i32],
+        channels: u16,
+        bits: u16,
+        sample_rate: u32,
+    ) -> Vec<u8> {
+        let channel_count = usize::from(channels);
+        let total = samples.len() / channel_count;
+        let frame_len = frame_length(sample_rate) as usize;
+        let frames: Vec<Vec<u8>> = samples
+            .chunks(frame_len * channel_count)
+            .map(|chunk| encode_frame(chunk, channel_count, usize::from(bits / 8)))
+            .collect();
+
+        let mut out = b"TTA1".to_vec();
+        out.extend_from_slice(&FORMAT_SIMPLE.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&bits.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(total as u32).to_le_bytes());
+        let crc = crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+
+        let table: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| (frame.len() as u32).to_le_bytes())
+            .collect();
+        out.extend_from_slice(&table);
+        out.extend_from_slice(&crc32(&table).to_le_bytes());
+        for frame in frames {
+            out.extend_from_slice(&frame);
+        }
+        out
+    }
+
+    fn test_signal(frames: usize, channels: usize, bits: u32) -> Vec<i32> {
+        let amplitude = (1i64 << (bits - 2)) as f64;
+        let mut noise = 0x1234_5678u32;
+        (0..frames * channels)
+            .map(|i| {
+                noise = noise.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let frame = (i / channels) as f64;
+                let channel = (i % channels) as f64;
+                let tone = (frame * (0.01 + channel * 0.003)).sin() * amplitude;
+                tone as i32 + (noise >> 28) as i32 - 8
+            })
+            .collect()
+    }
+
+    fn decode_all(bytes: Vec<u8>) -> (Vec<i32>, usize) {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("tta");
+        let mut format = registry::open_format(mss, &hint, None).unwrap();
+        let mut decoder = codec_registry()
+            .make(
+                &format.default_track().unwrap().codec_params,
+                &DecoderOptions::default(),
+            )
+            .unwrap();
+        let mut out = Vec::new();
+        let mut channels = 0;
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            let AudioBufferRef::S32(buf) = decoded else {
+                panic!("tta decodes to i32");
+            };
+            channels = buf.spec().channels.count();
+            for frame in 0..buf.frames() {
+                for channel in 0..channels {
+                    out.push(buf.chan(channel)[frame]);
+                }
+            }
+        }
+        (out, channels)
+    }
+
+    #[test]
+    fn stereo_16bit_frames_roundtrip_bit_exact() {
+        let samples = test_signal(30_000, 2, 16);
+        let (decoded, channels) = decode_all(tta_bytes(&samples, 2, 16, 22_050));
+
+        assert_eq!(channels, 2);
+        let expected: Vec<i32> = samples.iter().map(|sample| sample << 16).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn decodes_fixture_stream_bit_exact() {
+        let (decoded, channels) = decode_all(STEREO_16BIT.to_vec());
+
+        assert_eq!(channels, 2);
+        let expected: Vec<i32> = (0..2_200)
+            .flat_map(fixture_signal)
+            .map(|sample| sample << 16)
+            .collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn rejects_frame_counts_the_stream_cannot_hold() {
+        let samples = test_signal(2_000, 1, 16);
+        for (sample_rate, total_samples) in [(245u32, u32::MAX), (44_100, 1_000_000_000)] {
+            let mut bytes = tta_bytes(&samples, 1, 16, 44_100);
+            bytes[10..14].copy_from_slice(&sample_rate.to_le_bytes());
+            bytes[14..18].copy_from_slice(&total_samples.to_le_bytes());
+            let crc = crc32(&bytes[..18]);
+            bytes[18..22].copy_from_slice(&crc.to_le_bytes());
+            let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+            assert!(matches!(
+                TtaReader::try_new(mss, &FormatOptions::default()),
+                Err(Error::DecodeError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn multichannel_24bit_roundtrip_bit_exact() {
+        let samples = test_signal(5_000, 3, 24);
+        let (decoded, channels) = decode_all(tta_bytes(&samples, 3, 24, 8_000));
+
+        assert_eq!(channels, 3);
+        let expected: Vec<i32> = samples.iter().map(|sample| sample << 8).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn seek_lands_on_frame_boundary_and_reports_duration() {
+        let samples = test_signal(30_000, 1, 8);
+        let mss = MediaSourceStream::new(
+            Box::new(Cursor::new(tta_bytes(&samples, 1, 8, 22_050))),
+            Default::default(),
+        );
+        let mut format = TtaReader::try_new(mss, &FormatOptions::default()).unwrap();
+        assert_eq!(format.tracks()[0].codec_params.n_frames, Some(30_000));
+
+        let seeked = format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp {
+                    ts: 25_000,
+                    track_id: 0,
+                },
+            )
+            .unwrap();
+        let frame_len = frame_length(22_050);
+        assert_eq!(seeked.actual_ts, 25_000 / frame_len * frame_len);
+        let packet = format.next_packet().unwrap();
+        assert_eq!(packet.ts(), seeked.actual_ts);
+    }
+
+    #[test]
+    fn corrupted_frame_fails_checksum() {
+        let samples = test_signal(2_000, 1, 16);
+        let mut bytes = tta_bytes(&samples, 1, 16, 44_100);
+        let last = bytes.len() - 10;
+        bytes[last] ^= 0x40;
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+        let mut format = TtaReader::try_new(mss, &FormatOptions::default()).unwrap();
+        let mut decoder =
+            TtaDecoder::try_new(&format.tracks()[0].codec_params, &DecoderOptions::default())
+                .unwrap();
+        let packet = format.next_packet().unwrap();
+        assert!(matches!(
+            decoder.decode(&packet),
+            Err(Error::DecodeError(_))
+        ));
+    }
+}
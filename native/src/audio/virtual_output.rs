@@ -0,0 +1,666 @@
+//! 不对应声卡的虚拟输出：`null:` 按实时速度丢弃样本，`file:<路径>` 把实际输出的样本
+//! 写成 WAV（超过 4 GiB 时改写为 RF64；每次打开输出写一个新文件，已有的文件不覆盖），
+//! `fifo:<路径>` 把 PCM 写进命名管道供 Snapcast 等读取。
+//! 输出线程按实时节奏调用与 cpal 回调相同的 `render_output`，没有声卡也能播放。
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use cpal::Sample;
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+
+use crate::audio::alsa_hw::HwSample;
+use crate::audio::backend::{self, OutputDeviceInfo};
+use crate::audio::multi_output::{LinearResampler, OutputFanout};
+use crate::audio::state::SharedState;
+use crate::audio::wav::{WAV_FORMATS, WavWriter};
+
+const NULL_PREFIX: &str = "null:";
+const FILE_PREFIX: &str = "file:";
+const FIFO_PREFIX: &str = "fifo:";
+/// Snapcast 默认读取的管道与格式。
+const DEFAULT_FIFO_PATH: &str = "/tmp/snapfifo";
+const DEFAULT_FIFO_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_FIFO_BITS: u16 = 16;
+/// 每次渲染的时长。
+const PERIOD: Duration = Duration::from_millis(20);
+/// 管道读端跟不上时最多积压的时长，超出的旧数据按整帧丢弃。
+const MAX_FIFO_BACKLOG: Duration = Duration::from_secs(1);
+
+/// 虚拟输出设备，由设备 ID 的前缀决定。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum VirtualOutput {
+    Null,
+    File(PathBuf),
+    Fifo(PathBuf),
+}
+
+impl VirtualOutput {
+    pub(crate) fn parse(device_id: &str) -> Option<Self> {
+        let id = device_id.trim();
+        if id.starts_with(NULL_PREFIX) {
+            return Some(Self::Null);
+        }
+        if let Some(path) = id.strip_prefix(FILE_PREFIX) {
+            return Some(Self::File(PathBuf::from(path.trim())));
+        }
+        let path = id.strip_prefix(FIFO_PREFIX)?.trim();
+        Some(Self::Fifo(PathBuf::from(if path.is_empty() {
+            DEFAULT_FIFO_PATH
+        } else {
+            path
+        })))
+    }
+
+    fn device_id(&self) -> String {
+        match self {
+            Self::Null => NULL_PREFIX.to_string(),
+            Self::File(path) => format!("{FILE_PREFIX}{}", path.display()),
+            Self::Fifo(path) => format!("{FIFO_PREFIX}{}", path.display()),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        match self {
+            Self::Null => "Null output".to_string(),
+            Self::File(path) => format!("WAV file - {}", path.display()),
+            Self::Fifo(path) => format!("FIFO - {}", path.display()),
+        }
+    }
+}
+
+pub(crate) fn is_virtual_device(device_id: &str) -> bool {
+    VirtualOutput::parse(device_id).is_some()
+}
+
+/// 虚拟输出的设置，在下一次开始播放时生效。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct VirtualOutputOptions {
+    /// 列在设备列表中的 `file:` 输出路径，为空时不列出。
+    pub(crate) file_path: Option<String>,
+    /// 列在设备列表中的 `fifo:` 管道，管道存在时才列出。
+    pub(crate) fifo_path: String,
+    /// 写进管道的 PCM 格式，与读端（如 Snapcast 的 `sampleformat`）一致。
+    pub(crate) fifo_sample_rate: u32,
+    pub(crate) fifo_bits: u16,
+}
+
+impl Default for VirtualOutputOptions {
+    fn default() -> Self {
+        Self {
+            file_path: None,
+            fifo_path: DEFAULT_FIFO_PATH.to_string(),
+            fifo_sample_rate: DEFAULT_FIFO_SAMPLE_RATE,
+            fifo_bits: DEFAULT_FIFO_BITS,
+        }
+    }
+}
+
+/// 设备列表中的虚拟输出；当前选中的虚拟输出即使没有配置也会列出。
+pub(crate) fn output_devices(
+    options: &VirtualOutputOptions,
+    current_id: Option<&str>,
+) -> Vec<OutputDeviceInfo> {
+    let mut outputs = vec![VirtualOutput::Null];
+    if let Some(path) = options.file_path.as_deref().filter(|path| !path.is_empty()) {
+        outputs.push(VirtualOutput::File(PathBuf::from(path)));
+    }
+    if is_fifo(Path::new(&options.fifo_path)) {
+        outputs.push(VirtualOutput::Fifo(PathBuf::from(&options.fifo_path)));
+    }
+    let current = current_id.and_then(VirtualOutput::parse);
+    if let Some(current) = &current
+        && !outputs.contains(current)
+    {
+        outputs.push(current.clone());
+    }
+
+    outputs
+        .into_iter()
+        .map(|output| OutputDeviceInfo {
+            id: output.device_id(),
+            name: output.display_name(),
+            is_default: false,
+            is_current: current.as_ref() == Some(&output),
+            hw_volume: false,
+        })
+        .collect()
+}
+
+/// 虚拟输出的流配置：文件按音源的原始格式写，空输出与管道用 F32 再各自转换。
+pub(crate) fn stream_config(
+    output: &VirtualOutput,
+    sample_rate: u32,
+    channels: u16,
+    candidates: &[cpal::SampleFormat],
+    strict_bit_perfect: bool,
+    dop: bool,
+) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn std::error::Error>> {
+    if strict_bit_perfect {
+        return Err("当前无法满足BitPerfect条件拒绝播放：虚拟输出不是硬件设备".into());
+    }
+    if dop {
+        return Err("虚拟输出不支持 DoP".into());
+    }
+    let sample_format = match output {
+        VirtualOutput::File(_) => candidates
+            .iter()
+            .copied()
+            .find(|format| WAV_FORMATS.contains(format))
+            .unwrap_or(cpal::SampleFormat::F32),
+        VirtualOutput::Null | VirtualOutput::Fifo(_) => cpal::SampleFormat::F32,
+    };
+    Ok((
+        cpal::StreamConfig {
+            channels,
+            sample_rate,
+            buffer_size: cpal::BufferSize::Default,
+        },
+        sample_format,
+    ))
+}
+
+/// 实际生效的虚拟输出参数；管道输出的采样率是写进管道的采样率。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct VirtualOutputInfo {
+    pub(crate) sample_rate: u32,
+    pub(crate) period_frames: u32,
+}
+
+/// 虚拟输出流；`play` 后才启动输出线程，drop 时停止并补全 WAV 文件头。
+pub(crate) struct VirtualOutputStream {
+    info: VirtualOutputInfo,
+    stop: Arc<AtomicBool>,
+    run: Option<Box<dyn FnOnce() + Send>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl VirtualOutputStream {
+    /// 打开输出文件或管道；管道不存在、文件无法创建时报错。
+    pub(crate) fn open<In, Out, C>(
+        output: &VirtualOutput,
+        options: &VirtualOutputOptions,
+        config: &cpal::StreamConfig,
+        consumer: C,
+        state: Arc<SharedState>,
+        fanout: Arc<OutputFanout>,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        In: Copy + Send + 'static,
+        Out: HwSample + cpal::FromSample<In> + Send + 'static,
+        f32: cpal::FromSample<Out>,
+        f64: cpal::FromSample<Out>,
+        C: Consumer<Item = In> + Observer<Item = In> + Send + 'static,
+    {
+        let channels = usize::from(config.channels);
+        let source_period = period_frames(config.sample_rate);
+        let (sink, info) = match output {
+            VirtualOutput::Null => (
+                Sink::Null,
+                VirtualOutputInfo {
+                    sample_rate: config.sample_rate,
+                    period_frames: source_period as u32,
+                },
+            ),
+            VirtualOutput::File(path) => {
+                let path = capture_path(path);
+                let writer = WavWriter::create(
+                    &path,
+                    config.channels,
+                    config.sample_rate,
+                    Out::FORMAT,
+                    &[],
+                )?;
+                println!("[virtual] recording to {}", path.display());
+                (
+                    Sink::File(writer),
+                    VirtualOutputInfo {
+                        sample_rate: config.sample_rate,
+                        period_frames: source_period as u32,
+                    },
+                )
+            }
+            VirtualOutput::Fifo(path) => {
+                let writer = FifoWriter::open(path, options, config)?;
+                let info = VirtualOutputInfo {
+                    sample_rate: writer.sample_rate,
+                    period_frames: writer.period_frames as u32,
+                };
+                (Sink::Fifo(Box::new(writer)), info)
+            }
+        };
+        println!(
+            "[virtual] opened {} at {} Hz, source {} Hz",
+            output.device_id(),
+            info.sample_rate,
+            config.sample_rate
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let output = OutputLoop {
+            sink,
+            period_frames: source_period,
+            channels,
+            sample_rate: config.sample_rate,
+            state,
+            fanout,
+            stop: Arc::clone(&stop),
+        };
+        Ok(Self {
+            info,
+            stop,
+            run: Some(Box::new(move || output.run::<In, Out, C>(consumer))),
+            thread: None,
+        })
+    }
+
+    pub(crate) fn info(&self) -> &VirtualOutputInfo {
+        &self.info
+    }
+
+    pub(crate) fn play(&mut self) {
+        if let Some(run) = self.run.take() {
+            self.thread = Some(std::thread::spawn(run));
+        }
+    }
+}
+
+impl Drop for VirtualOutputStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// 本次录制写入的文件：路径已被占用时依次尝试 `名称-1.wav`、`名称-2.wav`…，
+/// 换曲目或重开输出不会清掉之前录下的内容。
+fn capture_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut index = 1u32;
+    loop {
+        let candidate = path.with_file_name(format!("{stem}-{index}{extension}"));
+        if !candidate.exists() {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+fn period_frames(sample_rate: u32) -> usize {
+    ((f64::from(sample_rate) * PERIOD.as_secs_f64()).round() as usize).max(1)
+}
+
+enum Sink {
+    Null,
+    File(WavWriter),
+    Fifo(Box<FifoWriter>),
+}
+
+impl Sink {
+    /// 下一次要渲染的音源帧数。
+    fn wanted_frames(&self, period_frames: usize) -> usize {
+        match self {
+            Self::Null | Self::File(_) => period_frames,
+            Self::Fifo(writer) => writer.wanted_frames(),
+        }
+    }
+
+    /// 写入一批样本，返回它们对应的播放时长。
+    fn write<Out>(&mut self, data: &[Out], channels: usize, sample_rate: u32) -> io::Result<f64>
+    where
+        Out: HwSample,
+        f32: cpal::FromSample<Out>,
+    {
+        let source_secs = (data.len() / channels) as f64 / f64::from(sample_rate);
+        match self {
+            Self::Null => Ok(source_secs),
+            Self::File(writer) => writer.write(data).map(|_| source_secs),
+            Self::Fifo(writer) => writer.write(data),
+        }
+    }
+
+    /// 已写出但尚未“播放”的音源帧。
+    fn buffered_frames(&self) -> usize {
+        match self {
+            Self::Null | Self::File(_) => 0,
+            Self::Fifo(writer) => writer.staged.occupied_len() / writer.channels,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::File(writer) => writer.finish(),
+            Self::Null | Self::Fifo(_) => Ok(()),
+        }
+    }
+}
+
+struct OutputLoop {
+    sink: Sink,
+    period_frames: usize,
+    channels: usize,
+    sample_rate: u32,
+    state: Arc<SharedState>,
+    fanout: Arc<OutputFanout>,
+    stop: Arc<AtomicBool>,
+}
+
+impl OutputLoop {
+    fn run<In, Out, C>(mut self, mut consumer: C)
+    where
+        In: Copy,
+        Out: HwSample + cpal::FromSample<In>,
+        f32: cpal::FromSample<Out>,
+        f64: cpal::FromSample<Out>,
+        C: Consumer<Item = In> + Observer<Item = In>,
+    {
+        let mut promoted = false;
+        let mut data = Vec::new();
+        let started = Instant::now();
+        let mut played = 0.0;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            backend::promote_output_thread_once(&mut promoted, &self.state);
+            // 已写出的时长减去实际流逝的时长，即这批样本到“发声”的延迟。
+            let ahead = Duration::from_secs_f64(played).saturating_sub(started.elapsed());
+            let latency = ahead
+                + Duration::from_secs_f64(
+                    self.sink.buffered_frames() as f64 / f64::from(self.sample_rate),
+                );
+
+            let frames = self.sink.wanted_frames(self.period_frames);
+            data.resize(frames * self.channels, Out::EQUILIBRIUM);
+            backend::render_output(
+                &mut consumer,
+                &mut data,
+                &self.state,
+                self.channels,
+                &self.fanout,
+                latency,
+            );
+            match self.sink.write(&data, self.channels, self.sample_rate) {
+                Ok(secs) => played += secs,
+                Err(err) => {
+                    self.state
+                        .report_output_error(&cpal::StreamError::BackendSpecific {
+                            err: cpal::BackendSpecificError {
+                                description: err.to_string(),
+                            },
+                        });
+                    break;
+                }
+            }
+
+            let due = started + Duration::from_secs_f64(played);
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+        }
+        if let Err(err) = self.sink.finish() {
+            eprintln!("[virtual] finish output failed: {}", err);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn open_fifo(path: &Path) -> Result<File, Box<dyn std::error::Error>> {
+    use std::os::unix::fs::OpenOptionsExt;
+    #[cfg(target_os = "linux")]
+    const O_NONBLOCK: i32 = 0o4000;
+    #[cfg(not(target_os = "linux"))]
+    const O_NONBLOCK: i32 = 0x0004;
+
+    if !is_fifo(path) {
+        return Err(format!("{} 不是命名管道", path.display()).into());
+    }
+    // 以读写方式打开，没有读端时也不会阻塞或收到 EPIPE。
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(O_NONBLOCK)
+        .open(path)
+        .map_err(|err| format!("无法打开命名管道 {}：{}", path.display(), err).into())
+}
+
+#[cfg(not(unix))]
+fn open_fifo(_path: &Path) -> Result<File, Box<dyn std::error::Error>> {
+    Err("当前平台不支持命名管道输出".into())
+}
+
+/// 写进管道的整数 PCM。
+fn write_pcm(sample: f32, bits: u16, out: &mut Vec<u8>) {
+    match bits {
+        16 => out.extend_from_slice(&i16::from_sample(sample).to_le_bytes()),
+        // Snapcast 的 24 位样本放在 4 字节容器里。
+        24 => cpal::I24::from_sample(sample).write_le(false, out),
+        _ => out.extend_from_slice(&i32::from_sample(sample).to_le_bytes()),
+    }
+}
+
+/// 按管道的采样率重采样后写入整数 PCM；读端跟不上时丢弃最旧的数据。
+struct FifoWriter {
+    file: File,
+    channels: usize,
+    bits: u16,
+    sample_rate: u32,
+    ratio: f64,
+    period_frames: usize,
+    staging: HeapProd<f32>,
+    staged: HeapCons<f32>,
+    resampler: LinearResampler,
+    scratch: Vec<f32>,
+    output: Vec<f32>,
+    pending: Vec<u8>,
+    frame_bytes: usize,
+    max_pending: usize,
+}
+
+impl FifoWriter {
+    fn open(
+        path: &Path,
+        options: &VirtualOutputOptions,
+        config: &cpal::StreamConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !matches!(options.fifo_bits, 16 | 24 | 32) {
+            return Err(format!("管道输出不支持 {} 位样本", options.fifo_bits).into());
+        }
+        let file = open_fifo(path)?;
+        let channels = usize::from(config.channels).max(1);
+        let sample_rate = options.fifo_sample_rate.max(1);
+        let period_frames = period_frames(sample_rate);
+        let ratio = f64::from(config.sample_rate) / f64::from(sample_rate);
+        let staging_frames = (period_frames as f64 * ratio).ceil() as usize * 2 + 4;
+        let (staging, staged) = HeapRb::<f32>::new(staging_frames * channels).split();
+        let frame_bytes = channels * usize::from(if options.fifo_bits == 16 { 2u8 } else { 4 });
+        Ok(Self {
+            file,
+            channels,
+            bits: options.fifo_bits,
+            sample_rate,
+            ratio,
+            period_frames,
+            staging,
+            staged,
+            resampler: LinearResampler::new(channels),
+            scratch: Vec::new(),
+            output: vec![0.0; period_frames * channels],
+            pending: Vec::new(),
+            frame_bytes,
+            max_pending: (MAX_FIFO_BACKLOG.as_secs_f64() * f64::from(sample_rate)) as usize
+                * frame_bytes,
+        })
+    }
+
+    /// 补足一个 period 要用的音源帧，多留两帧给插值。
+    fn wanted_frames(&self) -> usize {
+        let staged = self.staged.occupied_len() / self.channels;
+        let wanted = (self.period_frames as f64 * self.ratio).ceil() as usize + 2;
+        wanted
+            .saturating_sub(staged)
+            .min(self.staging.vacant_len() / self.channels)
+    }
+
+    fn write<Out>(&mut self, data: &[Out]) -> io::Result<f64>
+    where
+        Out: Copy,
+        f32: cpal::FromSample<Out>,
+    {
+        self.scratch.clear();
+        self.scratch
+            .extend(data.iter().map(|sample| f32::from_sample(*sample)));
+        self.staging.push_slice(&self.scratch);
+        self.resampler.process(
+            &mut self.staged,
+            &mut self.output,
+            self.channels,
+            self.ratio,
+        );
+        for sample in &self.output {
+            write_pcm(*sample, self.bits, &mut self.pending);
+        }
+        self.flush()?;
+        Ok(self.period_frames as f64 / f64::from(self.sample_rate))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.file.write(&self.pending) {
+                Ok(0) => break,
+                Ok(written) => {
+                    self.pending.drain(..written);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let excess = trimmed_backlog(self.pending.len(), self.max_pending, self.frame_bytes);
+        self.pending.drain(..excess);
+        Ok(())
+    }
+}
+
+/// 积压超过上限时要丢弃的字节数，按整帧丢弃以免读端错位。
+fn trimmed_backlog(pending: usize, max_pending: usize, frame_bytes: usize) -> usize {
+    if pending <= max_pending || frame_bytes == 0 {
+        return 0;
+    }
+    (pending - max_pending).div_ceil(frame_bytes) * frame_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_ids_select_the_virtual_output() {
+        assert_eq!(VirtualOutput::parse("null:"), Some(VirtualOutput::Null));
+        assert_eq!(
+            VirtualOutput::parse("file:/tmp/out.wav"),
+            Some(VirtualOutput::File(PathBuf::from("/tmp/out.wav")))
+        );
+        assert_eq!(
+            VirtualOutput::parse("fifo:"),
+            Some(VirtualOutput::Fifo(PathBuf::from(DEFAULT_FIFO_PATH)))
+        );
+        assert!(!is_virtual_device("hw:CARD=0,DEV=0"));
+        assert!(!is_virtual_device("jack:system"));
+    }
+
+    #[test]
+    fn current_virtual_output_is_always_listed() {
+        let options = VirtualOutputOptions {
+            file_path: Some("/tmp/a.wav".to_string()),
+            fifo_path: "/nonexistent/snapfifo".to_string(),
+            ..VirtualOutputOptions::default()
+        };
+        let devices = output_devices(&options, Some("file:/tmp/b.wav"));
+        let ids: Vec<&str> = devices.iter().map(|device| device.id.as_str()).collect();
+        assert_eq!(ids, vec!["null:", "file:/tmp/a.wav", "file:/tmp/b.wav"]);
+        assert!(devices[2].is_current);
+        assert!(!devices[0].is_current);
+    }
+
+    #[test]
+    fn file_output_keeps_the_source_format() {
+        let file = VirtualOutput::File(PathBuf::from("/tmp/out.wav"));
+        let (config, format) = stream_config(
+            &file,
+            96_000,
+            2,
+            &[cpal::SampleFormat::I24, cpal::SampleFormat::I32],
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(config.sample_rate, 96_000);
+        assert_eq!(format, cpal::SampleFormat::I24);
+
+        let (_, format) =
+            stream_config(&file, 44_100, 2, &[cpal::SampleFormat::U16], false, false).unwrap();
+        assert_eq!(format, cpal::SampleFormat::F32);
+        let (_, format) = stream_config(
+            &VirtualOutput::Null,
+            44_100,
+            2,
+            &[cpal::SampleFormat::I16],
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(format, cpal::SampleFormat::F32);
+        assert!(stream_config(&VirtualOutput::Null, 44_100, 2, &[], true, false).is_err());
+    }
+
+    #[test]
+    fn file_output_never_overwrites_an_earlier_capture() {
+        let dir = std::env::temp_dir().join(format!("virtual-capture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.wav");
+
+        assert_eq!(capture_path(&path), path);
+        std::fs::write(&path, b"first").unwrap();
+        assert_eq!(capture_path(&path), dir.join("out-1.wav"));
+        std::fs::write(dir.join("out-1.wav"), b"second").unwrap();
+        assert_eq!(capture_path(&path), dir.join("out-2.wav"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fifo_backlog_is_trimmed_by_whole_frames() {
+        assert_eq!(trimmed_backlog(100, 200, 4), 0);
+        assert_eq!(trimmed_backlog(205, 200, 4), 8);
+        assert_eq!(trimmed_backlog(300, 200, 0), 0);
+    }
+}
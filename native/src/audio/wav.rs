@@ -0,0 +1,287 @@
+//! WAV/RF64 文件写入，供 `file:` 虚拟输出与离线导出共用。文件头里预留 `JUNK` 块，
+//! 写完数据超过 4 GiB 时原地改写为 RF64 的 `ds64` 块。
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::audio::alsa_hw::HwSample;
+
+/// WAV 文件可写的样本格式，`file:` 输出按音源格式就近挑选，否则写 F32。
+pub(crate) const WAV_FORMATS: [cpal::SampleFormat; 6] = [
+    cpal::SampleFormat::U8,
+    cpal::SampleFormat::I16,
+    cpal::SampleFormat::I24,
+    cpal::SampleFormat::I32,
+    cpal::SampleFormat::F32,
+    cpal::SampleFormat::F64,
+];
+
+/// WAV 的格式标签与位深；24 位按 3 字节紧凑存放。
+fn wav_format(format: cpal::SampleFormat) -> Option<(u16, u16)> {
+    const PCM: u16 = 1;
+    const IEEE_FLOAT: u16 = 3;
+    match format {
+        cpal::SampleFormat::U8 => Some((PCM, 8)),
+        cpal::SampleFormat::I16 => Some((PCM, 16)),
+        cpal::SampleFormat::I24 => Some((PCM, 24)),
+        cpal::SampleFormat::I32 => Some((PCM, 32)),
+        cpal::SampleFormat::F32 => Some((IEEE_FLOAT, 32)),
+        cpal::SampleFormat::F64 => Some((IEEE_FLOAT, 64)),
+        _ => None,
+    }
+}
+
+/// `fmt ` 块；多于两声道或高于 16 位时用 WAVE_FORMAT_EXTENSIBLE。
+fn fmt_chunk(tag: u16, bits: u16, channels: u16, sample_rate: u32) -> Vec<u8> {
+    let block_align = channels * (bits / 8);
+    let extensible = channels > 2 || bits > 16;
+    let mut chunk = Vec::with_capacity(48);
+    chunk.extend_from_slice(b"fmt ");
+    chunk.extend_from_slice(&(if extensible { 40u32 } else { 16 }).to_le_bytes());
+    chunk.extend_from_slice(&(if extensible { 0xfffeu16 } else { tag }).to_le_bytes());
+    chunk.extend_from_slice(&channels.to_le_bytes());
+    chunk.extend_from_slice(&sample_rate.to_le_bytes());
+    chunk.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    chunk.extend_from_slice(&block_align.to_le_bytes());
+    chunk.extend_from_slice(&bits.to_le_bytes());
+    if extensible {
+        let channel_mask = if channels <= 18 {
+            (1u32 << channels) - 1
+        } else {
+            0
+        };
+        chunk.extend_from_slice(&22u16.to_le_bytes());
+        chunk.extend_from_slice(&bits.to_le_bytes());
+        chunk.extend_from_slice(&channel_mask.to_le_bytes());
+        chunk.extend_from_slice(&u32::from(tag).to_le_bytes());
+        chunk.extend_from_slice(&[
+            0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+        ]);
+    }
+    chunk
+}
+
+/// Vorbis 注释风格的标签名对应的 RIFF INFO 字段。
+fn info_id(key: &str) -> Option<&'static [u8; 4]> {
+    Some(match key {
+        "TITLE" => b"INAM",
+        "ARTIST" => b"IART",
+        "ALBUM" => b"IPRD",
+        "DATE" => b"ICRD",
+        "GENRE" => b"IGNR",
+        "COMMENT" => b"ICMT",
+        "TRACKNUMBER" => b"ITRK",
+        "COPYRIGHT" => b"ICOP",
+        "ENCODER" => b"ISFT",
+        _ => return None,
+    })
+}
+
+/// `LIST`/`INFO` 块；没有可写的标签时为空。
+fn info_chunk(tags: &[(String, String)]) -> Vec<u8> {
+    let mut info = Vec::new();
+    for (key, value) in tags {
+        let Some(id) = info_id(key) else {
+            continue;
+        };
+        if value.is_empty() || info.windows(4).any(|window| window == id) {
+            continue;
+        }
+        let len = value.len() + 1;
+        info.extend_from_slice(id);
+        info.extend_from_slice(&(len as u32).to_le_bytes());
+        info.extend_from_slice(value.as_bytes());
+        info.push(0);
+        if len % 2 == 1 {
+            info.push(0);
+        }
+    }
+    if info.is_empty() {
+        return info;
+    }
+    let mut chunk = Vec::with_capacity(info.len() + 12);
+    chunk.extend_from_slice(b"LIST");
+    chunk.extend_from_slice(&(info.len() as u32 + 4).to_le_bytes());
+    chunk.extend_from_slice(b"INFO");
+    chunk.extend_from_slice(&info);
+    chunk
+}
+
+/// `ds64` 块的大小；RIFF 头里先以同样大小的 `JUNK` 块占位，需要时原地改写为 RF64。
+const DS64_LEN: usize = 28;
+
+/// 写完数据后要回填的文件头：放得下 32 位大小时是普通 RIFF，否则是 RF64。
+fn finished_header(header: &[u8], data_bytes: u64, frames: u64) -> Vec<u8> {
+    let mut header = header.to_vec();
+    let riff_bytes = header.len() as u64 - 8 + data_bytes + data_bytes % 2;
+    let data_size_at = header.len() - 4;
+    if riff_bytes <= u64::from(u32::MAX) {
+        header[4..8].copy_from_slice(&(riff_bytes as u32).to_le_bytes());
+        header[data_size_at..].copy_from_slice(&(data_bytes as u32).to_le_bytes());
+        return header;
+    }
+
+    header[0..4].copy_from_slice(b"RF64");
+    header[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    header[12..16].copy_from_slice(b"ds64");
+    let mut ds64 = Vec::with_capacity(DS64_LEN);
+    ds64.extend_from_slice(&riff_bytes.to_le_bytes());
+    ds64.extend_from_slice(&data_bytes.to_le_bytes());
+    ds64.extend_from_slice(&frames.to_le_bytes());
+    ds64.extend_from_slice(&0u32.to_le_bytes());
+    header[20..20 + DS64_LEN].copy_from_slice(&ds64);
+    header[data_size_at..].copy_from_slice(&u32::MAX.to_le_bytes());
+    header
+}
+
+pub(crate) struct WavWriter {
+    file: BufWriter<File>,
+    header: Vec<u8>,
+    frame_bytes: u64,
+    data_bytes: u64,
+    bytes: Vec<u8>,
+}
+
+impl WavWriter {
+    /// 创建文件并写入占位的文件头；`tags` 中能对应 INFO 字段的写进 `LIST` 块。
+    pub(crate) fn create(
+        path: &Path,
+        channels: u16,
+        sample_rate: u32,
+        format: cpal::SampleFormat,
+        tags: &[(String, String)],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if path.as_os_str().is_empty() {
+            return Err("未指定输出文件路径".into());
+        }
+        let (tag, bits) =
+            wav_format(format).ok_or_else(|| format!("WAV 文件不支持 {} 样本", format))?;
+        let file = File::create(path)
+            .map_err(|err| format!("无法创建输出文件 {}：{}", path.display(), err))?;
+
+        let mut header = Vec::with_capacity(80);
+        header.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        header.extend_from_slice(b"JUNK");
+        header.extend_from_slice(&(DS64_LEN as u32).to_le_bytes());
+        header.extend_from_slice(&[0; DS64_LEN]);
+        header.extend_from_slice(&fmt_chunk(tag, bits, channels, sample_rate));
+        header.extend_from_slice(&info_chunk(tags));
+        header.extend_from_slice(b"data\0\0\0\0");
+        let header = finished_header(&header, 0, 0);
+
+        let mut file = BufWriter::new(file);
+        file.write_all(&header)?;
+        Ok(Self {
+            file,
+            header,
+            frame_bytes: u64::from(channels) * u64::from(bits / 8),
+            data_bytes: 0,
+            bytes: Vec::new(),
+        })
+    }
+
+    pub(crate) fn write<S: HwSample>(&mut self, data: &[S]) -> io::Result<()> {
+        let mut bytes = std::mem::take(&mut self.bytes);
+        bytes.clear();
+        for sample in data {
+            sample.write_le(true, &mut bytes);
+        }
+        let written = self.write_bytes(&bytes);
+        self.bytes = bytes;
+        written
+    }
+
+    /// 写入已按文件格式排好的小端样本字节。
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.file.write_all(bytes)?;
+        self.data_bytes += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// 补齐 data 块并回填文件头中的大小。
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        if self.data_bytes % 2 == 1 {
+            self.file.write_all(&[0])?;
+        }
+        let frames = self.data_bytes / self.frame_bytes.max(1);
+        let header = finished_header(&self.header, self.data_bytes, frames);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_file_round_trips_samples_and_header() {
+        let path = std::env::temp_dir().join(format!("ncm-wav-{}.wav", std::process::id()));
+        let mut writer = WavWriter::create(&path, 2, 44_100, cpal::SampleFormat::I16, &[]).unwrap();
+        writer.write(&[1i16, -1, 0x1234, 0]).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize,
+            bytes.len() - 8
+        );
+        assert_eq!(&bytes[12..16], b"JUNK");
+        let data_at = bytes.len() - 8;
+        assert_eq!(&bytes[data_at - 8..data_at - 4], b"data");
+        assert_eq!(
+            u32::from_le_bytes(bytes[data_at - 4..data_at].try_into().unwrap()),
+            8
+        );
+        assert_eq!(&bytes[data_at..], &[1, 0, 0xff, 0xff, 0x34, 0x12, 0, 0]);
+    }
+
+    #[test]
+    fn oversized_wav_switches_to_rf64() {
+        let header = {
+            let mut header = b"RIFF\0\0\0\0WAVEJUNK".to_vec();
+            header.extend_from_slice(&(DS64_LEN as u32).to_le_bytes());
+            header.extend_from_slice(&[0; DS64_LEN]);
+            header.extend_from_slice(&fmt_chunk(1, 16, 2, 44_100));
+            header.extend_from_slice(b"data\0\0\0\0");
+            header
+        };
+        let data_bytes = 5u64 << 30;
+        let patched = finished_header(&header, data_bytes, data_bytes / 4);
+        assert_eq!(&patched[0..4], b"RF64");
+        assert_eq!(&patched[12..16], b"ds64");
+        assert_eq!(
+            u64::from_le_bytes(patched[28..36].try_into().unwrap()),
+            data_bytes
+        );
+        assert_eq!(
+            u64::from_le_bytes(patched[36..44].try_into().unwrap()),
+            data_bytes / 4
+        );
+        assert_eq!(&patched[patched.len() - 4..], &u32::MAX.to_le_bytes());
+    }
+
+    #[test]
+    fn known_tags_go_into_an_even_padded_info_list() {
+        let tags = vec![
+            ("TITLE".to_string(), "晴天".to_string()),
+            ("REPLAYGAIN_TRACK_GAIN".to_string(), "-6.2 dB".to_string()),
+            ("ARTIST".to_string(), "周杰伦".to_string()),
+        ];
+        let chunk = info_chunk(&tags);
+        assert_eq!(&chunk[0..4], b"LIST");
+        assert_eq!(&chunk[8..12], b"INFO");
+        assert_eq!(
+            u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as usize,
+            chunk.len() - 8
+        );
+        assert_eq!(chunk.len() % 2, 0);
+        assert!(chunk.windows(4).any(|window| window == b"INAM"));
+        assert!(chunk.windows(4).any(|window| window == b"IART"));
+        assert!(info_chunk(&[]).is_empty());
+    }
+}
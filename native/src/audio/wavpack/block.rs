@@ -0,0 +1,227 @@
+//! WavPack block 头与 metadata 子块。
+
+pub(crate) const HEADER_LEN: usize = 32;
+/// 单个 block 的上限，用于在扫描时排除误匹配的 "wvpk"。
+const MAX_BLOCK_LEN: u32 = 16 << 20;
+const MIN_STREAM_VERSION: u16 = 0x402;
+const MAX_STREAM_VERSION: u16 = 0x410;
+
+pub(crate) const BYTES_STORED: u32 = 3;
+pub(crate) const MONO_FLAG: u32 = 4;
+pub(crate) const HYBRID_FLAG: u32 = 8;
+pub(crate) const JOINT_STEREO: u32 = 0x10;
+pub(crate) const CROSS_DECORR: u32 = 0x20;
+pub(crate) const HYBRID_SHAPE: u32 = 0x40;
+pub(crate) const FLOAT_DATA: u32 = 0x80;
+pub(crate) const INT32_DATA: u32 = 0x100;
+pub(crate) const HYBRID_BITRATE: u32 = 0x200;
+pub(crate) const HYBRID_BALANCE: u32 = 0x400;
+pub(crate) const INITIAL_BLOCK: u32 = 0x800;
+pub(crate) const FINAL_BLOCK: u32 = 0x1000;
+pub(crate) const SHIFT_LSB: u32 = 13;
+pub(crate) const MAG_LSB: u32 = 18;
+const SRATE_LSB: u32 = 23;
+pub(crate) const NEW_SHAPING: u32 = 0x2000_0000;
+pub(crate) const FALSE_STEREO: u32 = 0x4000_0000;
+pub(crate) const DSD_FLAG: u32 = 0x8000_0000;
+pub(crate) const MONO_DATA: u32 = MONO_FLAG | FALSE_STEREO;
+
+pub(crate) const ID_OPTIONAL_DATA: u8 = 0x20;
+const ID_ODD_SIZE: u8 = 0x40;
+const ID_LARGE: u8 = 0x80;
+pub(crate) const ID_DUMMY: u8 = 0x0;
+pub(crate) const ID_DECORR_TERMS: u8 = 0x2;
+pub(crate) const ID_DECORR_WEIGHTS: u8 = 0x3;
+pub(crate) const ID_DECORR_SAMPLES: u8 = 0x4;
+pub(crate) const ID_ENTROPY_VARS: u8 = 0x5;
+pub(crate) const ID_HYBRID_PROFILE: u8 = 0x6;
+pub(crate) const ID_SHAPING_WEIGHTS: u8 = 0x7;
+pub(crate) const ID_FLOAT_INFO: u8 = 0x8;
+pub(crate) const ID_INT32_INFO: u8 = 0x9;
+pub(crate) const ID_WV_BITSTREAM: u8 = 0xa;
+pub(crate) const ID_WVC_BITSTREAM: u8 = 0xb;
+pub(crate) const ID_WVX_BITSTREAM: u8 = 0xc;
+pub(crate) const ID_CHANNEL_INFO: u8 = 0xd;
+pub(crate) const ID_DSD_BLOCK: u8 = 0xe;
+pub(crate) const ID_SAMPLE_RATE: u8 = ID_OPTIONAL_DATA | 0x7;
+
+const SAMPLE_RATES: [u32; 15] = [
+    6_000, 8_000, 9_600, 11_025, 12_000, 16_000, 22_050, 24_000, 32_000, 44_100, 48_000, 64_000,
+    88_200, 96_000, 192_000,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlockHeader {
+    /// 含 32 字节头的完整 block 长度。
+    pub(crate) block_len: usize,
+    pub(crate) version: u16,
+    pub(crate) total_samples: Option<u64>,
+    pub(crate) block_index: u64,
+    pub(crate) block_samples: u32,
+    pub(crate) flags: u32,
+    pub(crate) crc: u32,
+}
+
+impl BlockHeader {
+    pub(crate) fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < HEADER_LEN || &buf[..4] != b"wvpk" {
+            return None;
+        }
+        let ck_size = read_u32(buf, 4);
+        let version = u16::from_le_bytes([buf[8], buf[9]]);
+        if !(24..=MAX_BLOCK_LEN).contains(&ck_size)
+            || !(MIN_STREAM_VERSION..=MAX_STREAM_VERSION).contains(&version)
+        {
+            return None;
+        }
+
+        let total_low = read_u32(buf, 12);
+        let total_high = u64::from(buf[11]);
+        let total_samples =
+            (total_low != u32::MAX).then(|| u64::from(total_low) + (total_high << 32) - total_high);
+        Some(Self {
+            block_len: ck_size as usize + 8,
+            version,
+            total_samples,
+            block_index: u64::from(read_u32(buf, 16)) + (u64::from(buf[10]) << 32),
+            block_samples: read_u32(buf, 20),
+            flags: read_u32(buf, 24),
+            crc: read_u32(buf, 28),
+        })
+    }
+
+    /// block 解出的声道数；FALSE_STEREO 数据按双声道输出。
+    pub(crate) fn channels(&self) -> usize {
+        if self.flags & MONO_FLAG != 0 { 1 } else { 2 }
+    }
+
+    pub(crate) fn bytes_per_sample(&self) -> u32 {
+        (self.flags & BYTES_STORED) + 1
+    }
+
+    pub(crate) fn bits_per_sample(&self) -> u32 {
+        self.bytes_per_sample() * 8 - ((self.flags >> SHIFT_LSB) & 0x1f)
+    }
+
+    /// 头部索引表中的采样率；索引 15 表示需要读 `ID_SAMPLE_RATE` 子块。
+    pub(crate) fn sample_rate(&self) -> Option<u32> {
+        SAMPLE_RATES
+            .get(((self.flags >> SRATE_LSB) & 0xf) as usize)
+            .copied()
+    }
+
+    pub(crate) fn is_initial(&self) -> bool {
+        self.flags & INITIAL_BLOCK != 0
+    }
+
+    pub(crate) fn is_final(&self) -> bool {
+        self.flags & FINAL_BLOCK != 0
+    }
+
+    pub(crate) fn is_float(&self) -> bool {
+        self.flags & FLOAT_DATA != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SubBlock<'a> {
+    pub(crate) id: u8,
+    pub(crate) data: &'a [u8],
+}
+
+/// 遍历 block 内的 metadata 子块。
+pub(crate) struct SubBlocks<'a> {
+    rest: &'a [u8],
+}
+
+pub(crate) fn sub_blocks<'a>(block: &'a [u8], header: &BlockHeader) -> SubBlocks<'a> {
+    let end = header.block_len.min(block.len());
+    SubBlocks {
+        rest: block.get(HEADER_LEN..end).unwrap_or_default(),
+    }
+}
+
+impl<'a> Iterator for SubBlocks<'a> {
+    type Item = Result<SubBlock<'a>, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.len() < 2 {
+            return None;
+        }
+        let raw_id = self.rest[0];
+        let mut len = usize::from(self.rest[1]) << 1;
+        let mut offset = 2;
+        if raw_id & ID_LARGE != 0 {
+            if self.rest.len() < 4 {
+                self.rest = &[];
+                return Some(Err("wavpack: truncated metadata header"));
+            }
+            len += (usize::from(self.rest[2]) << 9) + (usize::from(self.rest[3]) << 17);
+            offset = 4;
+        }
+        if raw_id & ID_ODD_SIZE != 0 {
+            if len == 0 {
+                self.rest = &[];
+                return Some(Err("wavpack: invalid metadata size"));
+            }
+            len -= 1;
+        }
+        let padded = len + (len & 1);
+        if self.rest.len() - offset < padded {
+            self.rest = &[];
+            return Some(Err("wavpack: truncated metadata"));
+        }
+        let data = &self.rest[offset..offset + len];
+        self.rest = &self.rest[offset + padded..];
+        Some(Ok(SubBlock {
+            id: raw_id & !(ID_LARGE | ID_ODD_SIZE),
+            data,
+        }))
+    }
+}
+
+/// `ID_CHANNEL_INFO`：总声道数与 WAVEFORMATEX 声道掩码。
+pub(crate) fn parse_channel_info(data: &[u8]) -> Option<(u32, u32)> {
+    match data.len() {
+        0 | 8.. => None,
+        6 | 7 => {
+            let channels = (u32::from(data[0]) | (u32::from(data[2] & 0xf) << 8)) + 1;
+            let mut mask =
+                u32::from(data[3]) | (u32::from(data[4]) << 8) | (u32::from(data[5]) << 16);
+            if data.len() == 7 {
+                mask |= u32::from(data[6]) << 24;
+            }
+            Some((channels, mask))
+        }
+        _ => {
+            let mask = data[1..]
+                .iter()
+                .enumerate()
+                .fold(0u32, |mask, (i, byte)| mask | (u32::from(*byte) << (8 * i)));
+            Some((u32::from(data[0]), mask))
+        }
+    }
+}
+
+/// `ID_SAMPLE_RATE`：非标准采样率。
+pub(crate) fn parse_sample_rate(data: &[u8]) -> Option<u32> {
+    match data.len() {
+        3 => Some(u32::from(data[0]) | (u32::from(data[1]) << 8) | (u32::from(data[2]) << 16)),
+        4 => Some(
+            u32::from(data[0])
+                | (u32::from(data[1]) << 8)
+                | (u32::from(data[2]) << 16)
+                | (u32::from(data[3] & 0x7f) << 24),
+        ),
+        _ => None,
+    }
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
@@ -0,0 +1,715 @@
+//! WavPack（.wv）容器与解码器，支持混合模式下配套的 .wvc 校正文件。
+
+mod block;
+mod unpack;
+
+use crate::audio::ape::{channels_from_count, read_up_to};
+use block::{BlockHeader, HEADER_LEN, ID_CHANNEL_INFO, ID_SAMPLE_RATE, sub_blocks};
+use std::io::{Seek, SeekFrom};
+use symphonia::core::audio::{
+    AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
+};
+use symphonia::core::codecs::{
+    CODEC_TYPE_WAVPACK, CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult,
+};
+use symphonia::core::errors::{
+    Error, Result, SeekErrorKind, decode_error, seek_error, unsupported_error,
+};
+use symphonia::core::formats::{
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+};
+use symphonia::core::io::{MediaSourceStream, ReadBytes};
+use symphonia::core::meta::{Metadata, MetadataLog};
+use symphonia::core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia::core::sample::{Sample, SampleFormat};
+use symphonia::core::units::TimeBase;
+use symphonia::core::{support_codec, support_format};
+
+/// 一个 frame：同一 block_index 下从 INITIAL 到 FINAL 的若干 block（多声道时每块 1~2 声道）。
+#[derive(Debug, Clone, Copy)]
+struct FrameEntry {
+    offset: u64,
+    block_index: u64,
+    block_samples: u32,
+}
+
+/// 顺序读取 frame，并在读取/扫描时建立 frame 索引供 seek 使用。
+struct BlockStream {
+    source: MediaSourceStream,
+    /// 已知的含音频 frame，按文件顺序。
+    index: Vec<FrameEntry>,
+    /// 索引覆盖范围之后的第一个字节。
+    scan_pos: u64,
+    /// 下一个要读取的 frame 位置。
+    pos: u64,
+}
+
+impl BlockStream {
+    fn new(source: MediaSourceStream) -> Self {
+        let pos = source.pos();
+        Self {
+            source,
+            index: Vec::new(),
+            scan_pos: pos,
+            pos,
+        }
+    }
+
+    fn read_header(&mut self, offset: u64) -> Result<Option<BlockHeader>> {
+        if self.source.pos() != offset {
+            self.source.seek(SeekFrom::Start(offset))?;
+        }
+        let mut buf = [0u8; HEADER_LEN];
+        if read_up_to(&mut self.source, &mut buf)? < HEADER_LEN {
+            return Ok(None);
+        }
+        // 文件末尾的 APEv2/ID3 标签等非 block 数据视为流结束。
+        Ok(BlockHeader::parse(&buf))
+    }
+
+    /// 读取当前位置的下一个含音频 frame；跳过只有 metadata 的 block。
+    fn read_frame(&mut self) -> Result<Option<(FrameEntry, Vec<u8>)>> {
+        loop {
+            let offset = self.pos;
+            let Some(first) = self.read_header(offset)? else {
+                return Ok(None);
+            };
+
+            let mut data = Vec::new();
+            let mut header = first;
+            let mut end = offset;
+            loop {
+                let start = data.len();
+                data.resize(start + header.block_len, 0);
+                if self.source.pos() != end {
+                    self.source.seek(SeekFrom::Start(end))?;
+                }
+                if read_up_to(&mut self.source, &mut data[start..])? < header.block_len {
+                    return Ok(None);
+                }
+                end += header.block_len as u64;
+                if header.is_final() {
+                    break;
+                }
+                header = match self.read_header(end)? {
+                    Some(next) if next.block_index == first.block_index && !next.is_initial() => {
+                        next
+                    }
+                    _ => return decode_error("wavpack: incomplete multichannel frame"),
+                };
+            }
+
+            self.pos = end;
+            let entry = FrameEntry {
+                offset,
+                block_index: first.block_index,
+                block_samples: first.block_samples,
+            };
+            self.record(entry, end);
+            if entry.block_samples > 0 {
+                return Ok(Some((entry, data)));
+            }
+        }
+    }
+
+    /// 只读 block 头向后扫描一个 frame，扩展索引。
+    fn scan_next(&mut self) -> Result<bool> {
+        let offset = self.scan_pos;
+        let Some(first) = self.read_header(offset)? else {
+            return Ok(false);
+        };
+        let mut header = first;
+        let mut end = offset + header.block_len as u64;
+        while !header.is_final() {
+            let Some(next) = self.read_header(end)? else {
+                return Ok(false);
+            };
+            end += next.block_len as u64;
+            header = next;
+        }
+        let entry = FrameEntry {
+            offset,
+            block_index: first.block_index,
+            block_samples: first.block_samples,
+        };
+        self.record(entry, end);
+        Ok(true)
+    }
+
+    fn record(&mut self, entry: FrameEntry, end: u64) {
+        if entry.offset == self.scan_pos {
+            if entry.block_samples > 0 {
+                self.index.push(entry);
+            }
+            self.scan_pos = end;
+        }
+    }
+
+    /// 定位到包含 `ts` 的 frame（或其后第一个 frame）。
+    fn seek_frame(&mut self, ts: u64) -> Result<Option<FrameEntry>> {
+        loop {
+            let found = self
+                .index
+                .partition_point(|entry| entry.block_index + u64::from(entry.block_samples) <= ts);
+            if let Some(&entry) = self.index.get(found) {
+                self.pos = entry.offset;
+                return Ok(Some(entry));
+            }
+            if !self.scan_next()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// 读取与主文件 frame 对齐的校正 frame；校正文件缺这一帧时返回 `None`。
+    fn frame_at(&mut self, block_index: u64) -> Result<Option<Vec<u8>>> {
+        loop {
+            let Some(header) = self.read_header(self.pos)? else {
+                return Ok(None);
+            };
+            if header.block_index > block_index {
+                return Ok(None);
+            }
+            match self.read_frame()? {
+                Some((entry, data)) if entry.block_index == block_index => return Ok(Some(data)),
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// WavPack 容器。每个 packet 是一个 frame：`[u32 主数据长度][.wv blocks][.wvc blocks]`。
+pub(crate) struct WavPackReader {
+    wv: BlockStream,
+    wvc: Option<BlockStream>,
+    /// 打开时为读取流参数预读的第一帧。
+    pending: Option<(FrameEntry, Vec<u8>)>,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    sample_rate: u32,
+    total_samples: Option<u64>,
+}
+
+impl WavPackReader {
+    /// 同时读取 .wv 与对应的 .wvc 校正文件，输出无损数据。
+    pub(crate) fn with_correction(
+        source: MediaSourceStream,
+        correction: MediaSourceStream,
+        _options: &FormatOptions,
+    ) -> Result<Self> {
+        Self::open(source, Some(correction))
+    }
+
+    fn open(source: MediaSourceStream, correction: Option<MediaSourceStream>) -> Result<Self> {
+        let mut wv = BlockStream::new(source);
+        let Some((entry, data)) = wv.read_frame()? else {
+            return unsupported_error("wavpack: no audio blocks found");
+        };
+
+        let mut layout = StreamLayout::default();
+        let mut rest = data.as_slice();
+        while let Some(header) = BlockHeader::parse(rest) {
+            layout.add_block(&header, &rest[..header.block_len])?;
+            rest = &rest[header.block_len..];
+        }
+        let Some(sample_rate) = layout.sample_rate.filter(|rate| *rate > 0) else {
+            return unsupported_error("wavpack: unknown sample rate");
+        };
+        let channels = layout.channels()?;
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_WAVPACK)
+            .with_sample_rate(sample_rate)
+            .with_time_base(TimeBase::new(1, sample_rate))
+            .with_bits_per_sample(layout.bits_per_sample)
+            .with_channels(channels)
+            .with_max_frames_per_packet(u64::from(entry.block_samples))
+            .with_sample_format(if layout.float {
+                SampleFormat::F32
+            } else {
+                SampleFormat::S32
+            });
+        if let Some(total_samples) = layout.total_samples {
+            params.with_n_frames(total_samples);
+        }
+
+        Ok(Self {
+            wv,
+            wvc: correction.map(BlockStream::new),
+            pending: Some((entry, data)),
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            sample_rate,
+            total_samples: layout.total_samples,
+        })
+    }
+}
+
+/// 从第一帧的各个 block 汇总出的流参数。
+#[derive(Debug, Default)]
+struct StreamLayout {
+    block_channels: u32,
+    channel_info: Option<(u32, u32)>,
+    sample_rate: Option<u32>,
+    bits_per_sample: u32,
+    float: bool,
+    total_samples: Option<u64>,
+}
+
+impl StreamLayout {
+    fn add_block(&mut self, header: &BlockHeader, block: &[u8]) -> Result<()> {
+        if header.is_initial() {
+            self.sample_rate = header.sample_rate();
+            self.bits_per_sample = header.bits_per_sample();
+            self.float = header.is_float();
+            self.total_samples = header.total_samples;
+        }
+        self.block_channels += header.channels() as u32;
+        for sub_block in sub_blocks(block, header) {
+            let sub_block = sub_block.or_else(decode_error)?;
+            match sub_block.id {
+                ID_CHANNEL_INFO if header.is_initial() => {
+                    self.channel_info = block::parse_channel_info(sub_block.data);
+                }
+                ID_SAMPLE_RATE if header.is_initial() => {
+                    self.sample_rate =
+                        block::parse_sample_rate(sub_block.data).or(self.sample_rate);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn channels(&self) -> Result<Channels> {
+        let count = self
+            .channel_info
+            .map_or(self.block_channels, |(count, _)| count);
+        if count == 0 || count != self.block_channels {
+            return unsupported_error("wavpack: unsupported channel layout");
+        }
+        let mask = self.channel_info.map_or(0, |(_, mask)| mask);
+        Ok(
+            match Channels::from_bits(mask).filter(|channels| channels.count() == count as usize) {
+                Some(channels) => channels,
+                None => channels_from_count(count as u16),
+            },
+        )
+    }
+}
+
+impl QueryDescriptor for WavPackReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "wavpack",
+            "WavPack",
+            &["wv"],
+            &["audio/wavpack", "audio/x-wavpack"],
+            &[b"wvpk"]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for WavPackReader {
+    fn try_new(source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        Self::open(source, None)
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => TimeBase::new(1, self.sample_rate).calc_timestamp(time),
+        };
+        if self.total_samples.is_some_and(|total| ts >= total) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        self.pending = None;
+        let Some(entry) = self.wv.seek_frame(ts)? else {
+            return seek_error(SeekErrorKind::OutOfRange);
+        };
+        if let Some(wvc) = self.wvc.as_mut() {
+            wvc.seek_frame(entry.block_index)?;
+        }
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts: ts,
+            actual_ts: entry.block_index,
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let next = match self.pending.take() {
+            Some(frame) => Some(frame),
+            None => self.wv.read_frame()?,
+        };
+        let Some((entry, wv_data)) = next else {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        };
+        let wvc_data = match self.wvc.as_mut() {
+            Some(wvc) => wvc.frame_at(entry.block_index)?.unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let mut data = Vec::with_capacity(4 + wv_data.len() + wvc_data.len());
+        data.extend_from_slice(&(wv_data.len() as u32).to_le_bytes());
+        data.extend_from_slice(&wv_data);
+        data.extend_from_slice(&wvc_data);
+        Ok(Packet::new_from_boxed_slice(
+            0,
+            entry.block_index,
+            u64::from(entry.block_samples),
+            data.into_boxed_slice(),
+        ))
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.wv.source
+    }
+}
+
+enum SampleBuffer {
+    Int(AudioBuffer<i32>),
+    Float(AudioBuffer<f32>),
+}
+
+/// WavPack 解码器：逐 block 解包，按顺序把各 block 的声道排进输出。
+pub(crate) struct WavPackDecoder {
+    params: CodecParameters,
+    buf: SampleBuffer,
+}
+
+impl Decoder for WavPackDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (Some(sample_rate), Some(channels)) = (params.sample_rate, params.channels) else {
+            return unsupported_error("wavpack: missing stream parameters");
+        };
+        let max_frames = params.max_frames_per_packet.unwrap_or(22_050);
+        let spec = SignalSpec::new(sample_rate, channels);
+        let buf = if matches!(params.sample_format, Some(SampleFormat::F32)) {
+            SampleBuffer::Float(AudioBuffer::new(max_frames, spec))
+        } else {
+            SampleBuffer::Int(AudioBuffer::new(max_frames, spec))
+        };
+        Ok(Self {
+            params: params.clone(),
+            buf,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_WAVPACK, "wavpack", "WavPack")]
+    }
+
+    fn reset(&mut self) {
+        // 每个 block 自带完整的解码状态，无需重置。
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let data = packet.buf();
+        if data.len() < 4 {
+            return decode_error("wavpack: truncated packet");
+        }
+        let wv_len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let Some(wv) = data.get(4..4 + wv_len) else {
+            return decode_error("wavpack: truncated packet");
+        };
+        let correction = split_blocks(&data[4 + wv_len..])?;
+        let blocks = split_blocks(wv)?;
+        let frames = blocks
+            .first()
+            .map_or(0, |(header, _)| header.block_samples as usize);
+
+        match &mut self.buf {
+            SampleBuffer::Int(buf) => {
+                decode_blocks(buf, &blocks, &correction, frames, |sample, header| {
+                    sample.wrapping_shl(32 - header.bytes_per_sample() * 8)
+                })?;
+            }
+            SampleBuffer::Float(buf) => {
+                decode_blocks(buf, &blocks, &correction, frames, |sample, _| {
+                    f32::from_bits(sample as u32)
+                })?;
+            }
+        }
+        Ok(self.last_decoded())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        match &self.buf {
+            SampleBuffer::Int(buf) => buf.as_audio_buffer_ref(),
+            SampleBuffer::Float(buf) => buf.as_audio_buffer_ref(),
+        }
+    }
+}
+
+fn split_blocks(mut data: &[u8]) -> Result<Vec<(BlockHeader, &[u8])>> {
+    let mut blocks = Vec::new();
+    while !data.is_empty() {
+        let Some(header) = BlockHeader::parse(data).filter(|header| header.block_len <= data.len())
+        else {
+            return decode_error("wavpack: invalid block in packet");
+        };
+        let (block, rest) = data.split_at(header.block_len);
+        blocks.push((header, block));
+        data = rest;
+    }
+    Ok(blocks)
+}
+
+fn decode_blocks<S: Sample>(
+    buf: &mut AudioBuffer<S>,
+    blocks: &[(BlockHeader, &[u8])],
+    correction: &[(BlockHeader, &[u8])],
+    frames: usize,
+    convert: impl Fn(i32, &BlockHeader) -> S,
+) -> Result<()> {
+    buf.clear();
+    if frames as u64 > buf.capacity() as u64 {
+        *buf = AudioBuffer::new(frames as u64, *buf.spec());
+    }
+    buf.render_reserved(Some(frames));
+
+    let total_channels = buf.spec().channels.count();
+    let mut corrections = correction.iter().peekable();
+    let mut channel = 0;
+    for (header, block) in blocks {
+        // 校正 block 与主 block 按顺序一一对应，头部的位置、长度与标志必须一致。
+        let paired = corrections
+            .next_if(|(other, _)| {
+                other.block_index == header.block_index
+                    && other.block_samples == header.block_samples
+                    && other.flags == header.flags
+            })
+            .map(|(_, data)| *data);
+        let samples = unpack::unpack_block(block, paired).or_else(decode_error)?;
+
+        let block_channels = header.channels();
+        if header.block_samples as usize != frames || channel + block_channels > total_channels {
+            return decode_error("wavpack: block layout does not match the stream");
+        }
+        for offset in 0..block_channels {
+            let out = buf.chan_mut(channel + offset);
+            for (frame, sample) in out.iter_mut().enumerate() {
+                *sample = convert(samples[frame * block_channels + offset], header);
+            }
+        }
+        channel += block_channels;
+    }
+    if channel != total_channels {
+        return decode_error("wavpack: block layout does not match the stream");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 由 wavpack 5.6 对 `test_signal` 编码得到（--blocksize=128，3 个 block）。
+    const LOSSLESS_WV: &str = "
+        7776706ba0010000100400008001000000000000800000003118bc142b78696c21285249464648060000574156456a75
+        6e6b1c00000000000000000000000000000000000000000000000000000000000000666d7420100000000100020044ac
+        000010b10200040010006461746100060000420357574756480003051c1b1b1917141616130e040446f938f982f77cf7
+        05062c0a160c5e0c0d0a110b9f0a6502000000002a008a7c00001738ac39e2bb6eb9a53924e90fea03f8822d212fa909
+        72a1b5a8071331668045c0303894c370205f8a806d01dd02cf01021448502042818a02155fee273d803e07e9d9c6485d
+        473cac282c59f826fe0b7fc31b7dde2e1b7d91d434c9028357c56419e948b7804eb0337010bc45afe1817cb001329fcd
+        87f96bfdf0e7e41a57487e298138739bdca2ab0c0ac881b8009eb805b7c22256c4aa401728c76c989ef24b24184f3878
+        67bfcbab948ff7ccf121e81d3ad89fe4e73f7647c97efd1c085d1e612b4ad253413349200994426800c061200663b814
+        982163688c05c24038484779205f0a40b540358171800ff6a3df9f2f76b0472c02e22f0241af1f3a7776706b40010000
+        100400008001000080000000800000003118bc146f60caf9420357574756480003053835322e2d262a2722180404d4f6
+        cdf6aefc18fb0506290a900c470ce009510b070b2a008a780000e33e7dee6e7cd1378a5bae20179958423a6398120035
+        2245b4040bd0080c85293895e6d01c9e44937832cfe2da5ccc9fe397fc54bec869e69fa50c184f6384582bb41d883848
+        09f4500d14311367626a4eed1339e8515f296ab537b182806809466c075198b98a07891049f9da1eff97f5a5ceb2fe60
+        30e0e1218ba92898ab116b1406621006221112802112c0012020022481022802032c41033591111dd23bfffaff879cfe
+        6fa49fc37ecda47058f17e400bba947b0484b41200010b20011882201102421086411908411118819018a9989ac9208c
+        6114ef237ebddf99bfd129bddbb458398559d620815a16d801022f02918828a97776706b4e0100001004000080010000
+        00010000800000003118bc141b9366b042035757475648000305524d47403f353531281e040438f72ef7a505e8040506
+        240a570d9b0cd808fc0b210b2a008a7f000057b22010f48973c69bfb25ea27beb964f18086994f99e065190923022331
+        844220066328848331d6477ceb3f85bfc1753f7451a018709cae4100b18c5002271e0541988449188043180043b8000a
+        4884443080ecc66bfe57f91d4e45fd7b09644a658a090c0089fff93cea67ef9b3ba547da102836c09e25c13b95213ae0
+        684e43000460202000002441022642020a82002882022ec3002cc2222c41113044397f4ff9e5fbf20efc918724185dd7
+        d5d37fa2a395776bbb66181403fcf7e5c1a1dfe3524340100200800268900241900441200241a0042230024a2041a470
+        dbff87f03738b4d79e4fbd08450226de6b514354c30c8586a94c4242003402e82f0285a3995d
+    ";
+    // 同一信号的混合模式（-b2.2 -c）主文件与校正文件。
+    const HYBRID_WV: &str = "
+        7776706b1a01000010040000800100000000000080000000591ebc346ac5bcf021285249464648060000574156456a75
+        6e6b1c00000000000000000000000000000000000000000000000000000000000000666d7420100000000100020044ac
+        000010b10200040010006461746100060000420357574756480003051c1b1b1917141616130e040446f938f982f77cf7
+        05062c0a160c5e0c0d0a110b9f0a06046d134b13000000016502400a00002a008a350000c7cdd7a3638c7019266ec071
+        4007744a266c0a12542092c4c7edd067674726d3993d715f341d6a388443d001c60810a9803f7f1f555b4ed5517d301f
+        428838943518cae376e751a40458fdf9ef8be37882b9364185679012e9c66e48848023b2200b34a8cf9f871a85ff2f01
+        1ba07776706bb800000010040000800100008000000080000000591ebc34622e031042035757475648000305322d2523
+        2723141318150404dcf6b0f600fd00000506d40ab70b8e0c600a910ab20a060484135e13000000012a008a3000008f7c
+        f9ec1128a30e3ac8086000000d3640040ceaaf9f973a1298281805c60043248cbeddc7a3d000201860040cd48f9fa723
+        5be08185541085358a2046ad5a88311fce3f9e279ca9d141eb90d8d01a0a0658d0e0036850c8fdbb5d915984b1fe2f01
+        96947776706ba600000010040000800100000001000080000000591ebc34762457e14203575747564800030545402827
+        362d090c141704041ef731f73f05cf040506d10a0a0c8e0c1d0a8d0afc0a060492136713000000012a008a270000237d
+        baab4c100ba8a106f140e8b87fd738a30c8b9221418282b9dca9410c3ee2f39f87c133655814451820088cc0401c1fbf
+        cb52fee5eedac090215110d8800a0808e8f8f8ddae489a61c90593ff2f0104bd
+    ";
+    const HYBRID_WVC: &str = "
+        7776706bc800000010040000800100000000000080000000591ebc342b78696c07060000001b0000001b4eed4eed8b4d
+        000001e5af3dd5c0a46dff24938fcf26c8c8f4dec9b44a2ac85d25710c91a4bf315a32fbf76ccf727b9a73ae387aef82
+        672a3a27c8ac77898e65fbdace1d2452868f5a9508dbf070a3658cd06d9d83f7385be9aa577873d907d04af4e7844481
+        86599963bae48b2127bb4b3b00c6d8c36e052c84608b217b052d48224d7c91d5fe3e61568040648ed81ee18579005eec
+        8c55d96633f84a457e3ce6fe2f01c1a17776706bce00000010040000800100008000000080000000591ebc346f60caf9
+        070696023d1a52043d1a49ed49ed8b5000006a6e84574a259f773510b58611c735e1b6d2e132e4a0471ed9ce8bf3504f
+        4c9d07080bff3d5120ddc4790da520e9e55ace8a55a7be47acc73469c3006ecf9830dfb2b351921747e81aaa572d2cd2
+        cc2ac2e53dd81e1c29ee5bded4c57ee2974f228d894cd92c095c3a56be816affc1bb730eec947d550b63d831a1e5066c
+        70d039050af6a17dc490536e6c73c4e8d0a147f2331f2a371fb4f5f5b5080bb854b92f013bcb7776706bd60000001004
+        0000800100000001000080000000591ebc341b9366b007064dfb8c1800028c1866ed66ed8b54000037ff13711b967094
+        8e0e109a8a41ae8e08f12b33117e1cb91237e0df7e7105908886cd69dc839e7914b73b39003b3337951a9169924ae3c7
+        9dcac987b11ea87e7865900694a305975bff4d7a25fe3625bc1d2ac6d05718aa1a05115489b7d85995767bf4fdcf3a86
+        5777203b8f5bbab830be74568b643d131ec2f7a1a9601b0a506c349d056c068b1010f6a7879533e7aac753c7c6d7e921
+        b0bd2dd1010a3793b88180c2fdf4e5ff2f01d591
+    ";
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        let digits: Vec<u8> = hex.bytes().filter(u8::is_ascii_hexdigit).collect();
+        digits
+            .chunks_exact(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    fn stream(hex: &str) -> MediaSourceStream {
+        MediaSourceStream::new(
+            Box::new(std::io::Cursor::new(bytes(hex))),
+            Default::default(),
+        )
+    }
+
+    fn test_signal() -> Vec<i32> {
+        (0..384)
+            .flat_map(|i| [(i * 13) % 400 - 200, (i * 7) % 300 - 150])
+            .collect()
+    }
+
+    fn decode_all(mut reader: WavPackReader) -> Vec<i32> {
+        let params = reader.tracks()[0].codec_params.clone();
+        let mut decoder = WavPackDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        let mut samples = Vec::new();
+        while let Ok(packet) = reader.next_packet() {
+            let AudioBufferRef::S32(buf) = decoder.decode(&packet).unwrap() else {
+                panic!("expected integer samples");
+            };
+            for frame in 0..buf.frames() {
+                samples.extend([buf.chan(0)[frame] >> 16, buf.chan(1)[frame] >> 16]);
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn lossless_blocks_decode_bit_exact() {
+        let reader =
+            WavPackReader::try_new(stream(LOSSLESS_WV), &FormatOptions::default()).unwrap();
+        let params = &reader.tracks()[0].codec_params;
+        assert_eq!(params.sample_rate, Some(44_100));
+        assert_eq!(params.n_frames, Some(384));
+        assert_eq!(params.channels.map(|channels| channels.count()), Some(2));
+        assert_eq!(decode_all(reader), test_signal());
+    }
+
+    #[test]
+    fn hybrid_is_lossy_alone_and_bit_exact_with_correction() {
+        let lossy = decode_all(
+            WavPackReader::try_new(stream(HYBRID_WV), &FormatOptions::default()).unwrap(),
+        );
+        let expected = test_signal();
+        assert_eq!(lossy.len(), expected.len());
+        assert_ne!(lossy, expected);
+        assert!(lossy.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 64));
+
+        let corrected = WavPackReader::with_correction(
+            stream(HYBRID_WV),
+            stream(HYBRID_WVC),
+            &FormatOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(decode_all(corrected), expected);
+    }
+
+    #[test]
+    fn seek_lands_on_block_start_and_keeps_correction_aligned() {
+        let mut reader = WavPackReader::with_correction(
+            stream(HYBRID_WV),
+            stream(HYBRID_WVC),
+            &FormatOptions::default(),
+        )
+        .unwrap();
+        let seeked = reader
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp {
+                    ts: 300,
+                    track_id: 0,
+                },
+            )
+            .unwrap();
+        assert_eq!(seeked.actual_ts, 256);
+        assert_eq!(decode_all(reader), test_signal()[512..]);
+
+        let mut reader =
+            WavPackReader::try_new(stream(LOSSLESS_WV), &FormatOptions::default()).unwrap();
+        assert!(
+            reader
+                .seek(
+                    SeekMode::Accurate,
+                    SeekTo::TimeStamp {
+                        ts: 384,
+                        track_id: 0
+                    }
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn corrupted_block_fails_checksum() {
+        let mut data = bytes(LOSSLESS_WV);
+        let last = data.len() - 8;
+        data[last] ^= 0x10;
+        let mut reader = WavPackReader::try_new(
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(data)), Default::default()),
+            &FormatOptions::default(),
+        )
+        .unwrap();
+        let params = reader.tracks()[0].codec_params.clone();
+        let mut decoder = WavPackDecoder::try_new(&params, &DecoderOptions::default()).unwrap();
+        let results: Vec<bool> = std::iter::from_fn(|| reader.next_packet().ok())
+            .map(|packet| decoder.decode(&packet).is_ok())
+            .collect();
+        assert_eq!(results, [true, true, false]);
+    }
+}
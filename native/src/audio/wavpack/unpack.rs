@@ -0,0 +1,1316 @@
+//! 单个 WavPack block 的解包，算法与 libwavpack 的 unpack.c / read_words.c 一一对应。
+
+use super::block::{
+    BYTES_STORED, BlockHeader, CROSS_DECORR, DSD_FLAG, FALSE_STEREO, FLOAT_DATA, HYBRID_BALANCE,
+    HYBRID_BITRATE, HYBRID_FLAG, HYBRID_SHAPE, ID_CHANNEL_INFO, ID_DECORR_SAMPLES, ID_DECORR_TERMS,
+    ID_DECORR_WEIGHTS, ID_DSD_BLOCK, ID_DUMMY, ID_ENTROPY_VARS, ID_FLOAT_INFO, ID_HYBRID_PROFILE,
+    ID_INT32_INFO, ID_OPTIONAL_DATA, ID_SHAPING_WEIGHTS, ID_WV_BITSTREAM, ID_WVC_BITSTREAM,
+    ID_WVX_BITSTREAM, INT32_DATA, JOINT_STEREO, MAG_LSB, MONO_DATA, NEW_SHAPING, SHIFT_LSB,
+    SubBlock, sub_blocks,
+};
+
+const MAX_NTERMS: usize = 16;
+const MAX_TERM: usize = 8;
+/// 单个 block 的采样数上限，防止损坏的头部触发超大分配。
+const MAX_BLOCK_SAMPLES: u32 = 1 << 22;
+const LIMIT_ONES: u32 = 16;
+const SLS: u32 = 8;
+const SLO: u32 = 1 << (SLS - 1);
+const DIV0: u32 = 128;
+const DIV1: u32 = 64;
+const DIV2: u32 = 32;
+
+const FLOAT_SHIFT_ONES: u8 = 1;
+const FLOAT_SHIFT_SAME: u8 = 2;
+const FLOAT_SHIFT_SENT: u8 = 4;
+const FLOAT_ZEROS_SENT: u8 = 8;
+const FLOAT_NEG_ZEROS: u8 = 0x10;
+
+const INVALID_BLOCK: &str = "wavpack: invalid block";
+const INVALID_METADATA: &str = "wavpack: invalid block metadata";
+const CORRUPT_BLOCK: &str = "wavpack: corrupt audio data";
+const CRC_MISMATCH: &str = "wavpack: block checksum mismatch";
+
+/// 解包一个 block（可附带 .wvc 中对应的校正 block）。
+///
+/// 返回按 block 声道数交错的采样；浮点流返回 `f32` 的位模式。
+pub(crate) fn unpack_block(
+    block: &[u8],
+    correction: Option<&[u8]>,
+) -> Result<Vec<i32>, &'static str> {
+    let header = BlockHeader::parse(block).ok_or(INVALID_BLOCK)?;
+    let correction = correction
+        .map(|data| {
+            BlockHeader::parse(data)
+                .map(|header| (header, data))
+                .ok_or(INVALID_BLOCK)
+        })
+        .transpose()?;
+    // 与 libwavpack 一致：有校正 block 时以它的头部为准。
+    let (flags, expected_crc) = correction.map_or((header.flags, header.crc), |(header, _)| {
+        (header.flags, header.crc)
+    });
+    if flags & DSD_FLAG != 0 {
+        return Err("wavpack: DSD streams are not supported");
+    }
+    if flags & MONO_DATA == MONO_DATA || header.block_samples > MAX_BLOCK_SAMPLES {
+        return Err(INVALID_BLOCK);
+    }
+
+    let mut state = BlockState::new(flags, header.version);
+    for sub_block in sub_blocks(block, &header) {
+        state.process_metadata(sub_block?)?;
+    }
+    let count = header.block_samples as usize;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if let Some((correction_header, data)) = correction {
+        for sub_block in sub_blocks(data, &correction_header) {
+            state.process_metadata(sub_block?)?;
+        }
+    }
+    if state.wv.is_none() {
+        return Err("wavpack: block has no audio bitstream");
+    }
+
+    let has_correction = correction.is_some();
+    let (mut buffer, crc) = state.decode(count, has_correction)?;
+    state.fixup_samples(&mut buffer, flags & HYBRID_FLAG != 0 && !has_correction);
+    if crc != expected_crc || (state.wvx.is_some() && state.crc_x != state.crc_wvx) {
+        return Err(CRC_MISMATCH);
+    }
+
+    if flags & FALSE_STEREO != 0 {
+        buffer = buffer.iter().flat_map(|&sample| [sample, sample]).collect();
+    }
+    Ok(buffer)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct DecorrPass {
+    term: i32,
+    delta: i32,
+    weight_a: i32,
+    weight_b: i32,
+    samples_a: [i32; MAX_TERM],
+    samples_b: [i32; MAX_TERM],
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EntropyData {
+    median: [u32; 3],
+    slow_level: u32,
+    error_limit: u32,
+}
+
+#[derive(Debug, Default)]
+struct Words {
+    c: [EntropyData; 2],
+    bitrate_delta: [u32; 2],
+    bitrate_acc: [u32; 2],
+    zeros_acc: u32,
+    holding_one: u32,
+    holding_zero: bool,
+}
+
+/// 混合模式噪声整形状态（libwavpack 的 `dc`）。
+#[derive(Debug, Default)]
+struct Shaping {
+    acc: [i32; 2],
+    delta: [i32; 2],
+    error: [i32; 2],
+}
+
+struct BlockState<'a> {
+    flags: u32,
+    version: u16,
+    passes: Vec<DecorrPass>,
+    words: Words,
+    dc: Shaping,
+    wv: Option<Bitstream<'a>>,
+    wvc: Option<Bitstream<'a>>,
+    wvx: Option<Bitstream<'a>>,
+    crc_x: u32,
+    crc_wvx: u32,
+    /// sent_bits, zeros, ones, dups
+    int32_info: [u8; 4],
+    /// flags, shift, max_exp, norm_exp
+    float_info: [u8; 4],
+}
+
+impl<'a> BlockState<'a> {
+    fn new(flags: u32, version: u16) -> Self {
+        Self {
+            flags,
+            version,
+            passes: Vec::new(),
+            words: Words::default(),
+            dc: Shaping::default(),
+            wv: None,
+            wvc: None,
+            wvx: None,
+            crc_x: u32::MAX,
+            crc_wvx: 0,
+            int32_info: [0; 4],
+            float_info: [0; 4],
+        }
+    }
+
+    fn is_mono(&self) -> bool {
+        self.flags & MONO_DATA != 0
+    }
+
+    fn process_metadata(&mut self, sub_block: SubBlock<'a>) -> Result<(), &'static str> {
+        let data = sub_block.data;
+        let valid = match sub_block.id {
+            ID_DUMMY | ID_CHANNEL_INFO => true,
+            ID_DECORR_TERMS => self.read_decorr_terms(data),
+            ID_DECORR_WEIGHTS => self.read_decorr_weights(data),
+            ID_DECORR_SAMPLES => self.read_decorr_samples(data),
+            ID_ENTROPY_VARS => self.read_entropy_vars(data),
+            ID_HYBRID_PROFILE => self.read_hybrid_profile(data),
+            ID_SHAPING_WEIGHTS => self.read_shaping_info(data),
+            ID_FLOAT_INFO | ID_INT32_INFO => {
+                let Ok(info) = <[u8; 4]>::try_from(data) else {
+                    return Err(INVALID_METADATA);
+                };
+                if sub_block.id == ID_FLOAT_INFO {
+                    self.float_info = info;
+                } else {
+                    self.int32_info = info;
+                }
+                true
+            }
+            ID_WV_BITSTREAM | ID_WVC_BITSTREAM => {
+                let valid = !data.is_empty() && data.len().is_multiple_of(2);
+                let stream = Some(Bitstream::new(data));
+                if sub_block.id == ID_WV_BITSTREAM {
+                    self.wv = stream;
+                } else {
+                    self.wvc = stream;
+                }
+                valid
+            }
+            ID_WVX_BITSTREAM => {
+                let valid = data.len() > 4 && data.len().is_multiple_of(2);
+                if valid {
+                    self.crc_wvx = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+                    self.wvx = Some(Bitstream::new(&data[4..]));
+                }
+                valid
+            }
+            ID_DSD_BLOCK => return Err("wavpack: DSD streams are not supported"),
+            id => id & ID_OPTIONAL_DATA != 0,
+        };
+        if valid { Ok(()) } else { Err(INVALID_METADATA) }
+    }
+
+    fn read_decorr_terms(&mut self, data: &[u8]) -> bool {
+        if data.len() > MAX_NTERMS {
+            return false;
+        }
+        let mono = self.is_mono();
+        self.passes = data
+            .iter()
+            .rev()
+            .map(|&byte| DecorrPass {
+                term: i32::from(byte & 0x1f) - 5,
+                delta: i32::from(byte >> 5) & 0x7,
+                ..DecorrPass::default()
+            })
+            .collect();
+        self.passes.iter().all(|pass| {
+            let term = pass.term;
+            term != 0
+                && term >= -3
+                && !(term > MAX_TERM as i32 && term < 17)
+                && term <= 18
+                && !(mono && term < 0)
+        })
+    }
+
+    fn read_decorr_weights(&mut self, data: &[u8]) -> bool {
+        let mono = self.is_mono();
+        let term_count = if mono { data.len() } else { data.len() / 2 };
+        if term_count > self.passes.len() {
+            return false;
+        }
+        for pass in &mut self.passes {
+            pass.weight_a = 0;
+            pass.weight_b = 0;
+        }
+        let mut bytes = data.iter().map(|&byte| restore_weight(byte as i8));
+        for pass in self.passes.iter_mut().rev().take(term_count) {
+            pass.weight_a = bytes.next().unwrap_or_default();
+            if !mono {
+                pass.weight_b = bytes.next().unwrap_or_default();
+            }
+        }
+        true
+    }
+
+    fn read_decorr_samples(&mut self, data: &[u8]) -> bool {
+        let mono = self.is_mono();
+        let mut reader = LogReader { data };
+        for pass in &mut self.passes {
+            pass.samples_a = [0; MAX_TERM];
+            pass.samples_b = [0; MAX_TERM];
+        }
+
+        if self.version == 0x402 && self.flags & HYBRID_FLAG != 0 {
+            if reader.remaining() < if mono { 2 } else { 4 } {
+                return false;
+            }
+            self.dc.error[0] = reader.next_exp2s();
+            if !mono {
+                self.dc.error[1] = reader.next_exp2s();
+            }
+        }
+
+        for pass in self.passes.iter_mut().rev() {
+            if reader.remaining() == 0 {
+                break;
+            }
+            if pass.term > MAX_TERM as i32 {
+                if reader.remaining() < if mono { 4 } else { 8 } {
+                    return false;
+                }
+                pass.samples_a[0] = reader.next_exp2s();
+                pass.samples_a[1] = reader.next_exp2s();
+                if !mono {
+                    pass.samples_b[0] = reader.next_exp2s();
+                    pass.samples_b[1] = reader.next_exp2s();
+                }
+            } else if pass.term < 0 {
+                if reader.remaining() < 4 {
+                    return false;
+                }
+                pass.samples_a[0] = reader.next_exp2s();
+                pass.samples_b[0] = reader.next_exp2s();
+            } else {
+                for m in 0..pass.term as usize {
+                    if reader.remaining() < if mono { 2 } else { 4 } {
+                        return false;
+                    }
+                    pass.samples_a[m] = reader.next_exp2s();
+                    if !mono {
+                        pass.samples_b[m] = reader.next_exp2s();
+                    }
+                }
+            }
+        }
+        reader.remaining() == 0
+    }
+
+    fn read_entropy_vars(&mut self, data: &[u8]) -> bool {
+        let channels = if self.is_mono() { 1 } else { 2 };
+        if data.len() != channels * 6 {
+            return false;
+        }
+        for (c, chunk) in self.words.c.iter_mut().zip(data.chunks_exact(6)) {
+            for (median, bytes) in c.median.iter_mut().zip(chunk.chunks_exact(2)) {
+                *median = wp_exp2s(i32::from(u16::from_le_bytes([bytes[0], bytes[1]]))) as u32;
+            }
+        }
+        true
+    }
+
+    fn read_hybrid_profile(&mut self, data: &[u8]) -> bool {
+        let mono = self.is_mono();
+        let step = if mono { 2 } else { 4 };
+        let mut rest = data;
+        let next = |rest: &mut &[u8]| {
+            let value = u16::from_le_bytes([rest[0], rest[1]]);
+            *rest = &rest[2..];
+            value
+        };
+
+        if self.flags & HYBRID_BITRATE != 0 {
+            if rest.len() < step {
+                return false;
+            }
+            self.words.c[0].slow_level = wp_exp2s(i32::from(next(&mut rest))) as u32;
+            if !mono {
+                self.words.c[1].slow_level = wp_exp2s(i32::from(next(&mut rest))) as u32;
+            }
+        }
+
+        if rest.len() < step {
+            return false;
+        }
+        self.words.bitrate_acc[0] = u32::from(next(&mut rest)) << 16;
+        if !mono {
+            self.words.bitrate_acc[1] = u32::from(next(&mut rest)) << 16;
+        }
+
+        if rest.is_empty() {
+            self.words.bitrate_delta = [0; 2];
+            return true;
+        }
+        if rest.len() < step {
+            return false;
+        }
+        self.words.bitrate_delta[0] = wp_exp2s(i32::from(next(&mut rest) as i16)) as u32;
+        if !mono {
+            self.words.bitrate_delta[1] = wp_exp2s(i32::from(next(&mut rest) as i16)) as u32;
+        }
+        rest.is_empty()
+    }
+
+    fn read_shaping_info(&mut self, data: &[u8]) -> bool {
+        let mono = self.is_mono();
+        if data.len() == 2 {
+            self.dc.acc[0] = ((restore_weight(data[0] as i8) as u32) << 16) as i32;
+            self.dc.acc[1] = ((restore_weight(data[1] as i8) as u32) << 16) as i32;
+            return true;
+        }
+        if data.len() < if mono { 4 } else { 8 } {
+            return false;
+        }
+
+        let mut reader = LogReader { data };
+        self.dc.error[0] = reader.next_exp2s();
+        self.dc.acc[0] = reader.next_exp2s();
+        if !mono {
+            self.dc.error[1] = reader.next_exp2s();
+            self.dc.acc[1] = reader.next_exp2s();
+        }
+        if data.len() == if mono { 6 } else { 12 } {
+            self.dc.delta[0] = reader.next_exp2s();
+            if !mono {
+                self.dc.delta[1] = reader.next_exp2s();
+            }
+        }
+        true
+    }
+
+    /// 熵解码 + 去相关，返回整数域采样与 block CRC。
+    fn decode(
+        &mut self,
+        count: usize,
+        has_correction: bool,
+    ) -> Result<(Vec<i32>, u32), &'static str> {
+        let flags = self.flags;
+        let mono = self.is_mono();
+        let mut mute_limit = (1i64 << ((flags >> MAG_LSB) & 0x1f)) + 2;
+        if flags & HYBRID_FLAG != 0 && !has_correction {
+            mute_limit = mute_limit * 2 + 128;
+        }
+        let exceeds = |sample: i32| i64::from(sample).abs() > mute_limit;
+        let mut crc = u32::MAX;
+        let mut buffer = vec![0i32; if mono { count } else { count * 2 }];
+
+        if !has_correction {
+            let stereo = usize::from(!mono);
+            for (i, sample) in buffer.iter_mut().enumerate() {
+                *sample = self.get_word(i & stereo, None).ok_or(CORRUPT_BLOCK)?;
+            }
+
+            if mono {
+                for pass in &mut self.passes {
+                    decorr_mono_pass(pass, &mut buffer);
+                }
+                for &sample in &buffer {
+                    if exceeds(sample) {
+                        return Err(CORRUPT_BLOCK);
+                    }
+                    crc = crc.wrapping_mul(3).wrapping_add(sample as u32);
+                }
+            } else {
+                for pass in &mut self.passes {
+                    decorr_stereo_pass(pass, &mut buffer);
+                }
+                for frame in buffer.chunks_exact_mut(2) {
+                    if flags & JOINT_STEREO != 0 {
+                        frame[1] = frame[1].wrapping_sub(frame[0] >> 1);
+                        frame[0] = frame[0].wrapping_add(frame[1]);
+                    }
+                    if exceeds(frame[0]) || exceeds(frame[1]) {
+                        return Err(CORRUPT_BLOCK);
+                    }
+                    crc = stereo_crc(crc, frame[0], frame[1]);
+                }
+            }
+            return Ok((buffer, crc));
+        }
+
+        let mut m = 0;
+        if mono {
+            for sample in &mut buffer {
+                let mut correction = 0;
+                let mut read_word = self
+                    .get_word(0, Some(&mut correction))
+                    .ok_or(CORRUPT_BLOCK)?;
+                for pass in &mut self.passes {
+                    let (sam, k) = if pass.term > MAX_TERM as i32 {
+                        let sam = extrapolate(pass.term, &pass.samples_a);
+                        pass.samples_a[1] = pass.samples_a[0];
+                        (sam, 0)
+                    } else {
+                        (pass.samples_a[m], (m + pass.term as usize) & (MAX_TERM - 1))
+                    };
+                    let temp = apply_weight(pass.weight_a, sam).wrapping_add(read_word);
+                    update_weight(&mut pass.weight_a, pass.delta, sam, read_word);
+                    pass.samples_a[k] = temp;
+                    read_word = temp;
+                }
+                m = (m + 1) & (MAX_TERM - 1);
+
+                if flags & HYBRID_SHAPE != 0 {
+                    let temp = self.shape(0, correction);
+                    read_word = read_word.wrapping_add(correction.wrapping_sub(temp));
+                } else {
+                    read_word = read_word.wrapping_add(correction);
+                }
+                crc = crc.wrapping_mul(3).wrapping_add(read_word as u32);
+                if exceeds(read_word) {
+                    return Err(CORRUPT_BLOCK);
+                }
+                *sample = read_word;
+            }
+            return Ok((buffer, crc));
+        }
+
+        for frame in buffer.chunks_exact_mut(2) {
+            let mut correction = [0; 2];
+            let (c0, c1) = correction.split_at_mut(1);
+            let mut left = self.get_word(0, Some(&mut c0[0])).ok_or(CORRUPT_BLOCK)?;
+            let mut right = self.get_word(1, Some(&mut c1[0])).ok_or(CORRUPT_BLOCK)?;
+            let (mut left_c, mut right_c) = (0i32, 0i32);
+
+            if flags & CROSS_DECORR != 0 {
+                left_c = left.wrapping_add(correction[0]);
+                right_c = right.wrapping_add(correction[1]);
+                for pass in &self.passes {
+                    if pass.term > 0 {
+                        let (sam_a, sam_b) = if pass.term > MAX_TERM as i32 {
+                            (
+                                extrapolate(pass.term, &pass.samples_a),
+                                extrapolate(pass.term, &pass.samples_b),
+                            )
+                        } else {
+                            (pass.samples_a[m], pass.samples_b[m])
+                        };
+                        left_c = left_c.wrapping_add(apply_weight(pass.weight_a, sam_a));
+                        right_c = right_c.wrapping_add(apply_weight(pass.weight_b, sam_b));
+                    } else if pass.term == -1 {
+                        left_c =
+                            left_c.wrapping_add(apply_weight(pass.weight_a, pass.samples_a[0]));
+                        right_c = right_c.wrapping_add(apply_weight(pass.weight_b, left_c));
+                    } else {
+                        right_c =
+                            right_c.wrapping_add(apply_weight(pass.weight_b, pass.samples_b[0]));
+                        let source = if pass.term == -3 {
+                            pass.samples_a[0]
+                        } else {
+                            right_c
+                        };
+                        left_c = left_c.wrapping_add(apply_weight(pass.weight_a, source));
+                    }
+                }
+                if flags & JOINT_STEREO != 0 {
+                    right_c = right_c.wrapping_sub(left_c >> 1);
+                    left_c = left_c.wrapping_add(right_c);
+                }
+            }
+
+            for pass in &mut self.passes {
+                if pass.term > 0 {
+                    let (sam_a, sam_b, k) = if pass.term > MAX_TERM as i32 {
+                        let sam_a = extrapolate(pass.term, &pass.samples_a);
+                        let sam_b = extrapolate(pass.term, &pass.samples_b);
+                        pass.samples_a[1] = pass.samples_a[0];
+                        pass.samples_b[1] = pass.samples_b[0];
+                        (sam_a, sam_b, 0)
+                    } else {
+                        let k = (m + pass.term as usize) & (MAX_TERM - 1);
+                        (pass.samples_a[m], pass.samples_b[m], k)
+                    };
+                    let left2 = apply_weight(pass.weight_a, sam_a).wrapping_add(left);
+                    let right2 = apply_weight(pass.weight_b, sam_b).wrapping_add(right);
+                    update_weight(&mut pass.weight_a, pass.delta, sam_a, left);
+                    update_weight(&mut pass.weight_b, pass.delta, sam_b, right);
+                    pass.samples_a[k] = left2;
+                    pass.samples_b[k] = right2;
+                    left = left2;
+                    right = right2;
+                } else if pass.term == -1 {
+                    let left2 = left.wrapping_add(apply_weight(pass.weight_a, pass.samples_a[0]));
+                    update_weight_clip(&mut pass.weight_a, pass.delta, pass.samples_a[0], left);
+                    left = left2;
+                    let right2 = right.wrapping_add(apply_weight(pass.weight_b, left2));
+                    update_weight_clip(&mut pass.weight_b, pass.delta, left2, right);
+                    pass.samples_a[0] = right2;
+                    right = right2;
+                } else {
+                    let mut right2 =
+                        right.wrapping_add(apply_weight(pass.weight_b, pass.samples_b[0]));
+                    update_weight_clip(&mut pass.weight_b, pass.delta, pass.samples_b[0], right);
+                    right = right2;
+                    if pass.term == -3 {
+                        right2 = pass.samples_a[0];
+                        pass.samples_a[0] = right;
+                    }
+                    let left2 = left.wrapping_add(apply_weight(pass.weight_a, right2));
+                    update_weight_clip(&mut pass.weight_a, pass.delta, right2, left);
+                    pass.samples_b[0] = left2;
+                    left = left2;
+                }
+            }
+            m = (m + 1) & (MAX_TERM - 1);
+
+            if flags & CROSS_DECORR == 0 {
+                left_c = left.wrapping_add(correction[0]);
+                right_c = right.wrapping_add(correction[1]);
+                if flags & JOINT_STEREO != 0 {
+                    right_c = right_c.wrapping_sub(left_c >> 1);
+                    left_c = left_c.wrapping_add(right_c);
+                }
+            }
+            if flags & JOINT_STEREO != 0 {
+                right = right.wrapping_sub(left >> 1);
+                left = left.wrapping_add(right);
+            }
+
+            if flags & HYBRID_SHAPE != 0 {
+                let temp = self.shape(0, left_c.wrapping_sub(left));
+                left = left_c.wrapping_sub(temp);
+                let temp = self.shape(1, right_c.wrapping_sub(right));
+                right = right_c.wrapping_sub(temp);
+            } else {
+                left = left_c;
+                right = right_c;
+            }
+
+            if exceeds(left) || exceeds(right) {
+                return Err(CORRUPT_BLOCK);
+            }
+            crc = stereo_crc(crc, left, right);
+            frame[0] = left;
+            frame[1] = right;
+        }
+        Ok((buffer, crc))
+    }
+
+    /// 噪声整形一步，返回要从校正后采样中减去的量。
+    fn shape(&mut self, chan: usize, correction: i32) -> i32 {
+        let dc = &mut self.dc;
+        dc.acc[chan] = dc.acc[chan].wrapping_add(dc.delta[chan]);
+        let shaping_weight = dc.acc[chan] >> 16;
+        let mut temp = apply_weight(shaping_weight, dc.error[chan]).wrapping_neg();
+        if self.flags & NEW_SHAPING != 0 && shaping_weight < 0 && temp != 0 {
+            if temp == dc.error[chan] {
+                temp = if temp < 0 { temp + 1 } else { temp - 1 };
+            }
+            dc.error[chan] = temp.wrapping_sub(correction);
+        } else {
+            dc.error[chan] = correction.wrapping_neg();
+        }
+        temp
+    }
+
+    fn get_word(&mut self, chan: usize, correction: Option<&mut i32>) -> Option<i32> {
+        let flags = self.flags;
+        let words = &mut self.words;
+        let wv = self.wv.as_mut()?;
+
+        if words.c[0].median[0] & !1 == 0
+            && !words.holding_zero
+            && words.holding_one == 0
+            && words.c[1].median[0] & !1 == 0
+        {
+            if words.zeros_acc != 0 {
+                words.zeros_acc -= 1;
+                if words.zeros_acc != 0 {
+                    decay_slow_level(&mut words.c[chan]);
+                    return Some(0);
+                }
+            } else {
+                words.zeros_acc = read_escape(wv)?;
+                if words.zeros_acc != 0 {
+                    decay_slow_level(&mut words.c[chan]);
+                    words.c[0].median = [0; 3];
+                    words.c[1].median = [0; 3];
+                    return Some(0);
+                }
+            }
+        }
+
+        let mut ones_count = 0;
+        if words.holding_zero {
+            words.holding_zero = false;
+        } else {
+            while ones_count < LIMIT_ONES + 1 && wv.getbit() {
+                ones_count += 1;
+            }
+            if ones_count >= LIMIT_ONES {
+                if ones_count == LIMIT_ONES + 1 {
+                    return None;
+                }
+                ones_count = read_escape(wv)?.wrapping_add(LIMIT_ONES);
+            }
+            if words.holding_one != 0 {
+                words.holding_one = ones_count & 1;
+                ones_count = (ones_count >> 1) + 1;
+            } else {
+                words.holding_one = ones_count & 1;
+                ones_count >>= 1;
+            }
+            words.holding_zero = words.holding_one == 0;
+        }
+
+        if flags & HYBRID_FLAG != 0 && chan == 0 {
+            words.update_error_limit(flags);
+        }
+
+        let c = &mut words.c[chan];
+        let (mut low, mut high);
+        if ones_count == 0 {
+            low = 0;
+            high = get_med(c, 0).wrapping_sub(1);
+            dec_med(c, 0, DIV0);
+        } else {
+            low = get_med(c, 0);
+            inc_med(c, 0, DIV0);
+            if ones_count == 1 {
+                high = low.wrapping_add(get_med(c, 1)).wrapping_sub(1);
+                dec_med(c, 1, DIV1);
+            } else {
+                low = low.wrapping_add(get_med(c, 1));
+                inc_med(c, 1, DIV1);
+                if ones_count == 2 {
+                    high = low.wrapping_add(get_med(c, 2)).wrapping_sub(1);
+                    dec_med(c, 2, DIV2);
+                } else {
+                    low = low.wrapping_add((ones_count - 2).wrapping_mul(get_med(c, 2)));
+                    high = low.wrapping_add(get_med(c, 2)).wrapping_sub(1);
+                    inc_med(c, 2, DIV2);
+                }
+            }
+        }
+
+        low &= 0x7fff_ffff;
+        high &= 0x7fff_ffff;
+        if low > high {
+            high = low;
+        }
+        let mut mid = (high + low + 1) >> 1;
+        if c.error_limit == 0 {
+            mid = read_code(wv, high - low) + low;
+        } else {
+            while high - low > c.error_limit {
+                if wv.getbit() {
+                    low = mid;
+                } else {
+                    high = mid - 1;
+                }
+                mid = (high + low + 1) >> 1;
+            }
+        }
+
+        let sign = wv.getbit();
+        if let Some(wvc) = self.wvc.as_mut()
+            && c.error_limit != 0
+        {
+            let value = read_code(wvc, high - low) + low;
+            if let Some(correction) = correction {
+                *correction = if sign {
+                    mid.wrapping_sub(value)
+                } else {
+                    value.wrapping_sub(mid)
+                } as i32;
+            }
+        } else if let Some(correction) = correction {
+            *correction = 0;
+        }
+
+        if flags & HYBRID_BITRATE != 0 {
+            decay_slow_level(c);
+            c.slow_level = c.slow_level.wrapping_add(wp_log2(mid) as u32);
+        }
+        Some(if sign { !mid as i32 } else { mid as i32 })
+    }
+
+    /// 浮点、32 位整数的还原，以及有损数据的截断与移位。
+    fn fixup_samples(&mut self, buffer: &mut [i32], lossy: bool) {
+        let flags = self.flags;
+        let mut shift = (flags >> SHIFT_LSB) & 0x1f;
+        if flags & FLOAT_DATA != 0 {
+            self.float_values(buffer);
+            return;
+        }
+
+        if flags & INT32_DATA != 0 {
+            let [sent_bits, mut zeros, mut ones, mut dups] =
+                self.int32_info.map(|v| u32::from(v & 0x1f));
+            if let Some(wvx) = self.wvx.as_mut() {
+                let mask = ((1u64 << sent_bits) - 1) as u32;
+                let mut crc = self.crc_x;
+                for value in buffer.iter_mut() {
+                    let data = wvx.getbits(sent_bits);
+                    *value = ((*value as u32).wrapping_shl(sent_bits) | (data & mask)) as i32;
+                    *value = restore_int32(*value, zeros, ones, dups);
+                    crc = crc
+                        .wrapping_mul(9)
+                        .wrapping_add((*value as u32 & 0xffff) * 3)
+                        .wrapping_add((*value as u32 >> 16) & 0xffff);
+                }
+                self.crc_x = crc;
+            } else if sent_bits == 0 && zeros + ones + dups != 0 {
+                while lossy && flags & BYTES_STORED == 3 && shift < 8 {
+                    if zeros != 0 {
+                        zeros -= 1;
+                    } else if ones != 0 {
+                        ones -= 1;
+                    } else if dups != 0 {
+                        dups -= 1;
+                    } else {
+                        break;
+                    }
+                    shift += 1;
+                }
+                for value in buffer.iter_mut() {
+                    *value = restore_int32(*value, zeros, ones, dups);
+                }
+            } else {
+                shift += zeros + sent_bits + ones + dups;
+            }
+        }
+
+        let shift = shift & 0x1f;
+        if lossy {
+            let (min_value, max_value) = match flags & BYTES_STORED {
+                0 => (-128 >> shift, 127 >> shift),
+                1 => (-32768 >> shift, 32767 >> shift),
+                2 => (-8_388_608 >> shift, 8_388_607 >> shift),
+                _ => (i32::MIN >> shift, i32::MAX >> shift),
+            };
+            let min_shifted = ((min_value as u32) << shift) as i32;
+            let max_shifted = ((max_value as u32) << shift) as i32;
+            for value in buffer.iter_mut() {
+                *value = if *value < min_value {
+                    min_shifted
+                } else if *value > max_value {
+                    max_shifted
+                } else {
+                    ((*value as u32) << shift) as i32
+                };
+            }
+        } else if shift != 0 {
+            for value in buffer.iter_mut() {
+                *value = ((*value as u32) << shift) as i32;
+            }
+        }
+    }
+
+    fn float_values(&mut self, values: &mut [i32]) {
+        let [float_flags, float_shift, max_exp, _] = self.float_info;
+        let float_shift = u32::from(float_shift & 0x1f);
+        let Some(wvx) = self.wvx.as_mut() else {
+            for value in values.iter_mut() {
+                *value = float_value_lossy(*value, float_flags, float_shift, max_exp) as i32;
+            }
+            return;
+        };
+
+        let mut crc = self.crc_x;
+        for value in values.iter_mut() {
+            let mut exp = u32::from(max_exp);
+            let mut outval = 0u32;
+            if *value == 0 {
+                if float_flags & FLOAT_ZEROS_SENT != 0 {
+                    if wvx.getbit() {
+                        outval |= wvx.getbits(23) & 0x7f_ffff;
+                        if exp >= 25 {
+                            outval |= (wvx.getbits(8) & 0xff) << 23;
+                        }
+                        outval |= u32::from(wvx.getbit()) << 31;
+                    } else if float_flags & FLOAT_NEG_ZEROS != 0 {
+                        outval |= u32::from(wvx.getbit()) << 31;
+                    }
+                }
+            } else {
+                let mut v = ((*value as u32) << float_shift) as i32;
+                if v < 0 {
+                    v = v.wrapping_neg();
+                    outval |= 1 << 31;
+                }
+                if v == 0x100_0000 {
+                    if wvx.getbit() {
+                        outval |= wvx.getbits(23) & 0x7f_ffff;
+                    }
+                    outval |= 0xff << 23;
+                } else {
+                    let mut shift_count = 0;
+                    if exp != 0 {
+                        while v & 0x80_0000 == 0 && {
+                            exp -= 1;
+                            exp != 0
+                        } {
+                            shift_count += 1;
+                            v = ((v as u32) << 1) as i32;
+                        }
+                    }
+                    shift_count &= 0x1f;
+                    if shift_count != 0 {
+                        let fill = (1u32 << shift_count) - 1;
+                        if float_flags & FLOAT_SHIFT_ONES != 0
+                            || (float_flags & FLOAT_SHIFT_SAME != 0 && wvx.getbit())
+                        {
+                            v |= fill as i32;
+                        } else if float_flags & FLOAT_SHIFT_SENT != 0 {
+                            v |= (wvx.getbits(shift_count) & fill) as i32;
+                        }
+                    }
+                    outval |= v as u32 & 0x7f_ffff;
+                    outval |= (exp & 0xff) << 23;
+                }
+            }
+            crc = crc
+                .wrapping_mul(27)
+                .wrapping_add((outval & 0x7f_ffff).wrapping_mul(9))
+                .wrapping_add(((outval >> 23) & 0xff) * 3)
+                .wrapping_add(outval >> 31);
+            *value = outval as i32;
+        }
+        self.crc_x = crc;
+    }
+}
+
+impl Words {
+    fn update_error_limit(&mut self, flags: u32) {
+        self.bitrate_acc[0] = self.bitrate_acc[0].wrapping_add(self.bitrate_delta[0]);
+        let mut bitrate_0 = (self.bitrate_acc[0] >> 16) as i32;
+        let slow_log = |c: &EntropyData| (c.slow_level.wrapping_add(SLO) >> SLS) as i32;
+        let limit = |slow_log: i32, bitrate: i32| {
+            if slow_log - bitrate > -0x100 {
+                wp_exp2s(slow_log - bitrate + 0x100) as u32
+            } else {
+                0
+            }
+        };
+
+        if flags & MONO_DATA != 0 {
+            self.c[0].error_limit = if flags & HYBRID_BITRATE != 0 {
+                limit(slow_log(&self.c[0]), bitrate_0)
+            } else {
+                wp_exp2s(bitrate_0) as u32
+            };
+            return;
+        }
+
+        self.bitrate_acc[1] = self.bitrate_acc[1].wrapping_add(self.bitrate_delta[1]);
+        let mut bitrate_1 = (self.bitrate_acc[1] >> 16) as i32;
+        if flags & HYBRID_BITRATE != 0 {
+            let slow_log_0 = slow_log(&self.c[0]);
+            let slow_log_1 = slow_log(&self.c[1]);
+            if flags & HYBRID_BALANCE != 0 {
+                let balance = (slow_log_1 - slow_log_0 + bitrate_1 + 1) >> 1;
+                if balance > bitrate_0 {
+                    bitrate_1 = bitrate_0 * 2;
+                    bitrate_0 = 0;
+                } else if -balance > bitrate_0 {
+                    bitrate_0 *= 2;
+                    bitrate_1 = 0;
+                } else {
+                    bitrate_1 = bitrate_0 + balance;
+                    bitrate_0 -= balance;
+                }
+            }
+            self.c[0].error_limit = limit(slow_log_0, bitrate_0);
+            self.c[1].error_limit = limit(slow_log_1, bitrate_1);
+        } else {
+            self.c[0].error_limit = wp_exp2s(bitrate_0) as u32;
+            self.c[1].error_limit = wp_exp2s(bitrate_1) as u32;
+        }
+    }
+}
+
+/// 按 LSB 优先读取的位流；越界后返回 0 位，损坏数据交给 CRC 检查发现。
+struct Bitstream<'a> {
+    data: &'a [u8],
+    pos: usize,
+    sr: u64,
+    bc: u32,
+}
+
+impl<'a> Bitstream<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            sr: 0,
+            bc: 0,
+        }
+    }
+
+    fn fill(&mut self, bits: u32) {
+        while self.bc < bits {
+            let byte = self.data.get(self.pos).copied().unwrap_or(0);
+            self.pos = self.pos.saturating_add(1);
+            self.sr |= u64::from(byte) << self.bc;
+            self.bc += 8;
+        }
+    }
+
+    fn getbit(&mut self) -> bool {
+        self.getbits(1) != 0
+    }
+
+    fn getbits(&mut self, bits: u32) -> u32 {
+        if bits == 0 {
+            return 0;
+        }
+        self.fill(bits);
+        let value = (self.sr & ((1u64 << bits) - 1)) as u32;
+        self.sr >>= bits;
+        self.bc -= bits;
+        value
+    }
+}
+
+/// 连续读取小端 i16 对数值并还原（`wp_exp2s`）。
+struct LogReader<'a> {
+    data: &'a [u8],
+}
+
+impl LogReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn next_exp2s(&mut self) -> i32 {
+        let value = i16::from_le_bytes([self.data[0], self.data[1]]);
+        self.data = &self.data[2..];
+        wp_exp2s(i32::from(value))
+    }
+}
+
+/// 读取 0 游程 / 长 1 游程的 Elias 风格转义长度；全 1 视为流结束。
+fn read_escape(bs: &mut Bitstream) -> Option<u32> {
+    let mut cbits = 0;
+    while cbits < 33 && bs.getbit() {
+        cbits += 1;
+    }
+    if cbits == 33 {
+        return None;
+    }
+    if cbits < 2 {
+        return Some(cbits);
+    }
+    let mut value = 0;
+    let mut mask = 1u32;
+    for _ in 1..cbits {
+        if bs.getbit() {
+            value |= mask;
+        }
+        mask <<= 1;
+    }
+    Some(value | mask)
+}
+
+/// 读取 0..=maxcode 的截断二进制码。
+fn read_code(bs: &mut Bitstream, maxcode: u32) -> u32 {
+    if maxcode < 2 {
+        return if maxcode != 0 {
+            u32::from(bs.getbit())
+        } else {
+            0
+        };
+    }
+    let bitcount = 32 - maxcode.leading_zeros();
+    let extras = ((1u64 << bitcount) - u64::from(maxcode) - 1) as u32;
+    let code = bs.getbits(bitcount - 1);
+    if code >= extras {
+        (code << 1) - extras + u32::from(bs.getbit())
+    } else {
+        code
+    }
+}
+
+fn get_med(c: &EntropyData, i: usize) -> u32 {
+    (c.median[i] >> 4) + 1
+}
+
+fn inc_med(c: &mut EntropyData, i: usize, div: u32) {
+    c.median[i] = c.median[i].wrapping_add((c.median[i].wrapping_add(div) / div).wrapping_mul(5));
+}
+
+fn dec_med(c: &mut EntropyData, i: usize, div: u32) {
+    c.median[i] =
+        c.median[i].wrapping_sub((c.median[i].wrapping_add(div - 2) / div).wrapping_mul(2));
+}
+
+fn decay_slow_level(c: &mut EntropyData) {
+    c.slow_level = c
+        .slow_level
+        .wrapping_sub(c.slow_level.wrapping_add(SLO) >> SLS);
+}
+
+fn stereo_crc(crc: u32, left: i32, right: i32) -> u32 {
+    crc.wrapping_add(crc << 3)
+        .wrapping_add((left as u32) << 1)
+        .wrapping_add(left as u32)
+        .wrapping_add(right as u32)
+}
+
+/// term 17/18 的线性外推预测。
+fn extrapolate(term: i32, samples: &[i32; MAX_TERM]) -> i32 {
+    if term & 1 != 0 {
+        samples[0].wrapping_mul(2).wrapping_sub(samples[1])
+    } else {
+        samples[0].wrapping_mul(3).wrapping_sub(samples[1]) >> 1
+    }
+}
+
+fn decorr_mono_pass(pass: &mut DecorrPass, buffer: &mut [i32]) {
+    let delta = pass.delta;
+    let mut weight = pass.weight_a;
+    match pass.term {
+        17 | 18 => {
+            for sample in buffer.iter_mut() {
+                let sam = extrapolate(pass.term, &pass.samples_a);
+                pass.samples_a[1] = pass.samples_a[0];
+                pass.samples_a[0] = apply_weight(weight, sam).wrapping_add(*sample);
+                update_weight(&mut weight, delta, sam, *sample);
+                *sample = pass.samples_a[0];
+            }
+        }
+        term => {
+            let (mut m, mut k) = (0, term as usize & (MAX_TERM - 1));
+            for sample in buffer.iter_mut() {
+                let sam = pass.samples_a[m];
+                pass.samples_a[k] = apply_weight(weight, sam).wrapping_add(*sample);
+                update_weight(&mut weight, delta, sam, *sample);
+                *sample = pass.samples_a[k];
+                m = (m + 1) & (MAX_TERM - 1);
+                k = (k + 1) & (MAX_TERM - 1);
+            }
+        }
+    }
+    pass.weight_a = weight;
+}
+
+fn decorr_stereo_pass(pass: &mut DecorrPass, buffer: &mut [i32]) {
+    let delta = pass.delta;
+    match pass.term {
+        17 | 18 => {
+            for frame in buffer.chunks_exact_mut(2) {
+                let sam = stereo_extrapolate(pass.term, &pass.samples_a);
+                pass.samples_a[1] = pass.samples_a[0];
+                pass.samples_a[0] = apply_weight(pass.weight_a, sam).wrapping_add(frame[0]);
+                update_weight(&mut pass.weight_a, delta, sam, frame[0]);
+                frame[0] = pass.samples_a[0];
+
+                let sam = stereo_extrapolate(pass.term, &pass.samples_b);
+                pass.samples_b[1] = pass.samples_b[0];
+                pass.samples_b[0] = apply_weight(pass.weight_b, sam).wrapping_add(frame[1]);
+                update_weight(&mut pass.weight_b, delta, sam, frame[1]);
+                frame[1] = pass.samples_b[0];
+            }
+        }
+        -1 => {
+            for frame in buffer.chunks_exact_mut(2) {
+                let sam = frame[0].wrapping_add(apply_weight(pass.weight_a, pass.samples_a[0]));
+                update_weight_clip(&mut pass.weight_a, delta, pass.samples_a[0], frame[0]);
+                frame[0] = sam;
+                pass.samples_a[0] = frame[1].wrapping_add(apply_weight(pass.weight_b, sam));
+                update_weight_clip(&mut pass.weight_b, delta, sam, frame[1]);
+                frame[1] = pass.samples_a[0];
+            }
+        }
+        -2 => {
+            for frame in buffer.chunks_exact_mut(2) {
+                let sam = frame[1].wrapping_add(apply_weight(pass.weight_b, pass.samples_b[0]));
+                update_weight_clip(&mut pass.weight_b, delta, pass.samples_b[0], frame[1]);
+                frame[1] = sam;
+                pass.samples_b[0] = frame[0].wrapping_add(apply_weight(pass.weight_a, sam));
+                update_weight_clip(&mut pass.weight_a, delta, sam, frame[0]);
+                frame[0] = pass.samples_b[0];
+            }
+        }
+        -3 => {
+            for frame in buffer.chunks_exact_mut(2) {
+                let sam_a = frame[0].wrapping_add(apply_weight(pass.weight_a, pass.samples_a[0]));
+                update_weight_clip(&mut pass.weight_a, delta, pass.samples_a[0], frame[0]);
+                let sam_b = frame[1].wrapping_add(apply_weight(pass.weight_b, pass.samples_b[0]));
+                update_weight_clip(&mut pass.weight_b, delta, pass.samples_b[0], frame[1]);
+                pass.samples_b[0] = sam_a;
+                pass.samples_a[0] = sam_b;
+                frame[0] = sam_a;
+                frame[1] = sam_b;
+            }
+        }
+        term => {
+            let (mut m, mut k) = (0, term as usize & (MAX_TERM - 1));
+            for frame in buffer.chunks_exact_mut(2) {
+                let sam = pass.samples_a[m];
+                pass.samples_a[k] = apply_weight(pass.weight_a, sam).wrapping_add(frame[0]);
+                update_weight(&mut pass.weight_a, delta, sam, frame[0]);
+                frame[0] = pass.samples_a[k];
+
+                let sam = pass.samples_b[m];
+                pass.samples_b[k] = apply_weight(pass.weight_b, sam).wrapping_add(frame[1]);
+                update_weight(&mut pass.weight_b, delta, sam, frame[1]);
+                frame[1] = pass.samples_b[k];
+
+                m = (m + 1) & (MAX_TERM - 1);
+                k = (k + 1) & (MAX_TERM - 1);
+            }
+        }
+    }
+}
+
+/// 立体声 pass 中 term 18 的写法与单声道不同，溢出时结果也不同，这里保持一致。
+fn stereo_extrapolate(term: i32, samples: &[i32; MAX_TERM]) -> i32 {
+    if term & 1 != 0 {
+        samples[0].wrapping_mul(2).wrapping_sub(samples[1])
+    } else {
+        samples[0].wrapping_add(samples[0].wrapping_sub(samples[1]) >> 1)
+    }
+}
+
+fn apply_weight(weight: i32, sample: i32) -> i32 {
+    if sample == i32::from(sample as i16) {
+        weight.wrapping_mul(sample).wrapping_add(512) >> 10
+    } else {
+        (((sample & 0xffff).wrapping_mul(weight) >> 9)
+            .wrapping_add(((sample & !0xffff) >> 9).wrapping_mul(weight))
+            .wrapping_add(1))
+            >> 1
+    }
+}
+
+fn update_weight(weight: &mut i32, delta: i32, source: i32, result: i32) {
+    if source != 0 && result != 0 {
+        let s = (source ^ result) >> 31;
+        *weight = (delta ^ s).wrapping_add(weight.wrapping_sub(s));
+    }
+}
+
+fn update_weight_clip(weight: &mut i32, delta: i32, source: i32, result: i32) {
+    if source != 0 && result != 0 {
+        let s = (source ^ result) >> 31;
+        let w = (*weight ^ s).wrapping_add(delta.wrapping_sub(s)).min(1024);
+        *weight = (w ^ s).wrapping_sub(s);
+    }
+}
+
+fn restore_weight(weight: i8) -> i32 {
+    let result = i32::from(weight) * 8;
+    if result > 0 {
+        result + ((result + 64) >> 7)
+    } else {
+        result
+    }
+}
+
+fn restore_int32(value: i32, zeros: u32, ones: u32, dups: u32) -> i32 {
+    if zeros != 0 {
+        ((value as u32) << zeros) as i32
+    } else if ones != 0 {
+        ((value.wrapping_add(1) as u32) << ones).wrapping_sub(1) as i32
+    } else if dups != 0 {
+        let odd = value & 1;
+        ((value.wrapping_add(odd) as u32) << dups).wrapping_sub(odd as u32) as i32
+    } else {
+        value
+    }
+}
+
+/// 没有 wvx 扩展流时的浮点还原（有损）。
+fn float_value_lossy(value: i32, float_flags: u8, float_shift: u32, max_exp: u8) -> u32 {
+    if value == 0 {
+        return 0;
+    }
+    let mut exp = u32::from(max_exp);
+    let mut outval = 0u32;
+    let mut v = ((value as u32) << float_shift) as i32;
+    if v < 0 {
+        v = v.wrapping_neg();
+        outval |= 1 << 31;
+    }
+    if v >= 0x100_0000 {
+        while v & 0xf00_0000 != 0 {
+            v >>= 1;
+            exp += 1;
+        }
+    } else if exp != 0 {
+        let mut shift_count = 0;
+        while v & 0x80_0000 == 0 && {
+            exp -= 1;
+            exp != 0
+        } {
+            shift_count += 1;
+            v = ((v as u32) << 1) as i32;
+        }
+        shift_count &= 0x1f;
+        if shift_count != 0 && float_flags & FLOAT_SHIFT_ONES != 0 {
+            v |= ((1u32 << shift_count) - 1) as i32;
+        }
+    }
+    outval | (v as u32 & 0x7f_ffff) | ((exp & 0xff) << 23)
+}
+
+fn wp_exp2s(log: i32) -> i32 {
+    if log < 0 {
+        return wp_exp2s(log.wrapping_neg()).wrapping_neg();
+    }
+    let value = u32::from(EXP2_TABLE[(log & 0xff) as usize]) | 0x100;
+    let exp = log >> 8;
+    if exp <= 9 {
+        (value >> (9 - exp)) as i32
+    } else {
+        value.wrapping_shl(((exp - 9) & 0x1f) as u32) as i32
+    }
+}
+
+fn wp_log2(value: u32) -> i32 {
+    let value = value.wrapping_add(value >> 9);
+    let dbits = 32 - value.leading_zeros();
+    let index = if value < 256 {
+        (value << (9 - dbits)) & 0xff
+    } else {
+        (value >> (dbits - 9)) & 0xff
+    };
+    ((dbits << 8) + u32::from(LOG2_TABLE[index as usize])) as i32
+}
+
+const EXP2_TABLE: [u8; 256] = [
+    0x00, 0x01, 0x01, 0x02, 0x03, 0x03, 0x04, 0x05, 0x06, 0x06, 0x07, 0x08, 0x08, 0x09, 0x0a, 0x0b,
+    0x0b, 0x0c, 0x0d, 0x0e, 0x0e, 0x0f, 0x10, 0x10, 0x11, 0x12, 0x13, 0x13, 0x14, 0x15, 0x16, 0x16,
+    0x17, 0x18, 0x19, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1d, 0x1e, 0x1f, 0x20, 0x20, 0x21, 0x22, 0x23,
+    0x24, 0x24, 0x25, 0x26, 0x27, 0x28, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2c, 0x2d, 0x2e, 0x2f, 0x30,
+    0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3a, 0x3b, 0x3c, 0x3d,
+    0x3e, 0x3f, 0x40, 0x41, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x48, 0x49, 0x4a, 0x4b,
+    0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a,
+    0x5b, 0x5c, 0x5d, 0x5e, 0x5e, 0x5f, 0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79,
+    0x7a, 0x7b, 0x7c, 0x7d, 0x7e, 0x7f, 0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x87, 0x88, 0x89, 0x8a,
+    0x8b, 0x8c, 0x8d, 0x8e, 0x8f, 0x90, 0x91, 0x92, 0x93, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b,
+    0x9c, 0x9d, 0x9f, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa8, 0xa9, 0xaa, 0xab, 0xac, 0xad,
+    0xaf, 0xb0, 0xb1, 0xb2, 0xb3, 0xb4, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xbc, 0xbd, 0xbe, 0xbf, 0xc0,
+    0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc8, 0xc9, 0xca, 0xcb, 0xcd, 0xce, 0xcf, 0xd0, 0xd2, 0xd3, 0xd4,
+    0xd6, 0xd7, 0xd8, 0xd9, 0xdb, 0xdc, 0xdd, 0xde, 0xe0, 0xe1, 0xe2, 0xe4, 0xe5, 0xe6, 0xe8, 0xe9,
+    0xea, 0xec, 0xed, 0xee, 0xf0, 0xf1, 0xf2, 0xf4, 0xf5, 0xf6, 0xf8, 0xf9, 0xfa, 0xfc, 0xfd, 0xff,
+];
+
+const LOG2_TABLE: [u8; 256] = [
+    0x00, 0x01, 0x03, 0x04, 0x06, 0x07, 0x09, 0x0a, 0x0b, 0x0d, 0x0e, 0x10, 0x11, 0x12, 0x14, 0x15,
+    0x16, 0x18, 0x19, 0x1a, 0x1c, 0x1d, 0x1e, 0x20, 0x21, 0x22, 0x24, 0x25, 0x26, 0x28, 0x29, 0x2a,
+    0x2c, 0x2d, 0x2e, 0x2f, 0x31, 0x32, 0x33, 0x34, 0x36, 0x37, 0x38, 0x39, 0x3b, 0x3c, 0x3d, 0x3e,
+    0x3f, 0x41, 0x42, 0x43, 0x44, 0x45, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4d, 0x4e, 0x4f, 0x50, 0x51,
+    0x52, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5c, 0x5d, 0x5e, 0x5f, 0x60, 0x61, 0x62, 0x63,
+    0x64, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x74, 0x75,
+    0x76, 0x77, 0x78, 0x79, 0x7a, 0x7b, 0x7c, 0x7d, 0x7e, 0x7f, 0x80, 0x81, 0x82, 0x83, 0x84, 0x85,
+    0x86, 0x87, 0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x8d, 0x8e, 0x8f, 0x90, 0x91, 0x92, 0x93, 0x94, 0x95,
+    0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b, 0x9b, 0x9c, 0x9d, 0x9e, 0x9f, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4,
+    0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xa9, 0xaa, 0xab, 0xac, 0xad, 0xae, 0xaf, 0xb0, 0xb1, 0xb2, 0xb2,
+    0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xb9, 0xba, 0xbb, 0xbc, 0xbd, 0xbe, 0xbf, 0xc0, 0xc0,
+    0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xcb, 0xcb, 0xcc, 0xcd, 0xce,
+    0xcf, 0xd0, 0xd0, 0xd1, 0xd2, 0xd3, 0xd4, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd8, 0xd9, 0xda, 0xdb,
+    0xdc, 0xdc, 0xdd, 0xde, 0xdf, 0xe0, 0xe0, 0xe1, 0xe2, 0xe3, 0xe4, 0xe4, 0xe5, 0xe6, 0xe7, 0xe7,
+    0xe8, 0xe9, 0xea, 0xea, 0xeb, 0xec, 0xed, 0xee, 0xee, 0xef, 0xf0, 0xf1, 0xf1, 0xf2, 0xf3, 0xf4,
+    0xf4, 0xf5, 0xf6, 0xf7, 0xf7, 0xf8, 0xf9, 0xf9, 0xfa, 0xfb, 0xfc, 0xfc, 0xfd, 0xfe, 0xff, 0xff,
+];
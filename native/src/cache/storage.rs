@@ -146,9 +146,13 @@ impl CacheFileStore {
 
     pub fn remove_song_meta(&self, relative_path: &str) -> CacheResult<()> {
         let meta_path = self.song_meta_path(relative_path);
+        let seek_index_path = crate::audio::seek_index::index_path_for_metadata(&meta_path);
         if meta_path.exists() {
             fs::remove_file(meta_path)?;
         }
+        if seek_index_path.exists() {
+            fs::remove_file(seek_index_path)?;
+        }
         Ok(())
     }
 
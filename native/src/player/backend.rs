@@ -1,10 +1,24 @@
+use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 
+use crate::audio::alsa_hw::AlsaHwOptions;
+use crate::audio::bit_perfect::BitPerfectReport;
+use crate::audio::chapters::Chapter;
+use crate::audio::hrtf::HrtfDataset;
+use crate::audio::hw_mixer::HwVolume;
+use crate::audio::jack_output::JackOptions;
+use crate::audio::multi_output::{SecondaryOutputReport, SecondaryOutputTarget};
+use crate::audio::pipeline_stats::PipelineStatsSnapshot;
+use crate::audio::player::StreamDuration;
+use crate::audio::silence::SilenceOptions;
+use crate::audio::thread_priority::SchedulingReport;
+use crate::audio::virtual_output::VirtualOutputOptions;
 use crate::audio::{AudioPlayer, OutputDeviceInfo};
 
 use super::types::{
-    BackendFuture, BackendResult, CachedUrlPlaybackRequest, PlaybackOptions, SignalFuture,
+    BackendFuture, BackendResult, BufferPlaybackRequest, CachedUrlPlaybackRequest,
+    FileRangePlaybackRequest, PlaybackOptions, SignalFuture,
 };
 
 pub(crate) trait PlayerBackend: Send {
@@ -26,15 +40,60 @@ pub(crate) trait PlayerBackend: Send {
         start_at: Option<Duration>,
         options: PlaybackOptions,
     ) -> BackendFuture<'a, ()>;
+    fn play_buffer<'a>(
+        &'a mut self,
+        request: &'a BufferPlaybackRequest,
+        start_at: Option<Duration>,
+        options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()>;
+    fn play_file_range<'a>(
+        &'a mut self,
+        request: &'a FileRangePlaybackRequest,
+        start_at: Option<Duration>,
+        options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()>;
+    fn queue_next_range(&self, request: &FileRangePlaybackRequest);
+    fn range_transitions(&self) -> u64;
     fn pause(&self);
     fn resume(&self);
     fn stop(&mut self);
     fn seek(&self, target: Duration);
+    fn begin_scrub(&self);
+    /// 以下两个返回是否处于拖动预览中；不在预览中时什么都不做。
+    fn scrub_to(&self, target: Duration) -> bool;
+    fn end_scrub(&self, commit: bool) -> bool;
     fn progress(&self) -> Duration;
+    fn duration(&self) -> Option<StreamDuration>;
+    /// 当前曲目的章节，按起点排序。
+    fn chapters(&self) -> &[Chapter];
     fn is_buffering(&self) -> bool;
     fn is_finished(&self) -> bool;
     fn wait_finished_signal(&self) -> SignalFuture;
+    /// 输出流已无法继续（设备断开等）时返回原因。
+    fn output_fault(&self) -> Option<String>;
     fn output_devices(&self) -> BackendResult<Vec<OutputDeviceInfo>>;
+    /// 设备列表未变时保持不变的签名，`None` 表示无法判断。
+    fn device_signature(&self) -> Option<String>;
+    fn set_realtime_scheduling(&mut self, enabled: bool);
+    fn scheduling_report(&self) -> SchedulingReport;
+    /// 播放管线健康指标，自播放器创建起累计。
+    fn pipeline_stats(&self) -> PipelineStatsSnapshot;
+    fn set_alsa_hw_output(&mut self, options: AlsaHwOptions);
+    fn set_jack_output(&mut self, options: JackOptions);
+    fn set_virtual_output(&mut self, options: VirtualOutputOptions);
+    fn set_silence_options(&mut self, options: SilenceOptions);
+    /// 多声道音源在本设备上的 HRTF 双耳渲染，`None` 为照常下混。
+    fn set_hrtf(&mut self, dataset: Option<HrtfDataset>);
+    /// 本设备是否接受 DoP；关闭时非严格模式下的 DSD 音源转成 PCM。
+    fn set_dop_output(&mut self, enabled: bool);
+    /// 当前输出设备所在声卡的硬件音量。
+    fn hw_volume(&self) -> BackendResult<HwVolume>;
+    fn set_hw_volume(&self, volume: f64) -> BackendResult<HwVolume>;
+    fn set_hw_mute(&self, muted: bool) -> BackendResult<HwVolume>;
+    fn set_bit_perfect_verification(&mut self, enabled: bool);
+    fn bit_perfect_report(&mut self) -> BitPerfectReport;
+    fn set_secondary_outputs(&mut self, targets: Vec<SecondaryOutputTarget>) -> BackendResult<()>;
+    fn secondary_output_reports(&self) -> Vec<SecondaryOutputReport>;
 }
 
 pub(crate) trait PlayerFactory: Send + Sync + 'static {
@@ -100,6 +159,53 @@ impl PlayerBackend for NativePlayer {
         })
     }
 
+    fn play_buffer<'a>(
+        &'a mut self,
+        request: &'a BufferPlaybackRequest,
+        start_at: Option<Duration>,
+        options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            self.0
+                .play_buffer(
+                    Arc::clone(&request.data),
+                    request.extension_hint.as_deref(),
+                    start_at,
+                    options.strict_bit_perfect,
+                )
+                .await
+                .map_err(|err| err.to_string())
+        })
+    }
+
+    fn play_file_range<'a>(
+        &'a mut self,
+        request: &'a FileRangePlaybackRequest,
+        start_at: Option<Duration>,
+        options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            self.0
+                .play_file_range(
+                    &request.path,
+                    request.range,
+                    request.next_range,
+                    start_at,
+                    options.strict_bit_perfect,
+                )
+                .await
+                .map_err(|err| err.to_string())
+        })
+    }
+
+    fn queue_next_range(&self, request: &FileRangePlaybackRequest) {
+        self.0.queue_next_range(request.next_range);
+    }
+
+    fn range_transitions(&self) -> u64 {
+        self.0.range_transitions()
+    }
+
     fn pause(&self) {
         self.0.pause();
     }
@@ -116,10 +222,30 @@ impl PlayerBackend for NativePlayer {
         self.0.seek(target);
     }
 
+    fn begin_scrub(&self) {
+        self.0.begin_scrub()
+    }
+
+    fn scrub_to(&self, target: Duration) -> bool {
+        self.0.scrub_to(target)
+    }
+
+    fn end_scrub(&self, commit: bool) -> bool {
+        self.0.end_scrub(commit)
+    }
+
     fn progress(&self) -> Duration {
         self.0.progress()
     }
 
+    fn duration(&self) -> Option<StreamDuration> {
+        self.0.duration()
+    }
+
+    fn chapters(&self) -> &[Chapter] {
+        self.0.chapters()
+    }
+
     fn is_buffering(&self) -> bool {
         self.0.get_state().waiting_for_seek.load(Ordering::Relaxed)
     }
@@ -130,17 +256,86 @@ impl PlayerBackend for NativePlayer {
 
     fn wait_finished_signal(&self) -> super::types::SignalFuture {
         let state = self.0.get_state();
-        Box::pin(async move {
-            if state.is_finished.load(Ordering::Relaxed) {
-                return;
-            }
-            state.finish_notify.notified().await;
-        })
+        Box::pin(async move { state.wait_finish_event().await })
+    }
+
+    fn output_fault(&self) -> Option<String> {
+        self.0.output_fault()
     }
 
     fn output_devices(&self) -> BackendResult<Vec<OutputDeviceInfo>> {
         self.0.output_devices().map_err(|err| err.to_string())
     }
+
+    fn device_signature(&self) -> Option<String> {
+        crate::audio::backend::sound_card_signature()
+    }
+
+    fn set_realtime_scheduling(&mut self, enabled: bool) {
+        self.0.set_realtime_scheduling(enabled);
+    }
+
+    fn scheduling_report(&self) -> SchedulingReport {
+        self.0.scheduling_report()
+    }
+
+    fn pipeline_stats(&self) -> PipelineStatsSnapshot {
+        self.0.pipeline_stats()
+    }
+
+    fn set_alsa_hw_output(&mut self, options: AlsaHwOptions) {
+        self.0.set_alsa_hw_output(options);
+    }
+
+    fn set_jack_output(&mut self, options: JackOptions) {
+        self.0.set_jack_output(options);
+    }
+
+    fn set_virtual_output(&mut self, options: VirtualOutputOptions) {
+        self.0.set_virtual_output(options);
+    }
+
+    fn set_silence_options(&mut self, options: SilenceOptions) {
+        self.0.set_silence_options(options);
+    }
+
+    fn set_hrtf(&mut self, dataset: Option<HrtfDataset>) {
+        self.0.set_hrtf(dataset);
+    }
+
+    fn set_dop_output(&mut self, enabled: bool) {
+        self.0.set_dop_output(enabled);
+    }
+
+    fn hw_volume(&self) -> BackendResult<HwVolume> {
+        self.0.hw_volume().map_err(|err| err.to_string())
+    }
+
+    fn set_hw_volume(&self, volume: f64) -> BackendResult<HwVolume> {
+        self.0.set_hw_volume(volume).map_err(|err| err.to_string())
+    }
+
+    fn set_hw_mute(&self, muted: bool) -> BackendResult<HwVolume> {
+        self.0.set_hw_mute(muted).map_err(|err| err.to_string())
+    }
+
+    fn set_bit_perfect_verification(&mut self, enabled: bool) {
+        self.0.set_bit_perfect_verification(enabled);
+    }
+
+    fn bit_perfect_report(&mut self) -> BitPerfectReport {
+        self.0.bit_perfect_report()
+    }
+
+    fn set_secondary_outputs(&mut self, targets: Vec<SecondaryOutputTarget>) -> BackendResult<()> {
+        self.0
+            .set_secondary_outputs(targets)
+            .map_err(|err| err.to_string())
+    }
+
+    fn secondary_output_reports(&self) -> Vec<SecondaryOutputReport> {
+        self.0.secondary_output_reports()
+    }
 }
 
 impl PlayerFactory for AudioPlayerFactory {
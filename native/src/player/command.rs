@@ -1,6 +1,18 @@
 use tokio::sync::oneshot;
 
-use super::types::{AudioDeviceInfo, BackendResult, CachedUrlPlaybackRequest, PlaybackOptions};
+use crate::audio::alsa_hw::AlsaHwOptions;
+use crate::audio::hrtf::HrtfDataset;
+use crate::audio::jack_output::JackOptions;
+use crate::audio::multi_output::SecondaryOutputTarget;
+use crate::audio::silence::SilenceOptions;
+use crate::audio::virtual_output::VirtualOutputOptions;
+
+use super::types::{
+    AudioDeviceInfo, BackendResult, BitPerfectReportInfo, BufferPlaybackRequest,
+    CachedUrlPlaybackRequest, ChapterInfo, DeviceLossPolicy, FileRangePlaybackRequest,
+    HwVolumeInfo, PipelineStatsInfo, PlaybackDurationInfo, PlaybackOptions, SchedulingDiagnostics,
+    SecondaryOutputStatus,
+};
 
 pub(crate) enum PlayerCommand {
     PlayFile(
@@ -21,11 +33,55 @@ pub(crate) enum PlayerCommand {
         PlaybackOptions,
         Option<oneshot::Sender<BackendResult<()>>>,
     ),
+    PlayBuffer(
+        BufferPlaybackRequest,
+        Option<f64>,
+        PlaybackOptions,
+        Option<oneshot::Sender<BackendResult<()>>>,
+    ),
+    PlayFileRange(
+        FileRangePlaybackRequest,
+        Option<f64>,
+        PlaybackOptions,
+        Option<oneshot::Sender<BackendResult<()>>>,
+    ),
     Pause,
     Resume,
     Stop,
     Seek(f64),
+    GetChapters(oneshot::Sender<Vec<ChapterInfo>>),
+    NextChapter(oneshot::Sender<bool>),
+    PreviousChapter(oneshot::Sender<bool>),
+    BeginScrub,
+    ScrubTo(f64),
+    EndScrub(bool),
     SwitchOutputDevice(Option<String>, oneshot::Sender<BackendResult<()>>),
     GetOutputDevices(oneshot::Sender<BackendResult<Vec<AudioDeviceInfo>>>),
+    SetPreferredOutputDevices(Vec<String>, oneshot::Sender<BackendResult<()>>),
+    WaitOutputDevicesChange(oneshot::Sender<Vec<AudioDeviceInfo>>),
     WaitFinished(oneshot::Sender<()>),
+    WaitDurationChange(oneshot::Sender<PlaybackDurationInfo>),
+    SetRealtimeScheduling(bool),
+    SetDeviceLossPolicy(DeviceLossPolicy),
+    GetSchedulingDiagnostics(oneshot::Sender<SchedulingDiagnostics>),
+    GetPipelineStats(oneshot::Sender<PipelineStatsInfo>),
+    SetAlsaHwOutput(AlsaHwOptions),
+    SetJackOutput(JackOptions),
+    SetVirtualOutput(VirtualOutputOptions),
+    SetSilenceOptions(SilenceOptions),
+    /// 设备（`None` 为系统默认设备）与它的 HRTF 数据，`None` 为关闭。
+    SetHrtf(Option<String>, Option<HrtfDataset>),
+    /// 设备（`None` 为系统默认设备）是否接受 DoP，未开启的设备上 DSD 转成 PCM 播放。
+    SetDopOutput(Option<String>, bool),
+    GetHwVolume(oneshot::Sender<BackendResult<HwVolumeInfo>>),
+    SetHwVolume(f64, oneshot::Sender<BackendResult<HwVolumeInfo>>),
+    SetHwMute(bool, oneshot::Sender<BackendResult<HwVolumeInfo>>),
+    SetVolume(f64, oneshot::Sender<BackendResult<bool>>),
+    SetBitPerfectVerification(bool),
+    GetBitPerfectReport(oneshot::Sender<BitPerfectReportInfo>),
+    SetSecondaryOutputs(
+        Vec<SecondaryOutputTarget>,
+        oneshot::Sender<BackendResult<()>>,
+    ),
+    GetSecondaryOutputs(oneshot::Sender<Vec<SecondaryOutputStatus>>),
 }
@@ -1,21 +1,39 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
+use napi::bindgen_prelude::Buffer;
 use napi::{Error, Result};
 use napi_derive::napi;
 use tokio::sync::{mpsc, oneshot};
 
+use crate::audio::cue;
+use crate::audio::decoder::AudioMetadata;
+use crate::audio::export::{self, ExportControl, ExportFormat, ExportOptions};
+use crate::audio::hrtf::{HrirSet, HrtfDataset};
+use crate::audio::player;
+use crate::audio::silence::SilenceOptions;
 use crate::runtime::native_runtime;
 
 use super::backend::{AudioPlayerFactory, PlayerFactory};
 use super::command::PlayerCommand;
 use super::state::SharedState;
-use super::types::{AudioDeviceInfo, CachedUrlPlaybackRequest, PlaybackOptions};
+use super::types::{
+    AlsaHwOutputConfig, AudioDeviceInfo, BitPerfectReportInfo, BufferPlaybackRequest,
+    CachedUrlPlaybackRequest, ChapterInfo, CueTrackInfo, DeviceCapabilitiesInfo, DeviceLossPolicy,
+    DopOutputConfig, ExportOptionsConfig, ExportProgressInfo, ExportResultInfo, ExportSource,
+    ExportSourceConfig, FileRangePlaybackRequest, HrtfConfig, HwVolumeInfo, JackOutputConfig,
+    NcmFileInfo, PipelineStatsInfo, PlaybackDurationInfo, PlaybackOptions, SchedulingDiagnostics,
+    SecondaryOutputConfig, SecondaryOutputStatus, SilenceOptionsConfig, VirtualOutputConfig,
+};
 use super::worker::WorkerCore;
 
 #[napi]
 pub struct PlayerService {
     sender: mpsc::UnboundedSender<PlayerCommand>,
     shared_state: Arc<SharedState>,
+    /// 进行中的导出，按目标路径索引。
+    exports: Arc<Mutex<HashMap<String, Arc<ExportControl>>>>,
 }
 
 #[napi]
@@ -34,24 +52,30 @@ impl PlayerService {
         Ok(Self {
             sender: tx,
             shared_state,
+            exports: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// `path` 为 .cue 时只播放其中 `cue_track` 号音轨（缺省为第一轨），
+    /// 进度从该轨起点算起；同一镜像中的下一轨会无缝续播。
     #[napi]
     pub async fn play_file(
         &self,
         path: String,
         start_secs: Option<f64>,
         strict_bit_perfect: Option<bool>,
+        cue_track: Option<u32>,
     ) -> Result<()> {
+        let options = playback_options(strict_bit_perfect);
         let (tx, rx) = oneshot::channel();
+        let command = if cue::is_cue_path(Path::new(&path)) {
+            let request = resolve_cue_track(path, cue_track).await?;
+            PlayerCommand::PlayFileRange(request, start_secs, options, Some(tx))
+        } else {
+            PlayerCommand::PlayFile(path, start_secs, options, Some(tx))
+        };
         self.sender
-            .send(PlayerCommand::PlayFile(
-                path,
-                start_secs,
-                playback_options(strict_bit_perfect),
-                Some(tx),
-            ))
+            .send(command)
             .map_err(|_| Error::from_reason("Background worker died"))?;
 
         rx.await
@@ -115,6 +139,34 @@ impl PlayerService {
             .map_err(Error::from_reason)
     }
 
+    /// 播放 JS 侧传入的完整音频数据（解密后的下载、生成的试听片段等）。
+    /// `extension_hint` 用于辅助格式探测，如 "flac"、"mp3"。
+    #[napi]
+    pub async fn play_buffer(
+        &self,
+        buffer: Buffer,
+        extension_hint: Option<String>,
+        start_secs: Option<f64>,
+        strict_bit_perfect: Option<bool>,
+    ) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::PlayBuffer(
+                BufferPlaybackRequest {
+                    data: Arc::from(buffer.as_ref()),
+                    extension_hint,
+                },
+                start_secs,
+                playback_options(strict_bit_perfect),
+                Some(tx),
+            ))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Playback start interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
     #[napi]
     pub fn pause(&self) -> Result<()> {
         let _ = self.sender.send(PlayerCommand::Pause);
@@ -139,6 +191,65 @@ impl PlayerService {
         Ok(())
     }
 
+    /// 当前曲目的章节（MP4/M4B 章节轨或 Nero 章节、MP3 的 ID3 `CHAP`）；没有章节时为空。
+    #[napi]
+    pub async fn get_chapters(&self) -> Result<Vec<ChapterInfo>> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::GetChapters(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Chapter query interrupted"))
+    }
+
+    /// 跳到下一章开头；已在最后一章或没有章节时返回 false。
+    #[napi]
+    pub async fn next_chapter(&self) -> Result<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::NextChapter(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Chapter seek interrupted"))
+    }
+
+    /// 本章已播放超过 3 秒时回到本章开头，否则跳到上一章开头。
+    #[napi]
+    pub async fn previous_chapter(&self) -> Result<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::PreviousChapter(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Chapter seek interrupted"))
+    }
+
+    /// 开始拖动进度条：之后的 `scrub_to` 只做粗略定位并播放短片段预览。
+    #[napi]
+    pub fn begin_scrub(&self) -> Result<()> {
+        let _ = self.sender.send(PlayerCommand::BeginScrub);
+        Ok(())
+    }
+
+    /// 拖动中的位置更新，频繁调用时只预览最新的位置。
+    #[napi]
+    pub fn scrub_to(&self, time_secs: f64) -> Result<()> {
+        let _ = self.sender.send(PlayerCommand::ScrubTo(time_secs));
+        Ok(())
+    }
+
+    /// 松开进度条：`commit`（缺省为真）时精确 seek 到最后预览的位置，否则回到拖动前的位置。
+    #[napi]
+    pub fn end_scrub(&self, commit: Option<bool>) -> Result<()> {
+        let _ = self
+            .sender
+            .send(PlayerCommand::EndScrub(commit.unwrap_or(true)));
+        Ok(())
+    }
+
     #[napi]
     pub async fn switch_output_device(&self, device_id: Option<String>) -> Result<()> {
         let (tx, rx) = oneshot::channel();
@@ -163,6 +274,46 @@ impl PlayerService {
             .map_err(Error::from_reason)
     }
 
+    /// 设置按优先级排列的输出设备 ID（传空数组关闭）。立即切到其中可用的
+    /// 最高优先级设备，之后设备插拔时也会自动切换。
+    #[napi]
+    pub async fn set_preferred_output_devices(&self, device_ids: Vec<String>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::SetPreferredOutputDevices(device_ids, tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Device switch interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
+    /// 等待输出设备插拔，返回变化后的设备列表（已按偏好列表切换过设备）。
+    #[napi]
+    pub async fn wait_output_devices_change(&self) -> Result<Vec<AudioDeviceInfo>> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::WaitOutputDevicesChange(tx))
+            .map_err(|_| Error::from_reason("Worker shutdown"))?;
+        rx.await
+            .map_err(|_| Error::from_reason("Device watch interrupted"))
+    }
+
+    /// 查询输出设备支持的采样率、声道数、采样格式和缓冲区范围；
+    /// `hw:` 设备额外给出驱动的 hw_params。正在独占播放的设备可能查询失败。
+    #[napi]
+    pub async fn get_device_capabilities(&self, id: String) -> Result<DeviceCapabilitiesInfo> {
+        native_runtime()
+            .spawn_blocking(move || {
+                crate::audio::capabilities::probe_output_device(&id)
+                    .map(DeviceCapabilitiesInfo::from)
+                    .map_err(|error| error.to_string())
+            })
+            .await
+            .map_err(|error| Error::from_reason(error.to_string()))?
+            .map_err(Error::from_reason)
+    }
+
     #[napi]
     pub async fn get_file_duration_ms(&self, path: String) -> Result<i64> {
         crate::audio::decoder::probe_file_duration_ms(path)
@@ -171,11 +322,368 @@ impl PlayerService {
             .map_err(|error| Error::from_reason(error.to_string()))
     }
 
+    /// 解析 .cue 文件（支持 GBK 等非 UTF-8 编码），列出各音轨及其在音频文件中的区间。
+    #[napi]
+    pub async fn get_cue_tracks(&self, path: String) -> Result<Vec<CueTrackInfo>> {
+        native_runtime()
+            .spawn_blocking(move || cue::read_file(Path::new(&path)))
+            .await
+            .map_err(|error| Error::from_reason(error.to_string()))?
+            .map(|sheet| sheet.tracks.iter().map(CueTrackInfo::from).collect())
+            .map_err(|error| Error::from_reason(error.to_string()))
+    }
+
+    /// 读取官方客户端下载的 .ncm 文件内嵌的歌曲信息与封面。
+    #[napi]
+    pub async fn get_ncm_info(&self, path: String) -> Result<NcmFileInfo> {
+        native_runtime()
+            .spawn_blocking(move || crate::audio::ncm::read_header(Path::new(&path)))
+            .await
+            .map_err(|error| Error::from_reason(error.to_string()))?
+            .map(NcmFileInfo::from)
+            .map_err(|error| Error::from_reason(error.to_string()))
+    }
+
+    /// 把 .ncm 解密成原始 FLAC/MP3，`output_dir` 缺省时写到源文件所在目录；
+    /// 返回输出文件路径。输出文件已存在时报错，`overwrite` 为 true 才覆盖。
+    #[napi]
+    pub async fn convert_ncm(
+        &self,
+        path: String,
+        output_dir: Option<String>,
+        overwrite: Option<bool>,
+    ) -> Result<String> {
+        native_runtime()
+            .spawn_blocking(move || {
+                crate::audio::ncm::convert_file(
+                    Path::new(&path),
+                    output_dir.as_deref().map(Path::new),
+                    overwrite.unwrap_or(false),
+                )
+            })
+            .await
+            .map_err(|error| Error::from_reason(error.to_string()))?
+            .map(|output| output.to_string_lossy().into_owned())
+            .map_err(|error| Error::from_reason(error.to_string()))
+    }
+
+    /// 不经过播放、以最快速度把音源解码后导出为 WAV、FLAC 或裸 PCM（`format` 为
+    /// "wav"/"flac"/"pcm"），可选重采样、增益与抖动，原有标签一并写入。
+    /// 同一 `dest` 再次导出会取消前一次；成功后才替换 `dest`，失败或取消时保留原有文件。
+    #[napi]
+    pub async fn export_track(
+        &self,
+        source: ExportSourceConfig,
+        dest: String,
+        format: String,
+        options: Option<ExportOptionsConfig>,
+    ) -> Result<ExportResultInfo> {
+        let source = ExportSource::try_from(source).map_err(Error::from_reason)?;
+        let format = ExportFormat::try_from(format.as_str()).map_err(Error::from_reason)?;
+        let options =
+            ExportOptions::try_from(options.unwrap_or_default()).map_err(Error::from_reason)?;
+
+        let control = Arc::new(ExportControl::new());
+        if let Some(previous) = self
+            .exports
+            .lock()
+            .unwrap()
+            .insert(dest.clone(), Arc::clone(&control))
+        {
+            previous.cancel();
+        }
+        let task_control = Arc::clone(&control);
+        let task_dest = dest.clone();
+        let result = native_runtime()
+            .spawn(async move {
+                let meta = probe_export_source(source).await?;
+                native_runtime()
+                    .spawn_blocking(move || {
+                        export::export_track(
+                            meta,
+                            Path::new(&task_dest),
+                            format,
+                            options,
+                            &task_control,
+                        )
+                        .map_err(|error| error.to_string())
+                    })
+                    .await
+                    .map_err(|error| error.to_string())?
+            })
+            .await
+            .map_err(|error| error.to_string())
+            .and_then(|result| result);
+
+        let mut exports = self.exports.lock().unwrap();
+        if exports
+            .get(&dest)
+            .is_some_and(|active| Arc::ptr_eq(active, &control))
+        {
+            exports.remove(&dest);
+        }
+        result
+            .map(ExportResultInfo::from)
+            .map_err(Error::from_reason)
+    }
+
+    /// 查询导出进度；`dest` 没有正在进行的导出时返回空。
+    #[napi]
+    pub fn get_export_progress(&self, dest: String) -> Option<ExportProgressInfo> {
+        self.exports
+            .lock()
+            .unwrap()
+            .get(&dest)
+            .map(|control| ExportProgressInfo::from(control.progress()))
+    }
+
+    /// 取消导出；返回是否有正在进行的导出。
+    #[napi]
+    pub fn cancel_export(&self, dest: String) -> bool {
+        let control = self.exports.lock().unwrap().remove(&dest);
+        control.inspect(|control| control.cancel()).is_some()
+    }
+
+    /// 为解码线程和输出回调线程申请实时调度（SCHED_RR/SCHED_FIFO），
+    /// 权限不足时回退到 nice。默认关闭，下一次开始播放时生效。
+    #[napi]
+    pub fn set_realtime_scheduling(&self, enabled: bool) -> Result<()> {
+        let _ = self
+            .sender
+            .send(PlayerCommand::SetRealtimeScheduling(enabled));
+        Ok(())
+    }
+
+    /// 严格 BitPerfect 播放时绕过 cpal 直连 ALSA `hw:` 设备（显式 hw_params、
+    /// S24_3LE、mmap/writei、xrun 自动恢复）。默认关闭，下一次开始播放时生效。
+    #[napi]
+    pub fn set_alsa_hw_output(&self, config: AlsaHwOutputConfig) -> Result<()> {
+        let _ = self
+            .sender
+            .send(PlayerCommand::SetAlsaHwOutput(config.into()));
+        Ok(())
+    }
+
+    /// 修剪曲目首尾静音、跳过曲目中间的长静音，对正在播放的曲目立即生效。
+    /// 进度仍按音源时间轴计算；严格 BitPerfect 与 DoP 输出下不生效。
+    #[napi]
+    pub fn set_silence_options(&self, config: SilenceOptionsConfig) -> Result<()> {
+        let options = SilenceOptions::try_from(config).map_err(Error::from_reason)?;
+        let _ = self.sender.send(PlayerCommand::SetSilenceOptions(options));
+        Ok(())
+    }
+
+    /// 在耳机等声道少于音源的设备上，把 5.1/7.1 音源经 HRTF 卷积渲染成双耳信号，
+    /// 代替简单下混。按设备设置，下一次开始播放时生效；严格 BitPerfect 与 DoP 输出下不生效。
+    #[napi]
+    pub async fn set_hrtf(&self, config: HrtfConfig) -> Result<()> {
+        let dataset = match (config.enabled, config.sofa_path) {
+            (false, _) => None,
+            (true, None) => Some(HrtfDataset::Builtin),
+            (true, Some(path)) => {
+                let set = native_runtime()
+                    .spawn_blocking(move || HrirSet::load_sofa(Path::new(&path)))
+                    .await
+                    .map_err(|error| Error::from_reason(error.to_string()))?
+                    .map_err(|error| Error::from_reason(format!("读取 SOFA 文件失败：{error}")))?;
+                Some(HrtfDataset::Sofa(Arc::new(set)))
+            }
+        };
+        let _ = self
+            .sender
+            .send(PlayerCommand::SetHrtf(config.device_id, dataset));
+        Ok(())
+    }
+
+    /// 某个设备是否接受 DoP（DSD over PCM）。未开启时，非严格 BitPerfect 下的 DSD 音源
+    /// 一律转成 PCM 播放，以免不解码 DoP 的 DAC 播出噪声；下一次开始播放时生效。
+    #[napi]
+    pub fn set_dop_output(&self, config: DopOutputConfig) -> Result<()> {
+        let _ = self.sender.send(PlayerCommand::SetDopOutput(
+            config.device_id,
+            config.enabled,
+        ));
+        Ok(())
+    }
+
+    /// `jack:<客户端>` 设备（如 `jack:system`）的 JACK 客户端名称与自动连线设置，
+    /// 下一次开始播放时生效。服务器采样率与音源不同时会重采样。
+    #[napi]
+    pub fn set_jack_output(&self, config: JackOutputConfig) -> Result<()> {
+        let _ = self
+            .sender
+            .send(PlayerCommand::SetJackOutput(config.into()));
+        Ok(())
+    }
+
+    /// 虚拟输出的设置：列出的 `file:` 路径与 `fifo:` 管道及其 PCM 格式，下一次开始播放时生效。
+    /// 选中 `null:`、`file:<路径>` 或 `fifo:<路径>` 设备即可在没有声卡时播放。
+    #[napi]
+    pub fn set_virtual_output(&self, config: VirtualOutputConfig) -> Result<()> {
+        let _ = self
+            .sender
+            .send(PlayerCommand::SetVirtualOutput(config.into()));
+        Ok(())
+    }
+
+    /// 当前输出设备所在声卡的硬件音量（ALSA simple mixer），含 dB 范围。
+    #[napi]
+    pub async fn get_hw_volume(&self) -> Result<HwVolumeInfo> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::GetHwVolume(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Volume query interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
+    /// 设置硬件音量，`volume` 为 0..=1；dB 范围较大的控件按 dB 做感知映射。
+    #[napi]
+    pub async fn set_hw_volume(&self, volume: f64) -> Result<HwVolumeInfo> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::SetHwVolume(volume, tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Volume update interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
+    #[napi]
+    pub async fn set_hw_mute(&self, muted: bool) -> Result<HwVolumeInfo> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::SetHwMute(muted, tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Volume update interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
+    /// 播放器音量变化时调用。严格 BitPerfect 播放中音量交给硬件混音器并返回 true；
+    /// 其余情况返回 false，由调用方照常处理。
+    #[napi]
+    pub async fn set_volume(&self, volume: f64) -> Result<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::SetVolume(volume, tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Volume update interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
+    /// BitPerfect 校验模式：对解码出的音源样本和交给输出回调的样本分别做哈希并比对，
+    /// 结果见 `get_bit_perfect_report`。默认关闭，下一次开始播放时生效。
+    #[napi]
+    pub fn set_bit_perfect_verification(&self, enabled: bool) -> Result<()> {
+        let _ = self
+            .sender
+            .send(PlayerCommand::SetBitPerfectVerification(enabled));
+        Ok(())
+    }
+
+    /// 当前播放的 BitPerfect 诊断：音源格式、协商出的输出配置、驱动实际的 hw_params、
+    /// 途中的转换环节、设备预留状态以及校验结果。
+    #[napi]
+    pub async fn get_bit_perfect_report(&self) -> Result<BitPerfectReportInfo> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::GetBitPerfectReport(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Diagnostics query interrupted"))
+    }
+
+    /// 播放管线健康指标：欠载次数、缓冲水位历史、解码耗时、seek 延迟、
+    /// 输出回调抖动和网络卡顿。切换输出设备会重建播放器，计数随之清零。
+    #[napi]
+    pub async fn get_pipeline_stats(&self) -> Result<PipelineStatsInfo> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::GetPipelineStats(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Diagnostics query interrupted"))
+    }
+
+    /// 播放中输出设备失效时的处理方式：`"pause"`（默认）暂停、
+    /// `"fallback_to_default"` 切换到默认设备继续、`"wait_for_device"` 等待原设备重新接入后继续。
+    #[napi]
+    pub fn set_device_loss_policy(&self, policy: String) -> Result<()> {
+        let policy = DeviceLossPolicy::try_from(policy.as_str()).map_err(Error::from_reason)?;
+        let _ = self.sender.send(PlayerCommand::SetDeviceLossPolicy(policy));
+        Ok(())
+    }
+
+    #[napi]
+    pub async fn get_scheduling_diagnostics(&self) -> Result<SchedulingDiagnostics> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::GetSchedulingDiagnostics(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Diagnostics query interrupted"))
+    }
+
+    /// 设置与主输出同步播放的副输出设备（传空数组关闭）。
+    /// 每个副输出独立做漂移补偿重采样，`delayMs` 用于与主输出对齐。
+    #[napi]
+    pub async fn set_secondary_outputs(&self, outputs: Vec<SecondaryOutputConfig>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::SetSecondaryOutputs(
+                outputs.into_iter().map(Into::into).collect(),
+                tx,
+            ))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Secondary output update interrupted"))?
+            .map_err(Error::from_reason)
+    }
+
+    #[napi]
+    pub async fn get_secondary_outputs(&self) -> Result<Vec<SecondaryOutputStatus>> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::GetSecondaryOutputs(tx))
+            .map_err(|_| Error::from_reason("Background worker died"))?;
+
+        rx.await
+            .map_err(|_| Error::from_reason("Secondary output query interrupted"))
+    }
+
     #[napi(getter)]
     pub fn progress_ms(&self) -> u32 {
         self.shared_state.progress_ms()
     }
 
+    /// 当前播放内容的时长；URL 播放时由探测结果得出，容器未给出总长度时为估算值。
+    #[napi(getter)]
+    pub fn duration_ms(&self) -> Option<u32> {
+        self.shared_state.duration().duration_ms
+    }
+
+    /// 当前播放位置所在章节的下标，对应 `get_chapters` 的 `index`。
+    #[napi(getter)]
+    pub fn current_chapter(&self) -> Option<u32> {
+        self.shared_state.chapter_index()
+    }
+
+    #[napi(getter)]
+    pub fn is_duration_estimated(&self) -> bool {
+        self.shared_state.duration().estimated
+    }
+
     #[napi(getter)]
     pub fn is_playing(&self) -> bool {
         self.shared_state.is_playing()
@@ -200,6 +708,60 @@ impl PlayerService {
         rx.await
             .map_err(|_| Error::from_reason("Playback task interrupted"))
     }
+
+    /// 等待时长发生变化（开始播放新内容、估算值被修正或停止播放），返回新的时长。
+    #[napi]
+    pub async fn wait_duration_change(&self) -> Result<PlaybackDurationInfo> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(PlayerCommand::WaitDurationChange(tx))
+            .map_err(|_| Error::from_reason("Worker shutdown"))?;
+        rx.await
+            .map_err(|_| Error::from_reason("Duration watch interrupted"))
+    }
+}
+
+async fn resolve_cue_track(
+    path: String,
+    cue_track: Option<u32>,
+) -> Result<FileRangePlaybackRequest> {
+    let sheet = native_runtime()
+        .spawn_blocking(move || cue::read_file(Path::new(&path)))
+        .await
+        .map_err(|error| Error::from_reason(error.to_string()))?
+        .map_err(|error| Error::from_reason(error.to_string()))?;
+
+    let track = match cue_track {
+        Some(number) => sheet.track(number),
+        None => sheet.tracks.first(),
+    }
+    .ok_or_else(|| Error::from_reason(format!("CUE 中没有音轨 {}", cue_track.unwrap_or(1))))?;
+
+    Ok(FileRangePlaybackRequest {
+        path: track.file.to_string_lossy().into_owned(),
+        range: track.range(),
+        next_range: sheet.following_track(track.number).map(|next| next.range()),
+    })
+}
+
+async fn probe_export_source(source: ExportSource) -> std::result::Result<AudioMetadata, String> {
+    match source {
+        ExportSource::File(path) => player::probe_file(&path).await,
+        ExportSource::Url(url) => player::probe_url(&url, None).await,
+        ExportSource::CachedUrl(request) => {
+            player::probe_cached_url(
+                &request.url,
+                &request.cache_path,
+                &request.metadata_path,
+                request.duration_ms,
+                request.cache_ahead_secs,
+                request.max_cache_ahead_bytes,
+                None,
+            )
+            .await
+        }
+    }
+    .map_err(|error| error.to_string())
 }
 
 fn playback_options(strict_bit_perfect: Option<bool>) -> PlaybackOptions {
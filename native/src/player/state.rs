@@ -1,19 +1,28 @@
 use std::sync::atomic::{AtomicU32, Ordering};
 
-use super::types::PlaybackStatus;
+use super::types::{PlaybackDurationInfo, PlaybackStatus};
 
 pub(crate) struct SharedState {
     progress_ms: AtomicU32,
     playback_status: AtomicU32,
     buffering: AtomicU32,
+    duration_ms: AtomicU32,
+    duration_estimated: AtomicU32,
+    chapter_index: AtomicU32,
 }
 
+const UNKNOWN_DURATION_MS: u32 = u32::MAX;
+const NO_CHAPTER: u32 = u32::MAX;
+
 impl SharedState {
     pub(crate) fn new() -> Self {
         Self {
             progress_ms: AtomicU32::new(0),
             playback_status: AtomicU32::new(PlaybackStatus::Stopped.as_u32()),
             buffering: AtomicU32::new(0),
+            duration_ms: AtomicU32::new(UNKNOWN_DURATION_MS),
+            duration_estimated: AtomicU32::new(0),
+            chapter_index: AtomicU32::new(NO_CHAPTER),
         }
     }
 
@@ -49,9 +58,39 @@ impl SharedState {
         self.buffering.store(u32::from(buffering), ordering);
     }
 
+    pub(crate) fn duration(&self) -> PlaybackDurationInfo {
+        let duration_ms = self.duration_ms.load(Ordering::Relaxed);
+        PlaybackDurationInfo {
+            duration_ms: (duration_ms != UNKNOWN_DURATION_MS).then_some(duration_ms),
+            estimated: self.duration_estimated.load(Ordering::Relaxed) != 0,
+        }
+    }
+
+    pub(crate) fn set_duration(&self, duration: &PlaybackDurationInfo) {
+        self.duration_ms.store(
+            duration.duration_ms.unwrap_or(UNKNOWN_DURATION_MS),
+            Ordering::SeqCst,
+        );
+        self.duration_estimated
+            .store(u32::from(duration.estimated), Ordering::SeqCst);
+    }
+
+    /// 当前播放位置所在的章节下标；没有章节或还没到第一章时为 `None`。
+    pub(crate) fn chapter_index(&self) -> Option<u32> {
+        let index = self.chapter_index.load(Ordering::Relaxed);
+        (index != NO_CHAPTER).then_some(index)
+    }
+
+    pub(crate) fn set_chapter_index(&self, index: Option<u32>) {
+        self.chapter_index
+            .store(index.unwrap_or(NO_CHAPTER), Ordering::Relaxed);
+    }
+
     pub(crate) fn reset_playback(&self) {
         self.set_playback_status(PlaybackStatus::Stopped, Ordering::SeqCst);
         self.set_progress_ms(0, Ordering::SeqCst);
         self.set_buffering(false, Ordering::SeqCst);
+        self.set_duration(&PlaybackDurationInfo::default());
+        self.set_chapter_index(None);
     }
 }
@@ -1,26 +1,41 @@
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use tokio::sync::{Notify, oneshot};
 
 use crate::audio::OutputDeviceInfo;
+use crate::audio::alsa_hw::AlsaHwOptions;
+use crate::audio::bit_perfect::{BitPerfectReport, VerificationReport};
+use crate::audio::chapters::Chapter;
+use crate::audio::hrtf::HrtfDataset;
+use crate::audio::hw_mixer::HwVolume;
+use crate::audio::jack_output::JackOptions;
+use crate::audio::multi_output::{SecondaryOutputReport, SecondaryOutputTarget};
+use crate::audio::pipeline_stats::{PipelineStatsSnapshot, TimingSnapshot};
+use crate::audio::player::{PlaybackRange, StreamDuration};
+use crate::audio::silence::SilenceOptions;
+use crate::audio::thread_priority::SchedulingReport;
+use crate::audio::virtual_output::VirtualOutputOptions;
 
 use super::backend::{PlayerBackend, PlayerFactory};
 use super::command::PlayerCommand;
 use super::state::SharedState;
 use super::types::{
-    AudioDeviceInfo, BackendFuture, BackendResult, CachedUrlPlaybackRequest, PlaybackOptions,
-    PlaybackSource, PlaybackStatus, SignalFuture, duration_to_millis,
+    AudioDeviceInfo, BackendFuture, BackendResult, BufferPlaybackRequest, CachedUrlPlaybackRequest,
+    DeviceLossPolicy, FileRangePlaybackRequest, PlaybackDurationInfo, PlaybackOptions,
+    PlaybackSource, PlaybackStatus, SchedulingDiagnostics, SecondaryOutputStatus, SignalFuture,
+    SilenceOptionsConfig, TimingStatsInfo, duration_to_millis,
 };
-use super::worker::WorkerCore;
+use super::worker::{DEVICE_POLL_TICKS, DEVICE_RETRY_TICKS, DEVICE_SCAN_POLLS, WorkerCore};
 
 #[derive(Clone)]
 struct MockFactory {
     events: Arc<Mutex<Vec<String>>>,
     fail_device: Option<String>,
-    devices: Vec<OutputDeviceInfo>,
+    devices: Arc<Mutex<Vec<OutputDeviceInfo>>>,
+    card_signature: Arc<Mutex<Option<String>>>,
 }
 
 impl MockFactory {
@@ -28,7 +43,8 @@ impl MockFactory {
         Self {
             events: Arc::new(Mutex::new(Vec::new())),
             fail_device: None,
-            devices: test_devices(),
+            devices: Arc::new(Mutex::new(test_devices())),
+            card_signature: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -41,6 +57,20 @@ impl MockFactory {
     fn events(&self) -> Vec<String> {
         self.events.lock().unwrap().clone()
     }
+
+    /// 模拟拔出设备，返回被拔出的设备以便重新接入。
+    fn unplug(&self, device_id: &str) -> OutputDeviceInfo {
+        let mut devices = self.devices.lock().unwrap();
+        let index = devices
+            .iter()
+            .position(|device| device.id == device_id)
+            .unwrap();
+        devices.remove(index)
+    }
+
+    fn plug_in(&self, device: OutputDeviceInfo) {
+        self.devices.lock().unwrap().push(device);
+    }
 }
 
 impl PlayerFactory for MockFactory {
@@ -57,28 +87,55 @@ impl PlayerFactory for MockFactory {
         {
             return Err(format!("failed to create device: {label}"));
         }
+        if let Some(device_name) = device_name
+            && !self
+                .devices
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|device| device.id == device_name)
+        {
+            return Err(format!("Device not found: {label}"));
+        }
 
-        Ok(MockPlayer::new(
+        let mut player = MockPlayer::new(
             device_name.map(str::to_string),
-            self.devices.clone(),
+            Arc::clone(&self.devices),
             Arc::clone(&self.events),
-        ))
+        );
+        player.card_signature = Arc::clone(&self.card_signature);
+        Ok(player)
     }
 }
 
 struct MockPlayer {
     device_name: Option<String>,
-    devices: Vec<OutputDeviceInfo>,
+    devices: Arc<Mutex<Vec<OutputDeviceInfo>>>,
     events: Arc<Mutex<Vec<String>>>,
     progress: Arc<Mutex<Duration>>,
     finished: Arc<AtomicBool>,
     finish_notify: Arc<Notify>,
+    range_transitions: Arc<AtomicU64>,
+    duration: Arc<Mutex<Option<StreamDuration>>>,
+    chapters: Vec<Chapter>,
+    output_fault: Arc<Mutex<Option<String>>>,
+    realtime_scheduling: bool,
+    alsa_hw: AlsaHwOptions,
+    jack: JackOptions,
+    virtual_output: VirtualOutputOptions,
+    silence: SilenceOptions,
+    hrtf: Option<String>,
+    dop: bool,
+    bit_perfect_verification: bool,
+    secondary_outputs: Vec<SecondaryOutputTarget>,
+    card_signature: Arc<Mutex<Option<String>>>,
+    scrubbing: AtomicBool,
 }
 
 impl MockPlayer {
     fn new(
         device_name: Option<String>,
-        devices: Vec<OutputDeviceInfo>,
+        devices: Arc<Mutex<Vec<OutputDeviceInfo>>>,
         events: Arc<Mutex<Vec<String>>>,
     ) -> Self {
         Self {
@@ -88,6 +145,21 @@ impl MockPlayer {
             progress: Arc::new(Mutex::new(Duration::ZERO)),
             finished: Arc::new(AtomicBool::new(false)),
             finish_notify: Arc::new(Notify::new()),
+            range_transitions: Arc::new(AtomicU64::new(0)),
+            duration: Arc::new(Mutex::new(None)),
+            chapters: Vec::new(),
+            output_fault: Arc::new(Mutex::new(None)),
+            realtime_scheduling: false,
+            alsa_hw: AlsaHwOptions::default(),
+            jack: JackOptions::default(),
+            virtual_output: VirtualOutputOptions::default(),
+            silence: SilenceOptions::default(),
+            hrtf: None,
+            dop: false,
+            bit_perfect_verification: false,
+            secondary_outputs: Vec::new(),
+            card_signature: Arc::new(Mutex::new(None)),
+            scrubbing: AtomicBool::new(false),
         }
     }
 
@@ -103,12 +175,35 @@ impl MockPlayer {
         *self.progress.lock().unwrap() = progress;
     }
 
+    fn set_duration(&self, duration: Option<StreamDuration>) {
+        *self.duration.lock().unwrap() = duration;
+    }
+
+    fn fail_output(&self, reason: &str) {
+        *self.output_fault.lock().unwrap() = Some(reason.to_string());
+    }
+
     fn set_finished(&self, finished: bool) {
         self.finished.store(finished, Ordering::SeqCst);
         if finished {
             self.finish_notify.notify_waiters();
         }
     }
+
+    /// 模拟输出端播完当前区间后无缝续上排队的区间。
+    fn roll_over_range(&self) {
+        self.range_transitions.fetch_add(1, Ordering::SeqCst);
+        self.set_progress(Duration::ZERO);
+        self.finish_notify.notify_waiters();
+    }
+}
+
+fn format_range(range: &PlaybackRange) -> String {
+    let end = range
+        .end
+        .map(|end| duration_to_millis(end).to_string())
+        .unwrap_or_default();
+    format!("{}-{end}", duration_to_millis(range.start))
 }
 
 impl PlayerBackend for MockPlayer {
@@ -170,6 +265,60 @@ impl PlayerBackend for MockPlayer {
         Box::pin(async { Ok(()) })
     }
 
+    fn play_buffer<'a>(
+        &'a mut self,
+        request: &'a BufferPlaybackRequest,
+        start_at: Option<Duration>,
+        _options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()> {
+        let label = self.label().to_string();
+        let len = request.data.len();
+        let extension = request.extension_hint.as_deref().unwrap_or("?");
+        let start_at = start_at.unwrap_or(Duration::ZERO);
+        self.log(format!(
+            "player[{label}] play_buffer:{len}B.{extension}@{}",
+            duration_to_millis(start_at)
+        ));
+        self.set_progress(start_at);
+        self.set_finished(false);
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn play_file_range<'a>(
+        &'a mut self,
+        request: &'a FileRangePlaybackRequest,
+        start_at: Option<Duration>,
+        _options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()> {
+        let label = self.label().to_string();
+        let path = request.path.clone();
+        let range = format_range(&request.range);
+        let start_at = start_at.unwrap_or(Duration::ZERO);
+        self.log(format!(
+            "player[{label}] play_file_range:{path}[{range}]@{}",
+            duration_to_millis(start_at)
+        ));
+        self.set_progress(start_at);
+        self.set_finished(false);
+        self.range_transitions.store(0, Ordering::SeqCst);
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn queue_next_range(&self, request: &FileRangePlaybackRequest) {
+        let next = request
+            .next_range
+            .as_ref()
+            .map(format_range)
+            .unwrap_or_default();
+        self.log(format!("player[{}] queue_next_range:{next}", self.label()));
+    }
+
+    fn range_transitions(&self) -> u64 {
+        self.range_transitions.load(Ordering::SeqCst)
+    }
+
     fn pause(&self) {
         self.log(format!("player[{}] pause", self.label()));
     }
@@ -192,10 +341,37 @@ impl PlayerBackend for MockPlayer {
         self.set_progress(target);
     }
 
+    fn begin_scrub(&self) {
+        self.log(format!("player[{}] begin_scrub", self.label()));
+        self.scrubbing.store(true, Ordering::SeqCst);
+    }
+
+    fn scrub_to(&self, target: Duration) -> bool {
+        self.log(format!(
+            "player[{}] scrub_to:{}",
+            self.label(),
+            duration_to_millis(target)
+        ));
+        self.scrubbing.load(Ordering::SeqCst)
+    }
+
+    fn end_scrub(&self, commit: bool) -> bool {
+        self.log(format!("player[{}] end_scrub:{commit}", self.label()));
+        self.scrubbing.swap(false, Ordering::SeqCst)
+    }
+
     fn progress(&self) -> Duration {
         *self.progress.lock().unwrap()
     }
 
+    fn duration(&self) -> Option<StreamDuration> {
+        *self.duration.lock().unwrap()
+    }
+
+    fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
     fn is_buffering(&self) -> bool {
         false
     }
@@ -215,10 +391,16 @@ impl PlayerBackend for MockPlayer {
         })
     }
 
+    fn output_fault(&self) -> Option<String> {
+        self.output_fault.lock().unwrap().clone()
+    }
+
     fn output_devices(&self) -> BackendResult<Vec<OutputDeviceInfo>> {
         let current_id = self.device_name.as_deref().unwrap_or("speaker");
         Ok(self
             .devices
+            .lock()
+            .unwrap()
             .iter()
             .cloned()
             .map(|mut device| {
@@ -227,6 +409,196 @@ impl PlayerBackend for MockPlayer {
             })
             .collect())
     }
+
+    fn device_signature(&self) -> Option<String> {
+        self.card_signature.lock().unwrap().clone()
+    }
+
+    fn set_realtime_scheduling(&mut self, enabled: bool) {
+        if self.realtime_scheduling == enabled {
+            return;
+        }
+        self.log(format!(
+            "player[{}] realtime_scheduling:{enabled}",
+            self.label()
+        ));
+        self.realtime_scheduling = enabled;
+    }
+
+    fn scheduling_report(&self) -> SchedulingReport {
+        SchedulingReport {
+            enabled: self.realtime_scheduling,
+            decode_thread: None,
+            output_thread: None,
+            rtprio_limit: None,
+        }
+    }
+
+    fn pipeline_stats(&self) -> PipelineStatsSnapshot {
+        PipelineStatsSnapshot {
+            underruns: 2,
+            buffered_ms: 1500,
+            buffer_capacity_ms: 10_000,
+            fill_history_ms: vec![1000, 1500],
+            decode: TimingSnapshot {
+                count: 4,
+                avg_us: 250,
+                max_us: 1000,
+                last_us: 100,
+            },
+            ..PipelineStatsSnapshot::default()
+        }
+    }
+
+    fn set_alsa_hw_output(&mut self, options: AlsaHwOptions) {
+        if self.alsa_hw == options {
+            return;
+        }
+        self.log(format!(
+            "player[{}] alsa_hw:{}",
+            self.label(),
+            options.enabled
+        ));
+        self.alsa_hw = options;
+    }
+
+    fn set_jack_output(&mut self, options: JackOptions) {
+        if self.jack == options {
+            return;
+        }
+        self.log(format!(
+            "player[{}] jack:{}",
+            self.label(),
+            options.client_name
+        ));
+        self.jack = options;
+    }
+
+    fn set_virtual_output(&mut self, options: VirtualOutputOptions) {
+        if self.virtual_output == options {
+            return;
+        }
+        self.log(format!(
+            "player[{}] virtual:{}",
+            self.label(),
+            options.fifo_path
+        ));
+        self.virtual_output = options;
+    }
+
+    fn set_silence_options(&mut self, options: SilenceOptions) {
+        if self.silence == options {
+            return;
+        }
+        self.log(format!(
+            "player[{}] silence:trim_edges={} skip_gaps={}",
+            self.label(),
+            options.trim_edges,
+            options.skip_gaps
+        ));
+        self.silence = options;
+    }
+
+    fn set_hrtf(&mut self, dataset: Option<HrtfDataset>) {
+        let name = dataset.as_ref().map(|dataset| dataset.name().to_string());
+        if self.hrtf == name {
+            return;
+        }
+        self.log(format!(
+            "player[{}] hrtf:{}",
+            self.label(),
+            name.as_deref().unwrap_or("off")
+        ));
+        self.hrtf = name;
+    }
+
+    fn set_dop_output(&mut self, enabled: bool) {
+        if self.dop == enabled {
+            return;
+        }
+        self.log(format!("player[{}] dop:{}", self.label(), enabled));
+        self.dop = enabled;
+    }
+
+    fn hw_volume(&self) -> BackendResult<HwVolume> {
+        Ok(mock_hw_volume(0.5))
+    }
+
+    fn set_hw_volume(&self, volume: f64) -> BackendResult<HwVolume> {
+        self.log(format!("player[{}] hw_volume:{volume}", self.label()));
+        Ok(mock_hw_volume(volume))
+    }
+
+    fn set_hw_mute(&self, muted: bool) -> BackendResult<HwVolume> {
+        self.log(format!("player[{}] hw_mute:{muted}", self.label()));
+        Ok(mock_hw_volume(0.5))
+    }
+
+    fn set_bit_perfect_verification(&mut self, enabled: bool) {
+        if self.bit_perfect_verification == enabled {
+            return;
+        }
+        self.log(format!(
+            "player[{}] bit_perfect_verification:{enabled}",
+            self.label()
+        ));
+        self.bit_perfect_verification = enabled;
+    }
+
+    fn bit_perfect_report(&mut self) -> BitPerfectReport {
+        BitPerfectReport::new(
+            false,
+            None,
+            None,
+            None,
+            VerificationReport {
+                enabled: self.bit_perfect_verification,
+                ..VerificationReport::default()
+            },
+            Vec::new(),
+        )
+    }
+
+    fn set_secondary_outputs(&mut self, targets: Vec<SecondaryOutputTarget>) -> BackendResult<()> {
+        if targets == self.secondary_outputs {
+            return Ok(());
+        }
+        if let Some(missing) = targets.iter().find(|target| {
+            !self
+                .devices
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|device| device.id == target.device_id)
+        }) {
+            return Err(format!("Device not found: {}", missing.device_id));
+        }
+        let summary = targets
+            .iter()
+            .map(|target| format!("{}+{}ms", target.device_id, target.delay_ms))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.log(format!(
+            "player[{}] secondary_outputs:{summary}",
+            self.label()
+        ));
+        self.secondary_outputs = targets;
+        Ok(())
+    }
+
+    fn secondary_output_reports(&self) -> Vec<SecondaryOutputReport> {
+        self.secondary_outputs
+            .iter()
+            .map(|target| SecondaryOutputReport {
+                device_id: target.device_id.clone(),
+                delay_ms: target.delay_ms,
+                sample_rate: 48_000,
+                resample_ratio: 1.0,
+                buffered_ms: target.delay_ms,
+                underruns: 0,
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone)]
@@ -392,6 +764,50 @@ impl PlayerBackend for BusyMockPlayer {
         Box::pin(async { Ok(()) })
     }
 
+    fn play_buffer<'a>(
+        &'a mut self,
+        request: &'a BufferPlaybackRequest,
+        start_at: Option<Duration>,
+        _options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()> {
+        let len = request.data.len();
+        let device_id = self.device_id.clone();
+        let start_at = start_at.unwrap_or(Duration::ZERO);
+        self.log(format!(
+            "player[{device_id}] play_buffer:{len}B@{}",
+            duration_to_millis(start_at)
+        ));
+        *self.progress.lock().unwrap() = start_at;
+        self.finished.store(false, Ordering::SeqCst);
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn play_file_range<'a>(
+        &'a mut self,
+        request: &'a FileRangePlaybackRequest,
+        start_at: Option<Duration>,
+        _options: PlaybackOptions,
+    ) -> BackendFuture<'a, ()> {
+        let path = request.path.clone();
+        let device_id = self.device_id.clone();
+        let start_at = start_at.unwrap_or(Duration::ZERO);
+        self.log(format!(
+            "player[{device_id}] play_file_range:{path}@{}",
+            duration_to_millis(start_at)
+        ));
+        *self.progress.lock().unwrap() = start_at;
+        self.finished.store(false, Ordering::SeqCst);
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn queue_next_range(&self, _request: &FileRangePlaybackRequest) {}
+
+    fn range_transitions(&self) -> u64 {
+        0
+    }
+
     fn pause(&self) {
         self.log(format!("player[{}] pause", self.device_id));
     }
@@ -411,10 +827,28 @@ impl PlayerBackend for BusyMockPlayer {
         *self.progress.lock().unwrap() = target;
     }
 
+    fn begin_scrub(&self) {}
+
+    fn scrub_to(&self, _target: Duration) -> bool {
+        false
+    }
+
+    fn end_scrub(&self, _commit: bool) -> bool {
+        false
+    }
+
     fn progress(&self) -> Duration {
         *self.progress.lock().unwrap()
     }
 
+    fn duration(&self) -> Option<StreamDuration> {
+        None
+    }
+
+    fn chapters(&self) -> &[Chapter] {
+        &[]
+    }
+
     fn is_buffering(&self) -> bool {
         false
     }
@@ -434,6 +868,10 @@ impl PlayerBackend for BusyMockPlayer {
         })
     }
 
+    fn output_fault(&self) -> Option<String> {
+        None
+    }
+
     fn output_devices(&self) -> BackendResult<Vec<OutputDeviceInfo>> {
         Ok(self
             .devices
@@ -445,6 +883,70 @@ impl PlayerBackend for BusyMockPlayer {
             })
             .collect())
     }
+
+    fn device_signature(&self) -> Option<String> {
+        None
+    }
+
+    fn set_realtime_scheduling(&mut self, _enabled: bool) {}
+
+    fn scheduling_report(&self) -> SchedulingReport {
+        SchedulingReport {
+            enabled: false,
+            decode_thread: None,
+            output_thread: None,
+            rtprio_limit: None,
+        }
+    }
+
+    fn pipeline_stats(&self) -> PipelineStatsSnapshot {
+        PipelineStatsSnapshot::default()
+    }
+
+    fn set_alsa_hw_output(&mut self, _options: AlsaHwOptions) {}
+
+    fn set_jack_output(&mut self, _options: JackOptions) {}
+
+    fn set_virtual_output(&mut self, _options: VirtualOutputOptions) {}
+
+    fn set_silence_options(&mut self, _options: SilenceOptions) {}
+
+    fn set_hrtf(&mut self, _dataset: Option<HrtfDataset>) {}
+
+    fn set_dop_output(&mut self, _enabled: bool) {}
+
+    fn hw_volume(&self) -> BackendResult<HwVolume> {
+        Err("no mixer".to_string())
+    }
+
+    fn set_hw_volume(&self, _volume: f64) -> BackendResult<HwVolume> {
+        Err("no mixer".to_string())
+    }
+
+    fn set_hw_mute(&self, _muted: bool) -> BackendResult<HwVolume> {
+        Err("no mixer".to_string())
+    }
+
+    fn set_bit_perfect_verification(&mut self, _enabled: bool) {}
+
+    fn bit_perfect_report(&mut self) -> BitPerfectReport {
+        BitPerfectReport::new(
+            false,
+            None,
+            None,
+            None,
+            VerificationReport::default(),
+            Vec::new(),
+        )
+    }
+
+    fn set_secondary_outputs(&mut self, _targets: Vec<SecondaryOutputTarget>) -> BackendResult<()> {
+        Ok(())
+    }
+
+    fn secondary_output_reports(&self) -> Vec<SecondaryOutputReport> {
+        Vec::new()
+    }
 }
 
 fn test_devices() -> Vec<OutputDeviceInfo> {
@@ -454,16 +956,30 @@ fn test_devices() -> Vec<OutputDeviceInfo> {
             name: "Speaker".to_string(),
             is_default: true,
             is_current: false,
+            hw_volume: false,
         },
         OutputDeviceInfo {
             id: "headphones".to_string(),
             name: "Headphones".to_string(),
             is_default: false,
             is_current: false,
+            hw_volume: false,
         },
     ]
 }
 
+fn mock_hw_volume(volume: f64) -> HwVolume {
+    HwVolume {
+        card: 1,
+        element: "PCM".to_string(),
+        volume,
+        db: None,
+        min_db: None,
+        max_db: None,
+        muted: Some(false),
+    }
+}
+
 fn create_shared_state() -> Arc<SharedState> {
     Arc::new(SharedState::new())
 }
@@ -611,12 +1127,14 @@ async fn get_output_devices_returns_current_and_default_flags() {
                 name: "Speaker".to_string(),
                 is_default: true,
                 is_current: true,
+                hw_volume: false,
             },
             AudioDeviceInfo {
                 id: "headphones".to_string(),
                 name: "Headphones".to_string(),
                 is_default: false,
                 is_current: false,
+                hw_volume: false,
             }
         ]
     );
@@ -733,3 +1251,968 @@ async fn switching_to_the_already_active_device_is_a_noop() {
     );
     assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
 }
+
+/// 每一项输出设置都要在切换设备后重新应用到新的播放器上。
+#[tokio::test]
+async fn output_options_are_reapplied_after_device_switch() {
+    type Case = (fn() -> PlayerCommand, &'static str);
+    let cases: [Case; 7] = [
+        (
+            || PlayerCommand::SetRealtimeScheduling(true),
+            "realtime_scheduling:true",
+        ),
+        (
+            || {
+                PlayerCommand::SetAlsaHwOutput(AlsaHwOptions {
+                    enabled: true,
+                    period_frames: Some(512),
+                    buffer_frames: None,
+                    mmap: true,
+                })
+            },
+            "alsa_hw:true",
+        ),
+        (
+            || {
+                PlayerCommand::SetSilenceOptions(SilenceOptions {
+                    skip_gaps: true,
+                    ..SilenceOptions::default()
+                })
+            },
+            "silence:trim_edges=false skip_gaps=true",
+        ),
+        (
+            || {
+                PlayerCommand::SetJackOutput(JackOptions {
+                    client_name: "ncm-test".to_string(),
+                    auto_connect: false,
+                    connect_ports: Vec::new(),
+                })
+            },
+            "jack:ncm-test",
+        ),
+        (
+            || {
+                PlayerCommand::SetVirtualOutput(VirtualOutputOptions {
+                    fifo_path: "/run/snapserver/fifo".to_string(),
+                    ..VirtualOutputOptions::default()
+                })
+            },
+            "virtual:/run/snapserver/fifo",
+        ),
+        (
+            || PlayerCommand::SetBitPerfectVerification(true),
+            "bit_perfect_verification:true",
+        ),
+        (
+            || {
+                PlayerCommand::SetSecondaryOutputs(
+                    vec![SecondaryOutputTarget {
+                        device_id: "speaker".to_string(),
+                        delay_ms: 35,
+                    }],
+                    oneshot::channel().0,
+                )
+            },
+            "secondary_outputs:speaker+35ms",
+        ),
+    ];
+
+    for (command, applied) in cases {
+        let (mut worker, _shared_state, factory) = create_worker(MockFactory::new());
+        worker.handle_command(command()).await;
+        worker
+            .handle_command(PlayerCommand::PlayFile(
+                "/tmp/test.flac".to_string(),
+                Some(0.0),
+                PlaybackOptions::default(),
+                None,
+            ))
+            .await;
+        let (tx, rx) = oneshot::channel();
+        worker
+            .handle_command(PlayerCommand::SwitchOutputDevice(
+                Some("headphones".to_string()),
+                tx,
+            ))
+            .await;
+        assert!(rx.await.unwrap().is_ok());
+
+        assert_eq!(
+            factory.events(),
+            vec![
+                "create:auto".to_string(),
+                format!("player[auto] {applied}"),
+                "player[auto] play_file:/tmp/test.flac@0".to_string(),
+                "create:headphones".to_string(),
+                format!("player[headphones] {applied}"),
+                "player[headphones] play_file:/tmp/test.flac@0".to_string(),
+                "player[auto] stop".to_string()
+            ],
+            "{applied}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn realtime_scheduling_shows_in_diagnostics() {
+    let (mut worker, _shared_state, _factory) = create_worker(MockFactory::new());
+    worker
+        .handle_command(PlayerCommand::SetRealtimeScheduling(true))
+        .await;
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::GetSchedulingDiagnostics(tx))
+        .await;
+    assert_eq!(
+        rx.await.unwrap(),
+        SchedulingDiagnostics {
+            enabled: true,
+            decode_thread: None,
+            output_thread: None,
+            rtprio_limit: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn bit_perfect_verification_shows_in_report() {
+    let (mut worker, _shared_state, _factory) = create_worker(MockFactory::new());
+    worker
+        .handle_command(PlayerCommand::SetBitPerfectVerification(true))
+        .await;
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::GetBitPerfectReport(tx))
+        .await;
+    let report = rx.await.unwrap();
+    assert!(report.verification.enabled);
+    assert!(!report.bit_perfect);
+    assert_eq!(report.source, None);
+    assert!(report.conversions.is_empty());
+}
+
+#[tokio::test]
+async fn secondary_output_status_is_reported() {
+    let (mut worker, _shared_state, _factory) = create_worker(MockFactory::new());
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetSecondaryOutputs(
+            vec![SecondaryOutputTarget {
+                device_id: "speaker".to_string(),
+                delay_ms: 35,
+            }],
+            tx,
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::GetSecondaryOutputs(tx))
+        .await;
+    assert_eq!(
+        rx.await.unwrap(),
+        vec![SecondaryOutputStatus {
+            device_id: "speaker".to_string(),
+            delay_ms: 35,
+            sample_rate: 48_000,
+            resample_ratio: 1.0,
+            buffered_ms: 35,
+            underruns: 0,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn pipeline_stats_are_reported_in_milliseconds() {
+    let factory = MockFactory::new();
+    let (mut worker, _shared_state, _factory) = create_worker(factory);
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::GetPipelineStats(tx))
+        .await;
+    let stats = rx.await.unwrap();
+    assert_eq!(stats.underruns, 2);
+    assert_eq!(stats.buffered_ms, 1500);
+    assert_eq!(stats.fill_history_ms, vec![1000, 1500]);
+    assert_eq!(
+        stats.decode,
+        TimingStatsInfo {
+            count: 4,
+            avg_ms: 0.25,
+            max_ms: 1.0,
+            last_ms: 0.1,
+        }
+    );
+    assert_eq!(stats.network_stalls, TimingStatsInfo::default());
+}
+
+#[tokio::test]
+async fn hrtf_setting_is_applied_per_output_device() {
+    let factory = MockFactory::new();
+    let (mut worker, _shared_state, factory) = create_worker(factory);
+
+    worker
+        .handle_command(PlayerCommand::SetHrtf(
+            Some(" headphones ".to_string()),
+            Some(HrtfDataset::Builtin),
+        ))
+        .await;
+    for device in [Some("headphones"), None] {
+        let (tx, rx) = oneshot::channel();
+        worker
+            .handle_command(PlayerCommand::SwitchOutputDevice(
+                device.map(str::to_string),
+                tx,
+            ))
+            .await;
+        assert!(rx.await.unwrap().is_ok());
+    }
+    worker
+        .handle_command(PlayerCommand::SetHrtf(None, Some(HrtfDataset::Builtin)))
+        .await;
+    worker
+        .handle_command(PlayerCommand::SetHrtf(None, None))
+        .await;
+
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "create:headphones".to_string(),
+            "player[headphones] hrtf:内置球形头模型".to_string(),
+            "player[auto] stop".to_string(),
+            "create:auto".to_string(),
+            "player[headphones] stop".to_string(),
+            "player[auto] hrtf:内置球形头模型".to_string(),
+            "player[auto] hrtf:off".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dop_setting_is_applied_per_output_device() {
+    let (mut worker, _shared_state, factory) = create_worker(MockFactory::new());
+
+    worker
+        .handle_command(PlayerCommand::SetDopOutput(
+            Some("headphones".to_string()),
+            true,
+        ))
+        .await;
+    for device in [Some("headphones"), None] {
+        let (tx, rx) = oneshot::channel();
+        worker
+            .handle_command(PlayerCommand::SwitchOutputDevice(
+                device.map(str::to_string),
+                tx,
+            ))
+            .await;
+        assert!(rx.await.unwrap().is_ok());
+    }
+
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "create:headphones".to_string(),
+            "player[headphones] dop:true".to_string(),
+            "player[auto] stop".to_string(),
+            "create:auto".to_string(),
+            "player[headphones] stop".to_string()
+        ]
+    );
+}
+
+#[test]
+fn silence_options_config_validates_threshold() {
+    let options = SilenceOptions::try_from(SilenceOptionsConfig {
+        trim_edges: true,
+        skip_silence: false,
+        threshold_db: None,
+        min_silence_ms: Some(1200),
+    })
+    .unwrap();
+    assert!(options.trim_edges);
+    assert_eq!(options.threshold_db, -60.0);
+    assert_eq!(options.min_silence, Duration::from_millis(1200));
+
+    assert!(
+        SilenceOptions::try_from(SilenceOptionsConfig {
+            threshold_db: Some(6.0),
+            ..SilenceOptionsConfig::default()
+        })
+        .is_err()
+    );
+}
+
+#[tokio::test]
+async fn volume_goes_to_hardware_mixer_only_in_strict_bit_perfect_playback() {
+    let factory = MockFactory::new();
+    let (mut worker, _shared_state, factory) = create_worker(factory);
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetVolume(0.3, tx))
+        .await;
+    assert_eq!(rx.await.unwrap(), Ok(false));
+
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/tmp/test.flac".to_string(),
+            None,
+            PlaybackOptions {
+                strict_bit_perfect: true,
+            },
+            None,
+        ))
+        .await;
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetVolume(0.75, tx))
+        .await;
+    assert_eq!(rx.await.unwrap(), Ok(true));
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetHwMute(true, tx))
+        .await;
+    let volume = rx.await.unwrap().unwrap();
+    assert_eq!(volume.element, "PCM");
+
+    worker.handle_command(PlayerCommand::Stop).await;
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetVolume(0.3, tx))
+        .await;
+    assert_eq!(rx.await.unwrap(), Ok(false));
+
+    let events = factory.events();
+    assert!(events.contains(&"player[auto] hw_volume:0.75".to_string()));
+    assert!(events.contains(&"player[auto] hw_mute:true".to_string()));
+    assert!(!events.iter().any(|event| event.contains("hw_volume:0.3")));
+}
+
+#[tokio::test]
+async fn unknown_secondary_output_is_rejected_and_not_remembered() {
+    let factory = MockFactory::new();
+    let (mut worker, _shared_state, factory) = create_worker(factory);
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetSecondaryOutputs(
+            vec![SecondaryOutputTarget {
+                device_id: "missing".to_string(),
+                delay_ms: 0,
+            }],
+            tx,
+        ))
+        .await;
+    assert_eq!(
+        rx.await.unwrap(),
+        Err("Device not found: missing".to_string())
+    );
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SwitchOutputDevice(
+            Some("headphones".to_string()),
+            tx,
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "create:headphones".to_string(),
+            "player[auto] stop".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn switch_output_device_replays_in_memory_buffer_without_copying() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+    let data: Arc<[u8]> = Arc::from(vec![0u8; 4096]);
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::PlayBuffer(
+            BufferPlaybackRequest {
+                data: Arc::clone(&data),
+                extension_hint: Some("flac".to_string()),
+            },
+            Some(0.0),
+            PlaybackOptions::default(),
+            Some(tx),
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+    worker.player.set_progress(Duration::from_millis(1_500));
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SwitchOutputDevice(
+            Some("headphones".to_string()),
+            tx,
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+
+    match worker.current_source.as_ref() {
+        Some(PlaybackSource::Buffer(request, _)) => {
+            assert!(Arc::ptr_eq(&request.data, &data));
+        }
+        other => panic!("unexpected current source: {other:?}"),
+    }
+    assert_eq!(shared_state.progress_ms(), 1_500);
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "player[auto] play_buffer:4096B.flac@0".to_string(),
+            "create:headphones".to_string(),
+            "player[headphones] play_buffer:4096B.flac@1500".to_string(),
+            "player[auto] stop".to_string()
+        ]
+    );
+}
+
+fn cue_track_request(track: usize) -> FileRangePlaybackRequest {
+    let boundaries = [0, 180_000, 420_000, 600_000];
+    let range = |index: usize| {
+        boundaries.get(index).map(|&start| PlaybackRange {
+            start: Duration::from_millis(start),
+            end: boundaries
+                .get(index + 1)
+                .map(|&end| Duration::from_millis(end)),
+        })
+    };
+    FileRangePlaybackRequest {
+        path: "/music/album.flac".to_string(),
+        range: range(track).unwrap(),
+        next_range: range(track + 1),
+    }
+}
+
+#[tokio::test]
+async fn consecutive_cue_track_continues_the_running_stream() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+
+    worker
+        .handle_command(PlayerCommand::PlayFileRange(
+            cue_track_request(0),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    worker.player.set_progress(Duration::from_millis(179_000));
+    worker.tick();
+    worker.player.roll_over_range();
+    worker.tick();
+
+    assert_eq!(shared_state.progress_ms(), 0);
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::PlayFileRange(
+            cue_track_request(1),
+            None,
+            PlaybackOptions::default(),
+            Some(tx),
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+
+    assert_eq!(
+        worker.current_source,
+        Some(PlaybackSource::FileRange(
+            cue_track_request(1),
+            PlaybackOptions::default()
+        ))
+    );
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "player[auto] play_file_range:/music/album.flac[0-180000]@0".to_string(),
+            "player[auto] queue_next_range:420000-600000".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn replaying_or_skipping_cue_tracks_reopens_the_file() {
+    let factory = MockFactory::new();
+    let (mut worker, _shared_state, factory) = create_worker(factory);
+
+    for track in [0, 0, 2] {
+        worker
+            .handle_command(PlayerCommand::PlayFileRange(
+                cue_track_request(track),
+                None,
+                PlaybackOptions::default(),
+                None,
+            ))
+            .await;
+    }
+
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "player[auto] play_file_range:/music/album.flac[0-180000]@0".to_string(),
+            "player[auto] play_file_range:/music/album.flac[0-180000]@0".to_string(),
+            "player[auto] play_file_range:/music/album.flac[420000-600000]@0".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn duration_updates_are_published_and_wake_waiters() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, _factory) = create_worker(factory);
+
+    worker
+        .handle_command(PlayerCommand::PlayUrl(
+            "https://example.com/trial.mp3".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    assert_eq!(shared_state.duration(), PlaybackDurationInfo::default());
+
+    let (tx, mut rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::WaitDurationChange(tx))
+        .await;
+    worker.tick();
+    assert!(rx.try_recv().is_err());
+
+    let estimate = StreamDuration {
+        duration: Duration::from_secs(30),
+        estimated: true,
+    };
+    worker.player.set_duration(Some(estimate));
+    worker.tick();
+    let expected = PlaybackDurationInfo {
+        duration_ms: Some(30_000),
+        estimated: true,
+    };
+    assert_eq!(rx.await.unwrap(), expected);
+    assert_eq!(shared_state.duration(), expected);
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::WaitDurationChange(tx))
+        .await;
+    worker.handle_command(PlayerCommand::Stop).await;
+    worker.tick();
+    assert_eq!(rx.await.unwrap(), PlaybackDurationInfo::default());
+    assert_eq!(shared_state.duration(), PlaybackDurationInfo::default());
+}
+
+#[tokio::test]
+async fn scrub_commands_reach_the_player_and_preview_progress() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+
+    worker.handle_command(PlayerCommand::BeginScrub).await;
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/music/song.flac".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    worker.handle_command(PlayerCommand::BeginScrub).await;
+    worker.handle_command(PlayerCommand::ScrubTo(12.5)).await;
+    assert_eq!(shared_state.progress_ms(), 12_500);
+    worker.handle_command(PlayerCommand::ScrubTo(14.0)).await;
+    worker.handle_command(PlayerCommand::EndScrub(true)).await;
+
+    assert!(shared_state.is_buffering());
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "player[auto] play_file:/music/song.flac@0".to_string(),
+            "player[auto] begin_scrub".to_string(),
+            "player[auto] scrub_to:12500".to_string(),
+            "player[auto] scrub_to:14000".to_string(),
+            "player[auto] end_scrub:true".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn scrub_without_a_session_leaves_progress_and_buffering_alone() {
+    let (mut worker, shared_state, factory) = create_worker(MockFactory::new());
+
+    worker.handle_command(PlayerCommand::ScrubTo(30.0)).await;
+    worker.handle_command(PlayerCommand::EndScrub(true)).await;
+
+    assert_eq!(shared_state.progress_ms(), 0);
+    assert!(!shared_state.is_buffering());
+    assert_eq!(
+        factory.events(),
+        vec![
+            "create:auto".to_string(),
+            "player[auto] scrub_to:30000".to_string(),
+            "player[auto] end_scrub:true".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn chapter_navigation_seeks_and_tracks_the_current_chapter() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+
+    let (tx, rx) = oneshot::channel();
+    worker.handle_command(PlayerCommand::NextChapter(tx)).await;
+    assert!(!rx.await.unwrap());
+
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/books/book.m4b".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    worker.player.chapters = ["序", "第一章", "第二章"]
+        .iter()
+        .enumerate()
+        .map(|(index, title)| Chapter {
+            title: title.to_string(),
+            start: Duration::from_secs(index as u64 * 60),
+            end: Some(Duration::from_secs(index as u64 * 60 + 60)),
+        })
+        .collect();
+
+    let (tx, rx) = oneshot::channel();
+    worker.handle_command(PlayerCommand::GetChapters(tx)).await;
+    let chapters = rx.await.unwrap();
+    assert_eq!(chapters.len(), 3);
+    assert_eq!(chapters[1].title, "第一章");
+    assert_eq!(
+        (chapters[1].start_ms, chapters[1].end_ms),
+        (60_000, Some(120_000))
+    );
+
+    worker.player.set_progress(Duration::from_secs(70));
+    worker.tick();
+    assert_eq!(shared_state.chapter_index(), Some(1));
+
+    let (tx, rx) = oneshot::channel();
+    worker.handle_command(PlayerCommand::NextChapter(tx)).await;
+    assert!(rx.await.unwrap());
+    assert_eq!(shared_state.progress_ms(), 120_000);
+    assert_eq!(shared_state.chapter_index(), Some(2));
+    assert!(shared_state.is_buffering());
+
+    let (tx, rx) = oneshot::channel();
+    worker.handle_command(PlayerCommand::NextChapter(tx)).await;
+    assert!(!rx.await.unwrap());
+
+    // 刚进入本章时回到上一章，播放一段时间后回到本章开头。
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::PreviousChapter(tx))
+        .await;
+    assert!(rx.await.unwrap());
+    worker.player.set_progress(Duration::from_secs(100));
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::PreviousChapter(tx))
+        .await;
+    assert!(rx.await.unwrap());
+    assert_eq!(shared_state.chapter_index(), Some(1));
+
+    assert_eq!(
+        factory.events()[2..],
+        [
+            "player[auto] seek:120000".to_string(),
+            "player[auto] seek:60000".to_string(),
+            "player[auto] seek:60000".to_string(),
+        ]
+    );
+
+    worker.handle_command(PlayerCommand::Stop).await;
+    assert_eq!(shared_state.chapter_index(), None);
+}
+
+async fn play_on_headphones(worker: &mut WorkerCore<MockPlayer, MockFactory>) {
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/tmp/test.flac".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SwitchOutputDevice(
+            Some("headphones".to_string()),
+            tx,
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+    worker.player.set_progress(Duration::from_millis(3_000));
+}
+
+#[tokio::test]
+async fn device_loss_pauses_and_resume_moves_to_an_available_device() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+    play_on_headphones(&mut worker).await;
+
+    factory.unplug("headphones");
+    worker.player.fail_output("device unplugged");
+    worker.check_output().await;
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Paused);
+
+    worker.handle_command(PlayerCommand::Resume).await;
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
+    assert_eq!(shared_state.progress_ms(), 3_000);
+    assert_eq!(
+        factory.events()[5..],
+        [
+            "player[headphones] pause".to_string(),
+            "create:auto".to_string(),
+            "player[auto] play_file:/tmp/test.flac@3000".to_string(),
+            "player[headphones] stop".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn device_loss_can_fall_back_to_the_default_device() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+    worker
+        .handle_command(PlayerCommand::SetDeviceLossPolicy(
+            DeviceLossPolicy::FallbackToDefault,
+        ))
+        .await;
+    play_on_headphones(&mut worker).await;
+
+    factory.unplug("headphones");
+    worker.player.fail_output("device unplugged");
+    worker.check_output().await;
+
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
+    assert_eq!(
+        factory.events()[5..],
+        [
+            "player[headphones] pause".to_string(),
+            "create:auto".to_string(),
+            "player[auto] play_file:/tmp/test.flac@3000".to_string(),
+            "player[headphones] stop".to_string(),
+        ]
+    );
+    worker.check_output().await;
+    assert_eq!(factory.events().len(), 9);
+}
+
+#[tokio::test]
+async fn device_loss_can_wait_for_the_device_to_return() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+    worker
+        .handle_command(PlayerCommand::SetDeviceLossPolicy(
+            DeviceLossPolicy::WaitForDevice,
+        ))
+        .await;
+    play_on_headphones(&mut worker).await;
+
+    let headphones = factory.unplug("headphones");
+    worker.player.fail_output("device unplugged");
+    for _ in 0..=DEVICE_RETRY_TICKS * 2 {
+        worker.check_output().await;
+    }
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Paused);
+    assert_eq!(factory.events().len(), 6);
+
+    factory.plug_in(headphones);
+    for _ in 0..=DEVICE_RETRY_TICKS {
+        worker.check_output().await;
+    }
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
+    assert_eq!(
+        factory.events()[5..],
+        [
+            "player[headphones] pause".to_string(),
+            "create:headphones".to_string(),
+            "player[headphones] play_file:/tmp/test.flac@3000".to_string(),
+            "player[headphones] stop".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn preferred_devices_pick_the_highest_priority_available_device() {
+    let factory = MockFactory::new();
+    let (mut worker, shared_state, factory) = create_worker(factory);
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/tmp/test.flac".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetPreferredOutputDevices(
+            vec!["usb-dac".to_string(), "headphones".to_string()],
+            tx,
+        ))
+        .await;
+
+    assert!(rx.await.unwrap().is_ok());
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
+    assert_eq!(
+        factory.events()[2..],
+        [
+            "create:headphones".to_string(),
+            "player[headphones] play_file:/tmp/test.flac@0".to_string(),
+            "player[auto] stop".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn hot_plugged_preferred_device_takes_over_and_notifies_waiters() {
+    let factory = MockFactory::new();
+    let headphones = factory.unplug("headphones");
+    let (mut worker, shared_state, factory) = create_worker(factory);
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/tmp/test.flac".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetPreferredOutputDevices(
+            vec!["headphones".to_string()],
+            tx,
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+    let (changed_tx, mut changed_rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::WaitOutputDevicesChange(changed_tx))
+        .await;
+
+    for _ in 0..=DEVICE_POLL_TICKS {
+        worker.poll_output_devices().await;
+    }
+    assert!(changed_rx.try_recv().is_err());
+    assert_eq!(factory.events().len(), 2);
+
+    worker.player.set_progress(Duration::from_millis(2_500));
+    factory.plug_in(headphones);
+    for _ in 0..=DEVICE_POLL_TICKS {
+        worker.poll_output_devices().await;
+    }
+
+    assert_eq!(shared_state.playback_status(), PlaybackStatus::Playing);
+    assert_eq!(
+        factory.events()[2..],
+        [
+            "create:headphones".to_string(),
+            "player[headphones] play_file:/tmp/test.flac@2500".to_string(),
+            "player[auto] stop".to_string(),
+        ]
+    );
+    let devices = changed_rx.try_recv().unwrap();
+    assert!(
+        devices
+            .iter()
+            .any(|device| device.id == "headphones" && device.is_current)
+    );
+}
+
+/// 耳机被拔出、声卡签名为 `card0` 时开始播放，并把耳机设为首选设备。
+async fn prefer_unplugged_headphones() -> (
+    WorkerCore<MockPlayer, MockFactory>,
+    MockFactory,
+    OutputDeviceInfo,
+) {
+    let factory = MockFactory::new();
+    *factory.card_signature.lock().unwrap() = Some("card0".to_string());
+    let headphones = factory.unplug("headphones");
+    let (mut worker, _shared_state, factory) = create_worker(factory);
+    worker
+        .handle_command(PlayerCommand::PlayFile(
+            "/tmp/test.flac".to_string(),
+            None,
+            PlaybackOptions::default(),
+            None,
+        ))
+        .await;
+    let (tx, rx) = oneshot::channel();
+    worker
+        .handle_command(PlayerCommand::SetPreferredOutputDevices(
+            vec!["headphones".to_string()],
+            tx,
+        ))
+        .await;
+    assert!(rx.await.unwrap().is_ok());
+    worker.poll_output_devices().await;
+    (worker, factory, headphones)
+}
+
+fn switched_to_headphones() -> [String; 3] {
+    [
+        "create:headphones".to_string(),
+        "player[headphones] play_file:/tmp/test.flac@0".to_string(),
+        "player[auto] stop".to_string(),
+    ]
+}
+
+#[tokio::test]
+async fn devices_are_rescanned_periodically_while_the_card_signature_is_unchanged() {
+    let (mut worker, factory, headphones) = prefer_unplugged_headphones().await;
+
+    // 蓝牙耳机接入不改变声卡签名：下一次检查不枚举，定期的完整枚举才发现它。
+    factory.plug_in(headphones);
+    for _ in 0..=DEVICE_POLL_TICKS {
+        worker.poll_output_devices().await;
+    }
+    assert_eq!(factory.events().len(), 2);
+    for _ in 0..DEVICE_SCAN_POLLS * (DEVICE_POLL_TICKS + 1) {
+        worker.poll_output_devices().await;
+    }
+    assert_eq!(factory.events()[2..], switched_to_headphones());
+}
+
+#[tokio::test]
+async fn card_signature_change_triggers_an_immediate_rescan() {
+    let (mut worker, factory, headphones) = prefer_unplugged_headphones().await;
+
+    factory.plug_in(headphones);
+    *factory.card_signature.lock().unwrap() = Some("card0,card1".to_string());
+    for _ in 0..=DEVICE_POLL_TICKS {
+        worker.poll_output_devices().await;
+    }
+    assert_eq!(factory.events()[2..], switched_to_headphones());
+}
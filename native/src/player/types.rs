@@ -1,9 +1,26 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 
+use napi::bindgen_prelude::Buffer;
 use napi_derive::napi;
 
 use crate::audio::OutputDeviceInfo;
+use crate::audio::alsa_hw::AlsaHwOptions;
+use crate::audio::bit_perfect::BitPerfectReport;
+use crate::audio::capabilities::DeviceCapabilities;
+use crate::audio::chapters::Chapter;
+use crate::audio::cue::CueTrack;
+use crate::audio::export::{ExportOptions, ExportProgress, ExportReport, ReplayGainMode};
+use crate::audio::hw_mixer::HwVolume;
+use crate::audio::jack_output::JackOptions;
+use crate::audio::multi_output::{SecondaryOutputReport, SecondaryOutputTarget};
+use crate::audio::ncm::NcmHeader;
+use crate::audio::pipeline_stats::{PipelineStatsSnapshot, TimingSnapshot};
+use crate::audio::player::{PlaybackRange, StreamDuration};
+use crate::audio::silence::SilenceOptions;
+use crate::audio::thread_priority::SchedulingReport;
+use crate::audio::virtual_output::VirtualOutputOptions;
 
 pub(crate) type BackendResult<T> = std::result::Result<T, String>;
 pub(crate) type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = BackendResult<T>> + Send + 'a>>;
@@ -14,6 +31,20 @@ pub(crate) enum PlaybackSource {
     File(String, PlaybackOptions),
     Url(String, PlaybackOptions),
     CachedUrl(CachedUrlPlaybackRequest, PlaybackOptions),
+    Buffer(BufferPlaybackRequest, PlaybackOptions),
+    FileRange(FileRangePlaybackRequest, PlaybackOptions),
+}
+
+impl PlaybackSource {
+    pub(crate) fn options(&self) -> PlaybackOptions {
+        match self {
+            Self::File(_, options)
+            | Self::Url(_, options)
+            | Self::CachedUrl(_, options)
+            | Self::Buffer(_, options)
+            | Self::FileRange(_, options) => *options,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
@@ -31,6 +62,47 @@ pub(crate) struct CachedUrlPlaybackRequest {
     pub(crate) max_cache_ahead_bytes: Option<u64>,
 }
 
+/// 单文件专辑中的一条 CUE 音轨；`next_range` 是同一文件中紧随其后的音轨，
+/// 用于无缝续播。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FileRangePlaybackRequest {
+    pub(crate) path: String,
+    pub(crate) range: PlaybackRange,
+    pub(crate) next_range: Option<PlaybackRange>,
+}
+
+impl FileRangePlaybackRequest {
+    /// 是否与 `other` 播放的是同一文件的同一段，`next_range` 不参与比较。
+    pub(crate) fn is_same_range(&self, other: &Self) -> bool {
+        self.path == other.path && self.range == other.range
+    }
+
+    /// 当前区间播完、已无缝续上 `next_range` 后对应的请求。
+    pub(crate) fn advanced(&self) -> Option<Self> {
+        self.next_range.map(|range| Self {
+            path: self.path.clone(),
+            range,
+            next_range: None,
+        })
+    }
+}
+
+/// 内存音频数据；`Arc` 共享，切换输出设备重放时不复制。
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct BufferPlaybackRequest {
+    pub(crate) data: Arc<[u8]>,
+    pub(crate) extension_hint: Option<String>,
+}
+
+impl std::fmt::Debug for BufferPlaybackRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPlaybackRequest")
+            .field("len", &self.data.len())
+            .field("extension_hint", &self.extension_hint)
+            .finish()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum PlaybackStatus {
     Stopped,
@@ -56,6 +128,31 @@ impl PlaybackStatus {
     }
 }
 
+/// 播放中输出设备失效（如拔出 USB 解码器）后的处理方式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum DeviceLossPolicy {
+    /// 暂停，恢复播放时改用仍可用的设备。
+    #[default]
+    Pause,
+    /// 立即切换到系统默认设备继续播放。
+    FallbackToDefault,
+    /// 暂停并等待原设备重新接入后继续播放。
+    WaitForDevice,
+}
+
+impl TryFrom<&str> for DeviceLossPolicy {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pause" => Ok(Self::Pause),
+            "fallback_to_default" => Ok(Self::FallbackToDefault),
+            "wait_for_device" => Ok(Self::WaitForDevice),
+            _ => Err(format!("Unknown device loss policy: {value}")),
+        }
+    }
+}
+
 #[napi(object)]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AudioDeviceInfo {
@@ -63,6 +160,8 @@ pub struct AudioDeviceInfo {
     pub name: String,
     pub is_default: bool,
     pub is_current: bool,
+    /// 可通过 `setHwVolume` 调节声卡的硬件音量。
+    pub hw_volume: bool,
 }
 
 impl From<OutputDeviceInfo> for AudioDeviceInfo {
@@ -72,6 +171,748 @@ impl From<OutputDeviceInfo> for AudioDeviceInfo {
             name: value.name,
             is_default: value.is_default,
             is_current: value.is_current,
+            hw_volume: value.hw_volume,
+        }
+    }
+}
+
+/// 声卡硬件混音器控件的状态，`volume` 为 0..=1。
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct HwVolumeInfo {
+    pub card: u32,
+    pub element: String,
+    pub volume: f64,
+    pub db: Option<f64>,
+    pub min_db: Option<f64>,
+    pub max_db: Option<f64>,
+    /// 控件没有静音开关时为空。
+    pub muted: Option<bool>,
+}
+
+impl From<HwVolume> for HwVolumeInfo {
+    fn from(value: HwVolume) -> Self {
+        Self {
+            card: value.card,
+            element: value.element,
+            volume: value.volume,
+            db: value.db,
+            min_db: value.min_db,
+            max_db: value.max_db,
+            muted: value.muted,
+        }
+    }
+}
+
+/// 输出设备支持的配置，供界面在选择设备前判断能否无损输出。
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceCapabilitiesInfo {
+    pub device_id: String,
+    pub sample_rates: Vec<u32>,
+    pub channel_counts: Vec<u32>,
+    pub sample_formats: Vec<String>,
+    pub configs: Vec<OutputConfigInfo>,
+    /// 仅 `hw:` 设备有，直接来自驱动的 hw_params。
+    pub hw_params: Option<AlsaHwParamsInfo>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputConfigInfo {
+    pub channels: u32,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+    pub min_buffer_frames: Option<u32>,
+    pub max_buffer_frames: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlsaHwParamsInfo {
+    pub sample_rates: Vec<u32>,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub min_channels: u32,
+    pub max_channels: u32,
+    pub formats: Vec<String>,
+    pub min_period_frames: i64,
+    pub max_period_frames: i64,
+    pub min_buffer_frames: i64,
+    pub max_buffer_frames: i64,
+}
+
+impl From<DeviceCapabilities> for DeviceCapabilitiesInfo {
+    fn from(value: DeviceCapabilities) -> Self {
+        let frames = |frames: u64| frames.min(i64::MAX as u64) as i64;
+        Self {
+            device_id: value.device_id,
+            sample_rates: value.sample_rates,
+            channel_counts: value.channel_counts.into_iter().map(u32::from).collect(),
+            sample_formats: value.sample_formats,
+            configs: value
+                .configs
+                .into_iter()
+                .map(|config| OutputConfigInfo {
+                    channels: u32::from(config.channels),
+                    min_sample_rate: config.min_sample_rate,
+                    max_sample_rate: config.max_sample_rate,
+                    sample_format: config.sample_format,
+                    min_buffer_frames: config.buffer_frames.map(|(min, _)| min),
+                    max_buffer_frames: config.buffer_frames.map(|(_, max)| max),
+                })
+                .collect(),
+            hw_params: value.hw_params.map(|hw| AlsaHwParamsInfo {
+                sample_rates: hw.sample_rates,
+                min_sample_rate: hw.min_sample_rate,
+                max_sample_rate: hw.max_sample_rate,
+                min_channels: hw.min_channels,
+                max_channels: hw.max_channels,
+                formats: hw.formats,
+                min_period_frames: frames(hw.period_frames.0),
+                max_period_frames: frames(hw.period_frames.1),
+                min_buffer_frames: frames(hw.buffer_frames.0),
+                max_buffer_frames: frames(hw.buffer_frames.1),
+            }),
+        }
+    }
+}
+
+/// 当前播放内容的时长；`estimated` 为真时由码率和音源大小推算，播放过程中可能更新。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlaybackDurationInfo {
+    pub duration_ms: Option<u32>,
+    pub estimated: bool,
+}
+
+impl From<Option<StreamDuration>> for PlaybackDurationInfo {
+    fn from(value: Option<StreamDuration>) -> Self {
+        Self {
+            duration_ms: value.map(|duration| duration_to_millis(duration.duration)),
+            estimated: value.is_some_and(|duration| duration.estimated),
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchedulingDiagnostics {
+    pub enabled: bool,
+    pub decode_thread: Option<String>,
+    pub output_thread: Option<String>,
+    pub rtprio_limit: Option<i64>,
+}
+
+impl From<SchedulingReport> for SchedulingDiagnostics {
+    fn from(value: SchedulingReport) -> Self {
+        Self {
+            enabled: value.enabled,
+            decode_thread: value.decode_thread.map(|priority| priority.to_string()),
+            output_thread: value.output_thread.map(|priority| priority.to_string()),
+            rtprio_limit: value
+                .rtprio_limit
+                .map(|limit| limit.min(i64::MAX as u64) as i64),
+        }
+    }
+}
+
+/// 静音处理设置，未给出的字段取默认值：阈值 -60 dBFS，最短 500ms。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SilenceOptionsConfig {
+    /// 修剪曲目首尾的静音。
+    pub trim_edges: bool,
+    /// 播客式跳过曲目中间的长静音，只保留 `minSilenceMs` 长的停顿。
+    pub skip_silence: bool,
+    pub threshold_db: Option<f64>,
+    pub min_silence_ms: Option<u32>,
+}
+
+impl TryFrom<SilenceOptionsConfig> for SilenceOptions {
+    type Error = String;
+
+    fn try_from(value: SilenceOptionsConfig) -> Result<Self, Self::Error> {
+        let defaults = Self::default();
+        let threshold_db = value.threshold_db.unwrap_or(defaults.threshold_db);
+        if !(-120.0..=0.0).contains(&threshold_db) {
+            return Err(format!(
+                "静音阈值应在 -120 到 0 dBFS 之间，收到 {threshold_db}"
+            ));
+        }
+        Ok(Self {
+            trim_edges: value.trim_edges,
+            skip_gaps: value.skip_silence,
+            threshold_db,
+            min_silence: value
+                .min_silence_ms
+                .map(|ms| std::time::Duration::from_millis(u64::from(ms)))
+                .unwrap_or(defaults.min_silence),
+        })
+    }
+}
+
+/// 某个输出设备上的 HRTF 双耳渲染设置；`device_id` 为空表示系统默认设备，
+/// `sofa_path` 为空时用内置的球形头模型。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HrtfConfig {
+    pub device_id: Option<String>,
+    pub enabled: bool,
+    pub sofa_path: Option<String>,
+}
+
+/// 某个输出设备是否接受 DoP；`device_id` 为空表示系统默认设备。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DopOutputConfig {
+    pub device_id: Option<String>,
+    pub enabled: bool,
+}
+
+/// 当前曲目的一个章节；`end_ms` 在最后一章没有终点时为空。
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChapterInfo {
+    pub index: u32,
+    pub title: String,
+    pub start_ms: u32,
+    pub end_ms: Option<u32>,
+}
+
+impl From<(usize, &Chapter)> for ChapterInfo {
+    fn from((index, chapter): (usize, &Chapter)) -> Self {
+        Self {
+            index: index as u32,
+            title: chapter.title.clone(),
+            start_ms: duration_to_millis(chapter.start),
+            end_ms: chapter.end.map(duration_to_millis),
+        }
+    }
+}
+
+/// 一组耗时统计，单位毫秒。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimingStatsInfo {
+    pub count: i64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub last_ms: f64,
+}
+
+impl From<TimingSnapshot> for TimingStatsInfo {
+    fn from(value: TimingSnapshot) -> Self {
+        Self {
+            count: value.count.min(i64::MAX as u64) as i64,
+            avg_ms: value.avg_us as f64 / 1000.0,
+            max_ms: value.max_us as f64 / 1000.0,
+            last_ms: value.last_us as f64 / 1000.0,
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PipelineStatsInfo {
+    pub underruns: i64,
+    pub buffered_ms: u32,
+    pub buffer_capacity_ms: u32,
+    /// 每 250ms 一个的缓冲时长，旧的在前，最多约 30 秒。
+    pub fill_history_ms: Vec<u32>,
+    /// 每个 packet 的解码耗时。
+    pub decode: TimingStatsInfo,
+    /// 发出 seek 到重新出声的耗时。
+    pub seek: TimingStatsInfo,
+    /// 输出回调间隔与上一批样本时长之差。
+    pub callback_jitter: TimingStatsInfo,
+    pub late_callbacks: i64,
+    /// 读取音源阻塞超过 100ms 的次数与耗时。
+    pub network_stalls: TimingStatsInfo,
+}
+
+impl From<PipelineStatsSnapshot> for PipelineStatsInfo {
+    fn from(value: PipelineStatsSnapshot) -> Self {
+        Self {
+            underruns: value.underruns.min(i64::MAX as u64) as i64,
+            buffered_ms: value.buffered_ms,
+            buffer_capacity_ms: value.buffer_capacity_ms,
+            fill_history_ms: value.fill_history_ms,
+            decode: value.decode.into(),
+            seek: value.seek.into(),
+            callback_jitter: value.callback_jitter.into(),
+            late_callbacks: value.late_callbacks.min(i64::MAX as u64) as i64,
+            network_stalls: value.network_stalls.into(),
+        }
+    }
+}
+
+/// 严格 BitPerfect 播放时绕过 cpal 直接打开 ALSA `hw:` 设备的设置。
+/// period/buffer 为期望帧数，驱动就近取值；`mmap` 为真时直接写 DMA 缓冲区。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AlsaHwOutputConfig {
+    pub enabled: bool,
+    pub period_frames: Option<u32>,
+    pub buffer_frames: Option<u32>,
+    pub mmap: Option<bool>,
+}
+
+impl From<AlsaHwOutputConfig> for AlsaHwOptions {
+    fn from(value: AlsaHwOutputConfig) -> Self {
+        Self {
+            enabled: value.enabled,
+            period_frames: value.period_frames.filter(|frames| *frames > 0),
+            buffer_frames: value.buffer_frames.filter(|frames| *frames > 0),
+            mmap: value.mmap.unwrap_or(false),
+        }
+    }
+}
+
+/// `jack:` 设备的输出设置。`clientName` 缺省为 ncm-desktop；`autoConnect`（缺省为真）
+/// 时依次连接 `connectPorts`，未给出则连接设备 ID 中客户端的输入端口。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JackOutputConfig {
+    pub client_name: Option<String>,
+    pub auto_connect: Option<bool>,
+    pub connect_ports: Option<Vec<String>>,
+}
+
+impl From<JackOutputConfig> for JackOptions {
+    fn from(value: JackOutputConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            client_name: value
+                .client_name
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or(defaults.client_name),
+            auto_connect: value.auto_connect.unwrap_or(defaults.auto_connect),
+            connect_ports: value.connect_ports.unwrap_or_default(),
+        }
+    }
+}
+
+/// `null:`/`file:<路径>`/`fifo:<路径>` 虚拟输出的设置。`filePath` 给出时列出对应的
+/// 文件输出，每次打开输出写一个新文件（已存在时加 `-1`、`-2`… 后缀）；`fifoPath` 缺省为 /tmp/snapfifo，管道按 `fifoSampleRate`（缺省 48000）与
+/// `fifoBits`（16/24/32，缺省 16）写入，与 Snapcast 的 `sampleformat` 对应。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VirtualOutputConfig {
+    pub file_path: Option<String>,
+    pub fifo_path: Option<String>,
+    pub fifo_sample_rate: Option<u32>,
+    pub fifo_bits: Option<u32>,
+}
+
+impl From<VirtualOutputConfig> for VirtualOutputOptions {
+    fn from(value: VirtualOutputConfig) -> Self {
+        let defaults = Self::default();
+        let non_empty = |path: Option<String>| {
+            path.map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+        };
+        Self {
+            file_path: non_empty(value.file_path),
+            fifo_path: non_empty(value.fifo_path).unwrap_or(defaults.fifo_path),
+            fifo_sample_rate: value
+                .fifo_sample_rate
+                .filter(|rate| *rate > 0)
+                .unwrap_or(defaults.fifo_sample_rate),
+            fifo_bits: value
+                .fifo_bits
+                .and_then(|bits| u16::try_from(bits).ok())
+                .unwrap_or(defaults.fifo_bits),
+        }
+    }
+}
+
+/// 当前播放的 BitPerfect 诊断；没有在播放时音源与输出为空。
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitPerfectReportInfo {
+    pub bit_perfect: bool,
+    pub strict: bool,
+    pub source: Option<SourceFormatInfo>,
+    pub output: Option<OutputFormatInfo>,
+    /// 读自 `/proc/asound/cardX/pcmYp/sub0/hw_params`，只有 ALSA 硬件设备有。
+    pub hw_params: Option<HwParamsInfo>,
+    /// 会改变样本的环节，空表示没有。
+    pub conversions: Vec<String>,
+    pub volume_active: bool,
+    pub dsp_active: bool,
+    pub reservation: Option<DeviceReservationInfo>,
+    pub verification: SampleVerificationInfo,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceFormatInfo {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits_per_sample: Option<u32>,
+    pub sample_format: Option<String>,
+    pub dsd_rate: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputFormatInfo {
+    pub device_id: String,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub sample_format: String,
+    pub buffer_frames: Option<u32>,
+    pub dop: bool,
+    /// 绕过 cpal 直连 `hw:` 时实际生效的参数。
+    pub alsa_hw: Option<AlsaHwStreamInfo>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlsaHwStreamInfo {
+    pub pcm_name: String,
+    pub format: String,
+    pub period_frames: i64,
+    pub buffer_frames: i64,
+    pub mmap: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HwParamsInfo {
+    pub path: String,
+    pub raw: String,
+    pub closed: bool,
+    pub access: Option<String>,
+    pub format: Option<String>,
+    pub channels: Option<u32>,
+    pub rate: Option<u32>,
+    pub period_frames: Option<i64>,
+    pub buffer_frames: Option<i64>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceReservationInfo {
+    pub name: String,
+    pub held: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SampleVerificationInfo {
+    pub enabled: bool,
+    pub verified_samples: i64,
+    pub matched_checkpoints: i64,
+    pub mismatched_checkpoints: i64,
+    pub first_mismatch_sample: Option<i64>,
+}
+
+impl From<BitPerfectReport> for BitPerfectReportInfo {
+    fn from(value: BitPerfectReport) -> Self {
+        let count = |count: u64| count.min(i64::MAX as u64) as i64;
+        Self {
+            bit_perfect: value.bit_perfect,
+            strict: value.strict,
+            source: value.source.map(|source| SourceFormatInfo {
+                sample_rate: source.sample_rate,
+                channels: u32::from(source.channels),
+                bits_per_sample: source.bits_per_sample,
+                sample_format: source.sample_format.map(|format| format!("{:?}", format)),
+                dsd_rate: source.dsd_rate,
+            }),
+            output: value.output.map(|output| OutputFormatInfo {
+                device_id: output.device_id,
+                sample_rate: output.sample_rate,
+                channels: u32::from(output.channels),
+                sample_format: output.sample_format.to_string(),
+                buffer_frames: output.buffer_frames,
+                dop: output.dop,
+                alsa_hw: output.alsa_hw.map(|hw| AlsaHwStreamInfo {
+                    pcm_name: hw.pcm_name,
+                    format: hw.format,
+                    period_frames: count(hw.period_frames),
+                    buffer_frames: count(hw.buffer_frames),
+                    mmap: hw.mmap,
+                }),
+            }),
+            hw_params: value.hw_params.map(|hw| HwParamsInfo {
+                path: hw.path,
+                raw: hw.raw,
+                closed: hw.closed,
+                access: hw.access,
+                format: hw.format,
+                channels: hw.channels,
+                rate: hw.rate,
+                period_frames: hw.period_frames.map(count),
+                buffer_frames: hw.buffer_frames.map(count),
+            }),
+            conversions: value.conversions,
+            volume_active: value.volume_active,
+            dsp_active: value.dsp_active,
+            reservation: value.reservation.map(|reservation| DeviceReservationInfo {
+                name: reservation.name,
+                held: reservation.held,
+            }),
+            verification: SampleVerificationInfo {
+                enabled: value.verification.enabled,
+                verified_samples: count(value.verification.verified_samples),
+                matched_checkpoints: count(value.verification.matched_checkpoints),
+                mismatched_checkpoints: count(value.verification.mismatched_checkpoints),
+                first_mismatch_sample: value.verification.first_mismatch_sample.map(count),
+            },
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecondaryOutputConfig {
+    pub device_id: String,
+    pub delay_ms: Option<u32>,
+}
+
+impl From<SecondaryOutputConfig> for SecondaryOutputTarget {
+    fn from(value: SecondaryOutputConfig) -> Self {
+        Self {
+            device_id: value.device_id,
+            delay_ms: value.delay_ms.unwrap_or(0),
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecondaryOutputStatus {
+    pub device_id: String,
+    pub delay_ms: u32,
+    pub sample_rate: u32,
+    pub resample_ratio: f64,
+    pub buffered_ms: u32,
+    pub underruns: i64,
+}
+
+impl From<SecondaryOutputReport> for SecondaryOutputStatus {
+    fn from(value: SecondaryOutputReport) -> Self {
+        Self {
+            device_id: value.device_id,
+            delay_ms: value.delay_ms,
+            sample_rate: value.sample_rate,
+            resample_ratio: value.resample_ratio,
+            buffered_ms: value.buffered_ms,
+            underruns: value.underruns.min(i64::MAX as u64) as i64,
+        }
+    }
+}
+
+/// `.ncm` 内嵌的歌曲信息与封面；`metadata_json` 为原始 JSON，便于 JS 侧读取其余字段。
+#[napi(object)]
+pub struct NcmFileInfo {
+    pub music_id: Option<String>,
+    pub music_name: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub format: Option<String>,
+    pub bitrate: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub metadata_json: Option<String>,
+    pub cover: Option<Buffer>,
+    pub cover_mime: Option<String>,
+}
+
+impl From<NcmHeader> for NcmFileInfo {
+    fn from(value: NcmHeader) -> Self {
+        let meta = value.metadata.unwrap_or_default();
+        let cover_mime = value.cover.as_deref().map(|cover| {
+            if cover.starts_with(b"\x89PNG") {
+                "image/png".to_string()
+            } else {
+                "image/jpeg".to_string()
+            }
+        });
+        Self {
+            music_id: meta.music_id,
+            music_name: meta.music_name,
+            artists: meta.artists,
+            album: meta.album,
+            format: meta.format,
+            bitrate: meta
+                .bitrate
+                .map(|bitrate| bitrate.min(i64::MAX as u64) as i64),
+            duration_ms: meta
+                .duration_ms
+                .map(|duration| duration.min(i64::MAX as u64) as i64),
+            metadata_json: Some(meta.raw_json).filter(|json| !json.is_empty()),
+            cover: value.cover.map(Buffer::from),
+            cover_mime,
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CueTrackInfo {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub file_path: String,
+    pub start_ms: i64,
+    /// 文件最后一轨为空，时长需由音频文件总时长推算。
+    pub end_ms: Option<i64>,
+    pub duration_ms: Option<i64>,
+}
+
+impl From<&CueTrack> for CueTrackInfo {
+    fn from(value: &CueTrack) -> Self {
+        let millis =
+            |duration: std::time::Duration| duration.as_millis().min(i64::MAX as u128) as i64;
+        Self {
+            number: value.number,
+            title: value.title.clone(),
+            performer: value.performer.clone(),
+            file_path: value.file.to_string_lossy().into_owned(),
+            start_ms: millis(value.start),
+            end_ms: value.end.map(millis),
+            duration_ms: value.duration().map(millis),
+        }
+    }
+}
+
+/// 导出的音源：`path` 为本地文件（含 .ncm）；否则用 `url`，同时给出 `cachePath` 与
+/// `metadataPath` 时与 `playUrlCached` 共用缓存，缓存完整时直接读文件。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportSourceConfig {
+    pub path: Option<String>,
+    pub url: Option<String>,
+    pub cache_path: Option<String>,
+    pub metadata_path: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ExportSource {
+    File(String),
+    Url(String),
+    CachedUrl(CachedUrlPlaybackRequest),
+}
+
+impl TryFrom<ExportSourceConfig> for ExportSource {
+    type Error = String;
+
+    fn try_from(value: ExportSourceConfig) -> Result<Self, Self::Error> {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        if let Some(path) = non_empty(value.path) {
+            return Ok(Self::File(path));
+        }
+        let url = non_empty(value.url).ok_or("导出源缺少 path 或 url")?;
+        Ok(
+            match (non_empty(value.cache_path), non_empty(value.metadata_path)) {
+                (Some(cache_path), Some(metadata_path)) => {
+                    Self::CachedUrl(CachedUrlPlaybackRequest {
+                        url,
+                        cache_path,
+                        metadata_path,
+                        duration_ms: value.duration_ms.map(|value| value.max(0) as u64),
+                        cache_ahead_secs: None,
+                        max_cache_ahead_bytes: None,
+                    })
+                }
+                _ => Self::Url(url),
+            },
+        )
+    }
+}
+
+/// 导出参数：`sampleRate` 缺省保持原采样率；`bits` 为 16/24/32（32 为浮点，FLAC 不支持），
+/// 缺省按音源取 16 或 24；`dither` 缺省开启，只在样本被改动或降低位深时生效；
+/// `replayGain` 为 "off"/"track"/"album"，与 `gainDb` 叠加。
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportOptionsConfig {
+    pub sample_rate: Option<u32>,
+    pub bits: Option<u32>,
+    pub dither: Option<bool>,
+    pub gain_db: Option<f64>,
+    pub replay_gain: Option<String>,
+}
+
+impl TryFrom<ExportOptionsConfig> for ExportOptions {
+    type Error = String;
+
+    fn try_from(value: ExportOptionsConfig) -> Result<Self, Self::Error> {
+        let defaults = Self::default();
+        Ok(Self {
+            sample_rate: value.sample_rate.filter(|rate| *rate > 0),
+            bits: value
+                .bits
+                .map(|bits| u16::try_from(bits).map_err(|_| format!("导出不支持 {bits} 位")))
+                .transpose()?,
+            dither: value.dither.unwrap_or(defaults.dither),
+            gain_db: value
+                .gain_db
+                .filter(|gain| gain.is_finite())
+                .unwrap_or(defaults.gain_db),
+            replay_gain: value
+                .replay_gain
+                .as_deref()
+                .map(ReplayGainMode::try_from)
+                .transpose()?
+                .unwrap_or(defaults.replay_gain),
+        })
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportResultInfo {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits: u32,
+    pub duration_ms: i64,
+    pub dithered: bool,
+    /// 实际应用的增益，含 ReplayGain。
+    pub gain_db: f64,
+}
+
+impl From<ExportReport> for ExportResultInfo {
+    fn from(value: ExportReport) -> Self {
+        Self {
+            sample_rate: value.sample_rate,
+            channels: u32::from(value.channels),
+            bits: u32::from(value.bits),
+            duration_ms: (u128::from(value.frames) * 1_000 / u128::from(value.sample_rate.max(1)))
+                .min(i64::MAX as u128) as i64,
+            dithered: value.dithered,
+            gain_db: value.gain_db,
+        }
+    }
+}
+
+/// 导出进度；音源时长未知时 `totalMs` 与 `fraction` 为空。
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportProgressInfo {
+    pub processed_ms: i64,
+    pub total_ms: Option<i64>,
+    pub fraction: Option<f64>,
+}
+
+impl From<ExportProgress> for ExportProgressInfo {
+    fn from(value: ExportProgress) -> Self {
+        let millis = |frames: u64| {
+            (u128::from(frames) * 1_000 / u128::from(value.sample_rate.max(1)))
+                .min(i64::MAX as u128) as i64
+        };
+        Self {
+            processed_ms: millis(value.done_frames),
+            total_ms: value.total_frames.map(millis),
+            fraction: value
+                .total_frames
+                .map(|total| (value.done_frames as f64 / total as f64).min(1.0)),
         }
     }
 }
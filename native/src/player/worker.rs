@@ -1,22 +1,80 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::audio::OutputDeviceInfo;
+use crate::audio::alsa_hw::AlsaHwOptions;
+use crate::audio::chapters;
+use crate::audio::hrtf::HrtfDataset;
+use crate::audio::jack_output::JackOptions;
+use crate::audio::multi_output::SecondaryOutputTarget;
+use crate::audio::silence::SilenceOptions;
+use crate::audio::virtual_output::VirtualOutputOptions;
 
 use super::backend::{PlayerBackend, PlayerFactory};
 use super::command::PlayerCommand;
 use super::state::SharedState;
 use super::types::{
-    AudioDeviceInfo, BackendResult, PlaybackSource, PlaybackStatus, duration_to_millis,
-    seconds_to_duration, start_secs_to_duration,
+    AudioDeviceInfo, BackendResult, BitPerfectReportInfo, ChapterInfo, DeviceLossPolicy,
+    HwVolumeInfo, PipelineStatsInfo, PlaybackDurationInfo, PlaybackSource, PlaybackStatus,
+    SchedulingDiagnostics, SecondaryOutputStatus, duration_to_millis, seconds_to_duration,
+    start_secs_to_duration,
 };
 
+/// 输出设备失效后，每隔多少次 tick（约 1 秒）重试恢复。
+pub(crate) const DEVICE_RETRY_TICKS: u32 = 60;
+/// 每隔多少次 tick（约 1 秒）检查一次设备插拔。
+pub(crate) const DEVICE_POLL_TICKS: u32 = 60;
+/// 声卡签名不变时每隔多少次检查仍完整枚举一次：PipeWire/PulseAudio、蓝牙等设备
+/// 的增减不一定体现在声卡列表里。
+pub(crate) const DEVICE_SCAN_POLLS: u32 = 5;
+
+/// 失效的输出设备及失效前的播放状态。
+struct LostOutput {
+    device_name: Option<String>,
+    /// 失效时正在播放，恢复后应继续播放。
+    resume: bool,
+    retry_in: u32,
+}
+
 pub(crate) struct WorkerCore<P, F> {
     pub(crate) player: P,
     factory: F,
     shared_state: Arc<SharedState>,
     pub(crate) current_source: Option<PlaybackSource>,
+    realtime_scheduling: bool,
+    alsa_hw: AlsaHwOptions,
+    jack: JackOptions,
+    virtual_output: VirtualOutputOptions,
+    silence: SilenceOptions,
+    /// 按输出设备设置的 HRTF 双耳渲染，键为设备（`None` 为系统默认设备）。
+    hrtf: HashMap<Option<String>, HrtfDataset>,
+    /// 开启了 DoP 的输出设备（`None` 为系统默认设备）。
+    dop_devices: HashSet<Option<String>>,
+    bit_perfect_verification: bool,
+    secondary_outputs: Vec<SecondaryOutputTarget>,
+    /// 当前播放器打开的设备，`None` 为系统默认设备。
+    output_device: Option<String>,
+    device_loss_policy: DeviceLossPolicy,
+    lost_output: Option<LostOutput>,
+    /// 按优先级排列的输出设备，插拔后自动切到其中可用的最高优先级设备。
+    preferred_devices: Vec<String>,
+    /// 上次轮询到的设备 ID 和声卡签名，用于发现插拔。
+    known_devices: Option<Vec<String>>,
+    device_signature: Option<String>,
+    device_poll_in: u32,
+    device_scan_in: u32,
+    device_waiters: Vec<oneshot::Sender<Vec<AudioDeviceInfo>>>,
+    /// 已同步到 `current_source` 的区间续播次数。
+    range_transitions: u64,
+    /// `current_source` 是无缝续播出来的下一条 CUE 音轨，等待 JS 侧确认。
+    range_continued: bool,
+    /// 最近一次发布到 `shared_state` 的时长，变化时唤醒 `duration_waiters`。
+    duration: PlaybackDurationInfo,
+    duration_waiters: Vec<oneshot::Sender<PlaybackDurationInfo>>,
 }
 
 impl<P, F> WorkerCore<P, F>
@@ -30,6 +88,28 @@ where
             factory,
             shared_state,
             current_source: None,
+            realtime_scheduling: false,
+            alsa_hw: AlsaHwOptions::default(),
+            jack: JackOptions::default(),
+            virtual_output: VirtualOutputOptions::default(),
+            silence: SilenceOptions::default(),
+            hrtf: HashMap::new(),
+            dop_devices: HashSet::new(),
+            bit_perfect_verification: false,
+            secondary_outputs: Vec::new(),
+            output_device: None,
+            device_loss_policy: DeviceLossPolicy::default(),
+            lost_output: None,
+            preferred_devices: Vec::new(),
+            known_devices: None,
+            device_signature: None,
+            device_poll_in: 0,
+            device_scan_in: 0,
+            device_waiters: Vec::new(),
+            range_transitions: 0,
+            range_continued: false,
+            duration: PlaybackDurationInfo::default(),
+            duration_waiters: Vec::new(),
         }
     }
 
@@ -44,7 +124,11 @@ where
                         None => break,
                     }
                 }
-                _ = ticker.tick() => self.tick(),
+                _ = ticker.tick() => {
+                    self.tick();
+                    self.check_output().await;
+                    self.poll_output_devices().await;
+                }
             }
         }
     }
@@ -93,15 +177,49 @@ where
                     let _ = reply_tx.send(result);
                 }
             }
+            PlayerCommand::PlayBuffer(request, start_secs, options, reply_tx) => {
+                let result = self
+                    .play_source(
+                        PlaybackSource::Buffer(request, options),
+                        start_secs_to_duration(start_secs),
+                    )
+                    .await;
+                if let Err(err) = &result {
+                    eprintln!("Play buffer failed: {}", err);
+                }
+                if let Some(reply_tx) = reply_tx {
+                    let _ = reply_tx.send(result);
+                }
+            }
+            PlayerCommand::PlayFileRange(request, start_secs, options, reply_tx) => {
+                let result = self
+                    .play_source(
+                        PlaybackSource::FileRange(request, options),
+                        start_secs_to_duration(start_secs),
+                    )
+                    .await;
+                if let Err(err) = &result {
+                    eprintln!("Play CUE track failed: {}", err);
+                }
+                if let Some(reply_tx) = reply_tx {
+                    let _ = reply_tx.send(result);
+                }
+            }
             PlayerCommand::Pause => {
                 self.player.pause();
                 self.shared_state
                     .set_playback_status(PlaybackStatus::Paused, Ordering::SeqCst);
             }
             PlayerCommand::Resume => {
-                self.player.resume();
-                self.shared_state
-                    .set_playback_status(PlaybackStatus::Playing, Ordering::SeqCst);
+                if self.lost_output.is_some() {
+                    if let Err(err) = self.restore_lost_output(PlaybackStatus::Playing).await {
+                        eprintln!("Restore output device failed: {}", err);
+                    }
+                } else {
+                    self.player.resume();
+                    self.shared_state
+                        .set_playback_status(PlaybackStatus::Playing, Ordering::SeqCst);
+                }
             }
             PlayerCommand::Stop => {
                 self.player.stop();
@@ -112,6 +230,45 @@ where
                 self.player.seek(seconds_to_duration(time_secs));
                 self.shared_state.set_buffering(true, Ordering::SeqCst);
             }
+            PlayerCommand::GetChapters(reply_tx) => {
+                let chapters = self
+                    .player
+                    .chapters()
+                    .iter()
+                    .enumerate()
+                    .map(ChapterInfo::from)
+                    .collect();
+                let _ = reply_tx.send(chapters);
+            }
+            PlayerCommand::NextChapter(reply_tx) => {
+                let target =
+                    chapters::next_chapter_start(self.player.chapters(), self.player.progress());
+                let _ = reply_tx.send(self.seek_to_chapter(target));
+            }
+            PlayerCommand::PreviousChapter(reply_tx) => {
+                let target = chapters::previous_chapter_start(
+                    self.player.chapters(),
+                    self.player.progress(),
+                );
+                let _ = reply_tx.send(self.seek_to_chapter(target));
+            }
+            PlayerCommand::BeginScrub => {
+                if self.current_source.is_some() {
+                    self.player.begin_scrub();
+                }
+            }
+            PlayerCommand::ScrubTo(time_secs) => {
+                let target = seconds_to_duration(time_secs);
+                if self.player.scrub_to(target) {
+                    self.shared_state
+                        .set_progress_ms(duration_to_millis(target), Ordering::SeqCst);
+                }
+            }
+            PlayerCommand::EndScrub(commit) => {
+                if self.player.end_scrub(commit) {
+                    self.shared_state.set_buffering(true, Ordering::SeqCst);
+                }
+            }
             PlayerCommand::SwitchOutputDevice(device_name, reply_tx) => {
                 let result = self
                     .switch_output_device(normalize_device_name(device_name))
@@ -125,6 +282,13 @@ where
                     .map(|devices| devices.into_iter().map(AudioDeviceInfo::from).collect());
                 let _ = reply_tx.send(result);
             }
+            PlayerCommand::SetPreferredOutputDevices(device_ids, reply_tx) => {
+                let result = self.set_preferred_devices(device_ids).await;
+                let _ = reply_tx.send(result);
+            }
+            PlayerCommand::WaitOutputDevicesChange(reply_tx) => {
+                self.device_waiters.push(reply_tx);
+            }
             PlayerCommand::WaitFinished(done_tx) => {
                 let wait_signal = self.player.wait_finished_signal();
                 tokio::spawn(async move {
@@ -132,7 +296,109 @@ where
                     let _ = done_tx.send(());
                 });
             }
+            PlayerCommand::WaitDurationChange(reply_tx) => {
+                self.duration_waiters.push(reply_tx);
+            }
+            PlayerCommand::SetRealtimeScheduling(enabled) => {
+                self.realtime_scheduling = enabled;
+                self.player.set_realtime_scheduling(enabled);
+            }
+            PlayerCommand::SetDeviceLossPolicy(policy) => {
+                self.device_loss_policy = policy;
+            }
+            PlayerCommand::GetSchedulingDiagnostics(reply_tx) => {
+                let _ = reply_tx.send(SchedulingDiagnostics::from(self.player.scheduling_report()));
+            }
+            PlayerCommand::GetPipelineStats(reply_tx) => {
+                let _ = reply_tx.send(PipelineStatsInfo::from(self.player.pipeline_stats()));
+            }
+            PlayerCommand::SetAlsaHwOutput(options) => {
+                self.alsa_hw = options;
+                self.player.set_alsa_hw_output(options);
+            }
+            PlayerCommand::SetJackOutput(options) => {
+                self.player.set_jack_output(options.clone());
+                self.jack = options;
+            }
+            PlayerCommand::SetVirtualOutput(options) => {
+                self.player.set_virtual_output(options.clone());
+                self.virtual_output = options;
+            }
+            PlayerCommand::SetSilenceOptions(options) => {
+                self.silence = options;
+                self.player.set_silence_options(options);
+            }
+            PlayerCommand::SetHrtf(device_name, dataset) => {
+                let device_name = normalize_device_name(device_name);
+                if device_name == self.output_device {
+                    self.player.set_hrtf(dataset.clone());
+                }
+                match dataset {
+                    Some(dataset) => self.hrtf.insert(device_name, dataset),
+                    None => self.hrtf.remove(&device_name),
+                };
+            }
+            PlayerCommand::SetDopOutput(device_name, enabled) => {
+                let device_name = normalize_device_name(device_name);
+                if device_name == self.output_device {
+                    self.player.set_dop_output(enabled);
+                }
+                if enabled {
+                    self.dop_devices.insert(device_name);
+                } else {
+                    self.dop_devices.remove(&device_name);
+                }
+            }
+            PlayerCommand::GetHwVolume(reply_tx) => {
+                let _ = reply_tx.send(self.player.hw_volume().map(HwVolumeInfo::from));
+            }
+            PlayerCommand::SetHwVolume(volume, reply_tx) => {
+                let _ = reply_tx.send(self.player.set_hw_volume(volume).map(HwVolumeInfo::from));
+            }
+            PlayerCommand::SetHwMute(muted, reply_tx) => {
+                let _ = reply_tx.send(self.player.set_hw_mute(muted).map(HwVolumeInfo::from));
+            }
+            PlayerCommand::SetVolume(volume, reply_tx) => {
+                let _ = reply_tx.send(self.set_volume(volume));
+            }
+            PlayerCommand::SetBitPerfectVerification(enabled) => {
+                self.bit_perfect_verification = enabled;
+                self.player.set_bit_perfect_verification(enabled);
+            }
+            PlayerCommand::GetBitPerfectReport(reply_tx) => {
+                let _ = reply_tx.send(BitPerfectReportInfo::from(self.player.bit_perfect_report()));
+            }
+            PlayerCommand::SetSecondaryOutputs(targets, reply_tx) => {
+                let result = self.player.set_secondary_outputs(targets.clone());
+                if result.is_ok() {
+                    self.secondary_outputs = targets;
+                }
+                let _ = reply_tx.send(result);
+            }
+            PlayerCommand::GetSecondaryOutputs(reply_tx) => {
+                let _ = reply_tx.send(
+                    self.player
+                        .secondary_output_reports()
+                        .into_iter()
+                        .map(SecondaryOutputStatus::from)
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    /// 严格 BitPerfect 播放时音量改由硬件混音器调节，返回是否已由硬件处理；
+    /// 否则交回调用方按软件音量处理。
+    fn set_volume(&self, volume: f64) -> BackendResult<bool> {
+        let strict = self
+            .current_source
+            .as_ref()
+            .is_some_and(|source| source.options().strict_bit_perfect);
+        if !strict {
+            return Ok(false);
         }
+        self.player.set_hw_volume(volume)?;
+        Ok(true)
     }
 
     async fn play_source(
@@ -140,6 +406,15 @@ where
         source: PlaybackSource,
         start_at: Option<Duration>,
     ) -> BackendResult<()> {
+        if self.lost_output.is_some() {
+            self.current_source = None;
+            self.restore_lost_output(PlaybackStatus::Stopped).await?;
+        }
+        if self.try_continue_range(&source, start_at) {
+            return Ok(());
+        }
+        self.range_continued = false;
+
         self.shared_state.set_progress_ms(
             duration_to_millis(start_at.unwrap_or(Duration::ZERO)),
             Ordering::SeqCst,
@@ -148,9 +423,11 @@ where
 
         match Self::play_source_on(&mut self.player, &source, start_at).await {
             Ok(()) => {
+                self.range_transitions = self.player.range_transitions();
                 self.current_source = Some(source);
                 self.shared_state
                     .set_playback_status(PlaybackStatus::Playing, Ordering::SeqCst);
+                self.sync_duration();
                 Ok(())
             }
             Err(err) => {
@@ -162,6 +439,83 @@ where
         }
     }
 
+    /// 上一条 CUE 音轨已无缝续播到 `source` 时直接沿用正在播放的流，
+    /// 只更新其后排队的区间，不重新打开文件。
+    fn try_continue_range(&mut self, source: &PlaybackSource, start_at: Option<Duration>) -> bool {
+        self.sync_range_transitions();
+        let (
+            PlaybackSource::FileRange(request, options),
+            Some(PlaybackSource::FileRange(current, current_options)),
+        ) = (source, self.current_source.as_ref())
+        else {
+            return false;
+        };
+        if !self.range_continued
+            || options != current_options
+            || !request.is_same_range(current)
+            || start_at.is_some_and(|target| !target.is_zero())
+            || self.shared_state.playback_status() == PlaybackStatus::Stopped
+        {
+            return false;
+        }
+
+        self.player.queue_next_range(request);
+        self.current_source = Some(source.clone());
+        self.range_continued = false;
+        true
+    }
+
+    fn sync_range_transitions(&mut self) {
+        let transitions = self.player.range_transitions();
+        if transitions == self.range_transitions {
+            return;
+        }
+        self.range_transitions = transitions;
+        if let Some(PlaybackSource::FileRange(request, options)) = self.current_source.as_ref()
+            && let Some(next) = request.advanced()
+        {
+            self.current_source = Some(PlaybackSource::FileRange(next, *options));
+            self.range_continued = true;
+        }
+    }
+
+    /// 跳到章节起点，走与 `Seek` 相同的精确 seek；没有目标章节时返回 false。
+    fn seek_to_chapter(&mut self, target: Option<Duration>) -> bool {
+        let Some(target) = target.filter(|_| self.current_source.is_some()) else {
+            return false;
+        };
+        self.player.seek(target);
+        self.shared_state.set_buffering(true, Ordering::SeqCst);
+        self.shared_state
+            .set_progress_ms(duration_to_millis(target), Ordering::SeqCst);
+        self.sync_chapter(target);
+        true
+    }
+
+    fn sync_chapter(&self, progress: Duration) {
+        let index = chapters::chapter_index_at(self.player.chapters(), progress);
+        self.shared_state
+            .set_chapter_index(index.map(|index| index as u32));
+    }
+
+    /// 发布当前时长；估算值随读取的数据更新、切换音轨或停止时通知等待者。
+    fn sync_duration(&mut self) {
+        let duration = match self.shared_state.playback_status() {
+            PlaybackStatus::Stopped => PlaybackDurationInfo::default(),
+            PlaybackStatus::Playing | PlaybackStatus::Paused => {
+                PlaybackDurationInfo::from(self.player.duration())
+            }
+        };
+        if duration == self.duration {
+            return;
+        }
+        self.shared_state.set_duration(&duration);
+        for waiter in self.duration_waiters.drain(..) {
+            let _ = waiter.send(duration.clone());
+        }
+        self.duration = duration;
+    }
+
     async fn play_source_on(
         player: &mut P,
         source: &PlaybackSource,
@@ -173,6 +527,12 @@ where
             PlaybackSource::CachedUrl(request, options) => {
                 player.play_url_cached(request, start_at, *options).await
             }
+            PlaybackSource::Buffer(request, options) => {
+                player.play_buffer(request, start_at, *options).await
+            }
+            PlaybackSource::FileRange(request, options) => {
+                player.play_file_range(request, start_at, *options).await
+            }
         }
     }
 
@@ -192,20 +552,65 @@ where
         }
     }
 
+    fn is_output_device_available(&self, device_name: Option<&str>) -> bool {
+        let Ok(devices) = self.player.output_devices() else {
+            return false;
+        };
+        match device_name {
+            Some(name) => devices
+                .iter()
+                .any(|device| device.id == name || device.name == name),
+            None => devices.iter().any(|device| device.is_default),
+        }
+    }
+
     async fn switch_output_device(&mut self, device_name: Option<String>) -> BackendResult<()> {
-        if Self::is_requested_output_device_already_active(&self.player, device_name.as_deref())? {
+        // 原设备失效后重新接入时名称相同，但播放器仍需重建。
+        if self.lost_output.is_none()
+            && Self::is_requested_output_device_already_active(
+                &self.player,
+                device_name.as_deref(),
+            )?
+        {
             return Ok(());
         }
 
         let playback_status = self.shared_state.playback_status();
+        self.reopen_output(device_name, playback_status).await
+    }
+
+    fn create_player(&self, device_name: Option<&str>) -> BackendResult<P> {
+        let mut player = self.factory.create(device_name)?;
+        player.set_realtime_scheduling(self.realtime_scheduling);
+        player.set_alsa_hw_output(self.alsa_hw);
+        player.set_jack_output(self.jack.clone());
+        player.set_virtual_output(self.virtual_output.clone());
+        player.set_silence_options(self.silence);
+        let device_key = device_name.map(str::to_string);
+        player.set_hrtf(self.hrtf.get(&device_key).cloned());
+        player.set_dop_output(self.dop_devices.contains(&device_key));
+        player.set_bit_perfect_verification(self.bit_perfect_verification);
+        // 副输出设备失效不应阻止切换主输出，只记录日志。
+        if let Err(err) = player.set_secondary_outputs(self.secondary_outputs.clone()) {
+            eprintln!("Restore secondary outputs failed: {}", err);
+        }
+        Ok(player)
+    }
+
+    /// 在 `device_name` 上重建播放器，从当前进度接着播放 `current_source`，
+    /// 之后处于 `playback_status`；失败时保留原播放器。
+    async fn reopen_output(
+        &mut self,
+        device_name: Option<String>,
+        playback_status: PlaybackStatus,
+    ) -> BackendResult<()> {
         let resume_source = self.current_source.clone();
         let resume_position = match playback_status {
             PlaybackStatus::Stopped => None,
             PlaybackStatus::Playing | PlaybackStatus::Paused => Some(self.player.progress()),
         };
 
-        let mut next_player = self.factory.create(device_name.as_deref())?;
-
+        let mut next_player = self.create_player(device_name.as_deref())?;
         if let Some(source) = resume_source.as_ref() {
             Self::play_source_on(&mut next_player, source, resume_position).await?;
             if playback_status == PlaybackStatus::Paused {
@@ -215,6 +620,9 @@ where
 
         self.player.stop();
         self.player = next_player;
+        self.output_device = device_name;
+        self.lost_output = None;
+        self.range_transitions = self.player.range_transitions();
 
         if let Some(position) = resume_position {
             self.shared_state
@@ -228,15 +636,179 @@ where
         Ok(())
     }
 
+    /// 检查输出流是否因设备断开而无法继续，并按 `device_loss_policy` 处理；
+    /// 设备失效期间每隔 `DEVICE_RETRY_TICKS` 次调用重试一次恢复。
+    pub(crate) async fn check_output(&mut self) {
+        let Some(lost) = self.lost_output.as_mut() else {
+            if let Some(reason) = self.player.output_fault() {
+                self.handle_output_lost(reason).await;
+            }
+            return;
+        };
+        if lost.retry_in > 0 {
+            lost.retry_in -= 1;
+            return;
+        }
+        lost.retry_in = DEVICE_RETRY_TICKS;
+        self.retry_lost_output().await;
+    }
+
+    async fn handle_output_lost(&mut self, reason: String) {
+        eprintln!("Output device lost: {}", reason);
+        let playback_status = self.shared_state.playback_status();
+        self.lost_output = Some(LostOutput {
+            device_name: self.output_device.clone(),
+            resume: playback_status == PlaybackStatus::Playing,
+            retry_in: DEVICE_RETRY_TICKS,
+        });
+        if playback_status == PlaybackStatus::Playing {
+            self.player.pause();
+            self.shared_state
+                .set_playback_status(PlaybackStatus::Paused, Ordering::SeqCst);
+        }
+        if self.device_loss_policy == DeviceLossPolicy::FallbackToDefault {
+            self.retry_lost_output().await;
+        }
+    }
+
+    async fn retry_lost_output(&mut self) {
+        let Some(lost) = self.lost_output.as_ref() else {
+            return;
+        };
+        let device_name = match self.device_loss_policy {
+            DeviceLossPolicy::Pause => return,
+            DeviceLossPolicy::FallbackToDefault => None,
+            DeviceLossPolicy::WaitForDevice => {
+                if !self.is_output_device_available(lost.device_name.as_deref()) {
+                    return;
+                }
+                lost.device_name.clone()
+            }
+        };
+        let playback_status = self.restore_status();
+        if let Err(err) = self.reopen_output(device_name, playback_status).await {
+            eprintln!("Restore output device failed: {}", err);
+        }
+    }
+
+    /// 重建播放器后的播放状态：设备失效时正在播放的应继续播放。
+    fn restore_status(&self) -> PlaybackStatus {
+        match &self.lost_output {
+            Some(lost) if lost.resume => PlaybackStatus::Playing,
+            _ => self.shared_state.playback_status(),
+        }
+    }
+
+    /// 设备失效期间用户继续或开始播放：原设备已重新接入就用原设备，否则用默认设备。
+    async fn restore_lost_output(&mut self, playback_status: PlaybackStatus) -> BackendResult<()> {
+        let device_name = self
+            .lost_output
+            .as_ref()
+            .and_then(|lost| lost.device_name.clone())
+            .filter(|name| self.is_output_device_available(Some(name)));
+        self.reopen_output(device_name, playback_status).await
+    }
+
+    async fn set_preferred_devices(&mut self, device_ids: Vec<String>) -> BackendResult<()> {
+        self.preferred_devices = device_ids
+            .into_iter()
+            .filter_map(|id| normalize_device_name(Some(id)))
+            .collect();
+        if self.preferred_devices.is_empty() {
+            return Ok(());
+        }
+
+        let devices = self.player.output_devices()?;
+        self.known_devices = Some(devices.iter().map(|device| device.id.clone()).collect());
+        self.switch_to_preferred_device(&devices).await
+    }
+
+    /// 切到 `devices` 中优先级最高的偏好设备；没有可用的偏好设备时保持不变。
+    async fn switch_to_preferred_device(
+        &mut self,
+        devices: &[OutputDeviceInfo],
+    ) -> BackendResult<()> {
+        let matches = |device: &OutputDeviceInfo, id: &str| device.id == id || device.name == id;
+        let Some(target) = self
+            .preferred_devices
+            .iter()
+            .find(|id| devices.iter().any(|device| matches(device, id)))
+            .cloned()
+        else {
+            return Ok(());
+        };
+        if self.lost_output.is_none()
+            && devices
+                .iter()
+                .any(|device| device.is_current && matches(device, &target))
+        {
+            return Ok(());
+        }
+
+        let playback_status = self.restore_status();
+        self.reopen_output(Some(target), playback_status).await
+    }
+
+    /// 定期检查设备插拔：声卡签名不变时跳过枚举；设备列表变化时
+    /// 按偏好列表切换输出设备，并把新的设备列表发给等待者。
+    pub(crate) async fn poll_output_devices(&mut self) {
+        if self.preferred_devices.is_empty() && self.device_waiters.is_empty() {
+            return;
+        }
+        if self.device_poll_in > 0 {
+            self.device_poll_in -= 1;
+            return;
+        }
+        self.device_poll_in = DEVICE_POLL_TICKS;
+
+        // 签名变化时立即枚举，不变时也定期枚举一次。
+        let signature = self.player.device_signature();
+        let signature_changed = signature.is_none() || signature != self.device_signature;
+        self.device_signature = signature;
+        if !signature_changed && self.device_scan_in > 0 {
+            self.device_scan_in -= 1;
+            return;
+        }
+        self.device_scan_in = DEVICE_SCAN_POLLS;
+
+        let Ok(devices) = self.player.output_devices() else {
+            return;
+        };
+        let ids: Vec<String> = devices.iter().map(|device| device.id.clone()).collect();
+        let changed = self
+            .known_devices
+            .as_ref()
+            .is_some_and(|known| *known != ids);
+        self.known_devices = Some(ids);
+        if !changed {
+            return;
+        }
+
+        if let Err(err) = self.switch_to_preferred_device(&devices).await {
+            eprintln!("Switch to preferred output device failed: {}", err);
+        }
+        let Ok(devices) = self.player.output_devices() else {
+            return;
+        };
+        let devices: Vec<AudioDeviceInfo> =
+            devices.into_iter().map(AudioDeviceInfo::from).collect();
+        for waiter in self.device_waiters.drain(..) {
+            let _ = waiter.send(devices.clone());
+        }
+    }
+
     pub(crate) fn tick(&mut self) {
+        self.sync_duration();
         let playback_status = self.shared_state.playback_status();
         if playback_status == PlaybackStatus::Stopped {
             return;
         }
 
+        self.sync_range_transitions();
         let progress = self.player.progress();
         self.shared_state
             .set_progress_ms(duration_to_millis(progress), Ordering::Relaxed);
+        self.sync_chapter(progress);
         self.shared_state
             .set_buffering(self.player.is_buffering(), Ordering::Relaxed);
 